@@ -0,0 +1,132 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Convert to/from blockchain::ArchiveManifest.
+
+use crate::{blockchain, ConversionError};
+use mc_blockchain_types::{ArchiveManifest, ArchiveManifestBucket, ArchiveManifestContents};
+
+impl From<&ArchiveManifestBucket> for blockchain::ArchiveManifestBucket {
+    fn from(src: &ArchiveManifestBucket) -> Self {
+        let mut proto = Self::new();
+        proto.set_first_block_index(src.first_block_index());
+        proto.set_num_blocks(src.num_blocks());
+        proto.set_hash(src.hash().to_vec());
+        proto.set_last_block_id(src.last_block_id().into());
+        proto
+    }
+}
+
+impl TryFrom<&blockchain::ArchiveManifestBucket> for ArchiveManifestBucket {
+    type Error = ConversionError;
+
+    fn try_from(src: &blockchain::ArchiveManifestBucket) -> Result<Self, Self::Error> {
+        let last_block_id = src.get_last_block_id().try_into()?;
+        Ok(ArchiveManifestBucket::new(
+            src.get_first_block_index(),
+            src.get_num_blocks(),
+            src.get_hash().to_vec(),
+            last_block_id,
+        ))
+    }
+}
+
+impl From<&ArchiveManifestContents> for blockchain::ArchiveManifestContents {
+    fn from(src: &ArchiveManifestContents) -> Self {
+        let mut proto = Self::new();
+        proto.set_buckets(src.buckets().iter().map(Into::into).collect());
+        proto
+    }
+}
+
+impl TryFrom<&blockchain::ArchiveManifestContents> for ArchiveManifestContents {
+    type Error = ConversionError;
+
+    fn try_from(src: &blockchain::ArchiveManifestContents) -> Result<Self, Self::Error> {
+        let buckets = src
+            .get_buckets()
+            .iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+        Ok(ArchiveManifestContents::new(buckets))
+    }
+}
+
+impl From<&ArchiveManifest> for blockchain::ArchiveManifest {
+    fn from(src: &ArchiveManifest) -> Self {
+        let mut proto = Self::new();
+        proto.set_contents(src.contents().into());
+        proto.set_signer(src.signer().into());
+        proto.set_signature(src.signature().into());
+        proto
+    }
+}
+
+impl TryFrom<&blockchain::ArchiveManifest> for ArchiveManifest {
+    type Error = ConversionError;
+
+    fn try_from(src: &blockchain::ArchiveManifest) -> Result<Self, Self::Error> {
+        let contents = src.get_contents().try_into()?;
+        let signer = src.get_signer().try_into()?;
+        let signature = src.get_signature().try_into()?;
+        let manifest = ArchiveManifest::new(contents, signer, signature);
+        manifest.verify()?;
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_blockchain_types::BlockID;
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_util_from_random::FromRandom;
+    use protobuf::Message;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn test_manifest() -> ArchiveManifest {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let contents = ArchiveManifestContents::new(vec![
+            ArchiveManifestBucket::from_object(0, 10, b"first", BlockID([1; 32])),
+            ArchiveManifestBucket::from_object(10, 10, b"second", BlockID([2; 32])),
+        ]);
+        ArchiveManifest::from_contents_and_keypair(contents, &Ed25519Pair::from_random(&mut rng))
+            .unwrap()
+    }
+
+    #[test]
+    // ArchiveManifest --> blockchain::ArchiveManifest --> ArchiveManifest
+    fn test_archive_manifest_round_trip() {
+        let source = test_manifest();
+        let proto = blockchain::ArchiveManifest::from(&source);
+        assert_eq!(ArchiveManifest::try_from(&proto).unwrap(), source);
+    }
+
+    #[test]
+    // Converting a manifest whose signature does not match fails.
+    fn test_archive_manifest_bad_signature() {
+        let mut proto = blockchain::ArchiveManifest::from(&test_manifest());
+        proto.mut_contents().mut_buckets()[0].set_num_blocks(11);
+        assert!(ArchiveManifest::try_from(&proto).is_err());
+    }
+
+    #[test]
+    // the blockchain::ArchiveManifest definition matches the ArchiveManifest
+    // prost attributes.
+    fn test_blockchain_archive_manifest_matches_prost() {
+        let source = test_manifest();
+
+        // Encode using `protobuf`, decode using `prost`.
+        let proto_bytes = blockchain::ArchiveManifest::from(&source)
+            .write_to_bytes()
+            .unwrap();
+        let from_prost: ArchiveManifest =
+            mc_util_serial::decode(&proto_bytes).expect("failed decoding");
+        assert_eq!(source, from_prost);
+
+        // Encode using `prost`, decode using `protobuf`.
+        let prost_bytes = mc_util_serial::encode(&source);
+        let proto =
+            blockchain::ArchiveManifest::parse_from_bytes(&prost_bytes).expect("failed decoding");
+        assert_eq!(proto, blockchain::ArchiveManifest::from(&source));
+    }
+}
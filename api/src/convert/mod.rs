@@ -9,6 +9,7 @@
 
 // blockchain
 mod archive_block;
+mod archive_manifest;
 mod block;
 mod block_contents;
 mod block_contents_hash;
@@ -54,6 +55,7 @@ mod unsigned_tx;
 mod validated_mint_config;
 mod verification_report;
 mod verification_signature;
+mod view_account_key;
 mod watcher;
 
 // printable
@@ -94,6 +96,22 @@ pub fn merged_block_num_to_s3block_path(
     path
 }
 
+/// Path of the manifest of a compressed archive, relative to the archive's
+/// root.
+pub const ARCHIVE_MANIFEST_PATH: &str = "manifest.pb";
+
+/// Helper method for getting the suggested path/filename of a bucket in a
+/// compressed archive.
+///
+/// A bucket is a consecutive collection of blocks, stored as a zstd-compressed
+/// `ArchiveBlocks` object and listed in the archive's manifest.
+pub fn compressed_bucket_path(bucket_size: u64, first_block_index: BlockIndex) -> PathBuf {
+    let mut path = PathBuf::from(format!("compressed-{bucket_size}"));
+    path.push(block_num_to_s3block_path(first_block_index));
+    path.set_extension("pb.zst");
+    path
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,4 +128,12 @@ mod tests {
             PathBuf::from("1a/2b/3c/4e/5a/6b/7c/1a2b3c4e5a6b7c8d.pb"),
         );
     }
+
+    #[test]
+    fn test_compressed_bucket_path() {
+        assert_eq!(
+            compressed_bucket_path(1000, 0x3e8),
+            PathBuf::from("compressed-1000/00/00/00/00/00/00/03/00000000000003e8.pb.zst"),
+        );
+    }
 }
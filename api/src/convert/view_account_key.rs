@@ -0,0 +1,56 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Convert to/from external::ViewAccountKey
+
+use crate::{external, ConversionError};
+use mc_account_keys::ViewAccountKey;
+use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
+
+impl From<&ViewAccountKey> for external::ViewAccountKey {
+    fn from(src: &ViewAccountKey) -> Self {
+        let mut dst = external::ViewAccountKey::new();
+        dst.set_view_private_key(external::RistrettoPrivate::from(src.view_private_key()));
+        dst.set_spend_public_key(external::CompressedRistretto::from(src.spend_public_key()));
+        dst
+    }
+}
+
+impl TryFrom<&external::ViewAccountKey> for ViewAccountKey {
+    type Error = ConversionError;
+
+    fn try_from(src: &external::ViewAccountKey) -> Result<Self, Self::Error> {
+        let view_private_key = RistrettoPrivate::try_from(src.get_view_private_key())?;
+        let spend_public_key = RistrettoPublic::try_from(src.get_spend_public_key())?;
+        Ok(ViewAccountKey::new(view_private_key, spend_public_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_account_keys::AccountKey;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // Test converting between external::ViewAccountKey and
+    // account_keys::ViewAccountKey
+    #[test]
+    fn test_view_account_key_conversion() {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let view_account_key = ViewAccountKey::from(&AccountKey::random(&mut rng));
+        let proto_credentials = external::ViewAccountKey::from(&view_account_key);
+        assert_eq!(
+            *proto_credentials.get_view_private_key(),
+            external::RistrettoPrivate::from(view_account_key.view_private_key())
+        );
+        assert_eq!(
+            *proto_credentials.get_spend_public_key(),
+            external::CompressedRistretto::from(view_account_key.spend_public_key())
+        );
+
+        assert_eq!(
+            view_account_key,
+            ViewAccountKey::try_from(&proto_credentials).unwrap()
+        );
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! The manifest of a compressed ledger archive.
+//!
+//! A compressed archive stores consecutive blocks in buckets, each of which is
+//! a single zstd-compressed object. The manifest lists the buckets, with the
+//! hash of each object and the id of the last block in it, and is signed by
+//! the archive's publisher.
+
+use crate::{BlockID, BlockIndex};
+use alloc::vec::Vec;
+use mc_crypto_digestible::Digestible;
+use mc_crypto_digestible_signature::{DigestibleSigner, DigestibleVerifier};
+use mc_crypto_hashes::{Blake2b256, Digest};
+use mc_crypto_keys::{Ed25519Pair, Ed25519Public, Ed25519Signature, SignatureError};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+/// The context to use for [ArchiveManifestContents] digests.
+pub fn archive_manifest_context() -> &'static [u8] {
+    b"archive_manifest"
+}
+
+/// A bucket of consecutive blocks, stored as a single compressed object.
+#[derive(Clone, Deserialize, Digestible, Eq, Message, PartialEq, Serialize)]
+pub struct ArchiveManifestBucket {
+    /// Index of the first block in the bucket.
+    #[prost(uint64, tag = 1)]
+    first_block_index: BlockIndex,
+
+    /// Number of blocks in the bucket.
+    #[prost(uint64, tag = 2)]
+    num_blocks: u64,
+
+    /// Blake2b-256 hash of the compressed object.
+    #[prost(bytes, tag = 3)]
+    hash: Vec<u8>,
+
+    /// Id of the last block in the bucket.
+    #[prost(message, required, tag = 4)]
+    last_block_id: BlockID,
+}
+
+impl ArchiveManifestBucket {
+    /// Instantiate an [ArchiveManifestBucket] with the given data.
+    pub fn new(
+        first_block_index: BlockIndex,
+        num_blocks: u64,
+        hash: Vec<u8>,
+        last_block_id: BlockID,
+    ) -> Self {
+        Self {
+            first_block_index,
+            num_blocks,
+            hash,
+            last_block_id,
+        }
+    }
+
+    /// Instantiate an [ArchiveManifestBucket] for the given compressed object.
+    pub fn from_object(
+        first_block_index: BlockIndex,
+        num_blocks: u64,
+        object: &[u8],
+        last_block_id: BlockID,
+    ) -> Self {
+        Self::new(
+            first_block_index,
+            num_blocks,
+            Self::hash_object(object),
+            last_block_id,
+        )
+    }
+
+    /// Hash a compressed object.
+    pub fn hash_object(object: &[u8]) -> Vec<u8> {
+        Blake2b256::digest(object).to_vec()
+    }
+
+    /// Check whether `object` is the compressed object of this bucket.
+    pub fn matches_object(&self, object: &[u8]) -> bool {
+        Self::hash_object(object) == self.hash
+    }
+
+    /// Check whether this bucket contains the given block.
+    pub fn contains(&self, block_index: BlockIndex) -> bool {
+        block_index >= self.first_block_index
+            && block_index - self.first_block_index < self.num_blocks
+    }
+
+    /// Get the index of the first block in the bucket.
+    pub fn first_block_index(&self) -> BlockIndex {
+        self.first_block_index
+    }
+
+    /// Get the number of blocks in the bucket.
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    /// Get the hash of the compressed object.
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+
+    /// Get the [BlockID] of the last block in the bucket.
+    pub fn last_block_id(&self) -> &BlockID {
+        &self.last_block_id
+    }
+}
+
+/// The buckets of a compressed ledger archive, in increasing block order.
+#[derive(Clone, Default, Deserialize, Digestible, Eq, Message, PartialEq, Serialize)]
+pub struct ArchiveManifestContents {
+    /// The buckets, in increasing block order.
+    #[prost(message, repeated, tag = 1)]
+    buckets: Vec<ArchiveManifestBucket>,
+}
+
+impl ArchiveManifestContents {
+    /// Instantiate an [ArchiveManifestContents] with the given buckets.
+    pub fn new(buckets: Vec<ArchiveManifestBucket>) -> Self {
+        Self { buckets }
+    }
+
+    /// Get the buckets.
+    pub fn buckets(&self) -> &[ArchiveManifestBucket] {
+        &self.buckets
+    }
+
+    /// Append a bucket, which should start right after the last one.
+    pub fn push(&mut self, bucket: ArchiveManifestBucket) {
+        self.buckets.push(bucket);
+    }
+
+    /// Get the index of the first block that is not in any bucket.
+    pub fn next_block_index(&self) -> BlockIndex {
+        self.buckets
+            .last()
+            .map_or(0, |bucket| bucket.first_block_index + bucket.num_blocks)
+    }
+
+    /// Find the bucket containing the given block.
+    pub fn bucket_containing(&self, block_index: BlockIndex) -> Option<&ArchiveManifestBucket> {
+        let position = self
+            .buckets
+            .partition_point(|bucket| bucket.first_block_index <= block_index);
+        position
+            .checked_sub(1)
+            .map(|position| &self.buckets[position])
+            .filter(|bucket| bucket.contains(block_index))
+    }
+}
+
+/// A signed manifest of a compressed ledger archive.
+#[derive(Clone, Deserialize, Digestible, Eq, Message, PartialEq, Serialize)]
+pub struct ArchiveManifest {
+    /// The buckets of the archive.
+    #[prost(message, required, tag = 1)]
+    contents: ArchiveManifestContents,
+
+    /// Key of the archive's publisher (signer).
+    #[prost(message, required, tag = 2)]
+    signer: Ed25519Public,
+
+    /// Signature using `signer` over the Digestible encoding of `contents`.
+    #[prost(message, required, tag = 3)]
+    signature: Ed25519Signature,
+}
+
+impl ArchiveManifest {
+    /// Instantiate an [ArchiveManifest] with the given data.
+    pub fn new(
+        contents: ArchiveManifestContents,
+        signer: Ed25519Public,
+        signature: Ed25519Signature,
+    ) -> Self {
+        Self {
+            contents,
+            signer,
+            signature,
+        }
+    }
+
+    /// Instantiate an [ArchiveManifest] by signing the given
+    /// [ArchiveManifestContents] with the given [Ed25519Pair].
+    pub fn from_contents_and_keypair(
+        contents: ArchiveManifestContents,
+        key_pair: &Ed25519Pair,
+    ) -> Result<Self, SignatureError> {
+        let signature = key_pair.try_sign_digestible(archive_manifest_context(), &contents)?;
+        Ok(Self::new(contents, key_pair.public_key(), signature))
+    }
+
+    /// Verify the signature over the contents.
+    pub fn verify(&self) -> Result<(), SignatureError> {
+        self.signer
+            .verify_digestible(archive_manifest_context(), &self.contents, &self.signature)
+    }
+
+    /// Get the [ArchiveManifestContents].
+    pub fn contents(&self) -> &ArchiveManifestContents {
+        &self.contents
+    }
+
+    /// Get the signing key.
+    pub fn signer(&self) -> &Ed25519Public {
+        &self.signer
+    }
+
+    /// Get the signature.
+    pub fn signature(&self) -> &Ed25519Signature {
+        &self.signature
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::vec;
+    use mc_util_from_random::FromRandom;
+    use mc_util_test_helper::get_seeded_rng;
+
+    fn test_contents() -> ArchiveManifestContents {
+        ArchiveManifestContents::new(vec![
+            ArchiveManifestBucket::from_object(0, 100, b"first", BlockID([1; 32])),
+            ArchiveManifestBucket::from_object(100, 100, b"second", BlockID([2; 32])),
+        ])
+    }
+
+    #[test]
+    fn bucket_lookup() {
+        let contents = test_contents();
+        assert_eq!(contents.next_block_index(), 200);
+        assert_eq!(contents.bucket_containing(0), Some(&contents.buckets()[0]));
+        assert_eq!(contents.bucket_containing(99), Some(&contents.buckets()[0]));
+        assert_eq!(
+            contents.bucket_containing(100),
+            Some(&contents.buckets()[1])
+        );
+        assert_eq!(contents.bucket_containing(200), None);
+
+        assert!(contents.buckets()[1].matches_object(b"second"));
+        assert!(!contents.buckets()[1].matches_object(b"first"));
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let key_pair = Ed25519Pair::from_random(&mut get_seeded_rng());
+        let manifest =
+            ArchiveManifest::from_contents_and_keypair(test_contents(), &key_pair).unwrap();
+        assert!(manifest.verify().is_ok());
+
+        // Tampering with the buckets invalidates the signature.
+        let mut contents = manifest.contents().clone();
+        contents.push(ArchiveManifestBucket::from_object(
+            200,
+            100,
+            b"third",
+            BlockID([3; 32]),
+        ));
+        let tampered = ArchiveManifest::new(contents, *manifest.signer(), *manifest.signature());
+        assert!(tampered.verify().is_err());
+    }
+}
@@ -9,6 +9,7 @@ extern crate alloc;
 
 pub mod crypto;
 
+mod archive_manifest;
 mod block;
 mod block_contents;
 mod block_data;
@@ -18,6 +19,9 @@ mod block_signature;
 mod error;
 
 pub use crate::{
+    archive_manifest::{
+        archive_manifest_context, ArchiveManifest, ArchiveManifestBucket, ArchiveManifestContents,
+    },
     block::{compute_block_id, Block, BlockIndex, MAX_BLOCK_VERSION},
     block_contents::{BlockContents, BlockContentsHash},
     block_data::BlockData,
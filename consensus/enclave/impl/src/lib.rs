@@ -52,8 +52,8 @@ use mc_consensus_enclave_api::{
     WellFormedEncryptedTx, WellFormedTxContext,
 };
 use mc_crypto_ake_enclave::AkeEnclaveState;
-use mc_crypto_digestible::{DigestTranscript, Digestible, MerlinTranscript};
-use mc_crypto_keys::{Ed25519Pair, Ed25519Public, RistrettoPrivate, RistrettoPublic, X25519Public};
+use mc_crypto_digestible::Digestible;
+use mc_crypto_keys::{Ed25519Pair, Ed25519Public, RistrettoPublic, X25519Public};
 use mc_crypto_message_cipher::{AesMessageCipher, MessageCipher};
 use mc_rand::McRng;
 use mc_sgx_compat::sync::Mutex;
@@ -62,10 +62,10 @@ use mc_transaction_core::{
     encrypted_fog_hint::EncryptedFogHint,
     membership_proofs::compute_implied_merkle_root,
     mint::{
-        validate_mint_config_tx, validate_mint_tx, MintConfig, MintConfigTx, MintTx,
-        MintValidationError, ValidatedMintConfigTx,
+        network_output_private_key, validate_mint_config_tx, validate_mint_tx, MintConfig,
+        MintConfigTx, MintTx, MintValidationError, ValidatedMintConfigTx,
     },
-    ring_signature::{KeyImage, Scalar},
+    ring_signature::KeyImage,
     tokens::Mob,
     tx::{Tx, TxOut, TxOutMembershipElement, TxOutMembershipProof},
     validation::TransactionValidationError,
@@ -80,8 +80,7 @@ use rand_core::{CryptoRng, RngCore};
 /// Domain separator for unified fees transaction private key.
 pub const FEES_OUTPUT_PRIVATE_KEY_DOMAIN_TAG: &str = "mc_fees_output_private_key";
 
-/// Domain separator for minted txouts public keys.
-pub const MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG: &str = "mc_minted_output_private_key";
+pub use mc_transaction_core::mint::constants::MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG;
 
 include!(concat!(env!("OUT_DIR"), "/target_features.rs"));
 
@@ -998,25 +997,11 @@ fn mint_output<T: Digestible>(
         ));
     }
 
-    // Create a determinstic private key based on the block contents.
-    let tx_private_key = {
-        let mut hash_value = [0u8; 32];
-        {
-            let mut transcript = MerlinTranscript::new(domain_tag);
-            parent_block
-                .id
-                .append_to_transcript(b"parent_block_id", &mut transcript);
-            transactions.append_to_transcript(b"transactions", &mut transcript);
-            amount.append_to_transcript(b"amount", &mut transcript);
-            counter.append_to_transcript(b"counter", &mut transcript);
-            transcript.extract_digest(&mut hash_value);
-        };
-
-        // This private key is generated from the hash of all transactions in this
-        // block. This ensures that all nodes generate the same fee output
-        // transaction.
-        RistrettoPrivate::from(Scalar::from_bytes_mod_order(hash_value))
-    };
+    // Create a determinstic private key based on the block contents. This
+    // private key is generated from the hash of all transactions in this block.
+    // This ensures that all nodes generate the same fee output transaction.
+    let tx_private_key =
+        network_output_private_key(domain_tag, &parent_block.id, transactions, amount, counter);
 
     // Create a single TxOut
     TxOut::new(
@@ -1035,14 +1020,14 @@ mod tests {
     use alloc::vec;
     use mc_common::{logger::test_with_logger, HashMap, HashSet};
     use mc_consensus_enclave_api::{GovernorsMap, GovernorsSigner};
-    use mc_crypto_keys::{Ed25519Private, Ed25519Signature, Signer};
+    use mc_crypto_keys::{Ed25519Private, Ed25519Signature, RistrettoPrivate, Signer};
     use mc_crypto_multisig::{MultiSig, SignerSet};
     use mc_ledger_db::{
         test_utils::{add_txos_to_ledger, create_ledger, create_transaction, initialize_ledger},
         Ledger,
     };
     use mc_transaction_core::{
-        mint::{constants::NONCE_LENGTH, MintConfigTxPrefix},
+        mint::{constants::NONCE_LENGTH, minted_tx_out_public_key, MintConfigTxPrefix},
         tx::TxOutMembershipHash,
         validation::validate_tx_out,
         FeeMap,
@@ -2348,6 +2333,13 @@ mod tests {
                 .unwrap();
             assert_eq!(amount.value, 200);
             assert_eq!(amount.token_id, token_id2);
+
+            // The minted tx outs can be located from the mint txs alone.
+            for (counter, output) in [output1, output2].into_iter().enumerate() {
+                let public_key =
+                    minted_tx_out_public_key(&parent_block.id, &block_contents.mint_txs, counter);
+                assert_eq!(output.public_key, public_key.into());
+            }
         }
     }
 
@@ -23,3 +23,6 @@ pub const HASH_TO_SCALAR_DOMAIN_TAG: &str = "mc_onetime_key_hash_to_scalar";
 
 /// Domain separator for RingMLSAG's challenges.
 pub const RING_MLSAG_CHALLENGE_DOMAIN_TAG: &str = "mc_ring_mlsag_challenge";
+
+/// Domain separator for the nonce commitments of threshold RingMLSAG signers.
+pub const THRESHOLD_MLSAG_NONCE_COMMITMENT_DOMAIN_TAG: &str = "mc_threshold_mlsag_nonce_commitment";
@@ -27,7 +27,11 @@ pub use ring_signature::{
 };
 
 #[cfg(feature = "alloc")]
-pub use ring_signature::RingMLSAG;
+pub use ring_signature::{
+    lagrange_coefficient, split_private_key, KeyShare, NonceCommitment, NonceReveal,
+    PartialResponse, PublicKeyShare, RingMLSAG, ThresholdMlsagChallenge, ThresholdMlsagCoordinator,
+    ThresholdMlsagNonce,
+};
 
 #[cfg(feature = "internals")]
 pub use ring_signature::{MlsagSignCtx, MlsagSignParams, MlsagVerify, Ring};
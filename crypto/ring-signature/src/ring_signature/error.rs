@@ -34,6 +34,24 @@ pub enum Error {
 
     /// Invalid signing state
     InvalidState,
+
+    /// Invalid threshold `{0}` for `{1}` key shares
+    InvalidThreshold(u32, u32),
+
+    /// Unknown signer `{0}`
+    UnknownSigner(u32),
+
+    /// Signer `{0}` appears more than once
+    DuplicateSigner(u32),
+
+    /// Not enough signers, provided `{0}`, required `{1}`
+    NotEnoughSigners(u32, u32),
+
+    /// The nonce of signer `{0}` does not match its commitment
+    NonceCommitmentMismatch(u32),
+
+    /// Invalid partial response from signer `{0}`
+    InvalidPartialResponse(u32),
 }
 
 impl From<mc_util_repr_bytes::LengthMismatch> for Error {
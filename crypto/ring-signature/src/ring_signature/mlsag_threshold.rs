@@ -0,0 +1,866 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Threshold signing of MLSAGs, where the spend private key of an account is
+//! shared among `n` signers, any `t` of which must cooperate to sign.
+//!
+//! The onetime private key of a TxOut received at subaddress `i` is
+//! `x = Hs(a * R) + Hs(a || i) + b`, where `b` is the spend private key. The
+//! offset `k = x - b` only depends on the view private key, so it is known to
+//! the coordinator of a signing session, while `b` is split with Shamir's
+//! secret sharing into shares `b_j = f(j)`, where `f` is a random polynomial
+//! of degree `t - 1` with `f(0) = b`.
+//!
+//! For a set `S` of at least `t` signers, with Lagrange coefficients
+//! `lambda_j`, `b = sum(lambda_j * b_j)`, so the key image and the response of
+//! the real input can be assembled from the contributions of the signers:
+//! * `I = k * Hp(P) + sum(lambda_j * b_j * Hp(P))`
+//! * `r_{pi,0} = sum(alpha_j - c_pi * lambda_j * b_j) - c_pi * k`
+//!
+//! Signing a ring takes three rounds between the coordinator and the signers:
+//! 1. Each signer picks a nonce `alpha_j`, and commits to its [NonceReveal]
+//!    `(alpha_j * G, alpha_j * Hp(P), b_j * Hp(P))`.
+//! 2. Once the coordinator has collected every commitment, each signer reveals
+//!    its [NonceReveal]. Committing first prevents a signer from choosing its
+//!    nonce depending on the nonces of the other signers.
+//! 3. The coordinator closes the ring, and sends the resulting
+//!    [ThresholdMlsagChallenge] to the signers, each of which recomputes the
+//!    challenge `c_pi` of the real input and returns a [PartialResponse].
+//!
+//! The coordinator checks each partial response against the signer's public
+//! share before assembling the [RingMLSAG], which is indistinguishable from one
+//! produced with the whole onetime private key.
+
+use alloc::vec::Vec;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use mc_crypto_hashes::{Blake2b512, Digest};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
+use rand_core::CryptoRngCore;
+use zeroize::Zeroize;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain_separators::THRESHOLD_MLSAG_NONCE_COMMITMENT_DOMAIN_TAG,
+    ring_signature::{
+        challenge, hash_to_point, CurveScalar, Error, KeyImage, PedersenGens, RingMLSAG, Scalar,
+        B_BLINDING,
+    },
+    Commitment, CompressedCommitment, ReducedTxOut,
+};
+
+/// A signer's share of a spend private key.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct KeyShare {
+    /// The index of the share, the point at which the polynomial is evaluated.
+    pub index: u32,
+
+    /// The share `b_j = f(index)`.
+    pub share: Scalar,
+}
+
+impl KeyShare {
+    /// The public counterpart of this share, used to check partial responses.
+    pub fn public_share(&self) -> PublicKeyShare {
+        PublicKeyShare {
+            index: self.index,
+            public_share: RistrettoPublic::from(self.share * B_BLINDING),
+        }
+    }
+}
+
+impl Drop for KeyShare {
+    fn drop(&mut self) {
+        self.share.zeroize();
+    }
+}
+
+/// The public counterpart `b_j * G` of a [KeyShare].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PublicKeyShare {
+    /// The index of the share.
+    pub index: u32,
+
+    /// The share times the base point, `b_j * G`.
+    pub public_share: RistrettoPublic,
+}
+
+/// Split a spend private key into `num_shares` shares, any `threshold` of
+/// which are needed to sign.
+///
+/// # Arguments
+/// * `spend_private_key` - The spend private key `b`.
+/// * `threshold` - The number of shares needed to sign.
+/// * `num_shares` - The number of shares to create, with indices
+///   `1..=num_shares`.
+/// * `rng` - Randomness.
+pub fn split_private_key(
+    spend_private_key: &Scalar,
+    threshold: u32,
+    num_shares: u32,
+    mut rng: impl CryptoRngCore,
+) -> Result<Vec<KeyShare>, Error> {
+    if threshold == 0 || threshold > num_shares {
+        return Err(Error::InvalidThreshold(threshold, num_shares));
+    }
+
+    // f(x) = b + a_1 * x + ... + a_{t-1} * x^{t-1}
+    let mut coefficients = alloc::vec![*spend_private_key];
+    coefficients.extend((1..threshold).map(|_| Scalar::random(&mut rng)));
+
+    let shares = (1..=num_shares)
+        .map(|index| {
+            // Horner's method
+            let x = Scalar::from(index);
+            let share = coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient);
+            KeyShare { index, share }
+        })
+        .collect();
+
+    coefficients.iter_mut().for_each(|v| v.zeroize());
+
+    Ok(shares)
+}
+
+/// The Lagrange coefficient `lambda_j` of the share `index`, for interpolating
+/// `f(0)` from the shares `indices`.
+pub fn lagrange_coefficient(index: u32, indices: &[u32]) -> Result<Scalar, Error> {
+    if index == 0 || !indices.contains(&index) {
+        return Err(Error::UnknownSigner(index));
+    }
+
+    let x_j = Scalar::from(index);
+    let mut numerator = Scalar::ONE;
+    let mut denominator = Scalar::ONE;
+    for &other in indices.iter().filter(|&&other| other != index) {
+        let x_m = Scalar::from(other);
+        numerator *= x_m;
+        denominator *= x_m - x_j;
+    }
+
+    if denominator == Scalar::ZERO {
+        return Err(Error::DuplicateSigner(index));
+    }
+
+    Ok(numerator * denominator.invert())
+}
+
+/// A signer's commitment to its [NonceReveal], sent in the first round.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct NonceCommitment {
+    /// The index of the signer's key share.
+    pub signer_index: u32,
+
+    /// `H(signer_index | L0 | R0 | partial_key_image)`
+    pub hash: [u8; 32],
+}
+
+/// The points a signer commits to in the first round, and reveals in the
+/// second round.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct NonceReveal {
+    /// The index of the signer's key share.
+    pub signer_index: u32,
+
+    /// `alpha_j * G`
+    pub L0: RistrettoPublic,
+
+    /// `alpha_j * Hp(P)`, where `P` is the real input's onetime public key.
+    pub R0: RistrettoPublic,
+
+    /// The signer's contribution to the key image, `b_j * Hp(P)`.
+    pub partial_key_image: RistrettoPublic,
+}
+
+impl NonceReveal {
+    /// The commitment to this reveal.
+    pub fn commitment(&self) -> NonceCommitment {
+        let mut hasher = Blake2b512::new();
+        hasher.update(THRESHOLD_MLSAG_NONCE_COMMITMENT_DOMAIN_TAG);
+        hasher.update(self.signer_index.to_le_bytes());
+        hasher.update(self.L0.to_bytes());
+        hasher.update(self.R0.to_bytes());
+        hasher.update(self.partial_key_image.to_bytes());
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&hasher.finalize()[..32]);
+
+        NonceCommitment {
+            signer_index: self.signer_index,
+            hash,
+        }
+    }
+}
+
+/// A signer's share of the response of the real input, sent in the third
+/// round.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PartialResponse {
+    /// The index of the signer's key share.
+    pub signer_index: u32,
+
+    /// `alpha_j - c_pi * lambda_j * b_j`
+    pub response: CurveScalar,
+}
+
+/// Everything a signer needs to recompute the challenge of the real input,
+/// sent by the coordinator in the third round.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ThresholdMlsagChallenge {
+    /// Message to be signed.
+    pub message: Vec<u8>,
+
+    /// A ring of reduced TxOuts.
+    pub ring: Vec<ReducedTxOut>,
+
+    /// The index in the ring of the real input.
+    pub real_index: usize,
+
+    /// Output amount commitment.
+    pub output_commitment: CompressedCommitment,
+
+    /// Key image "spent" by the signature.
+    pub key_image: KeyImage,
+
+    /// The reveals of every participating signer.
+    pub reveals: Vec<NonceReveal>,
+
+    /// `alpha_1 * G`, for the coordinator's nonce `alpha_1` of the commitment
+    /// to zero.
+    pub L1: RistrettoPublic,
+
+    /// Responses `r_{0,0}, r_{0,1}, ... , r_{ring_size-1,0},
+    /// r_{ring_size-1,1}`, with zeros for the real input.
+    pub responses: Vec<CurveScalar>,
+}
+
+impl ThresholdMlsagChallenge {
+    /// The indices of the participating signers.
+    fn signer_indices(&self) -> Vec<u32> {
+        self.reveals.iter().map(|r| r.signer_index).collect()
+    }
+
+    /// Walk the ring starting with the real input, returning the challenges
+    /// `(c_pi, c_0)`.
+    fn close_ring(&self) -> Result<(Scalar, Scalar), Error> {
+        let ring_size = self.ring.len();
+        if self.real_index >= ring_size {
+            return Err(Error::IndexOutOfBounds);
+        }
+        if self.responses.len() != 2 * ring_size {
+            return Err(Error::LengthMismatch(self.responses.len(), 2 * ring_size));
+        }
+
+        let G = B_BLINDING;
+        let I = self
+            .key_image
+            .point
+            .decompress()
+            .ok_or(Error::InvalidKeyImage)?;
+        let output_commitment = Commitment::try_from(&self.output_commitment)?;
+
+        // c_{pi+1} = Hn( m | key_image | sum(alpha_j) * G | sum(alpha_j) * Hp(P) |
+        //               alpha_1 * G )
+        let L0: RistrettoPoint = self.reveals.iter().map(|r| *r.L0.as_ref()).sum();
+        let R0: RistrettoPoint = self.reveals.iter().map(|r| *r.R0.as_ref()).sum();
+        let mut c = challenge(&self.message, &self.key_image, &L0, &R0, self.L1.as_ref());
+
+        let mut c_zero = None;
+        if (self.real_index + 1) % ring_size == 0 {
+            c_zero = Some(c);
+        }
+
+        for n in 1..ring_size {
+            let i = (self.real_index + n) % ring_size;
+            let (P_i, input_commitment): (RistrettoPublic, Commitment) =
+                (&self.ring[i]).try_into()?;
+
+            let r_0 = self.responses[2 * i].scalar;
+            let r_1 = self.responses[2 * i + 1].scalar;
+            let L0 = r_0 * G + c * P_i.as_ref();
+            let R0 = r_0 * hash_to_point(&P_i) + c * I;
+            let L1 = r_1 * G + c * (output_commitment.point - input_commitment.point);
+            c = challenge(&self.message, &self.key_image, &L0, &R0, &L1);
+
+            if (i + 1) % ring_size == 0 {
+                c_zero = Some(c);
+            }
+        }
+
+        // Having gone around the ring, c is the challenge of the real input.
+        Ok((c, c_zero.ok_or(Error::InvalidState)?))
+    }
+}
+
+/// A signer's state while signing one ring.
+///
+/// A nonce must only be used for a single signature, so it is consumed when
+/// responding to the challenge.
+pub struct ThresholdMlsagNonce {
+    alpha: Scalar,
+    target_key: RistrettoPublic,
+    reveal: NonceReveal,
+    commitments: Option<Vec<NonceCommitment>>,
+}
+
+impl ThresholdMlsagNonce {
+    /// First round: pick a nonce for signing a ring whose real input has the
+    /// onetime public key `target_key`.
+    pub fn new(
+        key_share: &KeyShare,
+        target_key: &RistrettoPublic,
+        mut rng: impl CryptoRngCore,
+    ) -> Self {
+        let alpha = Scalar::random(&mut rng);
+        let Hp = hash_to_point(target_key);
+
+        let reveal = NonceReveal {
+            signer_index: key_share.index,
+            L0: RistrettoPublic::from(alpha * B_BLINDING),
+            R0: RistrettoPublic::from(alpha * Hp),
+            partial_key_image: RistrettoPublic::from(key_share.share * Hp),
+        };
+
+        Self {
+            alpha,
+            target_key: *target_key,
+            reveal,
+            commitments: None,
+        }
+    }
+
+    /// The commitment to send to the coordinator.
+    pub fn commitment(&self) -> NonceCommitment {
+        self.reveal.commitment()
+    }
+
+    /// Second round: reveal the committed points, once the commitments of all
+    /// participating signers are known.
+    pub fn reveal(&mut self, commitments: &[NonceCommitment]) -> Result<NonceReveal, Error> {
+        check_unique_signers(commitments.iter().map(|c| c.signer_index))?;
+        if !commitments.contains(&self.commitment()) {
+            return Err(Error::NonceCommitmentMismatch(self.reveal.signer_index));
+        }
+
+        self.commitments = Some(commitments.to_vec());
+        Ok(self.reveal.clone())
+    }
+
+    /// Third round: respond to the coordinator's challenge.
+    pub fn respond(
+        self,
+        key_share: &KeyShare,
+        challenge: &ThresholdMlsagChallenge,
+    ) -> Result<PartialResponse, Error> {
+        let commitments = self.commitments.as_ref().ok_or(Error::InvalidState)?;
+
+        // The coordinator must use the reveals of exactly the signers that
+        // committed in the first round.
+        if challenge.reveals.len() != commitments.len() {
+            return Err(Error::LengthMismatch(
+                challenge.reveals.len(),
+                commitments.len(),
+            ));
+        }
+        for reveal in &challenge.reveals {
+            if !commitments.contains(&reveal.commitment()) {
+                return Err(Error::NonceCommitmentMismatch(reveal.signer_index));
+            }
+        }
+
+        let real_input = challenge
+            .ring
+            .get(challenge.real_index)
+            .ok_or(Error::IndexOutOfBounds)?;
+        if real_input.target_key != CompressedRistrettoPublic::from(self.target_key) {
+            return Err(Error::UnexpectedTxout);
+        }
+
+        let (c_pi, _c_zero) = challenge.close_ring()?;
+        let lambda = lagrange_coefficient(key_share.index, &challenge.signer_indices())?;
+
+        Ok(PartialResponse {
+            signer_index: key_share.index,
+            response: CurveScalar::from(self.alpha - c_pi * lambda * key_share.share),
+        })
+    }
+}
+
+impl Drop for ThresholdMlsagNonce {
+    fn drop(&mut self) {
+        self.alpha.zeroize();
+    }
+}
+
+/// The coordinator's state while signing one ring.
+pub struct ThresholdMlsagCoordinator {
+    message: Vec<u8>,
+    ring: Vec<ReducedTxOut>,
+    real_index: usize,
+    key_offset: Scalar,
+    output_commitment: CompressedCommitment,
+    commitment_to_zero_blinding: Scalar,
+    threshold: u32,
+    public_shares: Vec<PublicKeyShare>,
+    alpha_1: Scalar,
+    responses: Vec<CurveScalar>,
+    commitments: Vec<NonceCommitment>,
+    challenge: Option<ThresholdMlsagChallenge>,
+}
+
+impl ThresholdMlsagCoordinator {
+    /// Start signing a ring.
+    ///
+    /// # Arguments
+    /// * `message` - Message to be signed.
+    /// * `ring` - A ring of reduced TxOuts
+    /// * `real_index` - The index in the ring of the real input.
+    /// * `key_offset` - The part `k = x - b` of the real input's onetime
+    ///   private key that does not depend on the spend private key.
+    /// * `value` - Value of the real input.
+    /// * `blinding` - Blinding of the real input.
+    /// * `output_blinding` - The output amount's blinding factor.
+    /// * `generator` - The pedersen generator to use for this commitment and
+    ///   signature
+    /// * `threshold` - The number of signers needed to sign.
+    /// * `public_shares` - The public shares of every signer.
+    /// * `rng` - Randomness.
+    pub fn new(
+        message: &[u8],
+        ring: &[ReducedTxOut],
+        real_index: usize,
+        key_offset: &Scalar,
+        value: u64,
+        blinding: &Scalar,
+        output_blinding: &Scalar,
+        generator: &PedersenGens,
+        threshold: u32,
+        public_shares: &[PublicKeyShare],
+        mut rng: impl CryptoRngCore,
+    ) -> Result<Self, Error> {
+        let ring_size = ring.len();
+        let real_input = ring.get(real_index).ok_or(Error::IndexOutOfBounds)?;
+        check_unique_signers(public_shares.iter().map(|s| s.index))?;
+        if threshold == 0 || threshold as usize > public_shares.len() {
+            return Err(Error::InvalidThreshold(
+                threshold,
+                public_shares.len() as u32,
+            ));
+        }
+
+        // The difference between the output and input commitments must be a
+        // commitment to zero, i.e. a multiple of G.
+        let output_commitment = Commitment::new(value, *output_blinding, generator);
+        let input_commitment = Commitment::try_from(&real_input.commitment)?;
+        let commitment_to_zero_blinding = output_blinding - blinding;
+        if output_commitment.point - input_commitment.point
+            != commitment_to_zero_blinding * B_BLINDING
+        {
+            return Err(Error::ValueNotConserved);
+        }
+
+        let mut responses = alloc::vec![CurveScalar::from(Scalar::ZERO); 2 * ring_size];
+        for i in (0..ring_size).filter(|&i| i != real_index) {
+            responses[2 * i] = CurveScalar::from(Scalar::random(&mut rng));
+            responses[2 * i + 1] = CurveScalar::from(Scalar::random(&mut rng));
+        }
+
+        Ok(Self {
+            message: message.to_vec(),
+            ring: ring.to_vec(),
+            real_index,
+            key_offset: *key_offset,
+            output_commitment: CompressedCommitment::from(&output_commitment),
+            commitment_to_zero_blinding,
+            threshold,
+            public_shares: public_shares.to_vec(),
+            alpha_1: Scalar::random(&mut rng),
+            responses,
+            commitments: Vec::new(),
+            challenge: None,
+        })
+    }
+
+    /// The onetime public key of the real input, which signers need in the
+    /// first round.
+    pub fn target_key(&self) -> Result<RistrettoPublic, Error> {
+        RistrettoPublic::try_from(&self.ring[self.real_index].target_key)
+            .map_err(|_e| Error::InvalidCurvePoint)
+    }
+
+    /// After the first round: record the commitments of the participating
+    /// signers, which are then sent to each of them.
+    pub fn add_commitments(&mut self, commitments: &[NonceCommitment]) -> Result<(), Error> {
+        check_unique_signers(commitments.iter().map(|c| c.signer_index))?;
+        for commitment in commitments {
+            self.public_share(commitment.signer_index)?;
+        }
+        if commitments.len() < self.threshold as usize {
+            return Err(Error::NotEnoughSigners(
+                commitments.len() as u32,
+                self.threshold,
+            ));
+        }
+
+        self.commitments = commitments.to_vec();
+        Ok(())
+    }
+
+    /// After the second round: check the reveals against their commitments
+    /// and close the ring, producing the challenge for the third round.
+    pub fn challenge(&mut self, reveals: &[NonceReveal]) -> Result<ThresholdMlsagChallenge, Error> {
+        if self.commitments.is_empty() {
+            return Err(Error::InvalidState);
+        }
+        if reveals.len() != self.commitments.len() {
+            return Err(Error::LengthMismatch(reveals.len(), self.commitments.len()));
+        }
+        for reveal in reveals {
+            if !self.commitments.contains(&reveal.commitment()) {
+                return Err(Error::NonceCommitmentMismatch(reveal.signer_index));
+            }
+        }
+
+        // I = k * Hp(P) + sum(lambda_j * b_j * Hp(P))
+        let indices: Vec<u32> = reveals.iter().map(|r| r.signer_index).collect();
+        let mut I = self.key_offset * hash_to_point(&self.target_key()?);
+        for reveal in reveals {
+            let lambda = lagrange_coefficient(reveal.signer_index, &indices)?;
+            I += lambda * reveal.partial_key_image.as_ref();
+        }
+
+        let challenge = ThresholdMlsagChallenge {
+            message: self.message.clone(),
+            ring: self.ring.clone(),
+            real_index: self.real_index,
+            output_commitment: self.output_commitment,
+            key_image: KeyImage {
+                point: I.compress(),
+            },
+            reveals: reveals.to_vec(),
+            L1: RistrettoPublic::from(self.alpha_1 * B_BLINDING),
+            responses: self.responses.clone(),
+        };
+
+        self.challenge = Some(challenge.clone());
+        Ok(challenge)
+    }
+
+    /// After the third round: check the partial responses, and assemble the
+    /// signature.
+    pub fn finalize(mut self, partial_responses: &[PartialResponse]) -> Result<RingMLSAG, Error> {
+        let challenge = self.challenge.take().ok_or(Error::InvalidState)?;
+        if partial_responses.len() != challenge.reveals.len() {
+            return Err(Error::LengthMismatch(
+                partial_responses.len(),
+                challenge.reveals.len(),
+            ));
+        }
+
+        let (c_pi, c_zero) = challenge.close_ring()?;
+        let indices = challenge.signer_indices();
+        let Hp = hash_to_point(&self.target_key()?);
+
+        // r_{pi,0} = sum(alpha_j - c_pi * lambda_j * b_j) - c_pi * k
+        let mut r_0 = -(c_pi * self.key_offset);
+        for reveal in &challenge.reveals {
+            let signer_index = reveal.signer_index;
+            let s_j = partial_responses
+                .iter()
+                .find(|p| p.signer_index == signer_index)
+                .ok_or(Error::UnknownSigner(signer_index))?
+                .response
+                .scalar;
+            let lambda = lagrange_coefficient(signer_index, &indices)?;
+            let B_j = self.public_share(signer_index)?.public_share;
+
+            // s_j * G = alpha_j * G - c_pi * lambda_j * b_j * G
+            // s_j * Hp(P) = alpha_j * Hp(P) - c_pi * lambda_j * b_j * Hp(P)
+            let c_lambda = c_pi * lambda;
+            if s_j * B_BLINDING != reveal.L0.as_ref() - c_lambda * B_j.as_ref()
+                || s_j * Hp != reveal.R0.as_ref() - c_lambda * reveal.partial_key_image.as_ref()
+            {
+                return Err(Error::InvalidPartialResponse(signer_index));
+            }
+
+            r_0 += s_j;
+        }
+
+        // r_{pi,1} = alpha_1 - c_pi * z
+        let r_1 = self.alpha_1 - c_pi * self.commitment_to_zero_blinding;
+
+        let mut responses = challenge.responses;
+        responses[2 * self.real_index] = CurveScalar::from(r_0);
+        responses[2 * self.real_index + 1] = CurveScalar::from(r_1);
+
+        let signature = RingMLSAG {
+            c_zero: CurveScalar::from(c_zero),
+            responses,
+            key_image: challenge.key_image,
+        };
+        signature.verify(&self.message, &self.ring, &self.output_commitment)?;
+
+        Ok(signature)
+    }
+
+    fn public_share(&self, signer_index: u32) -> Result<PublicKeyShare, Error> {
+        self.public_shares
+            .iter()
+            .find(|s| s.index == signer_index)
+            .copied()
+            .ok_or(Error::UnknownSigner(signer_index))
+    }
+}
+
+impl Drop for ThresholdMlsagCoordinator {
+    fn drop(&mut self) {
+        self.key_offset.zeroize();
+        self.commitment_to_zero_blinding.zeroize();
+        self.alpha_1.zeroize();
+    }
+}
+
+/// Check that no signer index appears twice.
+fn check_unique_signers(indices: impl Iterator<Item = u32>) -> Result<(), Error> {
+    let mut seen = Vec::new();
+    for index in indices {
+        if seen.contains(&index) {
+            return Err(Error::DuplicateSigner(index));
+        }
+        seen.push(index);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod mlsag_threshold_tests {
+    use super::*;
+    use crate::generators;
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_util_from_random::FromRandom;
+    use mc_util_test_helper::{RngType, SeedableRng};
+    use proptest::prelude::*;
+
+    /// A ring whose real input has the onetime private key `key_offset + b`.
+    struct Params {
+        message: [u8; 32],
+        ring: Vec<ReducedTxOut>,
+        real_index: usize,
+        key_offset: Scalar,
+        value: u64,
+        blinding: Scalar,
+        output_blinding: Scalar,
+        generator: PedersenGens,
+    }
+
+    impl Params {
+        fn random(
+            num_mixins: usize,
+            spend_private_key: &Scalar,
+            rng: &mut impl CryptoRngCore,
+        ) -> Self {
+            let mut message = [0u8; 32];
+            rng.fill_bytes(&mut message);
+            let generator = generators(rng.next_u64());
+
+            let mut ring: Vec<ReducedTxOut> = (0..num_mixins)
+                .map(|_| ReducedTxOut {
+                    public_key: CompressedRistrettoPublic::from_random(rng),
+                    target_key: CompressedRistrettoPublic::from_random(rng),
+                    commitment: CompressedCommitment::new(
+                        rng.next_u64(),
+                        Scalar::random(rng),
+                        &generator,
+                    ),
+                })
+                .collect();
+
+            let key_offset = Scalar::random(rng);
+            let value = rng.next_u64();
+            let blinding = Scalar::random(rng);
+            let real_index = rng.next_u64() as usize % (num_mixins + 1);
+            ring.insert(
+                real_index,
+                ReducedTxOut {
+                    public_key: CompressedRistrettoPublic::from_random(rng),
+                    target_key: CompressedRistrettoPublic::from(RistrettoPublic::from(
+                        (key_offset + spend_private_key) * B_BLINDING,
+                    )),
+                    commitment: CompressedCommitment::new(value, blinding, &generator),
+                },
+            );
+
+            Self {
+                message,
+                ring,
+                real_index,
+                key_offset,
+                value,
+                blinding,
+                output_blinding: Scalar::random(rng),
+                generator,
+            }
+        }
+
+        fn coordinator(
+            &self,
+            threshold: u32,
+            public_shares: &[PublicKeyShare],
+            rng: &mut impl CryptoRngCore,
+        ) -> ThresholdMlsagCoordinator {
+            ThresholdMlsagCoordinator::new(
+                &self.message,
+                &self.ring,
+                self.real_index,
+                &self.key_offset,
+                self.value,
+                &self.blinding,
+                &self.output_blinding,
+                &self.generator,
+                threshold,
+                public_shares,
+                rng,
+            )
+            .unwrap()
+        }
+
+        fn output_commitment(&self) -> CompressedCommitment {
+            CompressedCommitment::new(self.value, self.output_blinding, &self.generator)
+        }
+    }
+
+    /// Run the three rounds with the given signers.
+    fn sign(
+        coordinator: &mut ThresholdMlsagCoordinator,
+        signers: &[&KeyShare],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(ThresholdMlsagChallenge, Vec<PartialResponse>), Error> {
+        let target_key = coordinator.target_key()?;
+        let mut nonces: Vec<_> = signers
+            .iter()
+            .map(|share| ThresholdMlsagNonce::new(share, &target_key, &mut *rng))
+            .collect();
+
+        let commitments: Vec<_> = nonces.iter().map(|n| n.commitment()).collect();
+        coordinator.add_commitments(&commitments)?;
+
+        let reveals = nonces
+            .iter_mut()
+            .map(|n| n.reveal(&commitments))
+            .collect::<Result<Vec<_>, _>>()?;
+        let challenge = coordinator.challenge(&reveals)?;
+
+        let partial_responses = nonces
+            .into_iter()
+            .zip(signers)
+            .map(|(n, share)| n.respond(share, &challenge))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((challenge, partial_responses))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(4))]
+
+        #[test]
+        // Any `threshold` shares interpolate the spend private key, fewer do not.
+        fn test_split_private_key_interpolates(seed in any::<[u8; 32]>()) {
+            let mut rng: RngType = SeedableRng::from_seed(seed);
+            let spend_private_key = Scalar::random(&mut rng);
+            let shares = split_private_key(&spend_private_key, 3, 5, &mut rng).unwrap();
+
+            let interpolate = |subset: &[&KeyShare]| {
+                let indices: Vec<u32> = subset.iter().map(|s| s.index).collect();
+                subset.iter().fold(Scalar::ZERO, |acc, s| {
+                    acc + lagrange_coefficient(s.index, &indices).unwrap() * s.share
+                })
+            };
+
+            assert_eq!(interpolate(&[&shares[0], &shares[1], &shares[2]]), spend_private_key);
+            assert_eq!(interpolate(&[&shares[4], &shares[0], &shares[3]]), spend_private_key);
+            assert_eq!(interpolate(&shares.iter().collect::<Vec<_>>()), spend_private_key);
+            assert_ne!(interpolate(&[&shares[1], &shares[3]]), spend_private_key);
+        }
+
+        #[test]
+        // A threshold signature verifies, and has the same key image as a
+        // signature made with the whole onetime private key.
+        fn test_threshold_sign_verifies(
+            num_mixins in 0..11usize,
+            seed in any::<[u8; 32]>(),
+        ) {
+            let mut rng: RngType = SeedableRng::from_seed(seed);
+            let spend_private_key = Scalar::random(&mut rng);
+            let shares = split_private_key(&spend_private_key, 3, 5, &mut rng).unwrap();
+            let public_shares: Vec<_> = shares.iter().map(|s| s.public_share()).collect();
+            let params = Params::random(num_mixins, &spend_private_key, &mut rng);
+
+            let mut coordinator = params.coordinator(3, &public_shares, &mut rng);
+            let (_challenge, partial_responses) =
+                sign(&mut coordinator, &[&shares[4], &shares[1], &shares[2]], &mut rng).unwrap();
+            let signature = coordinator.finalize(&partial_responses).unwrap();
+
+            signature
+                .verify(&params.message, &params.ring, &params.output_commitment())
+                .unwrap();
+
+            let onetime_private_key = RistrettoPrivate::from(params.key_offset + spend_private_key);
+            assert_eq!(signature.key_image, KeyImage::from(&onetime_private_key));
+        }
+
+        #[test]
+        // The coordinator rejects too few signers, and signers that do not
+        // match their commitments or public shares.
+        fn test_threshold_sign_rejects_misbehaviour(seed in any::<[u8; 32]>()) {
+            let mut rng: RngType = SeedableRng::from_seed(seed);
+            let spend_private_key = Scalar::random(&mut rng);
+            let shares = split_private_key(&spend_private_key, 2, 3, &mut rng).unwrap();
+            let public_shares: Vec<_> = shares.iter().map(|s| s.public_share()).collect();
+            let params = Params::random(4, &spend_private_key, &mut rng);
+
+            let mut coordinator = params.coordinator(2, &public_shares, &mut rng);
+            assert_eq!(
+                sign(&mut coordinator, &[&shares[0]], &mut rng).unwrap_err(),
+                Error::NotEnoughSigners(1, 2)
+            );
+
+            // A reveal that does not match its commitment.
+            let mut coordinator = params.coordinator(2, &public_shares, &mut rng);
+            let target_key = coordinator.target_key().unwrap();
+            let mut nonces: Vec<_> = shares[..2]
+                .iter()
+                .map(|share| ThresholdMlsagNonce::new(share, &target_key, &mut rng))
+                .collect();
+            let commitments: Vec<_> = nonces.iter().map(|n| n.commitment()).collect();
+            coordinator.add_commitments(&commitments).unwrap();
+            let mut reveals: Vec<_> =
+                nonces.iter_mut().map(|n| n.reveal(&commitments).unwrap()).collect();
+            reveals[1].R0 = RistrettoPublic::from_random(&mut rng);
+            assert_eq!(
+                coordinator.challenge(&reveals).unwrap_err(),
+                Error::NonceCommitmentMismatch(shares[1].index)
+            );
+
+            // A partial response made with the wrong share.
+            let mut coordinator = params.coordinator(2, &public_shares, &mut rng);
+            let wrong_share = KeyShare {
+                index: shares[1].index,
+                share: Scalar::random(&mut rng),
+            };
+            let (_challenge, partial_responses) =
+                sign(&mut coordinator, &[&shares[0], &wrong_share], &mut rng).unwrap();
+            assert_eq!(
+                coordinator.finalize(&partial_responses).unwrap_err(),
+                Error::InvalidPartialResponse(shares[1].index)
+            );
+        }
+    }
+}
@@ -25,9 +25,19 @@ mod mlsag_verify;
 #[cfg(feature = "alloc")]
 mod mlsag;
 
+#[cfg(feature = "alloc")]
+mod mlsag_threshold;
+
 #[cfg(feature = "alloc")]
 pub use self::mlsag::RingMLSAG;
 
+#[cfg(feature = "alloc")]
+pub use self::mlsag_threshold::{
+    lagrange_coefficient, split_private_key, KeyShare, NonceCommitment, NonceReveal,
+    PartialResponse, PublicKeyShare, ThresholdMlsagChallenge, ThresholdMlsagCoordinator,
+    ThresholdMlsagNonce,
+};
+
 pub use self::{curve_scalar::CurveScalar, error::Error, key_image::KeyImage};
 
 use crate::{
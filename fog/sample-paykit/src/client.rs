@@ -7,7 +7,7 @@ use crate::{
     error::{Error, Result},
     BlockInfo, MemoHandlerError, TransactionStatus,
 };
-use core::{result::Result as StdResult, str::FromStr};
+use core::{convert::Infallible, result::Result as StdResult, str::FromStr};
 use mc_account_keys::{AccountKey, PublicAddress};
 use mc_attestation_verifier::TrustedIdentity;
 use mc_blockchain_types::{BlockIndex, BlockVersion};
@@ -30,7 +30,7 @@ use mc_fog_types::{ledger::KeyImageResultCode, BlockCount};
 use mc_fog_view_connection::FogViewGrpcClient;
 use mc_rand::{CryptoRng, RngCore};
 use mc_transaction_builder::{
-    EmptyMemoBuilder, InputCredentials, RTHMemoBuilder, ReservedSubaddresses,
+    DecoyDistribution, EmptyMemoBuilder, InputCredentials, RTHMemoBuilder, ReservedSubaddresses,
     SignedContingentInputBuilder, TransactionBuilder,
 };
 use mc_transaction_core::{
@@ -40,8 +40,7 @@ use mc_transaction_core::{
 use mc_transaction_extra::{MemoType, SenderMemoCredential, SignedContingentInput};
 use mc_util_telemetry::{block_span_builder, telemetry_static_key, tracer, Key, Span};
 use mc_util_uri::{ConnectionUri, FogUri};
-use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 
 /// Default number of blocks used for calculating transaction tombstone block
 /// number. See `new_tx_block_attempts` below.
@@ -71,6 +70,9 @@ pub struct Client {
     /// tombstone block when generating a new transaction.
     new_tx_block_attempts: u16,
 
+    /// Distribution that ring mixins are sampled from.
+    decoy_distribution: DecoyDistribution,
+
     logger: Logger,
 }
 
@@ -106,6 +108,7 @@ impl Client {
             tx_data,
             block_info_cache: None,
             new_tx_block_attempts: DEFAULT_NEW_TX_BLOCK_ATTEMPTS,
+            decoy_distribution: Default::default(),
             logger,
         }
     }
@@ -133,6 +136,11 @@ impl Client {
         self.new_tx_block_attempts = new_tx_block_attempts;
     }
 
+    /// Sets the distribution that ring mixins are sampled from.
+    pub fn set_decoy_distribution(&mut self, decoy_distribution: DecoyDistribution) {
+        self.decoy_distribution = decoy_distribution;
+    }
+
     /// Check this user's current available balance.
     ///
     /// Returns:
@@ -810,9 +818,9 @@ impl Client {
 
     /// Gets several rings' worth of mixin TxOuts, with proofs of membership.
     ///
-    /// TODO: In discussions, some think the mixin distribution should
-    /// eventually be made "relatively close" to the global index of the
-    /// true input, but that is not implemented yet.
+    /// Mixins are sampled from self.decoy_distribution. Fog does not tell us
+    /// which block each TxOut is in, so for age-based distributions the
+    /// TxOuts are assumed to be spread evenly over the blocks we know of.
     ///
     /// # Arguments
     /// * true_inputs: The true inputs and membership proofs for these
@@ -830,7 +838,7 @@ impl Client {
     ) -> Result<Vec<Vec<(TxOut, TxOutMembershipProof)>>> {
         mc_common::trace_time!(self.logger, "MobileCoinClient.get_rings");
 
-        let mut all_avoid_indices: HashSet<u64> = true_inputs
+        let mut all_avoid_indices: BTreeSet<u64> = true_inputs
             .iter()
             .map(|input| input.0.global_index)
             .collect();
@@ -852,14 +860,23 @@ impl Client {
             ));
         }
 
-        let mut sampled_indices: HashSet<u64> = HashSet::default();
-        while sampled_indices.len() < num_requested {
-            let index = rng.gen_range(0..sample_limit) as u64;
-            if !all_avoid_indices.contains(&index) && !sampled_indices.contains(&index) {
-                sampled_indices.insert(index);
-            }
-        }
-        let indices: Vec<u64> = sampled_indices.iter().cloned().collect();
+        let num_txos = sample_limit as u64;
+        let num_blocks = u64::from(self.tx_data.get_num_blocks());
+        let indices: Vec<u64> = self
+            .decoy_distribution
+            .sample_indices(
+                num_requested,
+                &all_avoid_indices,
+                num_txos,
+                num_blocks,
+                |index| {
+                    Ok::<_, Infallible>(
+                        (index as u128 * num_blocks as u128 / num_txos as u128) as u64,
+                    )
+                },
+                rng,
+            )
+            .unwrap_or_else(|err| match err {});
         // FIXME: We are not sure whether this is a necessary parameter under ORAM.
         let merkle_root_block: u64 = 0; // self.get_txo_cursor(); // cursor > 0 ? cursor - 1 : 0
 
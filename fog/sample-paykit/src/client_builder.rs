@@ -16,6 +16,7 @@ use mc_fog_report_connection::GrpcFogReportConnection;
 use mc_fog_uri::{FogLedgerUri, FogViewUri};
 use mc_fog_view_connection::FogViewGrpcClient;
 use mc_sgx_css::Signature;
+use mc_transaction_builder::DecoyDistribution;
 use mc_transaction_core::constants::RING_SIZE;
 use mc_util_grpc::GrpcRetryConfig;
 use mc_util_uri::{ConnectionUri, ConsensusClientUri};
@@ -34,6 +35,7 @@ pub struct ClientBuilder {
 
     // Optional, has sane defaults
     ring_size: usize,
+    decoy_distribution: DecoyDistribution,
 
     // Uris to fog services
     fog_view_address: FogViewUri,
@@ -66,6 +68,7 @@ impl ClientBuilder {
             logger,
             grpc_retry_config: Default::default(),
             ring_size: RING_SIZE,
+            decoy_distribution: Default::default(),
             fog_view_address,
             ledger_server_address,
             address_book: Default::default(),
@@ -90,6 +93,13 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the distribution that ring mixins are sampled from.
+    #[must_use]
+    pub fn decoy_distribution(mut self, decoy_distribution: DecoyDistribution) -> Self {
+        self.decoy_distribution = decoy_distribution;
+        self
+    }
+
     /// Sets the address book for the client, used with memos
     #[must_use]
     pub fn address_book(mut self, address_book: Vec<PublicAddress>) -> Self {
@@ -172,7 +182,7 @@ impl ClientBuilder {
         let fog_report_conn =
             GrpcFogReportConnection::new(self.chain_id.clone(), grpc_env, self.logger.clone());
 
-        Client::new(
+        let mut client = Client::new(
             consensus_service_conn,
             fog_view_client,
             fog_merkle_proof,
@@ -185,7 +195,9 @@ impl ClientBuilder {
             self.key,
             self.address_book,
             self.logger,
-        )
+        );
+        client.set_decoy_distribution(self.decoy_distribution);
+        client
     }
 
     // Build a Fog View connection, taking into account acct_host_override
@@ -90,6 +90,15 @@ pub enum Error {
 
     /// Missing masked amonut
     MissingMaskedAmount,
+
+    /// Requested data was pruned from the ledger
+    Pruned,
+
+    /// Secondary indexes are not enabled
+    SecondaryIndexesDisabled,
+
+    /// SQLite: {0}
+    Sqlite(String),
 }
 
 // Implement Debug by forwarding to Display
@@ -146,6 +155,16 @@ impl From<RangeError> for Error {
     }
 }
 
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(src: rusqlite::Error) -> Self {
+        match src {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+            err => Error::Sqlite(err.to_string()),
+        }
+    }
+}
+
 impl From<MetadataStoreError> for Error {
     fn from(src: MetadataStoreError) -> Self {
         Self::MetadataStore(src)
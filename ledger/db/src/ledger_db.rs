@@ -1,16 +1,16 @@
 // Copyright (c) 2018-2023 The MobileCoin Foundation
 
 use crate::{
+    validation::{validate_append_block, AppendBlockState},
     ActiveMintConfig, ActiveMintConfigs, Error, Ledger, LedgerMetrics, MetadataStore,
-    MetadataStoreSettings, MintConfigStore, MintTxStore, TxOutStore,
+    MetadataStoreSettings, MintConfigStore, MintTxStore, SecondaryIndexStore, TxOutStore,
 };
 use lmdb::{
     Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
     Transaction, WriteFlags,
 };
 use mc_blockchain_types::{
-    Block, BlockContents, BlockData, BlockID, BlockIndex, BlockMetadata, BlockSignature,
-    BlockVersion, MAX_BLOCK_VERSION,
+    Block, BlockContents, BlockData, BlockIndex, BlockMetadata, BlockSignature,
 };
 use mc_common::{logger::global_log, HashMap};
 use mc_crypto_keys::CompressedRistrettoPublic;
@@ -35,7 +35,7 @@ use std::{
 pub const MAX_LMDB_FILE_SIZE: usize = 1 << 40; // 1 TB
 
 /// maximum number of [Database]s in the lmdb file
-pub const MAX_LMDB_DATABASES: u32 = 19;
+pub const MAX_LMDB_DATABASES: u32 = 23;
 
 // LMDB Database names.
 pub const COUNTS_DB_NAME: &str = "ledger_db:counts";
@@ -47,8 +47,13 @@ pub const KEY_IMAGES_BY_BLOCK_DB_NAME: &str = "ledger_db:key_images_by_block";
 pub const TX_OUTS_BY_BLOCK_DB_NAME: &str = "ledger_db:tx_outs_by_block";
 pub const BLOCK_NUMBER_BY_TX_OUT_INDEX: &str = "ledger_db:block_number_by_tx_out_index";
 
+/// Number of blocks indexed per LMDB transaction when backfilling secondary
+/// indexes.
+const SECONDARY_INDEX_BACKFILL_BATCH_SIZE: u64 = 1000;
+
 /// Keys used by the `counts` database.
 pub const NUM_BLOCKS_KEY: &str = "num_blocks";
+pub const PRUNED_BELOW_KEY: &str = "pruned_below";
 
 /// OpenTelemetry keys
 const TELEMETRY_BLOCK_INDEX_KEY: Key = telemetry_static_key!("block-index");
@@ -99,6 +104,8 @@ pub struct LedgerDB {
 
     /// Aggregate counts about the ledger.
     /// * `NUM_BLOCKS_KEY` --> number of blocks in the ledger.
+    /// * `PRUNED_BELOW_KEY` --> signatures and metadata of blocks below this
+    ///   index have been pruned. Missing if the ledger was never pruned.
     counts: Database,
 
     /// Blocks by block number. `block number -> Block`
@@ -134,11 +141,22 @@ pub struct LedgerDB {
     /// Storage abstraction for mint transactions.
     mint_tx_store: MintTxStore,
 
+    /// Storage abstraction for the optional secondary indexes.
+    secondary_index_store: SecondaryIndexStore,
+
     /// Location on filesystem.
     path: PathBuf,
 
     /// Metrics.
     metrics: LedgerMetrics,
+
+    /// When set, only the signatures and metadata of this many most recent
+    /// blocks are retained. See [LedgerDB::enable_pruning].
+    num_retained_blocks: Option<u64>,
+
+    /// When set, secondary indexes are maintained as blocks are appended.
+    /// See [LedgerDB::enable_secondary_indexes].
+    secondary_indexes_enabled: bool,
 }
 
 /// LedgerDB is an append-only log (or chain) of blocks of transactions.
@@ -172,7 +190,11 @@ impl Ledger for LedgerDB {
         let mut db_transaction = self.env.begin_rw_txn()?;
 
         // Validate the block is safe to append.
-        self.validate_append_block(block, block_contents, metadata, &db_transaction)?;
+        let state = LmdbAppendBlockState {
+            ledger_db: self,
+            db_transaction: &db_transaction,
+        };
+        validate_append_block(&state, block, block_contents, metadata)?;
 
         // Write key images included in block.
         self.write_key_images(block.index, &block_contents.key_images, &mut db_transaction)?;
@@ -200,6 +222,26 @@ impl Ledger for LedgerDB {
         // Write block.
         self.write_block(block, signature, metadata, &mut db_transaction)?;
 
+        // Index the block, catching up on any blocks appended while indexing
+        // was disabled.
+        if self.secondary_indexes_enabled {
+            self.update_secondary_indexes(block.index, &mut db_transaction)?;
+            let first_tx_out_index = self.tx_out_store.num_tx_outs(&db_transaction)?
+                - block_contents.outputs.len() as u64;
+            self.secondary_index_store.index_block(
+                block,
+                block_contents,
+                signature,
+                first_tx_out_index,
+                &mut db_transaction,
+            )?;
+        }
+
+        // Drop signatures and metadata that fell out of the retention window.
+        if let Some(num_retained_blocks) = self.num_retained_blocks {
+            self.prune_impl(block.index + 1, num_retained_blocks, &mut db_transaction)?;
+        }
+
         // Commit.
         db_transaction.commit()?;
 
@@ -262,6 +304,8 @@ impl Ledger for LedgerDB {
 
     /// Gets a block and all of its associated data by its index in the
     /// blockchain.
+    ///
+    /// Signatures and metadata that were pruned are returned as `None`.
     fn get_block_data(&self, block_number: u64) -> Result<BlockData, Error> {
         let db_transaction = self.env.begin_ro_txn()?;
 
@@ -269,12 +313,12 @@ impl Ledger for LedgerDB {
         let contents = self.get_block_contents_impl(&db_transaction, block_number)?;
         let signature = match self.get_block_signature_impl(&db_transaction, block_number) {
             Ok(sig) => Ok(Some(sig)),
-            Err(Error::NotFound) => Ok(None),
+            Err(Error::NotFound) | Err(Error::Pruned) => Ok(None),
             Err(err) => Err(err),
         }?;
         let metadata = match self.get_block_metadata_impl(&db_transaction, block_number) {
             Ok(metadata) => Ok(Some(metadata)),
-            Err(Error::NotFound) => Ok(None),
+            Err(Error::NotFound) | Err(Error::Pruned) => Ok(None),
             Err(err) => Err(err),
         }?;
 
@@ -427,6 +471,64 @@ impl Ledger for LedgerDB {
         self.mint_config_store
             .get_active_mint_config_for_mint_tx(mint_tx, &db_transaction)
     }
+
+    /// Get the indices of blocks whose signature timestamp is within
+    /// `[start_timestamp, end_timestamp)`, ordered by timestamp. Blocks
+    /// without a signature are not indexed.
+    fn get_block_indices_by_timestamp(
+        &self,
+        start_timestamp: u64,
+        end_timestamp: u64,
+    ) -> Result<Vec<BlockIndex>, Error> {
+        if !self.secondary_indexes_enabled {
+            return Err(Error::SecondaryIndexesDisabled);
+        }
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.secondary_index_store.get_block_indices_by_timestamp(
+            start_timestamp,
+            end_timestamp,
+            &db_transaction,
+        )
+    }
+
+    /// Get the global indices of TxOuts in `[start_index, end_index)` that
+    /// were minted for the given token id.
+    fn get_minted_tx_out_indices_by_token_id(
+        &self,
+        token_id: TokenId,
+        start_index: u64,
+        end_index: u64,
+    ) -> Result<Vec<u64>, Error> {
+        if !self.secondary_indexes_enabled {
+            return Err(Error::SecondaryIndexesDisabled);
+        }
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.secondary_index_store
+            .get_minted_tx_out_indices_by_token_id(
+                token_id,
+                start_index,
+                end_index,
+                &db_transaction,
+            )
+    }
+
+    /// Get the global indices of TxOuts in `[start_index, end_index)` that
+    /// carry an encrypted memo.
+    fn get_tx_out_indices_with_e_memo(
+        &self,
+        start_index: u64,
+        end_index: u64,
+    ) -> Result<Vec<u64>, Error> {
+        if !self.secondary_indexes_enabled {
+            return Err(Error::SecondaryIndexesDisabled);
+        }
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.secondary_index_store.get_tx_out_indices_with_e_memo(
+            start_index,
+            end_index,
+            &db_transaction,
+        )
+    }
 }
 
 impl LedgerDB {
@@ -462,6 +564,7 @@ impl LedgerDB {
         let tx_out_store = TxOutStore::new(&env)?;
         let mint_config_store = MintConfigStore::new(&env)?;
         let mint_tx_store = MintTxStore::new(&env)?;
+        let secondary_index_store = SecondaryIndexStore::new(&env)?;
 
         let metrics = LedgerMetrics::new(path);
 
@@ -479,7 +582,10 @@ impl LedgerDB {
             tx_out_store,
             mint_config_store,
             mint_tx_store,
+            secondary_index_store,
             metrics,
+            num_retained_blocks: None,
+            secondary_indexes_enabled: false,
         };
 
         // Get initial values for gauges.
@@ -508,6 +614,7 @@ impl LedgerDB {
         TxOutStore::create(&env)?;
         MintConfigStore::create(&env)?;
         MintTxStore::create(&env)?;
+        SecondaryIndexStore::create(&env)?;
 
         let mut db_transaction = env.begin_rw_txn()?;
 
@@ -539,6 +646,285 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Enable pruned mode.
+    ///
+    /// Block signatures and metadata are deleted for all but the
+    /// `num_retained_blocks` most recent blocks, both immediately and whenever
+    /// a new block is appended. The signature and metadata of the latest block
+    /// are always retained. Blocks, TxOuts (including the Merkle tree used for
+    /// membership proofs) and key images are never pruned.
+    ///
+    /// Pruning only applies to this instance (and clones made after this call),
+    /// but pruned data is permanently gone from the database.
+    pub fn enable_pruning(&mut self, num_retained_blocks: u64) -> Result<(), Error> {
+        self.num_retained_blocks = Some(num_retained_blocks);
+
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        let num_blocks = key_bytes_to_u64(db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        self.prune_impl(num_blocks, num_retained_blocks, &mut db_transaction)?;
+        db_transaction.commit()?;
+
+        let file_size = self.db_file_size().unwrap_or(0);
+        self.metrics.db_file_size.set(file_size as i64);
+
+        Ok(())
+    }
+
+    /// Get the index of the first block whose signature and metadata have not
+    /// been pruned. Returns 0 if the ledger was never pruned.
+    pub fn pruned_below(&self) -> Result<BlockIndex, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.pruned_below_impl(&db_transaction)
+    }
+
+    /// Enable secondary indexes.
+    ///
+    /// This maintains indexes of blocks by signature timestamp, of minted
+    /// TxOuts by token id and of TxOuts carrying an encrypted memo, which back
+    /// the range queries of the [Ledger] trait. Blocks already in the ledger are
+    /// indexed now, and new blocks are indexed as they are appended.
+    ///
+    /// Timestamps come from block signatures, so this should be enabled
+    /// before pruning to index blocks whose signatures would be pruned.
+    pub fn enable_secondary_indexes(&mut self) -> Result<(), Error> {
+        self.secondary_indexes_enabled = true;
+
+        // Backfill in batches to avoid one huge write transaction.
+        loop {
+            let mut db_transaction = self.env.begin_rw_txn()?;
+            let num_blocks = key_bytes_to_u64(db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+            let num_indexed_blocks = self
+                .secondary_index_store
+                .num_indexed_blocks(&db_transaction)?;
+            if num_indexed_blocks >= num_blocks {
+                return Ok(());
+            }
+
+            let target = num_blocks.min(num_indexed_blocks + SECONDARY_INDEX_BACKFILL_BATCH_SIZE);
+            self.update_secondary_indexes(target, &mut db_transaction)?;
+            db_transaction.commit()?;
+        }
+    }
+
+    /// Truncate the ledger so that `block_index` is its last block.
+    ///
+    /// Every block above `block_index` is removed, along with its TxOuts,
+    /// Merkle tree ranges, key images, MintTxs, MintConfigTxs, signature and
+    /// metadata. Active mint configurations are restored to their state as of
+    /// `block_index`, and secondary indexes are truncated to match. All changes
+    /// are made in a single LMDB transaction.
+    ///
+    /// This must not be called while other processes have the ledger open.
+    pub fn truncate_to(&mut self, block_index: BlockIndex) -> Result<(), Error> {
+        let mut db_transaction = self.env.begin_rw_txn()?;
+
+        let num_blocks = key_bytes_to_u64(db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        if block_index >= num_blocks {
+            return Err(Error::InvalidBlockIndex(block_index));
+        }
+        let new_num_blocks = block_index + 1;
+        if new_num_blocks == num_blocks {
+            return Ok(());
+        }
+
+        let first_removed: TxOutsByBlockValue =
+            decode(db_transaction.get(self.tx_outs_by_block, &u64_to_key_bytes(new_num_blocks))?)?;
+        let new_num_tx_outs = first_removed.first_tx_out_index;
+        let num_tx_outs = self.tx_out_store.num_tx_outs(&db_transaction)?;
+
+        // Remove the blocks, newest first.
+        let mut removed_minting_txs = false;
+        for removed_index in (new_num_blocks..num_blocks).rev() {
+            let key = u64_to_key_bytes(removed_index);
+
+            let key_image_list: KeyImageList =
+                decode(db_transaction.get(self.key_images_by_block, &key)?)?;
+            for key_image in &key_image_list.key_images {
+                db_transaction.del(self.key_images, &key_image, None)?;
+            }
+            db_transaction.del(self.key_images_by_block, &key, None)?;
+
+            let mint_txs = self
+                .mint_tx_store
+                .remove_mint_txs(removed_index, &mut db_transaction)?;
+            let validated_mint_config_txs = self
+                .mint_config_store
+                .remove_validated_mint_config_txs(removed_index, &mut db_transaction)?;
+            removed_minting_txs |= !mint_txs.is_empty() || !validated_mint_config_txs.is_empty();
+
+            db_transaction.del(self.tx_outs_by_block, &key, None)?;
+            db_transaction.del(self.blocks, &key, None)?;
+            for db in [self.block_signatures, self.block_metadata] {
+                match db_transaction.del(db, &key, None) {
+                    Ok(()) | Err(lmdb::Error::NotFound) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        for tx_out_index in new_num_tx_outs..num_tx_outs {
+            db_transaction.del(
+                self.block_number_by_tx_out_index,
+                &u64_to_key_bytes(tx_out_index),
+                None,
+            )?;
+        }
+        self.tx_out_store
+            .truncate(new_num_tx_outs, &mut db_transaction)?;
+
+        // Active mint configurations only record their latest state, so rebuild
+        // them by replaying the minting transactions of the remaining blocks.
+        if removed_minting_txs {
+            self.mint_config_store
+                .clear_active_mint_configs(&mut db_transaction)?;
+            for replayed_index in 0..new_num_blocks {
+                // MintTxs are applied before the block's configurations become active.
+                let mint_txs = self
+                    .mint_tx_store
+                    .get_mint_txs_by_block_index(replayed_index, &db_transaction)?;
+                for mint_tx in &mint_txs {
+                    self.mint_config_store
+                        .record_mint_tx(mint_tx, &mut db_transaction)?;
+                }
+
+                let validated_mint_config_txs = self
+                    .mint_config_store
+                    .get_validated_mint_config_txs_by_block_index(
+                        replayed_index,
+                        &db_transaction,
+                    )?;
+                for validated_mint_config_tx in &validated_mint_config_txs {
+                    self.mint_config_store
+                        .write_active_mint_configs_by_token_id(
+                            &validated_mint_config_tx.mint_config_tx,
+                            &mut db_transaction,
+                        )?;
+                }
+            }
+        }
+
+        self.secondary_index_store.truncate(
+            new_num_blocks,
+            new_num_tx_outs,
+            &mut db_transaction,
+        )?;
+
+        db_transaction.put(
+            self.counts,
+            &NUM_BLOCKS_KEY,
+            &u64_to_key_bytes(new_num_blocks),
+            WriteFlags::empty(),
+        )?;
+        if self.pruned_below_impl(&db_transaction)? > new_num_blocks {
+            db_transaction.put(
+                self.counts,
+                &PRUNED_BELOW_KEY,
+                &u64_to_key_bytes(new_num_blocks),
+                WriteFlags::empty(),
+            )?;
+        }
+
+        db_transaction.commit()?;
+
+        self.update_metrics()
+    }
+
+    /// Index all blocks below `num_blocks` that have not been indexed yet,
+    /// reading their data from the database.
+    fn update_secondary_indexes(
+        &self,
+        num_blocks: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let num_indexed_blocks = self
+            .secondary_index_store
+            .num_indexed_blocks(db_transaction)?;
+
+        for block_index in num_indexed_blocks..num_blocks {
+            let value: TxOutsByBlockValue =
+                decode(db_transaction.get(self.tx_outs_by_block, &u64_to_key_bytes(block_index))?)?;
+            let block = self.get_block_impl(db_transaction, block_index)?;
+            let contents = self.get_block_contents_impl(db_transaction, block_index)?;
+            let signature = match self.get_block_signature_impl(db_transaction, block_index) {
+                Ok(signature) => Some(signature),
+                Err(Error::NotFound) | Err(Error::Pruned) => None,
+                Err(err) => return Err(err),
+            };
+
+            self.secondary_index_store.index_block(
+                &block,
+                &contents,
+                signature.as_ref(),
+                value.first_tx_out_index,
+                db_transaction,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete signatures and metadata of blocks that are outside the retention
+    /// window, and record the new pruning boundary.
+    fn prune_impl(
+        &self,
+        num_blocks: u64,
+        num_retained_blocks: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let pruned_below = self.pruned_below_impl(db_transaction)?;
+        let prune_to = num_blocks.saturating_sub(num_retained_blocks.max(1));
+        if prune_to <= pruned_below {
+            return Ok(());
+        }
+
+        for block_index in pruned_below..prune_to {
+            let key = u64_to_key_bytes(block_index);
+            for db in [self.block_signatures, self.block_metadata] {
+                match db_transaction.del(db, &key, None) {
+                    Ok(()) | Err(lmdb::Error::NotFound) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        db_transaction.put(
+            self.counts,
+            &PRUNED_BELOW_KEY,
+            &u64_to_key_bytes(prune_to),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Implementation of the `pruned_below` method that operates inside a
+    /// given transaction.
+    fn pruned_below_impl(&self, db_transaction: &impl Transaction) -> Result<BlockIndex, Error> {
+        match db_transaction.get(self.counts, &PRUNED_BELOW_KEY) {
+            Ok(bytes) => Ok(key_bytes_to_u64(bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Map a `NotFound` error for a block's signature or metadata to `Pruned`
+    /// if that data was deleted by pruning.
+    fn map_pruned_error(
+        &self,
+        err: lmdb::Error,
+        db_transaction: &impl Transaction,
+        block_number: u64,
+    ) -> Error {
+        match err {
+            lmdb::Error::NotFound => match self.pruned_below_impl(db_transaction) {
+                Ok(pruned_below) if block_number < pruned_below => Error::Pruned,
+                Ok(_) => Error::NotFound,
+                Err(err) => err,
+            },
+            err => err.into(),
+        }
+    }
+
     /// Write a `Block`.
     fn write_block(
         &self,
@@ -662,153 +1048,6 @@ impl LedgerDB {
         Ok(())
     }
 
-    /// Checks if a block can be appended to the db.
-    fn validate_append_block(
-        &self,
-        block: &Block,
-        block_contents: &BlockContents,
-        metadata: Option<&BlockMetadata>,
-        db_transaction: &impl Transaction,
-    ) -> Result<(), Error> {
-        // Check version is correct
-        // Check if block is being appended at the correct place.
-        let num_blocks = self.num_blocks()?;
-        if num_blocks == 0 {
-            // This must be an origin block.
-
-            // The origin block is version 0
-            if block.version != 0 {
-                return Err(Error::InvalidBlockVersion(block.version));
-            }
-
-            // The origin block is index '0' with default-initialized parent ID, by
-            // convention
-            if block.index != 0 {
-                return Err(Error::InvalidBlockIndex(block.index));
-            }
-            if block.parent_id != BlockID::default() {
-                return Err(Error::InvalidParentBlockID(block.id.clone()));
-            }
-        } else {
-            let last_block = self.get_block(num_blocks - 1)?;
-
-            // The block's version should be bounded by
-            // [prev block version, max block version]
-            if block.version < last_block.version || block.version > *MAX_BLOCK_VERSION {
-                return Err(Error::InvalidBlockVersion(block.version));
-            }
-
-            // The block must have the correct index and parent.
-            if block.index != num_blocks {
-                return Err(Error::InvalidBlockIndex(block.index));
-            }
-            if block.parent_id != last_block.id {
-                return Err(Error::InvalidParentBlockID(block.parent_id.clone()));
-            }
-        }
-
-        // A block must have outputs, unless it has mint-config transactions:
-        // - Origin block had outputs only outputs in it.
-        // - Blocks before minting was introduced always had outputs in them.
-        // - Blocks that have MintTxs in them will also have the minted TxOuts in them.
-        // - Blocks with only MintConfigTxs are allowed to not have outputs.
-        let has_mint_config_txs = !block_contents.validated_mint_config_txs.is_empty();
-        if block_contents.outputs.is_empty() && !has_mint_config_txs {
-            return Err(Error::NoOutputs);
-        }
-
-        // Number of outputs must be >= number of mint transactions because each mint
-        // transaction must produce a single output.
-        if block_contents.outputs.len() < block_contents.mint_txs.len() {
-            return Err(Error::TooFewOutputs);
-        }
-
-        // Non-origin blocks must have key images, unless it has minting-related
-        // transactions. When we have minting transactions it implies we might've not
-        // spent any pre-existing outputs and as such we will not have key images.
-        let has_minting_txs = !block_contents.validated_mint_config_txs.is_empty()
-            || !block_contents.mint_txs.is_empty();
-        if block.index != 0 && block_contents.key_images.is_empty() && !has_minting_txs {
-            return Err(Error::NoKeyImages);
-        }
-
-        // Check that the block contents match the hash.
-        if block.contents_hash != block_contents.hash() {
-            return Err(Error::InvalidBlockContents);
-        }
-
-        // Check that none of the outputs are missing masked amount (or, have a masked
-        // amount we don't understand)
-        if block_contents
-            .outputs
-            .iter()
-            .any(|output| output.get_masked_amount().is_err())
-        {
-            return Err(Error::MissingMaskedAmount);
-        }
-
-        // Check that none of the key images were previously spent.
-        for key_image in &block_contents.key_images {
-            if self
-                .check_key_image_impl(key_image, db_transaction)?
-                .is_some()
-            {
-                return Err(Error::KeyImageAlreadySpent);
-            }
-        }
-
-        // Check that none of the output public keys appear in the ledger.
-        for output in block_contents.outputs.iter() {
-            if self.contains_tx_out_public_key_impl(&output.public_key, db_transaction)? {
-                return Err(Error::DuplicateOutputPublicKey);
-            }
-        }
-
-        // Validate block id.
-        if !block.is_block_id_valid() {
-            return Err(Error::InvalidBlockID(block.id.clone()));
-        }
-
-        // Check that none of the minting transaction nonces appear in the ledger.
-        for mint_tx in block_contents.mint_txs.iter() {
-            if self
-                .mint_tx_store
-                .check_mint_tx_nonce(
-                    mint_tx.prefix.token_id,
-                    &mint_tx.prefix.nonce,
-                    db_transaction,
-                )?
-                .is_some()
-            {
-                return Err(Error::DuplicateMintTx);
-            }
-        }
-
-        // Check that none of the mint-config-tx nonces appear in the ledger.
-        for validated_mint_config_tx in block_contents.validated_mint_config_txs.iter() {
-            if self
-                .mint_config_store
-                .check_mint_config_tx_nonce(
-                    validated_mint_config_tx.mint_config_tx.prefix.token_id,
-                    &validated_mint_config_tx.mint_config_tx.prefix.nonce,
-                    db_transaction,
-                )?
-                .is_some()
-            {
-                return Err(Error::DuplicateMintConfigTx);
-            }
-        }
-
-        let block_version = BlockVersion::try_from(block.version)
-            .or(Err(Error::InvalidBlockVersion(block.version)))?;
-        if block_version.require_block_metadata() && metadata.is_none() {
-            return Err(Error::BlockMetadataRequired);
-        }
-
-        // All good
-        Ok(())
-    }
-
     /// Get the database file size, in bytes.
     fn db_file_size(&self) -> std::io::Result<u64> {
         let mut filename = self.path.clone();
@@ -880,7 +1119,9 @@ impl LedgerDB {
         block_number: u64,
     ) -> Result<BlockSignature, Error> {
         let key = u64_to_key_bytes(block_number);
-        let signature_bytes = db_transaction.get(self.block_signatures, &key)?;
+        let signature_bytes = db_transaction
+            .get(self.block_signatures, &key)
+            .map_err(|err| self.map_pruned_error(err, db_transaction, block_number))?;
         let signature = decode(signature_bytes)?;
         Ok(signature)
     }
@@ -893,7 +1134,9 @@ impl LedgerDB {
         block_number: u64,
     ) -> Result<BlockMetadata, Error> {
         let key = u64_to_key_bytes(block_number);
-        let metadata_bytes = db_transaction.get(self.block_metadata, &key)?;
+        let metadata_bytes = db_transaction
+            .get(self.block_metadata, &key)
+            .map_err(|err| self.map_pruned_error(err, db_transaction, block_number))?;
         let metadata = decode(metadata_bytes)?;
         Ok(metadata)
     }
@@ -933,6 +1176,62 @@ impl LedgerDB {
     }
 }
 
+/// Ledger state visible inside an LMDB transaction, used to validate blocks
+/// before appending them.
+struct LmdbAppendBlockState<'a, T: Transaction> {
+    ledger_db: &'a LedgerDB,
+    db_transaction: &'a T,
+}
+
+impl<'a, T: Transaction> AppendBlockState for LmdbAppendBlockState<'a, T> {
+    fn num_blocks(&self) -> Result<u64, Error> {
+        Ok(key_bytes_to_u64(
+            self.db_transaction
+                .get(self.ledger_db.counts, &NUM_BLOCKS_KEY)?,
+        ))
+    }
+
+    fn get_block(&self, block_number: BlockIndex) -> Result<Block, Error> {
+        self.ledger_db
+            .get_block_impl(self.db_transaction, block_number)
+    }
+
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<BlockIndex>, Error> {
+        self.ledger_db
+            .check_key_image_impl(key_image, self.db_transaction)
+    }
+
+    fn contains_tx_out_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<bool, Error> {
+        self.ledger_db
+            .contains_tx_out_public_key_impl(public_key, self.db_transaction)
+    }
+
+    fn check_mint_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error> {
+        self.ledger_db
+            .mint_tx_store
+            .check_mint_tx_nonce(token_id, nonce, self.db_transaction)
+    }
+
+    fn check_mint_config_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error> {
+        self.ledger_db.mint_config_store.check_mint_config_tx_nonce(
+            token_id,
+            nonce,
+            self.db_transaction,
+        )
+    }
+}
+
 /// Creates a LedgerDB instance at the given path.
 pub fn create_ledger_in(path: &Path) -> LedgerDB {
     let path = PathBuf::from(path);
@@ -956,13 +1255,16 @@ pub fn key_bytes_to_u64(bytes: &[u8]) -> u64 {
 #[cfg(test)]
 mod ledger_db_test {
     use super::*;
-    use crate::test_utils::{add_block_contents_to_ledger, add_txos_and_key_images_to_ledger};
+    use crate::test_utils::{
+        add_block_contents_to_ledger, add_txos_and_key_images_to_ledger, ledger_conformance,
+    };
     use mc_blockchain_test_utils::{get_blocks, make_block_metadata};
+    use mc_blockchain_types::{BlockID, BlockVersion, MAX_BLOCK_VERSION};
     use mc_crypto_keys::Ed25519Pair;
     use mc_transaction_core::membership_proofs::compute_implied_merkle_root;
     use mc_transaction_core_test_utils::{
         create_mint_config_tx, create_mint_config_tx_and_signers, create_mint_tx,
-        create_test_tx_out, mint_config_tx_to_validated as to_validated,
+        create_minted_tx_out, create_test_tx_out, mint_config_tx_to_validated as to_validated,
     };
     use mc_util_from_random::FromRandom;
     use mc_util_test_helper::get_seeded_rng;
@@ -1035,6 +1337,12 @@ mod ledger_db_test {
         block_data
     }
 
+    #[test]
+    // LedgerDB should pass the tests shared by all Ledger backends.
+    fn conformance() {
+        ledger_conformance::run_all(create_db);
+    }
+
     #[test]
     // Test initial conditions of a new LedgerDB instance.
     fn ledger_db_initialization() {
@@ -2292,6 +2600,367 @@ mod ledger_db_test {
         );
     }
 
+    #[test]
+    // Pruning should drop old signatures and metadata, but keep everything needed
+    // for membership proofs and double-spend checks.
+    fn enable_pruning() {
+        let mut ledger_db = create_db();
+        let blocks = populate_db(&mut ledger_db, 10, 2);
+        let root_element = ledger_db.get_root_tx_out_membership_element().unwrap();
+        let proofs = ledger_db
+            .get_tx_out_proof_of_memberships(&[0, 1, 19])
+            .unwrap();
+        assert_eq!(ledger_db.pruned_below().unwrap(), 0);
+
+        ledger_db.enable_pruning(3).unwrap();
+        assert_eq!(ledger_db.pruned_below().unwrap(), 7);
+
+        for block_index in 0..7 {
+            assert_eq!(
+                ledger_db.get_block_signature(block_index),
+                Err(Error::Pruned)
+            );
+            assert_eq!(
+                ledger_db.get_block_metadata(block_index),
+                Err(Error::Pruned)
+            );
+
+            // Block data is still available, minus the pruned parts.
+            let block_data = ledger_db.get_block_data(block_index).unwrap();
+            assert_eq!(block_data.block(), blocks[block_index as usize].block());
+            assert_eq!(
+                block_data.contents(),
+                blocks[block_index as usize].contents()
+            );
+            assert_eq!(block_data.signature(), None);
+            assert_eq!(block_data.metadata(), None);
+        }
+        for block_index in 7..10 {
+            assert_eq!(
+                ledger_db.get_block_data(block_index).unwrap(),
+                blocks[block_index as usize]
+            );
+        }
+        assert_eq!(ledger_db.get_block_signature(10), Err(Error::NotFound));
+
+        // Key images and the TxOut Merkle tree are untouched.
+        for block_data in &blocks {
+            for key_image in &block_data.contents().key_images {
+                assert_eq!(
+                    ledger_db.check_key_image(key_image).unwrap(),
+                    Some(block_data.block().index)
+                );
+            }
+        }
+        assert_eq!(
+            ledger_db.get_root_tx_out_membership_element().unwrap(),
+            root_element
+        );
+        assert_eq!(
+            ledger_db
+                .get_tx_out_proof_of_memberships(&[0, 1, 19])
+                .unwrap(),
+            proofs
+        );
+
+        // Appending blocks moves the pruning window along.
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let new_blocks = get_blocks(
+            BLOCK_VERSION,
+            2,
+            1,
+            1,
+            2,
+            1 << 20,
+            blocks.last().unwrap().block().clone(),
+            &mut rng,
+        );
+        for block_data in &new_blocks {
+            ledger_db.append_block_data(block_data).unwrap();
+        }
+        assert_eq!(ledger_db.pruned_below().unwrap(), 9);
+        assert_eq!(ledger_db.get_block_signature(8), Err(Error::Pruned));
+        assert_eq!(
+            &ledger_db.get_block_signature(9).unwrap(),
+            blocks[9].signature().unwrap()
+        );
+        assert_eq!(
+            &ledger_db.get_block_metadata(11).unwrap(),
+            new_blocks[1].metadata().unwrap()
+        );
+    }
+
+    #[test]
+    // The latest block's signature and metadata are retained even when asking to
+    // retain nothing.
+    fn enable_pruning_keeps_latest_block() {
+        let mut ledger_db = create_db();
+        let blocks = populate_db(&mut ledger_db, 4, 1);
+
+        ledger_db.enable_pruning(0).unwrap();
+        assert_eq!(ledger_db.pruned_below().unwrap(), 3);
+        assert_eq!(ledger_db.get_block_signature(2), Err(Error::Pruned));
+        assert_eq!(ledger_db.get_block_data(3).unwrap(), blocks[3]);
+    }
+
+    #[test]
+    // Secondary indexes should cover blocks appended both before and after they
+    // were enabled.
+    fn enable_secondary_indexes() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        populate_db(&mut ledger_db, 3, 2);
+
+        assert_eq!(
+            ledger_db.get_block_indices_by_timestamp(0, u64::MAX),
+            Err(Error::SecondaryIndexesDisabled)
+        );
+
+        ledger_db.enable_secondary_indexes().unwrap();
+
+        // Test block signatures are timestamped with the block index.
+        assert_eq!(
+            ledger_db.get_block_indices_by_timestamp(1, 10).unwrap(),
+            vec![1, 2]
+        );
+        // TxOuts at this block version have no memos.
+        assert_eq!(
+            ledger_db.get_tx_out_indices_with_e_memo(0, 100).unwrap(),
+            Vec::<u64>::new()
+        );
+
+        // Append a block with a MintConfigTx, followed by a block with a MintTx,
+        // its minted TxOut and a TxOut carrying a memo.
+        let token_id1 = TokenId::from(1);
+        let (mint_config_tx1, signers1) = create_mint_config_tx_and_signers(token_id1, &mut rng);
+        let block_contents = BlockContents {
+            validated_mint_config_txs: vec![to_validated(&mint_config_tx1)],
+            ..Default::default()
+        };
+        add_block_contents_to_ledger(&mut ledger_db, BLOCK_VERSION, block_contents, &mut rng)
+            .unwrap();
+
+        let parent_id = ledger_db.get_latest_block().unwrap().id;
+        let mint_txs = vec![create_mint_tx(token_id1, &signers1, 10, &mut rng)];
+        let block_contents = BlockContents {
+            outputs: vec![
+                create_test_tx_out(BLOCK_VERSION, &mut rng),
+                create_test_tx_out(BlockVersion::MAX, &mut rng),
+                create_minted_tx_out(BlockVersion::MAX, &parent_id, &mint_txs, 0),
+            ],
+            mint_txs,
+            ..Default::default()
+        };
+        add_block_contents_to_ledger(&mut ledger_db, BLOCK_VERSION, block_contents, &mut rng)
+            .unwrap();
+
+        assert_eq!(
+            ledger_db
+                .get_block_indices_by_timestamp(0, u64::MAX)
+                .unwrap(),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            ledger_db
+                .get_minted_tx_out_indices_by_token_id(token_id1, 0, 100)
+                .unwrap(),
+            vec![8]
+        );
+        assert_eq!(
+            ledger_db
+                .get_minted_tx_out_indices_by_token_id(token_id1, 0, 8)
+                .unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            ledger_db
+                .get_minted_tx_out_indices_by_token_id(TokenId::from(2), 0, 100)
+                .unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            ledger_db.get_tx_out_indices_with_e_memo(0, 100).unwrap(),
+            vec![7, 8]
+        );
+    }
+
+    #[test]
+    /// Truncating and re-appending should reproduce the original ledger.
+    fn truncate_to() {
+        let mut ledger_db = create_db();
+        ledger_db.enable_secondary_indexes().unwrap();
+        let blocks = populate_db(&mut ledger_db, 10, 3);
+        let expected_root = ledger_db.get_root_tx_out_membership_element().unwrap();
+
+        // Truncating to the last block leaves the ledger unchanged.
+        ledger_db.truncate_to(9).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 10);
+        assert_eq!(ledger_db.num_txos().unwrap(), 30);
+        assert_eq!(ledger_db.get_block_data(9).unwrap(), blocks[9]);
+        for (block_index, block_data) in blocks.iter().enumerate() {
+            for key_image in &block_data.contents().key_images {
+                assert_eq!(
+                    ledger_db.check_key_image(key_image).unwrap(),
+                    Some(block_index as u64)
+                );
+            }
+        }
+        assert_eq!(
+            ledger_db.get_root_tx_out_membership_element().unwrap(),
+            expected_root
+        );
+
+        ledger_db.truncate_to(4).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 5);
+        assert_eq!(ledger_db.num_txos().unwrap(), 15);
+        assert_eq!(ledger_db.get_block_data(4).unwrap(), blocks[4]);
+        assert_eq!(ledger_db.get_block(5), Err(Error::NotFound));
+        assert_eq!(ledger_db.get_tx_out_by_index(15), Err(Error::NotFound));
+        for key_image in &blocks[5].contents().key_images {
+            assert_eq!(ledger_db.check_key_image(key_image).unwrap(), None);
+        }
+        assert_eq!(
+            ledger_db
+                .get_block_indices_by_timestamp(0, u64::MAX)
+                .unwrap(),
+            vec![0, 1, 2, 3, 4]
+        );
+
+        // The Merkle tree matches a ledger that only ever had these blocks.
+        let mut expected_ledger_db = create_db();
+        for block_data in &blocks[..5] {
+            expected_ledger_db.append_block_data(block_data).unwrap();
+        }
+        assert_eq!(
+            ledger_db.get_root_tx_out_membership_element().unwrap(),
+            expected_ledger_db
+                .get_root_tx_out_membership_element()
+                .unwrap()
+        );
+        let indexes: Vec<u64> = (0..15).collect();
+        assert_eq!(
+            ledger_db.get_tx_out_proof_of_memberships(&indexes).unwrap(),
+            expected_ledger_db
+                .get_tx_out_proof_of_memberships(&indexes)
+                .unwrap()
+        );
+
+        for block_data in &blocks[5..] {
+            ledger_db.append_block_data(block_data).unwrap();
+        }
+        assert_eq!(ledger_db.num_blocks().unwrap(), 10);
+        assert_eq!(
+            ledger_db.get_root_tx_out_membership_element().unwrap(),
+            expected_root
+        );
+        assert_eq!(
+            ledger_db
+                .get_block_indices_by_timestamp(0, u64::MAX)
+                .unwrap(),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    /// Truncating should restore the active mint configurations and free the
+    /// nonces of removed minting transactions.
+    fn truncate_to_restores_mint_configs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let token_id1 = TokenId::from(1);
+        add_origin_block(&mut ledger_db);
+
+        let (mint_config_tx1, signers1) = create_mint_config_tx_and_signers(token_id1, &mut rng);
+        let block_contents = BlockContents {
+            validated_mint_config_txs: vec![to_validated(&mint_config_tx1)],
+            ..Default::default()
+        };
+        add_block_contents_to_ledger(&mut ledger_db, BLOCK_VERSION, block_contents, &mut rng)
+            .unwrap();
+
+        let mint_tx1 = create_mint_tx(token_id1, &signers1, 10, &mut rng);
+        let block_contents = BlockContents {
+            mint_txs: vec![mint_tx1.clone()],
+            outputs: vec![create_test_tx_out(BLOCK_VERSION, &mut rng)],
+            ..Default::default()
+        };
+        add_block_contents_to_ledger(&mut ledger_db, BLOCK_VERSION, block_contents, &mut rng)
+            .unwrap();
+        let active_mint_configs = ledger_db.get_active_mint_configs(token_id1).unwrap();
+
+        let (mint_config_tx2, signers2) = create_mint_config_tx_and_signers(token_id1, &mut rng);
+        let block_contents = BlockContents {
+            validated_mint_config_txs: vec![to_validated(&mint_config_tx2)],
+            ..Default::default()
+        };
+        add_block_contents_to_ledger(&mut ledger_db, BLOCK_VERSION, block_contents, &mut rng)
+            .unwrap();
+
+        let mint_tx2 = create_mint_tx(token_id1, &signers2, 20, &mut rng);
+        let block_contents = BlockContents {
+            mint_txs: vec![mint_tx2.clone()],
+            outputs: vec![create_test_tx_out(BLOCK_VERSION, &mut rng)],
+            ..Default::default()
+        };
+        add_block_contents_to_ledger(&mut ledger_db, BLOCK_VERSION, block_contents, &mut rng)
+            .unwrap();
+
+        // Truncating to the last block leaves the mint state unchanged.
+        let latest_mint_configs = ledger_db.get_active_mint_configs(token_id1).unwrap();
+        ledger_db.truncate_to(4).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 5);
+        assert_eq!(
+            ledger_db.get_active_mint_configs(token_id1).unwrap(),
+            latest_mint_configs
+        );
+        assert_eq!(
+            ledger_db
+                .check_mint_config_tx_nonce(*token_id1, &mint_config_tx2.prefix.nonce)
+                .unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            ledger_db
+                .check_mint_tx_nonce(*token_id1, &mint_tx2.prefix.nonce)
+                .unwrap(),
+            Some(4)
+        );
+
+        ledger_db.truncate_to(2).unwrap();
+        assert_eq!(
+            ledger_db.get_active_mint_configs(token_id1).unwrap(),
+            active_mint_configs
+        );
+        assert_eq!(
+            ledger_db
+                .check_mint_config_tx_nonce(*token_id1, &mint_config_tx2.prefix.nonce)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            ledger_db
+                .check_mint_tx_nonce(*token_id1, &mint_tx2.prefix.nonce)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            ledger_db
+                .check_mint_tx_nonce(*token_id1, &mint_tx1.prefix.nonce)
+                .unwrap(),
+            Some(2)
+        );
+
+        ledger_db.truncate_to(1).unwrap();
+        assert_eq!(
+            ledger_db.get_active_mint_configs(token_id1).unwrap(),
+            Some(ActiveMintConfigs::from(&mint_config_tx1))
+        );
+
+        ledger_db.truncate_to(0).unwrap();
+        assert_eq!(ledger_db.get_active_mint_configs(token_id1).unwrap(), None);
+        assert_eq!(ledger_db.truncate_to(1), Err(Error::InvalidBlockIndex(1)));
+    }
+
     #[test]
     /// Attempting to append an empty block should return Error::NoOutputs.
     fn append_empty_block() {
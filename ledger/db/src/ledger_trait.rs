@@ -141,4 +141,41 @@ pub trait Ledger: Send {
         &self,
         mint_tx: &MintTx,
     ) -> Result<ActiveMintConfig, Error>;
+
+    /// Get the indices of blocks whose signature timestamp is within
+    /// `[start_timestamp, end_timestamp)`, ordered by timestamp.
+    ///
+    /// Requires secondary indexes, which ledgers do not maintain by default.
+    fn get_block_indices_by_timestamp(
+        &self,
+        _start_timestamp: u64,
+        _end_timestamp: u64,
+    ) -> Result<Vec<BlockIndex>, Error> {
+        Err(Error::SecondaryIndexesDisabled)
+    }
+
+    /// Get the global indices of TxOuts in `[start_index, end_index)` that
+    /// were minted for the given token id.
+    ///
+    /// Requires secondary indexes, which ledgers do not maintain by default.
+    fn get_minted_tx_out_indices_by_token_id(
+        &self,
+        _token_id: TokenId,
+        _start_index: u64,
+        _end_index: u64,
+    ) -> Result<Vec<u64>, Error> {
+        Err(Error::SecondaryIndexesDisabled)
+    }
+
+    /// Get the global indices of TxOuts in `[start_index, end_index)` that
+    /// carry an encrypted memo.
+    ///
+    /// Requires secondary indexes, which ledgers do not maintain by default.
+    fn get_tx_out_indices_with_e_memo(
+        &self,
+        _start_index: u64,
+        _end_index: u64,
+    ) -> Result<Vec<u64>, Error> {
+        Err(Error::SecondaryIndexesDisabled)
+    }
 }
@@ -12,12 +12,18 @@ mod ledger_trait;
 mod metrics;
 mod mint_config_store;
 mod mint_tx_store;
+mod secondary_index_store;
+#[cfg(feature = "sqlite")]
+mod sqlite_ledger;
+mod validation;
 
 pub mod ledger_db;
 #[cfg(any(test, feature = "test_utils"))]
 pub mod test_utils;
 pub mod tx_out_store;
 
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite_ledger::SqliteLedger;
 pub use crate::{
     error::Error,
     ledger_db::{create_ledger_in, key_bytes_to_u64, u64_to_key_bytes, LedgerDB},
@@ -25,6 +31,8 @@ pub use crate::{
     metrics::LedgerMetrics,
     mint_config_store::{ActiveMintConfig, ActiveMintConfigs, MintConfigStore},
     mint_tx_store::MintTxStore,
+    secondary_index_store::SecondaryIndexStore,
     tx_out_store::TxOutStore,
+    validation::{validate_append_block, AppendBlockState},
 };
 pub use mc_util_lmdb::{MetadataStore, MetadataStoreError, MetadataStoreSettings};
@@ -287,7 +287,9 @@ impl MintConfigStore {
         Ok(())
     }
 
-    fn write_active_mint_configs_by_token_id(
+    /// Make the configurations of a MintConfigTx the active ones for its token,
+    /// replacing any previously active configurations.
+    pub fn write_active_mint_configs_by_token_id(
         &self,
         mint_config_tx: &MintConfigTx,
         db_transaction: &mut RwTransaction,
@@ -302,6 +304,47 @@ impl MintConfigStore {
         Ok(())
     }
 
+    /// Remove the ValidatedMintConfigTxs of a given block, including their
+    /// nonces, and return them. The caller is responsible for restoring the
+    /// active mint configurations.
+    pub fn remove_validated_mint_config_txs(
+        &self,
+        block_index: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<Vec<ValidatedMintConfigTx>, Error> {
+        let validated_mint_config_txs =
+            self.get_validated_mint_config_txs_by_block_index(block_index, db_transaction)?;
+
+        for validated_mint_config_tx in &validated_mint_config_txs {
+            let prefix = &validated_mint_config_tx.mint_config_tx.prefix;
+            let combined_nonce_and_token_id =
+                [&prefix.nonce[..], &u64_to_key_bytes(prefix.token_id)].concat();
+            db_transaction.del(
+                self.block_index_by_mint_config_tx_nonce_and_token_id,
+                &combined_nonce_and_token_id,
+                None,
+            )?;
+        }
+
+        db_transaction.del(
+            self.validated_mint_config_txs_by_block,
+            &u64_to_key_bytes(block_index),
+            None,
+        )?;
+
+        Ok(validated_mint_config_txs)
+    }
+
+    /// Remove all active mint configurations, so that they can be rebuilt by
+    /// replaying the ledger.
+    pub fn clear_active_mint_configs(
+        &self,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        db_transaction.clear_db(self.active_mint_configs_by_token_id)?;
+        Ok(())
+    }
+
     /// Get ValidatedMintConfigTxs in a given block.
     pub fn get_validated_mint_config_txs_by_block_index(
         &self,
@@ -365,6 +408,28 @@ impl MintConfigStore {
         active_mint_configs.get_active_mint_config_for_mint_tx(mint_tx)
     }
 
+    /// Add the amount minted by a MintTx to the total minted by the active
+    /// configuration that allows it.
+    pub fn record_mint_tx(
+        &self,
+        mint_tx: &MintTx,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let active_mint_config =
+            self.get_active_mint_config_for_mint_tx(mint_tx, db_transaction)?;
+
+        let new_total_minted = active_mint_config
+            .total_minted
+            .checked_add(mint_tx.prefix.amount)
+            .ok_or(Error::NotFound)?;
+
+        self.update_total_minted(
+            &active_mint_config.mint_config,
+            new_total_minted,
+            db_transaction,
+        )
+    }
+
     /// Update the total minted amount for a given MintConfig.
     pub fn update_total_minted(
         &self,
@@ -92,20 +92,7 @@ impl MintTxStore {
         // and update the total minted count. We also need to ensure the nonce is
         // unique.
         for mint_tx in mint_txs {
-            // Update total minted.
-            let active_mint_config =
-                mint_config_store.get_active_mint_config_for_mint_tx(mint_tx, db_transaction)?;
-
-            let new_total_minted = active_mint_config
-                .total_minted
-                .checked_add(mint_tx.prefix.amount)
-                .ok_or(Error::NotFound)?;
-
-            mint_config_store.update_total_minted(
-                &active_mint_config.mint_config,
-                new_total_minted,
-                db_transaction,
-            )?;
+            mint_config_store.record_mint_tx(mint_tx, db_transaction)?;
             self.write_block_index_by_mint_tx_nonce_and_token_id(
                 mint_tx,
                 db_transaction,
@@ -116,6 +103,34 @@ impl MintTxStore {
         Ok(())
     }
 
+    /// Remove the mint txs of a given block, including their nonces, and
+    /// return them. The caller is responsible for reverting their effect on
+    /// the active mint configurations.
+    pub fn remove_mint_txs(
+        &self,
+        block_index: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<Vec<MintTx>, Error> {
+        let mint_txs = self.get_mint_txs_by_block_index(block_index, db_transaction)?;
+
+        for mint_tx in &mint_txs {
+            let combined_nonce_and_token_id = [
+                &mint_tx.prefix.nonce[..],
+                &u64_to_key_bytes(mint_tx.prefix.token_id),
+            ]
+            .concat();
+            db_transaction.del(
+                self.block_index_by_mint_tx_nonce_and_token_id,
+                &combined_nonce_and_token_id,
+                None,
+            )?;
+        }
+
+        db_transaction.del(self.mint_txs_by_block, &u64_to_key_bytes(block_index), None)?;
+
+        Ok(mint_txs)
+    }
+
     pub fn write_block_index_by_mint_tx_nonce_and_token_id(
         &self,
         mint_tx: &MintTx,
@@ -0,0 +1,512 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Data access abstraction for optional secondary indexes over the ledger.
+//!
+//! This store maintains the following LMDB databases:
+//! 1) Counters, used to track how many blocks have been indexed.
+//! 2) (block signature timestamp, block index) -> (). Allows looking up blocks
+//!    by the time they were signed.
+//! 3) (token id, TxOut global index) -> (). Allows looking up the TxOuts minted
+//!    for a given token, whose amounts are known from their MintTx.
+//! 4) TxOut global index -> (), for TxOuts that carry an encrypted memo.
+//!
+//! All keys are big-endian so that LMDB's lexicographic ordering matches the
+//! numeric ordering, which allows answering range queries with a cursor.
+
+use crate::{key_bytes_to_u64, u64_to_key_bytes, Error};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_blockchain_types::{Block, BlockContents, BlockIndex, BlockSignature};
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::{mint::minted_tx_out_public_key, TokenId};
+
+// LMDB Database names.
+pub const COUNTS_DB_NAME: &str = "secondary_index_store:counts";
+pub const BLOCK_INDEX_BY_TIMESTAMP_DB_NAME: &str = "secondary_index_store:block_index_by_timestamp";
+pub const MINTED_TX_OUTS_BY_TOKEN_ID_DB_NAME: &str =
+    "secondary_index_store:minted_tx_outs_by_token_id";
+pub const TX_OUTS_WITH_E_MEMO_DB_NAME: &str = "secondary_index_store:tx_outs_with_e_memo";
+
+/// Keys used by the `counts` database.
+pub const NUM_INDEXED_BLOCKS_KEY: &str = "num_indexed_blocks";
+
+#[derive(Clone)]
+pub struct SecondaryIndexStore {
+    /// Aggregate counts about the indexes.
+    /// * `NUM_INDEXED_BLOCKS_KEY` --> number of blocks that have been indexed.
+    counts: Database,
+
+    /// `(timestamp, block index) -> ()`
+    block_index_by_timestamp: Database,
+
+    /// `(token id, TxOut global index) -> ()`
+    minted_tx_outs_by_token_id: Database,
+
+    /// `TxOut global index -> ()`
+    tx_outs_with_e_memo: Database,
+}
+
+impl SecondaryIndexStore {
+    /// Opens a SecondaryIndexStore.
+    ///
+    /// Secondary indexes were added after the ledger format was established,
+    /// so this calls create_db instead of open_db. If the databases exist,
+    /// create_db returns them.
+    pub fn new(env: &Environment) -> Result<Self, Error> {
+        Ok(SecondaryIndexStore {
+            counts: env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?,
+            block_index_by_timestamp: env.create_db(
+                Some(BLOCK_INDEX_BY_TIMESTAMP_DB_NAME),
+                DatabaseFlags::empty(),
+            )?,
+            minted_tx_outs_by_token_id: env.create_db(
+                Some(MINTED_TX_OUTS_BY_TOKEN_ID_DB_NAME),
+                DatabaseFlags::empty(),
+            )?,
+            tx_outs_with_e_memo: env
+                .create_db(Some(TX_OUTS_WITH_E_MEMO_DB_NAME), DatabaseFlags::empty())?,
+        })
+    }
+
+    /// Creates a fresh SecondaryIndexStore.
+    pub fn create(env: &Environment) -> Result<(), Error> {
+        env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(
+            Some(BLOCK_INDEX_BY_TIMESTAMP_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+        env.create_db(
+            Some(MINTED_TX_OUTS_BY_TOKEN_ID_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+        env.create_db(Some(TX_OUTS_WITH_E_MEMO_DB_NAME), DatabaseFlags::empty())?;
+        Ok(())
+    }
+
+    /// Get the number of blocks that have been indexed. Blocks are always
+    /// indexed in order, so these are blocks `0..num_indexed_blocks`.
+    pub fn num_indexed_blocks(&self, db_transaction: &impl Transaction) -> Result<u64, Error> {
+        match db_transaction.get(self.counts, &NUM_INDEXED_BLOCKS_KEY) {
+            Ok(bytes) => Ok(key_bytes_to_u64(bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Add a block to the indexes. Blocks must be indexed in order.
+    ///
+    /// A MintTx's TxOut is located by recomputing its public key, the way
+    /// consensus does when minting it. MintTxs whose TxOut is missing from
+    /// the block, which only happens in blocks that were not formed by
+    /// consensus, are not indexed.
+    ///
+    /// # Arguments
+    /// * `block` - The block.
+    /// * `block_contents` - The contents of the block.
+    /// * `signature` - The block signature, if any, providing its timestamp.
+    /// * `first_tx_out_index` - The global index of the block's first TxOut.
+    /// * `db_transaction` - an LMDB transaction.
+    pub fn index_block(
+        &self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+        first_tx_out_index: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let block_index = block.index;
+        let num_indexed_blocks = self.num_indexed_blocks(db_transaction)?;
+        if block_index != num_indexed_blocks {
+            return Err(Error::InvalidBlockIndex(block_index));
+        }
+
+        if let Some(signature) = signature {
+            db_transaction.put(
+                self.block_index_by_timestamp,
+                &pair_key(signature.signed_at(), block_index),
+                &[],
+                WriteFlags::empty(),
+            )?;
+        }
+
+        for (counter, mint_tx) in block_contents.mint_txs.iter().enumerate() {
+            let public_key = CompressedRistrettoPublic::from(minted_tx_out_public_key(
+                &block.parent_id,
+                &block_contents.mint_txs,
+                counter,
+            ));
+            let Some(offset) = block_contents
+                .outputs
+                .iter()
+                .position(|tx_out| tx_out.public_key == public_key)
+            else {
+                continue;
+            };
+
+            db_transaction.put(
+                self.minted_tx_outs_by_token_id,
+                &pair_key(mint_tx.prefix.token_id, first_tx_out_index + offset as u64),
+                &[],
+                WriteFlags::empty(),
+            )?;
+        }
+
+        for (offset, tx_out) in block_contents.outputs.iter().enumerate() {
+            if tx_out.e_memo.is_some() {
+                db_transaction.put(
+                    self.tx_outs_with_e_memo,
+                    &u64_to_key_bytes(first_tx_out_index + offset as u64),
+                    &[],
+                    WriteFlags::empty(),
+                )?;
+            }
+        }
+
+        db_transaction.put(
+            self.counts,
+            &NUM_INDEXED_BLOCKS_KEY,
+            &u64_to_key_bytes(block_index + 1),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove blocks `num_blocks..` from the indexes, along with TxOuts whose
+    /// global index is `num_tx_outs` or higher. Used when truncating the
+    /// ledger.
+    ///
+    /// Timestamps and minted TxOuts are keyed by something other than the
+    /// block or TxOut index, so this scans those databases in full.
+    pub fn truncate(
+        &self,
+        num_blocks: u64,
+        num_tx_outs: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        if self.num_indexed_blocks(db_transaction)? <= num_blocks {
+            return Ok(());
+        }
+
+        let mut keys_to_delete = Vec::new();
+        // Both databases store the block or TxOut index in the second 8 bytes of
+        // the key.
+        for (db, limit) in [
+            (self.block_index_by_timestamp, num_blocks),
+            (self.minted_tx_outs_by_token_id, num_tx_outs),
+        ] {
+            let mut cursor = db_transaction.open_ro_cursor(db)?;
+            keys_to_delete.extend(
+                cursor
+                    .iter_start()
+                    .filter_map(Result::ok)
+                    .filter(|(key, _)| key_bytes_to_u64(&key[8..16]) >= limit)
+                    .map(|(key, _)| (db, key.to_vec())),
+            );
+        }
+        {
+            let mut cursor = db_transaction.open_ro_cursor(self.tx_outs_with_e_memo)?;
+            keys_to_delete.extend(
+                cursor
+                    .iter_from(u64_to_key_bytes(num_tx_outs))
+                    .filter_map(Result::ok)
+                    .map(|(key, _)| (self.tx_outs_with_e_memo, key.to_vec())),
+            );
+        }
+
+        for (db, key) in keys_to_delete {
+            db_transaction.del(db, &key, None)?;
+        }
+
+        db_transaction.put(
+            self.counts,
+            &NUM_INDEXED_BLOCKS_KEY,
+            &u64_to_key_bytes(num_blocks),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Get the indices of blocks signed within `[start_timestamp,
+    /// end_timestamp)`, ordered by timestamp.
+    pub fn get_block_indices_by_timestamp(
+        &self,
+        start_timestamp: u64,
+        end_timestamp: u64,
+        db_transaction: &impl Transaction,
+    ) -> Result<Vec<BlockIndex>, Error> {
+        let start_key = pair_key(start_timestamp, 0);
+        let end_key = pair_key(end_timestamp, 0);
+
+        let mut cursor = db_transaction.open_ro_cursor(self.block_index_by_timestamp)?;
+        Ok(cursor
+            .iter_from(start_key)
+            .filter_map(Result::ok)
+            .take_while(|(key, _)| *key < &end_key[..])
+            .map(|(key, _)| key_bytes_to_u64(&key[8..16]))
+            .collect())
+    }
+
+    /// Get the global indices of TxOuts in `[start_index, end_index)` that
+    /// were minted for the given token id.
+    pub fn get_minted_tx_out_indices_by_token_id(
+        &self,
+        token_id: TokenId,
+        start_index: u64,
+        end_index: u64,
+        db_transaction: &impl Transaction,
+    ) -> Result<Vec<u64>, Error> {
+        let start_key = pair_key(*token_id, start_index);
+        let end_key = pair_key(*token_id, end_index);
+
+        let mut cursor = db_transaction.open_ro_cursor(self.minted_tx_outs_by_token_id)?;
+        Ok(cursor
+            .iter_from(start_key)
+            .filter_map(Result::ok)
+            .take_while(|(key, _)| *key < &end_key[..])
+            .map(|(key, _)| key_bytes_to_u64(&key[8..16]))
+            .collect())
+    }
+
+    /// Get the global indices of TxOuts in `[start_index, end_index)` that
+    /// carry an encrypted memo.
+    pub fn get_tx_out_indices_with_e_memo(
+        &self,
+        start_index: u64,
+        end_index: u64,
+        db_transaction: &impl Transaction,
+    ) -> Result<Vec<u64>, Error> {
+        let mut cursor = db_transaction.open_ro_cursor(self.tx_outs_with_e_memo)?;
+        Ok(cursor
+            .iter_from(u64_to_key_bytes(start_index))
+            .filter_map(Result::ok)
+            .map(|(key, _)| key_bytes_to_u64(key))
+            .take_while(|index| *index < end_index)
+            .collect())
+    }
+}
+
+/// Key made of two big-endian u64s.
+fn pair_key(first: u64, second: u64) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&u64_to_key_bytes(first));
+    key[8..].copy_from_slice(&u64_to_key_bytes(second));
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ledger_db::{MAX_LMDB_DATABASES, MAX_LMDB_FILE_SIZE};
+    use mc_blockchain_test_utils::make_block_signature;
+    use mc_blockchain_types::{BlockID, BlockVersion};
+    use mc_transaction_core_test_utils::{
+        create_mint_config_tx_and_signers, create_mint_tx, create_minted_tx_out, create_test_tx_out,
+    };
+    use mc_util_test_helper::get_seeded_rng;
+    use tempfile::TempDir;
+
+    fn init_store() -> (SecondaryIndexStore, Environment, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let env = Environment::new()
+            .set_max_dbs(MAX_LMDB_DATABASES)
+            .set_map_size(MAX_LMDB_FILE_SIZE)
+            .open(temp_dir.path())
+            .unwrap();
+        let store = SecondaryIndexStore::new(&env).unwrap();
+        (store, env, temp_dir)
+    }
+
+    fn signature_at(timestamp: u64) -> BlockSignature {
+        let mut signature = make_block_signature(&Block::default(), &mut get_seeded_rng());
+        signature.set_signed_at(timestamp);
+        signature
+    }
+
+    /// A block at the given index, whose parent id is derived from the index.
+    fn block_at(index: BlockIndex) -> Block {
+        Block {
+            index,
+            parent_id: BlockID([index as u8; 32]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn index_blocks_in_order() {
+        let (store, env, _temp_dir) = init_store();
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_eq!(store.num_indexed_blocks(&db_txn).unwrap(), 0);
+
+        let contents = BlockContents::default();
+        store
+            .index_block(&block_at(0), &contents, None, 0, &mut db_txn)
+            .unwrap();
+        assert_eq!(store.num_indexed_blocks(&db_txn).unwrap(), 1);
+
+        assert_eq!(
+            store.index_block(&block_at(2), &contents, None, 0, &mut db_txn),
+            Err(Error::InvalidBlockIndex(2))
+        );
+    }
+
+    #[test]
+    fn block_indices_by_timestamp() {
+        let (store, env, _temp_dir) = init_store();
+        let mut db_txn = env.begin_rw_txn().unwrap();
+
+        // Block 2 has no signature, and blocks 3 and 4 share a timestamp.
+        let timestamps = [Some(100), Some(150), None, Some(200), Some(200), Some(300)];
+        for (block_index, timestamp) in timestamps.iter().enumerate() {
+            let signature = timestamp.map(signature_at);
+            store
+                .index_block(
+                    &block_at(block_index as u64),
+                    &BlockContents::default(),
+                    signature.as_ref(),
+                    0,
+                    &mut db_txn,
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            store
+                .get_block_indices_by_timestamp(0, u64::MAX, &db_txn)
+                .unwrap(),
+            vec![0, 1, 3, 4, 5]
+        );
+        assert_eq!(
+            store
+                .get_block_indices_by_timestamp(150, 300, &db_txn)
+                .unwrap(),
+            vec![1, 3, 4]
+        );
+        assert_eq!(
+            store
+                .get_block_indices_by_timestamp(301, 400, &db_txn)
+                .unwrap(),
+            Vec::<u64>::new()
+        );
+    }
+
+    #[test]
+    fn minted_tx_outs_by_token_id_and_e_memo() {
+        let (store, env, _temp_dir) = init_store();
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        let mut rng = get_seeded_rng();
+
+        let (_, signers1) = create_mint_config_tx_and_signers(1.into(), &mut rng);
+        let (_, signers2) = create_mint_config_tx_and_signers(2.into(), &mut rng);
+        let mint_tx_1 = create_mint_tx(1.into(), &signers1, 10, &mut rng);
+        let mint_tx_2 = create_mint_tx(2.into(), &signers2, 20, &mut rng);
+        let mint_tx_3 = create_mint_tx(1.into(), &signers1, 30, &mut rng);
+
+        let mut tx_out_with_memo = create_test_tx_out(BlockVersion::MAX, &mut rng);
+        tx_out_with_memo.e_memo = Some(Default::default());
+        let mut tx_out_without_memo = create_test_tx_out(BlockVersion::MAX, &mut rng);
+        tx_out_without_memo.e_memo = None;
+
+        // Block 0: TxOuts 0 and 1, no mints.
+        let contents = BlockContents {
+            outputs: vec![tx_out_without_memo.clone(), tx_out_with_memo.clone()],
+            ..Default::default()
+        };
+        store
+            .index_block(&block_at(0), &contents, None, 0, &mut db_txn)
+            .unwrap();
+
+        // Block 1: TxOuts 2 to 5, with TxOuts 3 and 5 minted for tokens 1 and 2.
+        let block = block_at(1);
+        let mint_txs = vec![mint_tx_1, mint_tx_2];
+        let contents = BlockContents {
+            outputs: vec![
+                tx_out_with_memo.clone(),
+                create_minted_tx_out(BlockVersion::MAX, &block.parent_id, &mint_txs, 0),
+                tx_out_without_memo.clone(),
+                create_minted_tx_out(BlockVersion::MAX, &block.parent_id, &mint_txs, 1),
+            ],
+            mint_txs,
+            ..Default::default()
+        };
+        store
+            .index_block(&block, &contents, None, 2, &mut db_txn)
+            .unwrap();
+
+        // Block 2: TxOut 6, minted for token 1, and a MintTx whose TxOut is
+        // missing from the block.
+        let block = block_at(2);
+        let mint_txs = vec![mint_tx_3.clone(), mint_tx_3];
+        let contents = BlockContents {
+            outputs: vec![create_minted_tx_out(
+                BlockVersion::MAX,
+                &block.parent_id,
+                &mint_txs,
+                0,
+            )],
+            mint_txs,
+            ..Default::default()
+        };
+        store
+            .index_block(&block, &contents, None, 6, &mut db_txn)
+            .unwrap();
+
+        assert_eq!(
+            store
+                .get_minted_tx_out_indices_by_token_id(1.into(), 0, 100, &db_txn)
+                .unwrap(),
+            vec![3, 6]
+        );
+        assert_eq!(
+            store
+                .get_minted_tx_out_indices_by_token_id(2.into(), 0, 100, &db_txn)
+                .unwrap(),
+            vec![5]
+        );
+        assert_eq!(
+            store
+                .get_minted_tx_out_indices_by_token_id(1.into(), 4, 100, &db_txn)
+                .unwrap(),
+            vec![6]
+        );
+        assert_eq!(
+            store
+                .get_minted_tx_out_indices_by_token_id(3.into(), 0, 100, &db_txn)
+                .unwrap(),
+            Vec::<u64>::new()
+        );
+
+        assert_eq!(
+            store
+                .get_tx_out_indices_with_e_memo(0, 100, &db_txn)
+                .unwrap(),
+            vec![1, 2, 3, 5, 6]
+        );
+        assert_eq!(
+            store.get_tx_out_indices_with_e_memo(2, 4, &db_txn).unwrap(),
+            vec![2, 3]
+        );
+
+        // Dropping blocks 1 and 2 drops their minted TxOuts and TxOuts.
+        store.truncate(1, 2, &mut db_txn).unwrap();
+        assert_eq!(store.num_indexed_blocks(&db_txn).unwrap(), 1);
+        assert_eq!(
+            store
+                .get_minted_tx_out_indices_by_token_id(1.into(), 0, 100, &db_txn)
+                .unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            store
+                .get_minted_tx_out_indices_by_token_id(2.into(), 0, 100, &db_txn)
+                .unwrap(),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            store
+                .get_tx_out_indices_with_e_memo(0, 100, &db_txn)
+                .unwrap(),
+            vec![1]
+        );
+    }
+}
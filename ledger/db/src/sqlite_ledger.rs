@@ -0,0 +1,821 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! A [Ledger] stored in a single SQLite file.
+//!
+//! [LedgerDB](crate::LedgerDB) memory-maps a sparse LMDB file that may grow up
+//! to [MAX_LMDB_FILE_SIZE](crate::ledger_db::MAX_LMDB_FILE_SIZE), which some
+//! container platforms handle poorly. `SqliteLedger` keeps the same data in a
+//! SQLite database using regular file IO instead.
+//!
+//! Blocks are validated by the same code as `LedgerDB`, and values are stored
+//! with the same prost encoding, so both backends accept the same blocks and
+//! a ledger can be converted between them block by block.
+//!
+//! `mc-ledger-from-archive --ledger-sqlite` writes a `SqliteLedger`, and
+//! `mc-ledger-migration` converts between the two backends. Consensus,
+//! mobilecoind and the watcher construct `LedgerDB` directly, so they still
+//! memory-map the LMDB file and cannot use this backend yet.
+
+use crate::{
+    ledger_db::KeyImageList, mint_config_store::ValidatedMintConfigTxList,
+    mint_tx_store::MintTxList, tx_out_store::containing_ranges, validate_append_block,
+    ActiveMintConfig, ActiveMintConfigs, AppendBlockState, Error, Ledger, MintConfigStore,
+};
+use mc_blockchain_types::{
+    Block, BlockContents, BlockData, BlockIndex, BlockMetadata, BlockSignature,
+};
+use mc_common::{Hash, HashMap};
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::{
+    membership_proofs::{hash_leaf, hash_nodes, Range, NIL_HASH},
+    mint::{MintConfigTx, MintTx},
+    ring_signature::KeyImage,
+    tx::{TxOut, TxOutMembershipElement, TxOutMembershipProof},
+    TokenId,
+};
+use mc_util_serial::{decode, encode};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, TransactionBehavior};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// Schema version, stored in SQLite's `user_version` pragma. This should be
+/// bumped when breaking changes are introduced.
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// Tables of the ledger. Token ids are stored as `i64`, reinterpreting the
+/// bits of the `u64`, since SQLite integers are signed.
+const SCHEMA: &str = r#"
+CREATE TABLE blocks (
+    block_index INTEGER PRIMARY KEY,
+    block BLOB NOT NULL,
+    signature BLOB,
+    metadata BLOB,
+    first_tx_out_index INTEGER NOT NULL,
+    num_tx_outs INTEGER NOT NULL,
+    key_images BLOB NOT NULL,
+    mint_txs BLOB NOT NULL,
+    validated_mint_config_txs BLOB NOT NULL
+);
+CREATE TABLE tx_outs (
+    tx_out_index INTEGER PRIMARY KEY,
+    block_index INTEGER NOT NULL,
+    hash BLOB NOT NULL UNIQUE,
+    public_key BLOB NOT NULL UNIQUE,
+    tx_out BLOB NOT NULL
+);
+CREATE TABLE key_images (
+    key_image BLOB PRIMARY KEY,
+    block_index INTEGER NOT NULL
+) WITHOUT ROWID;
+CREATE TABLE merkle_hashes (
+    range_from INTEGER NOT NULL,
+    range_to INTEGER NOT NULL,
+    hash BLOB NOT NULL,
+    PRIMARY KEY (range_from, range_to)
+) WITHOUT ROWID;
+CREATE TABLE active_mint_configs (
+    token_id INTEGER PRIMARY KEY,
+    active_mint_configs BLOB NOT NULL
+);
+CREATE TABLE mint_config_tx_nonces (
+    token_id INTEGER NOT NULL,
+    nonce BLOB NOT NULL,
+    block_index INTEGER NOT NULL,
+    PRIMARY KEY (token_id, nonce)
+) WITHOUT ROWID;
+CREATE TABLE mint_tx_nonces (
+    token_id INTEGER NOT NULL,
+    nonce BLOB NOT NULL,
+    block_index INTEGER NOT NULL,
+    PRIMARY KEY (token_id, nonce)
+) WITHOUT ROWID;
+"#;
+
+/// A [Ledger] backed by a single SQLite file.
+#[derive(Clone)]
+pub struct SqliteLedger {
+    /// The connection is shared between clones, which serializes access to the
+    /// database within a process.
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteLedger {
+    /// Opens an existing SQLite ledger at the given path.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            return Err(Error::Sqlite(format!(
+                "unsupported schema version {version}, expected {SCHEMA_VERSION}"
+            )));
+        }
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Creates a fresh SQLite ledger at the given path.
+    pub fn create(path: &Path) -> Result<(), Error> {
+        if path.exists() {
+            return Err(Error::Sqlite(format!("{} already exists", path.display())));
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<Connection> {
+        self.conn.lock().expect("mutex poisoned")
+    }
+}
+
+impl Ledger for SqliteLedger {
+    fn append_block<'b>(
+        &mut self,
+        block: &'b Block,
+        block_contents: &'b BlockContents,
+        signature: Option<&'b BlockSignature>,
+        metadata: Option<&'b BlockMetadata>,
+    ) -> Result<(), Error> {
+        let mut conn = self.lock();
+        let db_transaction = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        validate_append_block(
+            &SqliteAppendBlockState {
+                conn: &db_transaction,
+            },
+            block,
+            block_contents,
+            metadata,
+        )?;
+
+        let first_tx_out_index = num_txos_impl(&db_transaction)?;
+        for tx_out in &block_contents.outputs {
+            push_tx_out(&db_transaction, block.index, tx_out)?;
+        }
+
+        for key_image in &block_contents.key_images {
+            if check_key_image_impl(&db_transaction, key_image)?.is_some() {
+                return Err(Error::KeyImageAlreadySpent);
+            }
+            db_transaction.execute(
+                "INSERT INTO key_images (key_image, block_index) VALUES (?1, ?2)",
+                params![&key_image.as_bytes()[..], block.index],
+            )?;
+        }
+
+        // MintTxs are written before the configurations, since a new configuration
+        // is not yet active at the time its MintTx made its way into a block.
+        for mint_tx in &block_contents.mint_txs {
+            write_mint_tx(&db_transaction, block.index, mint_tx)?;
+        }
+        for validated_mint_config_tx in &block_contents.validated_mint_config_txs {
+            write_mint_config_tx(
+                &db_transaction,
+                block.index,
+                &validated_mint_config_tx.mint_config_tx,
+            )?;
+        }
+
+        db_transaction.execute(
+            "INSERT INTO blocks (
+                block_index, block, signature, metadata, first_tx_out_index, num_tx_outs,
+                key_images, mint_txs, validated_mint_config_txs
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                block.index,
+                encode(block),
+                signature.map(encode),
+                metadata.map(encode),
+                first_tx_out_index,
+                block_contents.outputs.len() as u64,
+                encode(&KeyImageList {
+                    key_images: block_contents.key_images.clone(),
+                }),
+                encode(&MintTxList {
+                    mint_txs: block_contents.mint_txs.clone(),
+                }),
+                encode(&ValidatedMintConfigTxList {
+                    validated_mint_config_txs: block_contents.validated_mint_config_txs.clone(),
+                }),
+            ],
+        )?;
+
+        db_transaction.commit()?;
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> Result<u64, Error> {
+        num_blocks_impl(&self.lock())
+    }
+
+    fn get_block(&self, block_number: BlockIndex) -> Result<Block, Error> {
+        get_block_impl(&self.lock(), block_number)
+    }
+
+    fn get_block_contents(&self, block_number: BlockIndex) -> Result<BlockContents, Error> {
+        get_block_contents_impl(&self.lock(), block_number)
+    }
+
+    fn get_block_signature(&self, block_number: BlockIndex) -> Result<BlockSignature, Error> {
+        let bytes: Option<Vec<u8>> = self.lock().query_row(
+            "SELECT signature FROM blocks WHERE block_index = ?1",
+            params![block_number],
+            |row| row.get(0),
+        )?;
+        Ok(decode(&bytes.ok_or(Error::NotFound)?)?)
+    }
+
+    fn get_block_metadata(&self, block_number: BlockIndex) -> Result<BlockMetadata, Error> {
+        let bytes: Option<Vec<u8>> = self.lock().query_row(
+            "SELECT metadata FROM blocks WHERE block_index = ?1",
+            params![block_number],
+            |row| row.get(0),
+        )?;
+        Ok(decode(&bytes.ok_or(Error::NotFound)?)?)
+    }
+
+    fn get_block_data(&self, block_number: BlockIndex) -> Result<BlockData, Error> {
+        let conn = self.lock();
+        let (block, signature, metadata): (Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>) = conn
+            .query_row(
+                "SELECT block, signature, metadata FROM blocks WHERE block_index = ?1",
+                params![block_number],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?;
+        let contents = get_block_contents_impl(&conn, block_number)?;
+        let signature: Option<BlockSignature> = signature.map(|s| decode(&s)).transpose()?;
+        let metadata: Option<BlockMetadata> = metadata.map(|m| decode(&m)).transpose()?;
+
+        Ok(BlockData::new(
+            decode(&block)?,
+            contents,
+            signature,
+            metadata,
+        ))
+    }
+
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<BlockIndex, Error> {
+        Ok(self.lock().query_row(
+            "SELECT block_index FROM tx_outs WHERE tx_out_index = ?1",
+            params![tx_out_index],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn num_txos(&self) -> Result<u64, Error> {
+        num_txos_impl(&self.lock())
+    }
+
+    fn get_tx_out_index_by_hash(&self, tx_out_hash: &Hash) -> Result<u64, Error> {
+        Ok(self.lock().query_row(
+            "SELECT tx_out_index FROM tx_outs WHERE hash = ?1",
+            params![&tx_out_hash[..]],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn get_tx_out_index_by_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error> {
+        Ok(self.lock().query_row(
+            "SELECT tx_out_index FROM tx_outs WHERE public_key = ?1",
+            params![&tx_out_public_key.as_bytes()[..]],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn get_tx_out_by_index(&self, index: u64) -> Result<TxOut, Error> {
+        get_tx_out_by_index_impl(&self.lock(), index)
+    }
+
+    fn get_tx_out_proof_of_memberships(
+        &self,
+        indexes: &[u64],
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let conn = self.lock();
+        let num_tx_outs = num_txos_impl(&conn)?;
+        indexes
+            .iter()
+            .map(|index| get_merkle_proof_of_membership(&conn, *index, num_tx_outs))
+            .collect()
+    }
+
+    fn contains_tx_out_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<bool, Error> {
+        contains_tx_out_public_key_impl(&self.lock(), public_key)
+    }
+
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<BlockIndex>, Error> {
+        check_key_image_impl(&self.lock(), key_image)
+    }
+
+    fn get_key_images_by_block(&self, block_number: BlockIndex) -> Result<Vec<KeyImage>, Error> {
+        let bytes: Vec<u8> = self.lock().query_row(
+            "SELECT key_images FROM blocks WHERE block_index = ?1",
+            params![block_number],
+            |row| row.get(0),
+        )?;
+        let key_image_list: KeyImageList = decode(&bytes)?;
+        Ok(key_image_list.key_images)
+    }
+
+    fn get_root_tx_out_membership_element(&self) -> Result<TxOutMembershipElement, Error> {
+        let conn = self.lock();
+        let num_txos = num_txos_impl(&conn)?;
+        if num_txos == 0 {
+            return Err(Error::NoOutputs);
+        }
+
+        let num_leaves_full_tree = num_txos
+            .checked_next_power_of_two()
+            .ok_or(Error::CapacityExceeded)?;
+        let range = Range::new(0, num_leaves_full_tree - 1)?;
+        let root_merkle_hash = get_merkle_hash(&conn, &range)?;
+        Ok(TxOutMembershipElement::new(range, root_merkle_hash))
+    }
+
+    fn get_active_mint_configs(
+        &self,
+        token_id: TokenId,
+    ) -> Result<Option<ActiveMintConfigs>, Error> {
+        get_active_mint_configs_impl(&self.lock(), token_id)
+    }
+
+    fn get_active_mint_configs_map(&self) -> Result<HashMap<TokenId, ActiveMintConfigs>, Error> {
+        let conn = self.lock();
+        let mut statement =
+            conn.prepare("SELECT token_id, active_mint_configs FROM active_mint_configs")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?;
+        let active_mint_configs_map = rows
+            .map(|row| {
+                let (token_id, bytes) = row?;
+                Ok((TokenId::from(token_id as u64), decode(&bytes)?))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(active_mint_configs_map)
+    }
+
+    fn check_mint_config_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error> {
+        check_nonce(&self.lock(), "mint_config_tx_nonces", token_id, nonce)
+    }
+
+    fn check_mint_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error> {
+        check_nonce(&self.lock(), "mint_tx_nonces", token_id, nonce)
+    }
+
+    fn get_active_mint_config_for_mint_tx(
+        &self,
+        mint_tx: &MintTx,
+    ) -> Result<ActiveMintConfig, Error> {
+        get_active_mint_configs_impl(&self.lock(), TokenId::from(mint_tx.prefix.token_id))?
+            .ok_or(Error::NotFound)?
+            .get_active_mint_config_for_mint_tx(mint_tx)
+    }
+}
+
+/// Ledger state visible inside a SQLite transaction, used to validate blocks
+/// before appending them.
+struct SqliteAppendBlockState<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> AppendBlockState for SqliteAppendBlockState<'a> {
+    fn num_blocks(&self) -> Result<u64, Error> {
+        num_blocks_impl(self.conn)
+    }
+
+    fn get_block(&self, block_number: BlockIndex) -> Result<Block, Error> {
+        get_block_impl(self.conn, block_number)
+    }
+
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<BlockIndex>, Error> {
+        check_key_image_impl(self.conn, key_image)
+    }
+
+    fn contains_tx_out_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<bool, Error> {
+        contains_tx_out_public_key_impl(self.conn, public_key)
+    }
+
+    fn check_mint_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error> {
+        check_nonce(self.conn, "mint_tx_nonces", token_id, nonce)
+    }
+
+    fn check_mint_config_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error> {
+        check_nonce(self.conn, "mint_config_tx_nonces", token_id, nonce)
+    }
+}
+
+// Blocks and TxOuts are indexed contiguously from 0, so they can be counted
+// from the largest rowid, which unlike `COUNT(*)` does not scan the table.
+fn num_blocks_impl(conn: &Connection) -> Result<u64, Error> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(MAX(block_index) + 1, 0) FROM blocks",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+fn num_txos_impl(conn: &Connection) -> Result<u64, Error> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(MAX(tx_out_index) + 1, 0) FROM tx_outs",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+fn get_block_impl(conn: &Connection, block_number: BlockIndex) -> Result<Block, Error> {
+    let bytes: Vec<u8> = conn.query_row(
+        "SELECT block FROM blocks WHERE block_index = ?1",
+        params![block_number],
+        |row| row.get(0),
+    )?;
+    Ok(decode(&bytes)?)
+}
+
+fn get_block_contents_impl(
+    conn: &Connection,
+    block_number: BlockIndex,
+) -> Result<BlockContents, Error> {
+    let (first_tx_out_index, num_tx_outs, key_images, mint_txs, validated_mint_config_txs): (
+        u64,
+        u64,
+        Vec<u8>,
+        Vec<u8>,
+        Vec<u8>,
+    ) = conn.query_row(
+        "SELECT first_tx_out_index, num_tx_outs, key_images, mint_txs, validated_mint_config_txs
+        FROM blocks WHERE block_index = ?1",
+        params![block_number],
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        },
+    )?;
+
+    let outputs = (first_tx_out_index..first_tx_out_index + num_tx_outs)
+        .map(|tx_out_index| get_tx_out_by_index_impl(conn, tx_out_index))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let key_image_list: KeyImageList = decode(&key_images)?;
+    let mint_tx_list: MintTxList = decode(&mint_txs)?;
+    let validated_mint_config_tx_list: ValidatedMintConfigTxList =
+        decode(&validated_mint_config_txs)?;
+
+    Ok(BlockContents {
+        key_images: key_image_list.key_images,
+        outputs,
+        validated_mint_config_txs: validated_mint_config_tx_list.validated_mint_config_txs,
+        mint_txs: mint_tx_list.mint_txs,
+    })
+}
+
+fn get_tx_out_by_index_impl(conn: &Connection, index: u64) -> Result<TxOut, Error> {
+    let bytes: Vec<u8> = conn.query_row(
+        "SELECT tx_out FROM tx_outs WHERE tx_out_index = ?1",
+        params![index],
+        |row| row.get(0),
+    )?;
+    Ok(decode(&bytes)?)
+}
+
+fn contains_tx_out_public_key_impl(
+    conn: &Connection,
+    public_key: &CompressedRistrettoPublic,
+) -> Result<bool, Error> {
+    Ok(conn
+        .query_row(
+            "SELECT 1 FROM tx_outs WHERE public_key = ?1",
+            params![&public_key.as_bytes()[..]],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+fn check_key_image_impl(conn: &Connection, key_image: &KeyImage) -> Result<Option<u64>, Error> {
+    Ok(conn
+        .query_row(
+            "SELECT block_index FROM key_images WHERE key_image = ?1",
+            params![&key_image.as_bytes()[..]],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Look up a MintTx or MintConfigTx nonce in the given table.
+fn check_nonce(
+    conn: &Connection,
+    table: &str,
+    token_id: u64,
+    nonce: &[u8],
+) -> Result<Option<BlockIndex>, Error> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT block_index FROM {table} WHERE token_id = ?1 AND nonce = ?2"),
+            params![token_id as i64, nonce],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+fn get_active_mint_configs_impl(
+    conn: &Connection,
+    token_id: TokenId,
+) -> Result<Option<ActiveMintConfigs>, Error> {
+    let bytes: Option<Vec<u8>> = conn
+        .query_row(
+            "SELECT active_mint_configs FROM active_mint_configs WHERE token_id = ?1",
+            params![*token_id as i64],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(bytes.map(|bytes| decode(&bytes)).transpose()?)
+}
+
+fn write_active_mint_configs(
+    conn: &Connection,
+    token_id: u64,
+    active_mint_configs: &ActiveMintConfigs,
+) -> Result<(), Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO active_mint_configs (token_id, active_mint_configs)
+        VALUES (?1, ?2)",
+        params![token_id as i64, encode(active_mint_configs)],
+    )?;
+    Ok(())
+}
+
+/// Record a MintTx, adding its amount to the active configuration that
+/// allows it.
+fn write_mint_tx(
+    conn: &Connection,
+    block_index: BlockIndex,
+    mint_tx: &MintTx,
+) -> Result<(), Error> {
+    let token_id = mint_tx.prefix.token_id;
+    if check_nonce(conn, "mint_tx_nonces", token_id, &mint_tx.prefix.nonce)?.is_some() {
+        return Err(Error::DuplicateMintTx);
+    }
+
+    let mut active_mint_configs =
+        get_active_mint_configs_impl(conn, TokenId::from(token_id))?.ok_or(Error::NotFound)?;
+    let mint_config = active_mint_configs
+        .get_active_mint_config_for_mint_tx(mint_tx)?
+        .mint_config;
+    let active_mint_config = active_mint_configs
+        .configs
+        .iter_mut()
+        .find(|active_mint_config| active_mint_config.mint_config == mint_config)
+        .ok_or(Error::NotFound)?;
+    active_mint_config.total_minted = active_mint_config
+        .total_minted
+        .checked_add(mint_tx.prefix.amount)
+        .ok_or(Error::MintLimitExceeded(
+            mint_tx.prefix.amount,
+            active_mint_config.total_minted,
+            active_mint_config.mint_config.mint_limit,
+        ))?;
+    write_active_mint_configs(conn, token_id, &active_mint_configs)?;
+
+    conn.execute(
+        "INSERT INTO mint_tx_nonces (token_id, nonce, block_index) VALUES (?1, ?2, ?3)",
+        params![token_id as i64, mint_tx.prefix.nonce, block_index],
+    )?;
+    Ok(())
+}
+
+/// Record a MintConfigTx, replacing the active configurations of its token.
+fn write_mint_config_tx(
+    conn: &Connection,
+    block_index: BlockIndex,
+    mint_config_tx: &MintConfigTx,
+) -> Result<(), Error> {
+    MintConfigStore::check_mint_config(mint_config_tx)?;
+
+    let token_id = mint_config_tx.prefix.token_id;
+    if check_nonce(
+        conn,
+        "mint_config_tx_nonces",
+        token_id,
+        &mint_config_tx.prefix.nonce,
+    )?
+    .is_some()
+    {
+        return Err(Error::DuplicateMintConfigTx);
+    }
+    conn.execute(
+        "INSERT INTO mint_config_tx_nonces (token_id, nonce, block_index) VALUES (?1, ?2, ?3)",
+        params![token_id as i64, mint_config_tx.prefix.nonce, block_index],
+    )?;
+
+    write_active_mint_configs(conn, token_id, &ActiveMintConfigs::from(mint_config_tx))
+}
+
+/// Append a TxOut and update the Merkle tree to include it.
+fn push_tx_out(conn: &Connection, block_index: BlockIndex, tx_out: &TxOut) -> Result<(), Error> {
+    if contains_tx_out_public_key_impl(conn, &tx_out.public_key)? {
+        return Err(Error::DuplicateOutputPublicKey);
+    }
+
+    let index = num_txos_impl(conn)?;
+    conn.execute(
+        "INSERT INTO tx_outs (tx_out_index, block_index, hash, public_key, tx_out)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            index,
+            block_index,
+            &tx_out.hash()[..],
+            &tx_out.public_key.as_bytes()[..],
+            encode(tx_out),
+        ],
+    )?;
+
+    // See TxOutStore::update_merkle_hashes.
+    let num_tx_outs = index + 1;
+    for (low, high) in containing_ranges(index, num_tx_outs)? {
+        let hash = if low == high {
+            hash_leaf(tx_out)
+        } else {
+            let mid = (low + high) / 2;
+            let left_child_hash = get_merkle_hash(conn, &Range::new(low, mid)?)?;
+            let right_child_hash = if mid + 1 >= num_tx_outs {
+                *NIL_HASH
+            } else {
+                get_merkle_hash(conn, &Range::new(mid + 1, high)?)?
+            };
+            hash_nodes(&left_child_hash, &right_child_hash)
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO merkle_hashes (range_from, range_to, hash)
+            VALUES (?1, ?2, ?3)",
+            params![low, high, &hash[..]],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn get_merkle_hash(conn: &Connection, range: &Range) -> Result<[u8; 32], Error> {
+    let bytes: Vec<u8> = conn.query_row(
+        "SELECT hash FROM merkle_hashes WHERE range_from = ?1 AND range_to = ?2",
+        params![range.from, range.to],
+        |row| row.get(0),
+    )?;
+    bytes.try_into().or(Err(Error::Deserialization))
+}
+
+/// See TxOutStore::get_merkle_proof_of_membership.
+fn get_merkle_proof_of_membership(
+    conn: &Connection,
+    index: u64,
+    num_tx_outs: u64,
+) -> Result<TxOutMembershipProof, Error> {
+    if index >= num_tx_outs {
+        return Err(Error::TxOutIndexOutOfBounds(index));
+    }
+
+    // The leaf itself, followed by the sibling of each subtree containing it.
+    let mut ranges_for_proof = vec![(index, index)];
+    for (low, high) in containing_ranges(index, num_tx_outs)?.iter().skip(1) {
+        let mid = (low + high) / 2;
+        if index <= mid {
+            ranges_for_proof.push((mid + 1, *high));
+        } else {
+            ranges_for_proof.push((*low, mid));
+        }
+    }
+
+    let elements = ranges_for_proof
+        .into_iter()
+        .map(|(low, high)| {
+            let range = Range::new(low, high)?;
+            let hash = if low >= num_tx_outs {
+                *NIL_HASH
+            } else {
+                get_merkle_hash(conn, &range)?
+            };
+            Ok(TxOutMembershipElement {
+                range,
+                hash: hash.into(),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(TxOutMembershipProof::new(index, num_tx_outs - 1, elements))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_ledger, ledger_conformance};
+    use mc_blockchain_test_utils::get_blocks;
+    use mc_blockchain_types::BlockVersion;
+    use mc_util_test_helper::get_seeded_rng;
+    use tempfile::TempDir;
+
+    fn create_sqlite_ledger() -> (SqliteLedger, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ledger.sqlite");
+        SqliteLedger::create(&path).unwrap();
+        (SqliteLedger::open(&path).unwrap(), temp_dir)
+    }
+
+    #[test]
+    fn conformance() {
+        let mut temp_dirs = Vec::new();
+        ledger_conformance::run_all(|| {
+            let (ledger, temp_dir) = create_sqlite_ledger();
+            temp_dirs.push(temp_dir);
+            ledger
+        });
+    }
+
+    #[test]
+    // Both backends should return identical data for the same blocks.
+    fn matches_ledger_db() {
+        let (mut sqlite_ledger, _temp_dir) = create_sqlite_ledger();
+        let mut ledger_db = create_ledger();
+
+        let blocks = get_blocks(
+            BlockVersion::MAX,
+            9,
+            3,
+            2,
+            1,
+            1 << 20,
+            None,
+            &mut get_seeded_rng(),
+        );
+        for block_data in &blocks {
+            sqlite_ledger.append_block_data(block_data).unwrap();
+            ledger_db.append_block_data(block_data).unwrap();
+        }
+
+        assert_eq!(sqlite_ledger.num_txos(), ledger_db.num_txos());
+        assert_eq!(
+            sqlite_ledger.get_root_tx_out_membership_element(),
+            ledger_db.get_root_tx_out_membership_element()
+        );
+        let indexes = (0..ledger_db.num_txos().unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            sqlite_ledger.get_tx_out_proof_of_memberships(&indexes),
+            ledger_db.get_tx_out_proof_of_memberships(&indexes)
+        );
+        for block_index in 0..blocks.len() as u64 {
+            assert_eq!(
+                sqlite_ledger.get_block_data(block_index),
+                ledger_db.get_block_data(block_index)
+            );
+        }
+    }
+
+    #[test]
+    fn open_requires_existing_ledger() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ledger.sqlite");
+        assert!(SqliteLedger::open(&path).is_err());
+
+        SqliteLedger::create(&path).unwrap();
+        assert!(SqliteLedger::create(&path).is_err());
+        assert_eq!(SqliteLedger::open(&path).unwrap().num_blocks(), Ok(0));
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Tests that every [Ledger] storage backend must pass.
+//!
+//! Each function takes a freshly created, empty ledger and panics if the
+//! backend deviates from the expected behavior.
+
+use super::add_block_contents_to_ledger;
+use crate::{ActiveMintConfig, Error, Ledger};
+use mc_blockchain_test_utils::get_blocks;
+use mc_blockchain_types::{BlockContents, BlockData, BlockVersion};
+use mc_crypto_keys::Ed25519Pair;
+use mc_transaction_core::{
+    membership_proofs::compute_implied_merkle_root, ring_signature::KeyImage, TokenId,
+};
+use mc_transaction_core_test_utils::{
+    create_mint_config_tx_and_signers, create_mint_tx, create_test_tx_out,
+    mint_config_tx_to_validated as to_validated,
+};
+use mc_util_test_helper::get_seeded_rng;
+
+const BLOCK_VERSION: BlockVersion = BlockVersion::ZERO;
+
+/// Run every conformance test, using `create_ledger` to get a fresh empty
+/// ledger for each of them.
+pub fn run_all<L: Ledger>(mut create_ledger: impl FnMut() -> L) {
+    append_and_get_blocks(&mut create_ledger());
+    tx_out_membership_proofs(&mut create_ledger());
+    reject_invalid_blocks(&mut create_ledger());
+    mint_configs_and_mint_txs(&mut create_ledger());
+}
+
+/// Append blocks, and read every part of them back.
+pub fn append_and_get_blocks(ledger: &mut impl Ledger) {
+    assert_eq!(ledger.num_blocks().unwrap(), 0);
+    assert_eq!(ledger.num_txos().unwrap(), 0);
+    assert_eq!(ledger.get_latest_block(), Err(Error::NotFound));
+    assert_eq!(ledger.get_block(0), Err(Error::NotFound));
+
+    let blocks = get_blocks(
+        BLOCK_VERSION,
+        5,
+        2,
+        1,
+        1,
+        1 << 20,
+        None,
+        &mut get_seeded_rng(),
+    );
+    for block_data in &blocks {
+        ledger.append_block_data(block_data).unwrap();
+    }
+
+    assert_eq!(ledger.num_blocks().unwrap(), 5);
+    assert_eq!(ledger.num_txos().unwrap(), 10);
+    assert_eq!(&ledger.get_latest_block().unwrap(), blocks[4].block());
+
+    let mut tx_out_index = 0;
+    for block_data in &blocks {
+        let block_index = block_data.block().index;
+        assert_eq!(&ledger.get_block(block_index).unwrap(), block_data.block());
+        assert_eq!(
+            &ledger.get_block_contents(block_index).unwrap(),
+            block_data.contents()
+        );
+        assert_eq!(
+            Some(&ledger.get_block_signature(block_index).unwrap()),
+            block_data.signature()
+        );
+        assert_eq!(
+            Some(&ledger.get_block_metadata(block_index).unwrap()),
+            block_data.metadata()
+        );
+        assert_eq!(&ledger.get_block_data(block_index).unwrap(), block_data);
+        assert_eq!(
+            ledger.get_key_images_by_block(block_index).unwrap(),
+            block_data.contents().key_images
+        );
+
+        for key_image in &block_data.contents().key_images {
+            assert_eq!(
+                ledger.check_key_image(key_image).unwrap(),
+                Some(block_index)
+            );
+            assert!(ledger.contains_key_image(key_image).unwrap());
+        }
+
+        for tx_out in &block_data.contents().outputs {
+            assert_eq!(&ledger.get_tx_out_by_index(tx_out_index).unwrap(), tx_out);
+            assert_eq!(
+                ledger.get_tx_out_index_by_hash(&tx_out.hash()).unwrap(),
+                tx_out_index
+            );
+            assert_eq!(
+                ledger
+                    .get_tx_out_index_by_public_key(&tx_out.public_key)
+                    .unwrap(),
+                tx_out_index
+            );
+            assert_eq!(
+                ledger
+                    .get_block_index_by_tx_out_index(tx_out_index)
+                    .unwrap(),
+                block_index
+            );
+            assert!(ledger
+                .contains_tx_out_public_key(&tx_out.public_key)
+                .unwrap());
+            tx_out_index += 1;
+        }
+    }
+
+    // Lookups of things that are not in the ledger.
+    assert_eq!(ledger.get_block(5), Err(Error::NotFound));
+    assert_eq!(ledger.get_tx_out_by_index(10), Err(Error::NotFound));
+    assert_eq!(
+        ledger.get_tx_out_index_by_hash(&[7u8; 32]),
+        Err(Error::NotFound)
+    );
+    assert_eq!(ledger.check_key_image(&KeyImage::from(1337)).unwrap(), None);
+}
+
+/// Membership proofs and the root element must agree with each other for
+/// every ledger size, including ones that are not powers of two.
+pub fn tx_out_membership_proofs(ledger: &mut impl Ledger) {
+    assert_eq!(
+        ledger.get_root_tx_out_membership_element(),
+        Err(Error::NoOutputs)
+    );
+
+    let mut rng = get_seeded_rng();
+    let mut prev_block = None;
+    for _ in 0..7 {
+        let block_data = get_blocks(BLOCK_VERSION, 1, 1, 1, 1, 1 << 20, prev_block, &mut rng)
+            .pop()
+            .unwrap();
+        ledger.append_block_data(&block_data).unwrap();
+        prev_block = Some(block_data.block().clone());
+
+        let num_txos = ledger.num_txos().unwrap();
+        let root_element = ledger.get_root_tx_out_membership_element().unwrap();
+        assert_eq!(root_element.range.from, 0);
+        assert_eq!(root_element.range.to, num_txos.next_power_of_two() - 1);
+
+        let indexes = (0..num_txos).collect::<Vec<_>>();
+        let proofs = ledger.get_tx_out_proof_of_memberships(&indexes).unwrap();
+        for (index, proof) in indexes.iter().zip(proofs.iter()) {
+            assert_eq!(proof.index, *index);
+            assert_eq!(proof.highest_index, num_txos - 1);
+            assert_eq!(
+                compute_implied_merkle_root(proof).unwrap(),
+                root_element,
+                "proof for TxOut {index} does not match the root element"
+            );
+        }
+    }
+
+    assert_eq!(
+        ledger.get_tx_out_proof_of_memberships(&[7]),
+        Err(Error::TxOutIndexOutOfBounds(7))
+    );
+}
+
+/// Blocks that do not extend the ledger correctly must be rejected without
+/// modifying it.
+pub fn reject_invalid_blocks(ledger: &mut impl Ledger) {
+    let mut rng = get_seeded_rng();
+    let blocks = get_blocks(BLOCK_VERSION, 3, 1, 1, 1, 1 << 20, None, &mut rng);
+    ledger.append_block_data(&blocks[0]).unwrap();
+    ledger.append_block_data(&blocks[1]).unwrap();
+
+    // Appending the same block twice.
+    assert_eq!(
+        ledger.append_block_data(&blocks[1]),
+        Err(Error::InvalidBlockIndex(1))
+    );
+
+    // Skipping a block.
+    let next_blocks = get_blocks(
+        BLOCK_VERSION,
+        1,
+        1,
+        1,
+        1,
+        1 << 20,
+        blocks[2].block().clone(),
+        &mut rng,
+    );
+    assert_eq!(
+        ledger.append_block_data(&next_blocks[0]),
+        Err(Error::InvalidBlockIndex(3))
+    );
+
+    // Spending a key image twice.
+    let key_images = blocks[1].contents().key_images.clone();
+    let block_contents = BlockContents {
+        key_images,
+        outputs: vec![create_test_tx_out(BLOCK_VERSION, &mut rng)],
+        ..Default::default()
+    };
+    assert_eq!(
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng),
+        Err(Error::KeyImageAlreadySpent)
+    );
+
+    // Reusing an output public key.
+    let block_contents = BlockContents {
+        key_images: vec![KeyImage::from(1)],
+        outputs: blocks[1].contents().outputs.clone(),
+        ..Default::default()
+    };
+    assert_eq!(
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng),
+        Err(Error::DuplicateOutputPublicKey)
+    );
+
+    // A block without outputs.
+    let block_contents = BlockContents {
+        key_images: vec![KeyImage::from(2)],
+        ..Default::default()
+    };
+    assert_eq!(
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng),
+        Err(Error::NoOutputs)
+    );
+
+    // None of the above modified the ledger.
+    assert_eq!(ledger.num_blocks().unwrap(), 2);
+    assert_eq!(&ledger.get_block_data(1).unwrap(), &blocks[1]);
+    assert_eq!(
+        ledger.num_txos().unwrap(),
+        (blocks[0].contents().outputs.len() + blocks[1].contents().outputs.len()) as u64
+    );
+
+    // The correct next block can still be appended.
+    ledger.append_block_data(&blocks[2]).unwrap();
+}
+
+/// Mint configurations become active when appended, and MintTxs update their
+/// total minted amounts.
+pub fn mint_configs_and_mint_txs(ledger: &mut impl Ledger) {
+    let mut rng = get_seeded_rng();
+    let token_id1 = TokenId::from(1);
+    let origin = get_blocks(BLOCK_VERSION, 1, 1, 1, 1, 1000, None, &mut rng)
+        .pop()
+        .unwrap();
+    ledger.append_block_data(&origin).unwrap();
+
+    assert_eq!(ledger.get_active_mint_configs(token_id1).unwrap(), None);
+    assert!(ledger.get_active_mint_configs_map().unwrap().is_empty());
+
+    // Activate a configuration.
+    let (mint_config_tx1, signers1) = create_mint_config_tx_and_signers(token_id1, &mut rng);
+    let block_contents = BlockContents {
+        validated_mint_config_txs: vec![to_validated(&mint_config_tx1)],
+        ..Default::default()
+    };
+    add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents.clone(), &mut rng).unwrap();
+
+    assert_eq!(
+        ledger
+            .check_mint_config_tx_nonce(*token_id1, &mint_config_tx1.prefix.nonce)
+            .unwrap(),
+        Some(1)
+    );
+    let active_mint_configs = ledger.get_active_mint_configs(token_id1).unwrap().unwrap();
+    assert_eq!(active_mint_configs.mint_config_tx, mint_config_tx1);
+    assert_eq!(active_mint_configs.total_minted(), 0);
+    assert_eq!(
+        ledger.get_active_mint_configs_map().unwrap()[&token_id1],
+        active_mint_configs
+    );
+
+    // The same MintConfigTx cannot be included twice.
+    assert_eq!(
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng),
+        Err(Error::DuplicateMintConfigTx)
+    );
+
+    // Mint using the first and second configurations.
+    let mint_tx1 = create_mint_tx(token_id1, &signers1, 10, &mut rng);
+    let mint_tx2 = create_mint_tx(
+        token_id1,
+        &[
+            Ed25519Pair::from(signers1[1].private_key()),
+            Ed25519Pair::from(signers1[2].private_key()),
+        ],
+        20,
+        &mut rng,
+    );
+    assert_eq!(
+        ledger
+            .get_active_mint_config_for_mint_tx(&mint_tx1)
+            .unwrap(),
+        ActiveMintConfig {
+            mint_config: mint_config_tx1.prefix.configs[0].clone(),
+            total_minted: 0,
+        }
+    );
+    let block_contents = BlockContents {
+        mint_txs: vec![mint_tx1.clone(), mint_tx2.clone()],
+        outputs: vec![
+            create_test_tx_out(BLOCK_VERSION, &mut rng),
+            create_test_tx_out(BLOCK_VERSION, &mut rng),
+        ],
+        ..Default::default()
+    };
+    let block_data: BlockData =
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng).unwrap();
+    assert_eq!(&ledger.get_block_data(2).unwrap(), &block_data);
+
+    assert_eq!(
+        ledger
+            .check_mint_tx_nonce(*token_id1, &mint_tx1.prefix.nonce)
+            .unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        ledger
+            .check_mint_tx_nonce(*token_id1, &mint_tx2.prefix.nonce)
+            .unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        ledger
+            .check_mint_tx_nonce(*TokenId::from(2), &mint_tx1.prefix.nonce)
+            .unwrap(),
+        None
+    );
+
+    let active_mint_configs = ledger.get_active_mint_configs(token_id1).unwrap().unwrap();
+    assert_eq!(
+        active_mint_configs
+            .configs
+            .iter()
+            .map(|config| config.total_minted)
+            .collect::<Vec<_>>(),
+        vec![10, 20, 0]
+    );
+
+    // A MintTx cannot be included twice.
+    let block_contents = BlockContents {
+        mint_txs: vec![mint_tx1],
+        outputs: vec![create_test_tx_out(BLOCK_VERSION, &mut rng)],
+        ..Default::default()
+    };
+    assert_eq!(
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng),
+        Err(Error::DuplicateMintTx)
+    );
+
+    // Minting over the total mint limit fails.
+    let mint_tx3 = create_mint_tx(
+        token_id1,
+        &signers1,
+        mint_config_tx1.prefix.total_mint_limit,
+        &mut rng,
+    );
+    let block_contents = BlockContents {
+        mint_txs: vec![mint_tx3],
+        outputs: vec![create_test_tx_out(BLOCK_VERSION, &mut rng)],
+        ..Default::default()
+    };
+    assert!(matches!(
+        add_block_contents_to_ledger(ledger, BLOCK_VERSION, block_contents, &mut rng),
+        Err(Error::MintLimitExceeded(..))
+    ));
+
+    assert_eq!(ledger.num_blocks().unwrap(), 3);
+    assert_eq!(
+        ledger.get_active_mint_configs(token_id1).unwrap().unwrap(),
+        active_mint_configs
+    );
+}
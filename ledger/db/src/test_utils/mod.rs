@@ -1,5 +1,6 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
+pub mod ledger_conformance;
 pub mod mock_ledger;
 pub use mock_ledger::{get_mock_ledger, get_test_ledger_blocks, MockLedger};
 
@@ -420,7 +421,7 @@ pub fn add_txos_and_key_images_to_ledger(
 /// * `block_contents` - The block contents.
 /// * `rng` - Random number generator.
 pub fn add_block_contents_to_ledger(
-    ledger_db: &mut LedgerDB,
+    ledger_db: &mut impl Ledger,
     block_version: BlockVersion,
     block_contents: BlockContents,
     rng: &mut (impl CryptoRng + RngCore),
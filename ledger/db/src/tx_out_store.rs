@@ -146,6 +146,54 @@ impl TxOutStore {
         Ok(index)
     }
 
+    /// Removes every TxOut with an index of `num_tx_outs` or higher, and
+    /// restores the Merkle Tree to what it was when the collection held
+    /// `num_tx_outs` TxOuts.
+    pub fn truncate(
+        &self,
+        num_tx_outs: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let prev_num_tx_outs = self.num_tx_outs(db_transaction)?;
+        if num_tx_outs >= prev_num_tx_outs {
+            return Ok(());
+        }
+
+        for index in num_tx_outs..prev_num_tx_outs {
+            let tx_out = self.get_tx_out_by_index(index, db_transaction)?;
+            db_transaction.del(self.tx_out_index_by_hash, &tx_out.hash(), None)?;
+            db_transaction.del(self.tx_out_index_by_public_key, &tx_out.public_key, None)?;
+            db_transaction.del(self.tx_out_by_index, &u64_to_key_bytes(index), None)?;
+
+            // Every subtree that is no longer complete contains at least one removed
+            // TxOut, so this visits all of them.
+            for (low, high) in containing_ranges(index, prev_num_tx_outs)? {
+                if high < num_tx_outs {
+                    continue;
+                }
+                let range = Range::new(low, high)?;
+                match db_transaction.del(self.merkle_hashes, &range_to_key_bytes(&range), None) {
+                    Ok(()) | Err(lmdb::Error::NotFound) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        db_transaction.put(
+            self.counts,
+            &NUM_TX_OUTS_KEY,
+            &u64_to_key_bytes(num_tx_outs),
+            WriteFlags::empty(),
+        )?;
+
+        // The subtrees containing the new last TxOut are incomplete again.
+        if num_tx_outs > 0 {
+            self.update_merkle_hashes(num_tx_outs - 1, db_transaction)?;
+        }
+
+        Ok(())
+    }
+
     /// Get the total number of TxOuts in the ledger.
     pub fn num_tx_outs<T: Transaction>(&self, db_transaction: &T) -> Result<u64, Error> {
         Ok(key_bytes_to_u64(
@@ -1061,6 +1109,67 @@ pub mod tx_out_store_tests {
         rw_transaction.commit().unwrap();
     }
 
+    #[test]
+    // Truncating should leave the store as if the removed TxOuts were never pushed.
+    fn test_truncate() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let (expected_store, expected_env) = init_tx_out_store();
+        let tx_outs = get_tx_outs(37);
+
+        let mut rw_transaction: RwTransaction = env.begin_rw_txn().unwrap();
+        for tx_out in &tx_outs {
+            tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+        }
+
+        for num_tx_outs in [33, 32, 17, 1, 0] {
+            tx_out_store
+                .truncate(num_tx_outs, &mut rw_transaction)
+                .unwrap();
+            assert_eq!(
+                num_tx_outs,
+                tx_out_store.num_tx_outs(&rw_transaction).unwrap()
+            );
+
+            let mut expected_transaction: RwTransaction = expected_env.begin_rw_txn().unwrap();
+            for tx_out in &tx_outs[..num_tx_outs as usize] {
+                expected_store
+                    .push(tx_out, &mut expected_transaction)
+                    .unwrap();
+            }
+            assert_eq!(
+                expected_store
+                    .get_root_merkle_hash(&expected_transaction)
+                    .unwrap(),
+                tx_out_store.get_root_merkle_hash(&rw_transaction).unwrap()
+            );
+            for index in 0..num_tx_outs {
+                assert_eq!(
+                    expected_store
+                        .get_merkle_proof_of_membership(index, &expected_transaction)
+                        .unwrap(),
+                    tx_out_store
+                        .get_merkle_proof_of_membership(index, &rw_transaction)
+                        .unwrap()
+                );
+            }
+            assert_eq!(
+                Err(Error::NotFound),
+                tx_out_store.get_tx_out_index_by_hash(
+                    &tx_outs[num_tx_outs as usize].hash(),
+                    &rw_transaction
+                )
+            );
+        }
+
+        // TxOuts can be pushed again after truncating.
+        for (i, tx_out) in tx_outs.iter().enumerate() {
+            assert_eq!(
+                i as u64,
+                tx_out_store.push(tx_out, &mut rw_transaction).unwrap()
+            );
+        }
+    }
+
     #[test]
     fn test_containing_range() {
         // The subtree of size 2^0 containing leaf 5 contains leaves [5,5].
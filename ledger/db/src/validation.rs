@@ -0,0 +1,183 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+
+//! Validation of blocks before they are appended to a ledger.
+//!
+//! The checks are shared between storage backends, which only need to expose
+//! the state the checks depend on through [AppendBlockState].
+
+use crate::Error;
+use mc_blockchain_types::{
+    Block, BlockContents, BlockID, BlockIndex, BlockMetadata, BlockVersion, MAX_BLOCK_VERSION,
+};
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::ring_signature::KeyImage;
+
+/// The ledger state needed to validate a block that is about to be appended.
+///
+/// Implementations should read from the same transaction the block will be
+/// written in.
+pub trait AppendBlockState {
+    /// Get the total number of blocks in the ledger.
+    fn num_blocks(&self) -> Result<u64, Error>;
+
+    /// Gets a Block by its index in the blockchain.
+    fn get_block(&self, block_number: BlockIndex) -> Result<Block, Error>;
+
+    /// Returns the index of the block containing the given key image, if any.
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<BlockIndex>, Error>;
+
+    /// Returns true if the ledger contains the given TxOut public key.
+    fn contains_tx_out_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<bool, Error>;
+
+    /// Returns the index of the block containing the MintTx with the given
+    /// nonce and token id, if any.
+    fn check_mint_tx_nonce(&self, token_id: u64, nonce: &[u8])
+        -> Result<Option<BlockIndex>, Error>;
+
+    /// Returns the index of the block containing the MintConfigTx with the
+    /// given nonce and token id, if any.
+    fn check_mint_config_tx_nonce(
+        &self,
+        token_id: u64,
+        nonce: &[u8],
+    ) -> Result<Option<BlockIndex>, Error>;
+}
+
+/// Checks if a block can be appended to a ledger with the given state.
+pub fn validate_append_block(
+    state: &impl AppendBlockState,
+    block: &Block,
+    block_contents: &BlockContents,
+    metadata: Option<&BlockMetadata>,
+) -> Result<(), Error> {
+    // Check version is correct
+    // Check if block is being appended at the correct place.
+    let num_blocks = state.num_blocks()?;
+    if num_blocks == 0 {
+        // This must be an origin block.
+
+        // The origin block is version 0
+        if block.version != 0 {
+            return Err(Error::InvalidBlockVersion(block.version));
+        }
+
+        // The origin block is index '0' with default-initialized parent ID, by
+        // convention
+        if block.index != 0 {
+            return Err(Error::InvalidBlockIndex(block.index));
+        }
+        if block.parent_id != BlockID::default() {
+            return Err(Error::InvalidParentBlockID(block.id.clone()));
+        }
+    } else {
+        let last_block = state.get_block(num_blocks - 1)?;
+
+        // The block's version should be bounded by
+        // [prev block version, max block version]
+        if block.version < last_block.version || block.version > *MAX_BLOCK_VERSION {
+            return Err(Error::InvalidBlockVersion(block.version));
+        }
+
+        // The block must have the correct index and parent.
+        if block.index != num_blocks {
+            return Err(Error::InvalidBlockIndex(block.index));
+        }
+        if block.parent_id != last_block.id {
+            return Err(Error::InvalidParentBlockID(block.parent_id.clone()));
+        }
+    }
+
+    // A block must have outputs, unless it has mint-config transactions:
+    // - Origin block had outputs only outputs in it.
+    // - Blocks before minting was introduced always had outputs in them.
+    // - Blocks that have MintTxs in them will also have the minted TxOuts in them.
+    // - Blocks with only MintConfigTxs are allowed to not have outputs.
+    let has_mint_config_txs = !block_contents.validated_mint_config_txs.is_empty();
+    if block_contents.outputs.is_empty() && !has_mint_config_txs {
+        return Err(Error::NoOutputs);
+    }
+
+    // Number of outputs must be >= number of mint transactions because each mint
+    // transaction must produce a single output.
+    if block_contents.outputs.len() < block_contents.mint_txs.len() {
+        return Err(Error::TooFewOutputs);
+    }
+
+    // Non-origin blocks must have key images, unless it has minting-related
+    // transactions. When we have minting transactions it implies we might've not
+    // spent any pre-existing outputs and as such we will not have key images.
+    let has_minting_txs =
+        !block_contents.validated_mint_config_txs.is_empty() || !block_contents.mint_txs.is_empty();
+    if block.index != 0 && block_contents.key_images.is_empty() && !has_minting_txs {
+        return Err(Error::NoKeyImages);
+    }
+
+    // Check that the block contents match the hash.
+    if block.contents_hash != block_contents.hash() {
+        return Err(Error::InvalidBlockContents);
+    }
+
+    // Check that none of the outputs are missing masked amount (or, have a masked
+    // amount we don't understand)
+    if block_contents
+        .outputs
+        .iter()
+        .any(|output| output.get_masked_amount().is_err())
+    {
+        return Err(Error::MissingMaskedAmount);
+    }
+
+    // Check that none of the key images were previously spent.
+    for key_image in &block_contents.key_images {
+        if state.check_key_image(key_image)?.is_some() {
+            return Err(Error::KeyImageAlreadySpent);
+        }
+    }
+
+    // Check that none of the output public keys appear in the ledger.
+    for output in block_contents.outputs.iter() {
+        if state.contains_tx_out_public_key(&output.public_key)? {
+            return Err(Error::DuplicateOutputPublicKey);
+        }
+    }
+
+    // Validate block id.
+    if !block.is_block_id_valid() {
+        return Err(Error::InvalidBlockID(block.id.clone()));
+    }
+
+    // Check that none of the minting transaction nonces appear in the ledger.
+    for mint_tx in block_contents.mint_txs.iter() {
+        if state
+            .check_mint_tx_nonce(mint_tx.prefix.token_id, &mint_tx.prefix.nonce)?
+            .is_some()
+        {
+            return Err(Error::DuplicateMintTx);
+        }
+    }
+
+    // Check that none of the mint-config-tx nonces appear in the ledger.
+    for validated_mint_config_tx in block_contents.validated_mint_config_txs.iter() {
+        if state
+            .check_mint_config_tx_nonce(
+                validated_mint_config_tx.mint_config_tx.prefix.token_id,
+                &validated_mint_config_tx.mint_config_tx.prefix.nonce,
+            )?
+            .is_some()
+        {
+            return Err(Error::DuplicateMintConfigTx);
+        }
+    }
+
+    let block_version =
+        BlockVersion::try_from(block.version).or(Err(Error::InvalidBlockVersion(block.version)))?;
+    if block_version.require_block_metadata() && metadata.is_none() {
+        return Err(Error::BlockMetadataRequired);
+    }
+
+    // All good
+    Ok(())
+}
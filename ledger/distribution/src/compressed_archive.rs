@@ -0,0 +1,255 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Writer for the compressed archive layout: buckets of consecutive blocks
+//! stored as zstd-compressed `ArchiveBlocks` objects, and a signed manifest
+//! listing the range, hash and last block id of every bucket.
+//!
+//! The compressed archive is written next to the individual and merged blocks,
+//! which remain the only way to get blocks that are not in a complete bucket
+//! yet.
+
+use crate::BlockHandler;
+use mc_api::{blockchain, compressed_bucket_path, ARCHIVE_MANIFEST_PATH};
+use mc_blockchain_types::{
+    ArchiveManifest, ArchiveManifestBucket, ArchiveManifestContents, BlockData,
+};
+use mc_common::logger::{log, Logger};
+use mc_crypto_keys::Ed25519Pair;
+use mc_ledger_db::Ledger;
+use protobuf::Message;
+use std::{path::Path, sync::Arc};
+
+/// zstd compression level of the buckets. Buckets are written once and read
+/// many times, so this favors size over speed.
+pub const COMPRESSION_LEVEL: i32 = 19;
+
+/// Writes the buckets and manifest of a compressed archive.
+pub struct CompressedArchiveWriter {
+    /// Number of blocks in each bucket.
+    bucket_size: u64,
+
+    /// The keypair with which to sign the manifest.
+    signer: Arc<Ed25519Pair>,
+
+    /// The buckets written so far.
+    contents: ArchiveManifestContents,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl CompressedArchiveWriter {
+    /// Create a writer, picking up after the buckets listed by the manifest
+    /// already at the destination, if it is signed by `signer`.
+    pub fn new(
+        bucket_size: u64,
+        signer: Arc<Ed25519Pair>,
+        block_handler: &dyn BlockHandler,
+        logger: Logger,
+    ) -> Self {
+        assert!(bucket_size > 1, "compressed bucket size must be at least 2");
+
+        let contents = block_handler
+            .read_archive_object(Path::new(ARCHIVE_MANIFEST_PATH))
+            .and_then(|bytes| {
+                // Only append to a manifest whose signature still checks out, so
+                // that a tampered manifest is never re-signed with our key.
+                let manifest = blockchain::ArchiveManifest::parse_from_bytes(&bytes)
+                    .ok()
+                    .and_then(|proto| ArchiveManifest::try_from(&proto).ok())
+                    .filter(|manifest| {
+                        *manifest.signer() == signer.public_key() && manifest.verify().is_ok()
+                    });
+                if manifest.is_none() {
+                    log::warn!(
+                        logger,
+                        "Existing manifest is invalid or signed by another key, rewriting it"
+                    );
+                }
+                manifest
+            })
+            .map(|manifest| manifest.contents().clone())
+            .unwrap_or_default();
+
+        log::info!(
+            logger,
+            "Compressed archive has {} buckets, up to block {}",
+            contents.buckets().len(),
+            contents.next_block_index()
+        );
+
+        Self {
+            bucket_size,
+            signer,
+            contents,
+            logger,
+        }
+    }
+
+    /// Write every bucket that is complete among the first `num_blocks` blocks
+    /// of the ledger and is not in the manifest yet, updating the manifest
+    /// after each one.
+    pub fn write_complete_buckets(
+        &mut self,
+        ledger: &impl Ledger,
+        num_blocks: u64,
+        block_handler: &mut dyn BlockHandler,
+    ) {
+        loop {
+            let first_block_index = self.contents.next_block_index();
+            if first_block_index + self.bucket_size > num_blocks {
+                return;
+            }
+
+            log::info!(
+                self.logger,
+                "Writing compressed bucket [{}-{}]",
+                first_block_index,
+                first_block_index + self.bucket_size - 1
+            );
+
+            let blocks_data = (first_block_index..first_block_index + self.bucket_size)
+                .map(|block_index| {
+                    ledger
+                        .get_block_data(block_index)
+                        .unwrap_or_else(|err| panic!("failed getting block #{block_index}: {err}"))
+                })
+                .collect::<Vec<_>>();
+            let object = compress_bucket(&blocks_data);
+            block_handler.write_archive_object(
+                &compressed_bucket_path(self.bucket_size, first_block_index),
+                &object,
+            );
+
+            // The manifest is only updated once the bucket is in place, so that
+            // readers never see a bucket listed before it can be fetched.
+            self.contents.push(ArchiveManifestBucket::from_object(
+                first_block_index,
+                self.bucket_size,
+                &object,
+                blocks_data.last().unwrap().block().id.clone(),
+            ));
+            let manifest =
+                ArchiveManifest::from_contents_and_keypair(self.contents.clone(), &self.signer)
+                    .expect("failed signing manifest");
+            block_handler.write_archive_object(
+                Path::new(ARCHIVE_MANIFEST_PATH),
+                &blockchain::ArchiveManifest::from(&manifest)
+                    .write_to_bytes()
+                    .expect("failed to serialize ArchiveManifest"),
+            );
+        }
+    }
+}
+
+/// Serialize and compress a bucket of blocks.
+pub fn compress_bucket(blocks_data: &[BlockData]) -> Vec<u8> {
+    let bytes = blockchain::ArchiveBlocks::from(blocks_data)
+        .write_to_bytes()
+        .expect("failed to serialize ArchiveBlocks");
+    zstd::encode_all(bytes.as_slice(), COMPRESSION_LEVEL).expect("failed compressing bucket")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LocalBlockWriter;
+    use mc_blockchain_types::{BlockID, BlockVersion};
+    use mc_common::logger::test_with_logger;
+    use mc_crypto_keys::Ed25519Private;
+    use mc_ledger_db::test_utils::{create_ledger, initialize_ledger};
+    use mc_transaction_core::AccountKey;
+    use mc_util_test_helper::{RngType, SeedableRng};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn read_manifest(dir: &Path) -> ArchiveManifest {
+        let bytes = fs::read(dir.join(ARCHIVE_MANIFEST_PATH)).unwrap();
+        let proto = blockchain::ArchiveManifest::parse_from_bytes(&bytes).unwrap();
+        ArchiveManifest::try_from(&proto).unwrap()
+    }
+
+    #[test_with_logger]
+    fn writes_buckets_and_manifest(logger: Logger) {
+        let mut rng = RngType::from_seed([0u8; 32]);
+        let key = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        initialize_ledger(BlockVersion::MAX, &mut ledger, 30, &key, &mut rng);
+
+        let signer = Arc::new(Ed25519Pair::from(
+            Ed25519Private::try_from(&[1; 32][..]).unwrap(),
+        ));
+        let temp_dir = TempDir::new().unwrap();
+        let mut block_handler = LocalBlockWriter::new(temp_dir.path().into(), logger.clone());
+        let mut writer =
+            CompressedArchiveWriter::new(10, signer.clone(), &block_handler, logger.clone());
+
+        // Only complete buckets among the blocks distributed so far are written.
+        writer.write_complete_buckets(&ledger, 25, &mut block_handler);
+        let manifest = read_manifest(temp_dir.path());
+        assert_eq!(manifest.signer(), &signer.public_key());
+        assert_eq!(manifest.contents().buckets().len(), 2);
+        assert_eq!(manifest.contents().next_block_index(), 20);
+
+        for bucket in manifest.contents().buckets() {
+            let path = compressed_bucket_path(10, bucket.first_block_index());
+            let object = fs::read(temp_dir.path().join(path)).unwrap();
+            assert!(bucket.matches_object(&object));
+
+            let bytes = zstd::decode_all(object.as_slice()).unwrap();
+            let archive_blocks = blockchain::ArchiveBlocks::parse_from_bytes(&bytes).unwrap();
+            let blocks_data = Vec::<BlockData>::try_from(&archive_blocks).unwrap();
+            for block_data in blocks_data {
+                assert_eq!(
+                    block_data,
+                    ledger.get_block_data(block_data.block().index).unwrap()
+                );
+            }
+            assert_eq!(
+                bucket.last_block_id(),
+                &ledger.get_block(bucket.first_block_index() + 9).unwrap().id
+            );
+        }
+
+        // A new writer picks up where the manifest left off.
+        let mut writer = CompressedArchiveWriter::new(10, signer, &block_handler, logger);
+        writer.write_complete_buckets(&ledger, 30, &mut block_handler);
+        let new_manifest = read_manifest(temp_dir.path());
+        assert_eq!(new_manifest.contents().buckets().len(), 3);
+        assert_eq!(
+            new_manifest.contents().buckets()[..2],
+            manifest.contents().buckets()[..]
+        );
+    }
+
+    #[test_with_logger]
+    fn ignores_tampered_manifest(logger: Logger) {
+        let signer = Arc::new(Ed25519Pair::from(
+            Ed25519Private::try_from(&[1; 32][..]).unwrap(),
+        ));
+        let temp_dir = TempDir::new().unwrap();
+        let mut block_handler = LocalBlockWriter::new(temp_dir.path().into(), logger.clone());
+
+        // A manifest claiming our key, with a signature over other contents.
+        let signature = ArchiveManifest::from_contents_and_keypair(Default::default(), &signer)
+            .unwrap()
+            .signature()
+            .clone();
+        let contents = ArchiveManifestContents::new(vec![ArchiveManifestBucket::from_object(
+            0,
+            10,
+            b"not a bucket",
+            BlockID([0; 32]),
+        )]);
+        let tampered = ArchiveManifest::new(contents, signer.public_key(), signature);
+        block_handler.write_archive_object(
+            Path::new(ARCHIVE_MANIFEST_PATH),
+            &blockchain::ArchiveManifest::from(&tampered)
+                .write_to_bytes()
+                .unwrap(),
+        );
+
+        let writer = CompressedArchiveWriter::new(10, signer, &block_handler, logger);
+        assert_eq!(writer.contents.next_block_index(), 0);
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Block writer for destinations reached with plain HTTP requests: servers
+//! accepting `PUT` requests (including WebDAV servers and Azure Blob Storage,
+//! authorized by a SAS token in the URL), and Google Cloud Storage, through
+//! its XML API.
+
+use crate::BlockHandler;
+use displaydoc::Display;
+use mc_api::{block_num_to_s3block_path, blockchain, merged_block_num_to_s3block_path};
+use mc_blockchain_types::{BlockData, BlockIndex};
+use mc_common::logger::{log, Logger};
+use protobuf::Message;
+use reqwest::{Client, Method, StatusCode};
+use retry::{delay, retry, OperationResult};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Handle;
+use url::Url;
+
+/// Public endpoint of Google Cloud Storage's XML API.
+pub const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// URL of the GCP metadata server's access tokens, for the service account of
+/// the instance this runs on.
+pub const GCP_METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Environment variable holding an OAuth access token for GCS, which is used
+/// instead of the metadata server's tokens when set.
+pub const GCS_ACCESS_TOKEN_ENV: &str = "GOOGLE_OAUTH_ACCESS_TOKEN";
+
+/// How long before their expiry access tokens are refreshed.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// How requests to the destination are authorized.
+#[derive(Clone, Debug)]
+pub enum HttpAuth {
+    /// No `Authorization` header. The URL may still carry credentials, e.g. an
+    /// Azure SAS token.
+    None,
+
+    /// A fixed bearer token.
+    Bearer(String),
+
+    /// Bearer tokens from a GCP metadata server at the given URL.
+    GcpMetadata(Url),
+}
+
+/// Errors of a single request to the destination.
+#[derive(Debug, Display)]
+pub enum HttpWriterError {
+    /// Request failed: {0}
+    Request(reqwest::Error),
+
+    /// Unexpected status {0} for {1}
+    Status(StatusCode, Url),
+
+    /// Invalid access token response: {0}
+    AccessToken(serde_json::Error),
+}
+
+impl From<reqwest::Error> for HttpWriterError {
+    fn from(src: reqwest::Error) -> Self {
+        Self::Request(src)
+    }
+}
+
+impl From<serde_json::Error> for HttpWriterError {
+    fn from(src: serde_json::Error) -> Self {
+        Self::AccessToken(src)
+    }
+}
+
+/// Response of the GCP metadata server's token endpoint.
+#[derive(Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// HTTP block writer.
+pub struct HttpBlockWriter {
+    base_url: Url,
+    auth: HttpAuth,
+    access_token: Mutex<Option<AccessToken>>,
+    client: Client,
+    logger: Logger,
+}
+
+impl HttpBlockWriter {
+    /// Create a writer for objects under `base_url`.
+    pub fn new(base_url: Url, auth: HttpAuth, logger: Logger) -> Self {
+        log::debug!(logger, "Creating HTTP Block Writer with url={}", base_url);
+
+        Self {
+            base_url,
+            auth,
+            access_token: Mutex::new(None),
+            client: Client::new(),
+            logger,
+        }
+    }
+
+    /// Create a writer for objects under `path` in the GCS bucket `bucket`.
+    ///
+    /// Requests are authorized with the token in the
+    /// `GOOGLE_OAUTH_ACCESS_TOKEN` environment variable if it is set, and
+    /// otherwise with tokens from the metadata server, unless a custom
+    /// `endpoint` is given, which is assumed not to require authorization.
+    pub fn new_gcs(bucket: &str, path: &Path, endpoint: Option<Url>, logger: Logger) -> Self {
+        let auth = match (std::env::var(GCS_ACCESS_TOKEN_ENV), &endpoint) {
+            (Ok(token), _) => HttpAuth::Bearer(token),
+            (Err(_), Some(_)) => HttpAuth::None,
+            (Err(_), None) => HttpAuth::GcpMetadata(
+                Url::parse(GCP_METADATA_TOKEN_URL).expect("invalid metadata server URL"),
+            ),
+        };
+
+        let mut base_url =
+            endpoint.unwrap_or_else(|| Url::parse(GCS_ENDPOINT).expect("invalid GCS endpoint"));
+        base_url
+            .path_segments_mut()
+            .expect("GCS endpoint cannot be a base")
+            .pop_if_empty()
+            .push(bucket)
+            .extend(path.iter().map(|c| c.to_str().expect("non UTF-8 path")));
+
+        Self::new(base_url, auth, logger)
+    }
+
+    /// The URL of an object, at a path relative to the base URL.
+    fn object_url(&self, path: &Path) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("base URL cannot be a base")
+            .pop_if_empty()
+            .extend(path.iter().map(|c| c.to_str().expect("non UTF-8 path")));
+        url
+    }
+
+    /// The bearer token to authorize requests with, if any.
+    fn bearer_token(&self) -> Result<Option<String>, HttpWriterError> {
+        let url = match &self.auth {
+            HttpAuth::None => return Ok(None),
+            HttpAuth::Bearer(token) => return Ok(Some(token.clone())),
+            HttpAuth::GcpMetadata(url) => url,
+        };
+
+        let mut access_token = self.access_token.lock().expect("mutex poisoned");
+        if let Some(access_token) = access_token
+            .as_ref()
+            .filter(|access_token| access_token.expires_at > Instant::now())
+        {
+            return Ok(Some(access_token.token.clone()));
+        }
+
+        log::debug!(self.logger, "Fetching an access token from {}", url);
+        let bytes = Handle::current().block_on(async {
+            self.client
+                .get(url.clone())
+                .header("Metadata-Flavor", "Google")
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        })?;
+        let response: AccessTokenResponse = serde_json::from_slice(&bytes)?;
+        let lifetime =
+            Duration::from_secs(response.expires_in).saturating_sub(TOKEN_REFRESH_MARGIN);
+
+        *access_token = Some(AccessToken {
+            token: response.access_token.clone(),
+            expires_at: Instant::now() + lifetime,
+        });
+        Ok(Some(response.access_token))
+    }
+
+    /// Send a single request, returning the status and body of the response.
+    fn request(
+        &self,
+        method: Method,
+        url: &Url,
+        body: Option<&[u8]>,
+    ) -> Result<(StatusCode, Vec<u8>), HttpWriterError> {
+        let mut request = self.client.request(method, url.clone());
+        if let Some(token) = self.bearer_token()? {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            // Azure Blob Storage requires the blob type, other servers ignore it.
+            request = request
+                .header("x-ms-blob-type", "BlockBlob")
+                .body(body.to_vec());
+        }
+
+        let (status, bytes) = Handle::current().block_on(async {
+            let response = request.send().await?;
+            let status = response.status();
+            Ok::<_, reqwest::Error>((status, response.bytes().await?))
+        })?;
+
+        if status == StatusCode::UNAUTHORIZED {
+            // The token may have been revoked before its expiry.
+            self.access_token.lock().expect("mutex poisoned").take();
+        }
+        Ok((status, bytes.to_vec()))
+    }
+
+    /// Write an object, creating its parent collections first if the server
+    /// requires it.
+    fn put_object(&self, path: &Path, bytes: &[u8]) -> Result<(), HttpWriterError> {
+        let url = self.object_url(path);
+        let (mut status, _) = self.request(Method::PUT, &url, Some(bytes))?;
+
+        // WebDAV servers refuse to create objects in collections that do not
+        // exist yet.
+        if status == StatusCode::CONFLICT {
+            self.create_collections(path)?;
+            (status, _) = self.request(Method::PUT, &url, Some(bytes))?;
+        }
+
+        if !status.is_success() {
+            return Err(HttpWriterError::Status(status, url));
+        }
+        Ok(())
+    }
+
+    /// Create the WebDAV collections containing the object at `path`.
+    fn create_collections(&self, path: &Path) -> Result<(), HttpWriterError> {
+        let mkcol = Method::from_bytes(b"MKCOL").expect("invalid method");
+        let mut collection = PathBuf::new();
+        for component in path.parent().into_iter().flat_map(Path::iter) {
+            collection.push(component);
+
+            // Collections are addressed with a trailing slash.
+            let mut url = self.object_url(&collection);
+            url.path_segments_mut()
+                .expect("base URL cannot be a base")
+                .push("");
+
+            // 405 Method Not Allowed means that the collection already exists.
+            let (status, _) = self.request(mkcol.clone(), &url, None)?;
+            if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(HttpWriterError::Status(status, url));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an object, if it exists.
+    fn get_object(&self, method: Method, path: &Path) -> Result<Option<Vec<u8>>, HttpWriterError> {
+        let url = self.object_url(path);
+        match self.request(method, &url, None)? {
+            (status, bytes) if status.is_success() => Ok(Some(bytes)),
+            (StatusCode::NOT_FOUND, _) => Ok(None),
+            (status, _) => Err(HttpWriterError::Status(status, url)),
+        }
+    }
+
+    /// Retry an operation until it succeeds.
+    fn retry<T>(&self, path: &Path, f: impl Fn() -> Result<T, HttpWriterError>) -> T {
+        let result = retry(
+            delay::Exponential::from_millis_with_base_factor(10).map(delay::jitter),
+            || {
+                f().map_or_else(
+                    |err| {
+                        log::warn!(self.logger, "Failed on {:?}: {}, retrying...", path, err);
+                        OperationResult::Retry(err)
+                    },
+                    OperationResult::Ok,
+                )
+            },
+        );
+
+        // We should always succeed since retrying should never stop until that happens.
+        result.expect("Stopped retrying HTTP request")
+    }
+
+    fn write_bytes(&self, path: &Path, bytes: &[u8]) {
+        self.retry(path, || self.put_object(path, bytes))
+    }
+}
+
+impl BlockHandler for HttpBlockWriter {
+    fn write_single_block(&mut self, block_data: &BlockData) {
+        log::info!(
+            self.logger,
+            "HTTP: Handling block {}",
+            block_data.block().index
+        );
+
+        let archive_block = blockchain::ArchiveBlock::from(block_data);
+
+        self.write_bytes(
+            &block_num_to_s3block_path(block_data.block().index),
+            &archive_block
+                .write_to_bytes()
+                .expect("failed to serialize ArchiveBlock"),
+        );
+    }
+
+    fn write_multiple_blocks(&mut self, blocks_data: &[BlockData]) {
+        assert!(blocks_data.len() >= 2);
+
+        let first_block_index = blocks_data[0].block().index;
+        let last_block_index = blocks_data.last().unwrap().block().index;
+        assert_eq!(
+            last_block_index,
+            first_block_index + blocks_data.len() as u64 - 1
+        );
+
+        log::info!(
+            self.logger,
+            "HTTP: Handling blocks {}-{}",
+            first_block_index,
+            last_block_index,
+        );
+
+        let archive_blocks = blockchain::ArchiveBlocks::from(blocks_data);
+
+        self.write_bytes(
+            &merged_block_num_to_s3block_path(blocks_data.len() as u64, first_block_index),
+            &archive_blocks
+                .write_to_bytes()
+                .expect("failed to serialize ArchiveBlocks"),
+        );
+    }
+
+    fn block_exists(&self, block_index: BlockIndex) -> bool {
+        log::info!(self.logger, "Checking for existence of block {block_index}");
+        let path = block_num_to_s3block_path(block_index);
+        self.retry(&path, || self.get_object(Method::HEAD, &path))
+            .is_some()
+    }
+
+    fn write_archive_object(&mut self, path: &Path, bytes: &[u8]) {
+        log::info!(self.logger, "HTTP: Writing {:?}", path);
+        self.write_bytes(path, bytes);
+    }
+
+    fn read_archive_object(&self, path: &Path) -> Option<Vec<u8>> {
+        self.retry(path, || self.get_object(Method::GET, path))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{first_block_to_handle, test_utils::TestObjectServer};
+    use mc_blockchain_types::BlockVersion;
+    use mc_common::logger::test_with_logger;
+    use mc_ledger_db::{
+        test_utils::{create_ledger, initialize_ledger},
+        Ledger, LedgerDB,
+    };
+    use mc_transaction_core::AccountKey;
+    use mc_util_test_helper::{RngType, SeedableRng};
+    use tokio::runtime::Runtime;
+
+    fn test_ledger(num_blocks: u64) -> LedgerDB {
+        let mut rng = RngType::from_seed([0u8; 32]);
+        let key = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        initialize_ledger(BlockVersion::MAX, &mut ledger, num_blocks, &key, &mut rng);
+        ledger
+    }
+
+    fn read_block(server: &TestObjectServer, prefix: &str, block_index: BlockIndex) -> BlockData {
+        let path = Path::new(prefix).join(block_num_to_s3block_path(block_index));
+        let bytes = server.object(path.to_str().unwrap()).unwrap();
+        let archive_block = blockchain::ArchiveBlock::parse_from_bytes(&bytes).unwrap();
+        BlockData::try_from(&archive_block).unwrap()
+    }
+
+    #[test_with_logger]
+    fn writes_and_resumes_over_http(logger: Logger) {
+        let runtime = Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let ledger = test_ledger(5);
+        let server = TestObjectServer::new();
+
+        // The query string, e.g. a SAS token, is kept on every request.
+        let base_url = Url::parse(&format!("{}ledger?sig=abc", server.url)).unwrap();
+        let mut block_handler = HttpBlockWriter::new(base_url, HttpAuth::None, logger);
+
+        for expected_block_count in [0, 1, 3, 5] {
+            for block_index in 0..expected_block_count {
+                block_handler.write_single_block(&ledger.get_block_data(block_index).unwrap());
+            }
+            assert_eq!(
+                first_block_to_handle(&ledger, &block_handler),
+                expected_block_count
+            );
+        }
+
+        for block_index in 0..5 {
+            assert_eq!(
+                read_block(&server, "ledger", block_index),
+                ledger.get_block_data(block_index).unwrap()
+            );
+        }
+        assert!(server
+            .requests()
+            .iter()
+            .all(|request| request.query.as_deref() == Some("sig=abc")));
+    }
+
+    #[test_with_logger]
+    fn creates_webdav_collections_and_retries(logger: Logger) {
+        let runtime = Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let ledger = test_ledger(2);
+        let server = TestObjectServer::webdav(&["ledger"]);
+        server.fail_next_requests(2);
+
+        let base_url = Url::parse(&format!("{}ledger/", server.url)).unwrap();
+        let mut block_handler = HttpBlockWriter::new(base_url, HttpAuth::None, logger);
+        block_handler.write_multiple_blocks(&[
+            ledger.get_block_data(0).unwrap(),
+            ledger.get_block_data(1).unwrap(),
+        ]);
+        block_handler.write_single_block(&ledger.get_block_data(1).unwrap());
+
+        assert_eq!(
+            read_block(&server, "ledger", 1),
+            ledger.get_block_data(1).unwrap()
+        );
+        let path = Path::new("ledger").join(merged_block_num_to_s3block_path(2, 0));
+        assert!(server.object(path.to_str().unwrap()).is_some());
+        assert!(server
+            .requests()
+            .iter()
+            .any(|request| request.method == "MKCOL"));
+
+        // The archive objects of the compressed archive go through the same path.
+        block_handler.write_archive_object(Path::new("compressed-10/manifest.pb"), b"manifest");
+        assert_eq!(
+            block_handler.read_archive_object(Path::new("compressed-10/manifest.pb")),
+            Some(b"manifest".to_vec())
+        );
+        assert_eq!(
+            block_handler.read_archive_object(Path::new("compressed-10/missing.pb")),
+            None
+        );
+    }
+
+    #[test_with_logger]
+    fn writes_to_gcs_with_metadata_token(logger: Logger) {
+        let runtime = Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let ledger = test_ledger(1);
+        let server = TestObjectServer::new();
+        server.insert_object(
+            "token",
+            br#"{"access_token":"secret","expires_in":3599,"token_type":"Bearer"}"#,
+        );
+
+        let mut block_handler = HttpBlockWriter::new_gcs(
+            "my_bucket",
+            Path::new("my_node"),
+            Some(Url::parse(&server.url).unwrap()),
+            logger,
+        );
+        block_handler.auth =
+            HttpAuth::GcpMetadata(Url::parse(&format!("{}token", server.url)).unwrap());
+
+        block_handler.write_single_block(&ledger.get_block_data(0).unwrap());
+        assert!(block_handler.block_exists(0));
+        assert_eq!(
+            read_block(&server, "my_bucket/my_node", 0),
+            ledger.get_block_data(0).unwrap()
+        );
+
+        // The token is fetched once, and used for every request to the bucket.
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .filter(|request| request.path == "/token")
+                .count(),
+            1
+        );
+        assert!(requests
+            .iter()
+            .filter(|request| request.path != "/token")
+            .all(|request| request.authorization.as_deref() == Some("Bearer secret")));
+    }
+}
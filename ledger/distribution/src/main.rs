@@ -2,23 +2,41 @@
 #![deny(missing_docs)]
 
 //! A helper utility for collecting blocks from a local ledger file and storing
-//! them as Protobuf-serialized files on S3.
+//! them as Protobuf-serialized files on S3, GCS, an HTTP server or local disk.
 
+pub mod compressed_archive;
+pub mod http;
 pub mod uri;
 
-use crate::uri::{Destination, Uri};
+#[cfg(test)]
+mod test_utils;
+
+use crate::{
+    compressed_archive::CompressedArchiveWriter,
+    http::{HttpAuth, HttpBlockWriter},
+    uri::{Destination, Uri},
+};
+use base64::{engine::general_purpose::STANDARD as BASE64_ENGINE, Engine};
 use clap::Parser;
 use mc_api::{block_num_to_s3block_path, blockchain, merged_block_num_to_s3block_path};
 use mc_blockchain_types::{BlockData, BlockIndex};
 use mc_common::logger::{create_app_logger, log, o, Logger};
+use mc_crypto_keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private};
 use mc_ledger_db::{Ledger, LedgerDB};
 use mc_util_telemetry::{mark_span_as_active, start_block_span, tracer, Tracer};
 use protobuf::Message;
 use retry::{delay, retry, OperationResult};
 use rusoto_core::{request::BufferedHttpResponse, Region, RusotoError};
-use rusoto_s3::{HeadObjectRequest, PutObjectRequest, S3Client, S3};
+use rusoto_s3::{
+    GetObjectError, GetObjectRequest, HeadObjectRequest, PutObjectRequest, S3Client, S3,
+};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::runtime::Handle;
 
 /// Block writer.
@@ -29,6 +47,11 @@ pub trait BlockHandler {
     fn write_multiple_blocks(&mut self, blocks_data: &[BlockData]);
     /// Returns true if the `block_index` exists in the destination.
     fn block_exists(&self, block_index: BlockIndex) -> bool;
+    /// Write an object of the compressed archive, at a path relative to the
+    /// destination.
+    fn write_archive_object(&mut self, path: &Path, bytes: &[u8]);
+    /// Read an object of the compressed archive, if it exists.
+    fn read_archive_object(&self, path: &Path) -> Option<Vec<u8>>;
 }
 
 /// Configuration for ledger distribution.
@@ -54,6 +77,33 @@ pub struct Config {
         env = "MC_MERGE_BUCKETS"
     )]
     merge_buckets: Vec<u64>,
+
+    /// Size of the buckets of the compressed archive, which is written
+    /// alongside the individual and merged blocks. The compressed archive is
+    /// not written unless this is set.
+    #[clap(
+        long,
+        requires = "manifest_signer_key",
+        env = "MC_COMPRESSED_BUCKET_SIZE"
+    )]
+    compressed_bucket_size: Option<u64>,
+
+    /// The keypair with which to sign the manifest of the compressed archive.
+    ///
+    /// The value provided via config is the keypair derived from the input
+    /// base64 DER-encoded private key.
+    #[clap(long, value_parser = keypair_from_base64, env = "MC_MANIFEST_SIGNER_KEY")]
+    manifest_signer_key: Option<Arc<Ed25519Pair>>,
+}
+
+fn keypair_from_base64(private_key: &str) -> Result<Arc<Ed25519Pair>, String> {
+    let privkey_bytes = BASE64_ENGINE
+        .decode(private_key)
+        .map_err(|err| format!("Could not decode private key from base64 {err:?}"))?;
+
+    let secret_key = Ed25519Private::try_from_der(privkey_bytes.as_slice())
+        .map_err(|err| format!("Could not get Ed25519Private from der {err:?}"))?;
+    Ok(Arc::new(Ed25519Pair::from(secret_key)))
 }
 
 /// State file contents.
@@ -226,6 +276,63 @@ impl BlockHandler for S3BlockWriter {
 
         result.expect("Stopped retrying getting block existence from S3")
     }
+
+    fn write_archive_object(&mut self, path: &Path, bytes: &[u8]) {
+        log::info!(self.logger, "S3: Writing {:?}", path);
+
+        let dest = self.path.join(path);
+        let dir = dest.as_path().parent().expect("failed getting parent");
+        let filename = dest.file_name().unwrap();
+
+        self.write_bytes_to_s3(dir.to_str().unwrap(), filename.to_str().unwrap(), bytes);
+    }
+
+    fn read_archive_object(&self, path: &Path) -> Option<Vec<u8>> {
+        let runtime = Handle::current();
+        let dest = self.path.join(path);
+        let dir = dest
+            .parent()
+            .expect("failed getting parent")
+            .to_string_lossy();
+        let filename = dest
+            .file_name()
+            .expect("Failed getting the file name")
+            .to_string_lossy();
+
+        let result = retry(
+            delay::Exponential::from_millis_with_base_factor(10).map(delay::jitter),
+            || {
+                let req = GetObjectRequest {
+                    bucket: dir.to_string(),
+                    key: filename.to_string(),
+                    ..Default::default()
+                };
+
+                match runtime.block_on(self.s3_client.get_object(req)) {
+                    Ok(output) => OperationResult::Ok(output.body),
+                    Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => {
+                        OperationResult::Ok(None)
+                    }
+                    Err(RusotoError::Unknown(BufferedHttpResponse { status, .. }))
+                        if status.as_u16() == 404 =>
+                    {
+                        OperationResult::Ok(None)
+                    }
+                    Err(e) => {
+                        log::warn!(self.logger, "Failed reading {dest:?}: {e:?}, retrying...");
+                        OperationResult::Retry(e)
+                    }
+                }
+            },
+        );
+
+        let body = result.expect("Stopped retrying reading from S3")?;
+        let mut bytes = Vec::new();
+        body.into_blocking_read()
+            .read_to_end(&mut bytes)
+            .unwrap_or_else(|err| panic!("failed reading {dest:?} from S3: {err}"));
+        Some(bytes)
+    }
 }
 
 /// Local directory block writer.
@@ -317,6 +424,26 @@ impl BlockHandler for LocalBlockWriter {
         let dest = self.path.join(block_num_to_s3block_path(block_index));
         dest.exists()
     }
+
+    fn write_archive_object(&mut self, path: &Path, bytes: &[u8]) {
+        log::info!(self.logger, "Local: Writing {:?}", path);
+
+        let dest = self.path.join(path);
+        let dir = dest.as_path().parent().expect("failed getting parent");
+        fs::create_dir_all(dir)
+            .unwrap_or_else(|e| panic!("failed creating directory {dir:?}: {e:?}"));
+
+        // Write to a temporary file first, so that readers never see a partially
+        // written object.
+        let tmp = dest.with_extension("tmp");
+        fs::write(&tmp, bytes).unwrap_or_else(|err| panic!("failed writing {tmp:?}: {err}"));
+        fs::rename(&tmp, &dest)
+            .unwrap_or_else(|err| panic!("failed renaming {tmp:?} to {dest:?}: {err}"));
+    }
+
+    fn read_archive_object(&self, path: &Path) -> Option<Vec<u8>> {
+        fs::read(self.path.join(path)).ok()
+    }
 }
 
 // Implements the ledger db polling loop
@@ -351,10 +478,33 @@ fn main() {
                 .unwrap_or_else(|_| panic!("Failed creating local destination directory {path:?}"));
             Box::new(LocalBlockWriter::new(path, logger.clone()))
         }
+
+        Destination::Http { url } => {
+            Box::new(HttpBlockWriter::new(url, HttpAuth::None, logger.clone()))
+        }
+
+        Destination::Gcs {
+            bucket,
+            path,
+            endpoint,
+        } => Box::new(HttpBlockWriter::new_gcs(
+            &bucket,
+            &path,
+            endpoint,
+            logger.clone(),
+        )),
     };
 
     let mut next_block_num = first_block_to_handle(&ledger_db, block_handler.as_ref());
 
+    let mut compressed_archive_writer = config.compressed_bucket_size.map(|bucket_size| {
+        let signer = config
+            .manifest_signer_key
+            .clone()
+            .expect("--manifest-signer-key is required by --compressed-bucket-size");
+        CompressedArchiveWriter::new(bucket_size, signer, block_handler.as_ref(), logger.clone())
+    });
+
     // Poll ledger for new blocks and process them as they come.
     log::info!(
         logger,
@@ -415,6 +565,10 @@ fn main() {
             next_block_num += 1;
         }
 
+        if let Some(writer) = compressed_archive_writer.as_mut() {
+            writer.write_complete_buckets(&ledger_db, next_block_num, block_handler.as_mut());
+        }
+
         // TODO: make this configurable
         std::thread::sleep(std::time::Duration::from_millis(10));
     }
@@ -0,0 +1,218 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! An in-process HTTP object store, standing in for the HTTP destinations in
+//! tests.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+/// A request received by a [TestObjectServer].
+#[derive(Clone, Debug)]
+pub struct TestRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub authorization: Option<String>,
+}
+
+#[derive(Default)]
+struct State {
+    objects: HashMap<String, Vec<u8>>,
+    /// WebDAV collections, or `None` if objects can be written anywhere.
+    collections: Option<HashSet<String>>,
+    requests: Vec<TestRequest>,
+}
+
+/// Stores the objects written with `PUT` requests in memory, and serves them
+/// to `HEAD` and `GET` requests.
+pub struct TestObjectServer {
+    /// The URL of the root of the store.
+    pub url: String,
+
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    failures: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+}
+
+impl TestObjectServer {
+    /// A server accepting objects at any path.
+    pub fn new() -> Self {
+        Self::start(None)
+    }
+
+    /// A server behaving like WebDAV, which only accepts objects in existing
+    /// collections, starting with the given ones.
+    pub fn webdav(collections: &[&str]) -> Self {
+        Self::start(Some(collections.iter().map(ToString::to_string).collect()))
+    }
+
+    /// Respond to the next `n` requests with a 500 error.
+    pub fn fail_next_requests(&self, n: usize) {
+        self.failures.store(n, Ordering::SeqCst);
+    }
+
+    /// Store an object, as if it had been written with a `PUT` request.
+    pub fn insert_object(&self, path: &str, bytes: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.objects.insert(path.to_string(), bytes.to_vec());
+    }
+
+    /// The object at the given path, if any.
+    pub fn object(&self, path: &str) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().objects.get(path).cloned()
+    }
+
+    /// The requests received so far.
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    fn start(collections: Option<HashSet<String>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed binding test server");
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State {
+            collections,
+            ..Default::default()
+        }));
+        let failures = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_state = state.clone();
+        let thread_failures = failures.clone();
+        let thread_stop = stop.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let state = thread_state.clone();
+                let failures = thread_failures.clone();
+                thread::spawn(move || {
+                    // Errors just mean that the client went away.
+                    let _ = Self::respond(stream, &state, &failures);
+                });
+            }
+        });
+
+        Self {
+            url: format!("http://{addr}/"),
+            addr,
+            state,
+            failures,
+            stop,
+        }
+    }
+
+    fn respond(
+        mut stream: TcpStream,
+        state: &Mutex<State>,
+        failures: &AtomicUsize,
+    ) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or("/");
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (target.to_string(), None),
+        };
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim().to_string();
+                match name.to_ascii_lowercase().as_str() {
+                    "content-length" => content_length = value.parse().unwrap_or_default(),
+                    "authorization" => authorization = Some(value),
+                    _ => {}
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let (status, body) = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(TestRequest {
+                method: method.clone(),
+                path: path.clone(),
+                query,
+                authorization,
+            });
+
+            let key = path.trim_matches('/').to_string();
+            let parent_exists = match &state.collections {
+                Some(collections) => key
+                    .rsplit_once('/')
+                    .map_or(true, |(parent, _)| collections.contains(parent)),
+                None => true,
+            };
+
+            let failed = failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            match method.as_str() {
+                _ if failed => ("500 Internal Server Error", vec![]),
+                "PUT" if !parent_exists => ("409 Conflict", vec![]),
+                "PUT" => {
+                    state.objects.insert(key, body);
+                    ("201 Created", vec![])
+                }
+                "MKCOL" if !parent_exists => ("409 Conflict", vec![]),
+                "MKCOL" => {
+                    let created = state
+                        .collections
+                        .as_mut()
+                        .is_some_and(|collections| collections.insert(key));
+                    if created {
+                        ("201 Created", vec![])
+                    } else {
+                        ("405 Method Not Allowed", vec![])
+                    }
+                }
+                "HEAD" | "GET" => match state.objects.get(&key) {
+                    // HEAD responses have no body, but advertise its length.
+                    Some(object) if method == "HEAD" => ("200 OK", vec![0; object.len()]),
+                    Some(object) => ("200 OK", object.clone()),
+                    None => ("404 Not Found", vec![]),
+                },
+                _ => ("405 Method Not Allowed", vec![]),
+            }
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        if method != "HEAD" {
+            stream.write_all(&body)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TestObjectServer {
+    fn drop(&mut self) {
+        // Wake up the accept loop so that it notices it should stop.
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.addr);
+    }
+}
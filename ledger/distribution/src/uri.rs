@@ -23,6 +23,22 @@ pub enum Destination {
         /// Local path.
         path: PathBuf,
     },
+    /// Write with HTTP PUT requests, e.g. to a WebDAV server or to Azure Blob
+    /// Storage with a SAS token.
+    Http {
+        /// URL under which objects are written. Its query string is added to
+        /// every request.
+        url: Url,
+    },
+    /// Write to Google Cloud Storage.
+    Gcs {
+        /// GCS bucket.
+        bucket: String,
+        /// Path inside the bucket.
+        path: PathBuf,
+        /// Endpoint to use instead of the public GCS one, e.g. an emulator.
+        endpoint: Option<Url>,
+    },
 }
 
 /// Wrapper for URL and associated Destination.
@@ -49,6 +65,12 @@ pub enum UriParseError {
 
     /// Invalid S3 region: {0}
     InvalidS3Region(ParseRegionError),
+
+    /// Missing GCS bucket
+    MissingBucket,
+
+    /// Invalid GCS endpoint: {0}
+    InvalidGcsEndpoint(url::ParseError),
 }
 
 impl std::error::Error for UriParseError {}
@@ -99,9 +121,79 @@ impl FromStr for Uri {
                 }
             }
 
+            "http" | "https" => {
+                if url.cannot_be_a_base() {
+                    return Err(UriParseError::MissingPath);
+                }
+
+                Destination::Http { url: url.clone() }
+            }
+
+            "gs" => {
+                let bucket = url.host_str().unwrap_or_default().to_string();
+                if bucket.is_empty() {
+                    return Err(UriParseError::MissingBucket);
+                }
+
+                let endpoint = url
+                    .query_pairs()
+                    .find(|(k, v)| k == "endpoint" && !v.is_empty())
+                    .map(|(_, v)| Url::parse(&v))
+                    .transpose()
+                    .map_err(UriParseError::InvalidGcsEndpoint)?;
+
+                Destination::Gcs {
+                    bucket,
+                    path: PathBuf::from(url.path().trim_matches('/')),
+                    endpoint,
+                }
+            }
+
             _ => return Err(UriParseError::UnknownScheme(url.scheme().to_string())),
         };
 
         Ok(Self { url, destination })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_http_and_gcs() {
+        let uri = Uri::from_str("https://dav.example.com/ledger?sig=abc").unwrap();
+        match uri.destination {
+            Destination::Http { url } => assert_eq!(url.query(), Some("sig=abc")),
+            destination => panic!("unexpected destination {destination:?}"),
+        }
+
+        let uri = Uri::from_str("gs://my_bucket/my_node/ledger").unwrap();
+        match uri.destination {
+            Destination::Gcs {
+                bucket,
+                path,
+                endpoint,
+            } => {
+                assert_eq!(bucket, "my_bucket");
+                assert_eq!(path, PathBuf::from("my_node/ledger"));
+                assert_eq!(endpoint, None);
+            }
+            destination => panic!("unexpected destination {destination:?}"),
+        }
+
+        let uri = Uri::from_str("gs://my_bucket?endpoint=http://127.0.0.1:4443").unwrap();
+        match uri.destination {
+            Destination::Gcs { path, endpoint, .. } => {
+                assert_eq!(path, PathBuf::new());
+                assert_eq!(endpoint.unwrap().as_str(), "http://127.0.0.1:4443/");
+            }
+            destination => panic!("unexpected destination {destination:?}"),
+        }
+
+        assert!(matches!(
+            Uri::from_str("gs:///ledger"),
+            Err(UriParseError::MissingBucket)
+        ));
+    }
+}
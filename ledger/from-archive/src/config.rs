@@ -4,6 +4,8 @@
 //! Configuration parameters to reconstitute the ledger
 
 use clap::Parser;
+use mc_crypto_keys::Ed25519Public;
+use mc_util_parse::parse_hex_key;
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -16,6 +18,11 @@ pub struct LedgerFromArchiveConfig {
     #[clap(long, default_value = "/tmp/ledgerdb", env = "MC_LEDGER_DB")]
     pub ledger_db: PathBuf,
 
+    /// (Optional) Path to a SQLite ledger file, written instead of the LMDB
+    /// ledger at `--ledger-db`. The file is created if it does not exist.
+    #[clap(long, env = "MC_LEDGER_SQLITE")]
+    pub ledger_sqlite: Option<PathBuf>,
+
     /// URLs to use to pull blocks.
     ///
     /// For example: https://s3-eu-central-1.amazonaws.com/mobilecoin.eu.development.chain/node1.alpha.development.mobilecoin.com/
@@ -28,6 +35,17 @@ pub struct LedgerFromArchiveConfig {
     )]
     pub tx_source_urls: Vec<String>,
 
+    /// Hex-encoded Ed25519 public keys trusted to sign the manifests of the
+    /// compressed archives published at the tx-source-urls. Compressed
+    /// archives are only used when at least one key is given.
+    #[clap(
+        long = "archive-manifest-signer",
+        value_parser = parse_hex_key::<Ed25519Public>,
+        use_value_delimiter = true,
+        env = "MC_ARCHIVE_MANIFEST_SIGNERS"
+    )]
+    pub archive_manifest_signers: Vec<Ed25519Public>,
+
     /// (Optional) Number of blocks to sync
     #[clap(long, env = "MC_NUM_BLOCKS")]
     pub num_blocks: Option<u64>,
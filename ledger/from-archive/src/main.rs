@@ -8,7 +8,7 @@ mod config;
 use clap::Parser;
 use config::LedgerFromArchiveConfig;
 use mc_common::logger::{create_app_logger, log, o, Logger};
-use mc_ledger_db::{create_ledger_in, Ledger, LedgerDB};
+use mc_ledger_db::{create_ledger_in, Ledger, LedgerDB, SqliteLedger};
 use mc_ledger_sync::ReqwestTransactionsFetcher;
 use std::path::Path;
 
@@ -18,12 +18,29 @@ fn main() {
 
     let config = LedgerFromArchiveConfig::parse();
 
-    let transactions_fetcher =
+    let mut transactions_fetcher =
         ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
             .expect("Failed creating ReqwestTransactionsFetcher");
+    transactions_fetcher.set_manifest_signers(&config.archive_manifest_signers);
 
-    let mut local_ledger = ledger_db(&logger, config.ledger_db, &transactions_fetcher);
+    match &config.ledger_sqlite {
+        Some(path) => {
+            let mut local_ledger = sqlite_ledger(&logger, path, &transactions_fetcher);
+            sync_ledger(&mut local_ledger, &config, &transactions_fetcher, &logger);
+        }
+        None => {
+            let mut local_ledger = ledger_db(&logger, &config.ledger_db, &transactions_fetcher);
+            sync_ledger(&mut local_ledger, &config, &transactions_fetcher, &logger);
+        }
+    }
+}
 
+fn sync_ledger(
+    local_ledger: &mut impl Ledger,
+    config: &LedgerFromArchiveConfig,
+    transactions_fetcher: &ReqwestTransactionsFetcher,
+    logger: &Logger,
+) {
     // Sync all blocks
     let mut block_index = local_ledger
         .num_blocks()
@@ -91,7 +108,41 @@ fn create_ledger_db(
     let ledger_path = ledger_path.as_ref();
     log::info!(logger, "Creating local ledger at {}", ledger_path.display());
     let mut local_ledger = create_ledger_in(ledger_path);
+    append_origin_block(logger, &mut local_ledger, transactions_fetcher);
+    local_ledger
+}
+
+fn sqlite_ledger(
+    logger: &Logger,
+    ledger_path: &Path,
+    transactions_fetcher: &ReqwestTransactionsFetcher,
+) -> SqliteLedger {
+    if ledger_path.exists() {
+        log::info!(
+            logger,
+            "Opening existing SQLite ledger at {}",
+            ledger_path.display()
+        );
+        return SqliteLedger::open(ledger_path).expect("Could not open existing SQLite ledger");
+    }
 
+    log::info!(
+        logger,
+        "Creating local SQLite ledger at {}",
+        ledger_path.display()
+    );
+    SqliteLedger::create(ledger_path).expect("Could not create SQLite ledger");
+    let mut local_ledger =
+        SqliteLedger::open(ledger_path).expect("Could not open new SQLite ledger");
+    append_origin_block(logger, &mut local_ledger, transactions_fetcher);
+    local_ledger
+}
+
+fn append_origin_block(
+    logger: &Logger,
+    local_ledger: &mut impl Ledger,
+    transactions_fetcher: &ReqwestTransactionsFetcher,
+) {
     // Sync Origin Block
     log::info!(logger, "Getting origin block");
     let block_data = transactions_fetcher
@@ -100,5 +151,4 @@ fn create_ledger_db(
     local_ledger
         .append_block_data(&block_data)
         .expect("Could not append origin block to ledger");
-    local_ledger
 }
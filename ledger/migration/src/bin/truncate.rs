@@ -0,0 +1,61 @@
+// Copyright (c) 2018-2022 The MobileCoin Foundation
+#![deny(missing_docs)]
+
+//! Roll back a LedgerDB by removing every block above a given index.
+//!
+//! This is meant for recovering from a bad block range ingested from a sync
+//! source, without having to resync the whole ledger. It refuses to run while
+//! any other process has the ledger open.
+
+use clap::Parser;
+use mc_common::logger::{create_app_logger, log, o};
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_migration::check_ledger_db_not_in_use;
+use std::{path::PathBuf, thread::sleep, time::Duration};
+
+/// Command line configuration
+#[derive(Clone, Debug, Parser)]
+#[clap(
+    name = "mc-ledger-truncate",
+    about = "Remove all blocks above a given index from a LedgerDB."
+)]
+pub struct Config {
+    /// Ledger DB path.
+    #[clap(long, env = "MC_LEDGER_DB")]
+    pub ledger_db: PathBuf,
+
+    /// Index of the last block to keep.
+    #[clap(long, env = "MC_BLOCK_INDEX")]
+    pub block_index: u64,
+}
+
+fn main() {
+    let _sentry_guard = mc_common::sentry::init();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+    mc_common::setup_panic_handler();
+
+    let config = Config::parse();
+
+    // Held until exit, see check_ledger_db_not_in_use.
+    let _lock_file =
+        check_ledger_db_not_in_use(&config.ledger_db).expect("Refusing to truncate ledger db");
+
+    let mut ledger_db = LedgerDB::open(&config.ledger_db).expect("Failed opening ledger db");
+    let num_blocks = ledger_db
+        .num_blocks()
+        .expect("Failed getting number of blocks");
+    log::info!(
+        logger,
+        "Truncating ledger db from {} blocks to {} blocks",
+        num_blocks,
+        config.block_index + 1
+    );
+
+    ledger_db
+        .truncate_to(config.block_index)
+        .expect("Failed truncating ledger db");
+    log::info!(logger, "Ledger db now ends at block {}", config.block_index);
+
+    // Give logger a moment to flush.
+    sleep(Duration::from_secs(1));
+}
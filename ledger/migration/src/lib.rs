@@ -1,7 +1,7 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
 //! Ledger migration: Perform updates of LedgerDB to accommodate for
-//! backward-incompatible changes.
+//! backward-incompatible changes, and other offline maintenance of ledgers.
 
 #![allow(clippy::inconsistent_digit_grouping)]
 
@@ -15,11 +15,16 @@ use mc_ledger_db::{
         TX_OUTS_BY_BLOCK_DB_NAME,
     },
     tx_out_store::TX_OUT_INDEX_BY_PUBLIC_KEY_DB_NAME,
-    u64_to_key_bytes, Error, MetadataStore, MintConfigStore, MintTxStore, TxOutStore,
+    u64_to_key_bytes, Error, Ledger, MetadataStore, MintConfigStore, MintTxStore, TxOutStore,
 };
 use mc_util_lmdb::MetadataStoreError;
 use mc_util_serial::decode;
-use std::path::Path;
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::Path,
+};
 
 pub fn migrate(ledger_db_path: impl AsRef<Path>, logger: &Logger) {
     // Open the LMDB database.
@@ -334,3 +339,195 @@ fn update_mint_stores_to_store_nonce_with_token_id(
     }
     Ok(db_txn.commit()?)
 }
+
+/// Copy the blocks of `src` that are missing from `dst`, converting a ledger
+/// between storage backends (e.g. from `LedgerDB` to `SqliteLedger`).
+///
+/// `dst` must be empty or a prefix of `src`, so an interrupted conversion can
+/// be resumed. Returns the number of blocks copied.
+pub fn convert_ledger(
+    src: &impl Ledger,
+    dst: &mut impl Ledger,
+    logger: &Logger,
+) -> Result<u64, Error> {
+    let num_blocks = src.num_blocks()?;
+    let first_block = dst.num_blocks()?;
+    if first_block > num_blocks {
+        return Err(Error::InvalidBlockIndex(first_block));
+    }
+    if first_block > 0 {
+        let last_block = dst.get_block(first_block - 1)?;
+        if last_block != src.get_block(first_block - 1)? {
+            return Err(Error::InvalidBlockID(last_block.id));
+        }
+        log::info!(
+            logger,
+            "Resuming ledger conversion at block {}",
+            first_block
+        );
+    }
+
+    let mut percents: u64 = 0;
+    for block_index in first_block..num_blocks {
+        dst.append_block_data(&src.get_block_data(block_index)?)?;
+
+        // Throttled logging.
+        let new_percents = block_index * 100 / num_blocks;
+        if new_percents != percents {
+            percents = new_percents;
+            log::info!(logger, "Converting ledger: {}% complete", percents);
+        }
+    }
+
+    Ok(num_blocks - first_block)
+}
+
+/// Name of the file locked by ledger maintenance tools while they run, next to
+/// the LMDB files of the ledger.
+pub const MAINTENANCE_LOCK_FILE_NAME: &str = "maintenance.lock";
+
+/// Check that no other process has the LMDB environment at `ledger_db_path`
+/// open, and that no other maintenance tool is working on it.
+///
+/// Every process that opens an LMDB environment holds a shared `fcntl` lock on
+/// the first byte of its `lock.mdb` file, so this fails if another process
+/// holds a lock that conflicts with an exclusive one. This only queries the
+/// lock: `fcntl` locks belong to the process, so a lock taken here would be
+/// downgraded by the one LMDB takes when the environment is opened afterwards.
+/// LMDB creates `lock.mdb` when the environment is opened, so a ledger without
+/// one is not in use.
+///
+/// Maintenance tools exclude each other with an exclusive `flock` on a
+/// separate [MAINTENANCE_LOCK_FILE_NAME] file, which does not interact with
+/// LMDB's locks. The returned file holds that lock, and must be kept open
+/// while working on the ledger.
+pub fn check_ledger_db_not_in_use(ledger_db_path: impl AsRef<Path>) -> io::Result<File> {
+    let ledger_db_path = ledger_db_path.as_ref();
+
+    let maintenance_lock_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(ledger_db_path.join(MAINTENANCE_LOCK_FILE_NAME))?;
+    // Safety: the descriptor is valid for the lifetime of
+    // `maintenance_lock_file`.
+    if unsafe {
+        libc::flock(
+            maintenance_lock_file.as_raw_fd(),
+            libc::LOCK_EX | libc::LOCK_NB,
+        )
+    } != 0
+    {
+        let err = io::Error::last_os_error();
+        return Err(match err.raw_os_error() {
+            Some(libc::EWOULDBLOCK) => io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "ledger db is locked by another maintenance tool",
+            ),
+            _ => err,
+        });
+    }
+
+    let lmdb_lock_file = match File::open(ledger_db_path.join("lock.mdb")) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(maintenance_lock_file),
+        Err(err) => return Err(err),
+    };
+
+    let mut lock = libc::flock {
+        l_type: libc::F_WRLCK as libc::c_short,
+        l_whence: libc::SEEK_SET as libc::c_short,
+        l_start: 0,
+        l_len: 1,
+        l_pid: 0,
+    };
+    // Safety: the descriptor is valid for the lifetime of `lmdb_lock_file`, and
+    // `lock` is a fully initialized flock struct.
+    if unsafe { libc::fcntl(lmdb_lock_file.as_raw_fd(), libc::F_GETLK, &mut lock) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if lock.l_type != libc::F_UNLCK as libc::c_short {
+        return Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            format!("ledger db is open in process {}", lock.l_pid),
+        ));
+    }
+
+    Ok(maintenance_lock_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_ledger_db::{
+        test_utils::{create_ledger, initialize_ledger},
+        LedgerDB, SqliteLedger,
+    };
+    use mc_transaction_core::BlockVersion;
+    use mc_util_test_helper::{get_seeded_rng, AccountKey};
+    use tempfile::TempDir;
+
+    #[test_with_logger]
+    fn convert_ledger_db_to_sqlite_and_back(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let mut ledger_db = create_ledger();
+        let account_key = AccountKey::random(&mut rng);
+        initialize_ledger(BlockVersion::MAX, &mut ledger_db, 5, &account_key, &mut rng);
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ledger.sqlite");
+        SqliteLedger::create(&path).unwrap();
+        let mut sqlite_ledger = SqliteLedger::open(&path).unwrap();
+        assert_eq!(
+            convert_ledger(&ledger_db, &mut sqlite_ledger, &logger).unwrap(),
+            5
+        );
+
+        let mut converted = create_ledger();
+        assert_eq!(
+            convert_ledger(&sqlite_ledger, &mut converted, &logger).unwrap(),
+            5
+        );
+        for block_index in 0..5 {
+            assert_eq!(
+                converted.get_block_data(block_index).unwrap(),
+                ledger_db.get_block_data(block_index).unwrap()
+            );
+        }
+        assert_eq!(
+            converted.get_root_tx_out_membership_element(),
+            ledger_db.get_root_tx_out_membership_element()
+        );
+
+        // Converting again is a no-op.
+        assert_eq!(
+            convert_ledger(&ledger_db, &mut sqlite_ledger, &logger).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn check_ledger_db_not_in_use_allows_unused_ledger() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // A ledger that was never opened has no lock.mdb.
+        drop(check_ledger_db_not_in_use(temp_dir.path()).unwrap());
+
+        LedgerDB::create(temp_dir.path()).unwrap();
+        let lock_file = check_ledger_db_not_in_use(temp_dir.path()).unwrap();
+        let ledger_db = LedgerDB::open(temp_dir.path()).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 0);
+
+        // Only one maintenance tool may work on the ledger at a time.
+        assert_eq!(
+            check_ledger_db_not_in_use(temp_dir.path())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::WouldBlock
+        );
+        drop(lock_file);
+        check_ledger_db_not_in_use(temp_dir.path()).unwrap();
+    }
+}
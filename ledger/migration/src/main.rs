@@ -2,11 +2,13 @@
 #![deny(missing_docs)]
 
 //! Ledger migration: Perform updates of LedgerDB to accommodate for
-//! backward-incompatible changes.
+//! backward-incompatible changes, and convert ledgers between storage
+//! backends.
 
 use clap::Parser;
-use mc_common::logger::{create_app_logger, o};
-use mc_ledger_migration::migrate;
+use mc_common::logger::{create_app_logger, log, o};
+use mc_ledger_db::{LedgerDB, SqliteLedger};
+use mc_ledger_migration::{convert_ledger, migrate};
 use std::{path::PathBuf, thread::sleep, time::Duration};
 
 /// Command line configuration
@@ -15,6 +17,16 @@ pub struct Config {
     /// Ledger DB path.
     #[clap(long, env = "MC_LEDGER_DB")]
     pub ledger_db: PathBuf,
+
+    /// After migrating, convert the LMDB ledger at `--ledger-db` into a SQLite
+    /// ledger at this path.
+    #[clap(long, env = "MC_TO_SQLITE", conflicts_with = "from_sqlite")]
+    pub to_sqlite: Option<PathBuf>,
+
+    /// Instead of migrating, convert the SQLite ledger at this path into an
+    /// LMDB ledger at `--ledger-db`.
+    #[clap(long, env = "MC_FROM_SQLITE")]
+    pub from_sqlite: Option<PathBuf>,
 }
 
 fn main() {
@@ -24,7 +36,31 @@ fn main() {
 
     let config = Config::parse();
 
-    migrate(config.ledger_db, &logger);
+    if let Some(from_sqlite) = config.from_sqlite {
+        let sqlite_ledger = SqliteLedger::open(&from_sqlite).expect("Failed opening SQLite ledger");
+        if !config.ledger_db.join("data.mdb").exists() {
+            std::fs::create_dir_all(&config.ledger_db).expect("Failed creating ledger db dir");
+            LedgerDB::create(&config.ledger_db).expect("Failed creating ledger db");
+        }
+        let mut ledger_db = LedgerDB::open(&config.ledger_db).expect("Failed opening ledger db");
+        let num_blocks = convert_ledger(&sqlite_ledger, &mut ledger_db, &logger)
+            .expect("Failed converting ledger");
+        log::info!(logger, "Converted {} blocks to LMDB", num_blocks);
+    } else {
+        migrate(&config.ledger_db, &logger);
+
+        if let Some(to_sqlite) = config.to_sqlite {
+            let ledger_db = LedgerDB::open(&config.ledger_db).expect("Failed opening ledger db");
+            if !to_sqlite.exists() {
+                SqliteLedger::create(&to_sqlite).expect("Failed creating SQLite ledger");
+            }
+            let mut sqlite_ledger =
+                SqliteLedger::open(&to_sqlite).expect("Failed opening SQLite ledger");
+            let num_blocks = convert_ledger(&ledger_db, &mut sqlite_ledger, &logger)
+                .expect("Failed converting ledger");
+            log::info!(logger, "Converted {} blocks to SQLite", num_blocks);
+        }
+    }
 
     // Give logger a moment to flush.
     sleep(Duration::from_secs(1));
@@ -0,0 +1,106 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! A utility to export and import LedgerDB snapshots.
+
+#![deny(missing_docs)]
+
+use clap::{Parser, Subcommand};
+use mc_common::logger::{create_app_logger, o};
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_snapshot::{export_snapshot, import_snapshot, parse_ed25519_public};
+use std::{path::PathBuf, process::exit};
+
+/// Configuration.
+#[derive(Debug, Parser)]
+#[clap(
+    name = "mc-ledger-snapshot",
+    about = "Export and import verified LedgerDB snapshots."
+)]
+struct Config {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+/// Supported commands.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write a snapshot of a ledger.
+    Export {
+        /// Path to [LedgerDB].
+        #[clap(long, env = "MC_LEDGER_DB")]
+        ledger_db: PathBuf,
+
+        /// Index of the last block to include; defaults to the latest block.
+        #[clap(long, env = "MC_BLOCK_INDEX")]
+        block_index: Option<u64>,
+
+        /// Directory to write the snapshot to.
+        #[clap(long, env = "MC_SNAPSHOT_DIR")]
+        snapshot_dir: PathBuf,
+    },
+
+    /// Verify a snapshot and create a new ledger from it.
+    Import {
+        /// Directory containing the snapshot.
+        #[clap(long, env = "MC_SNAPSHOT_DIR")]
+        snapshot_dir: PathBuf,
+
+        /// Path to the [LedgerDB] to create.
+        #[clap(long, env = "MC_LEDGER_DB")]
+        ledger_db: PathBuf,
+
+        /// Hex-encoded Ed25519 public keys of consensus nodes trusted to sign
+        /// block metadata. At least one is required.
+        #[clap(
+            long = "trusted-signer",
+            value_parser = parse_ed25519_public,
+            use_value_delimiter = true,
+            required = true,
+            env = "MC_TRUSTED_SIGNERS"
+        )]
+        trusted_signers: Vec<Ed25519Public>,
+    },
+}
+
+fn main() {
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+    mc_common::setup_panic_handler();
+
+    let config = Config::parse();
+
+    match config.command {
+        Command::Export {
+            ledger_db,
+            block_index,
+            snapshot_dir,
+        } => {
+            let ledger_db = LedgerDB::open(&ledger_db).expect("failed to open LedgerDB");
+            let block_index = match block_index {
+                Some(block_index) => block_index,
+                None => {
+                    let num_blocks = ledger_db.num_blocks().expect("failed to get num blocks");
+                    if num_blocks == 0 {
+                        eprintln!("The ledger is empty, there is nothing to export");
+                        exit(1);
+                    }
+                    num_blocks - 1
+                }
+            };
+            let manifest = export_snapshot(&ledger_db, block_index, snapshot_dir, &logger)
+                .expect("failed to export snapshot");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&manifest).expect("failed to serialize manifest")
+            );
+        }
+        Command::Import {
+            snapshot_dir,
+            ledger_db,
+            trusted_signers,
+        } => {
+            import_snapshot(snapshot_dir, ledger_db, &trusted_signers, &logger)
+                .expect("failed to import snapshot");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Error types.
+
+use displaydoc::Display;
+use mc_blockchain_types::BlockIndex;
+use mc_ledger_db::Error as LedgerError;
+use serde_json::Error as JsonError;
+use std::io::Error as IoError;
+
+/// Convenience wrapper for `Result` with [Error].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error enum.
+#[derive(Debug, Display)]
+pub enum Error {
+    /// Ledger: {0}
+    Ledger(LedgerError),
+    /// JSON: {0}
+    Json(JsonError),
+    /// IO: {0}
+    Io(IoError),
+    /// Unsupported snapshot format version: {0}
+    UnsupportedFormatVersion(u32),
+    /// Block index {0} is out of bounds, the ledger has {1} blocks
+    BlockIndexOutOfBounds(BlockIndex, u64),
+    /// A ledger already exists at {0}
+    DestinationExists(String),
+    /// Snapshot data checksum mismatch: expected {0}, got {1}
+    ChecksumMismatch(String, String),
+    /// Snapshot does not match its manifest: {0}
+    ManifestMismatch(String),
+    /// Invalid block {0}: {1}
+    InvalidBlock(BlockIndex, String),
+    /// TxOut Merkle root does not match the snapshot's TxOuts
+    MerkleRootMismatch,
+    /// Block {0} has no metadata, which is required to verify a snapshot
+    MissingBlockMetadata(BlockIndex),
+    /// Block metadata for block {0} has an invalid signature
+    InvalidMetadataSignature(BlockIndex),
+    /// Block metadata for block {0} is for a different block ID
+    MetadataBlockIdMismatch(BlockIndex),
+    /// Block metadata for block {0} was signed by an untrusted key
+    UntrustedSigner(BlockIndex),
+}
+
+impl From<LedgerError> for Error {
+    fn from(src: LedgerError) -> Self {
+        Self::Ledger(src)
+    }
+}
+
+impl From<JsonError> for Error {
+    fn from(src: JsonError) -> Self {
+        Self::Json(src)
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(src: IoError) -> Self {
+        Self::Io(src)
+    }
+}
@@ -0,0 +1,561 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Export and import of checksummed [LedgerDB] snapshots, used to bootstrap
+//! new nodes without replaying the whole chain from the network.
+//!
+//! A snapshot is a directory containing a `data.mdb` LedgerDB file with all
+//! blocks up to and including a given block index, and a `manifest.json`
+//! describing it. Importing a snapshot verifies the file checksum, the
+//! integrity of the block chain it contains, the TxOut Merkle root, and the
+//! signed [BlockMetadata](mc_blockchain_types::BlockMetadata) of the last
+//! block before moving the ledger into place.
+
+#![deny(missing_docs)]
+
+mod error;
+
+use mc_blockchain_types::{BlockID, BlockIndex};
+use mc_common::logger::{log, Logger};
+use mc_crypto_keys::Ed25519Public;
+use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
+use mc_transaction_core::membership_proofs::{hash_leaf, hash_nodes, NIL_HASH};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::Path,
+};
+
+pub use error::{Error, Result};
+
+/// The snapshot format version written by this crate.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Name of the LedgerDB file inside a snapshot directory.
+pub const SNAPSHOT_DATA_FILE: &str = "data.mdb";
+
+/// Name of the manifest file inside a snapshot directory.
+pub const SNAPSHOT_MANIFEST_FILE: &str = "manifest.json";
+
+/// Name of the staging directory used while importing a snapshot.
+const IMPORT_STAGING_DIR: &str = ".snapshot-import";
+
+/// Description of a snapshot, stored alongside its data.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SnapshotManifest {
+    /// Snapshot format version.
+    pub format_version: u32,
+
+    /// Index of the last block in the snapshot.
+    pub block_index: BlockIndex,
+
+    /// ID of the last block in the snapshot.
+    #[serde(with = "hex")]
+    pub block_id: [u8; 32],
+
+    /// Total number of TxOuts in the snapshot.
+    pub num_txos: u64,
+
+    /// Root hash of the TxOut Merkle tree.
+    #[serde(with = "hex")]
+    pub tx_out_merkle_root: [u8; 32],
+
+    /// SHA-256 checksum of the snapshot's `data.mdb` file.
+    #[serde(with = "hex")]
+    pub data_sha256: [u8; 32],
+}
+
+/// Returns true if the given directory looks like a snapshot.
+pub fn is_snapshot_dir(path: impl AsRef<Path>) -> bool {
+    path.as_ref().join(SNAPSHOT_MANIFEST_FILE).exists()
+}
+
+/// Write a snapshot of `ledger`, containing blocks `0..=block_index`, into
+/// `output_dir`.
+///
+/// The last block must have [BlockMetadata](mc_blockchain_types::BlockMetadata)
+/// so that the snapshot can be verified when it is imported.
+/// Since every block is re-validated as it is written, ledgers whose metadata
+/// was pruned can generally not be exported.
+pub fn export_snapshot(
+    ledger: &impl Ledger,
+    block_index: BlockIndex,
+    output_dir: impl AsRef<Path>,
+    logger: &Logger,
+) -> Result<SnapshotManifest> {
+    let output_dir = output_dir.as_ref();
+
+    let num_blocks = ledger.num_blocks()?;
+    if block_index >= num_blocks {
+        return Err(Error::BlockIndexOutOfBounds(block_index, num_blocks));
+    }
+    match ledger.get_block_metadata(block_index) {
+        Ok(_) => {}
+        Err(LedgerError::NotFound) | Err(LedgerError::Pruned) => {
+            return Err(Error::MissingBlockMetadata(block_index))
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let data_path = output_dir.join(SNAPSHOT_DATA_FILE);
+    if data_path.exists() {
+        return Err(Error::DestinationExists(output_dir.display().to_string()));
+    }
+
+    // Copy the blocks into a fresh LedgerDB. Since the ledger is append-only,
+    // this gives a consistent view of the requested blocks even if the source
+    // keeps growing, and the resulting file only contains what we need.
+    let (block_id, num_txos, tx_out_merkle_root) = {
+        LedgerDB::create(output_dir)?;
+        let mut snapshot_db = LedgerDB::open(output_dir)?;
+        for index in 0..=block_index {
+            let block_data = ledger.get_block_data(index)?;
+            snapshot_db.append_block_data(&block_data)?;
+
+            if index % 10_000 == 0 {
+                log::info!(logger, "Exported block {}/{}", index, block_index);
+            }
+        }
+
+        let last_block = snapshot_db.get_block(block_index)?;
+        let root_element = snapshot_db.get_root_tx_out_membership_element()?;
+        (last_block.id, snapshot_db.num_txos()?, root_element.hash.0)
+    };
+    let _ = fs::remove_file(output_dir.join("lock.mdb"));
+
+    let manifest = SnapshotManifest {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        block_index,
+        block_id: block_id.0,
+        num_txos,
+        tx_out_merkle_root,
+        data_sha256: sha256_file(&data_path)?,
+    };
+    let manifest_file = File::create(output_dir.join(SNAPSHOT_MANIFEST_FILE))?;
+    serde_json::to_writer_pretty(manifest_file, &manifest)?;
+
+    log::info!(
+        logger,
+        "Wrote snapshot of {} blocks to {}",
+        block_index + 1,
+        output_dir.display()
+    );
+    Ok(manifest)
+}
+
+/// Import the snapshot in `snapshot_dir` into a new LedgerDB at
+/// `ledger_db_path`.
+///
+/// The snapshot is copied to a staging directory and fully verified (see
+/// [verify_snapshot]) before it is moved into place, so a failed import never
+/// leaves a partial ledger behind. The last block's metadata must be signed by
+/// one of `trusted_signers`, so at least one must be given.
+pub fn import_snapshot(
+    snapshot_dir: impl AsRef<Path>,
+    ledger_db_path: impl AsRef<Path>,
+    trusted_signers: &[Ed25519Public],
+    logger: &Logger,
+) -> Result<SnapshotManifest> {
+    let snapshot_dir = snapshot_dir.as_ref();
+    let ledger_db_path = ledger_db_path.as_ref();
+
+    let manifest = read_manifest(snapshot_dir)?;
+    if trusted_signers.is_empty() {
+        return Err(Error::UntrustedSigner(manifest.block_index));
+    }
+
+    let ledger_db_file = ledger_db_path.join(SNAPSHOT_DATA_FILE);
+    if ledger_db_file.exists() {
+        return Err(Error::DestinationExists(
+            ledger_db_path.display().to_string(),
+        ));
+    }
+
+    let staging_dir = ledger_db_path.join(IMPORT_STAGING_DIR);
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)?;
+    }
+    fs::create_dir_all(&staging_dir)?;
+
+    let result = fs::copy(
+        snapshot_dir.join(SNAPSHOT_DATA_FILE),
+        staging_dir.join(SNAPSHOT_DATA_FILE),
+    )
+    .map_err(Error::from)
+    .and_then(|_| {
+        log::info!(
+            logger,
+            "Verifying snapshot of {} blocks",
+            manifest.block_index + 1
+        );
+        verify_snapshot(&staging_dir, &manifest, trusted_signers)
+    })
+    .and_then(|_| {
+        fs::rename(staging_dir.join(SNAPSHOT_DATA_FILE), &ledger_db_file)?;
+        Ok(())
+    });
+    fs::remove_dir_all(&staging_dir)?;
+    result?;
+
+    log::info!(
+        logger,
+        "Imported snapshot of {} blocks into {}",
+        manifest.block_index + 1,
+        ledger_db_path.display()
+    );
+    Ok(manifest)
+}
+
+/// Read the manifest of the snapshot in `snapshot_dir`.
+pub fn read_manifest(snapshot_dir: impl AsRef<Path>) -> Result<SnapshotManifest> {
+    let file = File::open(snapshot_dir.as_ref().join(SNAPSHOT_MANIFEST_FILE))?;
+    let manifest: SnapshotManifest = serde_json::from_reader(BufReader::new(file))?;
+    if manifest.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(Error::UnsupportedFormatVersion(manifest.format_version));
+    }
+    Ok(manifest)
+}
+
+/// Verify the snapshot data in `snapshot_dir` against its manifest.
+///
+/// This checks that:
+/// * the `data.mdb` checksum matches the manifest,
+/// * every block has a valid ID, links to its parent and matches its contents,
+///   up to the last block ID in the manifest,
+/// * the TxOut Merkle tree matches the TxOuts in those blocks,
+/// * the last block has metadata with a valid signature over its block ID,
+///   signed by one of `trusted_signers`. Nothing is trusted if that is empty.
+pub fn verify_snapshot(
+    snapshot_dir: impl AsRef<Path>,
+    manifest: &SnapshotManifest,
+    trusted_signers: &[Ed25519Public],
+) -> Result<()> {
+    let snapshot_dir = snapshot_dir.as_ref();
+
+    let checksum = sha256_file(snapshot_dir.join(SNAPSHOT_DATA_FILE))?;
+    if checksum != manifest.data_sha256 {
+        return Err(Error::ChecksumMismatch(
+            hex::encode(manifest.data_sha256),
+            hex::encode(checksum),
+        ));
+    }
+
+    let ledger = LedgerDB::open(snapshot_dir)?;
+    verify_ledger(&ledger, manifest, trusted_signers)
+}
+
+/// Verify the contents of a snapshot ledger against its manifest.
+fn verify_ledger(
+    ledger: &impl Ledger,
+    manifest: &SnapshotManifest,
+    trusted_signers: &[Ed25519Public],
+) -> Result<()> {
+    let num_blocks = ledger.num_blocks()?;
+    if num_blocks != manifest.block_index + 1 {
+        return Err(Error::ManifestMismatch(format!(
+            "expected {} blocks, found {}",
+            manifest.block_index + 1,
+            num_blocks
+        )));
+    }
+
+    // Walk the chain, collecting TxOut leaf hashes along the way.
+    let mut leaf_hashes = Vec::new();
+    let mut parent_id = BlockID::default();
+    for block_index in 0..num_blocks {
+        let block = ledger.get_block(block_index)?;
+        let contents = ledger.get_block_contents(block_index)?;
+
+        let invalid = |reason: &str| Err(Error::InvalidBlock(block_index, reason.to_string()));
+        if block.index != block_index {
+            return invalid("wrong index");
+        }
+        if !block.is_block_id_valid() {
+            return invalid("invalid block ID");
+        }
+        if block.parent_id != parent_id {
+            return invalid("wrong parent block ID");
+        }
+        if block.contents_hash != contents.hash() {
+            return invalid("contents do not match contents hash");
+        }
+        leaf_hashes.extend(contents.outputs.iter().map(hash_leaf));
+        if block.cumulative_txo_count != leaf_hashes.len() as u64 {
+            return invalid("wrong cumulative TxOut count");
+        }
+
+        parent_id = block.id;
+    }
+
+    if parent_id.0 != manifest.block_id {
+        return Err(Error::ManifestMismatch("last block ID".to_string()));
+    }
+    if ledger.num_txos()? != leaf_hashes.len() as u64
+        || manifest.num_txos != leaf_hashes.len() as u64
+    {
+        return Err(Error::ManifestMismatch("number of TxOuts".to_string()));
+    }
+
+    // The Merkle tree served by the ledger must match the TxOuts committed to by
+    // the block IDs.
+    let tx_out_merkle_root = compute_merkle_root(leaf_hashes);
+    if tx_out_merkle_root != manifest.tx_out_merkle_root
+        || ledger.get_root_tx_out_membership_element()?.hash.0 != tx_out_merkle_root
+    {
+        return Err(Error::MerkleRootMismatch);
+    }
+
+    // Finally, the last block ID must be vouched for by a consensus node.
+    let metadata = match ledger.get_block_metadata(manifest.block_index) {
+        Ok(metadata) => metadata,
+        Err(LedgerError::NotFound) | Err(LedgerError::Pruned) => {
+            return Err(Error::MissingBlockMetadata(manifest.block_index))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if metadata.verify().is_err() {
+        return Err(Error::InvalidMetadataSignature(manifest.block_index));
+    }
+    if metadata.contents().block_id() != &parent_id {
+        return Err(Error::MetadataBlockIdMismatch(manifest.block_index));
+    }
+    if !trusted_signers.contains(metadata.node_key()) {
+        return Err(Error::UntrustedSigner(manifest.block_index));
+    }
+
+    Ok(())
+}
+
+/// Parse a hex-encoded Ed25519 public key, e.g. a trusted block signer given on
+/// the command line.
+pub fn parse_ed25519_public(src: &str) -> std::result::Result<Ed25519Public, String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Invalid hex '{src}': {err}"))?;
+    Ed25519Public::try_from(&bytes[..])
+        .map_err(|err| format!("Invalid Ed25519 public key '{src}': {err}"))
+}
+
+/// Compute the root of the TxOut Merkle tree over the given leaf hashes, the
+/// same way [mc_ledger_db::TxOutStore] does.
+fn compute_merkle_root(mut hashes: Vec<[u8; 32]>) -> [u8; 32] {
+    if hashes.is_empty() {
+        return *NIL_HASH;
+    }
+
+    // Subtrees without any TxOuts hash to the nil hash, so a missing right
+    // sibling is always replaced with it.
+    while hashes.len() > 1 {
+        hashes = hashes
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_nodes(left, right),
+                [left] => hash_nodes(left, &NIL_HASH),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    hashes[0]
+}
+
+/// Compute the SHA-256 checksum of a file.
+fn sha256_file(path: impl AsRef<Path>) -> Result<[u8; 32]> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_blockchain_test_utils::get_blocks;
+    use mc_blockchain_types::BlockVersion;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_ledger_db::test_utils::create_ledger;
+    use mc_util_from_random::FromRandom;
+    use mc_util_test_helper::get_seeded_rng;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn create_source_ledger(num_blocks: usize) -> LedgerDB {
+        let mut ledger = create_ledger();
+        let blocks = get_blocks(
+            BlockVersion::MAX,
+            num_blocks,
+            2,
+            1,
+            2,
+            1 << 20,
+            None,
+            &mut get_seeded_rng(),
+        );
+        for block_data in &blocks {
+            ledger.append_block_data(block_data).unwrap();
+        }
+        ledger
+    }
+
+    /// The key that signed the metadata of the given block of `source`.
+    fn signer(source: &LedgerDB, block_index: BlockIndex) -> Ed25519Public {
+        *source.get_block_metadata(block_index).unwrap().node_key()
+    }
+
+    #[test_with_logger]
+    fn export_and_import_snapshot(logger: Logger) {
+        let source = create_source_ledger(10);
+        let snapshot_dir = TempDir::new().unwrap();
+        let ledger_dir = TempDir::new().unwrap();
+
+        let manifest = export_snapshot(&source, 6, snapshot_dir.path(), &logger).unwrap();
+        assert_eq!(manifest.block_index, 6);
+        assert_eq!(manifest.block_id, source.get_block(6).unwrap().id.0);
+        assert_eq!(manifest, read_manifest(snapshot_dir.path()).unwrap());
+        assert!(is_snapshot_dir(snapshot_dir.path()));
+
+        let signers = [signer(&source, 6)];
+        let imported =
+            import_snapshot(snapshot_dir.path(), ledger_dir.path(), &signers, &logger).unwrap();
+        assert_eq!(imported, manifest);
+
+        let ledger = LedgerDB::open(ledger_dir.path()).unwrap();
+        assert_eq!(ledger.num_blocks().unwrap(), 7);
+        for block_index in 0..7 {
+            assert_eq!(
+                ledger.get_block_data(block_index).unwrap(),
+                source.get_block_data(block_index).unwrap()
+            );
+        }
+        assert_eq!(
+            ledger.get_root_tx_out_membership_element().unwrap().hash.0,
+            manifest.tx_out_merkle_root
+        );
+        assert!(!ledger_dir.path().join(IMPORT_STAGING_DIR).exists());
+
+        // Importing again over an existing ledger is refused.
+        assert!(matches!(
+            import_snapshot(snapshot_dir.path(), ledger_dir.path(), &signers, &logger),
+            Err(Error::DestinationExists(_))
+        ));
+    }
+
+    #[test_with_logger]
+    fn export_snapshot_out_of_bounds(logger: Logger) {
+        let source = create_source_ledger(3);
+        let snapshot_dir = TempDir::new().unwrap();
+
+        assert!(matches!(
+            export_snapshot(&source, 3, snapshot_dir.path(), &logger),
+            Err(Error::BlockIndexOutOfBounds(3, 3))
+        ));
+    }
+
+    #[test_with_logger]
+    fn export_snapshot_requires_metadata(logger: Logger) {
+        let mut source = create_source_ledger(4);
+        source.enable_pruning(1).unwrap();
+        let snapshot_dir = TempDir::new().unwrap();
+
+        assert!(matches!(
+            export_snapshot(&source, 2, snapshot_dir.path(), &logger),
+            Err(Error::MissingBlockMetadata(2))
+        ));
+    }
+
+    #[test_with_logger]
+    fn import_rejects_corrupted_data(logger: Logger) {
+        let source = create_source_ledger(4);
+        let snapshot_dir = TempDir::new().unwrap();
+        let ledger_dir = TempDir::new().unwrap();
+        export_snapshot(&source, 3, snapshot_dir.path(), &logger).unwrap();
+
+        let mut data = fs::OpenOptions::new()
+            .append(true)
+            .open(snapshot_dir.path().join(SNAPSHOT_DATA_FILE))
+            .unwrap();
+        data.write_all(b"garbage").unwrap();
+
+        let signers = [signer(&source, 3)];
+        assert!(matches!(
+            import_snapshot(snapshot_dir.path(), ledger_dir.path(), &signers, &logger),
+            Err(Error::ChecksumMismatch(_, _))
+        ));
+        assert!(!ledger_dir.path().join(SNAPSHOT_DATA_FILE).exists());
+    }
+
+    #[test_with_logger]
+    fn import_rejects_tampered_manifest(logger: Logger) {
+        let source = create_source_ledger(4);
+        let snapshot_dir = TempDir::new().unwrap();
+        let ledger_dir = TempDir::new().unwrap();
+        let mut manifest = export_snapshot(&source, 3, snapshot_dir.path(), &logger).unwrap();
+
+        manifest.tx_out_merkle_root = [1u8; 32];
+        let manifest_file = File::create(snapshot_dir.path().join(SNAPSHOT_MANIFEST_FILE)).unwrap();
+        serde_json::to_writer(manifest_file, &manifest).unwrap();
+
+        let signers = [signer(&source, 3)];
+        assert!(matches!(
+            import_snapshot(snapshot_dir.path(), ledger_dir.path(), &signers, &logger),
+            Err(Error::MerkleRootMismatch)
+        ));
+    }
+
+    #[test_with_logger]
+    fn import_requires_trusted_signer(logger: Logger) {
+        let source = create_source_ledger(4);
+        let snapshot_dir = TempDir::new().unwrap();
+        export_snapshot(&source, 3, snapshot_dir.path(), &logger).unwrap();
+
+        // Without trusted signers, nothing is trusted.
+        let ledger_dir = TempDir::new().unwrap();
+        assert!(matches!(
+            import_snapshot(snapshot_dir.path(), ledger_dir.path(), &[], &logger),
+            Err(Error::UntrustedSigner(3))
+        ));
+        assert!(!ledger_dir.path().join(SNAPSHOT_DATA_FILE).exists());
+
+        let untrusted = Ed25519Pair::from_random(&mut get_seeded_rng()).public_key();
+        let ledger_dir = TempDir::new().unwrap();
+        assert!(matches!(
+            import_snapshot(
+                snapshot_dir.path(),
+                ledger_dir.path(),
+                &[untrusted],
+                &logger
+            ),
+            Err(Error::UntrustedSigner(3))
+        ));
+
+        let ledger_dir = TempDir::new().unwrap();
+        import_snapshot(
+            snapshot_dir.path(),
+            ledger_dir.path(),
+            &[signer(&source, 3)],
+            &logger,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_ed25519_public_roundtrip() {
+        let key = Ed25519Pair::from_random(&mut get_seeded_rng()).public_key();
+        assert_eq!(parse_ed25519_public(&hex::encode(key)).unwrap(), key);
+        assert!(parse_ed25519_public("not hex").is_err());
+        assert!(parse_ed25519_public("0102").is_err());
+    }
+
+    #[test]
+    fn compute_merkle_root_matches_ledger() {
+        let source = create_source_ledger(5);
+        let leaf_hashes = (0..source.num_txos().unwrap())
+            .map(|index| hash_leaf(&source.get_tx_out_by_index(index).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            compute_merkle_root(leaf_hashes),
+            source.get_root_tx_out_membership_element().unwrap().hash.0
+        );
+        assert_eq!(compute_merkle_root(vec![]), *NIL_HASH);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Serves the data of blocks from a local ledger, so that other clients can
+//! sync from this one.
+
+use grpcio::{RpcContext, UnarySink};
+use mc_blockchain_types::{BlockData, BlockIndex};
+use mc_common::logger::{log, Logger};
+use mc_consensus_api::{
+    blockchain::ArchiveBlocks, consensus_common::BlocksRequest, consensus_common_grpc::BlockDataApi,
+};
+use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
+use mc_util_grpc::{rpc_logger, send_result};
+use std::cmp;
+
+/// Default maximum number of blocks returned by a single request.
+pub const DEFAULT_MAX_BLOCKS_PER_REQUEST: u32 = 10;
+
+/// A source of block data served by [BlockDataApiService].
+pub trait BlockDataProvider: Clone + Send + Sync + 'static {
+    /// Get the data of a block, or None if it is not available.
+    fn block_data(&self, block_index: BlockIndex, logger: &Logger) -> Option<BlockData>;
+}
+
+impl BlockDataProvider for LedgerDB {
+    fn block_data(&self, block_index: BlockIndex, logger: &Logger) -> Option<BlockData> {
+        match self.get_block_data(block_index) {
+            Ok(block_data) => Some(block_data),
+            // Blocks past the end of the ledger, or below a pruned range, are
+            // simply not available.
+            Err(LedgerError::NotFound) | Err(LedgerError::Pruned) => None,
+            Err(err) => {
+                log::error!(logger, "Error getting block {}: {}", block_index, err);
+                None
+            }
+        }
+    }
+}
+
+/// Serves the `BlockDataAPI` from a [BlockDataProvider].
+#[derive(Clone)]
+pub struct BlockDataApiService<P: BlockDataProvider> {
+    /// Block data provider.
+    provider: P,
+
+    /// Maximum number of blocks returned by a single request.
+    max_blocks_per_request: u32,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl<P: BlockDataProvider> BlockDataApiService<P> {
+    /// Create a service serving the blocks of `provider`.
+    pub fn new(provider: P, logger: Logger) -> Self {
+        Self {
+            provider,
+            max_blocks_per_request: DEFAULT_MAX_BLOCKS_PER_REQUEST,
+            logger,
+        }
+    }
+
+    /// Set the maximum number of blocks returned by a single request.
+    pub fn set_max_blocks_per_request(&mut self, max_blocks_per_request: u32) {
+        self.max_blocks_per_request = max_blocks_per_request;
+    }
+
+    /// Returns the data of the blocks in [offset, offset + limit), stopping at
+    /// the first block that is not available.
+    fn get_block_data_helper(&self, offset: u64, limit: u32, logger: &Logger) -> ArchiveBlocks {
+        let limit = cmp::min(limit, self.max_blocks_per_request);
+        let blocks_data = (offset..offset.saturating_add(limit as u64))
+            .map_while(|block_index| self.provider.block_data(block_index, logger))
+            .collect::<Vec<_>>();
+        ArchiveBlocks::from(blocks_data.as_slice())
+    }
+}
+
+impl<P: BlockDataProvider> BlockDataApi for BlockDataApiService<P> {
+    fn get_block_data(
+        &mut self,
+        ctx: RpcContext,
+        request: BlocksRequest,
+        sink: UnarySink<ArchiveBlocks>,
+    ) {
+        mc_common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            log::trace!(
+                logger,
+                "Received BlocksRequest for offset {} and limit {}",
+                request.offset,
+                request.limit
+            );
+
+            let resp = self.get_block_data_helper(request.offset, request.limit, logger);
+            send_result(ctx, sink, Ok(resp), logger);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_ledger_db::test_utils::{create_ledger, initialize_ledger};
+    use mc_transaction_core::{AccountKey, BlockVersion};
+    use mc_util_test_helper::{RngType, SeedableRng};
+
+    #[test_with_logger]
+    fn get_block_data_is_capped(logger: Logger) {
+        let mut rng = RngType::from_seed([0u8; 32]);
+        let mut ledger = create_ledger();
+        let account_key = AccountKey::random(&mut rng);
+        initialize_ledger(BlockVersion::MAX, &mut ledger, 5, &account_key, &mut rng);
+
+        let mut service = BlockDataApiService::new(ledger.clone(), logger.clone());
+        service.set_max_blocks_per_request(3);
+
+        let blocks_data =
+            Vec::<BlockData>::try_from(&service.get_block_data_helper(1, 10, &logger)).unwrap();
+        let expected = (1..4)
+            .map(|block_index| ledger.get_block_data(block_index).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(blocks_data, expected);
+
+        // Requests past the end of the ledger return what is available.
+        let blocks_data =
+            Vec::<BlockData>::try_from(&service.get_block_data_helper(4, 3, &logger)).unwrap();
+        assert_eq!(blocks_data, vec![ledger.get_block_data(4).unwrap()]);
+        assert!(service
+            .get_block_data_helper(5, 3, &logger)
+            .get_blocks()
+            .is_empty());
+    }
+}
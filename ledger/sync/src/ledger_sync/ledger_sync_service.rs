@@ -896,7 +896,10 @@ pub fn identify_safe_blocks<L: Ledger>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{test_utils::MockTransactionsFetcher, SCPNetworkState};
+    use crate::{
+        test_utils::{write_ledger_archive, MockTransactionsFetcher, TestHttpServer},
+        ReqwestTransactionsFetcher, SCPNetworkState,
+    };
     use mc_blockchain_test_utils::make_block_metadata;
     use mc_blockchain_types::BlockMetadata;
     use mc_common::{logger::test_with_logger, NodeID};
@@ -904,6 +907,7 @@ mod tests {
     use mc_ledger_db::test_utils::{get_mock_ledger, get_test_ledger_blocks};
     use mc_peers_test_utils::{test_node_id, test_peer_uri, MockPeerConnection};
     use mc_util_test_helper::get_seeded_rng;
+    use tempfile::TempDir;
 
     #[test_with_logger]
     // A node with the trivial quorum set should never be "behind".
@@ -1581,6 +1585,65 @@ mod tests {
         }
     }
 
+    #[test_with_logger]
+    // Blocks fetched by a pipelined `ReqwestTransactionsFetcher` from several
+    // sources should be verified and appended to the ledger in order.
+    fn test_sync_from_pipelined_reqwest_fetcher(logger: Logger) {
+        let num_blocks = 40;
+        let blocks = get_test_ledger_blocks(num_blocks);
+        let archive_dir = TempDir::new().unwrap();
+        write_ledger_archive(archive_dir.path(), &blocks, &[10]);
+        let servers = [
+            TestHttpServer::new(archive_dir.path(), Duration::from_millis(5)),
+            TestHttpServer::new(archive_dir.path(), Duration::from_millis(20)),
+        ];
+
+        let mut transactions_fetcher = ReqwestTransactionsFetcher::new(
+            servers.iter().map(|server| server.url.clone()).collect(),
+            logger.clone(),
+        )
+        .unwrap();
+        transactions_fetcher.set_merged_blocks_bucket_sizes(&[10]);
+        transactions_fetcher.set_max_in_flight_requests(8);
+
+        let ledger = get_mock_ledger(1);
+        let conn_manager = ConnectionManager::<MockPeerConnection>::new(vec![], logger.clone());
+        let mut sync_service =
+            LedgerSyncService::new(ledger, conn_manager, transactions_fetcher, logger.clone());
+
+        let potentially_safe_blocks: Vec<Block> = blocks[1..]
+            .iter()
+            .map(|block_data| block_data.block().clone())
+            .collect();
+        let block_index_to_opt_data = get_block_contents(
+            sync_service.transactions_fetcher.clone(),
+            &[],
+            &potentially_safe_blocks,
+            Duration::from_secs(30),
+            &logger,
+        );
+        let blocks_data: Vec<BlockData> = block_index_to_opt_data
+            .into_values()
+            .map(|data_opt| data_opt.expect("all blocks should be populated"))
+            .collect();
+
+        let safe_blocks = identify_safe_blocks(&sync_service.ledger, &blocks_data, &logger);
+        assert_eq!(safe_blocks.len(), num_blocks - 1);
+        sync_service
+            .append_safe_blocks(&safe_blocks)
+            .expect("failed to append blocks");
+
+        assert_eq!(sync_service.ledger.num_blocks().unwrap(), num_blocks as u64);
+        for expected_block in blocks {
+            let block_data = sync_service
+                .ledger
+                .get_block_data(expected_block.block().index)
+                .unwrap();
+            assert_eq!(block_data.block(), expected_block.block());
+            assert_eq!(block_data.contents(), expected_block.contents());
+        }
+    }
+
     #[test_with_logger]
     fn test_append_safe_blocks_default_metadata_provider(logger: Logger) {
         let ledger = get_mock_ledger(10);
@@ -1,21 +1,26 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
+mod block_data_api_service;
 mod ledger_sync;
 mod metadata_provider;
 mod network_state;
+mod peer_transactions_fetcher;
 mod reqwest_transactions_fetcher;
+mod source_selector;
 mod transactions_fetcher_trait;
 
 #[cfg(any(test, feature = "test_utils"))]
 pub mod test_utils;
 
 pub use crate::{
+    block_data_api_service::{BlockDataApiService, BlockDataProvider},
     ledger_sync::{
         identify_safe_blocks, LedgerSync, LedgerSyncError, LedgerSyncService,
         LedgerSyncServiceThread, MockLedgerSync,
     },
     metadata_provider::{BlockMetadataProvider, PassThroughMetadataProvider},
     network_state::{NetworkState, PollingNetworkState, SCPNetworkState},
+    peer_transactions_fetcher::{PeerBlockError, PeerTransactionsFetcher},
     reqwest_transactions_fetcher::{ReqwestTransactionsFetcher, ReqwestTransactionsFetcherError},
     transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
 };
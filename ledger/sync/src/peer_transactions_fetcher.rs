@@ -0,0 +1,409 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Implementation of the `TransactionsFetcher` trait that fetches block data
+//! from other full clients serving the `BlockDataAPI`, e.g. mobilecoind, and
+//! falls back to another fetcher when they cannot provide it.
+//!
+//! Peers are not trusted: a block they serve is only used if it matches the
+//! block that the quorum agreed on, and carries `BlockMetadata` signed by one
+//! of the trusted consensus nodes for that block.
+
+use crate::{
+    source_selector::{FetchOutcome, SourceSelector},
+    transactions_fetcher_trait::TransactionsFetcher,
+};
+use displaydoc::Display;
+use grpcio::{CallOption, ChannelBuilder, Environment, Error as GrpcError};
+use mc_api::ConversionError;
+use mc_blockchain_types::{Block, BlockData};
+use mc_common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use mc_consensus_api::{
+    consensus_common::BlocksRequest, consensus_common_grpc::BlockDataApiClient,
+};
+use mc_crypto_keys::{Ed25519Public, SignatureError};
+use mc_util_grpc::ConnectionUriGrpcioChannel;
+use mc_util_uri::ConnectionUri;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for a peer to respond.
+const PEER_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reasons for not using a block from a peer.
+#[derive(Debug, Display)]
+pub enum PeerBlockError {
+    /// gRPC error: {0}
+    Grpc(GrpcError),
+
+    /// Conversion error: {0}
+    Conversion(ConversionError),
+
+    /// The peer does not have the block
+    NotFound,
+
+    /// The block does not match the one the quorum agreed on
+    BlockMismatch,
+
+    /// The block has no metadata
+    MissingMetadata,
+
+    /// Invalid metadata signature: {0}
+    InvalidMetadataSignature(SignatureError),
+
+    /// The metadata is for another block
+    MetadataMismatch,
+
+    /// The metadata is signed by an untrusted key: {0:?}
+    UntrustedSigner(Ed25519Public),
+}
+
+impl From<GrpcError> for PeerBlockError {
+    fn from(src: GrpcError) -> Self {
+        Self::Grpc(src)
+    }
+}
+
+impl From<ConversionError> for PeerBlockError {
+    fn from(src: ConversionError) -> Self {
+        Self::Conversion(src)
+    }
+}
+
+/// A peer serving the `BlockDataAPI`.
+#[derive(Clone)]
+struct Peer {
+    /// The peer's URI, for logging.
+    uri: String,
+
+    /// gRPC client.
+    client: BlockDataApiClient,
+}
+
+/// Fetches block data from peers, falling back to another fetcher.
+#[derive(Clone)]
+pub struct PeerTransactionsFetcher<F: TransactionsFetcher + Clone> {
+    /// The peers to fetch blocks from.
+    peers: Vec<Peer>,
+
+    /// Keys of the consensus nodes whose block metadata is trusted.
+    trusted_signers: Arc<Vec<Ed25519Public>>,
+
+    /// Picks the peer to fetch each block from.
+    source_selector: Arc<SourceSelector>,
+
+    /// Fetcher used when no peer can provide a block.
+    fallback: F,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl<F: TransactionsFetcher + Clone> PeerTransactionsFetcher<F> {
+    /// Create a fetcher for the given peers.
+    ///
+    /// # Arguments
+    /// * `peer_uris` - URIs of the peers serving the `BlockDataAPI`.
+    /// * `trusted_signers` - Keys of the consensus nodes whose block metadata
+    ///   is trusted. Blocks without metadata signed by one of them are fetched
+    ///   from `fallback`.
+    /// * `fallback` - Fetcher used when no peer can provide a block.
+    /// * `env` - gRPC environment.
+    /// * `logger` - Logger.
+    pub fn new(
+        peer_uris: &[impl ConnectionUri],
+        trusted_signers: Vec<Ed25519Public>,
+        fallback: F,
+        env: Arc<Environment>,
+        logger: Logger,
+    ) -> Self {
+        let peers = peer_uris
+            .iter()
+            .map(|uri| {
+                let channel = ChannelBuilder::default_channel_builder(env.clone())
+                    .connect_to_uri(uri, &logger);
+                Peer {
+                    uri: uri.to_string(),
+                    client: BlockDataApiClient::new(channel),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            source_selector: Arc::new(SourceSelector::new(peers.len())),
+            peers,
+            trusted_signers: Arc::new(trusted_signers),
+            fallback,
+            logger,
+        }
+    }
+
+    /// The fetcher used when no peer can provide a block.
+    pub fn fallback(&self) -> &F {
+        &self.fallback
+    }
+
+    /// Fetch a block from a peer, and check that it can be trusted.
+    fn fetch_from_peer(&self, peer: &Peer, block: &Block) -> Result<BlockData, PeerBlockError> {
+        let mut request = BlocksRequest::new();
+        request.set_offset(block.index);
+        request.set_limit(1);
+        let response = peer.client.get_block_data_opt(
+            &request,
+            CallOption::default().timeout(PEER_REQUEST_TIMEOUT),
+        )?;
+
+        let block_data = Vec::<BlockData>::try_from(&response)?
+            .into_iter()
+            .next()
+            .ok_or(PeerBlockError::NotFound)?;
+        self.verify(block, &block_data)?;
+        Ok(block_data)
+    }
+
+    /// Check that `block_data` is the data of `block`, with metadata signed by
+    /// a trusted consensus node.
+    fn verify(&self, block: &Block, block_data: &BlockData) -> Result<(), PeerBlockError> {
+        if block_data.block() != block || block_data.contents().hash() != block.contents_hash {
+            return Err(PeerBlockError::BlockMismatch);
+        }
+
+        let metadata = block_data
+            .metadata()
+            .ok_or(PeerBlockError::MissingMetadata)?;
+        metadata
+            .verify()
+            .map_err(PeerBlockError::InvalidMetadataSignature)?;
+        if metadata.contents().block_id() != &block.id {
+            return Err(PeerBlockError::MetadataMismatch);
+        }
+        if !self.trusted_signers.contains(metadata.node_key()) {
+            return Err(PeerBlockError::UntrustedSigner(*metadata.node_key()));
+        }
+        Ok(())
+    }
+}
+
+impl<F: TransactionsFetcher + Clone> TransactionsFetcher for PeerTransactionsFetcher<F> {
+    type Error = F::Error;
+
+    fn get_block_data(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<BlockData, Self::Error> {
+        // Try each peer at most once, best first.
+        let mut tried = Vec::new();
+        while let Some(peer_index) = self.source_selector.pick_excluding(&tried) {
+            tried.push(peer_index);
+            let peer = &self.peers[peer_index];
+
+            self.source_selector.start(peer_index);
+            let start = Instant::now();
+            let result = self.fetch_from_peer(peer, block);
+            self.source_selector.finish(
+                peer_index,
+                match &result {
+                    Ok(_) => FetchOutcome::Success(start.elapsed()),
+                    // Old blocks have no metadata, which is not the peer's fault.
+                    Err(PeerBlockError::MissingMetadata) => FetchOutcome::NotFound,
+                    // The quorum agreed on the block, so a peer that does not have
+                    // it is lagging behind, and the next block should be fetched
+                    // elsewhere.
+                    Err(_) => FetchOutcome::Failure,
+                },
+            );
+
+            match result {
+                Ok(block_data) => return Ok(block_data),
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "Not using block {} from peer {}: {}",
+                        block.index,
+                        peer.uri,
+                        err
+                    );
+                    // No peer has metadata for blocks that predate it.
+                    if matches!(err, PeerBlockError::MissingMetadata) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.fallback.get_block_data(safe_responder_ids, block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::MockTransactionsFetcher, BlockDataApiService};
+    use grpcio::{EnvBuilder, Server, ServerBuilder, ServerCredentials};
+    use mc_blockchain_test_utils::{get_blocks, make_block_metadata_contents};
+    use mc_blockchain_types::{BlockMetadata, BlockVersion};
+    use mc_common::logger::test_with_logger;
+    use mc_consensus_api::consensus_common_grpc;
+    use mc_crypto_keys::{Ed25519Pair, Ed25519Private};
+    use mc_ledger_db::{
+        test_utils::{create_ledger, MockLedger},
+        Ledger, LedgerDB,
+    };
+    use mc_util_test_helper::{RngType, SeedableRng};
+    use mc_util_uri::ConsensusClientUri;
+    use std::str::FromStr;
+
+    /// A ledger whose blocks carry metadata signed by `signer`.
+    fn signed_ledger(num_blocks: usize, signer: &Ed25519Pair) -> (LedgerDB, Vec<BlockData>) {
+        let mut rng = RngType::from_seed([1u8; 32]);
+        let blocks = get_blocks(BlockVersion::MAX, num_blocks, 2, 1, 2, 42, None, &mut rng)
+            .into_iter()
+            .map(|block_data| {
+                let contents =
+                    make_block_metadata_contents(block_data.block().id.clone(), &mut rng);
+                let metadata = BlockMetadata::from_contents_and_keypair(contents, signer).unwrap();
+                BlockData::new(
+                    block_data.block().clone(),
+                    block_data.contents().clone(),
+                    block_data.signature().cloned(),
+                    Some(metadata),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut ledger = create_ledger();
+        for block_data in &blocks {
+            ledger.append_block_data(block_data).unwrap();
+        }
+        (ledger, blocks)
+    }
+
+    fn start_peer(ledger: LedgerDB, logger: &Logger) -> (Server, ConsensusClientUri) {
+        let env = Arc::new(EnvBuilder::new().build());
+        let service = consensus_common_grpc::create_block_data_api(BlockDataApiService::new(
+            ledger,
+            logger.clone(),
+        ));
+        let mut server = ServerBuilder::new(env)
+            .register_service(service)
+            .build()
+            .unwrap();
+        let port = server
+            .add_listening_port("127.0.0.1:0", ServerCredentials::insecure())
+            .unwrap();
+        server.start();
+        let uri =
+            ConsensusClientUri::from_str(&format!("insecure-mc://127.0.0.1:{port}/")).unwrap();
+        (server, uri)
+    }
+
+    fn test_signer(seed: u8) -> Ed25519Pair {
+        Ed25519Pair::from(Ed25519Private::try_from(&[seed; 32][..]).unwrap())
+    }
+
+    fn peer_fetcher(
+        uri: &ConsensusClientUri,
+        trusted_signers: Vec<Ed25519Public>,
+        fallback: MockLedger,
+        logger: &Logger,
+    ) -> PeerTransactionsFetcher<MockTransactionsFetcher<MockLedger>> {
+        PeerTransactionsFetcher::new(
+            &[uri.clone()],
+            trusted_signers,
+            MockTransactionsFetcher::new(fallback),
+            Arc::new(EnvBuilder::new().build()),
+            logger.clone(),
+        )
+    }
+
+    #[test_with_logger]
+    fn fetches_verified_blocks_from_peer(logger: Logger) {
+        let signer = test_signer(3);
+        let (ledger, blocks) = signed_ledger(3, &signer);
+        let (_server, uri) = start_peer(ledger, &logger);
+
+        // The fallback has nothing, so the blocks must come from the peer.
+        let fetcher = peer_fetcher(
+            &uri,
+            vec![signer.public_key()],
+            MockLedger::default(),
+            &logger,
+        );
+        for block_data in &blocks {
+            assert_eq!(
+                &fetcher.get_block_data(&[], block_data.block()).unwrap(),
+                block_data
+            );
+        }
+    }
+
+    #[test_with_logger]
+    fn tries_other_peers_when_a_peer_lags_behind(logger: Logger) {
+        let signer = test_signer(3);
+        let (ledger, blocks) = signed_ledger(3, &signer);
+        let (_server, uri) = start_peer(ledger, &logger);
+
+        // A peer that has not synced any block yet.
+        let (_lagging_server, lagging_uri) = start_peer(create_ledger(), &logger);
+
+        // The fallback has nothing, so the blocks must come from the peers.
+        let fetcher = PeerTransactionsFetcher::new(
+            &[lagging_uri, uri],
+            vec![signer.public_key()],
+            MockTransactionsFetcher::new(MockLedger::default()),
+            Arc::new(EnvBuilder::new().build()),
+            logger.clone(),
+        );
+        for block_data in &blocks {
+            assert_eq!(
+                &fetcher.get_block_data(&[], block_data.block()).unwrap(),
+                block_data
+            );
+        }
+
+        // The lagging peer is no longer preferred.
+        assert_eq!(fetcher.source_selector.pick(), 1);
+    }
+
+    #[test_with_logger]
+    fn falls_back_on_untrusted_or_mismatched_blocks(logger: Logger) {
+        let signer = test_signer(3);
+        let (ledger, blocks) = signed_ledger(3, &signer);
+        let (_server, uri) = start_peer(ledger, &logger);
+
+        // The fallback serves the same blocks, without metadata.
+        let mut fallback = MockLedger::default();
+        for block_data in &blocks {
+            fallback
+                .append_block(block_data.block(), block_data.contents(), None, None)
+                .unwrap();
+        }
+
+        // Metadata signed by a key that is not trusted is not used.
+        let untrusted = test_signer(4);
+        let fetcher = peer_fetcher(
+            &uri,
+            vec![untrusted.public_key()],
+            fallback.clone(),
+            &logger,
+        );
+        let block_data = fetcher.get_block_data(&[], blocks[1].block()).unwrap();
+        assert_eq!(block_data.metadata(), None);
+
+        // A block that does not match the one the quorum agreed on is not used.
+        let fetcher = peer_fetcher(&uri, vec![signer.public_key()], fallback, &logger);
+        let mut block = blocks[2].block().clone();
+        block.cumulative_txo_count += 1;
+        let block_data = fetcher.get_block_data(&[], &block).unwrap();
+        assert_eq!(block_data.metadata(), None);
+        assert_eq!(
+            fetcher.get_block_data(&[], blocks[2].block()).unwrap(),
+            blocks[2]
+        );
+    }
+}
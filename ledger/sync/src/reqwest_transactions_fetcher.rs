@@ -3,24 +3,53 @@
 //! Implementation of the `TransactionsFetcher` trait that fetches transactions
 //! data over http(s) using the `reqwest` library. It can be used, for example,
 //! to get transaction data from S3.
+//!
+//! By default blocks are fetched one request at a time, as they are asked for.
+//! With [ReqwestTransactionsFetcher::set_max_in_flight_requests], a pool of
+//! worker threads instead keeps several requests in flight, fetching the blocks
+//! following the requested ones ahead of time and spreading the requests
+//! across the sources according to their latency and error rate.
+//!
+//! Sources may also publish a compressed archive: zstd-compressed buckets of
+//! blocks, listed in a signed manifest at the root of the source. When a
+//! source has a manifest, blocks it lists are fetched a bucket at a time, and
+//! each bucket is checked against the manifest before being decoded. Blocks
+//! that are not in the manifest yet are fetched from the individual and merged
+//! block objects, as for sources without a manifest. Manifests are only used
+//! once the keys trusted to sign them are set with
+//! [ReqwestTransactionsFetcher::set_manifest_signers].
 
-use crate::transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
+use crate::{
+    source_selector::{FetchOutcome, SourceSelector},
+    transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
+};
+use crossbeam_channel::{Receiver, Sender};
 use displaydoc::Display;
-use mc_api::{block_num_to_s3block_path, blockchain, merged_block_num_to_s3block_path};
-use mc_blockchain_types::{Block, BlockData, BlockIndex};
+use mc_api::{
+    block_num_to_s3block_path, blockchain, compressed_bucket_path,
+    merged_block_num_to_s3block_path, ARCHIVE_MANIFEST_PATH,
+};
+use mc_blockchain_types::{
+    ArchiveManifest, ArchiveManifestBucket, ArchiveManifestContents, Block, BlockData, BlockIndex,
+};
 use mc_common::{
     logger::{log, Logger},
     lru::LruCache,
     ResponderId,
 };
+use mc_crypto_keys::Ed25519Public;
 use protobuf::Message;
-use reqwest::Error as ReqwestError;
+use reqwest::{Error as ReqwestError, StatusCode};
 use std::{
+    collections::HashSet,
     fs,
+    io::ErrorKind,
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc, Mutex,
+        Arc, Condvar, Mutex,
     },
+    thread,
+    time::{Duration, Instant},
 };
 use url::Url;
 
@@ -35,6 +64,14 @@ pub const DEFAULT_MERGED_BLOCKS_BUCKET_SIZES: &[u64] = &[10000, 1000, 100];
 /// Maximum number of pre-fetched blocks to keep in cache.
 pub const MAX_PREFETCHED_BLOCKS: usize = 10000;
 
+/// How long to wait for a block that is being prefetched before requesting it
+/// directly.
+const PREFETCH_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to check a source for a newer manifest, when asked for blocks
+/// that its manifest does not list.
+const MANIFEST_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Display)]
 pub enum ReqwestTransactionsFetcherError {
     /// Url parse error on {0}: {1}
@@ -62,6 +99,23 @@ impl From<ReqwestError> for ReqwestTransactionsFetcherError {
     }
 }
 
+impl ReqwestTransactionsFetcherError {
+    /// Whether the source does not have the requested object, which is
+    /// expected for blocks that have not been published yet.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            // S3 responds with 403 rather than 404 for missing objects when
+            // listing the bucket is not allowed.
+            Self::ReqwestError(_, err) => matches!(
+                err.status(),
+                Some(StatusCode::NOT_FOUND | StatusCode::FORBIDDEN)
+            ),
+            Self::IO(_, err) => err.kind() == ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+}
+
 impl TransactionFetcherError for ReqwestTransactionsFetcherError {}
 
 #[derive(Clone)]
@@ -85,6 +139,25 @@ pub struct ReqwestTransactionsFetcher {
     /// Merged blocks bucket sizes to attempt fetching.
     merged_blocks_bucket_sizes: Vec<u64>,
 
+    /// Latency, error rate and load of each source.
+    source_selector: Arc<SourceSelector>,
+
+    /// Whether to pick sources using `source_selector` rather than in turn.
+    select_sources_by_latency: bool,
+
+    /// Workers fetching blocks ahead of time, when pipelining is enabled.
+    prefetcher: Option<Arc<Prefetcher>>,
+
+    /// The latest manifest of each source's compressed archive.
+    manifests: Arc<Vec<Mutex<Option<SourceManifest>>>>,
+
+    /// Keys trusted to sign manifests. When empty, manifests are not used.
+    manifest_signers: Vec<Ed25519Public>,
+
+    /// Compressed buckets being fetched, by first block index, so that each
+    /// bucket is only fetched once at a time.
+    bucket_fetches: Arc<(Mutex<HashSet<BlockIndex>>, Condvar)>,
+
     /// Number of successful cache hits when attempting ot get block data.
     /// Used for debugging purposes.
     hits: Arc<AtomicU64>,
@@ -123,13 +196,20 @@ impl ReqwestTransactionsFetcher {
             })
             .collect();
 
+        let source_urls = source_urls?;
         Ok(Self {
-            source_urls: source_urls?,
+            source_selector: Arc::new(SourceSelector::new(source_urls.len())),
+            manifests: Arc::new(source_urls.iter().map(|_| Mutex::new(None)).collect()),
+            source_urls,
             client,
             logger,
             source_index_counter: Arc::new(AtomicU64::new(0)),
             blocks_cache: Arc::new(Mutex::new(LruCache::new(MAX_PREFETCHED_BLOCKS))),
             merged_blocks_bucket_sizes: DEFAULT_MERGED_BLOCKS_BUCKET_SIZES.to_vec(),
+            select_sources_by_latency: false,
+            prefetcher: None,
+            manifest_signers: Vec::new(),
+            bucket_fetches: Default::default(),
             hits: Arc::new(AtomicU64::new(0)),
             misses: Arc::new(AtomicU64::new(0)),
         })
@@ -137,6 +217,55 @@ impl ReqwestTransactionsFetcher {
 
     pub fn set_merged_blocks_bucket_sizes(&mut self, bucket_sizes: &[u64]) {
         self.merged_blocks_bucket_sizes = bucket_sizes.to_vec();
+        self.restart_prefetcher();
+    }
+
+    /// Use the manifests of compressed archives signed by one of the given
+    /// keys. Sources whose manifest is signed by another key are treated as
+    /// having no manifest, and no manifest is used until signers are set.
+    pub fn set_manifest_signers(&mut self, signers: &[Ed25519Public]) {
+        self.manifest_signers = signers.to_vec();
+        self.restart_prefetcher();
+    }
+
+    /// Restart the prefetch workers, if any, so that they pick up changes to
+    /// the configuration.
+    fn restart_prefetcher(&mut self) {
+        if let Some(max_in_flight) = self.prefetcher.as_ref().map(|p| p.max_in_flight) {
+            self.set_max_in_flight_requests(max_in_flight);
+        }
+    }
+
+    /// Keep up to `max_in_flight` requests in flight, fetching the blocks
+    /// following the requested ones ahead of time, and pick the source of each
+    /// request by its latency, error rate and load rather than in turn.
+    /// Passing 0 goes back to fetching blocks one at a time.
+    pub fn set_max_in_flight_requests(&mut self, max_in_flight: usize) {
+        // Dropping the previous prefetcher stops its workers once every clone of
+        // this fetcher has been dropped or updated.
+        self.prefetcher = None;
+        self.select_sources_by_latency = max_in_flight > 0;
+        if max_in_flight == 0 {
+            return;
+        }
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let state = Arc::new(PrefetchState::default());
+        for worker_num in 0..max_in_flight {
+            let worker = self.clone();
+            let receiver = receiver.clone();
+            let state = state.clone();
+            thread::Builder::new()
+                .name(format!("ReqwestFetcher:{worker_num}"))
+                .spawn(move || worker.run_prefetch_worker(receiver, state))
+                .expect("Failed spawning prefetch worker");
+        }
+
+        self.prefetcher = Some(Arc::new(Prefetcher {
+            max_in_flight,
+            sender,
+            state,
+        }));
     }
 
     pub fn block_from_url(&self, url: &Url) -> Result<BlockData, ReqwestTransactionsFetcherError> {
@@ -171,6 +300,23 @@ impl ReqwestTransactionsFetcher {
         &self,
         url: &Url,
     ) -> Result<M, ReqwestTransactionsFetcherError> {
+        let bytes = self.fetch_bytes(url)?;
+        Self::parse_protobuf_object(url, &bytes)
+    }
+
+    fn parse_protobuf_object<M: Message>(
+        url: &Url,
+        bytes: &[u8],
+    ) -> Result<M, ReqwestTransactionsFetcherError> {
+        M::parse_from_bytes(bytes).map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                url.to_string(),
+                format!("protobuf parse failed: {err:?}"),
+            )
+        })
+    }
+
+    fn fetch_bytes(&self, url: &Url) -> Result<Vec<u8>, ReqwestTransactionsFetcherError> {
         // Special treatment for file:// to read from a local directory.
         let bytes: Vec<u8> = if url.scheme() == "file" {
             let path = &url[url::Position::BeforeHost..url::Position::AfterPath];
@@ -178,23 +324,21 @@ impl ReqwestTransactionsFetcher {
                 .map_err(|err| ReqwestTransactionsFetcherError::IO(path.to_string(), err))?
                 .to_vec()
         } else {
-            let mut response = self.client.get(url.as_str()).send().map_err(|err| {
-                ReqwestTransactionsFetcherError::ReqwestError(url.to_string(), err)
-            })?;
+            let mut response = self
+                .client
+                .get(url.as_str())
+                .send()
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| {
+                    ReqwestTransactionsFetcherError::ReqwestError(url.to_string(), err)
+                })?;
 
             let mut bytes = Vec::new();
             response.copy_to(&mut bytes)?;
             bytes
         };
 
-        let obj = M::parse_from_bytes(&bytes).map_err(|err| {
-            ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                format!("protobuf parse failed: {err:?}"),
-            )
-        })?;
-
-        Ok(obj)
+        Ok(bytes)
     }
 
     fn get_cached_block_data(
@@ -261,12 +405,310 @@ impl ReqwestTransactionsFetcher {
             return Ok(cached_block_data);
         }
 
+        // Have the prefetch workers fetch this block and the ones after it, and wait
+        // for this one.
+        if let Some(prefetcher) = &self.prefetcher {
+            prefetcher.prefetch(block_index, |index| {
+                self.blocks_cache
+                    .lock()
+                    .expect("mutex poisoned")
+                    .contains(&index)
+            });
+            if prefetcher.wait(block_index, PREFETCH_WAIT_TIMEOUT) {
+                if let Some(cached_block_data) =
+                    self.get_cached_block_data(block_index, expected_block)
+                {
+                    return Ok(cached_block_data);
+                }
+            }
+        }
+
         // Get the source to fetch from.
-        let source_index_counter =
-            self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
-        let source_url = &self.source_urls[source_index_counter % self.source_urls.len()];
+        let source_index = self.next_source_index();
+
+        // Try and fetch a compressed bucket or merged block if we stand a chance of
+        // finding one.
+        if let Some(blocks_data) = self.fetch_bucket(source_index, block_index) {
+            self.cache_blocks(blocks_data);
+
+            // Supposedly we have the block we asked for in the cache now.
+            if let Some(cached_block_data) = self.get_cached_block_data(block_index, expected_block)
+            {
+                return Ok(cached_block_data);
+            }
+        }
+
+        let block_data = self.fetch_block(source_index, block_index)?;
+
+        // If the caller is expecting a specific block, check that we received data for
+        // the block they asked for
+        if let Some(expected_block) = expected_block {
+            if expected_block != block_data.block() {
+                return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                    self.source_urls[source_index].to_string(),
+                    "block data mismatch".to_string(),
+                ));
+            }
+        }
+
+        let hits = self.hits.load(Ordering::SeqCst);
+        let misses = self.misses.fetch_add(1, Ordering::SeqCst);
+        log::trace!(
+            self.logger,
+            "Cache miss while getting block #{} (total hits/misses: {}/{})",
+            block_data.block().index,
+            hits,
+            misses
+        );
+
+        // Got what we wanted!
+        Ok(block_data)
+    }
+
+    /// Pick the source to send the next request to.
+    fn next_source_index(&self) -> usize {
+        if self.select_sources_by_latency {
+            // Requests are counted against the source as they are sent, see
+            // `fetch_from_source`.
+            self.source_selector.pick()
+        } else {
+            let source_index_counter =
+                self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
+            source_index_counter % self.source_urls.len()
+        }
+    }
+
+    /// Fetch an object from a source, recording how the source fared.
+    fn fetch_from_source<T>(
+        &self,
+        source_index: usize,
+        url: &Url,
+        fetch: impl FnOnce(&Self, &Url) -> Result<T, ReqwestTransactionsFetcherError>,
+    ) -> Result<T, ReqwestTransactionsFetcherError> {
+        self.source_selector.start(source_index);
+        let start = Instant::now();
+        let result = fetch(self, url);
+        let outcome = match &result {
+            Ok(_) => FetchOutcome::Success(start.elapsed()),
+            Err(err) if err.is_not_found() => FetchOutcome::NotFound,
+            Err(_) => FetchOutcome::Failure,
+        };
+        self.source_selector.finish(source_index, outcome);
+        result
+    }
+
+    /// Fetch the compressed bucket containing `block_index`, or the largest
+    /// merged block starting at it, if there is one. When another thread is
+    /// already fetching the bucket, this waits for it and returns no blocks,
+    /// since they are cached by the other thread.
+    fn fetch_bucket(&self, source_index: usize, block_index: BlockIndex) -> Option<Vec<BlockData>> {
+        match self.fetch_compressed_bucket(source_index, block_index) {
+            Ok(Some(blocks_data)) => return Some(blocks_data),
+            Ok(None) => {}
+            Err(err) => log::warn!(
+                self.logger,
+                "Failed fetching the compressed bucket of block #{}: {}",
+                block_index,
+                err
+            ),
+        }
+
+        self.fetch_merged_blocks(source_index, block_index)
+    }
+
+    /// Fetch the compressed bucket containing `block_index`, if the source's
+    /// manifest lists one.
+    fn fetch_compressed_bucket(
+        &self,
+        source_index: usize,
+        block_index: BlockIndex,
+    ) -> Result<Option<Vec<BlockData>>, ReqwestTransactionsFetcherError> {
+        let Some(manifest) = self.source_manifest(source_index, block_index) else {
+            return Ok(None);
+        };
+        let Some(bucket) = manifest.bucket_containing(block_index) else {
+            return Ok(None);
+        };
+        let first_block_index = bucket.first_block_index();
+
+        let (lock, condvar) = &*self.bucket_fetches;
+        {
+            let mut bucket_fetches = lock.lock().expect("mutex poisoned");
+            if bucket_fetches.contains(&first_block_index) {
+                let _bucket_fetches = condvar
+                    .wait_timeout_while(bucket_fetches, PREFETCH_WAIT_TIMEOUT, |fetches| {
+                        fetches.contains(&first_block_index)
+                    })
+                    .expect("mutex poisoned");
+                return Ok(Some(Vec::new()));
+            }
+            bucket_fetches.insert(first_block_index);
+        }
+
+        let filename = compressed_bucket_path(bucket.num_blocks(), first_block_index)
+            .into_os_string()
+            .into_string()
+            .unwrap();
+        let result = self.source_urls[source_index]
+            .join(&filename)
+            .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename, e))
+            .and_then(|url| {
+                log::debug!(
+                    self.logger,
+                    "Attempting to fetch compressed bucket for #{} from {}",
+                    block_index,
+                    url
+                );
+                self.fetch_from_source(source_index, &url, |fetcher, url| {
+                    fetcher.bucket_from_url(url, bucket)
+                })
+            });
+
+        lock.lock()
+            .expect("mutex poisoned")
+            .remove(&first_block_index);
+        condvar.notify_all();
+
+        result.map(Some)
+    }
+
+    /// Fetches a compressed bucket from a given url, checking it against its
+    /// manifest entry before decoding it.
+    pub fn bucket_from_url(
+        &self,
+        url: &Url,
+        bucket: &ArchiveManifestBucket,
+    ) -> Result<Vec<BlockData>, ReqwestTransactionsFetcherError> {
+        let invalid = |reason: String| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(url.to_string(), reason)
+        };
+
+        let compressed = self.fetch_bytes(url)?;
+        if !bucket.matches_object(&compressed) {
+            return Err(invalid("bucket hash mismatch".to_string()));
+        }
+
+        let bytes = zstd::decode_all(compressed.as_slice())
+            .map_err(|err| invalid(format!("zstd decompression failed: {err}")))?;
+        let archive_blocks: blockchain::ArchiveBlocks = Self::parse_protobuf_object(url, &bytes)?;
+        let blocks_data =
+            Vec::<BlockData>::try_from(&archive_blocks).map_err(|err| invalid(err.to_string()))?;
+
+        let expected_indices =
+            bucket.first_block_index()..bucket.first_block_index() + bucket.num_blocks();
+        if !blocks_data
+            .iter()
+            .map(|block_data| block_data.block().index)
+            .eq(expected_indices)
+        {
+            return Err(invalid("unexpected blocks in bucket".to_string()));
+        }
+        if blocks_data.last().map(|block_data| &block_data.block().id)
+            != Some(bucket.last_block_id())
+        {
+            return Err(invalid("last block id mismatch".to_string()));
+        }
+
+        Ok(blocks_data)
+    }
+
+    /// Get the buckets listed by a source's manifest, fetching the manifest if
+    /// it might have changed since it was last fetched.
+    fn source_manifest(
+        &self,
+        source_index: usize,
+        block_index: BlockIndex,
+    ) -> Option<Arc<ArchiveManifestContents>> {
+        // Without trusted signers there is no way to tell a genuine manifest
+        // from one published by whoever controls the source.
+        if self.manifest_signers.is_empty() {
+            return None;
+        }
+
+        let mut source_manifest = self.manifests[source_index].lock().expect("mutex poisoned");
+
+        // Only check for a newer manifest when asked for a block that the current
+        // one does not list, and not too often.
+        let is_current = source_manifest.as_ref().is_some_and(|source_manifest| {
+            source_manifest.fetched_at.elapsed() < MANIFEST_REFRESH_INTERVAL
+                || source_manifest
+                    .contents
+                    .as_ref()
+                    .is_some_and(|contents| contents.next_block_index() > block_index)
+        });
+        if !is_current {
+            let previous_contents = source_manifest
+                .take()
+                .and_then(|source_manifest| source_manifest.contents);
+            let contents = match self.fetch_manifest(source_index) {
+                Ok(contents) => contents.map(Arc::new),
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "Failed fetching manifest from {}: {}",
+                        self.source_urls[source_index],
+                        err
+                    );
+                    previous_contents
+                }
+            };
+            *source_manifest = Some(SourceManifest {
+                contents,
+                fetched_at: Instant::now(),
+            });
+        }
 
-        // Try and fetch a merged block if we stand a chance of finding one.
+        source_manifest
+            .as_ref()
+            .and_then(|source_manifest| source_manifest.contents.clone())
+    }
+
+    /// Fetch and verify the manifest of a source. Returns None if the source
+    /// has no manifest, or if it is not signed by a trusted key.
+    fn fetch_manifest(
+        &self,
+        source_index: usize,
+    ) -> Result<Option<ArchiveManifestContents>, ReqwestTransactionsFetcherError> {
+        let url = self.source_urls[source_index]
+            .join(ARCHIVE_MANIFEST_PATH)
+            .map_err(|e| {
+                ReqwestTransactionsFetcherError::UrlParse(ARCHIVE_MANIFEST_PATH.to_string(), e)
+            })?;
+
+        let proto: blockchain::ArchiveManifest =
+            match self.fetch_from_source(source_index, &url, Self::fetch_protobuf_object) {
+                Ok(proto) => proto,
+                Err(err) if err.is_not_found() => return Ok(None),
+                Err(err) => return Err(err),
+            };
+
+        // This verifies the signature.
+        let manifest = ArchiveManifest::try_from(&proto).map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                url.to_string(),
+                format!("invalid manifest: {err}"),
+            )
+        })?;
+        if !self.manifest_signers.contains(manifest.signer()) {
+            log::warn!(
+                self.logger,
+                "Ignoring manifest {} signed by untrusted key {}",
+                url,
+                manifest.signer()
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(manifest.contents().clone()))
+    }
+
+    /// Fetch the largest merged block starting at `block_index`, if there is
+    /// one.
+    fn fetch_merged_blocks(
+        &self,
+        source_index: usize,
+        block_index: BlockIndex,
+    ) -> Option<Vec<BlockData>> {
         for bucket in self.merged_blocks_bucket_sizes.iter() {
             if block_index % bucket == 0 {
                 log::debug!(
@@ -279,11 +721,14 @@ impl ReqwestTransactionsFetcher {
                     .into_os_string()
                     .into_string()
                     .unwrap();
-                let url = source_url
-                    .join(&filename)
-                    .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename.clone(), e))?;
+                let url = match self.source_urls[source_index].join(&filename) {
+                    Ok(url) => url,
+                    Err(_) => continue,
+                };
 
-                if let Ok(blocks_data) = self.blocks_from_url(&url) {
+                if let Ok(blocks_data) =
+                    self.fetch_from_source(source_index, &url, Self::blocks_from_url)
+                {
                     log::debug!(
                         self.logger,
                         "Got a merged block for #{} (bucket size {}): {} entries @ {:?}",
@@ -292,30 +737,26 @@ impl ReqwestTransactionsFetcher {
                         blocks_data.len(),
                         std::thread::current().name()
                     );
-
-                    {
-                        let mut blocks_cache = self.blocks_cache.lock().expect("mutex poisoned");
-                        for block_data in blocks_data.into_iter() {
-                            blocks_cache.put(block_data.block().index, block_data);
-                        }
-                    }
-
-                    // Supposedly we have the block we asked for in the cache now.
-                    if let Some(cached_block_data) =
-                        self.get_cached_block_data(block_index, expected_block)
-                    {
-                        return Ok(cached_block_data);
-                    }
+                    return Some(blocks_data);
                 }
             }
         }
 
+        None
+    }
+
+    /// Fetch a single block.
+    fn fetch_block(
+        &self,
+        source_index: usize,
+        block_index: BlockIndex,
+    ) -> Result<BlockData, ReqwestTransactionsFetcherError> {
         // Construct URL for the block we are trying to fetch.
         let filename = block_num_to_s3block_path(block_index)
             .into_os_string()
             .into_string()
             .unwrap();
-        let url = source_url
+        let url = self.source_urls[source_index]
             .join(&filename)
             .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename, e))?;
 
@@ -327,34 +768,176 @@ impl ReqwestTransactionsFetcher {
             url
         );
 
-        let block_data = self.block_from_url(&url)?;
+        self.fetch_from_source(source_index, &url, Self::block_from_url)
+    }
 
-        // If the caller is expecting a specific block, check that we received data for
-        // the block they asked for
-        if let Some(expected_block) = expected_block {
-            if expected_block != block_data.block() {
-                return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
-                    "block data mismatch".to_string(),
-                ));
+    fn cache_blocks(&self, blocks_data: Vec<BlockData>) {
+        let mut blocks_cache = self.blocks_cache.lock().expect("mutex poisoned");
+        for block_data in blocks_data.into_iter() {
+            blocks_cache.put(block_data.block().index, block_data);
+        }
+    }
+
+    /// Fetch the blocks queued by [Prefetcher::prefetch] into the cache, until
+    /// the prefetcher is dropped.
+    fn run_prefetch_worker(self, receiver: Receiver<BlockIndex>, state: Arc<PrefetchState>) {
+        for block_index in receiver.iter() {
+            let source_index = self.next_source_index();
+            let result = match self.fetch_bucket(source_index, block_index) {
+                Some(blocks_data) => Ok(blocks_data),
+                None => self
+                    .fetch_block(source_index, block_index)
+                    .map(|block_data| vec![block_data]),
+            };
+
+            let mut not_found = false;
+            match result {
+                Ok(blocks_data) => {
+                    // Only keep blocks whose contents match, so that a bad source does not
+                    // spoil the cache.
+                    let (valid, invalid): (Vec<_>, Vec<_>) =
+                        blocks_data.into_iter().partition(|block_data| {
+                            block_data.contents().hash() == block_data.block().contents_hash
+                        });
+                    for block_data in invalid {
+                        log::warn!(
+                            self.logger,
+                            "Discarding block #{} from {}: contents hash mismatch",
+                            block_data.block().index,
+                            self.source_urls[source_index]
+                        );
+                    }
+                    self.cache_blocks(valid);
+                }
+                Err(err) if err.is_not_found() => {
+                    log::trace!(
+                        self.logger,
+                        "Block #{} is not available yet: {}",
+                        block_index,
+                        err
+                    );
+                    not_found = true;
+                }
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "Failed prefetching block #{}: {}",
+                        block_index,
+                        err
+                    );
+                }
             }
+
+            state.done(block_index, not_found);
         }
+    }
+}
 
-        let hits = self.hits.load(Ordering::SeqCst);
-        let misses = self.misses.fetch_add(1, Ordering::SeqCst);
-        log::trace!(
-            self.logger,
-            "Cache miss while getting block #{} (total hits/misses: {}/{})",
-            block_data.block().index,
-            hits,
-            misses
-        );
+/// The manifest of a source's compressed archive.
+struct SourceManifest {
+    /// The buckets listed by the manifest, or None if the source has no usable
+    /// manifest.
+    contents: Option<Arc<ArchiveManifestContents>>,
 
-        // Got what we wanted!
-        Ok(block_data)
+    /// When the manifest was last fetched.
+    fetched_at: Instant,
+}
+
+/// Blocks queued for, or being fetched by, the prefetch workers.
+struct Prefetcher {
+    /// Maximum number of blocks to have in flight at once.
+    max_in_flight: usize,
+
+    /// Queue of blocks for the workers to fetch. The workers exit once this is
+    /// dropped.
+    sender: Sender<BlockIndex>,
+
+    /// State shared with the workers.
+    state: Arc<PrefetchState>,
+}
+
+impl Prefetcher {
+    /// Queue `block_index` and the blocks following it, unless they are
+    /// already cached or in flight, or are past the last published block.
+    fn prefetch(&self, block_index: BlockIndex, is_cached: impl Fn(BlockIndex) -> bool) {
+        let mut inner = self.state.inner.lock().expect("mutex poisoned");
+
+        // The caller knows that this block exists, so the sources might have it by
+        // now.
+        if inner
+            .not_found_from
+            .is_some_and(|index| index <= block_index)
+        {
+            inner.not_found_from = None;
+        }
+        let end = inner
+            .not_found_from
+            .unwrap_or(BlockIndex::MAX)
+            .min(block_index.saturating_add(self.max_in_flight as u64));
+
+        for index in block_index..end {
+            if inner.in_flight.len() >= self.max_in_flight {
+                break;
+            }
+            if inner.in_flight.contains(&index) || is_cached(index) {
+                continue;
+            }
+            if self.sender.send(index).is_err() {
+                break;
+            }
+            inner.in_flight.insert(index);
+        }
+    }
+
+    /// Wait until `block_index` is no longer in flight. Returns false if it
+    /// still is after `timeout`.
+    fn wait(&self, block_index: BlockIndex, timeout: Duration) -> bool {
+        let inner = self.state.inner.lock().expect("mutex poisoned");
+        let (_inner, result) = self
+            .state
+            .condvar
+            .wait_timeout_while(inner, timeout, |inner| {
+                inner.in_flight.contains(&block_index)
+            })
+            .expect("mutex poisoned");
+        !result.timed_out()
     }
 }
 
+#[derive(Default)]
+struct PrefetchState {
+    inner: Mutex<PrefetchStateInner>,
+
+    /// Notified whenever a block is no longer in flight.
+    condvar: Condvar,
+}
+
+impl PrefetchState {
+    /// Record that a worker is done with `block_index`.
+    fn done(&self, block_index: BlockIndex, not_found: bool) {
+        let mut inner = self.inner.lock().expect("mutex poisoned");
+        inner.in_flight.remove(&block_index);
+        if not_found {
+            inner.not_found_from = Some(
+                inner
+                    .not_found_from
+                    .map_or(block_index, |index| index.min(block_index)),
+            );
+        }
+        self.condvar.notify_all();
+    }
+}
+
+#[derive(Default)]
+struct PrefetchStateInner {
+    /// Blocks queued for, or being fetched by, the workers.
+    in_flight: HashSet<BlockIndex>,
+
+    /// The lowest block that a source did not have. Blocks from here on are not
+    /// prefetched, since they have most likely not been published yet.
+    not_found_from: Option<BlockIndex>,
+}
+
 impl TransactionsFetcher for ReqwestTransactionsFetcher {
     type Error = ReqwestTransactionsFetcherError;
 
@@ -366,3 +949,159 @@ impl TransactionsFetcher for ReqwestTransactionsFetcher {
         self.get_block_data_by_index(block.index, Some(block))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        write_compressed_ledger_archive, write_ledger_archive, TestHttpServer,
+    };
+    use mc_common::logger::test_with_logger;
+    use mc_crypto_keys::{Ed25519Pair, Ed25519Private};
+    use mc_ledger_db::test_utils::get_test_ledger_blocks;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn test_signer(seed: u8) -> Ed25519Pair {
+        Ed25519Pair::from(Ed25519Private::try_from(&[seed; 32][..]).unwrap())
+    }
+
+    /// Write an archive where blocks 0-19 are only available from compressed
+    /// buckets, and blocks 20-24 only as individual blocks.
+    fn write_compressed_test_archive(dir: &Path, signer: &Ed25519Pair) -> Vec<BlockData> {
+        let blocks = get_test_ledger_blocks(25);
+        write_compressed_ledger_archive(dir, &blocks, 10, signer);
+        write_ledger_archive(dir, &blocks[20..], &[]);
+        blocks
+    }
+
+    fn file_url(dir: &Path) -> String {
+        Url::from_directory_path(dir).unwrap().to_string()
+    }
+
+    #[test_with_logger]
+    fn test_fetch_from_compressed_archive(logger: Logger) {
+        let archive_dir = TempDir::new().unwrap();
+        let blocks = write_compressed_test_archive(archive_dir.path(), &test_signer(1));
+
+        let mut fetcher =
+            ReqwestTransactionsFetcher::new(vec![file_url(archive_dir.path())], logger).unwrap();
+        fetcher.set_manifest_signers(&[test_signer(1).public_key()]);
+
+        for block_data in blocks.iter().rev() {
+            let fetched = fetcher.get_block_data(&[], block_data.block()).unwrap();
+            assert_eq!(&fetched, block_data);
+        }
+    }
+
+    #[test_with_logger]
+    fn test_compressed_archive_untrusted_signer(logger: Logger) {
+        let archive_dir = TempDir::new().unwrap();
+        let blocks = write_compressed_test_archive(archive_dir.path(), &test_signer(1));
+
+        let mut fetcher =
+            ReqwestTransactionsFetcher::new(vec![file_url(archive_dir.path())], logger).unwrap();
+        fetcher.set_manifest_signers(&[test_signer(2).public_key()]);
+
+        // The manifest is ignored, so only the individual blocks are found.
+        assert!(fetcher.get_block_data(&[], blocks[5].block()).is_err());
+        let fetched = fetcher.get_block_data(&[], blocks[22].block()).unwrap();
+        assert_eq!(fetched, blocks[22]);
+    }
+
+    #[test_with_logger]
+    fn test_compressed_archive_requires_signers(logger: Logger) {
+        let archive_dir = TempDir::new().unwrap();
+        let blocks = write_compressed_test_archive(archive_dir.path(), &test_signer(1));
+
+        let fetcher =
+            ReqwestTransactionsFetcher::new(vec![file_url(archive_dir.path())], logger).unwrap();
+
+        // No manifest is trusted until signers are set.
+        assert!(fetcher.source_manifest(0, 0).is_none());
+        assert!(fetcher.get_block_data(&[], blocks[5].block()).is_err());
+        let fetched = fetcher.get_block_data(&[], blocks[22].block()).unwrap();
+        assert_eq!(fetched, blocks[22]);
+    }
+
+    #[test_with_logger]
+    fn test_compressed_bucket_hash_mismatch(logger: Logger) {
+        let archive_dir = TempDir::new().unwrap();
+        let blocks = write_compressed_test_archive(archive_dir.path(), &test_signer(1));
+
+        let mut fetcher =
+            ReqwestTransactionsFetcher::new(vec![file_url(archive_dir.path())], logger).unwrap();
+        fetcher.set_manifest_signers(&[test_signer(1).public_key()]);
+        let manifest = fetcher
+            .source_manifest(0, 0)
+            .expect("manifest should be found");
+        let bucket = manifest.bucket_containing(0).unwrap();
+        let url = fetcher.source_urls[0]
+            .join(compressed_bucket_path(10, 0).to_str().unwrap())
+            .unwrap();
+        assert_eq!(fetcher.bucket_from_url(&url, bucket).unwrap(), blocks[..10]);
+
+        // Appending an empty frame leaves the blocks unchanged, but the object no
+        // longer matches the manifest.
+        let path = archive_dir.path().join(compressed_bucket_path(10, 0));
+        let mut object = fs::read(&path).unwrap();
+        object.extend(zstd::encode_all(&[][..], 0).unwrap());
+        fs::write(&path, object).unwrap();
+        assert!(matches!(
+            fetcher.bucket_from_url(&url, bucket),
+            Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(_, _))
+        ));
+    }
+
+    #[test_with_logger]
+    fn test_pipelined_fetch(logger: Logger) {
+        let blocks = get_test_ledger_blocks(25);
+        let archive_dir = TempDir::new().unwrap();
+        write_ledger_archive(archive_dir.path(), &blocks, &[10]);
+        let server = TestHttpServer::new(archive_dir.path(), Duration::from_millis(10));
+
+        let mut fetcher =
+            ReqwestTransactionsFetcher::new(vec![server.url.clone()], logger).unwrap();
+        fetcher.set_merged_blocks_bucket_sizes(&[10]);
+        fetcher.set_max_in_flight_requests(8);
+
+        for block_data in &blocks {
+            let fetched = fetcher.get_block_data(&[], block_data.block()).unwrap();
+            assert_eq!(&fetched, block_data);
+        }
+
+        // Blocks past the end of the archive are reported as not found.
+        let err = fetcher
+            .get_block_data_by_index(blocks.len() as u64, None)
+            .unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[test_with_logger]
+    fn test_pipelined_fetch_avoids_failing_source(logger: Logger) {
+        let blocks = get_test_ledger_blocks(30);
+        let archive_dir = TempDir::new().unwrap();
+        write_ledger_archive(archive_dir.path(), &blocks, &[]);
+        let failing_server = TestHttpServer::failing();
+        let server = TestHttpServer::new(archive_dir.path(), Duration::from_millis(1));
+
+        let mut fetcher = ReqwestTransactionsFetcher::new(
+            vec![failing_server.url.clone(), server.url.clone()],
+            logger,
+        )
+        .unwrap();
+        fetcher.set_merged_blocks_bucket_sizes(&[]);
+        fetcher.set_max_in_flight_requests(4);
+
+        for block_data in &blocks {
+            // Requests that went to the failing source need to be retried, as
+            // LedgerSyncService does.
+            let fetched = (0..5)
+                .find_map(|_| fetcher.get_block_data(&[], block_data.block()).ok())
+                .expect("failed fetching block");
+            assert_eq!(&fetched, block_data);
+        }
+
+        assert!(failing_server.num_requests() * 3 < server.num_requests());
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Picks which of several block sources to fetch from, based on how quickly
+//! and how reliably each of them has been responding.
+
+use std::{sync::Mutex, time::Duration};
+
+/// Weight given to the latest measurement in the moving averages.
+const SMOOTHING_FACTOR: f64 = 0.2;
+
+/// How much a source that always fails is penalized, relative to one that
+/// never fails.
+const ERROR_PENALTY: f64 = 10.0;
+
+/// Latency assumed for sources that have not responded yet, so that each of
+/// them gets tried.
+const MIN_LATENCY_SECS: f64 = 0.001;
+
+/// Latency counted for failed requests, since the request has to be retried
+/// elsewhere. This keeps sources that fail quickly from looking fast.
+const FAILURE_LATENCY_SECS: f64 = 1.0;
+
+/// The outcome of a request to a source.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FetchOutcome {
+    /// The source responded after the given time.
+    Success(Duration),
+
+    /// The source does not have the object. This is expected for blocks that
+    /// have not been published yet, so it does not count against the source.
+    NotFound,
+
+    /// The request failed, or the source responded with invalid data.
+    Failure,
+}
+
+#[derive(Clone, Debug, Default)]
+struct SourceStats {
+    /// Moving average of the latency of successful requests, in seconds.
+    latency_secs: Option<f64>,
+
+    /// Moving average of the fraction of requests that failed.
+    error_rate: f64,
+
+    /// Number of requests currently in flight.
+    in_flight: usize,
+}
+
+impl SourceStats {
+    fn score(&self) -> f64 {
+        self.latency_secs.unwrap_or(0.0).max(MIN_LATENCY_SECS)
+            * (1 + self.in_flight) as f64
+            * (1.0 + ERROR_PENALTY * self.error_rate)
+    }
+}
+
+/// Keeps track of the latency, error rate and load of each source, and picks
+/// the one expected to respond soonest.
+#[derive(Debug)]
+pub struct SourceSelector {
+    stats: Mutex<Vec<SourceStats>>,
+}
+
+impl SourceSelector {
+    /// Create a selector for `num_sources` sources, with no measurements yet.
+    pub fn new(num_sources: usize) -> Self {
+        Self {
+            stats: Mutex::new(vec![SourceStats::default(); num_sources]),
+        }
+    }
+
+    /// Pick the source expected to respond soonest.
+    pub fn pick(&self) -> usize {
+        self.pick_excluding(&[]).expect("no sources")
+    }
+
+    /// Pick the source expected to respond soonest, other than the `excluded`
+    /// ones, e.g. because they were already tried. Returns None if every
+    /// source is excluded.
+    pub fn pick_excluding(&self, excluded: &[usize]) -> Option<usize> {
+        let stats = self.stats.lock().expect("mutex poisoned");
+        stats
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !excluded.contains(index))
+            .min_by(|(_, a), (_, b)| a.score().total_cmp(&b.score()))
+            .map(|(index, _)| index)
+    }
+
+    /// Count a request to a source as in flight. Every call must be followed
+    /// by a call to [SourceSelector::finish].
+    pub fn start(&self, source_index: usize) {
+        self.stats.lock().expect("mutex poisoned")[source_index].in_flight += 1;
+    }
+
+    /// Record the outcome of a request started with [SourceSelector::start].
+    pub fn finish(&self, source_index: usize, outcome: FetchOutcome) {
+        let mut stats = self.stats.lock().expect("mutex poisoned");
+        let source = &mut stats[source_index];
+        source.in_flight -= 1;
+
+        let (latency, failed) = match outcome {
+            FetchOutcome::Success(latency) => (latency.as_secs_f64(), 0.0),
+            FetchOutcome::NotFound => return,
+            FetchOutcome::Failure => (FAILURE_LATENCY_SECS, 1.0),
+        };
+        source.latency_secs = Some(source.latency_secs.map_or(latency, |average| {
+            average + SMOOTHING_FACTOR * (latency - average)
+        }));
+        source.error_rate += SMOOTHING_FACTOR * (failed - source.error_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_faster_sources() {
+        let selector = SourceSelector::new(2);
+        for (source_index, latency) in [(0, 100), (1, 10)] {
+            assert_eq!(selector.pick(), source_index);
+            selector.start(source_index);
+            selector.finish(
+                source_index,
+                FetchOutcome::Success(Duration::from_millis(latency)),
+            );
+        }
+
+        assert_eq!(selector.pick(), 1);
+    }
+
+    #[test]
+    fn spreads_load_across_sources() {
+        let selector = SourceSelector::new(2);
+        for (source_index, latency) in [(0, 10), (1, 15)] {
+            selector.start(source_index);
+            selector.finish(
+                source_index,
+                FetchOutcome::Success(Duration::from_millis(latency)),
+            );
+        }
+
+        // Once the faster source is busy enough, requests go to the other one.
+        assert_eq!(selector.pick(), 0);
+        selector.start(0);
+        assert_eq!(selector.pick(), 1);
+    }
+
+    #[test]
+    fn avoids_failing_sources() {
+        let selector = SourceSelector::new(2);
+        for (source_index, latency) in [(0, 10), (1, 20)] {
+            selector.start(source_index);
+            selector.finish(
+                source_index,
+                FetchOutcome::Success(Duration::from_millis(latency)),
+            );
+        }
+
+        assert_eq!(selector.pick(), 0);
+        selector.start(0);
+        selector.finish(0, FetchOutcome::Failure);
+        assert_eq!(selector.pick(), 1);
+
+        // Missing objects do not count against a source.
+        let selector = SourceSelector::new(2);
+        for _ in 0..5 {
+            assert_eq!(selector.pick(), 0);
+            selector.start(0);
+            selector.finish(0, FetchOutcome::NotFound);
+        }
+        assert_eq!(selector.pick(), 0);
+    }
+
+    #[test]
+    fn picks_among_sources_not_excluded() {
+        let selector = SourceSelector::new(3);
+        for (source_index, latency) in [(0, 10), (1, 20), (2, 30)] {
+            selector.start(source_index);
+            selector.finish(
+                source_index,
+                FetchOutcome::Success(Duration::from_millis(latency)),
+            );
+        }
+
+        assert_eq!(selector.pick_excluding(&[]), Some(0));
+        assert_eq!(selector.pick_excluding(&[0]), Some(1));
+        assert_eq!(selector.pick_excluding(&[1, 0]), Some(2));
+        assert_eq!(selector.pick_excluding(&[0, 1, 2]), None);
+    }
+}
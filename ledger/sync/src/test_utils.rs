@@ -1,9 +1,30 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
 use crate::{TransactionFetcherError, TransactionsFetcher};
-use mc_blockchain_types::{Block, BlockData};
+use mc_api::{
+    block_num_to_s3block_path,
+    blockchain::{self, ArchiveBlock, ArchiveBlocks},
+    compressed_bucket_path, merged_block_num_to_s3block_path, ARCHIVE_MANIFEST_PATH,
+};
+use mc_blockchain_types::{
+    ArchiveManifest, ArchiveManifestBucket, ArchiveManifestContents, Block, BlockData,
+};
 use mc_common::ResponderId;
+use mc_crypto_keys::Ed25519Pair;
 use mc_ledger_db::Ledger;
+use protobuf::Message;
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
 
 impl TransactionFetcherError for String {}
 
@@ -31,3 +52,167 @@ impl<L: Ledger + Sync> TransactionsFetcher for MockTransactionsFetcher<L> {
             .map_err(|e| format!("Error getting data for block #{}: {:?}", block.index, e))
     }
 }
+
+/// Write blocks to `dir` in the layout of a ledger archive: every block on its
+/// own, and every complete bucket of consecutive blocks as a merged block for
+/// each of the given bucket sizes. `blocks` should start at block 0.
+pub fn write_ledger_archive(dir: &Path, blocks: &[BlockData], merged_blocks_bucket_sizes: &[u64]) {
+    for block_data in blocks {
+        write_file(
+            dir.join(block_num_to_s3block_path(block_data.block().index)),
+            ArchiveBlock::from(block_data).write_to_bytes().unwrap(),
+        );
+    }
+
+    for bucket_size in merged_blocks_bucket_sizes {
+        for bucket in blocks.chunks_exact(*bucket_size as usize) {
+            write_file(
+                dir.join(merged_block_num_to_s3block_path(
+                    *bucket_size,
+                    bucket[0].block().index,
+                )),
+                ArchiveBlocks::from(bucket).write_to_bytes().unwrap(),
+            );
+        }
+    }
+}
+
+/// Write blocks to `dir` as a compressed archive: every complete bucket of
+/// `bucket_size` consecutive blocks as a zstd-compressed object, and a manifest
+/// listing them signed by `signer`. `blocks` should start at block 0.
+pub fn write_compressed_ledger_archive(
+    dir: &Path,
+    blocks: &[BlockData],
+    bucket_size: u64,
+    signer: &Ed25519Pair,
+) {
+    let mut contents = ArchiveManifestContents::default();
+    for bucket in blocks.chunks_exact(bucket_size as usize) {
+        let first_block_index = bucket[0].block().index;
+        let bytes = ArchiveBlocks::from(bucket).write_to_bytes().unwrap();
+        let object = zstd::encode_all(bytes.as_slice(), 0).unwrap();
+        contents.push(ArchiveManifestBucket::from_object(
+            first_block_index,
+            bucket_size,
+            &object,
+            bucket.last().unwrap().block().id.clone(),
+        ));
+        write_file(
+            dir.join(compressed_bucket_path(bucket_size, first_block_index)),
+            object,
+        );
+    }
+
+    let manifest = ArchiveManifest::from_contents_and_keypair(contents, signer).unwrap();
+    write_file(
+        dir.join(ARCHIVE_MANIFEST_PATH),
+        blockchain::ArchiveManifest::from(&manifest)
+            .write_to_bytes()
+            .unwrap(),
+    );
+}
+
+fn write_file(path: PathBuf, bytes: Vec<u8>) {
+    fs::create_dir_all(path.parent().unwrap()).expect("failed creating archive dir");
+    fs::write(path, bytes).expect("failed writing archive file");
+}
+
+/// A minimal HTTP server standing in for a ledger archive bucket in tests.
+/// It serves the files in a directory, and responds with a 404 for files
+/// that do not exist.
+pub struct TestHttpServer {
+    /// The URL the files are served under.
+    pub url: String,
+
+    addr: SocketAddr,
+    num_requests: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+}
+
+impl TestHttpServer {
+    /// Serve the files in `root`, responding to each request after `delay`.
+    pub fn new(root: &Path, delay: Duration) -> Self {
+        Self::start(Some(root.to_path_buf()), delay)
+    }
+
+    /// A server that responds to every request with a 500 error.
+    pub fn failing() -> Self {
+        Self::start(None, Duration::ZERO)
+    }
+
+    /// The number of requests received so far.
+    pub fn num_requests(&self) -> usize {
+        self.num_requests.load(Ordering::SeqCst)
+    }
+
+    fn start(root: Option<PathBuf>, delay: Duration) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed binding test server");
+        let addr = listener.local_addr().unwrap();
+        let num_requests = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_num_requests = num_requests.clone();
+        let thread_stop = stop.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                thread_num_requests.fetch_add(1, Ordering::SeqCst);
+                let root = root.clone();
+                thread::spawn(move || {
+                    // Errors just mean that the client went away.
+                    let _ = Self::respond(stream, root.as_deref(), delay);
+                });
+            }
+        });
+
+        Self {
+            url: format!("http://{addr}/"),
+            addr,
+            num_requests,
+            stop,
+        }
+    }
+
+    fn respond(mut stream: TcpStream, root: Option<&Path>, delay: Duration) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // Skip the headers.
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+                break;
+            }
+        }
+
+        thread::sleep(delay);
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let (status, body) = match root {
+            Some(root) => match fs::read(root.join(path.trim_start_matches('/'))) {
+                Ok(body) => ("200 OK", body),
+                Err(_) => ("404 Not Found", vec![]),
+            },
+            None => ("500 Internal Server Error", vec![]),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        stream.write_all(&body)
+    }
+}
+
+impl Drop for TestHttpServer {
+    fn drop(&mut self) {
+        // Wake up the accept loop so that it notices it should stop.
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.addr);
+    }
+}
@@ -10,10 +10,20 @@ use grpcio::ChannelBuilder;
 use mc_api::external::{CompressedRistretto, PublicAddress, RistrettoPrivate};
 use mc_common::logger::{create_app_logger, log, o};
 use mc_mobilecoind_api::{self as api, mobilecoind_api_grpc::MobilecoindApiClient, MobilecoindUri};
-use mc_mobilecoind_json::data_types::*;
+use mc_mobilecoind_json::{
+    data_types::*,
+    openapi::openapi_document,
+    rpc::{self, RpcError},
+};
 use mc_util_grpc::ConnectionUriGrpcioChannel;
 use protobuf::RepeatedField;
-use rocket::{delete, get, post, routes, serde::json::Json};
+use rocket::{
+    delete, get,
+    http::{ContentType, Header, Status},
+    post, routes,
+    serde::json::{Json, Value},
+    Responder,
+};
 use std::sync::Arc;
 
 /// Command line config, set with defaults that will work with
@@ -43,6 +53,13 @@ struct State {
     pub mobilecoind_api_client: MobilecoindApiClient,
 }
 
+/// A CSV document, along with the block the next page starts at
+#[derive(Responder)]
+struct CsvResponse {
+    csv: (ContentType, String),
+    next_block: Header<'static>,
+}
+
 /// Set the password for the mobilecoind-db
 #[post("/set-password", format = "json", data = "<password>")]
 fn set_password(
@@ -310,6 +327,9 @@ fn create_request_code(
     if let Some(memo) = request.memo.clone() {
         req.set_memo(memo);
     }
+    if let Some(payment_id) = request.payment_id {
+        req.set_payment_id(u64::from(payment_id));
+    }
 
     let resp = state
         .mobilecoind_api_client
@@ -338,6 +358,91 @@ fn parse_request_code(
     Ok(Json(JsonParseRequestCodeResponse::from(&resp)))
 }
 
+/// Creates a request code for a monitored subaddress and starts tracking
+/// payments made against it
+#[post(
+    "/monitors/<monitor_hex>/subaddresses/<subaddress_index>/payment-requests",
+    format = "json",
+    data = "<request>"
+)]
+fn add_payment_request(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    subaddress_index: u64,
+    request: Json<JsonAddPaymentRequestRequest>,
+) -> Result<Json<JsonAddPaymentRequestResponse>, String> {
+    let monitor_id =
+        hex::decode(monitor_hex).map_err(|err| format!("Failed to decode monitor hex: {err}"))?;
+
+    let mut req = api::AddPaymentRequestRequest::new();
+    req.set_monitor_id(monitor_id);
+    req.set_subaddress_index(subaddress_index);
+    req.set_value(u64::from(request.value));
+    if let Some(token_id) = request.token_id {
+        req.set_token_id(u64::from(token_id));
+    }
+    if let Some(memo) = request.memo.clone() {
+        req.set_memo(memo);
+    }
+    if let Some(payment_id) = request.payment_id {
+        req.set_payment_id(u64::from(payment_id));
+    }
+    if let Some(expiry_block) = request.expiry_block {
+        req.set_expiry_block(u64::from(expiry_block));
+    }
+
+    let resp = state
+        .mobilecoind_api_client
+        .add_payment_request(&req)
+        .map_err(|err| format!("Failed adding payment request: {err}"))?;
+
+    Ok(Json(JsonAddPaymentRequestResponse::from(&resp)))
+}
+
+/// Gets the status of all tracked payment requests
+#[get("/payment-requests")]
+fn payment_requests(
+    state: &rocket::State<State>,
+) -> Result<Json<JsonPaymentRequestListResponse>, String> {
+    let resp = state
+        .mobilecoind_api_client
+        .get_payment_request_list(&api::Empty::new())
+        .map_err(|err| format!("Failed getting payment request list: {err}"))?;
+
+    Ok(Json(JsonPaymentRequestListResponse::from(&resp)))
+}
+
+/// Gets the status of a tracked payment request
+#[get("/payment-requests/<payment_id>")]
+fn payment_request_status(
+    state: &rocket::State<State>,
+    payment_id: u64,
+) -> Result<Json<JsonPaymentRequestStatus>, String> {
+    let mut req = api::GetPaymentRequestStatusRequest::new();
+    req.set_payment_id(payment_id);
+
+    let resp = state
+        .mobilecoind_api_client
+        .get_payment_request_status(&req)
+        .map_err(|err| format!("Failed getting payment request status: {err}"))?;
+
+    Ok(Json(JsonPaymentRequestStatus::from(resp.get_status())))
+}
+
+/// Stops tracking a payment request
+#[delete("/payment-requests/<payment_id>")]
+fn remove_payment_request(state: &rocket::State<State>, payment_id: u64) -> Result<(), String> {
+    let mut req = api::RemovePaymentRequestRequest::new();
+    req.set_payment_id(payment_id);
+
+    let _resp = state
+        .mobilecoind_api_client
+        .remove_payment_request(&req)
+        .map_err(|err| format!("Failed removing payment request: {err}"))?;
+
+    Ok(())
+}
+
 /// Generates an address code
 #[post("/codes/address", format = "json", data = "<request>")]
 fn create_address_code(
@@ -417,6 +522,16 @@ fn build_and_submit(
         req.set_change_subaddress(u64::from(subaddress))
     }
 
+    // Let the receiver match the payment to their request.
+    let payment_id = u64::from(transfer.request_data.payment_id);
+    if payment_id != 0 {
+        let mut rth = api::TransactionMemo_RTH::new();
+        rth.set_payment_request_id(payment_id);
+        let mut memo = api::TransactionMemo::new();
+        memo.set_rth(rth);
+        req.set_memo(memo);
+    }
+
     let resp = state
         .mobilecoind_api_client
         .send_payment(&req)
@@ -669,6 +784,60 @@ fn processed_block(
     Ok(Json(JsonProcessedBlockResponse::from(&resp)))
 }
 
+/// Get a page of the transaction history of a monitor, as a
+/// GetTransactionHistoryRequest.
+fn get_transaction_history(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    first_block: Option<u64>,
+    max_events: Option<u32>,
+    csv: bool,
+) -> Result<api::GetTransactionHistoryResponse, String> {
+    let monitor_id =
+        hex::decode(monitor_hex).map_err(|err| format!("Failed to decode monitor hex: {err}"))?;
+
+    let mut req = api::GetTransactionHistoryRequest::new();
+    req.set_monitor_id(monitor_id);
+    req.set_first_block(first_block.unwrap_or(0));
+    req.set_max_events(max_events.unwrap_or(0));
+    req.set_csv(csv);
+
+    state
+        .mobilecoind_api_client
+        .get_transaction_history(&req)
+        .map_err(|err| format!("Failed getting transaction history: {err}"))
+}
+
+/// Retreives the transaction history of a monitor.
+#[get("/monitors/<monitor_hex>/history?<first_block>&<max_events>")]
+fn transaction_history(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    first_block: Option<u64>,
+    max_events: Option<u32>,
+) -> Result<Json<JsonTransactionHistoryResponse>, String> {
+    let resp = get_transaction_history(state, monitor_hex, first_block, max_events, false)?;
+
+    Ok(Json(JsonTransactionHistoryResponse::from(&resp)))
+}
+
+/// Retreives the transaction history of a monitor as CSV. The block to request
+/// the next page with is returned in the `X-Next-Block` header.
+#[get("/monitors/<monitor_hex>/history/csv?<first_block>&<max_events>")]
+fn transaction_history_csv(
+    state: &rocket::State<State>,
+    monitor_hex: String,
+    first_block: Option<u64>,
+    max_events: Option<u32>,
+) -> Result<CsvResponse, String> {
+    let resp = get_transaction_history(state, monitor_hex, first_block, max_events, true)?;
+
+    Ok(CsvResponse {
+        csv: (ContentType::CSV, resp.get_csv().to_owned()),
+        next_block: Header::new("X-Next-Block", resp.get_next_block().to_string()),
+    })
+}
+
 /// Get the block index of a given tx out, identified by its public key.
 #[get("/tx-out/<public_key_hex>/block-index")]
 fn tx_out_get_block_index_by_public_key(
@@ -776,6 +945,32 @@ fn get_mixins(
     Ok(Json(response))
 }
 
+/// Call any mobilecoind RPC, using the protobuf JSON mapping of its request
+/// and response messages
+#[post("/rpc/<method>", data = "<body>")]
+fn call_rpc(
+    state: &rocket::State<State>,
+    method: &str,
+    body: String,
+) -> Result<(ContentType, String), (Status, String)> {
+    rpc::call(&state.mobilecoind_api_client, method, &body)
+        .map(|json| (ContentType::JSON, json))
+        .map_err(|err| {
+            let status = match err {
+                RpcError::UnknownMethod(_) => Status::NotFound,
+                RpcError::InvalidRequest(_) => Status::BadRequest,
+                RpcError::Grpc(_) | RpcError::InvalidResponse(_) => Status::InternalServerError,
+            };
+            (status, err.to_string())
+        })
+}
+
+/// OpenAPI document describing the /rpc endpoints
+#[get("/openapi.json")]
+fn openapi() -> Json<Value> {
+    Json(openapi_document())
+}
+
 #[rocket::main]
 async fn main() -> Result<(), rocket::Error> {
     let _sentry_guard = mc_common::sentry::init();
@@ -825,6 +1020,10 @@ async fn main() -> Result<(), rocket::Error> {
                 public_address,
                 create_request_code,
                 parse_request_code,
+                add_payment_request,
+                payment_requests,
+                payment_request_status,
+                remove_payment_request,
                 create_address_code,
                 parse_address_code,
                 build_and_submit,
@@ -837,9 +1036,13 @@ async fn main() -> Result<(), rocket::Error> {
                 block_info,
                 block_details,
                 processed_block,
+                transaction_history,
+                transaction_history_csv,
                 tx_out_get_block_index_by_public_key,
                 get_mixins,
                 get_proof_of_membership,
+                call_rpc,
+                openapi,
             ],
         )
         .manage(State {
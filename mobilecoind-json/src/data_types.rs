@@ -222,6 +222,7 @@ pub struct JsonCreateRequestCodeRequest {
     pub receiver: JsonPublicAddress,
     pub value: Option<JsonU64>,
     pub memo: Option<String>,
+    pub payment_id: Option<JsonU64>,
 }
 
 #[derive(Serialize, Default, Debug)]
@@ -339,6 +340,8 @@ pub struct JsonParseRequestCodeResponse {
     pub receiver: JsonPublicAddress,
     pub value: JsonU64,
     pub memo: String,
+    #[serde(default)]
+    pub payment_id: JsonU64,
 }
 
 impl From<&api::ParseRequestCodeResponse> for JsonParseRequestCodeResponse {
@@ -347,6 +350,90 @@ impl From<&api::ParseRequestCodeResponse> for JsonParseRequestCodeResponse {
             receiver: JsonPublicAddress::from(src.get_receiver()),
             value: JsonU64(src.get_value()),
             memo: src.get_memo().to_string(),
+            payment_id: JsonU64(src.get_payment_id()),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct JsonAddPaymentRequestRequest {
+    pub value: JsonU64,
+    pub token_id: Option<JsonU64>,
+    pub memo: Option<String>,
+    pub payment_id: Option<JsonU64>,
+    pub expiry_block: Option<JsonU64>,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct JsonAddPaymentRequestResponse {
+    pub payment_id: JsonU64,
+    pub b58_request_code: String,
+}
+
+impl From<&api::AddPaymentRequestResponse> for JsonAddPaymentRequestResponse {
+    fn from(src: &api::AddPaymentRequestResponse) -> Self {
+        Self {
+            payment_id: JsonU64(src.get_payment_id()),
+            b58_request_code: String::from(src.get_b58_code()),
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct JsonPaymentRequestStatus {
+    pub payment_id: JsonU64,
+    pub monitor_id: String,
+    pub subaddress_index: u64,
+    pub value: JsonU64,
+    pub token_id: JsonU64,
+    pub memo: String,
+    pub expiry_block: JsonU64,
+    pub amount_received: JsonU64,
+    pub tx_out_public_keys: Vec<String>,
+    pub status: String,
+}
+
+impl From<&api::PaymentRequestStatus> for JsonPaymentRequestStatus {
+    fn from(src: &api::PaymentRequestStatus) -> Self {
+        let status_str = match src.get_state() {
+            api::PaymentRequestState::PaymentRequestPending => "pending",
+            api::PaymentRequestState::PaymentRequestPartiallyPaid => "partially_paid",
+            api::PaymentRequestState::PaymentRequestPaid => "paid",
+            api::PaymentRequestState::PaymentRequestExpired => "expired",
+        };
+
+        Self {
+            payment_id: JsonU64(src.get_payment_id()),
+            monitor_id: hex::encode(src.get_monitor_id()),
+            subaddress_index: src.get_subaddress_index(),
+            value: JsonU64(src.get_value()),
+            token_id: JsonU64(src.get_token_id()),
+            memo: src.get_memo().to_string(),
+            expiry_block: JsonU64(src.get_expiry_block()),
+            amount_received: JsonU64(src.get_amount_received()),
+            tx_out_public_keys: src
+                .get_tx_out_public_key_list()
+                .iter()
+                .map(|key| hex::encode(key.get_data()))
+                .collect(),
+            status: status_str.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct JsonPaymentRequestListResponse {
+    pub payment_requests: Vec<JsonPaymentRequestStatus>,
+}
+
+impl From<&api::GetPaymentRequestListResponse> for JsonPaymentRequestListResponse {
+    fn from(src: &api::GetPaymentRequestListResponse) -> Self {
+        Self {
+            payment_requests: src
+                .get_status_list()
+                .iter()
+                .map(JsonPaymentRequestStatus::from)
+                .collect(),
         }
     }
 }
@@ -1411,6 +1498,125 @@ impl From<&api::GetProcessedBlockResponse> for JsonProcessedBlockResponse {
     }
 }
 
+#[derive(Serialize, Default, Debug)]
+pub struct JsonDecodedMemo {
+    pub memo_type: String,
+    pub address_hash: Option<String>,
+    pub num_recipients: Option<u32>,
+    pub fee: Option<JsonU64>,
+    pub total_outlay: Option<JsonU64>,
+    pub payment_request_id: Option<JsonU64>,
+    pub payment_intent_id: Option<JsonU64>,
+    pub type_bytes: Option<String>,
+}
+
+impl From<&api::DecodedMemo> for JsonDecodedMemo {
+    fn from(src: &api::DecodedMemo) -> Self {
+        if src.has_authenticated_sender_memo() {
+            let memo = src.get_authenticated_sender_memo();
+            Self {
+                memo_type: "authenticated_sender".to_owned(),
+                address_hash: Some(hex::encode(memo.get_sender_hash())),
+                payment_request_id: memo
+                    .has_payment_request_id()
+                    .then(|| JsonU64(memo.get_payment_request_id())),
+                payment_intent_id: memo
+                    .has_payment_intent_id()
+                    .then(|| JsonU64(memo.get_payment_intent_id())),
+                ..Default::default()
+            }
+        } else if src.has_destination_memo() {
+            let memo = src.get_destination_memo();
+            Self {
+                memo_type: "destination".to_owned(),
+                address_hash: Some(hex::encode(memo.get_recipient_hash())),
+                num_recipients: Some(memo.get_num_recipients()),
+                fee: Some(JsonU64(memo.get_fee())),
+                total_outlay: Some(JsonU64(memo.get_total_outlay())),
+                payment_request_id: memo
+                    .has_payment_request_id()
+                    .then(|| JsonU64(memo.get_payment_request_id())),
+                payment_intent_id: memo
+                    .has_payment_intent_id()
+                    .then(|| JsonU64(memo.get_payment_intent_id())),
+                ..Default::default()
+            }
+        } else if src.has_unknown_memo() {
+            Self {
+                memo_type: "unknown".to_owned(),
+                type_bytes: Some(hex::encode(src.get_unknown_memo().get_type_bytes())),
+                ..Default::default()
+            }
+        } else {
+            Self {
+                memo_type: "unused".to_owned(),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct JsonTransactionHistoryEvent {
+    pub block: JsonU64,
+    pub timestamp: Option<JsonU64>,
+    pub direction: String,
+    pub subaddress_index: u64,
+    pub public_key: String,
+    pub key_image: String,
+    pub value: JsonU64,
+    pub token_id: JsonU64,
+    pub memo: Option<JsonDecodedMemo>,
+    pub counterparty_hash: Option<String>,
+}
+
+impl From<&api::TransactionHistoryEvent> for JsonTransactionHistoryEvent {
+    fn from(src: &api::TransactionHistoryEvent) -> Self {
+        let direction_str = match src.get_direction() {
+            api::ProcessedTxOutDirection::Invalid => "invalid",
+            api::ProcessedTxOutDirection::Received => "received",
+            api::ProcessedTxOutDirection::Spent => "spent",
+        };
+
+        Self {
+            block: JsonU64(src.get_block()),
+            timestamp: (src.get_timestamp_result_code()
+                == mc_api::watcher::TimestampResultCode::TimestampFound)
+                .then(|| JsonU64(src.get_timestamp())),
+            direction: direction_str.to_owned(),
+            subaddress_index: src.get_subaddress_index(),
+            public_key: hex::encode(src.get_public_key().get_data()),
+            key_image: hex::encode(src.get_key_image().get_data()),
+            value: JsonU64(src.get_value()),
+            token_id: JsonU64(src.get_token_id()),
+            memo: src
+                .has_decoded_memo()
+                .then(|| JsonDecodedMemo::from(src.get_decoded_memo())),
+            counterparty_hash: (!src.get_counterparty_hash().is_empty())
+                .then(|| hex::encode(src.get_counterparty_hash())),
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct JsonTransactionHistoryResponse {
+    pub events: Vec<JsonTransactionHistoryEvent>,
+    pub next_block: JsonU64,
+}
+
+impl From<&api::GetTransactionHistoryResponse> for JsonTransactionHistoryResponse {
+    fn from(src: &api::GetTransactionHistoryResponse) -> Self {
+        Self {
+            events: src
+                .get_events()
+                .iter()
+                .map(JsonTransactionHistoryEvent::from)
+                .collect(),
+            next_block: JsonU64(src.get_next_block()),
+        }
+    }
+}
+
 #[derive(Serialize, Default, Debug)]
 pub struct JsonBlockIndexByTxPubKeyResponse {
     pub block_index: String,
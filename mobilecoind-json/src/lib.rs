@@ -3,3 +3,5 @@
 //! JSON wrapper for the mobilecoind API.
 
 pub mod data_types;
+pub mod openapi;
+pub mod rpc;
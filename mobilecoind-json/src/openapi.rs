@@ -0,0 +1,278 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! OpenAPI document for the JSON RPC endpoints.
+//!
+//! The schemas are generated from the protobuf descriptors embedded in the
+//! mobilecoind API types, so they always match the messages accepted and
+//! returned by [crate::rpc::call].
+
+use crate::rpc::{rpc_methods, RpcMethod};
+use mc_mobilecoind_api as api;
+use protobuf::descriptor::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto,
+    FieldDescriptorProto_Label as Label, FieldDescriptorProto_Type as Type, FileDescriptorProto,
+};
+use rocket::serde::json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+
+/// The protobuf files that the mobilecoind API messages are defined in.
+fn file_descriptors() -> Vec<&'static FileDescriptorProto> {
+    vec![
+        api::mobilecoind_api::file_descriptor_proto(),
+        api::external::file_descriptor_proto(),
+        api::blockchain::file_descriptor_proto(),
+        api::watcher::file_descriptor_proto(),
+        api::ledger::file_descriptor_proto(),
+        mc_api::quorum_set::file_descriptor_proto(),
+    ]
+}
+
+/// Message and enum definitions, indexed by their fully-qualified name.
+#[derive(Default)]
+struct Definitions {
+    messages: HashMap<String, &'static DescriptorProto>,
+    enums: HashMap<String, &'static EnumDescriptorProto>,
+}
+
+impl Definitions {
+    fn new() -> Self {
+        let mut definitions = Self::default();
+        for file in file_descriptors() {
+            let package = file.get_package();
+            for message in file.get_message_type() {
+                definitions.add_message(package, message);
+            }
+            for enum_type in file.get_enum_type() {
+                definitions.add_enum(package, enum_type);
+            }
+        }
+        definitions
+    }
+
+    fn add_message(&mut self, scope: &str, message: &'static DescriptorProto) {
+        let name = qualified_name(scope, message.get_name());
+        for nested in message.get_nested_type() {
+            self.add_message(&name, nested);
+        }
+        for enum_type in message.get_enum_type() {
+            self.add_enum(&name, enum_type);
+        }
+        self.messages.insert(name, message);
+    }
+
+    fn add_enum(&mut self, scope: &str, enum_type: &'static EnumDescriptorProto) {
+        self.enums
+            .insert(qualified_name(scope, enum_type.get_name()), enum_type);
+    }
+
+    /// The map entry message a field refers to, if it is a map field.
+    fn map_entry(&self, field: &FieldDescriptorProto) -> Option<&'static DescriptorProto> {
+        if field.get_label() != Label::LABEL_REPEATED
+            || field.get_field_type() != Type::TYPE_MESSAGE
+        {
+            return None;
+        }
+        self.messages
+            .get(type_name(field))
+            .copied()
+            .filter(|message| message.get_options().get_map_entry())
+    }
+
+    /// The schema of a single field. Referenced messages and enums are added to
+    /// `refs`.
+    fn field_schema(&self, field: &FieldDescriptorProto, refs: &mut Vec<String>) -> Value {
+        if let Some(entry) = self.map_entry(field) {
+            let value = entry
+                .get_field()
+                .iter()
+                .find(|field| field.get_name() == "value")
+                .map(|field| self.field_schema(field, refs))
+                .unwrap_or_else(|| json!({}));
+            return json!({ "type": "object", "additionalProperties": value });
+        }
+
+        let schema = match field.get_field_type() {
+            Type::TYPE_DOUBLE => json!({ "type": "number", "format": "double" }),
+            Type::TYPE_FLOAT => json!({ "type": "number", "format": "float" }),
+            Type::TYPE_INT32 | Type::TYPE_SINT32 | Type::TYPE_SFIXED32 => {
+                json!({ "type": "integer", "format": "int32" })
+            }
+            Type::TYPE_UINT32 | Type::TYPE_FIXED32 => {
+                json!({ "type": "integer", "format": "uint32" })
+            }
+            // The protobuf JSON mapping encodes 64-bit integers as strings.
+            Type::TYPE_INT64 | Type::TYPE_SINT64 | Type::TYPE_SFIXED64 => {
+                json!({ "type": "string", "format": "int64" })
+            }
+            Type::TYPE_UINT64 | Type::TYPE_FIXED64 => {
+                json!({ "type": "string", "format": "uint64" })
+            }
+            Type::TYPE_BOOL => json!({ "type": "boolean" }),
+            Type::TYPE_STRING => json!({ "type": "string" }),
+            Type::TYPE_BYTES => json!({ "type": "string", "format": "byte" }),
+            Type::TYPE_ENUM | Type::TYPE_MESSAGE | Type::TYPE_GROUP => {
+                let name = type_name(field).to_owned();
+                let schema = json!({ "$ref": schema_ref(&name) });
+                refs.push(name);
+                schema
+            }
+        };
+
+        if field.get_label() == Label::LABEL_REPEATED {
+            json!({ "type": "array", "items": schema })
+        } else {
+            schema
+        }
+    }
+
+    /// The schema of the message or enum `name`.
+    fn schema(&self, name: &str, refs: &mut Vec<String>) -> Value {
+        if let Some(enum_type) = self.enums.get(name) {
+            let values: Vec<&str> = enum_type
+                .get_value()
+                .iter()
+                .map(|value| value.get_name())
+                .collect();
+            return json!({ "type": "string", "enum": values });
+        }
+
+        // google.protobuf.Empty, and anything else defined outside of the
+        // mobilecoind API files, is described as an arbitrary object.
+        let mut schema = json!({ "type": "object" });
+        if let Some(message) = self.messages.get(name) {
+            let mut properties = json!({});
+            for field in message.get_field() {
+                properties[field.get_name()] = self.field_schema(field, refs);
+            }
+            schema["properties"] = properties;
+        }
+        schema
+    }
+}
+
+/// The fully-qualified name of a definition in `scope`.
+fn qualified_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+/// The fully-qualified name of the message or enum a field refers to.
+fn type_name(field: &FieldDescriptorProto) -> &str {
+    field.get_type_name().trim_start_matches('.')
+}
+
+fn schema_ref(name: &str) -> String {
+    format!("#/components/schemas/{name}")
+}
+
+fn json_content(name: &str) -> Value {
+    json!({ "application/json": { "schema": { "$ref": schema_ref(name) } } })
+}
+
+fn path_item(method: &RpcMethod) -> Value {
+    json!({
+        "post": {
+            "operationId": method.name,
+            "requestBody": {
+                "required": true,
+                "content": json_content(method.request.full_name()),
+            },
+            "responses": {
+                "200": {
+                    "description": "The RPC response",
+                    "content": json_content(method.response.full_name()),
+                },
+                "400": { "description": "The request could not be parsed" },
+                "500": { "description": "The RPC failed" },
+            },
+        },
+    })
+}
+
+/// Generate an OpenAPI 3 document describing every JSON RPC endpoint.
+pub fn openapi_document() -> Value {
+    let definitions = Definitions::new();
+
+    let mut paths = json!({});
+    let mut refs = Vec::new();
+    for method in rpc_methods() {
+        paths[format!("/rpc/{}", method.name)] = path_item(&method);
+        refs.push(method.request.full_name().to_owned());
+        refs.push(method.response.full_name().to_owned());
+    }
+
+    let mut schemas = json!({});
+    let mut seen = BTreeSet::new();
+    while let Some(name) = refs.pop() {
+        if seen.insert(name.clone()) {
+            schemas[name.as_str()] = definitions.schema(&name, &mut refs);
+        }
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "mobilecoind",
+            "description": "The mobilecoind API, using the protobuf JSON mapping",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_openapi_document() {
+        let document = openapi_document();
+        let schemas = &document["components"]["schemas"];
+
+        // Every RPC has an endpoint.
+        for method in rpc_methods() {
+            let path = &document["paths"][format!("/rpc/{}", method.name)];
+            assert!(path["post"].is_object(), "{}", method.name);
+        }
+
+        // Every referenced schema is defined.
+        let mut values = vec![&document];
+        while let Some(value) = values.pop() {
+            match value {
+                Value::Object(object) => {
+                    if let Some(Value::String(reference)) = object.get("$ref") {
+                        let name = reference.trim_start_matches("#/components/schemas/");
+                        assert!(schemas[name].is_object(), "{reference}");
+                    }
+                    values.extend(object.values());
+                }
+                Value::Array(array) => values.extend(array),
+                _ => {}
+            }
+        }
+
+        // Spot check how fields are described.
+        let swap = &schemas["mobilecoind_api.GenerateSwapRequest"]["properties"];
+        assert_eq!(swap["counter_value"]["type"], "string");
+        assert_eq!(swap["counter_value"]["format"], "uint64");
+        assert_eq!(
+            swap["input"]["$ref"],
+            "#/components/schemas/mobilecoind_api.UnspentTxOut"
+        );
+
+        let results = &schemas["mobilecoind_api.GetTxOutResultsByPubKeyResponse"]["properties"];
+        assert_eq!(results["results"]["type"], "array");
+        assert_eq!(
+            results["results"]["items"]["$ref"],
+            "#/components/schemas/fog_ledger.TxOutResult"
+        );
+        assert_eq!(schemas["fog_ledger.TxOutResultCode"]["enum"][1], "Found");
+
+        let compressed = &schemas["external.CompressedRistretto"]["properties"];
+        assert_eq!(compressed["data"]["format"], "byte");
+    }
+}
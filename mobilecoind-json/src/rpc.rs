@@ -0,0 +1,199 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Generic JSON access to every unary mobilecoind RPC.
+//!
+//! Requests and responses use the canonical protobuf JSON mapping of the
+//! mobilecoind API messages, with the field names from the proto files: 64-bit
+//! integers are strings, `bytes` fields are base64 strings and enums are
+//! referred to by name.
+
+use mc_mobilecoind_api::{self as api, mobilecoind_api_grpc::MobilecoindApiClient};
+use protobuf::{
+    json::{parse_from_str, print_to_string_with_options, PrintOptions},
+    reflect::MessageDescriptor,
+    Message,
+};
+use std::fmt;
+
+/// A mobilecoind RPC that can be called over JSON.
+pub struct RpcMethod {
+    /// The name of the method in the mobilecoind service definition.
+    pub name: &'static str,
+
+    /// The request message.
+    pub request: &'static MessageDescriptor,
+
+    /// The response message.
+    pub response: &'static MessageDescriptor,
+}
+
+/// Errors returned when calling an RPC over JSON.
+#[derive(Debug)]
+pub enum RpcError {
+    /// There is no RPC with this name.
+    UnknownMethod(String),
+
+    /// The request is not valid JSON for the request message.
+    InvalidRequest(String),
+
+    /// The RPC failed.
+    Grpc(grpcio::Error),
+
+    /// The response could not be converted to JSON.
+    InvalidResponse(String),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMethod(name) => write!(f, "Unknown method: {name}"),
+            Self::InvalidRequest(err) => write!(f, "Invalid request: {err}"),
+            Self::Grpc(err) => write!(f, "RPC failed: {err}"),
+            Self::InvalidResponse(err) => write!(f, "Invalid response: {err}"),
+        }
+    }
+}
+
+fn parse_request<M: Message>(body: &str) -> Result<M, RpcError> {
+    // Requests without fields may be sent without a body.
+    let body = if body.trim().is_empty() { "{}" } else { body };
+    parse_from_str(body).map_err(|err| RpcError::InvalidRequest(format!("{err:?}")))
+}
+
+fn print_response(response: &dyn Message) -> Result<String, RpcError> {
+    let print_options = PrintOptions {
+        proto_field_name: true,
+        always_output_default_values: true,
+        ..Default::default()
+    };
+    print_to_string_with_options(response, &print_options)
+        .map_err(|err| RpcError::InvalidResponse(format!("{err:?}")))
+}
+
+macro_rules! rpc_methods {
+    ($($name:literal $method:ident $request:ident $response:ident,)*) => {
+        /// Every unary mobilecoind RPC, in the order of the service definition.
+        pub fn rpc_methods() -> Vec<RpcMethod> {
+            vec![$(
+                RpcMethod {
+                    name: $name,
+                    request: api::$request::descriptor_static(),
+                    response: api::$response::descriptor_static(),
+                },
+            )*]
+        }
+
+        /// Call the RPC `name` with a JSON request, returning the JSON response.
+        pub fn call(
+            client: &MobilecoindApiClient,
+            name: &str,
+            body: &str,
+        ) -> Result<String, RpcError> {
+            match name {
+                $(
+                    $name => {
+                        let request: api::$request = parse_request(body)?;
+                        let response = client.$method(&request).map_err(RpcError::Grpc)?;
+                        print_response(&response)
+                    }
+                )*
+                _ => Err(RpcError::UnknownMethod(name.to_owned())),
+            }
+        }
+    };
+}
+
+// SubscribeProcessedBlocks is a streaming RPC, so it is not included.
+// GetProcessedBlock can be polled instead.
+rpc_methods! {
+    // Monitors
+    "AddMonitor" add_monitor AddMonitorRequest AddMonitorResponse,
+    "RemoveMonitor" remove_monitor RemoveMonitorRequest Empty,
+    "GetMonitorList" get_monitor_list Empty GetMonitorListResponse,
+    "GetMonitorStatus" get_monitor_status GetMonitorStatusRequest GetMonitorStatusResponse,
+    "GetUnspentTxOutList" get_unspent_tx_out_list GetUnspentTxOutListRequest GetUnspentTxOutListResponse,
+    "GetAllUnspentTxOut" get_all_unspent_tx_out GetAllUnspentTxOutRequest GetAllUnspentTxOutResponse,
+    "SetDefragmentationPolicy" set_defragmentation_policy SetDefragmentationPolicyRequest Empty,
+
+    // HD monitors
+    "AddHdMonitor" add_hd_monitor AddHdMonitorRequest AddHdMonitorResponse,
+    "RemoveHdMonitor" remove_hd_monitor RemoveHdMonitorRequest Empty,
+    "GetHdMonitorStatus" get_hd_monitor_status GetHdMonitorStatusRequest GetHdMonitorStatusResponse,
+    "GetHdMonitorBalance" get_hd_monitor_balance GetHdMonitorBalanceRequest GetHdMonitorBalanceResponse,
+
+    // Watch-only monitors
+    "GetUnsyncedTxOutList" get_unsynced_tx_out_list GetUnsyncedTxOutListRequest GetUnsyncedTxOutListResponse,
+    "SyncTxOuts" sync_tx_outs SyncTxOutsRequest SyncTxOutsResponse,
+    "GenerateUnsignedTx" generate_unsigned_tx GenerateUnsignedTxRequest GenerateUnsignedTxResponse,
+    "SubmitSignedTx" submit_signed_tx SubmitSignedTxRequest SubmitTxResponse,
+
+    // Utilities
+    "GenerateRootEntropy" generate_root_entropy Empty GenerateRootEntropyResponse,
+    "GenerateMnemonic" generate_mnemonic Empty GenerateMnemonicResponse,
+    "GetAccountKeyFromRootEntropy" get_account_key_from_root_entropy GetAccountKeyFromRootEntropyRequest GetAccountKeyResponse,
+    "GetAccountKeyFromMnemonic" get_account_key_from_mnemonic GetAccountKeyFromMnemonicRequest GetAccountKeyResponse,
+    "GetPublicAddress" get_public_address GetPublicAddressRequest GetPublicAddressResponse,
+    "GetShortAddressHash" get_short_address_hash GetShortAddressHashRequest GetShortAddressHashResponse,
+    "ValidateAuthenticatedSenderMemo" validate_authenticated_sender_memo ValidateAuthenticatedSenderMemoRequest ValidateAuthenticatedSenderMemoResponse,
+    "TxOutViewKeyMatch" tx_out_view_key_match TxOutViewKeyMatchRequest TxOutViewKeyMatchResponse,
+
+    // b58 codes
+    "ParseRequestCode" parse_request_code ParseRequestCodeRequest ParseRequestCodeResponse,
+    "CreateRequestCode" create_request_code CreateRequestCodeRequest CreateRequestCodeResponse,
+    "ParseTransferCode" parse_transfer_code ParseTransferCodeRequest ParseTransferCodeResponse,
+    "CreateTransferCode" create_transfer_code CreateTransferCodeRequest CreateTransferCodeResponse,
+    "ParseAddressCode" parse_address_code ParseAddressCodeRequest ParseAddressCodeResponse,
+    "CreateAddressCode" create_address_code CreateAddressCodeRequest CreateAddressCodeResponse,
+
+    // Payment requests
+    "AddPaymentRequest" add_payment_request AddPaymentRequestRequest AddPaymentRequestResponse,
+    "RemovePaymentRequest" remove_payment_request RemovePaymentRequestRequest Empty,
+    "GetPaymentRequestStatus" get_payment_request_status GetPaymentRequestStatusRequest GetPaymentRequestStatusResponse,
+    "GetPaymentRequestList" get_payment_request_list Empty GetPaymentRequestListResponse,
+
+    // Transactions
+    "GetMixins" get_mixins GetMixinsRequest GetMixinsResponse,
+    "GetMembershipProofs" get_membership_proofs GetMembershipProofsRequest GetMembershipProofsResponse,
+    "GenerateTx" generate_tx GenerateTxRequest GenerateTxResponse,
+    "GenerateOptimizationTx" generate_optimization_tx GenerateOptimizationTxRequest GenerateOptimizationTxResponse,
+    "GenerateTransferCodeTx" generate_transfer_code_tx GenerateTransferCodeTxRequest GenerateTransferCodeTxResponse,
+    "GenerateTxFromTxOutList" generate_tx_from_tx_out_list GenerateTxFromTxOutListRequest GenerateTxFromTxOutListResponse,
+    "GenerateBurnRedemptionTx" generate_burn_redemption_tx GenerateBurnRedemptionTxRequest GenerateBurnRedemptionTxResponse,
+    "SubmitTx" submit_tx SubmitTxRequest SubmitTxResponse,
+
+    // Signed contingent inputs
+    "GenerateSwap" generate_swap GenerateSwapRequest GenerateSwapResponse,
+    "GenerateMixedTx" generate_mixed_tx GenerateMixedTxRequest GenerateMixedTxResponse,
+
+    // Databases
+    "GetLedgerInfo" get_ledger_info Empty GetLedgerInfoResponse,
+    "GetBlockInfo" get_block_info GetBlockInfoRequest GetBlockInfoResponse,
+    "GetBlock" get_block GetBlockRequest GetBlockResponse,
+    "GetLatestBlock" get_latest_block Empty GetBlockResponse,
+    "GetBlocksData" get_blocks_data GetBlocksDataRequest GetBlocksDataResponse,
+    "GetTxStatusAsSender" get_tx_status_as_sender SubmitTxResponse GetTxStatusAsSenderResponse,
+    "GetTxStatusAsReceiver" get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse,
+    "GetProcessedBlock" get_processed_block GetProcessedBlockRequest GetProcessedBlockResponse,
+    "GetTransactionHistory" get_transaction_history GetTransactionHistoryRequest GetTransactionHistoryResponse,
+    "GetBlockIndexByTxPubKey" get_block_index_by_tx_pub_key GetBlockIndexByTxPubKeyRequest GetBlockIndexByTxPubKeyResponse,
+    "GetTxOutResultsByPubKey" get_tx_out_results_by_pub_key GetTxOutResultsByPubKeyRequest GetTxOutResultsByPubKeyResponse,
+
+    // Convenience calls
+    "GetBalance" get_balance GetBalanceRequest GetBalanceResponse,
+    "SendPayment" send_payment SendPaymentRequest SendPaymentResponse,
+    "PayAddressCode" pay_address_code PayAddressCodeRequest SendPaymentResponse,
+
+    // Scheduled payments
+    "CreateScheduledPayment" create_scheduled_payment CreateScheduledPaymentRequest CreateScheduledPaymentResponse,
+    "GetScheduledPaymentList" get_scheduled_payment_list Empty GetScheduledPaymentListResponse,
+    "CancelScheduledPayment" cancel_scheduled_payment CancelScheduledPaymentRequest Empty,
+
+    // Network status
+    "GetNetworkStatus" get_network_status Empty GetNetworkStatusResponse,
+
+    // Database encryption
+    "SetDbPassword" set_db_password SetDbPasswordRequest Empty,
+    "UnlockDb" unlock_db UnlockDbRequest Empty,
+
+    "GetVersion" get_version Empty MobilecoindVersionResponse,
+}
@@ -4,13 +4,22 @@
 //! mobilecoind daemon entry point
 
 use clap::Parser;
+use grpcio::{EnvBuilder, ServerBuilder};
 use mc_common::logger::{create_app_logger, log, o, Logger};
+use mc_consensus_api::consensus_common_grpc::create_block_data_api;
 use mc_ledger_db::{Ledger, LedgerDB};
-use mc_ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
+use mc_ledger_sync::{
+    BlockDataApiService, LedgerSyncServiceThread, PeerTransactionsFetcher, PollingNetworkState,
+    ReqwestTransactionsFetcher,
+};
 use mc_mobilecoind::{
-    config::Config, database::Database, payments::TransactionsManager, service::Service,
-    t3_sync::T3SyncThread,
+    config::Config, database::Database, defragmentation::DefragmentationThread,
+    payments::TransactionsManager, resubmission::ResubmissionThread,
+    scheduled_payments::ScheduledPaymentThread, service::Service, t3_sync::T3SyncThread,
+    webhooks::WebhookThread,
 };
+use mc_mobilecoind_api::MobilecoindUri;
+use mc_util_grpc::{ConnectionUriGrpcioServer, HealthService};
 use mc_util_telemetry::setup_default_tracer;
 use mc_watcher::{watcher::WatcherSyncThread, watcher_db::create_or_open_rw_watcher_db};
 use std::{
@@ -51,14 +60,58 @@ fn main() {
         logger.clone(),
     )));
 
-    let transactions_fetcher = ReqwestTransactionsFetcher::new(
+    let mut transactions_fetcher = ReqwestTransactionsFetcher::new(
         config.tx_source_urls.clone().unwrap_or_default(),
         logger.clone(),
     )
     .expect("Failed creating ReqwestTransactionsFetcher");
+    transactions_fetcher.set_manifest_signers(&config.archive_manifest_signers);
+    if let Some(max_in_flight) = config.max_in_flight_block_requests {
+        transactions_fetcher.set_max_in_flight_requests(max_in_flight);
+    }
 
     // Create the ledger_db.
-    let ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
+    let mut ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
+    // Index before pruning, since block timestamps come from signatures.
+    if config.ledger_db_secondary_indexes {
+        log::info!(logger, "Enabling ledger db secondary indexes");
+        ledger_db
+            .enable_secondary_indexes()
+            .expect("Failed enabling ledger db secondary indexes");
+    }
+    if let Some(num_retained_blocks) = config.ledger_db_prune {
+        log::info!(
+            logger,
+            "Pruning ledger db, retaining signatures and metadata of the last {} blocks",
+            num_retained_blocks
+        );
+        ledger_db
+            .enable_pruning(num_retained_blocks)
+            .expect("Failed pruning ledger db");
+    }
+
+    // Fetch blocks from the block peers first, if any. Their blocks are only used
+    // if the metadata is signed by a trusted consensus node.
+    let mut block_peer_signers = config.peers_config.msg_signer_keys();
+    block_peer_signers.extend(config.block_peer_signers.iter().cloned());
+    if !config.block_peers.is_empty() && block_peer_signers.is_empty() {
+        log::warn!(
+            logger,
+            "No trusted signers for the block peers, blocks will only be fetched from the tx-source-urls. Use peer URIs with a consensus-msg-key or --block-peer-signer."
+        );
+    }
+    let peer_transactions_fetcher = PeerTransactionsFetcher::new(
+        &config.block_peers,
+        block_peer_signers,
+        transactions_fetcher.clone(),
+        Arc::new(
+            EnvBuilder::new()
+                .cq_count(1)
+                .name_prefix("block-peer")
+                .build(),
+        ),
+        logger.clone(),
+    );
 
     // Start ledger sync thread unless running in offline mode.
     let _ledger_sync_service_thread = if config.offline {
@@ -68,12 +121,18 @@ fn main() {
             ledger_db.clone(),
             peer_manager.clone(),
             network_state.clone(),
-            transactions_fetcher.clone(),
+            peer_transactions_fetcher,
             config.poll_interval,
             logger.clone(),
         ))
     };
 
+    // Serve the local ledger to other clients, if requested.
+    let _block_data_server = config
+        .serve_blocks_uri
+        .as_ref()
+        .map(|uri| start_block_data_server(ledger_db.clone(), uri, &logger));
+
     // Optionally instantiate the watcher sync thread and get the watcher_db handle.
     let (watcher_db, _watcher_sync_thread) = match &config.watcher_db {
         Some(watcher_db_path) => {
@@ -98,6 +157,7 @@ fn main() {
                         ledger_db.clone(),
                         config.poll_interval,
                         false,
+                        &config.archive_manifest_signers,
                         logger.clone(),
                     )
                     .expect("Failed starting watcher thread"),
@@ -123,6 +183,7 @@ fn main() {
                 mobilecoind_db.clone(),
                 peer_manager,
                 config.get_fog_resolver_factory(logger.clone()),
+                config.decoy_distribution,
                 logger.clone(),
             );
 
@@ -141,6 +202,63 @@ fn main() {
                 _ => None,
             };
 
+            let _webhook_thread = match (&config.webhook_url, &config.webhook_secret) {
+                (Some(webhook_url), Some(webhook_secret)) => Some(WebhookThread::start(
+                    ledger_db.clone(),
+                    mobilecoind_db.clone(),
+                    webhook_url.clone(),
+                    webhook_secret.clone(),
+                    config.poll_interval,
+                    logger.clone(),
+                )),
+                _ => None,
+            };
+
+            // Automatic defragmentation needs to submit transactions, which is not possible
+            // in offline mode.
+            let _defragmentation_thread = if config.offline {
+                None
+            } else {
+                Some(DefragmentationThread::start(
+                    ledger_db.clone(),
+                    mobilecoind_db.clone(),
+                    transactions_manager.clone(),
+                    network_state.clone(),
+                    config.poll_interval,
+                    logger.clone(),
+                ))
+            };
+
+            // Transactions cannot be submitted in offline mode, so there is nothing to
+            // resubmit.
+            let _resubmission_thread = if config.offline {
+                None
+            } else {
+                Some(ResubmissionThread::start(
+                    ledger_db.clone(),
+                    mobilecoind_db.clone(),
+                    transactions_manager.clone(),
+                    network_state.clone(),
+                    config.poll_interval,
+                    logger.clone(),
+                ))
+            };
+
+            // Scheduled payments are not executed in offline mode, they stay due until
+            // mobilecoind runs online again.
+            let _scheduled_payment_thread = if config.offline {
+                None
+            } else {
+                Some(ScheduledPaymentThread::start(
+                    ledger_db.clone(),
+                    mobilecoind_db.clone(),
+                    transactions_manager.clone(),
+                    network_state.clone(),
+                    config.poll_interval,
+                    logger.clone(),
+                ))
+            };
+
             let _api_server = Service::new(
                 ledger_db,
                 mobilecoind_db,
@@ -173,6 +291,34 @@ fn main() {
     }
 }
 
+/// Start a gRPC server serving the blocks of `ledger_db` with the block data
+/// API.
+fn start_block_data_server(
+    ledger_db: LedgerDB,
+    uri: &MobilecoindUri,
+    logger: &Logger,
+) -> grpcio::Server {
+    log::info!(logger, "Serving blocks on {}", uri);
+
+    let block_data_service =
+        create_block_data_api(BlockDataApiService::new(ledger_db, logger.clone()));
+    let health_service = HealthService::new(None, logger.clone()).into_service();
+    let env = Arc::new(
+        EnvBuilder::new()
+            .cq_count(1)
+            .name_prefix("BlockData-RPC".to_string())
+            .build(),
+    );
+
+    let mut server = ServerBuilder::new(env)
+        .register_service(health_service)
+        .register_service(block_data_service)
+        .build_using_uri(uri, logger.clone())
+        .expect("Could not build gRPC server for the serve blocks URI");
+    server.start();
+    server
+}
+
 fn create_or_open_ledger_db(
     config: &Config,
     logger: &Logger,
@@ -234,14 +380,41 @@ fn create_or_open_ledger_db(
                     .unwrap_or_else(|_| panic!("Failed creating directory {:?}", config.ledger_db));
             }
 
-            let src = format!("{ledger_db_bootstrap}/data.mdb");
-            std::fs::copy(src.clone(), &ledger_db_file).unwrap_or_else(|_| {
-                panic!(
-                    "Failed copying ledger from {} into directory {}",
-                    src,
-                    ledger_db_file.display()
+            if mc_ledger_snapshot::is_snapshot_dir(ledger_db_bootstrap) {
+                // Snapshots are verified before being moved into place, and refuse to
+                // overwrite an existing file. We only get here if the existing ledger is
+                // empty, so it is safe to remove.
+                if ledger_db_file.exists() {
+                    std::fs::remove_file(&ledger_db_file).unwrap_or_else(|_| {
+                        panic!("Failed removing empty ledger {}", ledger_db_file.display())
+                    });
+                }
+                let mut trusted_signers = config.peers_config.msg_signer_keys();
+                trusted_signers.extend(config.ledger_db_bootstrap_signers.iter().cloned());
+                let manifest = mc_ledger_snapshot::import_snapshot(
+                    ledger_db_bootstrap,
+                    &config.ledger_db,
+                    &trusted_signers,
+                    logger,
                 )
-            });
+                .unwrap_or_else(|err| {
+                    panic!("Failed importing ledger snapshot from {ledger_db_bootstrap}: {err}")
+                });
+                log::info!(
+                    logger,
+                    "Imported ledger snapshot up to block {}",
+                    manifest.block_index
+                );
+            } else {
+                let src = format!("{ledger_db_bootstrap}/data.mdb");
+                std::fs::copy(src.clone(), &ledger_db_file).unwrap_or_else(|_| {
+                    panic!(
+                        "Failed copying ledger from {} into directory {}",
+                        src,
+                        ledger_db_file.display()
+                    )
+                });
+            }
         }
         None => {
             log::info!(
@@ -9,13 +9,17 @@ use mc_attestation_verifier::{TrustedIdentity, TrustedMrSignerIdentity};
 use mc_common::{logger::Logger, ResponderId};
 use mc_connection::{ConnectionManager, HardcodedCredentialsProvider, ThickClient};
 use mc_consensus_scp::QuorumSet;
+use mc_crypto_keys::Ed25519Public;
 use mc_fog_report_connection::GrpcFogReportConnection;
 use mc_fog_report_resolver::FogResolver;
+use mc_ledger_snapshot::parse_ed25519_public;
 use mc_mobilecoind_api::MobilecoindUri;
 use mc_sgx_css::Signature;
 use mc_t3_api::T3Uri;
+use mc_transaction_builder::DecoyDistribution;
 use mc_util_parse::{load_css_file, parse_duration_in_seconds};
 use mc_util_uri::{ConnectionUri, ConsensusClientUri, FogUri};
+use reqwest::Url;
 #[cfg(all(feature = "ip-check", not(feature = "bypass-ip-check")))]
 use reqwest::{
     blocking::Client,
@@ -31,11 +35,25 @@ pub struct Config {
     #[clap(long, default_value = "/tmp/ledgerdb", env = "MC_LEDGER_DB")]
     pub ledger_db: PathBuf,
 
-    /// Path to existing ledger db that contains the origin block, used when
-    /// initializing new ledger dbs.
+    /// Path to existing ledger db that contains the origin block, or to a
+    /// ledger snapshot created by mc-ledger-snapshot, used when initializing
+    /// new ledger dbs.
     #[clap(long, env = "MC_LEDGER_DB_BOOTSTRAP")]
     pub ledger_db_bootstrap: Option<String>,
 
+    /// Hex-encoded Ed25519 public keys of consensus nodes trusted to sign the
+    /// last block of a bootstrap snapshot, in addition to the
+    /// `consensus-msg-key` of the peers. Bootstrapping from a snapshot is
+    /// refused if no key is trusted.
+    #[clap(
+        long = "ledger-db-bootstrap-signer",
+        value_parser = parse_ed25519_public,
+        use_value_delimiter = true,
+        requires = "ledger_db_bootstrap",
+        env = "MC_LEDGER_DB_BOOTSTRAP_SIGNERS"
+    )]
+    pub ledger_db_bootstrap_signers: Vec<Ed25519Public>,
+
     /// Path to watcher db (lmdb).
     #[clap(long, env = "MC_WATCHER_DB")]
     pub watcher_db: Option<PathBuf>,
@@ -64,6 +82,52 @@ pub struct Config {
     )]
     pub tx_source_urls: Option<Vec<String>>,
 
+    /// Number of block requests to keep in flight when syncing from the
+    /// tx-source-urls. When set, upcoming blocks are fetched ahead of time and
+    /// each request goes to the source with the best latency and error rate.
+    #[clap(long, env = "MC_MAX_IN_FLIGHT_BLOCK_REQUESTS")]
+    pub max_in_flight_block_requests: Option<usize>,
+
+    /// Hex-encoded Ed25519 public keys trusted to sign the manifests of the
+    /// compressed archives published at the tx-source-urls. Compressed
+    /// archives are only used when at least one key is given.
+    #[clap(
+        long = "archive-manifest-signer",
+        value_parser = parse_ed25519_public,
+        use_value_delimiter = true,
+        env = "MC_ARCHIVE_MANIFEST_SIGNERS"
+    )]
+    pub archive_manifest_signers: Vec<Ed25519Public>,
+
+    /// Other full clients serving their ledger with --serve-blocks-uri, to
+    /// fetch blocks from before falling back to the tx-source-urls.
+    ///
+    /// A block from a block peer is only used if its metadata is signed by
+    /// the `consensus-msg-key` of one of the peers, or by one of the
+    /// block-peer-signers.
+    #[clap(
+        long = "block-peer",
+        use_value_delimiter = true,
+        env = "MC_BLOCK_PEERS"
+    )]
+    pub block_peers: Vec<MobilecoindUri>,
+
+    /// Hex-encoded Ed25519 public keys of consensus nodes trusted to sign the
+    /// metadata of blocks fetched from block peers.
+    #[clap(
+        long = "block-peer-signer",
+        value_parser = parse_ed25519_public,
+        use_value_delimiter = true,
+        requires = "block_peers",
+        env = "MC_BLOCK_PEER_SIGNERS"
+    )]
+    pub block_peer_signers: Vec<Ed25519Public>,
+
+    /// URI to serve the local ledger on, with the read-only block data API, so
+    /// that other clients can use this one as a block peer.
+    #[clap(long, env = "MC_SERVE_BLOCKS_URI")]
+    pub serve_blocks_uri: Option<MobilecoindUri>,
+
     /// How many seconds to wait between polling.
     #[clap(long, default_value = "5", value_parser = parse_duration_in_seconds, env = "MC_POLL_INTERVAL")]
     pub poll_interval: Duration,
@@ -96,6 +160,18 @@ pub struct Config {
     #[clap(long, env = "MC_LEDGER_DB_MIGRATE")]
     pub ledger_db_migrate: bool,
 
+    /// Run the ledger db in pruned mode, keeping block signatures and metadata
+    /// only for this many most recent blocks. Older ones are permanently
+    /// deleted. TxOuts and key images are always kept.
+    #[clap(long, env = "MC_LEDGER_DB_PRUNE")]
+    pub ledger_db_prune: Option<u64>,
+
+    /// Maintain secondary indexes in the ledger db, allowing blocks to be
+    /// looked up by timestamp, minted TxOuts by token id and TxOuts by memo
+    /// presence.
+    #[clap(long, env = "MC_LEDGER_DB_SECONDARY_INDEXES")]
+    pub ledger_db_secondary_indexes: bool,
+
     /// An authorization token for the ipinfo.io service, if available
     #[clap(long, env = "MC_IP_INFO_TOKEN", default_value = "")]
     pub ip_info_token: String,
@@ -113,6 +189,27 @@ pub struct Config {
     /// T3 API Key
     #[clap(long, env = "T3_API_KEY", requires = "t3_uri")]
     pub t3_api_key: Option<String>,
+
+    /// Optional webhook URL. When provided, mobilecoind will POST monitor
+    /// events (received TxOuts, spent TxOuts and final statuses of submitted
+    /// transactions) to it.
+    #[clap(
+        long,
+        env = "MC_WEBHOOK_URL",
+        requires = "webhook_secret",
+        requires = "mobilecoind_db"
+    )]
+    pub webhook_url: Option<Url>,
+
+    /// Shared secret used to sign webhook requests with HMAC-SHA256.
+    #[clap(long, env = "MC_WEBHOOK_SECRET", requires = "webhook_url")]
+    pub webhook_secret: Option<String>,
+
+    /// Distribution that ring mixins are sampled from. Either `uniform`, or
+    /// `gamma:<shape>:<scale>` to sample mixin ages (in blocks) from a gamma
+    /// distribution.
+    #[clap(long, default_value = "uniform", value_parser = parse_decoy_distribution, env = "MC_DECOY_DISTRIBUTION")]
+    pub decoy_distribution: DecoyDistribution,
 }
 
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
@@ -126,6 +223,10 @@ fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, Strin
     Ok(quorum_set)
 }
 
+fn parse_decoy_distribution(src: &str) -> Result<DecoyDistribution, String> {
+    src.parse().map_err(|err| format!("{err}"))
+}
+
 /// Error type.
 #[derive(Display, Debug)]
 pub enum ConfigError {
@@ -322,6 +423,16 @@ impl PeersConfig {
             .collect()
     }
 
+    /// The message signing keys given by the peer URIs, which also sign the
+    /// metadata of the blocks the peers externalize.
+    pub fn msg_signer_keys(&self) -> Vec<Ed25519Public> {
+        self.peers
+            .iter()
+            .flatten()
+            .filter_map(|peer| peer.consensus_msg_key().ok())
+            .collect()
+    }
+
     /// Instantiate a client for each of the peer URIs.
     pub fn create_peers(
         &self,
@@ -4,7 +4,14 @@
 //! types.
 
 use crate::{
-    payments::{Outlay, OutlayV2, SciForTx, TxProposal},
+    defragmentation_store::{DefragmentationData, DefragmentationPolicy},
+    monitor_store::MonitorData,
+    payments::{Outlay, OutlayV2, SciForTx, TxProposal, UnsignedTxProposal},
+    unsynced_txo_store::UnsyncedTxOut,
+    utxo_selection::{
+        AvoidRecentlyAttempted, ExactMatch, LargestFirst, RandomSelection, SmallestFirst,
+        UtxoSelectionStrategy,
+    },
     utxo_store::UnspentTxOut,
 };
 use mc_account_keys::PublicAddress;
@@ -17,7 +24,7 @@ use mc_transaction_core::{
     tx::{Tx, TxOut},
     Amount, MemoPayload, TokenId,
 };
-use mc_transaction_extra::{MemoType, TxOutConfirmationNumber};
+use mc_transaction_extra::{MemoType, TxOutConfirmationNumber, UnsignedTx};
 use protobuf::RepeatedField;
 
 impl From<&UnspentTxOut> for api::UnspentTxOut {
@@ -67,6 +74,83 @@ impl TryFrom<&api::UnspentTxOut> for UnspentTxOut {
     }
 }
 
+impl From<&MonitorData> for api::MonitorStatus {
+    fn from(src: &MonitorData) -> Self {
+        let mut dst = Self::new();
+
+        match &src.account_key {
+            Some(account_key) => dst.set_account_key(account_key.into()),
+            None => {
+                if let Some(view_account_key) = &src.view_account_key {
+                    dst.set_view_account_key(view_account_key.into());
+                }
+                dst.set_watch_only(true);
+            }
+        }
+        dst.set_first_subaddress(src.first_subaddress);
+        dst.set_num_subaddresses(src.num_subaddresses);
+        dst.set_first_block(src.first_block);
+        dst.set_next_block(src.next_block);
+
+        dst
+    }
+}
+
+impl From<api::UtxoSelectionStrategy> for Box<dyn UtxoSelectionStrategy> {
+    fn from(src: api::UtxoSelectionStrategy) -> Self {
+        match src {
+            api::UtxoSelectionStrategy::SmallestFirst => Box::new(SmallestFirst),
+            api::UtxoSelectionStrategy::LargestFirst => Box::new(LargestFirst),
+            api::UtxoSelectionStrategy::ExactMatch => Box::new(ExactMatch::default()),
+            api::UtxoSelectionStrategy::RandomSelection => Box::new(RandomSelection),
+            api::UtxoSelectionStrategy::AvoidRecentlyAttempted => {
+                Box::new(AvoidRecentlyAttempted::<SmallestFirst>::default())
+            }
+        }
+    }
+}
+
+impl From<&DefragmentationPolicy> for api::DefragmentationPolicy {
+    fn from(src: &DefragmentationPolicy) -> Self {
+        let mut dst = Self::new();
+        dst.set_token_id(src.token_id);
+        dst.set_trigger_utxo_count(src.trigger_utxo_count);
+        dst.set_target_utxo_count(src.target_utxo_count);
+        dst.set_fee_budget(src.fee_budget);
+        dst
+    }
+}
+
+impl From<&api::DefragmentationPolicy> for DefragmentationPolicy {
+    fn from(src: &api::DefragmentationPolicy) -> Self {
+        Self {
+            token_id: src.token_id,
+            trigger_utxo_count: src.trigger_utxo_count,
+            target_utxo_count: src.target_utxo_count,
+            fee_budget: src.fee_budget,
+        }
+    }
+}
+
+impl From<&DefragmentationData> for api::DefragmentationStatus {
+    fn from(src: &DefragmentationData) -> Self {
+        let mut dst = Self::new();
+        dst.set_policy((&src.policy).into());
+        dst.set_active(src.active);
+        dst.set_subaddress_index(src.subaddress_index);
+        dst.set_num_utxos(src.num_utxos);
+        dst.set_defrag_id(src.defrag_id);
+        dst.set_fees_spent(src.fees_spent);
+        dst.set_remaining_budget(src.remaining_budget());
+        dst.set_num_txs_submitted(src.num_txs_submitted);
+        dst.set_num_txs_landed(src.num_txs_landed);
+        dst.set_num_txs_failed(src.num_txs_failed);
+        dst.set_tx_pending(src.pending_tx.is_some());
+        dst.set_last_error(src.last_error.clone());
+        dst
+    }
+}
+
 fn bytes_to_tx_private_key(bytes: &[u8]) -> Result<Option<RistrettoPrivate>, ConversionError> {
     if bytes.is_empty() {
         return Ok(None);
@@ -81,7 +165,7 @@ fn bytes_to_tx_private_key(bytes: &[u8]) -> Result<Option<RistrettoPrivate>, Con
 //
 // Note: This could be From<&MemoPayload> for api::DecodedMemo, but there are
 // orphan rules issues.
-fn decode_memo(memo_payload: &MemoPayload) -> api::DecodedMemo {
+pub fn decode_memo(memo_payload: &MemoPayload) -> api::DecodedMemo {
     let mut result = api::DecodedMemo::new();
 
     match MemoType::try_from(memo_payload) {
@@ -103,6 +187,32 @@ fn decode_memo(memo_payload: &MemoPayload) -> api::DecodedMemo {
             asm.set_payment_intent_id(memo.payment_intent_id());
             result.set_authenticated_sender_memo(asm);
         }
+        Ok(MemoType::Destination(memo)) => {
+            let mut dm = api::DestinationMemo::new();
+            dm.set_recipient_hash(memo.get_address_hash().as_ref().to_vec());
+            dm.set_num_recipients(memo.get_num_recipients() as u32);
+            dm.set_fee(memo.get_fee());
+            dm.set_total_outlay(memo.get_total_outlay());
+            result.set_destination_memo(dm);
+        }
+        Ok(MemoType::DestinationWithPaymentRequestId(memo)) => {
+            let mut dm = api::DestinationMemo::new();
+            dm.set_recipient_hash(memo.get_address_hash().as_ref().to_vec());
+            dm.set_num_recipients(memo.get_num_recipients() as u32);
+            dm.set_fee(memo.get_fee());
+            dm.set_total_outlay(memo.get_total_outlay());
+            dm.set_payment_request_id(memo.get_payment_request_id());
+            result.set_destination_memo(dm);
+        }
+        Ok(MemoType::DestinationWithPaymentIntentId(memo)) => {
+            let mut dm = api::DestinationMemo::new();
+            dm.set_recipient_hash(memo.get_address_hash().as_ref().to_vec());
+            dm.set_num_recipients(memo.get_num_recipients() as u32);
+            dm.set_fee(memo.get_fee());
+            dm.set_total_outlay(memo.get_total_outlay());
+            dm.set_payment_intent_id(memo.get_payment_intent_id());
+            result.set_destination_memo(dm);
+        }
         Ok(_) | Err(_) => {
             let mut um = api::UnknownMemo::new();
             um.set_type_bytes(memo_payload.get_memo_type().to_vec());
@@ -232,47 +342,144 @@ impl TryFrom<&api::TxProposal> for TxProposal {
 
         let tx = Tx::try_from(src.get_tx())?;
 
-        let outlay_index_to_tx_out_index = src
-            .get_outlay_index_to_tx_out_index()
-            .iter()
-            .map(|(key, val)| (*key as usize, *val as usize))
-            .collect::<HashMap<_, _>>();
+        let outlay_index_to_tx_out_index = outlay_index_to_tx_out_index_from_proto(
+            src.get_outlay_index_to_tx_out_index(),
+            outlays.len(),
+            tx.prefix.outputs.len(),
+        )?;
 
-        // Check that none of the indices are out of bound.
-        if outlay_index_to_tx_out_index.len() != outlays.len() {
-            return Err(ConversionError::IndexOutOfBounds);
-        }
+        let outlay_confirmation_numbers =
+            outlay_confirmation_numbers_from_proto(src.get_outlay_confirmation_numbers())?;
 
-        for (outlay_index, tx_out_index) in outlay_index_to_tx_out_index.iter() {
-            if *outlay_index >= outlays.len() || *tx_out_index >= tx.prefix.outputs.len() {
-                return Err(ConversionError::IndexOutOfBounds);
-            }
-        }
+        Ok(Self {
+            utxos,
+            outlays,
+            tx,
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers,
+            scis,
+        })
+    }
+}
 
-        let outlay_confirmation_numbers = src
-            .get_outlay_confirmation_numbers()
+impl From<&UnsignedTxProposal> for api::UnsignedTxProposal {
+    fn from(src: &UnsignedTxProposal) -> api::UnsignedTxProposal {
+        let mut dst = api::UnsignedTxProposal::new();
+
+        dst.set_input_list(RepeatedField::from_vec(
+            src.utxos.iter().map(|utxo| utxo.into()).collect(),
+        ));
+        dst.set_outlay_list(RepeatedField::from_vec(
+            src.outlays.iter().map(|outlay| outlay.into()).collect(),
+        ));
+        dst.set_unsigned_tx((&src.unsigned_tx).into());
+        dst.set_outlay_index_to_tx_out_index(
+            src.outlay_index_to_tx_out_index
+                .iter()
+                .map(|(key, val)| (*key as u64, *val as u64))
+                .collect(),
+        );
+        dst.set_outlay_confirmation_numbers(
+            src.outlay_confirmation_numbers
+                .iter()
+                .map(|val| val.to_vec())
+                .collect(),
+        );
+
+        dst
+    }
+}
+
+impl TryFrom<&api::UnsignedTxProposal> for UnsignedTxProposal {
+    type Error = ConversionError;
+
+    fn try_from(src: &api::UnsignedTxProposal) -> Result<Self, Self::Error> {
+        let utxos = src
+            .get_input_list()
             .iter()
-            .map(|src| match src.len() {
-                32 => {
-                    let mut bytes = [0u8; 32];
-                    bytes.copy_from_slice(src);
-                    Ok(TxOutConfirmationNumber::from(bytes))
-                }
-                _ => Err(ConversionError::IndexOutOfBounds),
-            })
-            .collect::<Result<Vec<TxOutConfirmationNumber>, ConversionError>>()?;
+            .map(UnspentTxOut::try_from)
+            .collect::<Result<Vec<UnspentTxOut>, ConversionError>>()?;
+
+        let outlays: Vec<OutlayV2> = src
+            .get_outlay_list()
+            .iter()
+            .map(OutlayV2::try_from)
+            .collect::<Result<_, _>>()?;
+
+        let unsigned_tx = UnsignedTx::try_from(src.get_unsigned_tx())?;
+
+        let outlay_index_to_tx_out_index = outlay_index_to_tx_out_index_from_proto(
+            src.get_outlay_index_to_tx_out_index(),
+            outlays.len(),
+            unsigned_tx.tx_prefix.outputs.len(),
+        )?;
+
+        let outlay_confirmation_numbers =
+            outlay_confirmation_numbers_from_proto(src.get_outlay_confirmation_numbers())?;
 
         Ok(Self {
             utxos,
             outlays,
-            tx,
+            unsigned_tx,
             outlay_index_to_tx_out_index,
             outlay_confirmation_numbers,
-            scis,
         })
     }
 }
 
+/// Convert an outlay index -> TxOut index map, checking that none of the
+/// indices are out of bound.
+fn outlay_index_to_tx_out_index_from_proto(
+    src: &std::collections::HashMap<u64, u64>,
+    num_outlays: usize,
+    num_outputs: usize,
+) -> Result<HashMap<usize, usize>, ConversionError> {
+    let outlay_index_to_tx_out_index = src
+        .iter()
+        .map(|(key, val)| (*key as usize, *val as usize))
+        .collect::<HashMap<_, _>>();
+
+    if outlay_index_to_tx_out_index.len() != num_outlays {
+        return Err(ConversionError::IndexOutOfBounds);
+    }
+
+    for (outlay_index, tx_out_index) in outlay_index_to_tx_out_index.iter() {
+        if *outlay_index >= num_outlays || *tx_out_index >= num_outputs {
+            return Err(ConversionError::IndexOutOfBounds);
+        }
+    }
+
+    Ok(outlay_index_to_tx_out_index)
+}
+
+fn outlay_confirmation_numbers_from_proto(
+    src: &[Vec<u8>],
+) -> Result<Vec<TxOutConfirmationNumber>, ConversionError> {
+    src.iter()
+        .map(|src| match src.len() {
+            32 => {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(src);
+                Ok(TxOutConfirmationNumber::from(bytes))
+            }
+            _ => Err(ConversionError::IndexOutOfBounds),
+        })
+        .collect()
+}
+
+impl From<&UnsyncedTxOut> for api::UnsyncedTxOut {
+    fn from(src: &UnsyncedTxOut) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_tx_out((&src.tx_out).into());
+        dst.set_subaddress_index(src.subaddress_index);
+        dst.set_value(src.value);
+        dst.set_token_id(src.token_id);
+
+        dst
+    }
+}
+
 impl From<&SciForTx> for api::SciForTx {
     fn from(src: &SciForTx) -> Self {
         let mut dst = Self::new();
@@ -308,8 +515,8 @@ mod test {
     use mc_transaction_core::{tokens::Mob, BlockVersion, Token};
     use mc_transaction_extra::{
         AuthenticatedSenderMemo, AuthenticatedSenderWithPaymentIntentIdMemo,
-        AuthenticatedSenderWithPaymentRequestIdMemo, DestinationMemo, SenderMemoCredential,
-        UnusedMemo,
+        AuthenticatedSenderWithPaymentRequestIdMemo, DestinationMemo,
+        DestinationWithPaymentRequestIdMemo, SenderMemoCredential, UnusedMemo,
     };
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
@@ -428,13 +635,28 @@ mod test {
         assert!(sender_memo.has_payment_intent_id());
         assert_eq!(sender_memo.get_payment_intent_id(), 9);
 
-        // Destination memos are not implemented yet
         let memo5 = DestinationMemo::new(ShortAddressHash::from(&bob_addr), 17, 18).unwrap();
         let decoded = decode_memo(&MemoPayload::from(memo5));
         assert!(!decoded.has_authenticated_sender_memo());
-        assert!(decoded.has_unknown_memo());
-        let type_bytes = decoded.get_unknown_memo().get_type_bytes();
-        assert_eq!(&type_bytes, &[2u8, 0u8]);
+        assert!(!decoded.has_unknown_memo());
+        let destination_memo = decoded.get_destination_memo();
+        assert_eq!(
+            destination_memo.get_recipient_hash(),
+            ShortAddressHash::from(&bob_addr).as_ref()
+        );
+        assert_eq!(destination_memo.get_num_recipients(), 1);
+        assert_eq!(destination_memo.get_fee(), 18);
+        assert_eq!(destination_memo.get_total_outlay(), 17);
+        assert!(!destination_memo.has_payment_request_id());
+        assert!(!destination_memo.has_payment_intent_id());
+
+        let memo5b =
+            DestinationWithPaymentRequestIdMemo::new(ShortAddressHash::from(&bob_addr), 17, 18, 7)
+                .unwrap();
+        let decoded = decode_memo(&MemoPayload::from(memo5b));
+        let destination_memo = decoded.get_destination_memo();
+        assert_eq!(destination_memo.get_payment_request_id(), 7);
+        assert!(!destination_memo.has_payment_intent_id());
 
         // This is an unassigned memo type
         let memo6 = MemoPayload::new([7u8, 8u8], [0u8; 64]);
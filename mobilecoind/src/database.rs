@@ -4,24 +4,43 @@
 
 use crate::{
     db_crypto::DbCryptoProvider,
+    defragmentation_store::{DefragmentationData, DefragmentationPolicy, DefragmentationStore},
     error::Error,
+    hd_monitor_store::{HdMonitorData, HdMonitorId, HdMonitorStore},
     monitor_store::{MonitorData, MonitorId, MonitorStore},
+    payment_request_store::{PaymentRequestData, PaymentRequestStore},
     processed_block_store::{ProcessedBlockStore, ProcessedTxOut},
+    resubmission_store::{ResubmissionStore, ResubmittedTx},
+    scheduled_payment_store::{ScheduledPaymentData, ScheduledPaymentStore},
     subaddress_store::{SubaddressId, SubaddressSPKId, SubaddressStore},
     t3_store::T3Store,
+    unsynced_txo_store::{UnsyncedTxOut, UnsyncedTxoStore},
     utxo_store::{UtxoId, UtxoStore},
+    webhook_store::{WatchedTx, WebhookEvent, WebhookStore},
+    webhooks::{
+        tx_out_event_data, unix_timestamp, TX_OUT_RECEIVED_EVENT, TX_OUT_SPENT_EVENT,
+        TX_STATUS_EVENT,
+    },
 };
 
 use crate::utxo_store::UnspentTxOut;
-use lmdb::{Environment, Transaction};
+use lmdb::{Environment, RwTransaction, Transaction};
 use mc_common::{
     logger::{log, Logger},
     HashMap,
 };
+use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_t3_api::TransparentTransaction;
 use mc_transaction_core::ring_signature::KeyImage;
 use mc_util_lmdb::{MetadataStore, MetadataStoreSettings};
-use std::{path::Path, sync::Arc};
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
 
 // LMDB Constants
 const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
@@ -44,6 +63,38 @@ impl MetadataStoreSettings for MobilecoindDbMetadataStoreSettings {
     const DB_NAME: &'static str = "mobilecoind_db_metadata";
 }
 
+/// Used to wake up threads waiting for monitors to process new blocks.
+///
+/// The notifier keeps a counter that is incremented every time any monitor
+/// finishes processing a block. Waiters remember the counter value they last
+/// observed, which ensures notifications that happen between checking the
+/// database and starting to wait are not lost.
+#[derive(Clone, Default)]
+struct BlockProcessedNotifier {
+    inner: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl BlockProcessedNotifier {
+    fn generation(&self) -> u64 {
+        *self.inner.0.lock().expect("mutex poisoned")
+    }
+
+    fn notify(&self) {
+        let (lock, condvar) = &*self.inner;
+        let mut generation = lock.lock().expect("mutex poisoned");
+        *generation = generation.wrapping_add(1);
+        condvar.notify_all();
+    }
+
+    fn wait(&self, generation: u64, timeout: Duration) {
+        let (lock, condvar) = &*self.inner;
+        let guard = lock.lock().expect("mutex poisoned");
+        let _ = condvar
+            .wait_timeout_while(guard, timeout, |current| *current == generation)
+            .expect("mutex poisoned");
+    }
+}
+
 /// The main mobilecoind database.
 #[derive(Clone)]
 pub struct Database {
@@ -56,18 +107,45 @@ pub struct Database {
     /// Monitor store.
     monitor_store: MonitorStore,
 
+    /// HD monitor store.
+    hd_monitor_store: HdMonitorStore,
+
     /// Subaddress store.
     subaddress_store: SubaddressStore,
 
     /// Utxo store.
     utxo_store: UtxoStore,
 
+    /// Unsynced TxOut store.
+    unsynced_txo_store: UnsyncedTxoStore,
+
     /// Processed block store.
     processed_block_store: ProcessedBlockStore,
 
     /// T3 store.
     t3_store: T3Store,
 
+    /// Webhook store.
+    webhook_store: WebhookStore,
+
+    /// Payment request store.
+    payment_request_store: PaymentRequestStore,
+
+    /// Defragmentation store.
+    defragmentation_store: DefragmentationStore,
+
+    /// Resubmission store.
+    resubmission_store: ResubmissionStore,
+
+    /// Scheduled payment store.
+    scheduled_payment_store: ScheduledPaymentStore,
+
+    /// Whether webhook events should be recorded.
+    webhooks_enabled: Arc<AtomicBool>,
+
+    /// Notifier for threads waiting on newly processed blocks.
+    block_processed_notifier: BlockProcessedNotifier,
+
     /// Logger.
     logger: Logger,
 }
@@ -76,7 +154,7 @@ impl Database {
     pub fn new<P: AsRef<Path>>(path: P, logger: Logger) -> Result<Self, Error> {
         let env = Arc::new(
             Environment::new()
-                .set_max_dbs(13)
+                .set_max_dbs(32)
                 .set_map_size(MAX_LMDB_FILE_SIZE)
                 .open(path.as_ref())?,
         );
@@ -99,19 +177,36 @@ impl Database {
 
         let monitor_store =
             MonitorStore::new(env.clone(), crypto_provider.clone(), logger.clone())?;
+        let hd_monitor_store = HdMonitorStore::new(env.clone(), logger.clone())?;
         let subaddress_store = SubaddressStore::new(env.clone(), logger.clone())?;
         let utxo_store = UtxoStore::new(env.clone(), logger.clone())?;
+        let unsynced_txo_store = UnsyncedTxoStore::new(env.clone(), logger.clone())?;
         let processed_block_store = ProcessedBlockStore::new(env.clone(), logger.clone())?;
         let t3_store = T3Store::new(env.clone(), logger.clone())?;
+        let webhook_store = WebhookStore::new(env.clone(), logger.clone())?;
+        let payment_request_store = PaymentRequestStore::new(env.clone(), logger.clone())?;
+        let defragmentation_store = DefragmentationStore::new(env.clone(), logger.clone())?;
+        let resubmission_store = ResubmissionStore::new(env.clone(), logger.clone())?;
+        let scheduled_payment_store =
+            ScheduledPaymentStore::new(env.clone(), crypto_provider.clone(), logger.clone())?;
 
         Ok(Self {
             env,
             crypto_provider,
             monitor_store,
+            hd_monitor_store,
             subaddress_store,
             utxo_store,
+            unsynced_txo_store,
             processed_block_store,
             t3_store,
+            webhook_store,
+            payment_request_store,
+            defragmentation_store,
+            resubmission_store,
+            scheduled_payment_store,
+            webhooks_enabled: Arc::new(AtomicBool::new(false)),
+            block_processed_notifier: BlockProcessedNotifier::default(),
             logger,
         })
     }
@@ -141,8 +236,9 @@ impl Database {
     pub fn re_encrypt(&self, new_password: &[u8]) -> Result<(), Error> {
         let mut db_txn = self.env.begin_rw_txn()?;
 
-        // Currently only the monitor store stores encrypted data.
         self.monitor_store.re_encrypt(&mut db_txn, new_password)?;
+        self.scheduled_payment_store
+            .re_encrypt(&mut db_txn, new_password)?;
 
         // set_password consumes the transaction to ensure atomicity.
         self.crypto_provider.change_password(db_txn, new_password)?;
@@ -154,15 +250,23 @@ impl Database {
         mc_common::trace_time!(self.logger, "add_monitor");
 
         let mut db_txn = self.env.begin_rw_txn()?;
-        let id = self.monitor_store.add(&mut db_txn, data)?;
+        let id = self.add_monitor_impl(&mut db_txn, data)?;
+        db_txn.commit()?;
+        Ok(id)
+    }
+
+    fn add_monitor_impl(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        data: &MonitorData,
+    ) -> Result<MonitorId, Error> {
+        let id = self.monitor_store.add(db_txn, data)?;
 
         //for index in 0..data.num_subaddresses {
         for index in data.subaddress_indexes() {
-            self.subaddress_store
-                .insert(&mut db_txn, &id, data, index)?;
+            self.subaddress_store.insert(db_txn, &id, data, index)?;
         }
 
-        db_txn.commit()?;
         Ok(id)
     }
 
@@ -170,23 +274,278 @@ impl Database {
         mc_common::trace_time!(self.logger, "remove_monitor");
 
         let mut db_txn = self.env.begin_rw_txn()?;
+        self.remove_monitor_impl(&mut db_txn, id)?;
+        db_txn.commit()?;
+
+        Ok(())
+    }
 
-        let data = self.monitor_store.get_data(&db_txn, id)?;
+    fn remove_monitor_impl(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        id: &MonitorId,
+    ) -> Result<(), Error> {
+        let data = self.monitor_store.get_data(db_txn, id)?;
 
         for index in data.subaddress_indexes() {
-            self.subaddress_store.delete(&mut db_txn, &data, index)?;
-            self.utxo_store.remove_utxos(&mut db_txn, id, index)?;
+            self.subaddress_store.delete(db_txn, &data, index)?;
+            self.utxo_store.remove_utxos(db_txn, id, index)?;
+        }
+
+        self.unsynced_txo_store.remove_for_monitor(db_txn, id)?;
+
+        self.processed_block_store.remove(db_txn, id)?;
+
+        self.payment_request_store.remove_for_monitor(db_txn, id)?;
+
+        match self.defragmentation_store.remove(db_txn, id) {
+            Ok(()) | Err(Error::DefragmentationPolicyNotFound) => {}
+            Err(err) => return Err(err),
         }
 
-        self.processed_block_store.remove(&mut db_txn, id)?;
+        self.resubmission_store.remove_for_monitor(db_txn, id)?;
+
+        self.scheduled_payment_store
+            .remove_for_monitor(db_txn, id)?;
 
-        self.monitor_store.remove(&mut db_txn, id)?;
+        self.monitor_store.remove(db_txn, id)?;
+
+        Ok(())
+    }
+
+    /// Add an HD monitor, along with a monitor for each of its accounts.
+    /// `account_monitors` holds the monitor data of each account, in the same
+    /// order as `account_indices`.
+    pub fn add_hd_monitor(
+        &self,
+        account_indices: &[u32],
+        account_monitors: &[MonitorData],
+        name: &str,
+    ) -> Result<HdMonitorId, Error> {
+        mc_common::trace_time!(self.logger, "add_hd_monitor");
+
+        let monitor_ids = account_monitors
+            .iter()
+            .map(MonitorId::try_from)
+            .collect::<Result<_, _>>()?;
+        let data = HdMonitorData::new(account_indices.to_vec(), monitor_ids, name)?;
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let id = self.hd_monitor_store.add(&mut db_txn, &data)?;
+        for monitor_data in account_monitors {
+            self.add_monitor_impl(&mut db_txn, monitor_data)?;
+        }
+        db_txn.commit()?;
+
+        Ok(id)
+    }
+
+    /// Remove an HD monitor, along with the monitors of its accounts.
+    pub fn remove_hd_monitor(&self, id: &HdMonitorId) -> Result<(), Error> {
+        mc_common::trace_time!(self.logger, "remove_hd_monitor");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let data = self.hd_monitor_store.get_data(&db_txn, id)?;
+        for monitor_id in &data.monitor_ids {
+            self.remove_monitor_impl(&mut db_txn, monitor_id)?;
+        }
+        self.hd_monitor_store.remove(&mut db_txn, id)?;
 
         db_txn.commit()?;
 
         Ok(())
     }
 
+    pub fn get_hd_monitor_data(&self, id: &HdMonitorId) -> Result<HdMonitorData, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.hd_monitor_store.get_data(&db_txn, id)
+    }
+
+    /// Start tracking a payment request.
+    pub fn add_payment_request(
+        &self,
+        payment_id: u64,
+        data: &PaymentRequestData,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        // Make sure the receiving monitor exists.
+        self.monitor_store.get_data(&db_txn, &data.monitor_id)?;
+
+        self.payment_request_store
+            .add(&mut db_txn, payment_id, data)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Stop tracking a payment request.
+    pub fn remove_payment_request(&self, payment_id: u64) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.payment_request_store.remove(&mut db_txn, payment_id)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get a tracked payment request.
+    pub fn get_payment_request(&self, payment_id: u64) -> Result<PaymentRequestData, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.payment_request_store.get_data(&db_txn, payment_id)
+    }
+
+    /// Get all tracked payment requests, ordered by payment id.
+    pub fn get_payment_requests(&self) -> Result<Vec<(u64, PaymentRequestData)>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.payment_request_store.get_all(&db_txn)
+    }
+
+    /// Set the defragmentation policy of a monitor.
+    pub fn set_defragmentation_policy(
+        &self,
+        monitor_id: &MonitorId,
+        policy: &DefragmentationPolicy,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        // Make sure the monitor exists.
+        self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        self.defragmentation_store
+            .set_policy(&mut db_txn, monitor_id, policy)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Remove the defragmentation policy of a monitor.
+    pub fn remove_defragmentation_policy(&self, monitor_id: &MonitorId) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.defragmentation_store.remove(&mut db_txn, monitor_id)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get the defragmentation policy and progress of a monitor.
+    pub fn get_defragmentation_data(
+        &self,
+        monitor_id: &MonitorId,
+    ) -> Result<DefragmentationData, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.defragmentation_store.get_data(&db_txn, monitor_id)
+    }
+
+    /// Get the defragmentation policies and progress of all monitors.
+    pub fn get_all_defragmentation_data(
+        &self,
+    ) -> Result<Vec<(MonitorId, DefragmentationData)>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.defragmentation_store.get_all(&db_txn)
+    }
+
+    /// Update the defragmentation progress of a monitor. This is a no-op if
+    /// the policy was removed in the meantime.
+    pub fn update_defragmentation_data(
+        &self,
+        monitor_id: &MonitorId,
+        update: impl FnOnce(&mut DefragmentationData),
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut data = match self.defragmentation_store.get_data(&db_txn, monitor_id) {
+            Ok(data) => data,
+            Err(Error::DefragmentationPolicyNotFound) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        update(&mut data);
+        self.defragmentation_store
+            .set_data(&mut db_txn, monitor_id, &data)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Start resubmitting a transaction according to its policy.
+    pub fn add_resubmitted_tx(&self, resubmitted_tx: &ResubmittedTx) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        // Make sure the monitor exists.
+        self.monitor_store
+            .get_data(&db_txn, &resubmitted_tx.sender_monitor_id)?;
+
+        self.resubmission_store.set(&mut db_txn, resubmitted_tx)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get the resubmitted transaction whose first input has the given key
+    /// image.
+    pub fn get_resubmitted_tx(&self, key_image: &KeyImage) -> Result<ResubmittedTx, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.resubmission_store.get(&db_txn, key_image)
+    }
+
+    /// Get all resubmitted transactions that did not reach a final state yet.
+    pub fn get_unfinished_resubmitted_txs(&self) -> Result<Vec<ResubmittedTx>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.resubmission_store.get_unfinished(&db_txn)
+    }
+
+    /// Update a resubmitted transaction. This is a no-op if it was removed in
+    /// the meantime.
+    pub fn update_resubmitted_tx(
+        &self,
+        key_image: &KeyImage,
+        update: impl FnOnce(&mut ResubmittedTx),
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut resubmitted_tx = match self.resubmission_store.get(&db_txn, key_image) {
+            Ok(resubmitted_tx) => resubmitted_tx,
+            Err(Error::ResubmittedTxNotFound) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        update(&mut resubmitted_tx);
+        self.resubmission_store.set(&mut db_txn, &resubmitted_tx)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Add a scheduled payment, returning its id.
+    pub fn add_scheduled_payment(&self, data: &ScheduledPaymentData) -> Result<u64, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        // Make sure the sending monitor exists.
+        self.monitor_store
+            .get_data(&db_txn, &data.sender_monitor_id)?;
+
+        let id = self.scheduled_payment_store.add(&mut db_txn, data)?;
+        db_txn.commit()?;
+        Ok(id)
+    }
+
+    /// Get a scheduled payment.
+    pub fn get_scheduled_payment(&self, id: u64) -> Result<ScheduledPaymentData, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.scheduled_payment_store.get_data(&db_txn, id)
+    }
+
+    /// Get all scheduled payments, ordered by id.
+    pub fn get_scheduled_payments(&self) -> Result<Vec<(u64, ScheduledPaymentData)>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.scheduled_payment_store.get_all(&db_txn)
+    }
+
+    /// Update a scheduled payment, returning its updated data.
+    pub fn update_scheduled_payment(
+        &self,
+        id: u64,
+        update: impl FnOnce(&mut ScheduledPaymentData),
+    ) -> Result<ScheduledPaymentData, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut data = self.scheduled_payment_store.get_data(&db_txn, id)?;
+        update(&mut data);
+        self.scheduled_payment_store
+            .set_data(&mut db_txn, id, &data)?;
+        db_txn.commit()?;
+        Ok(data)
+    }
+
     pub fn get_monitor_data(&self, id: &MonitorId) -> Result<MonitorData, Error> {
         let db_txn = self.env.begin_ro_txn()?;
         self.monitor_store.get_data(&db_txn, id)
@@ -254,12 +613,64 @@ impl Database {
         Ok(())
     }
 
+    /// Get the TxOuts received by a watch-only monitor whose key images have
+    /// not been supplied yet.
+    pub fn get_unsynced_txos(&self, monitor_id: &MonitorId) -> Result<Vec<UnsyncedTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.unsynced_txo_store.get_for_monitor(&db_txn, monitor_id)
+    }
+
+    /// Supply the key images of TxOuts received by a watch-only monitor,
+    /// turning them into UTXOs.
+    ///
+    /// `is_spent` is called with each key image while the database is locked
+    /// for writing, so no block can be processed for the monitor in between.
+    /// TxOuts that are already spent are dropped instead of becoming UTXOs.
+    /// Returns the new UTXOs.
+    pub fn sync_txos(
+        &self,
+        monitor_id: &MonitorId,
+        key_images: &[(CompressedRistrettoPublic, KeyImage)],
+        is_spent: impl Fn(&KeyImage) -> Result<bool, Error>,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut utxos = Vec::new();
+        for (tx_out_public_key, key_image) in key_images {
+            let unsynced_txo =
+                self.unsynced_txo_store
+                    .remove(&mut db_txn, monitor_id, tx_out_public_key)?;
+            if is_spent(key_image)? {
+                continue;
+            }
+
+            let utxo = unsynced_txo.into_utxo(*key_image);
+            self.utxo_store
+                .append_utxo(&mut db_txn, monitor_id, utxo.subaddress_index, &utxo)?;
+            utxos.push(utxo);
+        }
+
+        db_txn.commit()?;
+
+        log::info!(
+            self.logger,
+            "Synced {} TxOuts ({} unspent) for monitor id {}",
+            key_images.len(),
+            utxos.len(),
+            monitor_id
+        );
+        Ok(utxos)
+    }
+
     /// Feed data processed from a given block into the various stores.
+    /// `discovered_unsynced_txos` holds the TxOuts received by a watch-only
+    /// monitor, whose key images are not known yet.
     pub fn block_processed(
         &self,
         monitor_id: &MonitorId,
         block_num: u64,
         discovered_utxos: &[UnspentTxOut],
+        discovered_unsynced_txos: &[UnsyncedTxOut],
         spent_key_images: &[KeyImage],
     ) -> Result<(), Error> {
         let mut db_txn = self.env.begin_rw_txn()?;
@@ -284,6 +695,40 @@ impl Database {
                 .append_utxo(&mut db_txn, monitor_id, utxo.subaddress_index, utxo)?;
             self.t3_store
                 .process_utxo(&mut db_txn, monitor_id, &self.monitor_store, utxo)?;
+            self.payment_request_store
+                .process_utxo(&mut db_txn, monitor_id, block_num, utxo)?;
+        }
+
+        // Store new unsynced TxOuts
+        for unsynced_txo in discovered_unsynced_txos {
+            self.unsynced_txo_store
+                .add(&mut db_txn, monitor_id, unsynced_txo)?;
+        }
+
+        // Extend the subaddress range if funds arrived too close to its end.
+        // Subaddresses added here are watched starting with the next block.
+        if let Some(extended_indexes) = discovered_utxos
+            .iter()
+            .map(|utxo| utxo.subaddress_index)
+            .chain(
+                discovered_unsynced_txos
+                    .iter()
+                    .map(|unsynced_txo| unsynced_txo.subaddress_index),
+            )
+            .filter_map(|index| monitor_data.extended_subaddress_indexes(index))
+            .max_by_key(|indexes| indexes.end)
+        {
+            for index in monitor_data.subaddress_indexes().end..extended_indexes.end {
+                self.subaddress_store
+                    .insert(&mut db_txn, monitor_id, &monitor_data, index)?;
+            }
+            log::info!(
+                self.logger,
+                "Extended subaddress range of monitor id {} to {:?}",
+                monitor_id,
+                extended_indexes
+            );
+            monitor_data.num_subaddresses = extended_indexes.end - extended_indexes.start;
         }
 
         // Remove spent utxos
@@ -307,11 +752,38 @@ impl Database {
             &removed_utxos,
         )?;
 
+        // Queue webhook events.
+        if self.webhooks_enabled() {
+            let now = unix_timestamp();
+            for utxo in discovered_utxos {
+                self.webhook_store.append_event(
+                    &mut db_txn,
+                    TX_OUT_RECEIVED_EVENT,
+                    &tx_out_event_data(monitor_id, block_num, utxo),
+                    now,
+                )?;
+            }
+            for utxo in removed_utxos.iter() {
+                self.webhook_store.append_event(
+                    &mut db_txn,
+                    TX_OUT_SPENT_EVENT,
+                    &tx_out_event_data(monitor_id, block_num, utxo),
+                    now,
+                )?;
+            }
+        }
+
         // Commit.
         db_txn.commit()?;
 
+        // Wake up anyone waiting for newly processed blocks.
+        self.block_processed_notifier.notify();
+
         // Success.
-        if discovered_utxos.is_empty() && removed_utxos.is_empty() {
+        if discovered_utxos.is_empty()
+            && discovered_unsynced_txos.is_empty()
+            && removed_utxos.is_empty()
+        {
             log::debug!(
                 self.logger,
                 "Processed {} utxos and {} key images in block {} for monitor id {}",
@@ -323,8 +795,9 @@ impl Database {
         } else {
             log::info!(
                 self.logger,
-                "Processed {} utxos and {} key images in block {} for monitor id {}",
+                "Processed {} utxos, {} unsynced txos and {} key images in block {} for monitor id {}",
                 discovered_utxos.len(),
+                discovered_unsynced_txos.len(),
                 removed_utxos.len(),
                 block_num,
                 monitor_id
@@ -360,6 +833,113 @@ impl Database {
             .get_processed_block(&db_txn, monitor_id, block_num)
     }
 
+    /// Get a page of the processed blocks of a monitor that contain any
+    /// TxOuts, starting at `first_block`. Blocks are never split across pages.
+    /// Also returns the block the next page starts at.
+    pub fn get_processed_blocks_page(
+        &self,
+        monitor_id: &MonitorId,
+        first_block: u64,
+        max_tx_outs: usize,
+    ) -> Result<(Vec<(u64, Vec<ProcessedTxOut>)>, u64), Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let monitor_data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        let blocks = self.processed_block_store.get_processed_blocks(
+            &db_txn,
+            monitor_id,
+            first_block,
+            max_tx_outs,
+        )?;
+
+        let num_tx_outs: usize = blocks.iter().map(|(_, tx_outs)| tx_outs.len()).sum();
+        let next_block = match blocks.last() {
+            Some((block_index, _)) if num_tx_outs >= max_tx_outs => block_index + 1,
+            _ => monitor_data.next_block.max(first_block),
+        };
+
+        Ok((blocks, next_block))
+    }
+
+    /// Start recording webhook events.
+    pub fn enable_webhooks(&self) {
+        self.webhooks_enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether webhook events are being recorded.
+    pub fn webhooks_enabled(&self) -> bool {
+        self.webhooks_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Start watching a submitted transaction, so that a webhook event gets
+    /// queued once it reaches a final state.
+    pub fn watch_submitted_tx(&self, watched_tx: &WatchedTx) -> Result<u64, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let id = self.webhook_store.add_watched_tx(&mut db_txn, watched_tx)?;
+        db_txn.commit()?;
+        Ok(id)
+    }
+
+    /// Get all transactions we are waiting to reach a final state.
+    pub fn get_watched_txs(&self) -> Result<Vec<(u64, WatchedTx)>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.webhook_store.get_watched_txs(&db_txn)
+    }
+
+    /// Stop watching a transaction that reached a final state, and queue the
+    /// corresponding webhook event.
+    pub fn watched_tx_finalized(&self, watch_id: u64, event_data: &[u8]) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.webhook_store
+            .remove_watched_tx(&mut db_txn, watch_id)?;
+        self.webhook_store.append_event(
+            &mut db_txn,
+            TX_STATUS_EVENT,
+            event_data,
+            unix_timestamp(),
+        )?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get up to `limit` webhook events that are due for delivery.
+    pub fn get_due_webhook_events(
+        &self,
+        now: u64,
+        limit: usize,
+    ) -> Result<Vec<WebhookEvent>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.webhook_store.get_due_events(&db_txn, now, limit)
+    }
+
+    /// Update a webhook event after a failed delivery attempt.
+    pub fn update_webhook_event(&self, event: &WebhookEvent) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.webhook_store.update_event(&mut db_txn, event)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Remove a webhook event after it was delivered.
+    pub fn remove_webhook_event(&self, id: u64) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.webhook_store.remove_event(&mut db_txn, id)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get a value that changes every time a monitor finishes processing a
+    /// block. This is meant to be passed to `wait_for_processed_block`.
+    pub fn processed_block_generation(&self) -> u64 {
+        self.block_processed_notifier.generation()
+    }
+
+    /// Block the calling thread until a monitor finishes processing a block
+    /// after `generation` was obtained, or until `timeout` elapses.
+    pub fn wait_for_processed_block(&self, generation: u64, timeout: Duration) {
+        self.block_processed_notifier.wait(generation, timeout)
+    }
+
     /// Get the next transparent transaction that needs to be synced to t3.
     /// Additionally return its index so it can then be removed.
     pub fn dequeue_transparent_tx(&self) -> Result<Option<(u64, TransparentTransaction)>, Error> {
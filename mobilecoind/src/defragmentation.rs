@@ -0,0 +1,554 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Automatic background defragmentation of monitor UTXOs.
+//!
+//! Monitors can be given a defragmentation policy (see
+//! `defragmentation_store`). Once a subaddress of the monitor holds at least
+//! `trigger_utxo_count` UTXOs of the policy's token, a background thread starts
+//! submitting optimization transactions that merge them, until the subaddress
+//! is down to `target_utxo_count` UTXOs or the fee budget runs out.
+//!
+//! Only one transaction is in flight per monitor: the next one is built once
+//! the previous one reached a final state and the monitor synced past it, so
+//! that it spends the merged output rather than racing the previous
+//! transaction. All transactions of a round carry a defragmentation memo with
+//! the same randomly chosen defragmentation id.
+
+use crate::{
+    database::Database, defragmentation_store::DefragmentationData, error::Error,
+    monitor_store::MonitorId, payments::TransactionsManager, utxo_store::UtxoId,
+    webhook_store::WatchedTx, webhooks::get_final_tx_status,
+};
+use mc_common::logger::{log, Logger};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_sync::{NetworkState, PollingNetworkState};
+use mc_mobilecoind_api::TxStatus;
+use mc_transaction_builder::DefragmentationMemoBuilder;
+use mc_transaction_core::{constants::MAX_INPUTS, TokenId};
+use rand::Rng;
+use std::{
+    cmp::{min, Reverse},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Defragmentation Thread - holds objects needed to cleanly terminate the
+/// defragmentation thread.
+pub struct DefragmentationThread {
+    /// The main thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl DefragmentationThread {
+    pub fn start<
+        T: BlockchainConnection + UserTxConnection + 'static,
+        FPR: FogPubkeyResolver + 'static,
+    >(
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        transactions_manager: TransactionsManager<T, FPR>,
+        network_state: Arc<RwLock<PollingNetworkState<T>>>,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = thread::Builder::new()
+            .name("defragmentation".to_string())
+            .spawn(move || {
+                defragmentation_thread_entry_point(
+                    ledger_db,
+                    mobilecoind_db,
+                    transactions_manager,
+                    network_state,
+                    poll_interval,
+                    thread_stop_requested,
+                    logger,
+                );
+            })
+            .expect("failed starting defragmentation thread");
+
+        Self {
+            join_handle: Some(join_handle),
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle
+                .join()
+                .expect("DefragmentationThread join failed");
+        }
+    }
+}
+
+impl Drop for DefragmentationThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn defragmentation_thread_entry_point<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    transactions_manager: TransactionsManager<T, FPR>,
+    network_state: Arc<RwLock<PollingNetworkState<T>>>,
+    poll_interval: Duration,
+    stop_requested: Arc<AtomicBool>,
+    logger: Logger,
+) {
+    log::info!(logger, "Defragmentation thread started");
+
+    loop {
+        if stop_requested.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // Monitor data cannot be accessed until the database is unlocked.
+        if mobilecoind_db.is_unlocked() {
+            match mobilecoind_db.get_all_defragmentation_data() {
+                Ok(policies) => {
+                    for (monitor_id, data) in policies {
+                        if let Err(err) = defragment_monitor(
+                            &ledger_db,
+                            &mobilecoind_db,
+                            &transactions_manager,
+                            &network_state,
+                            &monitor_id,
+                            data,
+                            &logger,
+                        ) {
+                            log::error!(
+                                logger,
+                                "Error defragmenting monitor {}: {:?}",
+                                monitor_id,
+                                err
+                            );
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::error!(logger, "Error getting defragmentation policies: {:?}", err);
+                }
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+/// Make progress on the defragmentation policy of a single monitor.
+fn defragment_monitor<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    transactions_manager: &TransactionsManager<T, FPR>,
+    network_state: &Arc<RwLock<PollingNetworkState<T>>>,
+    monitor_id: &MonitorId,
+    mut data: DefragmentationData,
+    logger: &Logger,
+) -> Result<(), Error> {
+    // Wait for the previously submitted transaction to reach a final state.
+    if let Some(pending_tx) = data.pending_tx.take() {
+        let Some(status) = get_final_tx_status(ledger_db, &pending_tx)? else {
+            return Ok(());
+        };
+
+        log::info!(
+            logger,
+            "Defragmentation transaction of monitor {} reached final status {:?}",
+            monitor_id,
+            status
+        );
+        mobilecoind_db.update_defragmentation_data(monitor_id, |data| {
+            data.pending_tx = None;
+            if status == TxStatus::Verified {
+                data.num_txs_landed += 1;
+            } else {
+                data.num_txs_failed += 1;
+                data.last_error = format!("Transaction failed: {status:?}");
+            }
+        })?;
+    }
+
+    // Only look at the UTXOs of the monitor once it caught up with the ledger, so
+    // that they reflect the outcome of the previous transaction.
+    let monitor_data = mobilecoind_db.get_monitor_data(monitor_id)?;
+    if monitor_data.next_block < ledger_db.num_blocks()? {
+        return Ok(());
+    }
+
+    let token_id = TokenId::from(data.policy.token_id);
+    let count_utxos = |subaddress_index| -> Result<u64, Error> {
+        Ok(mobilecoind_db
+            .get_utxos_for_subaddress(monitor_id, subaddress_index)?
+            .iter()
+            .filter(|utxo| utxo.token_id == *token_id)
+            .count() as u64)
+    };
+
+    // A round sticks to its subaddress, otherwise pick the most fragmented one.
+    let (subaddress_index, num_utxos) = if data.active {
+        (data.subaddress_index, count_utxos(data.subaddress_index)?)
+    } else {
+        let counts = monitor_data
+            .subaddress_indexes()
+            .map(|index| Ok((index, count_utxos(index)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        match counts
+            .into_iter()
+            .max_by_key(|(index, count)| (*count, Reverse(*index)))
+        {
+            Some(most_fragmented) => most_fragmented,
+            None => return Ok(()),
+        }
+    };
+
+    let mut active = data.active;
+    let mut defrag_id = data.defrag_id;
+    if !active && num_utxos >= data.policy.trigger_utxo_count && data.remaining_budget() > 0 {
+        active = true;
+        defrag_id = rand::thread_rng().gen();
+        log::info!(
+            logger,
+            "Starting defragmentation of monitor {} subaddress {} ({} UTXOs), defrag id {}",
+            monitor_id,
+            subaddress_index,
+            num_utxos,
+            defrag_id
+        );
+    } else if active && num_utxos <= data.policy.target_utxo_count {
+        active = false;
+        log::info!(
+            logger,
+            "Finished defragmentation of monitor {} subaddress {} ({} UTXOs)",
+            monitor_id,
+            subaddress_index,
+            num_utxos
+        );
+    }
+
+    if (active, subaddress_index, num_utxos, defrag_id)
+        != (
+            data.active,
+            data.subaddress_index,
+            data.num_utxos,
+            data.defrag_id,
+        )
+    {
+        mobilecoind_db.update_defragmentation_data(monitor_id, |data| {
+            data.active = active;
+            data.subaddress_index = subaddress_index;
+            data.num_utxos = num_utxos;
+            data.defrag_id = defrag_id;
+        })?;
+    }
+    if !active {
+        return Ok(());
+    }
+
+    // Merge just enough UTXOs to reach the target count.
+    let max_inputs = min(
+        MAX_INPUTS as usize,
+        (num_utxos - data.policy.target_utxo_count + 1) as usize,
+    );
+    let last_block_infos = network_state
+        .read()
+        .expect("lock poisoned")
+        .peer_to_block_info()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let tx_proposal = match transactions_manager.generate_optimization_tx(
+        monitor_id,
+        subaddress_index,
+        token_id,
+        &last_block_infos,
+        0,
+        max_inputs,
+        Some(Box::new(DefragmentationMemoBuilder::new(defrag_id))),
+    ) {
+        Ok(tx_proposal) => tx_proposal,
+        Err(err) => {
+            // Merging is no longer beneficial, the round cannot make any more
+            // progress.
+            let stop = matches!(err, Error::OptimizationNotBeneficial(_));
+            mobilecoind_db.update_defragmentation_data(monitor_id, |data| {
+                data.active &= !stop;
+                data.last_error = err.to_string();
+            })?;
+            return Err(err);
+        }
+    };
+
+    let fee = tx_proposal.fee();
+    if fee > data.remaining_budget() {
+        log::warn!(
+            logger,
+            "Stopping defragmentation of monitor {}: fee budget exhausted",
+            monitor_id
+        );
+        mobilecoind_db.update_defragmentation_data(monitor_id, |data| {
+            data.active = false;
+            data.last_error = "Fee budget exhausted".to_owned();
+        })?;
+        return Ok(());
+    }
+
+    let block_height = match transactions_manager.submit_tx_proposal(&tx_proposal) {
+        Ok(block_height) => block_height,
+        Err(err) => {
+            mobilecoind_db.update_defragmentation_data(monitor_id, |data| {
+                data.last_error = err.to_string();
+            })?;
+            return Err(err);
+        }
+    };
+    log::info!(
+        logger,
+        "Submitted defragmentation transaction of monitor {} merging {} UTXOs",
+        monitor_id,
+        tx_proposal.utxos.len()
+    );
+
+    // The transaction was sent to the network, so it has to be accounted for even
+    // if updating the UTXOs fails.
+    let pending_tx = WatchedTx {
+        key_images: tx_proposal
+            .utxos
+            .iter()
+            .map(|utxo| utxo.key_image)
+            .collect(),
+        tx_public_keys: tx_proposal
+            .tx
+            .prefix
+            .outputs
+            .iter()
+            .map(|tx_out| tx_out.public_key)
+            .collect(),
+        tombstone: tx_proposal.tx.prefix.tombstone_block,
+    };
+    mobilecoind_db.update_defragmentation_data(monitor_id, |data| {
+        data.pending_tx = Some(pending_tx);
+        data.fees_spent += fee;
+        data.num_txs_submitted += 1;
+        data.last_error.clear();
+    })?;
+
+    let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+    mobilecoind_db.update_attempted_spend(
+        &utxo_ids,
+        block_height,
+        tx_proposal.tx.prefix.tombstone_block,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        defragmentation_store::DefragmentationPolicy,
+        monitor_store::MonitorData,
+        test_utils::{
+            add_block_to_ledger, get_testing_environment, wait_for_monitors, BlockVersion,
+            DEFAULT_PER_RECIPIENT_AMOUNT, GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+        },
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::test_with_logger;
+    use mc_connection::{Connection, ConnectionManager};
+    use mc_connection_test_utils::MockBlockchainConnection;
+    use mc_consensus_scp::QuorumSet;
+    use mc_crypto_keys::RistrettoPublic;
+    use mc_fog_report_validation_test_utils::MockFogResolver;
+    use mc_ledger_db::test_utils::add_txos_and_key_images_to_ledger;
+    use mc_transaction_core::{
+        constants::RING_SIZE, get_tx_out_shared_secret, ring_signature::KeyImage, tokens::Mob,
+        tx::Tx, Amount, Token,
+    };
+    use mc_transaction_extra::MemoType;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    /// Get the transactions that were submitted to our mock peers.
+    fn proposed_txs(
+        conn_manager: &ConnectionManager<MockBlockchainConnection<LedgerDB>>,
+    ) -> Vec<Tx> {
+        conn_manager
+            .conns()
+            .iter()
+            .flat_map(|conn| conn.read().proposed_txs.clone())
+            .collect()
+    }
+
+    #[test_with_logger]
+    fn test_defragment_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([46u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let default_subaddress = account_key.default_subaddress();
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // The random recipients are needed for mixins.
+        let num_random_recipients =
+            MAX_INPUTS as u32 * RING_SIZE as u32 / GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u32;
+        let (mut ledger_db, mobilecoind_db, _client, _server, conn_manager) =
+            get_testing_environment(
+                BlockVersion::MAX,
+                num_random_recipients,
+                &[default_subaddress.clone()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Give the monitor 26 UTXOs in total.
+        for _ in 0..MAX_INPUTS {
+            add_block_to_ledger(
+                &mut ledger_db,
+                BlockVersion::MAX,
+                &[default_subaddress.clone()],
+                Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            )
+            .unwrap();
+        }
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let node_ids = conn_manager
+            .conns()
+            .iter()
+            .map(|conn| conn.uri().host_and_port_responder_id().unwrap())
+            .collect();
+        let network_state = Arc::new(RwLock::new(PollingNetworkState::new(
+            QuorumSet::new_with_node_ids(2, node_ids),
+            conn_manager.clone(),
+            logger.clone(),
+        )));
+        network_state.write().unwrap().poll();
+
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            conn_manager.clone(),
+            Arc::new(|_| Ok(MockFogResolver::default())),
+            Default::default(),
+            logger.clone(),
+        );
+
+        let defragment = |ledger_db: &LedgerDB| {
+            let data = mobilecoind_db
+                .get_defragmentation_data(&monitor_id)
+                .unwrap();
+            defragment_monitor(
+                ledger_db,
+                &mobilecoind_db,
+                &transactions_manager,
+                &network_state,
+                &monitor_id,
+                data,
+                &logger,
+            )
+            .unwrap();
+            mobilecoind_db
+                .get_defragmentation_data(&monitor_id)
+                .unwrap()
+        };
+
+        mobilecoind_db
+            .set_defragmentation_policy(
+                &monitor_id,
+                &DefragmentationPolicy {
+                    token_id: *Mob::ID,
+                    trigger_utxo_count: 20,
+                    target_utxo_count: 15,
+                    fee_budget: 2 * Mob::MINIMUM_FEE,
+                },
+            )
+            .unwrap();
+
+        // The trigger count was reached, so a transaction merging just enough UTXOs
+        // to reach the target count gets submitted.
+        let data = defragment(&ledger_db);
+        assert!(data.active);
+        assert_eq!(data.subaddress_index, 0);
+        assert_eq!(data.num_utxos, 26);
+        assert_eq!(data.num_txs_submitted, 1);
+        assert_eq!(data.fees_spent, Mob::MINIMUM_FEE);
+        assert!(data.pending_tx.is_some());
+        assert_eq!(data.last_error, "");
+
+        let txs = proposed_txs(&conn_manager);
+        assert_eq!(txs.len(), 1);
+        let tx = txs[0].clone();
+        assert_eq!(tx.prefix.inputs.len(), 12);
+        assert_eq!(tx.prefix.outputs.len(), 1);
+
+        // The output carries a defragmentation memo.
+        let tx_out = &tx.prefix.outputs[0];
+        let shared_secret = get_tx_out_shared_secret(
+            account_key.view_private_key(),
+            &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+        );
+        let memo_payload = tx_out.decrypt_memo(&shared_secret);
+        match MemoType::try_from(&memo_payload).unwrap() {
+            MemoType::Defragmentation(memo) => {
+                assert_eq!(memo.defrag_id(), data.defrag_id);
+                assert_eq!(memo.fee(), Mob::MINIMUM_FEE);
+            }
+            memo => panic!("unexpected memo type {memo:?}"),
+        }
+
+        // Nothing else is submitted while the transaction is pending.
+        assert_eq!(defragment(&ledger_db), data);
+        assert_eq!(proposed_txs(&conn_manager).len(), 1);
+
+        // Once the transaction lands and the monitor synced past it, the target count
+        // is reached.
+        add_txos_and_key_images_to_ledger(
+            &mut ledger_db,
+            BlockVersion::MAX,
+            tx.prefix.outputs.clone(),
+            tx.key_images(),
+            &mut rng,
+        )
+        .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let data = defragment(&ledger_db);
+        assert!(!data.active);
+        assert_eq!(data.num_utxos, 15);
+        assert_eq!(data.num_txs_landed, 1);
+        assert_eq!(data.num_txs_failed, 0);
+        assert!(data.pending_tx.is_none());
+        assert_eq!(data.remaining_budget(), Mob::MINIMUM_FEE);
+        assert_eq!(proposed_txs(&conn_manager).len(), 1);
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for automatic defragmentation policies.
+//! * Stores a map of monitor id -> DefragmentationData.
+//! * Each entry holds the policy configured for the monitor, along with the
+//!   progress the background defragmentation thread made towards it.
+
+use crate::{error::Error, monitor_store::MonitorId, webhook_store::WatchedTx};
+
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_common::logger::Logger;
+use prost::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const MONITOR_ID_TO_DEFRAGMENTATION_DATA_DB_NAME: &str =
+    "mobilecoind_db:defragmentation_store:monitor_id_to_defragmentation_data";
+
+/// Configuration of automatic defragmentation for a single monitor.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct DefragmentationPolicy {
+    /// The token whose UTXOs get consolidated.
+    #[prost(uint64, tag = "1")]
+    pub token_id: u64,
+
+    /// Defragmentation starts once a subaddress holds at least this many
+    /// UTXOs of the token.
+    #[prost(uint64, tag = "2")]
+    pub trigger_utxo_count: u64,
+
+    /// Defragmentation stops once the subaddress is down to this many UTXOs
+    /// of the token.
+    #[prost(uint64, tag = "3")]
+    pub target_utxo_count: u64,
+
+    /// The total fees that may be spent on defragmentation transactions.
+    #[prost(uint64, tag = "4")]
+    pub fee_budget: u64,
+}
+
+impl DefragmentationPolicy {
+    /// Check that the policy is usable.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.target_utxo_count == 0 {
+            return Err(Error::InvalidArgument(
+                "target_utxo_count".to_owned(),
+                "must be greater than 0".to_owned(),
+            ));
+        }
+        if self.trigger_utxo_count <= self.target_utxo_count {
+            return Err(Error::InvalidArgument(
+                "trigger_utxo_count".to_owned(),
+                "must be greater than target_utxo_count".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Type used as the stored data in the monitor_id_to_defragmentation_data
+/// database.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct DefragmentationData {
+    /// The configured policy.
+    #[prost(message, required, tag = "1")]
+    pub policy: DefragmentationPolicy,
+
+    /// Whether a defragmentation round is in progress, i.e. the trigger count
+    /// was reached and the target count was not reached yet.
+    #[prost(bool, tag = "2")]
+    pub active: bool,
+
+    /// The subaddress being defragmented by the current (or last) round.
+    #[prost(uint64, tag = "3")]
+    pub subaddress_index: u64,
+
+    /// Number of UTXOs the subaddress held when it was last checked.
+    #[prost(uint64, tag = "4")]
+    pub num_utxos: u64,
+
+    /// Defragmentation id written into the memos of the current (or last)
+    /// round.
+    #[prost(uint64, tag = "5")]
+    pub defrag_id: u64,
+
+    /// Total fees paid by submitted transactions.
+    #[prost(uint64, tag = "6")]
+    pub fees_spent: u64,
+
+    /// Number of transactions submitted.
+    #[prost(uint64, tag = "7")]
+    pub num_txs_submitted: u64,
+
+    /// Number of submitted transactions that landed in the ledger.
+    #[prost(uint64, tag = "8")]
+    pub num_txs_landed: u64,
+
+    /// Number of submitted transactions that failed to land.
+    #[prost(uint64, tag = "9")]
+    pub num_txs_failed: u64,
+
+    /// The submitted transaction we are waiting on, if any.
+    #[prost(message, optional, tag = "10")]
+    pub pending_tx: Option<WatchedTx>,
+
+    /// The most recent error encountered while defragmenting, empty if the
+    /// last attempt succeeded.
+    #[prost(string, tag = "11")]
+    pub last_error: String,
+}
+
+impl DefragmentationData {
+    pub fn new(policy: DefragmentationPolicy) -> Self {
+        Self {
+            policy,
+            active: false,
+            subaddress_index: 0,
+            num_utxos: 0,
+            defrag_id: 0,
+            fees_spent: 0,
+            num_txs_submitted: 0,
+            num_txs_landed: 0,
+            num_txs_failed: 0,
+            pending_tx: None,
+            last_error: String::new(),
+        }
+    }
+
+    /// The part of the fee budget that was not spent yet.
+    pub fn remaining_budget(&self) -> u64 {
+        self.policy.fee_budget.saturating_sub(self.fees_spent)
+    }
+}
+
+/// Wrapper for the monitor_id_to_defragmentation_data database
+#[derive(Clone)]
+pub struct DefragmentationStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Mapping of monitor id -> DefragmentationData
+    monitor_id_to_defragmentation_data: Database,
+}
+
+impl DefragmentationStore {
+    pub fn new(env: Arc<Environment>, _logger: Logger) -> Result<Self, Error> {
+        let monitor_id_to_defragmentation_data = env.create_db(
+            Some(MONITOR_ID_TO_DEFRAGMENTATION_DATA_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            _env: env,
+            monitor_id_to_defragmentation_data,
+        })
+    }
+
+    /// Set the policy of a monitor. Progress made under a previous policy is
+    /// kept, so the fee budget covers every transaction submitted on behalf of
+    /// the monitor.
+    pub fn set_policy(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+        policy: &DefragmentationPolicy,
+    ) -> Result<(), Error> {
+        let data = match self.get_data(db_txn, monitor_id) {
+            Ok(data) => DefragmentationData {
+                policy: policy.clone(),
+                ..data
+            },
+            Err(Error::DefragmentationPolicyNotFound) => DefragmentationData::new(policy.clone()),
+            Err(err) => return Err(err),
+        };
+        self.set_data(db_txn, monitor_id, &data)
+    }
+
+    /// Store the data of a monitor.
+    pub fn set_data(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+        data: &DefragmentationData,
+    ) -> Result<(), Error> {
+        db_txn.put(
+            self.monitor_id_to_defragmentation_data,
+            monitor_id,
+            &mc_util_serial::encode(data),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Remove the policy of a monitor.
+    pub fn remove(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        match db_txn.del(self.monitor_id_to_defragmentation_data, monitor_id, None) {
+            Ok(()) => Ok(()),
+            Err(lmdb::Error::NotFound) => Err(Error::DefragmentationPolicyNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the DefragmentationData for a given `monitor_id`.
+    pub fn get_data(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+    ) -> Result<DefragmentationData, Error> {
+        match db_txn.get(self.monitor_id_to_defragmentation_data, monitor_id) {
+            Ok(value_bytes) => Ok(mc_util_serial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::DefragmentationPolicyNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the data of all monitors that have a policy.
+    pub fn get_all(
+        &self,
+        db_txn: &impl Transaction,
+    ) -> Result<Vec<(MonitorId, DefragmentationData)>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.monitor_id_to_defragmentation_data)?;
+
+        cursor
+            .iter_start()
+            .map(|result| {
+                let (key, value) = result?;
+                let monitor_id = MonitorId::try_from(key).map_err(|_| Error::KeyDeserialization)?;
+                Ok((monitor_id, mc_util_serial::decode(value)?))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use mc_common::logger::test_with_logger;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_policy() {
+        let policy = DefragmentationPolicy {
+            token_id: 0,
+            trigger_utxo_count: 20,
+            target_utxo_count: 5,
+            fee_budget: 1000,
+        };
+        assert!(policy.validate().is_ok());
+
+        assert_matches!(
+            DefragmentationPolicy {
+                target_utxo_count: 0,
+                ..policy.clone()
+            }
+            .validate(),
+            Err(Error::InvalidArgument(..))
+        );
+        assert_matches!(
+            DefragmentationPolicy {
+                trigger_utxo_count: 5,
+                ..policy
+            }
+            .validate(),
+            Err(Error::InvalidArgument(..))
+        );
+    }
+
+    #[test_with_logger]
+    fn test_defragmentation_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let db_tmp = TempDir::new().expect("Could not make tempdir for defragmentation store db");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_tmp.path())
+                .unwrap(),
+        );
+        let store = DefragmentationStore::new(env.clone(), logger).unwrap();
+
+        let (_monitor_data, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let policy = DefragmentationPolicy {
+            token_id: 0,
+            trigger_utxo_count: 20,
+            target_utxo_count: 5,
+            fee_budget: 1000,
+        };
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_matches!(
+            store.get_data(&db_txn, &monitor_id0),
+            Err(Error::DefragmentationPolicyNotFound)
+        );
+        store
+            .set_policy(&mut db_txn, &monitor_id0, &policy)
+            .unwrap();
+        assert_eq!(
+            store.get_data(&db_txn, &monitor_id0).unwrap(),
+            DefragmentationData::new(policy.clone())
+        );
+
+        // Changing the policy keeps the progress made so far.
+        let mut data = store.get_data(&db_txn, &monitor_id0).unwrap();
+        data.fees_spent = 400;
+        data.num_txs_submitted = 1;
+        store.set_data(&mut db_txn, &monitor_id0, &data).unwrap();
+
+        let new_policy = DefragmentationPolicy {
+            fee_budget: 2000,
+            ..policy.clone()
+        };
+        store
+            .set_policy(&mut db_txn, &monitor_id0, &new_policy)
+            .unwrap();
+        let data = store.get_data(&db_txn, &monitor_id0).unwrap();
+        assert_eq!(data.policy, new_policy);
+        assert_eq!(data.num_txs_submitted, 1);
+        assert_eq!(data.remaining_budget(), 1600);
+
+        store
+            .set_policy(&mut db_txn, &monitor_id1, &policy)
+            .unwrap();
+        let mut expected = vec![monitor_id0, monitor_id1];
+        expected.sort();
+        assert_eq!(
+            store
+                .get_all(&db_txn)
+                .unwrap()
+                .into_iter()
+                .map(|(monitor_id, _data)| monitor_id)
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        store.remove(&mut db_txn, &monitor_id0).unwrap();
+        assert_matches!(
+            store.remove(&mut db_txn, &monitor_id0),
+            Err(Error::DefragmentationPolicyNotFound)
+        );
+        assert_eq!(store.get_all(&db_txn).unwrap().len(), 1);
+    }
+}
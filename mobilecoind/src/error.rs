@@ -57,6 +57,39 @@ pub enum Error {
     /// No matching key in MonitorStore was found
     MonitorIdNotFound,
 
+    /// An entry in HdMonitorStore already exists for this HdMonitorId key
+    HdMonitorIdExists,
+
+    /// No matching key in HdMonitorStore was found
+    HdMonitorIdNotFound,
+
+    /// A payment request with this payment id is already being tracked
+    PaymentIdExists,
+
+    /// No payment request with this payment id is being tracked
+    PaymentIdNotFound,
+
+    /// No defragmentation policy is set for this monitor
+    DefragmentationPolicyNotFound,
+
+    /// No transaction spending this key image is being resubmitted
+    ResubmittedTxNotFound,
+
+    /// Scheduled payment not found
+    ScheduledPaymentNotFound,
+
+    /// Unsynced TxOut not found
+    UnsyncedTxOutNotFound,
+
+    /// Watch-only monitors cannot sign transactions
+    WatchOnlyMonitor,
+
+    /// Monitor data has neither an account key nor a view account key
+    MissingAccountKey,
+
+    /// Invalid signed transaction: {0}
+    InvalidSignedTx(String),
+
     /// Failed to deserialize lmdb key bytes
     KeyDeserialization,
 
@@ -96,6 +129,9 @@ pub enum Error {
     /// Insufficient funds due to UTXO fragmentation
     InsufficientFundsFragmentedUtxos,
 
+    /// UTXO selection failed: {0}
+    UtxoSelection(String),
+
     /// The validator node we're tying to talk to was not found
     NodeNotFound,
 
@@ -0,0 +1,179 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for HD monitors
+//! * An HD monitor groups the monitors of several accounts derived from the
+//!   same BIP39 mnemonic, so that they can be managed under a single id.
+//! * Each account is tracked by a regular monitor, which is what the sync code
+//!   operates on. This store only keeps track of the grouping.
+//! * HdMonitorId is a hash of the account indices and their monitor ids.
+
+use crate::{database_key::DatabaseByteArrayKey, error::Error, monitor_store::MonitorId};
+
+use lmdb::{Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_common::logger::{log, Logger};
+use mc_crypto_digestible::{Digestible, MerlinTranscript};
+use mc_util_serial::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const HD_MONITOR_ID_TO_HD_MONITOR_DATA_DB_NAME: &str =
+    "mobilecoind_db:hd_monitor_store:hd_monitor_id_to_hd_monitor_data";
+
+/// Type used as the stored data in the hd_monitor_id_to_hd_monitor_data
+/// database.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct HdMonitorData {
+    /// The SLIP-0010 account indices of the accounts in this HD monitor.
+    #[prost(uint32, repeated, tag = "1")]
+    pub account_indices: Vec<u32>,
+
+    /// The monitor tracking each account, in the same order as
+    /// `account_indices`.
+    #[prost(message, repeated, tag = "2")]
+    pub monitor_ids: Vec<MonitorId>,
+
+    /// Optional HD monitor name.
+    #[prost(string, tag = "3")]
+    pub name: String,
+}
+
+impl HdMonitorData {
+    pub fn new(
+        account_indices: Vec<u32>,
+        monitor_ids: Vec<MonitorId>,
+        name: &str,
+    ) -> Result<Self, Error> {
+        if account_indices.is_empty() {
+            return Err(Error::InvalidArgument(
+                "account_indices".to_string(),
+                "must not be empty".to_string(),
+            ));
+        }
+        if account_indices.len() != monitor_ids.len() {
+            return Err(Error::InvalidArgument(
+                "monitor_ids".to_string(),
+                "must have one entry per account index".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            account_indices,
+            monitor_ids,
+            name: name.to_owned(),
+        })
+    }
+
+    /// Iterate over (account index, monitor id) pairs.
+    pub fn accounts(&self) -> impl Iterator<Item = (u32, &MonitorId)> {
+        self.account_indices
+            .iter()
+            .copied()
+            .zip(self.monitor_ids.iter())
+    }
+}
+
+/// Type used as the key in the hd_monitor_id_to_hd_monitor_data database
+pub type HdMonitorId = DatabaseByteArrayKey;
+
+impl From<&HdMonitorData> for HdMonitorId {
+    // Name isn't included here, for the same reason as for MonitorId. The
+    // monitor ids already commit to the accounts, subaddress ranges and first
+    // block.
+    fn from(src: &HdMonitorData) -> HdMonitorId {
+        #[derive(Digestible)]
+        struct ConstHdMonitorData {
+            pub account_indices: Vec<u32>,
+            pub monitor_ids: Vec<MonitorId>,
+        }
+
+        let const_data = ConstHdMonitorData {
+            account_indices: src.account_indices.clone(),
+            monitor_ids: src.monitor_ids.clone(),
+        };
+
+        let temp: [u8; 32] = const_data.digest32::<MerlinTranscript>(b"hd_monitor_data");
+
+        Self::from(temp)
+    }
+}
+
+/// Wrapper for the hd_monitor_id_to_hd_monitor_data database
+#[derive(Clone)]
+pub struct HdMonitorStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Mapping of HdMonitorId -> HdMonitorData
+    hd_monitor_id_to_hd_monitor_data: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl HdMonitorStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let hd_monitor_id_to_hd_monitor_data = env.create_db(
+            Some(HD_MONITOR_ID_TO_HD_MONITOR_DATA_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            _env: env,
+            hd_monitor_id_to_hd_monitor_data,
+            logger,
+        })
+    }
+
+    /// Add a new HD monitor.
+    pub fn add(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        data: &HdMonitorData,
+    ) -> Result<HdMonitorId, Error> {
+        let hd_monitor_id = HdMonitorId::from(data);
+
+        log::trace!(
+            self.logger,
+            "adding new hd monitor {}: {:?}",
+            hd_monitor_id,
+            data
+        );
+
+        match db_txn.put(
+            self.hd_monitor_id_to_hd_monitor_data,
+            hd_monitor_id.as_bytes(),
+            &mc_util_serial::encode(data),
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(_) => Ok(hd_monitor_id),
+            Err(lmdb::Error::KeyExist) => Err(Error::HdMonitorIdExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Delete data for a given HD monitor.
+    pub fn remove(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        hd_monitor_id: &HdMonitorId,
+    ) -> Result<(), Error> {
+        match db_txn.del(self.hd_monitor_id_to_hd_monitor_data, hd_monitor_id, None) {
+            Ok(()) => Ok(()),
+            Err(lmdb::Error::NotFound) => Err(Error::HdMonitorIdNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the HdMonitorData for a given `hd_monitor_id`.
+    pub fn get_data(
+        &self,
+        db_txn: &impl Transaction,
+        hd_monitor_id: &HdMonitorId,
+    ) -> Result<HdMonitorData, Error> {
+        match db_txn.get(self.hd_monitor_id_to_hd_monitor_data, hd_monitor_id) {
+            Ok(value_bytes) => Ok(mc_util_serial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::HdMonitorIdNotFound),
+            Err(err) => Err(Error::Lmdb(err)),
+        }
+    }
+}
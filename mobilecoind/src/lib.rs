@@ -6,21 +6,34 @@ extern crate alloc;
 
 pub mod config;
 pub mod database;
+pub mod defragmentation;
 pub mod payments;
+pub mod resubmission;
+pub mod scheduled_payments;
 pub mod service;
 pub mod t3_sync;
+pub mod utxo_selection;
+pub mod webhooks;
 
 mod conversions;
 mod database_key;
 mod db_crypto;
+mod defragmentation_store;
 mod error;
+mod hd_monitor_store;
 mod monitor_store;
+mod payment_request_store;
 mod processed_block_store;
+mod resubmission_store;
+mod scheduled_payment_store;
 mod subaddress_store;
 mod sync;
 mod t3_store;
 mod transaction_memo;
+mod tx_status;
+mod unsynced_txo_store;
 mod utxo_store;
+mod webhook_store;
 pub use utxo_store::UnspentTxOut;
 
 #[cfg(test)]
@@ -7,13 +7,13 @@
 use crate::{database_key::DatabaseByteArrayKey, db_crypto::DbCryptoProvider, error::Error};
 
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
-use mc_account_keys::AccountKey;
+use mc_account_keys::{AccountKey, PublicAddress, ViewAccountKey};
 use mc_common::{
     logger::{log, Logger},
     HashMap,
 };
 use mc_crypto_digestible::{Digestible, MerlinTranscript};
-use mc_crypto_keys::RistrettoPublic;
+use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
 use mc_util_serial::Message;
 use std::{ops::Range, sync::Arc};
 
@@ -24,9 +24,10 @@ pub const MONITOR_ID_TO_MONITOR_DATA_DB_NAME: &str =
 /// Type used as the stored data in the monitor_id_to_monitor_data database.
 #[derive(Clone, Eq, Hash, PartialEq, Message)]
 pub struct MonitorData {
-    /// The private key pair for the account this monitor watches.
-    #[prost(message, required, tag = "1")]
-    pub account_key: AccountKey,
+    /// The private key pair for the account this monitor watches. This is not
+    /// set for watch-only monitors.
+    #[prost(message, optional, tag = "1")]
+    pub account_key: Option<AccountKey>,
 
     /// The smallest subaddress index in the range this monitor watches.
     #[prost(uint64, tag = "2")]
@@ -47,6 +48,18 @@ pub struct MonitorData {
     /// Optional monitor name.
     #[prost(string, tag = "6")]
     pub name: String,
+
+    /// When non-zero, the subaddress range is extended whenever funds arrive
+    /// at one of its last `gap_limit` subaddresses, so that at least this many
+    /// subaddresses past the highest one that received funds are watched.
+    #[prost(uint64, tag = "7")]
+    pub gap_limit: u64,
+
+    /// The view private key and spend public key of the account a watch-only
+    /// monitor watches. Only one of `account_key` and `view_account_key` is
+    /// set.
+    #[prost(message, optional, tag = "8")]
+    pub view_account_key: Option<ViewAccountKey>,
 }
 
 impl MonitorData {
@@ -56,6 +69,44 @@ impl MonitorData {
         num_subaddresses: u64,
         first_block: u64,
         name: &str,
+    ) -> Result<Self, Error> {
+        Self::new_impl(
+            Some(account_key),
+            None,
+            first_subaddress,
+            num_subaddresses,
+            first_block,
+            name,
+        )
+    }
+
+    /// Create the data of a watch-only monitor. Watch-only monitors detect
+    /// incoming TxOuts, but need their key images to be supplied externally
+    /// before they can be spent, and can only build unsigned transactions.
+    pub fn new_watch_only(
+        view_account_key: ViewAccountKey,
+        first_subaddress: u64,
+        num_subaddresses: u64,
+        first_block: u64,
+        name: &str,
+    ) -> Result<Self, Error> {
+        Self::new_impl(
+            None,
+            Some(view_account_key),
+            first_subaddress,
+            num_subaddresses,
+            first_block,
+            name,
+        )
+    }
+
+    fn new_impl(
+        account_key: Option<AccountKey>,
+        view_account_key: Option<ViewAccountKey>,
+        first_subaddress: u64,
+        num_subaddresses: u64,
+        first_block: u64,
+        name: &str,
     ) -> Result<Self, Error> {
         if num_subaddresses == 0 {
             return Err(Error::InvalidArgument(
@@ -72,9 +123,65 @@ impl MonitorData {
             // The next block we need to sync is our first block.
             next_block: first_block,
             name: name.to_owned(),
+            gap_limit: 0,
+            view_account_key,
         })
     }
 
+    /// Whether this monitor only holds the view key of its account.
+    pub fn is_watch_only(&self) -> bool {
+        self.account_key.is_none()
+    }
+
+    /// Get the account key needed to spend this monitor's funds.
+    pub fn spending_account_key(&self) -> Result<&AccountKey, Error> {
+        self.account_key.as_ref().ok_or(Error::WatchOnlyMonitor)
+    }
+
+    /// Get the view private key and spend public key of this monitor's
+    /// account.
+    pub fn view_account_key(&self) -> Result<ViewAccountKey, Error> {
+        match (&self.account_key, &self.view_account_key) {
+            (Some(account_key), _) => Ok(ViewAccountKey::from(account_key)),
+            (None, Some(view_account_key)) => Ok(view_account_key.clone()),
+            (None, None) => Err(Error::MissingAccountKey),
+        }
+    }
+
+    /// Get the view private key of this monitor's account.
+    pub fn view_private_key(&self) -> Result<RistrettoPrivate, Error> {
+        Ok(*self.view_account_key()?.view_private_key())
+    }
+
+    /// Get a subaddress of this monitor's account. Watch-only monitors have no
+    /// fog information, so their subaddresses never include any.
+    pub fn subaddress(&self, index: u64) -> Result<PublicAddress, Error> {
+        match &self.account_key {
+            Some(account_key) => Ok(account_key.subaddress(index)),
+            None => Ok(self.view_account_key()?.subaddress(index)),
+        }
+    }
+
+    /// Get the default subaddress of this monitor's account.
+    pub fn default_subaddress(&self) -> Result<PublicAddress, Error> {
+        match &self.account_key {
+            Some(account_key) => Ok(account_key.default_subaddress()),
+            None => Ok(self.view_account_key()?.default_subaddress()),
+        }
+    }
+
+    /// Get the subaddress range needed to keep `gap_limit` subaddresses
+    /// past `subaddress_index` watched, if it is larger than the current one.
+    pub fn extended_subaddress_indexes(&self, subaddress_index: u64) -> Option<Range<u64>> {
+        if self.gap_limit == 0 || !self.subaddress_indexes().contains(&subaddress_index) {
+            return None;
+        }
+        let end = subaddress_index
+            .checked_add(1)?
+            .checked_add(self.gap_limit)?;
+        (end > self.subaddress_indexes().end).then_some(self.first_subaddress..end)
+    }
+
     pub fn subaddress_indexes(&self) -> Range<u64> {
         self.first_subaddress..self.first_subaddress + self.num_subaddresses
     }
@@ -83,13 +190,16 @@ impl MonitorData {
 /// Type used as the key in the monitor_id_to_monitor_data database
 pub type MonitorId = DatabaseByteArrayKey;
 
-impl From<&MonitorData> for MonitorId {
+impl TryFrom<&MonitorData> for MonitorId {
+    type Error = Error;
+
     // When constructing a MonitorId from a given MonitorData object we only want to
     // hash the data that doesn't change over time.
     // Name isn't included here - two monitors with identical address/subaddress
     // range/first_block should have the same id even if they have a different
-    // name,
-    fn from(src: &MonitorData) -> MonitorId {
+    // name. Watch-only monitors have no fog information, so they only share the
+    // id of the full monitor of the same account when the account has no fog.
+    fn try_from(src: &MonitorData) -> Result<MonitorId, Error> {
         // The structure of mc_account_keys::PublicAddress changed when the fog
         // signature scheme was implemented. This re-implements the original
         // structure in order to maintain a consistent hash in the database.
@@ -119,7 +229,7 @@ impl From<&MonitorData> for MonitorId {
             pub first_block: u64,
         }
 
-        let real_subaddress = src.account_key.default_subaddress();
+        let real_subaddress = src.default_subaddress()?;
 
         let const_data = ConstMonitorData {
             address: PublicAddress {
@@ -145,7 +255,7 @@ impl From<&MonitorData> for MonitorId {
 
         let temp: [u8; 32] = const_data.digest32::<MerlinTranscript>(b"monitor_data");
 
-        Self::from(temp)
+        Ok(Self::from(temp))
     }
 }
 
@@ -191,7 +301,7 @@ impl MonitorStore {
         db_txn: &mut RwTransaction<'_>,
         data: &MonitorData,
     ) -> Result<MonitorId, Error> {
-        let monitor_id = MonitorId::from(data);
+        let monitor_id = MonitorId::try_from(data)?;
         let key_bytes = monitor_id.as_bytes();
 
         let value_bytes = self
@@ -370,7 +480,7 @@ pKZkdp8MQU5TLFOE9qjNeVsCAwEAAQ==
         let identity = RootIdentity::from_random(&mut rng);
         let key = AccountKey::from(&identity);
         let data = MonitorData::new(key, 1, 10, 1, "test").expect("Could not create monitor data");
-        let id = MonitorId::from(&data);
+        let id = MonitorId::try_from(&data).unwrap();
         let expected = hex::decode(HEXPECTED).expect("Could not decode expected data to bytes");
         assert_eq!(expected, id.as_bytes().to_vec(), "{}", hex_fmt::HexFmt(id));
 
@@ -385,7 +495,7 @@ pKZkdp8MQU5TLFOE9qjNeVsCAwEAAQ==
         let fog_key = AccountKey::from(&fog_identity);
         let fog_data = MonitorData::new(fog_key, 10, 100, 10, "fog test")
             .expect("Could not create monitor data");
-        let fog_id = MonitorId::from(&fog_data);
+        let fog_id = MonitorId::try_from(&fog_data).unwrap();
         let fog_expected =
             hex::decode(FOG_HEXPECTED).expect("Could not decode expected data to bytes");
         assert_eq!(
@@ -395,6 +505,102 @@ pKZkdp8MQU5TLFOE9qjNeVsCAwEAAQ==
         );
     }
 
+    #[test]
+    fn extended_subaddress_indexes() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let key = AccountKey::random(&mut rng);
+        let mut data = MonitorData::new(key, 2, 5, 0, "").unwrap();
+
+        // Without a gap limit the range never grows.
+        assert_eq!(data.extended_subaddress_indexes(6), None);
+
+        data.gap_limit = 3;
+        // Enough subaddresses are already watched past index 2 and 3.
+        assert_eq!(data.extended_subaddress_indexes(2), None);
+        assert_eq!(data.extended_subaddress_indexes(3), None);
+        // Funds close to the end of the range extend it.
+        assert_eq!(data.extended_subaddress_indexes(4), Some(2..8));
+        assert_eq!(data.extended_subaddress_indexes(6), Some(2..10));
+        // Subaddresses outside of the range are ignored.
+        assert_eq!(data.extended_subaddress_indexes(1), None);
+        assert_eq!(data.extended_subaddress_indexes(7), None);
+    }
+
+    #[test]
+    fn watch_only_monitor_data() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(key.clone(), 0, 10, 0, "").unwrap();
+        let watch_only_data =
+            MonitorData::new_watch_only(ViewAccountKey::from(&key), 0, 10, 0, "").unwrap();
+
+        // For an account without fog, a watch-only monitor watches the same
+        // subaddresses as a full monitor, and so has the same id.
+        assert!(!data.is_watch_only());
+        assert!(watch_only_data.is_watch_only());
+        assert_eq!(
+            MonitorId::try_from(&data).unwrap(),
+            MonitorId::try_from(&watch_only_data).unwrap()
+        );
+        assert_eq!(watch_only_data.subaddress(3).unwrap(), key.subaddress(3));
+        assert_eq!(
+            watch_only_data.view_private_key().unwrap(),
+            *key.view_private_key()
+        );
+
+        // It cannot spend, though.
+        assert_eq!(data.spending_account_key().unwrap(), &key);
+        assert_matches!(
+            watch_only_data.spending_account_key(),
+            Err(Error::WatchOnlyMonitor)
+        );
+
+        // The keys survive serialization.
+        let decoded: MonitorData =
+            mc_util_serial::decode(&mc_util_serial::encode(&watch_only_data)).unwrap();
+        assert_eq!(decoded, watch_only_data);
+        assert!(decoded.is_watch_only());
+
+        // Monitor data without any key is rejected.
+        let mut keyless_data = watch_only_data;
+        keyless_data.view_account_key = None;
+        assert_matches!(
+            keyless_data.view_account_key(),
+            Err(Error::MissingAccountKey)
+        );
+        assert_matches!(
+            MonitorId::try_from(&keyless_data),
+            Err(Error::MissingAccountKey)
+        );
+    }
+
+    #[test]
+    fn watch_only_monitor_data_with_fog() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let key = AccountKey::random_with_fog(&mut rng);
+        let data = MonitorData::new(key.clone(), 0, 10, 0, "").unwrap();
+        let watch_only_data =
+            MonitorData::new_watch_only(ViewAccountKey::from(&key), 0, 10, 0, "").unwrap();
+
+        // The watch-only subaddresses have the same keys, but no fog information,
+        // so the monitors have different ids.
+        let subaddress = watch_only_data.subaddress(3).unwrap();
+        assert_eq!(
+            subaddress.spend_public_key(),
+            key.subaddress(3).spend_public_key()
+        );
+        assert_eq!(
+            subaddress.view_public_key(),
+            key.subaddress(3).view_public_key()
+        );
+        assert_eq!(subaddress.fog_report_url(), None);
+        assert_ne!(data.subaddress(3).unwrap(), subaddress);
+        assert_ne!(
+            MonitorId::try_from(&data).unwrap(),
+            MonitorId::try_from(&watch_only_data).unwrap()
+        );
+    }
+
     // MonitorStore basic functionality tests
     #[test_with_logger]
     fn test_monitor_store(logger: Logger) {
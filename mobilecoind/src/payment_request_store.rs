@@ -0,0 +1,443 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for outstanding payment requests.
+//! * Stores a map of payment id -> PaymentRequestData.
+//! * Incoming TxOuts whose memo is an AuthenticatedSenderWithPaymentRequestId
+//!   memo carrying a known payment id are credited to the matching request,
+//!   provided they were received at the requested monitor/subaddress with the
+//!   requested token.
+//! * The sender of a TxOut is not known to mobilecoind, so the memo is not
+//!   authenticated. This only matters for attributing a payment to a sender,
+//!   the amount credited is always what was actually received.
+
+use crate::{error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_common::logger::{log, Logger};
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::MemoPayload;
+use mc_transaction_extra::MemoType;
+use prost::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const PAYMENT_ID_TO_PAYMENT_REQUEST_DATA_DB_NAME: &str =
+    "mobilecoind_db:payment_request_store:payment_id_to_payment_request_data";
+
+/// The status of a payment request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PaymentRequestStatus {
+    /// Nothing has been received yet.
+    Pending,
+
+    /// Some, but not all, of the requested value has been received.
+    PartiallyPaid,
+
+    /// The requested value has been received in full.
+    Paid,
+
+    /// The expiry block passed before the requested value was received in
+    /// full.
+    Expired,
+}
+
+/// Type used as the stored data in the payment_id_to_payment_request_data
+/// database.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct PaymentRequestData {
+    /// The monitor that is expected to receive the payment.
+    #[prost(message, required, tag = "1")]
+    pub monitor_id: MonitorId,
+
+    /// The subaddress that is expected to receive the payment.
+    #[prost(uint64, tag = "2")]
+    pub subaddress_index: u64,
+
+    /// The requested value.
+    #[prost(uint64, tag = "3")]
+    pub value: u64,
+
+    /// The requested token id.
+    #[prost(uint64, tag = "4")]
+    pub token_id: u64,
+
+    /// The memo included in the request code.
+    #[prost(string, tag = "5")]
+    pub memo: String,
+
+    /// The last block a payment is accepted in, or 0 if the request never
+    /// expires.
+    #[prost(uint64, tag = "6")]
+    pub expiry_block: u64,
+
+    /// Total value received so far.
+    #[prost(uint64, tag = "7")]
+    pub amount_received: u64,
+
+    /// Public keys of the TxOuts that were credited to this request.
+    #[prost(message, repeated, tag = "8")]
+    pub tx_out_public_keys: Vec<CompressedRistrettoPublic>,
+}
+
+impl PaymentRequestData {
+    pub fn new(
+        monitor_id: MonitorId,
+        subaddress_index: u64,
+        value: u64,
+        token_id: u64,
+        memo: &str,
+        expiry_block: u64,
+    ) -> Self {
+        Self {
+            monitor_id,
+            subaddress_index,
+            value,
+            token_id,
+            memo: memo.to_owned(),
+            expiry_block,
+            amount_received: 0,
+            tx_out_public_keys: Vec::new(),
+        }
+    }
+
+    /// Get the status of this request, given the next block the receiving
+    /// monitor is going to process.
+    pub fn status(&self, next_block: u64) -> PaymentRequestStatus {
+        if self.amount_received >= self.value {
+            PaymentRequestStatus::Paid
+        } else if self.expiry_block != 0 && next_block > self.expiry_block {
+            PaymentRequestStatus::Expired
+        } else if self.amount_received > 0 {
+            PaymentRequestStatus::PartiallyPaid
+        } else {
+            PaymentRequestStatus::Pending
+        }
+    }
+
+    /// Check whether a TxOut received by `monitor_id` in block `block_index`
+    /// counts towards this request.
+    fn accepts(&self, monitor_id: &MonitorId, block_index: u64, utxo: &UnspentTxOut) -> bool {
+        self.monitor_id == *monitor_id
+            && self.subaddress_index == utxo.subaddress_index
+            && self.token_id == utxo.token_id
+            && (self.expiry_block == 0 || block_index <= self.expiry_block)
+    }
+}
+
+/// Extract the payment request id from a memo payload, if it has one.
+fn payment_request_id(memo_payload: &[u8]) -> Option<u64> {
+    let memo_payload = MemoPayload::try_from(memo_payload).ok()?;
+    match MemoType::try_from(&memo_payload) {
+        Ok(MemoType::AuthenticatedSenderWithPaymentRequestId(memo)) => {
+            Some(memo.payment_request_id())
+        }
+        _ => None,
+    }
+}
+
+/// Wrapper for the payment_id_to_payment_request_data database
+#[derive(Clone)]
+pub struct PaymentRequestStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Mapping of payment id -> PaymentRequestData
+    payment_id_to_payment_request_data: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl PaymentRequestStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let payment_id_to_payment_request_data = env.create_db(
+            Some(PAYMENT_ID_TO_PAYMENT_REQUEST_DATA_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            _env: env,
+            payment_id_to_payment_request_data,
+            logger,
+        })
+    }
+
+    /// Start tracking a new payment request.
+    pub fn add(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        payment_id: u64,
+        data: &PaymentRequestData,
+    ) -> Result<(), Error> {
+        match db_txn.put(
+            self.payment_id_to_payment_request_data,
+            &payment_id.to_be_bytes(),
+            &mc_util_serial::encode(data),
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(()) => Ok(()),
+            Err(lmdb::Error::KeyExist) => Err(Error::PaymentIdExists),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Stop tracking a payment request.
+    pub fn remove(&self, db_txn: &mut RwTransaction<'_>, payment_id: u64) -> Result<(), Error> {
+        match db_txn.del(
+            self.payment_id_to_payment_request_data,
+            &payment_id.to_be_bytes(),
+            None,
+        ) {
+            Ok(()) => Ok(()),
+            Err(lmdb::Error::NotFound) => Err(Error::PaymentIdNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Stop tracking all payment requests made out to a given monitor.
+    pub fn remove_for_monitor(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        for (payment_id, data) in self.get_all(db_txn)? {
+            if data.monitor_id == *monitor_id {
+                self.remove(db_txn, payment_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the PaymentRequestData for a given `payment_id`.
+    pub fn get_data(
+        &self,
+        db_txn: &impl Transaction,
+        payment_id: u64,
+    ) -> Result<PaymentRequestData, Error> {
+        match db_txn.get(
+            self.payment_id_to_payment_request_data,
+            &payment_id.to_be_bytes(),
+        ) {
+            Ok(value_bytes) => Ok(mc_util_serial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::PaymentIdNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get all tracked payment requests, ordered by payment id.
+    pub fn get_all(
+        &self,
+        db_txn: &impl Transaction,
+    ) -> Result<Vec<(u64, PaymentRequestData)>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.payment_id_to_payment_request_data)?;
+
+        cursor
+            .iter_start()
+            .map(|result| {
+                let (key, value) = result?;
+                let payment_id =
+                    u64::from_be_bytes(key.try_into().map_err(|_| Error::KeyDeserialization)?);
+                Ok((payment_id, mc_util_serial::decode(value)?))
+            })
+            .collect()
+    }
+
+    /// Credit a TxOut received by `monitor_id` in block `block_index` to the
+    /// payment request its memo refers to, if any. Returns the payment id of
+    /// the request that was credited.
+    pub fn process_utxo(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+        block_index: u64,
+        utxo: &UnspentTxOut,
+    ) -> Result<Option<u64>, Error> {
+        let Some(payment_id) = payment_request_id(&utxo.memo_payload) else {
+            return Ok(None);
+        };
+
+        let mut data = match self.get_data(db_txn, payment_id) {
+            Ok(data) => data,
+            Err(Error::PaymentIdNotFound) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        if !data.accepts(monitor_id, block_index, utxo) {
+            return Ok(None);
+        }
+
+        data.amount_received = data.amount_received.saturating_add(utxo.value);
+        data.tx_out_public_keys.push(utxo.tx_out.public_key);
+
+        log::debug!(
+            self.logger,
+            "Received {} towards payment request {} ({}/{})",
+            utxo.value,
+            payment_id,
+            data.amount_received,
+            data.value
+        );
+
+        db_txn.put(
+            self.payment_id_to_payment_request_data,
+            &payment_id.to_be_bytes(),
+            &mc_util_serial::encode(&data),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(Some(payment_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{get_test_databases, get_test_monitor_data_and_id, BlockVersion};
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::test_with_logger;
+    use mc_ledger_db::Ledger;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_transaction_extra::{AuthenticatedSenderWithPaymentRequestIdMemo, SenderMemoCredential};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_payment_request_status() {
+        let mut data = PaymentRequestData::new(MonitorId::default(), 0, 100, 0, "", 10);
+        assert_eq!(data.status(5), PaymentRequestStatus::Pending);
+        assert_eq!(data.status(11), PaymentRequestStatus::Expired);
+
+        data.amount_received = 40;
+        assert_eq!(data.status(5), PaymentRequestStatus::PartiallyPaid);
+        assert_eq!(data.status(11), PaymentRequestStatus::Expired);
+
+        data.amount_received = 100;
+        assert_eq!(data.status(5), PaymentRequestStatus::Paid);
+        assert_eq!(data.status(11), PaymentRequestStatus::Paid);
+
+        // Requests without an expiry block never expire.
+        data.expiry_block = 0;
+        data.amount_received = 0;
+        assert_eq!(data.status(u64::MAX), PaymentRequestStatus::Pending);
+    }
+
+    #[test_with_logger]
+    fn test_payment_request_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (ledger_db, _mobilecoind_db) =
+            get_test_databases(BlockVersion::MAX, 3, &[], 10, logger.clone(), &mut rng);
+
+        let db_tmp = TempDir::new().expect("Could not make tempdir for payment request store db");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_tmp.path())
+                .unwrap(),
+        );
+        let store = PaymentRequestStore::new(env.clone(), logger).unwrap();
+
+        let (_monitor_data, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        // Construct UnspentTxOuts carrying a payment request id memo.
+        let sender = AccountKey::random(&mut rng);
+        let receiver = AccountKey::random(&mut rng);
+        let make_utxo = |idx: u64, value: u64, token_id: u64, payment_request_id: Option<u64>| {
+            let tx_out = ledger_db.get_tx_out_by_index(idx).unwrap();
+            let memo_payload = payment_request_id
+                .map(|id| {
+                    let memo = AuthenticatedSenderWithPaymentRequestIdMemo::new(
+                        &SenderMemoCredential::from(&sender),
+                        receiver.default_subaddress().view_public_key(),
+                        &tx_out.public_key,
+                        id,
+                    );
+                    MemoPayload::from(memo).as_ref().to_vec()
+                })
+                .unwrap_or_default();
+            UnspentTxOut {
+                tx_out,
+                subaddress_index: 0,
+                key_image: KeyImage::from(idx),
+                value,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                token_id,
+                memo_payload,
+            }
+        };
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store
+            .add(
+                &mut db_txn,
+                7,
+                &PaymentRequestData::new(monitor_id0, 0, 100, 0, "rent", 5),
+            )
+            .unwrap();
+        assert_matches!(
+            store.add(
+                &mut db_txn,
+                7,
+                &PaymentRequestData::new(monitor_id0, 0, 1, 0, "", 0),
+            ),
+            Err(Error::PaymentIdExists)
+        );
+
+        // Only matching TxOuts are credited.
+        for (monitor_id, block_index, utxo, expected) in [
+            (monitor_id0, 1, make_utxo(0, 40, 0, Some(7)), Some(7)),
+            (monitor_id0, 1, make_utxo(1, 40, 0, None), None),
+            (monitor_id0, 1, make_utxo(2, 40, 0, Some(8)), None),
+            (monitor_id0, 1, make_utxo(3, 40, 1, Some(7)), None),
+            (monitor_id1, 1, make_utxo(4, 40, 0, Some(7)), None),
+            (monitor_id0, 6, make_utxo(5, 40, 0, Some(7)), None),
+            (monitor_id0, 5, make_utxo(6, 30, 0, Some(7)), Some(7)),
+        ] {
+            assert_eq!(
+                store
+                    .process_utxo(&mut db_txn, &monitor_id, block_index, &utxo)
+                    .unwrap(),
+                expected
+            );
+        }
+        db_txn.commit().unwrap();
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        let data = store.get_data(&db_txn, 7).unwrap();
+        assert_eq!(data.amount_received, 70);
+        assert_eq!(
+            data.tx_out_public_keys,
+            vec![
+                ledger_db.get_tx_out_by_index(0).unwrap().public_key,
+                ledger_db.get_tx_out_by_index(6).unwrap().public_key,
+            ]
+        );
+        assert_eq!(data.status(6), PaymentRequestStatus::Expired);
+        assert_matches!(store.get_data(&db_txn, 8), Err(Error::PaymentIdNotFound));
+        drop(db_txn);
+
+        // Removing a monitor's requests leaves other monitors' requests alone.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store
+            .add(
+                &mut db_txn,
+                8,
+                &PaymentRequestData::new(monitor_id1, 0, 1, 0, "", 0),
+            )
+            .unwrap();
+        store.remove_for_monitor(&mut db_txn, &monitor_id0).unwrap();
+        assert_eq!(
+            store
+                .get_all(&db_txn)
+                .unwrap()
+                .into_iter()
+                .map(|(payment_id, _data)| payment_id)
+                .collect::<Vec<_>>(),
+            vec![8]
+        );
+        store.remove(&mut db_txn, 8).unwrap();
+        assert_matches!(store.remove(&mut db_txn, 8), Err(Error::PaymentIdNotFound));
+    }
+}
@@ -2,7 +2,13 @@
 
 //! Construct and submit transactions to the validator network.
 
-use crate::{database::Database, error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+use crate::{
+    database::Database,
+    error::Error,
+    monitor_store::{MonitorData, MonitorId},
+    utxo_selection::UtxoSelectionStrategy,
+    utxo_store::UnspentTxOut,
+};
 use mc_account_keys::{AccountKey, PublicAddress};
 use mc_blockchain_types::{BlockIndex, BlockVersion};
 use mc_common::{
@@ -13,29 +19,30 @@ use mc_connection::{
     BlockInfo, BlockchainConnection, ConnectionManager, RetryableUserTxConnection, UserTxConnection,
 };
 use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
-use mc_crypto_ring_signature_signer::NoKeysRingSigner;
+use mc_crypto_ring_signature_signer::{NoKeysRingSigner, OneTimeKeyDeriveData};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
 use mc_rand::{CryptoRng, RngCore};
 use mc_transaction_builder::{
-    InputCredentials, MemoBuilder, ReservedSubaddresses, SignedContingentInputBuilder,
-    TransactionBuilder, TxOutContext,
+    DecoyDistribution, DefaultTxOutputsOrdering, InputCredentials, MemoBuilder,
+    ReservedSubaddresses, SignedContingentInputBuilder, TransactionBuilder, TxOutContext,
 };
 use mc_transaction_core::{
     constants::{MAX_INPUTS, RING_SIZE},
     onetime_keys::recover_onetime_private_key,
-    ring_signature::KeyImage,
+    ring_ct::InputRing,
     tx::{Tx, TxOut, TxOutMembershipProof},
+    validation::validate_signature,
     Amount, FeeMap, TokenId,
 };
 use mc_transaction_extra::{
-    SignedContingentInput, SignedContingentInputAmounts, TxOutConfirmationNumber,
+    SignedContingentInput, SignedContingentInputAmounts, TxOutConfirmationNumber, UnsignedTx,
 };
+use mc_transaction_signer::types::{AccountId, TxSignReq, TxSignSecrets};
 use mc_util_uri::FogUri;
-use rand::Rng;
 use std::{
-    cmp::{max, Reverse},
-    collections::BTreeMap,
+    cmp::{max, min, Reverse},
+    collections::{BTreeMap, BTreeSet},
     str::FromStr,
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -123,6 +130,229 @@ pub struct SciForTx {
     pub partial_fill_value: u64,
 }
 
+/// A pending transaction whose inputs have not been signed. This is produced
+/// for watch-only monitors, whose spend private key is held by an external
+/// signer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnsignedTxProposal {
+    /// UTXOs used as inputs for this transaction.
+    pub utxos: Vec<UnspentTxOut>,
+
+    /// Destinations the transaction is being sent to.
+    pub outlays: Vec<OutlayV2>,
+
+    /// The transaction, along with the data needed to sign it.
+    pub unsigned_tx: UnsignedTx,
+
+    /// A map of outlay index -> TxOut index in the transaction prefix.
+    /// This is needed to map recipients to their respective TxOuts.
+    pub outlay_index_to_tx_out_index: HashMap<usize, usize>,
+
+    /// A list of the confirmation numbers, in the same order
+    /// as the outlays.
+    pub outlay_confirmation_numbers: Vec<TxOutConfirmationNumber>,
+}
+
+impl UnsignedTxProposal {
+    /// The signing request read by the `sign-tx` command of
+    /// mc-transaction-signer. The id of the monitor sending the funds is used
+    /// as the account id, and is echoed back in the signing response.
+    pub fn tx_sign_req(&self, sender_monitor_id: &MonitorId) -> TxSignReq {
+        TxSignReq {
+            account_id: AccountId::from(sender_monitor_id.as_bytes()),
+            tx_prefix: self.unsigned_tx.tx_prefix.clone(),
+            rings: self.unsigned_tx.rings.clone(),
+            secrets: TxSignSecrets::TxOutUnblindingData(
+                self.unsigned_tx.tx_out_unblinding_data.clone(),
+            ),
+            block_version: self.unsigned_tx.block_version,
+        }
+    }
+
+    /// Turn this into a TxProposal, given the transaction signed externally.
+    /// Fails unless `tx` is this transaction, spends the inputs with the key
+    /// images they were synced with, and carries a valid signature.
+    pub fn into_tx_proposal(
+        self,
+        tx: Tx,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<TxProposal, Error> {
+        if tx.prefix != self.unsigned_tx.tx_prefix {
+            return Err(Error::InvalidSignedTx("tx prefix does not match".into()));
+        }
+
+        let key_images = tx.key_images();
+        if key_images.len() != self.unsigned_tx.rings.len() {
+            return Err(Error::InvalidSignedTx(format!(
+                "expected {} key images, got {}",
+                self.unsigned_tx.rings.len(),
+                key_images.len()
+            )));
+        }
+        for (i, (ring, key_image)) in self.unsigned_tx.rings.iter().zip(&key_images).enumerate() {
+            let real_input = match ring {
+                InputRing::Signable(ring) => ring.members.get(ring.real_input_index),
+                InputRing::Presigned(_) => None,
+            };
+            let utxo = real_input.and_then(|real_input| {
+                self.utxos
+                    .iter()
+                    .find(|utxo| utxo.tx_out.public_key == real_input.public_key)
+            });
+            match utxo {
+                Some(utxo) if utxo.key_image == *key_image => {}
+                Some(_) => {
+                    return Err(Error::InvalidSignedTx(format!(
+                        "key image of ring {i} does not match its input"
+                    )))
+                }
+                None => {
+                    return Err(Error::InvalidSignedTx(format!(
+                        "ring {i} does not spend one of the inputs"
+                    )))
+                }
+            }
+        }
+
+        validate_signature(self.unsigned_tx.block_version, &tx, rng)
+            .map_err(|err| Error::InvalidSignedTx(err.to_string()))?;
+
+        Ok(TxProposal {
+            utxos: self.utxos,
+            outlays: self.outlays,
+            tx,
+            outlay_index_to_tx_out_index: self.outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers: self.outlay_confirmation_numbers,
+            scis: Vec::new(),
+        })
+    }
+}
+
+/// The keys used to add inputs to a transaction.
+#[derive(Clone, Copy)]
+enum InputKeys<'a> {
+    /// The inputs are signed using the spend private key of this account.
+    Sign(&'a AccountKey),
+
+    /// The inputs are left unsigned, and refer to the subaddress that owns
+    /// them so that an external signer can derive their one-time keys. This
+    /// holds the view private key of the account.
+    Unsigned(RistrettoPrivate),
+}
+
+/// A transaction builder that all inputs and outputs have been added to, along
+/// with the data needed to describe the resulting transaction.
+struct PreparedTx<FPR: FogPubkeyResolver> {
+    tx_builder: TransactionBuilder<FPR>,
+    utxos: Vec<UnspentTxOut>,
+    outlays: Vec<OutlayV2>,
+    tx_out_to_outlay_index: HashMap<TxOut, usize>,
+    outlay_confirmation_numbers: Vec<TxOutConfirmationNumber>,
+    scis: Vec<SciForTx>,
+}
+
+impl<FPR: FogPubkeyResolver> PreparedTx<FPR> {
+    /// Build and sign the transaction.
+    fn build(self, rng: &mut (impl RngCore + CryptoRng)) -> Result<TxProposal, Error> {
+        let tx = self
+            .tx_builder
+            .build(&NoKeysRingSigner {}, rng)
+            .map_err(|err| Error::TxBuild(format!("build tx failed: {err}")))?;
+
+        let outlay_index_to_tx_out_index = map_outlays_to_tx_outs(
+            &tx.prefix.outputs,
+            &self.tx_out_to_outlay_index,
+            self.outlays.len(),
+        );
+
+        Ok(TxProposal {
+            utxos: self.utxos,
+            outlays: self.outlays,
+            tx,
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers: self.outlay_confirmation_numbers,
+            scis: self.scis,
+        })
+    }
+
+    /// Build the transaction without signing it.
+    fn build_unsigned(self) -> Result<UnsignedTxProposal, Error> {
+        let unsigned_tx = self
+            .tx_builder
+            .build_unsigned::<DefaultTxOutputsOrdering>()
+            .map_err(|err| Error::TxBuild(format!("build unsigned tx failed: {err}")))?;
+
+        let outlay_index_to_tx_out_index = map_outlays_to_tx_outs(
+            &unsigned_tx.tx_prefix.outputs,
+            &self.tx_out_to_outlay_index,
+            self.outlays.len(),
+        );
+
+        Ok(UnsignedTxProposal {
+            utxos: self.utxos,
+            outlays: self.outlays,
+            unsigned_tx,
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers: self.outlay_confirmation_numbers,
+        })
+    }
+}
+
+/// Map each TxOut in a constructed transaction to its respective outlay.
+fn map_outlays_to_tx_outs(
+    outputs: &[TxOut],
+    tx_out_to_outlay_index: &HashMap<TxOut, usize>,
+    num_outlays: usize,
+) -> HashMap<usize, usize> {
+    let outlay_index_to_tx_out_index = outputs
+        .iter()
+        .enumerate()
+        .filter_map(|(tx_out_index, tx_out)| {
+            tx_out_to_outlay_index
+                .get(tx_out)
+                .map(|outlay_index| (*outlay_index, tx_out_index))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Sanity check: All of our outlays should have a unique index in the map.
+    assert_eq!(outlay_index_to_tx_out_index.len(), num_outlays);
+    let mut found_tx_out_indices = HashSet::default();
+    for i in 0..num_outlays {
+        let tx_out_index = outlay_index_to_tx_out_index
+            .get(&i)
+            .expect("index not in map");
+        if !found_tx_out_indices.insert(tx_out_index) {
+            panic!("duplicate index {tx_out_index} found in map");
+        }
+    }
+
+    outlay_index_to_tx_out_index
+}
+
+/// The destination of the change of a transaction sent by a monitor.
+/// Watch-only monitors only know the public addresses of their account, so
+/// their reserved subaddresses are derived from the view account key.
+fn monitor_change_destination(
+    monitor_data: &MonitorData,
+    change_subaddress: u64,
+) -> Result<ReservedSubaddresses, Error> {
+    match &monitor_data.account_key {
+        Some(account_key) => Ok(ReservedSubaddresses::from_subaddress_index(
+            account_key,
+            Some(change_subaddress),
+            None,
+        )),
+        None => {
+            let view_account_key = monitor_data.view_account_key()?;
+            Ok(ReservedSubaddresses {
+                primary_address: view_account_key.default_subaddress(),
+                change_subaddress: view_account_key.subaddress(change_subaddress),
+                gift_code_subaddress: view_account_key.gift_code_subaddress(),
+            })
+        }
+    }
+}
+
 pub struct TransactionsManager<
     T: BlockchainConnection + UserTxConnection + 'static,
     FPR: FogPubkeyResolver,
@@ -145,6 +375,9 @@ pub struct TransactionsManager<
     /// connections to fog
     fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
 
+    /// Distribution that mixins are sampled from.
+    decoy_distribution: DecoyDistribution,
+
     /// Logger.
     logger: Logger,
 }
@@ -159,6 +392,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             peer_manager: self.peer_manager.clone(),
             submit_node_offset: self.submit_node_offset.clone(),
             fog_resolver_factory: self.fog_resolver_factory.clone(),
+            decoy_distribution: self.decoy_distribution,
             logger: self.logger.clone(),
         }
     }
@@ -188,6 +422,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         mobilecoind_db: Database,
         peer_manager: ConnectionManager<T>,
         fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
+        decoy_distribution: DecoyDistribution,
         logger: Logger,
     ) -> Self {
         let mut rng = rand::thread_rng();
@@ -197,6 +432,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             peer_manager,
             submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
             fog_resolver_factory,
+            decoy_distribution,
             logger,
         }
     }
@@ -275,6 +511,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     ///   zero, use network-reported minimum fee.
     /// * `opt_tombstone` - Tombstone block. If zero, sets to default.
     /// * `memo_builder` - Memo builder to use.
+    /// * `utxo_selection` - Strategy for choosing which of the inputs to spend.
     pub fn build_transaction(
         &self,
         sender_monitor_id: &MonitorId,
@@ -286,6 +523,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         opt_fee: u64,
         opt_tombstone: u64,
         memo_builder: Box<dyn MemoBuilder + 'static + Send + Sync>,
+        utxo_selection: &dyn UtxoSelectionStrategy,
     ) -> Result<TxProposal, Error> {
         let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{outlays:?}")));
         log::trace!(logger, "Building pending transaction...");
@@ -324,6 +562,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             opt_fee,
             opt_tombstone,
             Some(memo_builder),
+            utxo_selection,
         )
     }
 
@@ -344,6 +583,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     /// * `opt_tombstone` - Tombstone block. If zero, sets to default.
     /// * `opt_memo_builder` - Optional memo builder to use instead of the
     ///   default one (EmptyMemoBuilder).
+    /// * `utxo_selection` - Strategy for choosing which of the inputs to spend.
     pub fn build_mixed_transaction(
         &self,
         sender_monitor_id: &MonitorId,
@@ -356,7 +596,125 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         opt_fee: u64,
         opt_tombstone: u64,
         opt_memo_builder: Option<Box<dyn MemoBuilder + 'static + Send + Sync>>,
+        utxo_selection: &dyn UtxoSelectionStrategy,
     ) -> Result<TxProposal, Error> {
+        let mut rng = rand::thread_rng();
+        let tx_proposal = self
+            .prepare_mixed_transaction(
+                sender_monitor_id,
+                fee_token_id,
+                change_subaddress,
+                inputs,
+                scis,
+                outlays,
+                last_block_infos,
+                opt_fee,
+                opt_tombstone,
+                opt_memo_builder,
+                utxo_selection,
+                true,
+                &mut rng,
+            )?
+            .build(&mut rng)?;
+        log::trace!(
+            self.logger,
+            "Tx constructed, hash={}",
+            tx_proposal.tx.tx_hash()
+        );
+
+        Ok(tx_proposal)
+    }
+
+    /// Create an UnsignedTxProposal, using only one token id for the whole
+    /// transaction. The inputs of the transaction are left for an external
+    /// signer holding the spend private key of the sender to sign, so this
+    /// works for watch-only monitors. Empty memos are written.
+    ///
+    /// # Arguments
+    /// * `sender_monitor_id` - Indicates the account that owns the txo's.
+    /// * `token_id` - The token id to transact in.
+    /// * `change_subaddress` - Recipient of any change.
+    /// * `inputs` - UTXOs that will be spent by the transaction.
+    /// * `outlays` - Output amounts and recipients.
+    /// * `last_block_infos` - Last block info responses from the network, for
+    ///   determining fees. This should normally come from polling_network_state
+    /// * `opt_fee` - Transaction fee value in smallest representable units. If
+    ///   zero, use network-reported minimum fee.
+    /// * `opt_tombstone` - Tombstone block. If zero, sets to default.
+    /// * `utxo_selection` - Strategy for choosing which of the inputs to spend.
+    pub fn build_unsigned_transaction(
+        &self,
+        sender_monitor_id: &MonitorId,
+        token_id: TokenId,
+        change_subaddress: u64,
+        inputs: &[UnspentTxOut],
+        outlays: &[Outlay],
+        last_block_infos: &[BlockInfo],
+        opt_fee: u64,
+        opt_tombstone: u64,
+        utxo_selection: &dyn UtxoSelectionStrategy,
+    ) -> Result<UnsignedTxProposal, Error> {
+        // All inputs must be of the correct token id.
+        if inputs.iter().any(|utxo| utxo.token_id != *token_id) {
+            return Err(Error::InvalidArgument(
+                "inputs".to_string(),
+                format!("All inputs must be of token_id {token_id}"),
+            ));
+        }
+
+        let outlays: Vec<OutlayV2> = outlays
+            .iter()
+            .map(|outlay_v1| OutlayV2 {
+                receiver: outlay_v1.receiver.clone(),
+                amount: Amount::new(outlay_v1.value, token_id),
+                tx_private_key: outlay_v1.tx_private_key,
+            })
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        let unsigned_tx_proposal = self
+            .prepare_mixed_transaction(
+                sender_monitor_id,
+                token_id,
+                change_subaddress,
+                inputs,
+                &[],
+                &outlays,
+                last_block_infos,
+                opt_fee,
+                opt_tombstone,
+                None,
+                utxo_selection,
+                false,
+                &mut rng,
+            )?
+            .build_unsigned()?;
+        log::trace!(self.logger, "Unsigned tx constructed");
+
+        Ok(unsigned_tx_proposal)
+    }
+
+    /// Select the inputs of a transaction that possibly has mixed token ids,
+    /// and add them and its outputs to a transaction builder. See
+    /// [Self::build_mixed_transaction] for the arguments. When `sign` is
+    /// false, the inputs are left unsigned.
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_mixed_transaction(
+        &self,
+        sender_monitor_id: &MonitorId,
+        fee_token_id: TokenId,
+        change_subaddress: u64,
+        inputs: &[UnspentTxOut],
+        scis: &[SciForTx],
+        outlays: &[OutlayV2],
+        last_block_infos: &[BlockInfo],
+        opt_fee: u64,
+        opt_tombstone: u64,
+        opt_memo_builder: Option<Box<dyn MemoBuilder + 'static + Send + Sync>>,
+        utxo_selection: &dyn UtxoSelectionStrategy,
+        sign: bool,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<PreparedTx<FPR>, Error> {
         let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{outlays:?}")));
         log::trace!(logger, "Building pending transaction...");
 
@@ -367,6 +725,11 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // Get sender monitor data.
         let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
+        let input_keys = if sign {
+            InputKeys::Sign(sender_monitor_data.spending_account_key()?)
+        } else {
+            InputKeys::Unsigned(sender_monitor_data.view_private_key()?)
+        };
 
         // Figure out the block version, fee and minimum fee map.
         let (fee, fee_map, block_version) =
@@ -397,6 +760,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
 
         // Select the UTXOs to be used for this transaction.
+        let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
         let mut all_selected_utxos = vec![];
         for (token_id, val) in balance_sheet.iter() {
             if *val > 0 {
@@ -408,10 +772,12 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                     ));
                 }
                 let selected_utxos = Self::select_utxos_for_value(
+                    utxo_selection,
                     *token_id,
                     inputs,
                     *val as u64,
                     remaining_input_slots,
+                    num_blocks_in_ledger,
                 )?;
                 all_selected_utxos.extend(selected_utxos);
             }
@@ -458,33 +824,29 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let tombstone_block = if opt_tombstone > 0 {
             opt_tombstone
         } else {
-            let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
             num_blocks_in_ledger + DEFAULT_NEW_TX_BLOCK_ATTEMPTS
         };
         log::trace!(logger, "Tombstone block set to {}", tombstone_block);
 
-        // Build and return the TxProposal object
-        let mut rng = rand::thread_rng();
-        let tx_proposal = Self::build_tx_proposal(
+        // Add the inputs and outputs to a transaction builder.
+        let change_dest = monitor_change_destination(&sender_monitor_data, change_subaddress)?;
+        Self::prepare_tx(
             &selected_utxos_with_proofs,
             rings,
             &scis_and_amounts,
             block_version,
             fee_token_id,
             fee,
-            &sender_monitor_data.account_key,
-            change_subaddress,
+            input_keys,
+            &change_dest,
             outlays,
             tombstone_block,
             &self.fog_resolver_factory,
             opt_memo_builder,
             fee_map,
-            &mut rng,
+            rng,
             &self.logger,
-        )?;
-        log::trace!(logger, "Tx constructed, hash={}", tx_proposal.tx.tx_hash());
-
-        Ok(tx_proposal)
+        )
     }
 
     /// Create and return an SCI that offers to trade one of our inputs for a
@@ -525,10 +887,10 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Get sender monitor data.
         let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
 
+        let sender_account_key = sender_monitor_data.spending_account_key()?;
+
         // Get the subaddress.
-        let change_subaddress = sender_monitor_data
-            .account_key
-            .subaddress(change_subaddress_index);
+        let change_subaddress = sender_account_key.subaddress(change_subaddress_index);
 
         // Figure out the block version, fee and minimum fee map.
         let (_fee, _fee_map, block_version) =
@@ -570,7 +932,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             global_index,
             ring,
             block_version,
-            &sender_monitor_data.account_key,
+            sender_account_key,
             change_subaddress_index,
             None, // custom change_amount
             &required_outputs,
@@ -598,6 +960,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     ///   determining fees. This should normally come from polling_network_state
     /// * `opt_fee` - Optional fee to use. If zero, we will attempt to query the
     ///   network for fee information.
+    /// * `max_inputs` - Maximum number of UTXOs to merge, at most MAX_INPUTS.
+    /// * `opt_memo_builder` - Optional memo builder to use. If None, empty
+    ///   memos are written.
     pub fn generate_optimization_tx(
         &self,
         monitor_id: &MonitorId,
@@ -605,6 +970,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         token_id: TokenId,
         last_block_infos: &[BlockInfo],
         opt_fee: u64,
+        max_inputs: usize,
+        opt_memo_builder: Option<Box<dyn MemoBuilder + 'static + Send + Sync>>,
     ) -> Result<TxProposal, Error> {
         let logger = self.logger.new(
             o!("monitor_id" => monitor_id.to_string(), "subaddress_index" => subaddress_index),
@@ -613,6 +980,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // Get monitor data.
         let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
+        let account_key = monitor_data.spending_account_key()?;
 
         let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
 
@@ -631,7 +999,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             Self::select_utxos_for_optimization(
                 num_blocks_in_ledger,
                 &inputs,
-                MAX_INPUTS as usize,
+                min(max_inputs, MAX_INPUTS as usize),
                 token_id,
                 fee,
             )?
@@ -685,7 +1053,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // We are paying ourselves the entire amount.
         let outlays = vec![OutlayV2 {
-            receiver: monitor_data.account_key.subaddress(subaddress_index),
+            receiver: account_key.subaddress(subaddress_index),
             amount: Amount::new(total_value - fee, token_id),
             tx_private_key: None,
         }];
@@ -699,12 +1067,12 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             block_version,
             token_id,
             fee,
-            &monitor_data.account_key,
+            account_key,
             subaddress_index,
             &outlays,
             tombstone_block,
             &self.fog_resolver_factory,
-            None,
+            opt_memo_builder,
             fee_map,
             &mut rng,
             &self.logger,
@@ -823,6 +1191,105 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(tx_proposal)
     }
 
+    /// Rebuild a transaction that spends exactly the given inputs and pays the
+    /// given outlays, with a new fee and a fresh tombstone block. Since every
+    /// rebuilt transaction spends the same inputs, at most one of them can
+    /// land.
+    ///
+    /// # Arguments
+    /// * `sender_monitor_id` - Indicates the the account key needed to spend
+    ///   the txo's.
+    /// * `fee_token_id` - The token id the fee is paid in.
+    /// * `change_subaddress` - Recipient of any change.
+    /// * `inputs` - UTXOs that will all be spent by the transaction.
+    /// * `outlays` - Output amounts and recipients.
+    /// * `last_block_infos` - Last block info responses from the network, for
+    ///   determining the block version. This should normally come from
+    ///   polling_network_state
+    /// * `fee` - Transaction fee in smallest representable units.
+    pub fn rebuild_transaction(
+        &self,
+        sender_monitor_id: &MonitorId,
+        fee_token_id: TokenId,
+        change_subaddress: u64,
+        inputs: &[UnspentTxOut],
+        outlays: &[OutlayV2],
+        last_block_infos: &[BlockInfo],
+        fee: u64,
+    ) -> Result<TxProposal, Error> {
+        let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{outlays:?}")));
+        log::trace!(logger, "Rebuilding transaction with fee {}...", fee);
+
+        if inputs.is_empty() {
+            return Err(Error::InvalidArgument(
+                "inputs".to_string(),
+                "Must have at least one input".to_string(),
+            ));
+        }
+
+        // Get sender monitor data.
+        let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
+
+        // Figure out the block version and minimum fee map.
+        let (fee, fee_map, block_version) =
+            self.get_fee_info_and_block_version(last_block_infos, fee_token_id, fee)?;
+
+        // The inputs with corresponding proofs of membership.
+        let inputs_with_proofs: Vec<(UnspentTxOut, TxOutMembershipProof)> = {
+            let tx_outs: Vec<TxOut> = inputs.iter().map(|utxo| utxo.tx_out.clone()).collect();
+            let proofs = self.get_membership_proofs(&tx_outs)?;
+            inputs.iter().cloned().zip(proofs).collect()
+        };
+        log::trace!(logger, "Got membership proofs");
+
+        // The index of each input in the ledger.
+        let input_indices: Vec<u64> = inputs_with_proofs
+            .iter()
+            .map(|(_, membership_proof)| membership_proof.index)
+            .collect();
+
+        let rings = self.get_rings(DEFAULT_RING_SIZE, inputs_with_proofs.len(), &input_indices)?;
+        log::trace!(logger, "Got {} rings", rings.len());
+
+        // Come up with tombstone block.
+        let tombstone_block = self.ledger_db.num_blocks()? + DEFAULT_NEW_TX_BLOCK_ATTEMPTS;
+        log::trace!(logger, "Tombstone block set to {}", tombstone_block);
+
+        // Build and return the TxProposal object
+        let mut rng = rand::thread_rng();
+        let tx_proposal = Self::build_tx_proposal(
+            &inputs_with_proofs,
+            rings,
+            &[],
+            block_version,
+            fee_token_id,
+            fee,
+            sender_monitor_data.spending_account_key()?,
+            change_subaddress,
+            outlays,
+            tombstone_block,
+            &self.fog_resolver_factory,
+            None,
+            fee_map,
+            &mut rng,
+            &self.logger,
+        )?;
+        log::trace!(logger, "Tx rebuilt, hash={}", tx_proposal.tx.tx_hash());
+
+        Ok(tx_proposal)
+    }
+
+    /// Get the minimum fee the network currently requires for a given token.
+    pub fn get_minimum_fee(
+        &self,
+        last_block_infos: &[BlockInfo],
+        token_id: TokenId,
+    ) -> Result<u64, Error> {
+        let (fee, _fee_map, _block_version) =
+            self.get_fee_info_and_block_version(last_block_infos, token_id, 0)?;
+        Ok(fee)
+    }
+
     /// Submit a previously built tx proposal to the network.
     pub fn submit_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<u64, Error> {
         // Pick a peer to submit to.
@@ -863,13 +1330,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(block_height)
     }
 
-    /// Returns a subset of UTXOs totalling at least the given amount.
-    // TODO: This method should take attempted_spend_height into account.
+    /// Returns a subset of UTXOs totalling at least the given amount, chosen by
+    /// the given selection strategy.
     fn select_utxos_for_value(
+        utxo_selection: &dyn UtxoSelectionStrategy,
         token_id: TokenId,
         utxos: &[UnspentTxOut],
         value: u64,
         max_inputs: usize,
+        num_blocks_in_ledger: u64,
     ) -> Result<Vec<UnspentTxOut>, Error> {
         // Sort the utxos in descending order by value.
         let mut sorted_utxos: Vec<UnspentTxOut> = utxos
@@ -880,15 +1349,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
 
         // The maximum spendable is limited by the maximal number of inputs we can use.
-        let max_spendable_amount = sorted_utxos
+        let max_spendable_amount: u128 = sorted_utxos
             .iter()
             .take(max_inputs)
-            .map(|utxo| utxo.value)
+            .map(|utxo| utxo.value as u128)
             .sum();
-        if value > max_spendable_amount {
+        if value as u128 > max_spendable_amount {
             // See if we merged the UTXOs we would be able to spend this amount.
-            let total_utxos_value: u64 = sorted_utxos.iter().map(|utxo| utxo.value).sum();
-            if total_utxos_value >= value {
+            let total_utxos_value: u128 = sorted_utxos.iter().map(|utxo| utxo.value as u128).sum();
+            if total_utxos_value >= value as u128 {
                 return Err(Error::InsufficientFundsFragmentedUtxos);
             } else {
                 return Err(Error::InsufficientFunds);
@@ -896,28 +1365,25 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
 
         // Choose utxos to spend.
-        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
-        loop {
-            let total: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
-            if total >= value {
-                break;
-            }
-
-            // Grab the next (smallest utxo)
-            let next_utxo = sorted_utxos.pop().ok_or(Error::InsufficientFunds)?;
-            selected_utxos.push(next_utxo.clone());
-
-            // Cap at maximum allowed inputs.
-            if selected_utxos.len() > max_inputs {
-                // Remove the lowest utxo.
-                selected_utxos.remove(0);
-            }
+        let selected_utxos =
+            utxo_selection.select(&sorted_utxos, value, max_inputs, num_blocks_in_ledger);
+
+        // The strategy is pluggable, so check that its selection can fund the
+        // transaction.
+        let selected_value: u128 = selected_utxos.iter().map(|utxo| utxo.value as u128).sum();
+        if selected_utxos.is_empty()
+            || selected_utxos.len() > max_inputs
+            || selected_value < value as u128
+        {
+            return Err(Error::UtxoSelection(format!(
+                "selected {} UTXOs totalling {}, to cover {} with at most {} inputs",
+                selected_utxos.len(),
+                selected_value,
+                value,
+                max_inputs
+            )));
         }
 
-        // Sanity.
-        assert!(!selected_utxos.is_empty());
-        assert!(selected_utxos.len() <= max_inputs);
-
         // Return selected utxos.
         Ok(selected_utxos)
     }
@@ -1032,6 +1498,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     ) -> Result<Vec<Vec<(TxOut, TxOutMembershipProof)>>, Error> {
         let num_requested = ring_size * num_rings;
         let num_txos = self.ledger_db.num_txos()?;
+        let excluded_tx_out_indices: BTreeSet<u64> =
+            excluded_tx_out_indices.iter().copied().collect();
 
         // Check that the ledger contains enough tx outs.
         if excluded_tx_out_indices.len() as u64 > num_txos {
@@ -1045,19 +1513,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             return Err(Error::InsufficientTxOuts);
         }
 
-        // Randomly sample `num_requested` indices of TxOuts to use as mixins.
-        let mixin_indices: Vec<u64> = {
-            let mut rng = rand::thread_rng();
-            let mut samples: HashSet<u64> = HashSet::default();
-            while samples.len() < num_requested {
-                let index = rng.gen_range(0..num_txos);
-                if excluded_tx_out_indices.contains(&index) {
-                    continue;
-                }
-                samples.insert(index);
-            }
-            samples.into_iter().collect()
-        };
+        // Sample `num_requested` indices of TxOuts to use as mixins.
+        let mixin_indices: Vec<u64> = self.decoy_distribution.sample_indices(
+            num_requested,
+            &excluded_tx_out_indices,
+            num_txos,
+            self.ledger_db.num_blocks()?,
+            |index| self.ledger_db.get_block_index_by_tx_out_index(index),
+            &mut rand::thread_rng(),
+        )?;
 
         let mixins_result: Result<Vec<TxOut>, _> = mixin_indices
             .iter()
@@ -1121,6 +1585,72 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         rng: &mut (impl RngCore + CryptoRng),
         logger: &Logger,
     ) -> Result<TxProposal, Error> {
+        let change_dest = ReservedSubaddresses::from_subaddress_index(
+            from_account_key,
+            Some(change_subaddress),
+            None,
+        );
+
+        Self::prepare_tx(
+            inputs,
+            rings,
+            scis,
+            block_version,
+            fee_token_id,
+            fee,
+            InputKeys::Sign(from_account_key),
+            &change_dest,
+            destinations,
+            tombstone_block,
+            fog_resolver_factory,
+            opt_memo_builder,
+            fee_map,
+            rng,
+            logger,
+        )?
+        .build(rng)
+    }
+
+    /// Add the inputs and outputs of a transaction to a transaction builder,
+    /// without building it.
+    ///
+    /// # Arguments
+    /// * `inputs` - UTXOs to spend, with membership proofs.
+    /// * `rings` - A set of mixins for each input, with membership proofs.
+    /// * `scis` - A set of scis to add to the Tx. Assumes SCI's already
+    ///   validated and had proofs added. The Sci Amounts from validation also
+    ///   need to be included.
+    /// * `block_version` - The block version to target for this transaction
+    /// * `fee_token_id` - The token id of the fee
+    /// * `fee` - Transaction fee, in picoMOB.
+    /// * `input_keys` - The keys of the account that owns the inputs.
+    /// * `change_dest` - The recipient of any change.
+    /// * `destinations` - Outputs of the transaction.
+    /// * `tombstone_block` - Tombstone block of the transaciton.
+    /// * `fog_pubkey_resolver` - Provides Fog key report, when Fog is enabled.
+    /// * `opt_memo_builder` - Optional memo builder to use instead of the
+    ///   default one (EmptyMemoBuilder).
+    /// * `fee_map` - The current minimum fee map consensus is configured with.
+    /// * `rng` - randomness
+    /// * `logger` - Logger
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_tx(
+        inputs: &[(UnspentTxOut, TxOutMembershipProof)],
+        rings: Vec<Vec<(TxOut, TxOutMembershipProof)>>,
+        scis: &[(SciForTx, SignedContingentInputAmounts)],
+        block_version: BlockVersion,
+        fee_token_id: TokenId,
+        fee: u64,
+        input_keys: InputKeys<'_>,
+        change_dest: &ReservedSubaddresses,
+        destinations: &[OutlayV2],
+        tombstone_block: BlockIndex,
+        fog_resolver_factory: &Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
+        opt_memo_builder: Option<Box<dyn MemoBuilder + 'static + Send + Sync>>,
+        fee_map: FeeMap,
+        rng: &mut (impl RngCore + CryptoRng),
+        logger: &Logger,
+    ) -> Result<PreparedTx<FPR>, Error> {
         // Check that number of rings matches number of inputs.
         if rings.len() != inputs.len() {
             let err = format!(
@@ -1140,8 +1670,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Collect all required FogUris from public addresses, then pass to resolver
         // factory
         let fog_resolver = {
-            let change_address = from_account_key.subaddress(change_subaddress);
-            let fog_uris = core::slice::from_ref(&change_address)
+            let fog_uris = core::slice::from_ref(&change_dest.change_subaddress)
                 .iter()
                 .chain(destinations.iter().map(|x| &x.receiver))
                 .filter_map(|x| extract_fog_uri(x).transpose())
@@ -1218,19 +1747,30 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             );
 
             let public_key = RistrettoPublic::try_from(&utxo.tx_out.public_key)?;
-            let onetime_private_key = recover_onetime_private_key(
-                &public_key,
-                from_account_key.view_private_key(),
-                &from_account_key.subaddress_spend_private(utxo.subaddress_index),
-            );
+            let (onetime_key_derive_data, view_private_key) = match input_keys {
+                InputKeys::Sign(from_account_key) => {
+                    let onetime_private_key = recover_onetime_private_key(
+                        &public_key,
+                        from_account_key.view_private_key(),
+                        &from_account_key.subaddress_spend_private(utxo.subaddress_index),
+                    );
+                    (
+                        OneTimeKeyDeriveData::OneTimeKey(onetime_private_key),
+                        *from_account_key.view_private_key(),
+                    )
+                }
+                InputKeys::Unsigned(view_private_key) => (
+                    OneTimeKeyDeriveData::SubaddressIndex(utxo.subaddress_index),
+                    view_private_key,
+                ),
+            };
 
-            let key_image = KeyImage::from(&onetime_private_key);
             log::debug!(
                 logger,
                 "Adding input: ring {:?}, utxo index {:?}, key image {:?}, pubkey {:?}",
                 ring,
                 real_key_index,
-                key_image,
+                utxo.key_image,
                 public_key
             );
 
@@ -1239,8 +1779,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                     ring,
                     membership_proofs,
                     real_key_index,
-                    onetime_private_key,
-                    *from_account_key.view_private_key(),
+                    onetime_key_derive_data,
+                    view_private_key,
                 )
                 .map_err(|_| Error::TxBuild("failed creating InputCredentials".into()))?,
             );
@@ -1303,12 +1843,6 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Figure out if we have change. Change occurs when the total value of the
         // inputs exceeds the value of the outlays, so we have a negative entry
         // in the balance sheet.
-        let change_dest = ReservedSubaddresses::from_subaddress_index(
-            from_account_key,
-            Some(change_subaddress),
-            None,
-        );
-
         for (token_id, val) in balance_sheet.iter() {
             if *val > 0 {
                 log::error!(
@@ -1334,7 +1868,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 let change_amount = Amount::new(change_val, *token_id);
 
                 tx_builder
-                    .add_change_output(change_amount, &change_dest, rng)
+                    .add_change_output(change_amount, change_dest, rng)
                     .map_err(|err| {
                         Error::TxBuild(format!("failed adding output (change): {err}"))
                     })?;
@@ -1344,47 +1878,16 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Set tombstone block.
         tx_builder.set_tombstone_block(tombstone_block);
 
-        // Build tx.
-        let tx = tx_builder
-            .build(&NoKeysRingSigner {}, rng)
-            .map_err(|err| Error::TxBuild(format!("build tx failed: {err}")))?;
-
-        // Map each TxOut in the constructed transaction to its respective outlay.
-        let outlay_index_to_tx_out_index = tx
-            .prefix
-            .outputs
-            .iter()
-            .enumerate()
-            .filter_map(|(tx_out_index, tx_out)| {
-                tx_out_to_outlay_index
-                    .get(tx_out)
-                    .map(|outlay_index| (*outlay_index, tx_out_index))
-            })
-            .collect::<HashMap<_, _>>();
-
-        // Sanity check: All of our outlays should have a unique index in the map.
-        assert_eq!(outlay_index_to_tx_out_index.len(), destinations.len());
-        let mut found_tx_out_indices = HashSet::default();
-        for i in 0..destinations.len() {
-            let tx_out_index = outlay_index_to_tx_out_index
-                .get(&i)
-                .expect("index not in map");
-            if !found_tx_out_indices.insert(tx_out_index) {
-                panic!("duplicate index {tx_out_index} found in map");
-            }
-        }
-
-        // Return the TxProposal
-        let selected_utxos = inputs
+        let utxos = inputs
             .iter()
             .map(|(utxo, _membership_proof)| utxo.clone())
             .collect();
 
-        Ok(TxProposal {
-            utxos: selected_utxos,
+        Ok(PreparedTx {
+            tx_builder,
+            utxos,
             outlays: destinations.to_vec(),
-            tx,
-            outlay_index_to_tx_out_index,
+            tx_out_to_outlay_index,
             outlay_confirmation_numbers,
             scis: scis
                 .iter()
@@ -1621,6 +2124,7 @@ fn extract_fog_uri(addr: &PublicAddress) -> Result<Option<FogUri>, Error> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::utxo_selection::SmallestFirst;
     use mc_connection::{HardcodedCredentialsProvider, ThickClient};
     use mc_fog_report_validation::MockFogPubkeyResolver;
     use mc_transaction_core::{constants::MILLIMOB_TO_PICOMOB, tokens::Mob, Token};
@@ -1672,7 +2176,9 @@ mod test {
         let selected_utxos = TransactionsManager::<
             ThickClient<HardcodedCredentialsProvider>,
             MockFogPubkeyResolver,
-        >::select_utxos_for_value(Mob::ID, &utxos, 300, utxos.len())
+        >::select_utxos_for_value(
+            &SmallestFirst, Mob::ID, &utxos, 300, utxos.len(), 0
+        )
         .unwrap();
 
         assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[1].clone()]);
@@ -1681,7 +2187,9 @@ mod test {
         let selected_utxos = TransactionsManager::<
             ThickClient<HardcodedCredentialsProvider>,
             MockFogPubkeyResolver,
-        >::select_utxos_for_value(Mob::ID, &utxos, 301, utxos.len())
+        >::select_utxos_for_value(
+            &SmallestFirst, Mob::ID, &utxos, 301, utxos.len(), 0
+        )
         .unwrap();
 
         assert_eq!(
@@ -1693,7 +2201,9 @@ mod test {
         let selected_utxos = TransactionsManager::<
             ThickClient<HardcodedCredentialsProvider>,
             MockFogPubkeyResolver,
-        >::select_utxos_for_value(Mob::ID, &utxos, 301, 2)
+        >::select_utxos_for_value(
+            &SmallestFirst, Mob::ID, &utxos, 301, 2, 0
+        )
         .unwrap();
 
         assert_eq!(selected_utxos, vec![utxos[1].clone(), utxos[2].clone()]);
@@ -1705,7 +2215,7 @@ mod test {
         // While we have enough utxos to sum to 5, if the input limit is 4 we should
         // fail.
         match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
-            Mob::ID, &utxos, 5, 4,
+            &SmallestFirst, Mob::ID, &utxos, 5, 4, 0,
         ) {
             Err(Error::InsufficientFundsFragmentedUtxos) => {
                 // Expected.
@@ -1720,7 +2230,7 @@ mod test {
         // While we have enough utxos to sum to 5, if the input limit is 4 we should
         // fail.
         match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
-            Mob::ID, &utxos, 50, 100,
+            &SmallestFirst, Mob::ID, &utxos, 50, 100, 0,
         ) {
             Err(Error::InsufficientFunds) => {
                 // Expected.
@@ -1729,6 +2239,40 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_select_utxos_for_value_errors_if_strategy_falls_short() {
+        /// Selects the smallest UTXO, whatever the value.
+        struct SmallestOnly;
+
+        impl UtxoSelectionStrategy for SmallestOnly {
+            fn select(
+                &self,
+                utxos: &[UnspentTxOut],
+                _value: u64,
+                _max_inputs: usize,
+                _num_blocks_in_ledger: u64,
+            ) -> Vec<UnspentTxOut> {
+                utxos.last().cloned().into_iter().collect()
+            }
+        }
+
+        let mut utxos = generate_utxos(3);
+        utxos[0].value = 100;
+        utxos[1].value = u64::MAX;
+        utxos[2].value = u64::MAX;
+
+        // The UTXOs total more than u64::MAX, and the selection does not cover the
+        // value.
+        match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
+            &SmallestOnly, Mob::ID, &utxos, 1000, 3, 0,
+        ) {
+            Err(Error::UtxoSelection(_)) => {
+                // Expected.
+            }
+            _ => panic!("Did not get expected error"),
+        };
+    }
+
     #[test]
     fn test_select_utxos_for_optimization_selects_smallest_inputs() {
         // Optimizing with max_inputs=2 should select 100, 2000
@@ -181,6 +181,48 @@ impl ProcessedBlockStore {
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Get the processed blocks of a given monitor id that contain any
+    /// TxOuts, in order, starting at `first_block`. Blocks are never split, so
+    /// the last returned block may take the number of returned TxOuts past
+    /// `max_tx_outs`.
+    pub fn get_processed_blocks(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        first_block: u64,
+        max_tx_outs: usize,
+    ) -> Result<Vec<(u64, Vec<ProcessedTxOut>)>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+
+        let start_key = ProcessedBlockKey::new(monitor_id, first_block);
+        let start_key_bytes = start_key.to_vec();
+
+        let mut blocks: Vec<(u64, Vec<ProcessedTxOut>)> = Vec::new();
+        let mut num_tx_outs = 0;
+        for result in cursor.iter_from(&start_key_bytes) {
+            let (db_key, db_value) = result?;
+            let key = ProcessedBlockKey::try_from(db_key)?;
+            if key.monitor_id != *monitor_id {
+                break;
+            }
+
+            match blocks.last_mut() {
+                Some((block_index, tx_outs)) if *block_index == key.block_index => {
+                    tx_outs.push(mc_util_serial::decode(db_value)?);
+                }
+                _ => {
+                    if num_tx_outs >= max_tx_outs {
+                        break;
+                    }
+                    blocks.push((key.block_index, vec![mc_util_serial::decode(db_value)?]));
+                }
+            }
+            num_tx_outs += 1;
+        }
+
+        Ok(blocks)
+    }
+
     /// Remove the data associated with a given monitor id.
     pub fn remove(
         &self,
@@ -372,7 +414,7 @@ mod test {
         )
         .expect("failed to create data");
 
-        let monitor_id = MonitorId::from(&monitor_data);
+        let monitor_id = MonitorId::try_from(&monitor_data).unwrap();
 
         // Initially, we should have no data for any of our blocks.
         {
@@ -440,6 +482,22 @@ mod test {
                 expected_processed_tx_outs,
                 HashSet::from_iter(processed_tx_outs)
             );
+
+            // Ranges of blocks, which are never split.
+            let block_sizes = |first_block, max_tx_outs| -> Vec<(u64, usize)> {
+                store
+                    .get_processed_blocks(&db_txn, &monitor_id, first_block, max_tx_outs)
+                    .expect("get_processed_blocks failed")
+                    .into_iter()
+                    .map(|(block_index, tx_outs)| (block_index, tx_outs.len()))
+                    .collect()
+            };
+            assert_eq!(block_sizes(0, 100), vec![(0, 3), (1, utxos.len() - 3)]);
+            assert_eq!(block_sizes(0, 2), vec![(0, 3)]);
+            assert_eq!(block_sizes(0, 3), vec![(0, 3)]);
+            assert_eq!(block_sizes(0, 4), vec![(0, 3), (1, utxos.len() - 3)]);
+            assert_eq!(block_sizes(1, 100), vec![(1, utxos.len() - 3)]);
+            assert_eq!(block_sizes(2, 100), vec![]);
         }
 
         // Querying with a different monitor id should return no results.
@@ -453,7 +511,7 @@ mod test {
             )
             .expect("failed to create data");
 
-            let monitor_id = MonitorId::from(&monitor_data);
+            let monitor_id = MonitorId::try_from(&monitor_data).unwrap();
 
             let mut db_txn = env.begin_rw_txn().unwrap();
 
@@ -521,7 +579,7 @@ mod test {
             )
             .expect("failed to create data");
 
-            let monitor_id2 = MonitorId::from(&monitor_data2);
+            let monitor_id2 = MonitorId::try_from(&monitor_data2).unwrap();
 
             store
                 .block_processed(&mut db_txn, &monitor_id2, 0, &utxos[0..1], &utxos[1..2])
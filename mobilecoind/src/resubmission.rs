@@ -0,0 +1,522 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Resubmission of slow transactions with a higher fee.
+//!
+//! Transactions submitted with a resubmission policy (see
+//! `resubmission_store`) are watched by a background thread. If the latest
+//! attempt did not land within `resubmit_after_blocks` blocks, or its
+//! tombstone block was exceeded, the transaction is rebuilt with the same
+//! inputs and outlays, a bumped fee and a new tombstone block, and submitted
+//! again. Since every attempt spends the same inputs, at most one of them can
+//! land.
+
+use crate::{
+    database::Database,
+    error::Error,
+    payments::{OutlayV2, TransactionsManager},
+    resubmission_store::{ResubmittedTx, TxAttempt},
+    utxo_store::UtxoId,
+    webhooks::get_final_tx_status,
+};
+use mc_common::logger::{log, Logger};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_sync::{NetworkState, PollingNetworkState};
+use mc_mobilecoind_api::TxStatus;
+use mc_transaction_core::{Amount, TokenId};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Resubmission Thread - holds objects needed to cleanly terminate the
+/// resubmission thread.
+pub struct ResubmissionThread {
+    /// The main thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ResubmissionThread {
+    pub fn start<
+        T: BlockchainConnection + UserTxConnection + 'static,
+        FPR: FogPubkeyResolver + 'static,
+    >(
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        transactions_manager: TransactionsManager<T, FPR>,
+        network_state: Arc<RwLock<PollingNetworkState<T>>>,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = thread::Builder::new()
+            .name("resubmission".to_string())
+            .spawn(move || {
+                resubmission_thread_entry_point(
+                    ledger_db,
+                    mobilecoind_db,
+                    transactions_manager,
+                    network_state,
+                    poll_interval,
+                    thread_stop_requested,
+                    logger,
+                );
+            })
+            .expect("failed starting resubmission thread");
+
+        Self {
+            join_handle: Some(join_handle),
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("ResubmissionThread join failed");
+        }
+    }
+}
+
+impl Drop for ResubmissionThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn resubmission_thread_entry_point<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    transactions_manager: TransactionsManager<T, FPR>,
+    network_state: Arc<RwLock<PollingNetworkState<T>>>,
+    poll_interval: Duration,
+    stop_requested: Arc<AtomicBool>,
+    logger: Logger,
+) {
+    log::info!(logger, "Resubmission thread started");
+
+    loop {
+        if stop_requested.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // Monitor data cannot be accessed until the database is unlocked.
+        if mobilecoind_db.is_unlocked() {
+            match mobilecoind_db.get_unfinished_resubmitted_txs() {
+                Ok(resubmitted_txs) => {
+                    for resubmitted_tx in resubmitted_txs {
+                        if let Err(err) = resubmit_tx(
+                            &ledger_db,
+                            &mobilecoind_db,
+                            &transactions_manager,
+                            &network_state,
+                            resubmitted_tx,
+                            &logger,
+                        ) {
+                            log::error!(logger, "Error resubmitting transaction: {:?}", err);
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::error!(logger, "Error getting resubmitted transactions: {:?}", err);
+                }
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+/// Check on a single resubmitted transaction, and make another attempt if its
+/// policy calls for one.
+fn resubmit_tx<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    transactions_manager: &TransactionsManager<T, FPR>,
+    network_state: &Arc<RwLock<PollingNetworkState<T>>>,
+    resubmitted_tx: ResubmittedTx,
+    logger: &Logger,
+) -> Result<(), Error> {
+    let key_image = *resubmitted_tx.key_image()?;
+    let latest_attempt = resubmitted_tx
+        .attempts
+        .last()
+        .ok_or_else(|| Error::InvalidArgument("attempts".to_owned(), "empty".to_owned()))?;
+    let policy = &resubmitted_tx.policy;
+
+    // Any attempt may land, not only the latest one.
+    for attempt in &resubmitted_tx.attempts {
+        let status = get_final_tx_status(ledger_db, &resubmitted_tx.watched_tx(attempt))?;
+        if status == Some(TxStatus::Verified) {
+            log::info!(
+                logger,
+                "Transaction spending {} landed, paying a fee of {}",
+                key_image,
+                attempt.fee
+            );
+            mobilecoind_db.update_resubmitted_tx(&key_image, |tx| tx.finished = true)?;
+            return Ok(());
+        }
+    }
+
+    let expired = match get_final_tx_status(ledger_db, &resubmitted_tx.watched_tx(latest_attempt))?
+    {
+        None => false,
+        Some(TxStatus::TombstoneBlockExceeded) => true,
+        Some(status) => {
+            // The inputs were spent by some other transaction.
+            log::warn!(
+                logger,
+                "Transaction spending {} failed: {:?}",
+                key_image,
+                status
+            );
+            mobilecoind_db.update_resubmitted_tx(&key_image, |tx| {
+                tx.finished = true;
+                tx.last_error = format!("Transaction failed: {status:?}");
+            })?;
+            return Ok(());
+        }
+    };
+
+    if resubmitted_tx.attempts.len() >= policy.max_attempts as usize {
+        if expired {
+            log::warn!(
+                logger,
+                "Giving up on transaction spending {} after {} attempts",
+                key_image,
+                resubmitted_tx.attempts.len()
+            );
+            mobilecoind_db.update_resubmitted_tx(&key_image, |tx| {
+                tx.finished = true;
+                tx.last_error = "Tombstone block exceeded, no attempts left".to_owned();
+            })?;
+        }
+        return Ok(());
+    }
+
+    if !expired
+        && ledger_db.num_blocks()? < latest_attempt.submitted_block + policy.resubmit_after_blocks
+    {
+        return Ok(());
+    }
+
+    // Bump the fee, without spending more than the inputs can cover.
+    let fee_token_id = TokenId::from(resubmitted_tx.fee_token_id);
+    let last_block_infos = network_state
+        .read()
+        .expect("lock poisoned")
+        .peer_to_block_info()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let minimum_fee = transactions_manager.get_minimum_fee(&last_block_infos, fee_token_id)?;
+    let available: u64 = resubmitted_tx
+        .utxos
+        .iter()
+        .filter(|utxo| utxo.token_id == *fee_token_id)
+        .map(|utxo| utxo.value)
+        .sum::<u64>()
+        .saturating_sub(
+            resubmitted_tx
+                .outlays
+                .iter()
+                .filter(|outlay| outlay.token_id == *fee_token_id)
+                .map(|outlay| outlay.value)
+                .sum(),
+        );
+    let fee = policy
+        .next_fee(latest_attempt.fee, minimum_fee)
+        .min(available);
+
+    // A pending attempt is only replaced by one that pays more.
+    if !expired && fee <= latest_attempt.fee {
+        return Ok(());
+    }
+
+    let outlays = resubmitted_tx
+        .outlays
+        .iter()
+        .map(|outlay| OutlayV2 {
+            amount: Amount::new(outlay.value, TokenId::from(outlay.token_id)),
+            receiver: outlay.receiver.clone(),
+            tx_private_key: outlay.tx_private_key,
+        })
+        .collect::<Vec<_>>();
+
+    let result = transactions_manager
+        .rebuild_transaction(
+            &resubmitted_tx.sender_monitor_id,
+            fee_token_id,
+            resubmitted_tx.change_subaddress,
+            &resubmitted_tx.utxos,
+            &outlays,
+            &last_block_infos,
+            fee,
+        )
+        .and_then(|tx_proposal| {
+            let block_height = transactions_manager.submit_tx_proposal(&tx_proposal)?;
+            Ok((tx_proposal, block_height))
+        });
+    let (tx_proposal, block_height) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            mobilecoind_db.update_resubmitted_tx(&key_image, |tx| {
+                tx.last_error = err.to_string();
+            })?;
+            return Err(err);
+        }
+    };
+    log::info!(
+        logger,
+        "Resubmitted transaction spending {} with a fee of {} (attempt {})",
+        key_image,
+        fee,
+        resubmitted_tx.attempts.len() + 1
+    );
+
+    // The transaction was sent to the network, so it has to be accounted for even
+    // if updating the UTXOs fails.
+    let attempt = TxAttempt::new(&tx_proposal, block_height)?;
+    mobilecoind_db.update_resubmitted_tx(&key_image, |tx| {
+        tx.attempts.push(attempt);
+        tx.last_error.clear();
+    })?;
+
+    let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+    mobilecoind_db.update_attempted_spend(
+        &utxo_ids,
+        block_height,
+        tx_proposal.tx.prefix.tombstone_block,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        monitor_store::MonitorData,
+        resubmission_store::{ResubmissionPolicy, ResubmittedOutlay},
+        test_utils::{
+            add_block_to_ledger, get_testing_environment, wait_for_monitors, BlockVersion,
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+        },
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::test_with_logger;
+    use mc_connection::{Connection, ConnectionManager};
+    use mc_connection_test_utils::MockBlockchainConnection;
+    use mc_consensus_scp::QuorumSet;
+    use mc_fog_report_validation_test_utils::MockFogResolver;
+    use mc_ledger_db::test_utils::add_txos_and_key_images_to_ledger;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, tx::Tx, Token};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    /// Get the transactions that were submitted to our mock peers.
+    fn proposed_txs(
+        conn_manager: &ConnectionManager<MockBlockchainConnection<LedgerDB>>,
+    ) -> Vec<Tx> {
+        conn_manager
+            .conns()
+            .iter()
+            .flat_map(|conn| conn.read().proposed_txs.clone())
+            .collect()
+    }
+
+    #[test_with_logger]
+    fn test_resubmit_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([47u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (mut ledger_db, mobilecoind_db, _client, _server, conn_manager) =
+            get_testing_environment(
+                BlockVersion::MAX,
+                10,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let node_ids = conn_manager
+            .conns()
+            .iter()
+            .map(|conn| conn.uri().host_and_port_responder_id().unwrap())
+            .collect();
+        let network_state = Arc::new(RwLock::new(PollingNetworkState::new(
+            QuorumSet::new_with_node_ids(2, node_ids),
+            conn_manager.clone(),
+            logger.clone(),
+        )));
+        network_state.write().unwrap().poll();
+        let last_block_infos = network_state
+            .read()
+            .unwrap()
+            .peer_to_block_info()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            conn_manager.clone(),
+            Arc::new(|_| Ok(MockFogResolver::default())),
+            Default::default(),
+            logger.clone(),
+        );
+
+        // Submit the original attempt, paying the minimum fee.
+        let mut utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        utxos.truncate(2);
+        let outlay = ResubmittedOutlay {
+            value: DEFAULT_PER_RECIPIENT_AMOUNT,
+            token_id: *Mob::ID,
+            receiver: receiver.clone(),
+            tx_private_key: None,
+        };
+        let outlays = vec![OutlayV2 {
+            amount: Amount::new(outlay.value, Mob::ID),
+            receiver: receiver.clone(),
+            tx_private_key: None,
+        }];
+        let tx_proposal = transactions_manager
+            .rebuild_transaction(
+                &monitor_id,
+                Mob::ID,
+                0,
+                &utxos,
+                &outlays,
+                &last_block_infos,
+                Mob::MINIMUM_FEE,
+            )
+            .unwrap();
+        let block_height = transactions_manager
+            .submit_tx_proposal(&tx_proposal)
+            .unwrap();
+
+        let policy = ResubmissionPolicy {
+            resubmit_after_blocks: 2,
+            fee_bump_percent: 50,
+            max_fee: 2 * Mob::MINIMUM_FEE,
+            max_attempts: 3,
+        };
+        mobilecoind_db
+            .add_resubmitted_tx(&ResubmittedTx {
+                policy,
+                sender_monitor_id: monitor_id,
+                change_subaddress: 0,
+                fee_token_id: *Mob::ID,
+                utxos: utxos.clone(),
+                outlays: vec![outlay],
+                attempts: vec![TxAttempt::new(&tx_proposal, block_height).unwrap()],
+                finished: false,
+                last_error: String::new(),
+            })
+            .unwrap();
+        let key_image = utxos[0].key_image;
+
+        let resubmit = |ledger_db: &LedgerDB| {
+            let resubmitted_tx = mobilecoind_db.get_resubmitted_tx(&key_image).unwrap();
+            resubmit_tx(
+                ledger_db,
+                &mobilecoind_db,
+                &transactions_manager,
+                &network_state,
+                resubmitted_tx,
+                &logger,
+            )
+            .unwrap();
+            mobilecoind_db.get_resubmitted_tx(&key_image).unwrap()
+        };
+
+        // Nothing happens before `resubmit_after_blocks` blocks went by.
+        let resubmitted_tx = resubmit(&ledger_db);
+        assert_eq!(resubmitted_tx.attempts.len(), 1);
+        assert_eq!(proposed_txs(&conn_manager).len(), 1);
+
+        for _ in 0..2 {
+            add_block_to_ledger(
+                &mut ledger_db,
+                BlockVersion::MAX,
+                &[receiver.clone()],
+                Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            )
+            .unwrap();
+        }
+
+        // The transaction is rebuilt with the same inputs and a bumped fee.
+        let resubmitted_tx = resubmit(&ledger_db);
+        assert!(!resubmitted_tx.finished);
+        assert_eq!(resubmitted_tx.last_error, "");
+        assert_eq!(resubmitted_tx.attempts.len(), 2);
+        assert_eq!(resubmitted_tx.attempts[1].fee, Mob::MINIMUM_FEE * 3 / 2);
+
+        let txs = proposed_txs(&conn_manager);
+        assert_eq!(txs.len(), 2);
+        let tx = txs
+            .iter()
+            .find(|tx| tx.prefix.fee == Mob::MINIMUM_FEE * 3 / 2)
+            .unwrap()
+            .clone();
+        let mut expected_key_images = tx_proposal.tx.key_images();
+        let mut key_images = tx.key_images();
+        expected_key_images.sort();
+        key_images.sort();
+        assert_eq!(key_images, expected_key_images);
+        assert!(tx.prefix.tombstone_block > tx_proposal.tx.prefix.tombstone_block);
+
+        // Once the second attempt lands, no more attempts are made.
+        add_txos_and_key_images_to_ledger(
+            &mut ledger_db,
+            BlockVersion::MAX,
+            tx.prefix.outputs.clone(),
+            tx.key_images(),
+            &mut rng,
+        )
+        .unwrap();
+
+        let resubmitted_tx = resubmit(&ledger_db);
+        assert!(resubmitted_tx.finished);
+        assert_eq!(resubmitted_tx.attempts.len(), 2);
+        assert_eq!(proposed_txs(&conn_manager).len(), 2);
+        assert!(mobilecoind_db
+            .get_unfinished_resubmitted_txs()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,450 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for transactions that get resubmitted with a higher fee
+//! when they are slow to land.
+//! * Stores a map of key image -> ResubmittedTx. The key is the key image of
+//!   the first input of the transaction, which all attempts share since they
+//!   spend the same inputs.
+
+use crate::{
+    error::Error, monitor_store::MonitorId, payments::TxProposal, utxo_store::UnspentTxOut,
+    webhook_store::WatchedTx,
+};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_account_keys::PublicAddress;
+use mc_common::logger::Logger;
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate};
+use mc_transaction_core::ring_signature::KeyImage;
+use prost::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const KEY_IMAGE_TO_RESUBMITTED_TX_DB_NAME: &str =
+    "mobilecoind_db:resubmission_store:key_image_to_resubmitted_tx";
+
+/// Configuration of fee bumping for a submitted transaction.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ResubmissionPolicy {
+    /// Rebuild the transaction if it did not land within this many blocks of
+    /// being submitted.
+    #[prost(uint64, tag = "1")]
+    pub resubmit_after_blocks: u64,
+
+    /// Percentage the fee is increased by on each attempt.
+    #[prost(uint32, tag = "2")]
+    pub fee_bump_percent: u32,
+
+    /// The fee is never increased past this value.
+    #[prost(uint64, tag = "3")]
+    pub max_fee: u64,
+
+    /// Maximum number of attempts, including the original submission.
+    #[prost(uint32, tag = "4")]
+    pub max_attempts: u32,
+}
+
+impl ResubmissionPolicy {
+    /// Check that the policy is usable.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.resubmit_after_blocks == 0 {
+            return Err(Error::InvalidArgument(
+                "resubmit_after_blocks".to_owned(),
+                "must be greater than 0".to_owned(),
+            ));
+        }
+        if self.max_attempts < 2 {
+            return Err(Error::InvalidArgument(
+                "max_attempts".to_owned(),
+                "must be at least 2".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The fee of the attempt following one that paid `fee`, given the
+    /// current network minimum fee.
+    pub fn next_fee(&self, fee: u64, minimum_fee: u64) -> u64 {
+        let bumped = fee.saturating_add(fee.saturating_mul(self.fee_bump_percent as u64) / 100);
+        bumped.max(minimum_fee).min(self.max_fee.max(fee))
+    }
+}
+
+/// An outlay of a resubmitted transaction.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ResubmittedOutlay {
+    /// Value being sent.
+    #[prost(uint64, tag = "1")]
+    pub value: u64,
+
+    /// Token id being sent.
+    #[prost(uint64, tag = "2")]
+    pub token_id: u64,
+
+    /// Destination.
+    #[prost(message, required, tag = "3")]
+    pub receiver: PublicAddress,
+
+    /// The tx private key the outlay was built with, if one was chosen
+    /// explicitly. Reusing it keeps the public key of the output the same
+    /// across attempts.
+    #[prost(message, optional, tag = "4")]
+    pub tx_private_key: Option<RistrettoPrivate>,
+}
+
+/// What a receiver needs to know about their output of an attempt.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct AttemptReceipt {
+    /// The public key of the output.
+    #[prost(message, required, tag = "1")]
+    pub tx_public_key: CompressedRistrettoPublic,
+
+    /// The hash of the output.
+    #[prost(bytes, tag = "2")]
+    pub tx_out_hash: Vec<u8>,
+
+    /// The confirmation number of the output.
+    #[prost(bytes, tag = "3")]
+    pub confirmation_number: Vec<u8>,
+}
+
+/// A single submission of a resubmitted transaction.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct TxAttempt {
+    /// The fee paid by this attempt.
+    #[prost(uint64, tag = "1")]
+    pub fee: u64,
+
+    /// Tombstone block of this attempt.
+    #[prost(uint64, tag = "2")]
+    pub tombstone: u64,
+
+    /// The block height the network reported when this attempt was submitted.
+    #[prost(uint64, tag = "3")]
+    pub submitted_block: u64,
+
+    /// Public keys of all outputs of this attempt, including change.
+    #[prost(message, repeated, tag = "4")]
+    pub tx_public_keys: Vec<CompressedRistrettoPublic>,
+
+    /// Receipts for the outputs of this attempt, in the same order as the
+    /// outlays.
+    #[prost(message, repeated, tag = "5")]
+    pub receipts: Vec<AttemptReceipt>,
+}
+
+impl TxAttempt {
+    pub fn new(tx_proposal: &TxProposal, submitted_block: u64) -> Result<Self, Error> {
+        let receipts = (0..tx_proposal.outlays.len())
+            .map(|outlay_index| {
+                let tx_out = tx_proposal
+                    .outlay_index_to_tx_out_index
+                    .get(&outlay_index)
+                    .and_then(|tx_out_index| tx_proposal.tx.prefix.outputs.get(*tx_out_index))
+                    .ok_or_else(|| {
+                        Error::InvalidArgument(
+                            "outlay_index_to_tx_out_index".to_owned(),
+                            format!("no output for outlay {outlay_index}"),
+                        )
+                    })?;
+                Ok(AttemptReceipt {
+                    tx_public_key: tx_out.public_key,
+                    tx_out_hash: tx_out.hash().to_vec(),
+                    confirmation_number: tx_proposal
+                        .outlay_confirmation_numbers
+                        .get(outlay_index)
+                        .map(|confirmation_number| confirmation_number.to_vec())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            fee: tx_proposal.fee(),
+            tombstone: tx_proposal.tx.prefix.tombstone_block,
+            submitted_block,
+            tx_public_keys: tx_proposal
+                .tx
+                .prefix
+                .outputs
+                .iter()
+                .map(|tx_out| tx_out.public_key)
+                .collect(),
+            receipts,
+        })
+    }
+}
+
+/// Type used as the stored data in the key_image_to_resubmitted_tx database.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ResubmittedTx {
+    /// The configured policy.
+    #[prost(message, required, tag = "1")]
+    pub policy: ResubmissionPolicy,
+
+    /// The monitor that owns the inputs.
+    #[prost(message, required, tag = "2")]
+    pub sender_monitor_id: MonitorId,
+
+    /// The subaddress change is sent to.
+    #[prost(uint64, tag = "3")]
+    pub change_subaddress: u64,
+
+    /// The token the fee is paid in.
+    #[prost(uint64, tag = "4")]
+    pub fee_token_id: u64,
+
+    /// The inputs spent by every attempt.
+    #[prost(message, repeated, tag = "5")]
+    pub utxos: Vec<UnspentTxOut>,
+
+    /// The outlays paid by every attempt.
+    #[prost(message, repeated, tag = "6")]
+    pub outlays: Vec<ResubmittedOutlay>,
+
+    /// Every attempt made so far, oldest first.
+    #[prost(message, repeated, tag = "7")]
+    pub attempts: Vec<TxAttempt>,
+
+    /// Whether the transaction reached a final state, so no more attempts
+    /// are going to be made.
+    #[prost(bool, tag = "8")]
+    pub finished: bool,
+
+    /// The most recent error encountered while resubmitting, empty if the
+    /// last attempt succeeded.
+    #[prost(string, tag = "9")]
+    pub last_error: String,
+}
+
+impl ResubmittedTx {
+    /// The key the transaction is stored under.
+    pub fn key_image(&self) -> Result<&KeyImage, Error> {
+        self.utxos
+            .first()
+            .map(|utxo| &utxo.key_image)
+            .ok_or_else(|| Error::InvalidArgument("utxos".to_owned(), "empty".to_owned()))
+    }
+
+    /// The transaction spent by a given attempt, for status lookups.
+    pub fn watched_tx(&self, attempt: &TxAttempt) -> WatchedTx {
+        WatchedTx {
+            key_images: self.utxos.iter().map(|utxo| utxo.key_image).collect(),
+            tx_public_keys: attempt.tx_public_keys.clone(),
+            tombstone: attempt.tombstone,
+        }
+    }
+}
+
+/// Wrapper for the key_image_to_resubmitted_tx database
+#[derive(Clone)]
+pub struct ResubmissionStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Mapping of key image -> ResubmittedTx
+    key_image_to_resubmitted_tx: Database,
+}
+
+impl ResubmissionStore {
+    pub fn new(env: Arc<Environment>, _logger: Logger) -> Result<Self, Error> {
+        let key_image_to_resubmitted_tx = env.create_db(
+            Some(KEY_IMAGE_TO_RESUBMITTED_TX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            _env: env,
+            key_image_to_resubmitted_tx,
+        })
+    }
+
+    /// Store a transaction, replacing any previous data stored for it.
+    pub fn set(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        resubmitted_tx: &ResubmittedTx,
+    ) -> Result<(), Error> {
+        db_txn.put(
+            self.key_image_to_resubmitted_tx,
+            resubmitted_tx.key_image()?,
+            &mc_util_serial::encode(resubmitted_tx),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Get the transaction whose first input has the given key image.
+    pub fn get(
+        &self,
+        db_txn: &impl Transaction,
+        key_image: &KeyImage,
+    ) -> Result<ResubmittedTx, Error> {
+        match db_txn.get(self.key_image_to_resubmitted_tx, key_image) {
+            Ok(value_bytes) => Ok(mc_util_serial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::ResubmittedTxNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get all transactions that did not reach a final state yet.
+    pub fn get_unfinished(&self, db_txn: &impl Transaction) -> Result<Vec<ResubmittedTx>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.key_image_to_resubmitted_tx)?;
+
+        let mut results = Vec::new();
+        for result in cursor.iter_start() {
+            let (_key, value) = result?;
+            let resubmitted_tx: ResubmittedTx = mc_util_serial::decode(value)?;
+            if !resubmitted_tx.finished {
+                results.push(resubmitted_tx);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Remove all transactions sent by a given monitor.
+    pub fn remove_for_monitor(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        let keys = {
+            let mut cursor = db_txn.open_ro_cursor(self.key_image_to_resubmitted_tx)?;
+            let mut keys = Vec::new();
+            for result in cursor.iter_start() {
+                let (key, value) = result?;
+                let resubmitted_tx: ResubmittedTx = mc_util_serial::decode(value)?;
+                if resubmitted_tx.sender_monitor_id == *monitor_id {
+                    keys.push(key.to_vec());
+                }
+            }
+            keys
+        };
+
+        for key in keys {
+            db_txn.del(self.key_image_to_resubmitted_tx, &key, None)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use mc_common::logger::test_with_logger;
+    use mc_transaction_core::{tokens::Mob, Token};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_next_fee() {
+        let policy = ResubmissionPolicy {
+            resubmit_after_blocks: 5,
+            fee_bump_percent: 50,
+            max_fee: 1000,
+            max_attempts: 3,
+        };
+        assert!(policy.validate().is_ok());
+
+        assert_eq!(policy.next_fee(400, 100), 600);
+        // Never below the network minimum fee.
+        assert_eq!(policy.next_fee(400, 700), 700);
+        // Never above the maximum fee.
+        assert_eq!(policy.next_fee(800, 100), 1000);
+        // A fee that already exceeds the maximum is kept.
+        assert_eq!(policy.next_fee(2000, 100), 2000);
+
+        assert_matches!(
+            ResubmissionPolicy {
+                resubmit_after_blocks: 0,
+                ..policy.clone()
+            }
+            .validate(),
+            Err(Error::InvalidArgument(..))
+        );
+        assert_matches!(
+            ResubmissionPolicy {
+                max_attempts: 1,
+                ..policy
+            }
+            .validate(),
+            Err(Error::InvalidArgument(..))
+        );
+    }
+
+    #[test_with_logger]
+    fn test_resubmission_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let db_tmp = TempDir::new().expect("Could not make tempdir for resubmission store db");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_tmp.path())
+                .unwrap(),
+        );
+        let store = ResubmissionStore::new(env.clone(), logger).unwrap();
+
+        let (monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let resubmitted_tx = |monitor_id: MonitorId, key_image: u64| ResubmittedTx {
+            policy: ResubmissionPolicy {
+                resubmit_after_blocks: 5,
+                fee_bump_percent: 50,
+                max_fee: 1000,
+                max_attempts: 3,
+            },
+            sender_monitor_id: monitor_id,
+            change_subaddress: 0,
+            fee_token_id: *Mob::ID,
+            utxos: vec![UnspentTxOut {
+                key_image: KeyImage::from(key_image),
+                ..Default::default()
+            }],
+            outlays: vec![ResubmittedOutlay {
+                value: 10,
+                token_id: *Mob::ID,
+                receiver: monitor_data0.default_subaddress().unwrap(),
+                tx_private_key: None,
+            }],
+            attempts: vec![],
+            finished: false,
+            last_error: String::new(),
+        };
+
+        let tx0 = resubmitted_tx(monitor_id0, 1);
+        let tx1 = resubmitted_tx(monitor_id1, 2);
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_matches!(
+            store.get(&db_txn, &KeyImage::from(1)),
+            Err(Error::ResubmittedTxNotFound)
+        );
+        store.set(&mut db_txn, &tx0).unwrap();
+        store.set(&mut db_txn, &tx1).unwrap();
+        assert_eq!(store.get(&db_txn, &KeyImage::from(1)).unwrap(), tx0);
+        assert_eq!(store.get_unfinished(&db_txn).unwrap().len(), 2);
+
+        // Finished transactions are kept, but no longer returned as unfinished.
+        let finished_tx0 = ResubmittedTx {
+            finished: true,
+            ..tx0
+        };
+        store.set(&mut db_txn, &finished_tx0).unwrap();
+        assert_eq!(store.get_unfinished(&db_txn).unwrap(), vec![tx1.clone()]);
+        assert_eq!(
+            store.get(&db_txn, &KeyImage::from(1)).unwrap(),
+            finished_tx0
+        );
+
+        store.remove_for_monitor(&mut db_txn, &monitor_id1).unwrap();
+        assert!(store.get_unfinished(&db_txn).unwrap().is_empty());
+        assert_matches!(
+            store.get(&db_txn, &KeyImage::from(2)),
+            Err(Error::ResubmittedTxNotFound)
+        );
+    }
+}
@@ -0,0 +1,620 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for scheduled and recurring payments.
+//! * Stores a map of scheduled payment id -> ScheduledPaymentData.
+//! * Values are encrypted with the database password, since they hold the
+//!   receivers and amounts of standing orders.
+//! * Cancelled and completed payments are kept, along with the results of their
+//!   latest executions, until the sending monitor is removed.
+
+use crate::{
+    db_crypto::DbCryptoProvider, error::Error, monitor_store::MonitorId,
+    resubmission_store::AttemptReceipt, webhook_store::WatchedTx,
+};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_account_keys::PublicAddress;
+use mc_common::logger::{log, Logger};
+use prost::{Enumeration, Message};
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const SCHEDULED_PAYMENT_ID_TO_SCHEDULED_PAYMENT_DATA_DB_NAME: &str =
+    "mobilecoind_db:scheduled_payment_store:scheduled_payment_id_to_scheduled_payment_data";
+
+/// Number of executions kept for each scheduled payment, older ones are pruned.
+pub const MAX_STORED_EXECUTIONS: usize = 100;
+
+/// Outcome of a single execution of a scheduled payment.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Enumeration)]
+pub enum ScheduledPaymentExecutionStatus {
+    /// The transaction was submitted and did not reach a final state yet.
+    Pending = 0,
+
+    /// The transaction landed in the ledger.
+    Verified = 1,
+
+    /// The transaction could not be built or submitted, or did not land.
+    Failed = 2,
+}
+
+/// A single execution of a scheduled payment.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ScheduledPaymentExecution {
+    /// Number of blocks in the local ledger when the payment was executed.
+    #[prost(uint64, tag = "1")]
+    pub block_count: u64,
+
+    /// Unix timestamp (in seconds) of when the payment was executed.
+    #[prost(uint64, tag = "2")]
+    pub timestamp: u64,
+
+    /// The fee paid, 0 if no transaction was submitted.
+    #[prost(uint64, tag = "3")]
+    pub fee: u64,
+
+    /// The submitted transaction, if any.
+    #[prost(message, optional, tag = "4")]
+    pub tx: Option<WatchedTx>,
+
+    /// Receipt for the output paid to the receiver, if a transaction was
+    /// submitted.
+    #[prost(message, optional, tag = "5")]
+    pub receipt: Option<AttemptReceipt>,
+
+    /// Outcome of the execution.
+    #[prost(enumeration = "ScheduledPaymentExecutionStatus", tag = "6")]
+    pub status: i32,
+
+    /// Why the execution failed, empty otherwise.
+    #[prost(string, tag = "7")]
+    pub error: String,
+}
+
+/// Type used as the stored data in the
+/// scheduled_payment_id_to_scheduled_payment_data database.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ScheduledPaymentData {
+    /// The monitor paying.
+    #[prost(message, required, tag = "1")]
+    pub sender_monitor_id: MonitorId,
+
+    /// The subaddress the funds are coming from, change is returned to it.
+    #[prost(uint64, tag = "2")]
+    pub sender_subaddress: u64,
+
+    /// The address being paid.
+    #[prost(message, required, tag = "3")]
+    pub receiver: PublicAddress,
+
+    /// Value paid by each execution.
+    #[prost(uint64, tag = "4")]
+    pub value: u64,
+
+    /// Token id being paid.
+    #[prost(uint64, tag = "5")]
+    pub token_id: u64,
+
+    /// Number of blocks between executions, 0 for wall-clock schedules.
+    #[prost(uint64, tag = "6")]
+    pub interval_blocks: u64,
+
+    /// Number of seconds between executions, 0 for block schedules.
+    #[prost(uint64, tag = "7")]
+    pub interval_seconds: u64,
+
+    /// Block schedules execute once the ledger contains this block index.
+    #[prost(uint64, tag = "8")]
+    pub next_block: u64,
+
+    /// Wall-clock schedules execute once this unix timestamp (in seconds) is
+    /// reached.
+    #[prost(uint64, tag = "9")]
+    pub next_timestamp: u64,
+
+    /// Number of executions after which the payment completes, 0 to repeat
+    /// until cancelled.
+    #[prost(uint64, tag = "10")]
+    pub max_executions: u64,
+
+    /// Whether the payment was cancelled.
+    #[prost(bool, tag = "11")]
+    pub cancelled: bool,
+
+    /// The latest executions, oldest first.
+    #[prost(message, repeated, tag = "12")]
+    pub executions: Vec<ScheduledPaymentExecution>,
+
+    /// Number of executions that did not fail and were pruned from
+    /// `executions`.
+    #[prost(uint64, tag = "13")]
+    pub num_pruned_executions: u64,
+}
+
+impl ScheduledPaymentData {
+    /// Check that the schedule is usable.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.value == 0 {
+            return Err(Error::InvalidArgument(
+                "value".to_owned(),
+                "must be greater than 0".to_owned(),
+            ));
+        }
+        if (self.interval_blocks == 0) == (self.interval_seconds == 0) {
+            return Err(Error::InvalidArgument(
+                "interval".to_owned(),
+                "exactly one of interval_blocks and interval_seconds must be set".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of executions that did not fail, which are the ones counted
+    /// against `max_executions`.
+    pub fn num_executions(&self) -> u64 {
+        let num_stored = self
+            .executions
+            .iter()
+            .filter(|execution| execution.status != ScheduledPaymentExecutionStatus::Failed as i32)
+            .count() as u64;
+        self.num_pruned_executions + num_stored
+    }
+
+    /// Whether more executions are going to happen.
+    pub fn is_active(&self) -> bool {
+        !self.cancelled && (self.max_executions == 0 || self.num_executions() < self.max_executions)
+    }
+
+    /// Whether the next execution is due, given the number of blocks in the
+    /// ledger and the current unix timestamp.
+    pub fn is_due(&self, num_blocks: u64, now: u64) -> bool {
+        if self.interval_blocks > 0 {
+            num_blocks > self.next_block
+        } else {
+            now >= self.next_timestamp
+        }
+    }
+
+    /// Move the schedule past the current block/time. Executions that were
+    /// missed, e.g. while mobilecoind was not running, are skipped rather than
+    /// made up for.
+    pub fn advance(&mut self, num_blocks: u64, now: u64) {
+        fn next_after(next: u64, interval: u64, current: u64) -> u64 {
+            if current < next {
+                return next;
+            }
+            let num_intervals = (current - next) / interval + 1;
+            next.saturating_add(num_intervals.saturating_mul(interval))
+        }
+
+        if self.interval_blocks > 0 {
+            // The ledger contains block indexes up to num_blocks - 1.
+            self.next_block = next_after(
+                self.next_block,
+                self.interval_blocks,
+                num_blocks.saturating_sub(1),
+            );
+        } else {
+            self.next_timestamp = next_after(self.next_timestamp, self.interval_seconds, now);
+        }
+    }
+
+    /// Make the payment due again, to retry an execution whose transaction
+    /// did not land.
+    pub fn retry(&mut self, execution: &ScheduledPaymentExecution) {
+        if self.interval_blocks > 0 {
+            self.next_block = self.next_block.min(execution.block_count.saturating_sub(1));
+        } else {
+            self.next_timestamp = self.next_timestamp.min(execution.timestamp);
+        }
+    }
+
+    /// Record an execution, pruning the oldest finished executions beyond
+    /// [MAX_STORED_EXECUTIONS]. An execution that could not be submitted
+    /// replaces the previous one if it also could not be submitted, since they
+    /// are attempts at the same payment.
+    pub fn push_execution(&mut self, execution: ScheduledPaymentExecution) {
+        match self.executions.last_mut() {
+            Some(last)
+                if execution.tx.is_none()
+                    && last.tx.is_none()
+                    && last.status == ScheduledPaymentExecutionStatus::Failed as i32 =>
+            {
+                *last = execution;
+            }
+            _ => self.executions.push(execution),
+        }
+
+        while self.executions.len() > MAX_STORED_EXECUTIONS {
+            let Some(index) = self.executions.iter().position(|execution| {
+                execution.status != ScheduledPaymentExecutionStatus::Pending as i32
+            }) else {
+                break;
+            };
+            let pruned = self.executions.remove(index);
+            if pruned.status != ScheduledPaymentExecutionStatus::Failed as i32 {
+                self.num_pruned_executions += 1;
+            }
+        }
+    }
+
+    /// The execution whose transaction did not reach a final state yet, if
+    /// any.
+    pub fn pending_execution(&self) -> Option<&ScheduledPaymentExecution> {
+        self.executions
+            .iter()
+            .find(|execution| execution.status == ScheduledPaymentExecutionStatus::Pending as i32)
+    }
+}
+
+/// Wrapper for the scheduled_payment_id_to_scheduled_payment_data database
+#[derive(Clone)]
+pub struct ScheduledPaymentStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Crypto provider, used for managing database encryption.
+    crypto_provider: DbCryptoProvider,
+
+    /// Mapping of scheduled payment id -> ScheduledPaymentData
+    scheduled_payment_id_to_scheduled_payment_data: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl ScheduledPaymentStore {
+    pub fn new(
+        env: Arc<Environment>,
+        crypto_provider: DbCryptoProvider,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let scheduled_payment_id_to_scheduled_payment_data = env.create_db(
+            Some(SCHEDULED_PAYMENT_ID_TO_SCHEDULED_PAYMENT_DATA_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            _env: env,
+            crypto_provider,
+            scheduled_payment_id_to_scheduled_payment_data,
+            logger,
+        })
+    }
+
+    /// Add a new scheduled payment. Ids are assigned sequentially, starting
+    /// at 1.
+    pub fn add(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        data: &ScheduledPaymentData,
+    ) -> Result<u64, Error> {
+        let id = self.get_ids(db_txn)?.last().copied().unwrap_or(0) + 1;
+
+        log::trace!(
+            self.logger,
+            "adding scheduled payment {} for monitor {}",
+            id,
+            data.sender_monitor_id
+        );
+
+        self.set_data(db_txn, id, data)?;
+        Ok(id)
+    }
+
+    /// Store the data of a scheduled payment.
+    pub fn set_data(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        id: u64,
+        data: &ScheduledPaymentData,
+    ) -> Result<(), Error> {
+        let value_bytes = self
+            .crypto_provider
+            .encrypt(&mc_util_serial::encode(data))?;
+        db_txn.put(
+            self.scheduled_payment_id_to_scheduled_payment_data,
+            &id.to_be_bytes(),
+            &value_bytes,
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Get the ScheduledPaymentData for a given `id`.
+    pub fn get_data(
+        &self,
+        db_txn: &impl Transaction,
+        id: u64,
+    ) -> Result<ScheduledPaymentData, Error> {
+        match db_txn.get(
+            self.scheduled_payment_id_to_scheduled_payment_data,
+            &id.to_be_bytes(),
+        ) {
+            Ok(value_bytes) => {
+                let value_bytes = self.crypto_provider.decrypt(value_bytes)?;
+                Ok(mc_util_serial::decode(&value_bytes)?)
+            }
+            Err(lmdb::Error::NotFound) => Err(Error::ScheduledPaymentNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the ids of all scheduled payments, in ascending order.
+    pub fn get_ids(&self, db_txn: &impl Transaction) -> Result<Vec<u64>, Error> {
+        let mut cursor =
+            db_txn.open_ro_cursor(self.scheduled_payment_id_to_scheduled_payment_data)?;
+
+        cursor
+            .iter_start()
+            .map(|result| {
+                let (key, _value) = result?;
+                Ok(u64::from_be_bytes(
+                    key.try_into().map_err(|_| Error::KeyDeserialization)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Get all scheduled payments, ordered by id.
+    pub fn get_all(
+        &self,
+        db_txn: &impl Transaction,
+    ) -> Result<Vec<(u64, ScheduledPaymentData)>, Error> {
+        let mut cursor =
+            db_txn.open_ro_cursor(self.scheduled_payment_id_to_scheduled_payment_data)?;
+
+        cursor
+            .iter_start()
+            .map(|result| {
+                let (key, value) = result?;
+                let id = u64::from_be_bytes(key.try_into().map_err(|_| Error::KeyDeserialization)?);
+                let value_bytes = self.crypto_provider.decrypt(value)?;
+                Ok((id, mc_util_serial::decode(&value_bytes)?))
+            })
+            .collect()
+    }
+
+    /// Remove all scheduled payments made by a given monitor.
+    pub fn remove_for_monitor(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        for (id, data) in self.get_all(db_txn)? {
+            if data.sender_monitor_id == *monitor_id {
+                db_txn.del(
+                    self.scheduled_payment_id_to_scheduled_payment_data,
+                    &id.to_be_bytes(),
+                    None,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-encrypt all scheduled payments with a new password.
+    /// This will fail if the current password is not set in the crypto_provider
+    /// since part of the re-encryption process relies on being able to
+    /// decrypt the existing data.
+    pub fn re_encrypt(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        new_password: &[u8],
+    ) -> Result<(), Error> {
+        let mut cursor =
+            db_txn.open_rw_cursor(self.scheduled_payment_id_to_scheduled_payment_data)?;
+
+        for (key_bytes, value_bytes) in cursor.iter().filter_map(|r| r.ok()) {
+            let decrypted_bytes = self.crypto_provider.decrypt(value_bytes)?;
+            let encrypted_bytes = self
+                .crypto_provider
+                .encrypt_with_password(new_password, &decrypted_bytes)?;
+            cursor.put(&key_bytes, &encrypted_bytes, WriteFlags::CURRENT)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::test_with_logger;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    fn scheduled_payment(
+        sender_monitor_id: MonitorId,
+        receiver: PublicAddress,
+    ) -> ScheduledPaymentData {
+        ScheduledPaymentData {
+            sender_monitor_id,
+            sender_subaddress: 0,
+            receiver,
+            value: 100,
+            token_id: 0,
+            interval_blocks: 10,
+            interval_seconds: 0,
+            next_block: 5,
+            next_timestamp: 0,
+            max_executions: 2,
+            cancelled: false,
+            executions: vec![],
+            num_pruned_executions: 0,
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+
+        let mut data = scheduled_payment(monitor_id, receiver);
+        assert!(data.validate().is_ok());
+        assert_matches!(
+            ScheduledPaymentData {
+                interval_seconds: 60,
+                ..data.clone()
+            }
+            .validate(),
+            Err(Error::InvalidArgument(..))
+        );
+        assert_matches!(
+            ScheduledPaymentData {
+                value: 0,
+                ..data.clone()
+            }
+            .validate(),
+            Err(Error::InvalidArgument(..))
+        );
+
+        // Block schedules are due once the ledger contains `next_block`.
+        assert!(!data.is_due(5, u64::MAX));
+        assert!(data.is_due(6, 0));
+        data.advance(6, 0);
+        assert_eq!(data.next_block, 15);
+
+        // Missed executions are skipped.
+        data.advance(40, 0);
+        assert_eq!(data.next_block, 45);
+        data.advance(40, 0);
+        assert_eq!(data.next_block, 45);
+
+        // Wall-clock schedules.
+        let mut data = ScheduledPaymentData {
+            interval_blocks: 0,
+            interval_seconds: 60,
+            next_timestamp: 1000,
+            ..data
+        };
+        assert!(data.validate().is_ok());
+        assert!(!data.is_due(u64::MAX, 999));
+        assert!(data.is_due(0, 1000));
+        data.advance(0, 1000);
+        assert_eq!(data.next_timestamp, 1060);
+        data.advance(0, 1200);
+        assert_eq!(data.next_timestamp, 1240);
+
+        // The payment completes after max_executions.
+        assert!(data.is_active());
+        data.executions.push(ScheduledPaymentExecution::default());
+        assert!(data.is_active());
+        assert!(data.pending_execution().is_some());
+        data.executions.push(ScheduledPaymentExecution::default());
+        assert!(!data.is_active());
+
+        // Failed executions do not count.
+        data.executions[1].status = ScheduledPaymentExecutionStatus::Failed as i32;
+        assert!(data.is_active());
+
+        // Retrying makes the payment due again.
+        let execution = ScheduledPaymentExecution {
+            block_count: 0,
+            timestamp: 1100,
+            ..Default::default()
+        };
+        assert!(!data.is_due(0, 1100));
+        data.retry(&execution);
+        assert!(data.is_due(0, 1100));
+
+        data.max_executions = 0;
+        assert!(data.is_active());
+        data.cancelled = true;
+        assert!(!data.is_active());
+    }
+
+    #[test]
+    fn test_push_execution() {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+
+        let mut data = ScheduledPaymentData {
+            max_executions: 0,
+            ..scheduled_payment(monitor_id, receiver)
+        };
+        let submitted = |status: ScheduledPaymentExecutionStatus| ScheduledPaymentExecution {
+            tx: Some(WatchedTx::default()),
+            status: status as i32,
+            ..Default::default()
+        };
+        let not_submitted = |error: &str| ScheduledPaymentExecution {
+            status: ScheduledPaymentExecutionStatus::Failed as i32,
+            error: error.to_owned(),
+            ..Default::default()
+        };
+
+        // Repeated failures to submit are recorded once.
+        data.push_execution(not_submitted("first"));
+        data.push_execution(not_submitted("second"));
+        assert_eq!(data.executions, vec![not_submitted("second")]);
+        data.push_execution(submitted(ScheduledPaymentExecutionStatus::Pending));
+        data.push_execution(not_submitted("third"));
+        assert_eq!(data.executions.len(), 3);
+        assert_eq!(data.num_executions(), 1);
+
+        // Old executions are pruned, still counting the ones that did not fail.
+        data.executions[1].status = ScheduledPaymentExecutionStatus::Verified as i32;
+        for _ in 0..MAX_STORED_EXECUTIONS {
+            data.push_execution(submitted(ScheduledPaymentExecutionStatus::Verified));
+        }
+        data.push_execution(submitted(ScheduledPaymentExecutionStatus::Pending));
+        assert_eq!(data.executions.len(), MAX_STORED_EXECUTIONS);
+        assert_eq!(data.num_executions(), MAX_STORED_EXECUTIONS as u64 + 2);
+        assert!(data.pending_execution().is_some());
+    }
+
+    #[test_with_logger]
+    fn test_scheduled_payment_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let db_tmp = TempDir::new().expect("Could not make tempdir for scheduled payment store db");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_tmp.path())
+                .unwrap(),
+        );
+        let crypto_provider = DbCryptoProvider::new(env.clone()).unwrap();
+        let store = ScheduledPaymentStore::new(env.clone(), crypto_provider, logger).unwrap();
+
+        let (_monitor_data, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_matches!(
+            store.get_data(&db_txn, 1),
+            Err(Error::ScheduledPaymentNotFound)
+        );
+
+        let data0 = scheduled_payment(monitor_id0, receiver.clone());
+        let data1 = scheduled_payment(monitor_id1, receiver.clone());
+        assert_eq!(store.add(&mut db_txn, &data0).unwrap(), 1);
+        assert_eq!(store.add(&mut db_txn, &data1).unwrap(), 2);
+        assert_eq!(store.add(&mut db_txn, &data0).unwrap(), 3);
+        assert_eq!(store.get_data(&db_txn, 2).unwrap(), data1);
+
+        let mut data = store.get_data(&db_txn, 1).unwrap();
+        data.cancelled = true;
+        store.set_data(&mut db_txn, 1, &data).unwrap();
+        assert_eq!(
+            store.get_all(&db_txn).unwrap(),
+            vec![(1, data), (2, data1.clone()), (3, data0)]
+        );
+
+        store.remove_for_monitor(&mut db_txn, &monitor_id0).unwrap();
+        assert_eq!(store.get_all(&db_txn).unwrap(), vec![(2, data1)]);
+        assert_eq!(
+            store
+                .add(&mut db_txn, &scheduled_payment(monitor_id0, receiver))
+                .unwrap(),
+            3
+        );
+    }
+}
@@ -0,0 +1,574 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Execution of scheduled and recurring payments.
+//!
+//! A background thread checks the scheduled payments stored in the database
+//! (see `scheduled_payment_store`). Once a payment is due, it is built from the
+//! UTXOs of the sending subaddress and submitted, and the result is recorded
+//! as an execution of the payment. The next execution waits for the previous
+//! transaction to reach a final state, so that executions do not compete for
+//! the same UTXOs.
+//!
+//! The schedule only moves on once a transaction was submitted. Payments that
+//! could not be built or submitted are retried on the next poll, as are
+//! payments whose transaction did not land before its tombstone block.
+
+use crate::{
+    database::Database,
+    error::Error,
+    payments::{Outlay, TransactionsManager},
+    resubmission_store::TxAttempt,
+    scheduled_payment_store::{
+        ScheduledPaymentData, ScheduledPaymentExecution, ScheduledPaymentExecutionStatus,
+    },
+    transaction_memo::TransactionMemo,
+    utxo_selection::{AvoidRecentlyAttempted, SmallestFirst},
+    utxo_store::UtxoId,
+    webhook_store::WatchedTx,
+    webhooks::{get_final_tx_status, unix_timestamp},
+};
+use mc_common::logger::{log, Logger};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_sync::{NetworkState, PollingNetworkState};
+use mc_mobilecoind_api::TxStatus;
+use mc_transaction_core::TokenId;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Scheduled Payment Thread - holds objects needed to cleanly terminate the
+/// scheduled payment thread.
+pub struct ScheduledPaymentThread {
+    /// The main thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ScheduledPaymentThread {
+    pub fn start<
+        T: BlockchainConnection + UserTxConnection + 'static,
+        FPR: FogPubkeyResolver + 'static,
+    >(
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        transactions_manager: TransactionsManager<T, FPR>,
+        network_state: Arc<RwLock<PollingNetworkState<T>>>,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = thread::Builder::new()
+            .name("scheduled_payments".to_string())
+            .spawn(move || {
+                scheduled_payment_thread_entry_point(
+                    ledger_db,
+                    mobilecoind_db,
+                    transactions_manager,
+                    network_state,
+                    poll_interval,
+                    thread_stop_requested,
+                    logger,
+                );
+            })
+            .expect("failed starting scheduled payment thread");
+
+        Self {
+            join_handle: Some(join_handle),
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle
+                .join()
+                .expect("ScheduledPaymentThread join failed");
+        }
+    }
+}
+
+impl Drop for ScheduledPaymentThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn scheduled_payment_thread_entry_point<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    transactions_manager: TransactionsManager<T, FPR>,
+    network_state: Arc<RwLock<PollingNetworkState<T>>>,
+    poll_interval: Duration,
+    stop_requested: Arc<AtomicBool>,
+    logger: Logger,
+) {
+    log::info!(logger, "Scheduled payment thread started");
+
+    loop {
+        if stop_requested.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // Scheduled payments are encrypted, so they cannot be accessed until the
+        // database is unlocked.
+        if mobilecoind_db.is_unlocked() {
+            match mobilecoind_db.get_scheduled_payments() {
+                Ok(scheduled_payments) => {
+                    for (id, data) in scheduled_payments {
+                        if !data.is_active() && data.pending_execution().is_none() {
+                            continue;
+                        }
+                        if let Err(err) = execute_scheduled_payment(
+                            &ledger_db,
+                            &mobilecoind_db,
+                            &transactions_manager,
+                            &network_state,
+                            id,
+                            data,
+                            &logger,
+                        ) {
+                            log::error!(
+                                logger,
+                                "Error executing scheduled payment {}: {:?}",
+                                id,
+                                err
+                            );
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::error!(logger, "Error getting scheduled payments: {:?}", err);
+                }
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+/// Record the outcome of the pending execution of a scheduled payment, and
+/// make the next execution once it is due.
+fn execute_scheduled_payment<
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+>(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    transactions_manager: &TransactionsManager<T, FPR>,
+    network_state: &Arc<RwLock<PollingNetworkState<T>>>,
+    id: u64,
+    data: ScheduledPaymentData,
+    logger: &Logger,
+) -> Result<(), Error> {
+    // Wait for the previous execution to reach a final state.
+    let data = match data
+        .pending_execution()
+        .and_then(|execution| execution.tx.clone())
+    {
+        Some(pending_tx) => {
+            let Some(status) = get_final_tx_status(ledger_db, &pending_tx)? else {
+                return Ok(());
+            };
+
+            log::info!(
+                logger,
+                "Scheduled payment {} transaction reached final status {:?}",
+                id,
+                status
+            );
+            mobilecoind_db.update_scheduled_payment(id, |data| {
+                let Some(execution) = data.executions.iter_mut().find(|execution| {
+                    execution.status == ScheduledPaymentExecutionStatus::Pending as i32
+                }) else {
+                    return;
+                };
+
+                if status == TxStatus::Verified {
+                    execution.status = ScheduledPaymentExecutionStatus::Verified as i32;
+                    return;
+                }
+                execution.status = ScheduledPaymentExecutionStatus::Failed as i32;
+                execution.error = format!("Transaction failed: {status:?}");
+
+                // The transaction will never land, so the payment is made again.
+                if status == TxStatus::TombstoneBlockExceeded {
+                    let execution = execution.clone();
+                    data.retry(&execution);
+                }
+            })?
+        }
+        None => data,
+    };
+
+    if !data.is_active() {
+        return Ok(());
+    }
+
+    let num_blocks = ledger_db.num_blocks()?;
+    let now = unix_timestamp();
+    if !data.is_due(num_blocks, now) {
+        return Ok(());
+    }
+
+    // Only look at the UTXOs of the monitor once it caught up with the ledger, so
+    // that they reflect the outcome of the previous execution.
+    let monitor_data = mobilecoind_db.get_monitor_data(&data.sender_monitor_id)?;
+    if monitor_data.next_block < num_blocks {
+        return Ok(());
+    }
+
+    let token_id = TokenId::from(data.token_id);
+    let mut utxos =
+        mobilecoind_db.get_utxos_for_subaddress(&data.sender_monitor_id, data.sender_subaddress)?;
+    utxos.retain(|utxo| utxo.token_id == *token_id);
+
+    let last_block_infos = network_state
+        .read()
+        .expect("lock poisoned")
+        .peer_to_block_info()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let memo_builder = TransactionMemo::Rth {
+        subaddress_index: None,
+    }
+    .memo_builder(monitor_data.spending_account_key()?);
+
+    let result = transactions_manager
+        .build_transaction(
+            &data.sender_monitor_id,
+            token_id,
+            data.sender_subaddress,
+            &utxos,
+            &[Outlay {
+                value: data.value,
+                receiver: data.receiver.clone(),
+                tx_private_key: None,
+            }],
+            &last_block_infos,
+            0,
+            0,
+            memo_builder,
+            &AvoidRecentlyAttempted::<SmallestFirst>::default(),
+        )
+        .and_then(|tx_proposal| {
+            let block_height = transactions_manager.submit_tx_proposal(&tx_proposal)?;
+            Ok((tx_proposal, block_height))
+        });
+
+    let mut execution = ScheduledPaymentExecution {
+        block_count: num_blocks,
+        timestamp: now,
+        ..Default::default()
+    };
+    let submitted = match result {
+        Ok((tx_proposal, block_height)) => {
+            log::info!(
+                logger,
+                "Submitted scheduled payment {} of {} to {}",
+                id,
+                data.value,
+                data.receiver
+            );
+            let attempt = TxAttempt::new(&tx_proposal, block_height)?;
+            execution.fee = attempt.fee;
+            execution.receipt = attempt.receipts.into_iter().next();
+            execution.tx = Some(WatchedTx {
+                key_images: tx_proposal
+                    .utxos
+                    .iter()
+                    .map(|utxo| utxo.key_image)
+                    .collect(),
+                tx_public_keys: attempt.tx_public_keys,
+                tombstone: attempt.tombstone,
+            });
+            execution.status = ScheduledPaymentExecutionStatus::Pending as i32;
+            Some((tx_proposal, block_height))
+        }
+        Err(err) => {
+            log::warn!(logger, "Scheduled payment {} failed: {}", id, err);
+            execution.status = ScheduledPaymentExecutionStatus::Failed as i32;
+            execution.error = err.to_string();
+            None
+        }
+    };
+
+    // A transaction that was sent to the network has to be accounted for even if
+    // updating the UTXOs fails. Otherwise the payment is retried on the next poll.
+    mobilecoind_db.update_scheduled_payment(id, |data| {
+        data.push_execution(execution);
+        if submitted.is_some() {
+            data.advance(num_blocks, now);
+        }
+    })?;
+
+    if let Some((tx_proposal, block_height)) = submitted {
+        let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+        mobilecoind_db.update_attempted_spend(
+            &utxo_ids,
+            block_height,
+            tx_proposal.tx.prefix.tombstone_block,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        monitor_store::MonitorData,
+        test_utils::{
+            add_block_to_ledger, get_testing_environment, wait_for_monitors, BlockVersion,
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+        },
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::test_with_logger;
+    use mc_connection::{Connection, ConnectionManager};
+    use mc_connection_test_utils::MockBlockchainConnection;
+    use mc_consensus_scp::QuorumSet;
+    use mc_fog_report_validation_test_utils::MockFogResolver;
+    use mc_ledger_db::test_utils::add_txos_and_key_images_to_ledger;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, tx::Tx, Amount, Token};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    /// Get the transactions that were submitted to our mock peers.
+    fn proposed_txs(
+        conn_manager: &ConnectionManager<MockBlockchainConnection<LedgerDB>>,
+    ) -> Vec<Tx> {
+        conn_manager
+            .conns()
+            .iter()
+            .flat_map(|conn| conn.read().proposed_txs.clone())
+            .collect()
+    }
+
+    #[test_with_logger]
+    fn test_execute_scheduled_payment(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([48u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (mut ledger_db, mobilecoind_db, _client, _server, conn_manager) =
+            get_testing_environment(
+                BlockVersion::MAX,
+                10,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let node_ids = conn_manager
+            .conns()
+            .iter()
+            .map(|conn| conn.uri().host_and_port_responder_id().unwrap())
+            .collect();
+        let network_state = Arc::new(RwLock::new(PollingNetworkState::new(
+            QuorumSet::new_with_node_ids(2, node_ids),
+            conn_manager.clone(),
+            logger.clone(),
+        )));
+        network_state.write().unwrap().poll();
+
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            conn_manager.clone(),
+            Arc::new(|_| Ok(MockFogResolver::default())),
+            Default::default(),
+            logger.clone(),
+        );
+
+        let data = ScheduledPaymentData {
+            sender_monitor_id: monitor_id,
+            sender_subaddress: 0,
+            receiver: receiver.clone(),
+            value: DEFAULT_PER_RECIPIENT_AMOUNT / 2,
+            token_id: *Mob::ID,
+            interval_blocks: 2,
+            interval_seconds: 0,
+            next_block: 0,
+            next_timestamp: 0,
+            max_executions: 2,
+            cancelled: false,
+            executions: vec![],
+            num_pruned_executions: 0,
+        };
+        let id = mobilecoind_db.add_scheduled_payment(&data).unwrap();
+
+        let execute = |ledger_db: &LedgerDB, id: u64| {
+            let data = mobilecoind_db.get_scheduled_payment(id).unwrap();
+            execute_scheduled_payment(
+                ledger_db,
+                &mobilecoind_db,
+                &transactions_manager,
+                &network_state,
+                id,
+                data,
+                &logger,
+            )
+            .unwrap();
+            mobilecoind_db.get_scheduled_payment(id).unwrap()
+        };
+
+        // The first execution is due right away.
+        let data = execute(&ledger_db, id);
+        assert_eq!(data.executions.len(), 1);
+        let execution = &data.executions[0];
+        assert_eq!(
+            execution.status,
+            ScheduledPaymentExecutionStatus::Pending as i32
+        );
+        assert_eq!(execution.fee, Mob::MINIMUM_FEE);
+        assert_eq!(execution.error, "");
+        assert!(execution.receipt.is_some());
+        assert!(data.next_block >= ledger_db.num_blocks().unwrap());
+
+        let txs = proposed_txs(&conn_manager);
+        assert_eq!(txs.len(), 1);
+        let tx = txs[0].clone();
+        assert_eq!(
+            tx.prefix.outputs[0].public_key,
+            execution.receipt.as_ref().unwrap().tx_public_key
+        );
+
+        // Nothing else is submitted while the transaction is pending, even once the
+        // next execution is due.
+        while ledger_db.num_blocks().unwrap() <= data.next_block {
+            add_block_to_ledger(
+                &mut ledger_db,
+                BlockVersion::MAX,
+                &[receiver.clone()],
+                Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            )
+            .unwrap();
+        }
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        assert_eq!(execute(&ledger_db, id), data);
+        assert_eq!(proposed_txs(&conn_manager).len(), 1);
+
+        // Once the transaction lands, its result is recorded and the second execution
+        // is made.
+        add_txos_and_key_images_to_ledger(
+            &mut ledger_db,
+            BlockVersion::MAX,
+            tx.prefix.outputs.clone(),
+            tx.key_images(),
+            &mut rng,
+        )
+        .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let data = execute(&ledger_db, id);
+        assert_eq!(data.executions.len(), 2);
+        assert_eq!(
+            data.executions[0].status,
+            ScheduledPaymentExecutionStatus::Verified as i32
+        );
+        assert_eq!(
+            data.executions[1].status,
+            ScheduledPaymentExecutionStatus::Pending as i32
+        );
+        assert_eq!(proposed_txs(&conn_manager).len(), 2);
+
+        // The payment completed.
+        assert!(!data.is_active());
+        assert!(data.pending_execution().is_some());
+
+        // Executions that cannot be funded are recorded as failed, and retried on the
+        // next poll.
+        let unfunded_id = mobilecoind_db
+            .add_scheduled_payment(&ScheduledPaymentData {
+                value: 1000 * DEFAULT_PER_RECIPIENT_AMOUNT,
+                next_block: 0,
+                max_executions: 1,
+                executions: vec![],
+                ..data.clone()
+            })
+            .unwrap();
+        execute(&ledger_db, unfunded_id);
+        let unfunded = execute(&ledger_db, unfunded_id);
+        assert_eq!(unfunded.executions.len(), 1);
+        assert_eq!(
+            unfunded.executions[0].status,
+            ScheduledPaymentExecutionStatus::Failed as i32
+        );
+        assert_ne!(unfunded.executions[0].error, "");
+        assert!(unfunded.executions[0].tx.is_none());
+        assert!(unfunded.is_active());
+        assert!(unfunded.is_due(ledger_db.num_blocks().unwrap(), 0));
+        assert_eq!(proposed_txs(&conn_manager).len(), 2);
+
+        // Executions whose transaction does not land before its tombstone block are
+        // made again.
+        let retried_id = mobilecoind_db
+            .add_scheduled_payment(&ScheduledPaymentData {
+                next_block: 0,
+                max_executions: 1,
+                executions: vec![],
+                ..data
+            })
+            .unwrap();
+        let data = execute(&ledger_db, retried_id);
+        let tombstone = data.executions[0].tx.as_ref().unwrap().tombstone;
+        assert_eq!(proposed_txs(&conn_manager).len(), 3);
+        assert!(!data.is_active());
+
+        while ledger_db.num_blocks().unwrap() < tombstone {
+            add_block_to_ledger(
+                &mut ledger_db,
+                BlockVersion::MAX,
+                &[receiver.clone()],
+                Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            )
+            .unwrap();
+        }
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let data = execute(&ledger_db, retried_id);
+        assert_eq!(data.executions.len(), 2);
+        assert_eq!(
+            data.executions[0].status,
+            ScheduledPaymentExecutionStatus::Failed as i32
+        );
+        assert!(data.executions[0].error.contains("TombstoneBlockExceeded"));
+        assert_eq!(
+            data.executions[1].status,
+            ScheduledPaymentExecutionStatus::Pending as i32
+        );
+        assert_eq!(proposed_txs(&conn_manager).len(), 4);
+    }
+}
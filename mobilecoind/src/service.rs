@@ -7,19 +7,40 @@
 //! * writes matching transactions to a local DB, organized by subaddress_id
 
 use crate::{
+    conversions::decode_memo,
     database::Database,
+    defragmentation_store::DefragmentationPolicy,
     error::Error,
+    hd_monitor_store::{HdMonitorData, HdMonitorId},
     monitor_store::{MonitorData, MonitorId},
-    payments::{Outlay, OutlayV2, SciForTx, TransactionsManager, TxProposal},
+    payment_request_store::{PaymentRequestData, PaymentRequestStatus},
+    payments::{Outlay, OutlayV2, SciForTx, TransactionsManager, TxProposal, UnsignedTxProposal},
+    processed_block_store::ProcessedTxOut,
+    resubmission_store::{ResubmissionPolicy, ResubmittedOutlay, ResubmittedTx, TxAttempt},
+    scheduled_payment_store::{ScheduledPaymentData, ScheduledPaymentExecutionStatus},
     sync::SyncThread,
     transaction_memo::TransactionMemo,
+    tx_status::get_tx_status_as_sender,
+    utxo_selection::{ExactMatch, SmallestFirst, UtxoSelectionStrategy},
     utxo_store::{UnspentTxOut, UtxoId},
+    webhook_store::WatchedTx,
+    webhooks::get_final_tx_status,
 };
 use api::ledger::{TxOutResult, TxOutResultCode};
 use bip39::{Language, Mnemonic, MnemonicType};
-use grpcio::{EnvBuilder, RpcContext, RpcStatus, RpcStatusCode, ServerBuilder, UnarySink};
+use futures::{
+    channel::mpsc::{channel, Receiver},
+    executor::block_on,
+    future::poll_fn,
+    SinkExt, StreamExt,
+};
+use grpcio::{
+    EnvBuilder, RpcContext, RpcStatus, RpcStatusCode, ServerBuilder, ServerStreamingSink,
+    UnarySink, WriteFlags,
+};
+use hex_fmt::HexFmt;
 use mc_account_keys::{
-    burn_address, AccountKey, PublicAddress, RootIdentity, ShortAddressHash,
+    burn_address, AccountKey, PublicAddress, RootIdentity, ShortAddressHash, ViewAccountKey,
     DEFAULT_SUBADDRESS_INDEX,
 };
 use mc_api::blockchain::ArchiveBlock;
@@ -41,6 +62,7 @@ use mc_mobilecoind_api::{
 };
 use mc_transaction_builder::BurnRedemptionMemoBuilder;
 use mc_transaction_core::{
+    constants::MAX_INPUTS,
     get_tx_out_shared_secret,
     onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
@@ -48,6 +70,7 @@ use mc_transaction_core::{
     Amount, MemoPayload, TokenId,
 };
 use mc_transaction_extra::{BurnRedemptionMemo, MemoType, TxOutConfirmationNumber};
+use mc_transaction_signer::types::TxSignResp;
 use mc_util_from_random::FromRandom;
 use mc_util_grpc::{
     rpc_internal_error, rpc_invalid_arg_error, rpc_logger, send_result, AdminService,
@@ -56,7 +79,32 @@ use mc_util_grpc::{
 use mc_watcher::watcher_db::WatcherDB;
 use mc_watcher_api::TimestampResultCode;
 use protobuf::{ProtobufEnum, RepeatedField};
-use std::sync::{Arc, Mutex, RwLock};
+use rand::Rng;
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex, RwLock},
+    task::Poll,
+    thread,
+    time::Duration,
+};
+
+/// How long a processed blocks subscription waits for a new block before
+/// checking whether its subscriber is still connected.
+const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of processed blocks a subscription buffers ahead of its subscriber.
+const SUBSCRIPTION_CHANNEL_SIZE: usize = 16;
+
+/// Number of events a transaction history page holds when the request does
+/// not specify a limit.
+const DEFAULT_MAX_HISTORY_EVENTS: usize = 1000;
+
+/// Receiving end of the channel used to hand processed blocks from a
+/// subscription thread to the gRPC streaming sink. `None` is sent while the
+/// thread is idle, to have the forwarding task check that the subscriber is
+/// still connected.
+type ProcessedBlocksReceiver =
+    Receiver<Option<Result<api::SubscribeProcessedBlocksResponse, RpcStatus>>>;
 
 pub struct Service {
     /// Sync thread.
@@ -249,27 +297,49 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         &mut self,
         request: api::AddMonitorRequest,
     ) -> Result<api::AddMonitorResponse, RpcStatus> {
-        // Get the AccountKey from the GRPC request.
-        let proto_account_key = request.account_key.as_ref().ok_or_else(|| {
-            RpcStatus::with_message(RpcStatusCode::INVALID_ARGUMENT, "account_key".into())
-        })?;
-        let account_key = AccountKey::try_from(proto_account_key)
-            .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
-
-        // Populate a new `MonitorData` instance.
-        let data = MonitorData::new(
-            account_key,
-            request.first_subaddress,
-            request.num_subaddresses,
-            request.first_block,
-            &request.name,
-        )
+        // Populate a new `MonitorData` instance, from either the AccountKey or the
+        // ViewAccountKey in the GRPC request.
+        let data = match (
+            request.account_key.as_ref(),
+            request.view_account_key.as_ref(),
+        ) {
+            (Some(proto_account_key), None) => {
+                let account_key = AccountKey::try_from(proto_account_key)
+                    .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
+                MonitorData::new(
+                    account_key,
+                    request.first_subaddress,
+                    request.num_subaddresses,
+                    request.first_block,
+                    &request.name,
+                )
+            }
+            (None, Some(proto_view_account_key)) => {
+                let view_account_key =
+                    ViewAccountKey::try_from(proto_view_account_key).map_err(|err| {
+                        rpc_invalid_arg_error("view_account_key.try_from", err, &self.logger)
+                    })?;
+                MonitorData::new_watch_only(
+                    view_account_key,
+                    request.first_subaddress,
+                    request.num_subaddresses,
+                    request.first_block,
+                    &request.name,
+                )
+            }
+            _ => {
+                return Err(RpcStatus::with_message(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    "exactly one of account_key and view_account_key must be set".into(),
+                ))
+            }
+        }
         .map_err(|err| rpc_internal_error("monitor_data.new", err, &self.logger))?;
 
         // Insert into database. Return the id and flag if the monitor already existed.
         let (id, is_new) = match self.mobilecoind_db.add_monitor(&data) {
             Ok(id) => Ok((id, true)),
-            Err(Error::MonitorIdExists) => Ok((MonitorId::from(&data), false)),
+            Err(Error::MonitorIdExists) => MonitorId::try_from(&data).map(|id| (id, false)),
             Err(err) => Err(err),
         }
         .map_err(|err| rpc_internal_error("mobilecoind_db.add_monitor", err, &self.logger))?;
@@ -331,15 +401,270 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
             })?;
 
-        let mut status = api::MonitorStatus::new();
-        status.set_account_key(mc_api::external::AccountKey::from(&data.account_key));
-        status.set_first_subaddress(data.first_subaddress);
-        status.set_num_subaddresses(data.num_subaddresses);
-        status.set_first_block(data.first_block);
-        status.set_next_block(data.next_block);
-
         let mut response = api::GetMonitorStatusResponse::new();
-        response.set_status(status);
+        response.set_status((&data).into());
+
+        match self.mobilecoind_db.get_defragmentation_data(&monitor_id) {
+            Ok(defragmentation_data) => {
+                response.set_defragmentation((&defragmentation_data).into());
+            }
+            Err(Error::DefragmentationPolicyNotFound) => {}
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "mobilecoind_db.get_defragmentation_data",
+                    err,
+                    &self.logger,
+                ))
+            }
+        }
+
+        Ok(response)
+    }
+
+    fn set_defragmentation_policy_impl(
+        &mut self,
+        request: api::SetDefragmentationPolicyRequest,
+    ) -> Result<api::Empty, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        if request.has_policy() {
+            let policy = DefragmentationPolicy::from(request.get_policy());
+            policy
+                .validate()
+                .map_err(|err| rpc_invalid_arg_error("policy", err, &self.logger))?;
+
+            // Defragmentation transactions are signed by mobilecoind.
+            self.mobilecoind_db
+                .get_monitor_data(&monitor_id)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                })?
+                .spending_account_key()
+                .map_err(|err| rpc_invalid_arg_error("monitor_id", err, &self.logger))?;
+
+            self.mobilecoind_db
+                .set_defragmentation_policy(&monitor_id, &policy)
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "mobilecoind_db.set_defragmentation_policy",
+                        err,
+                        &self.logger,
+                    )
+                })?;
+        } else {
+            match self
+                .mobilecoind_db
+                .remove_defragmentation_policy(&monitor_id)
+            {
+                Ok(()) | Err(Error::DefragmentationPolicyNotFound) => {}
+                Err(err) => {
+                    return Err(rpc_internal_error(
+                        "mobilecoind_db.remove_defragmentation_policy",
+                        err,
+                        &self.logger,
+                    ))
+                }
+            }
+        }
+
+        Ok(api::Empty::new())
+    }
+
+    fn add_hd_monitor_impl(
+        &mut self,
+        request: api::AddHdMonitorRequest,
+    ) -> Result<api::AddHdMonitorResponse, RpcStatus> {
+        let mnemonic = Mnemonic::from_phrase(request.get_mnemonic(), Language::English)
+            .map_err(|err| rpc_invalid_arg_error("mnemonic", err, &self.logger))?;
+        if request.account_indices.is_empty() {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "account_indices".into(),
+            ));
+        }
+
+        // Populate a `MonitorData` instance for each account.
+        let account_monitors = request
+            .get_account_indices()
+            .iter()
+            .map(|account_index| {
+                let key = mnemonic.clone().derive_slip10_key(*account_index);
+                let mut data = MonitorData::new(
+                    AccountKey::from(key),
+                    request.first_subaddress,
+                    request.num_subaddresses,
+                    request.first_block,
+                    &request.name,
+                )?;
+                data.gap_limit = request.gap_limit;
+                Ok(data)
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(|err| rpc_invalid_arg_error("monitor_data.new", err, &self.logger))?;
+        let monitor_ids = account_monitors
+            .iter()
+            .map(MonitorId::try_from)
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(|err| rpc_internal_error("monitor_id.try_from", err, &self.logger))?;
+
+        // Insert into database. Return the id and flag if the monitor already existed.
+        let (id, is_new) = match self.mobilecoind_db.add_hd_monitor(
+            request.get_account_indices(),
+            &account_monitors,
+            &request.name,
+        ) {
+            Ok(id) => Ok((id, true)),
+            Err(Error::HdMonitorIdExists) => HdMonitorData::new(
+                request.account_indices.clone(),
+                monitor_ids.clone(),
+                &request.name,
+            )
+            .map(|data| (HdMonitorId::from(&data), false)),
+            Err(err) => Err(err),
+        }
+        .map_err(|err| match err {
+            // One of the accounts is already tracked by a monitor of its own.
+            Error::MonitorIdExists => {
+                let account_index = request
+                    .account_indices
+                    .iter()
+                    .zip(&monitor_ids)
+                    .find(|(_, id)| self.mobilecoind_db.get_monitor_data(id).is_ok())
+                    .map(|(account_index, _)| account_index.to_string())
+                    .unwrap_or_default();
+                RpcStatus::with_message(
+                    RpcStatusCode::ALREADY_EXISTS,
+                    format!("account {account_index} already has a monitor, remove it first"),
+                )
+            }
+            err => rpc_internal_error("mobilecoind_db.add_hd_monitor", err, &self.logger),
+        })?;
+
+        // Return success response.
+        let mut response = api::AddHdMonitorResponse::new();
+        response.set_hd_monitor_id(id.to_vec());
+        response.set_monitor_id_list(monitor_ids.iter().map(|id| id.to_vec()).collect());
+        response.set_is_new(is_new);
+        Ok(response)
+    }
+
+    fn remove_hd_monitor_impl(
+        &mut self,
+        request: api::RemoveHdMonitorRequest,
+    ) -> Result<api::Empty, RpcStatus> {
+        let hd_monitor_id = HdMonitorId::try_from(&request.hd_monitor_id).map_err(|err| {
+            rpc_invalid_arg_error("hd_monitor_id.try_from.bytes", err, &self.logger)
+        })?;
+
+        self.mobilecoind_db
+            .remove_hd_monitor(&hd_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.remove_hd_monitor", err, &self.logger)
+            })?;
+
+        Ok(api::Empty::new())
+    }
+
+    fn get_hd_monitor_status_impl(
+        &mut self,
+        request: api::GetHdMonitorStatusRequest,
+    ) -> Result<api::GetHdMonitorStatusResponse, RpcStatus> {
+        let hd_monitor_id = HdMonitorId::try_from(&request.hd_monitor_id).map_err(|err| {
+            rpc_invalid_arg_error("hd_monitor_id.try_from.bytes", err, &self.logger)
+        })?;
+
+        let hd_data = self
+            .mobilecoind_db
+            .get_hd_monitor_data(&hd_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_hd_monitor_data", err, &self.logger)
+            })?;
+
+        let mut response = api::GetHdMonitorStatusResponse::new();
+        for (account_index, monitor_id) in hd_data.accounts() {
+            let data = self
+                .mobilecoind_db
+                .get_monitor_data(monitor_id)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                })?;
+
+            let mut account_status = api::HdMonitorAccountStatus::new();
+            account_status.set_account_index(account_index);
+            account_status.set_monitor_id(monitor_id.to_vec());
+            account_status.set_status((&data).into());
+            response.mut_account_status_list().push(account_status);
+        }
+        response.set_name(hd_data.name);
+        Ok(response)
+    }
+
+    fn get_hd_monitor_balance_impl(
+        &mut self,
+        request: api::GetHdMonitorBalanceRequest,
+    ) -> Result<api::GetHdMonitorBalanceResponse, RpcStatus> {
+        let hd_monitor_id = HdMonitorId::try_from(&request.hd_monitor_id).map_err(|err| {
+            rpc_invalid_arg_error("hd_monitor_id.try_from.bytes", err, &self.logger)
+        })?;
+
+        let hd_data = self
+            .mobilecoind_db
+            .get_hd_monitor_data(&hd_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_hd_monitor_data", err, &self.logger)
+            })?;
+
+        let mut response = api::GetHdMonitorBalanceResponse::new();
+        for (account_index, monitor_id) in hd_data.accounts() {
+            let data = self
+                .mobilecoind_db
+                .get_monitor_data(monitor_id)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                })?;
+
+            let mut account_balance = api::HdMonitorAccountBalance::new();
+            account_balance.set_account_index(account_index);
+            account_balance.set_monitor_id(monitor_id.to_vec());
+
+            let mut account_total = 0u128;
+            for subaddress_index in data.subaddress_indexes() {
+                let balance = self
+                    .mobilecoind_db
+                    .get_utxos_for_subaddress(monitor_id, subaddress_index)
+                    .map_err(|err| {
+                        rpc_internal_error(
+                            "mobilecoind_db.get_utxos_for_subaddress",
+                            err,
+                            &self.logger,
+                        )
+                    })?
+                    .iter()
+                    .filter(|utxo| utxo.token_id == request.token_id)
+                    .map(|utxo| utxo.value as u128)
+                    .sum::<u128>();
+                account_total += balance;
+
+                // It's possible the balance does not fit into a u64.
+                if account_total > u64::MAX.into() {
+                    return Err(RpcStatus::with_message(
+                        RpcStatusCode::INTERNAL,
+                        format!("balance of account {account_index} won't fit in u64"),
+                    ));
+                }
+
+                let mut subaddress_balance = api::SubaddressBalance::new();
+                subaddress_balance.set_subaddress_index(subaddress_index);
+                subaddress_balance.set_balance(balance as u64);
+                account_balance
+                    .mut_subaddress_balance_list()
+                    .push(subaddress_balance);
+            }
+            account_balance.set_balance(account_total as u64);
+
+            response.mut_account_balance_list().push(account_balance);
+        }
         Ok(response)
     }
 
@@ -399,6 +724,78 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn get_unsynced_tx_out_list_impl(
+        &mut self,
+        request: api::GetUnsyncedTxOutListRequest,
+    ) -> Result<api::GetUnsyncedTxOutListResponse, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_invalid_arg_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let unsynced_txos = self
+            .mobilecoind_db
+            .get_unsynced_txos(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_unsynced_txos", err, &self.logger)
+            })?;
+
+        let mut response = api::GetUnsyncedTxOutListResponse::new();
+        response.set_unsynced_tx_out_list(RepeatedField::from_vec(
+            unsynced_txos.iter().map(|txo| txo.into()).collect(),
+        ));
+        Ok(response)
+    }
+
+    fn sync_tx_outs_impl(
+        &mut self,
+        request: api::SyncTxOutsRequest,
+    ) -> Result<api::SyncTxOutsResponse, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_invalid_arg_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let key_images = request
+            .get_synced_tx_out_list()
+            .iter()
+            .enumerate()
+            .map(|(i, synced_txo)| {
+                let tx_out_public_key =
+                    CompressedRistrettoPublic::try_from(synced_txo.get_tx_out_public_key())
+                        .map_err(|err| {
+                            rpc_invalid_arg_error(
+                                format!("synced_tx_out_list[{i}].tx_out_public_key"),
+                                err,
+                                &self.logger,
+                            )
+                        })?;
+                let key_image = KeyImage::try_from(synced_txo.get_key_image()).map_err(|err| {
+                    rpc_invalid_arg_error(
+                        format!("synced_tx_out_list[{i}].key_image"),
+                        err,
+                        &self.logger,
+                    )
+                })?;
+                Ok((tx_out_public_key, key_image))
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+        let utxos = self
+            .mobilecoind_db
+            .sync_txos(&monitor_id, &key_images, |key_image| {
+                Ok(self.ledger_db.contains_key_image(key_image)?)
+            })
+            .map_err(|err| match err {
+                Error::UnsyncedTxOutNotFound => {
+                    rpc_invalid_arg_error("synced_tx_out_list", err, &self.logger)
+                }
+                err => rpc_internal_error("mobilecoind_db.sync_txos", err, &self.logger),
+            })?;
+
+        let mut response = api::SyncTxOutsResponse::new();
+        response.set_utxo_list(RepeatedField::from_vec(
+            utxos.iter().map(|utxo| utxo.into()).collect(),
+        ));
+        Ok(response)
+    }
+
     fn generate_root_entropy_impl(
         &mut self,
         _request: api::Empty,
@@ -489,7 +886,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
 
         // Get the subaddress.
-        let subaddress = data.account_key.subaddress(request.subaddress_index);
+        let subaddress = data
+            .subaddress(request.subaddress_index)
+            .map_err(|err| rpc_internal_error("monitor_data.subaddress", err, &self.logger))?;
 
         // Also build the b58 wrapper
         let mut wrapper = api::printable::PrintableWrapper::new();
@@ -548,8 +947,10 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
             })?;
 
+        // The subaddress view private key depends on the spend private key.
         let subaddress_vpk = data
-            .account_key
+            .spending_account_key()
+            .map_err(|err| rpc_invalid_arg_error("monitor_id", err, &self.logger))?
             .subaddress_view_private(utxo.subaddress_index);
         let tx_out_public_key = &utxo.tx_out.public_key;
 
@@ -630,6 +1031,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             response.set_value(payment_request.get_value());
             response.set_memo(payment_request.get_memo().to_string());
             response.set_token_id(payment_request.get_token_id());
+            response.set_payment_id(payment_request.get_payment_id());
             Ok(response)
         } else if wrapper.has_public_address() {
             let public_address = wrapper.get_public_address();
@@ -658,6 +1060,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         payment_request.set_value(request.get_value());
         payment_request.set_memo(request.get_memo().to_string());
         payment_request.set_token_id(request.get_token_id());
+        payment_request.set_payment_id(request.get_payment_id());
 
         let mut wrapper = api::printable::PrintableWrapper::new();
         wrapper.set_payment_request(payment_request);
@@ -888,6 +1291,123 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn add_payment_request_impl(
+        &mut self,
+        request: api::AddPaymentRequestRequest,
+    ) -> Result<api::AddPaymentRequestResponse, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_invalid_arg_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+
+        // Verify subaddress falls in the range we are monitoring.
+        if !monitor_data
+            .subaddress_indexes()
+            .contains(&request.subaddress_index)
+        {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "subaddress_index".into(),
+            ));
+        }
+
+        let payment_id = if request.payment_id == 0 {
+            rand::thread_rng().gen_range(1..=u64::MAX)
+        } else {
+            request.payment_id
+        };
+
+        let data = PaymentRequestData::new(
+            monitor_id,
+            request.subaddress_index,
+            request.value,
+            request.token_id,
+            &request.memo,
+            request.expiry_block,
+        );
+        self.mobilecoind_db
+            .add_payment_request(payment_id, &data)
+            .map_err(|err| match err {
+                Error::PaymentIdExists => rpc_invalid_arg_error("payment_id", err, &self.logger),
+                err => rpc_internal_error("mobilecoind_db.add_payment_request", err, &self.logger),
+            })?;
+
+        // Build the request code.
+        let receiver = monitor_data
+            .subaddress(request.subaddress_index)
+            .map_err(|err| rpc_internal_error("monitor_data.subaddress", err, &self.logger))?;
+
+        let mut payment_request = api::printable::PaymentRequest::new();
+        payment_request.set_public_address((&receiver).into());
+        payment_request.set_value(request.value);
+        payment_request.set_memo(request.memo);
+        payment_request.set_token_id(request.token_id);
+        payment_request.set_payment_id(payment_id);
+
+        let mut wrapper = api::printable::PrintableWrapper::new();
+        wrapper.set_payment_request(payment_request);
+
+        let encoded = wrapper
+            .b58_encode()
+            .map_err(|err| rpc_internal_error("b58_encode", err, &self.logger))?;
+
+        let mut response = api::AddPaymentRequestResponse::new();
+        response.set_payment_id(payment_id);
+        response.set_b58_code(encoded);
+        Ok(response)
+    }
+
+    fn remove_payment_request_impl(
+        &mut self,
+        request: api::RemovePaymentRequestRequest,
+    ) -> Result<api::Empty, RpcStatus> {
+        self.mobilecoind_db
+            .remove_payment_request(request.payment_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.remove_payment_request", err, &self.logger)
+            })?;
+
+        Ok(api::Empty::new())
+    }
+
+    fn get_payment_request_status_impl(
+        &mut self,
+        request: api::GetPaymentRequestStatusRequest,
+    ) -> Result<api::GetPaymentRequestStatusResponse, RpcStatus> {
+        let data = self
+            .mobilecoind_db
+            .get_payment_request(request.payment_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_payment_request", err, &self.logger)
+            })?;
+
+        let mut response = api::GetPaymentRequestStatusResponse::new();
+        response.set_status(self.payment_request_status(request.payment_id, data)?);
+        Ok(response)
+    }
+
+    fn get_payment_request_list_impl(
+        &mut self,
+        _request: api::Empty,
+    ) -> Result<api::GetPaymentRequestListResponse, RpcStatus> {
+        let payment_requests = self.mobilecoind_db.get_payment_requests().map_err(|err| {
+            rpc_internal_error("mobilecoind_db.get_payment_requests", err, &self.logger)
+        })?;
+
+        let mut response = api::GetPaymentRequestListResponse::new();
+        for (payment_id, data) in payment_requests {
+            response
+                .mut_status_list()
+                .push(self.payment_request_status(payment_id, data)?);
+        }
+        Ok(response)
+    }
+
     /// Get mixins
     fn get_mixins_impl(
         &mut self,
@@ -1091,7 +1611,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Get transaction memo builder.
         let transaction_memo = TransactionMemo::try_from(request.get_memo())
             .map_err(|err| rpc_invalid_arg_error("transaction_memo.try_from", err, &self.logger))?;
-        let memo_builder = transaction_memo.memo_builder(&sender_monitor_data.account_key);
+        let sender_account_key = sender_monitor_data
+            .spending_account_key()
+            .map_err(|err| rpc_invalid_arg_error("sender_monitor_id", err, &self.logger))?;
+        let memo_builder = transaction_memo.memo_builder(sender_account_key);
+
+        let utxo_selection: Box<dyn UtxoSelectionStrategy> =
+            request.get_utxo_selection_strategy().into();
 
         // Attempt to construct a transaction.
         let tx_proposal = self
@@ -1106,6 +1632,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 request.fee,
                 request.tombstone,
                 memo_builder,
+                utxo_selection.as_ref(),
             )
             .map_err(|err| {
                 rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
@@ -1117,10 +1644,163 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
-    fn generate_mixed_tx_impl(
+    fn generate_unsigned_tx_impl(
         &mut self,
-        request: api::GenerateMixedTxRequest,
-    ) -> Result<api::GenerateMixedTxResponse, RpcStatus> {
+        request: api::GenerateUnsignedTxRequest,
+    ) -> Result<api::GenerateUnsignedTxResponse, RpcStatus> {
+        // Get sender monitor id from request.
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Get monitor data for this monitor.
+        let sender_monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&sender_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+
+        // Check that change_subaddress is covered by this monitor.
+        if !sender_monitor_data
+            .subaddress_indexes()
+            .contains(&request.change_subaddress)
+        {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "change_subaddress".into(),
+            ));
+        }
+
+        // Get the list of potential inputs, which must belong to the monitor.
+        let input_list: Vec<UnspentTxOut> = request
+            .get_input_list()
+            .iter()
+            .enumerate()
+            .map(|(i, proto_utxo)| {
+                let utxo = UnspentTxOut::try_from(proto_utxo).map_err(|err| {
+                    rpc_internal_error("unspent_tx_out.try_from", err, &self.logger)
+                })?;
+
+                if utxo.token_id != request.token_id {
+                    return Err(RpcStatus::with_message(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        format!("input_list[{i}].token_id"),
+                    ));
+                }
+
+                let subaddress_id = self
+                    .mobilecoind_db
+                    .get_subaddress_id_by_utxo_id(&UtxoId::from(&utxo))
+                    .map_err(|err| {
+                        rpc_internal_error(
+                            "mobilecoind_db.get_subaddress_id_by_utxo_id",
+                            err,
+                            &self.logger,
+                        )
+                    })?;
+                if subaddress_id.monitor_id != sender_monitor_id {
+                    return Err(RpcStatus::with_message(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        format!("input_list.{i}"),
+                    ));
+                }
+
+                Ok(utxo)
+            })
+            .collect::<Result<Vec<UnspentTxOut>, RpcStatus>>()?;
+
+        // Get the list of outlays.
+        let outlays: Vec<Outlay> = request
+            .get_outlay_list()
+            .iter()
+            .map(|outlay_proto| {
+                Outlay::try_from(outlay_proto)
+                    .map_err(|err| rpc_internal_error("outlay.try_from", err, &self.logger))
+            })
+            .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
+
+        let utxo_selection: Box<dyn UtxoSelectionStrategy> =
+            request.get_utxo_selection_strategy().into();
+
+        // Attempt to construct the unsigned transaction.
+        let unsigned_tx_proposal = self
+            .transactions_manager
+            .build_unsigned_transaction(
+                &sender_monitor_id,
+                TokenId::from(request.token_id),
+                request.change_subaddress,
+                &input_list,
+                &outlays,
+                &self.get_last_block_infos(),
+                request.fee,
+                request.tombstone,
+                utxo_selection.as_ref(),
+            )
+            .map_err(|err| {
+                rpc_internal_error(
+                    "transactions_manager.build_unsigned_transaction",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        let tx_sign_request =
+            serde_json::to_string(&unsigned_tx_proposal.tx_sign_req(&sender_monitor_id))
+                .map_err(|err| rpc_internal_error("serde_json.to_string", err, &self.logger))?;
+
+        let mut response = api::GenerateUnsignedTxResponse::new();
+        response.set_unsigned_tx_proposal((&unsigned_tx_proposal).into());
+        response.set_tx_sign_request(tx_sign_request);
+        Ok(response)
+    }
+
+    fn submit_signed_tx_impl(
+        &mut self,
+        request: api::SubmitSignedTxRequest,
+    ) -> Result<api::SubmitTxResponse, RpcStatus> {
+        let unsigned_tx_proposal = UnsignedTxProposal::try_from(request.get_unsigned_tx_proposal())
+            .map_err(|err| {
+                rpc_invalid_arg_error("unsigned_tx_proposal.try_from", err, &self.logger)
+            })?;
+
+        let tx_sign_response: TxSignResp = serde_json::from_str(request.get_tx_sign_response())
+            .map_err(|err| rpc_invalid_arg_error("tx_sign_response", err, &self.logger))?;
+
+        // The account id is the id of the monitor the transaction was generated for,
+        // which must own the inputs.
+        let sender_monitor_id = MonitorId::from(tx_sign_response.account_id.as_ref());
+        for (i, utxo) in unsigned_tx_proposal.utxos.iter().enumerate() {
+            let subaddress_id = self
+                .mobilecoind_db
+                .get_subaddress_id_by_utxo_id(&UtxoId::from(utxo))
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "mobilecoind_db.get_subaddress_id_by_utxo_id",
+                        err,
+                        &self.logger,
+                    )
+                })?;
+            if subaddress_id.monitor_id != sender_monitor_id {
+                return Err(RpcStatus::with_message(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    format!("unsigned_tx_proposal.input_list.{i}"),
+                ));
+            }
+        }
+
+        let tx_proposal = unsigned_tx_proposal
+            .into_tx_proposal(tx_sign_response.tx, &mut rand::thread_rng())
+            .map_err(|err| rpc_invalid_arg_error("tx_sign_response", err, &self.logger))?;
+
+        let mut submit_tx_request = api::SubmitTxRequest::new();
+        submit_tx_request.set_tx_proposal((&tx_proposal).into());
+        self.submit_tx_impl(submit_tx_request)
+    }
+
+    fn generate_mixed_tx_impl(
+        &mut self,
+        request: api::GenerateMixedTxRequest,
+    ) -> Result<api::GenerateMixedTxResponse, RpcStatus> {
         // Get sender monitor id from request.
         let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -1209,6 +1889,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 request.fee,
                 request.tombstone,
                 None, // opt_memo_builder
+                &SmallestFirst,
             )
             .map_err(|err| {
                 rpc_internal_error(
@@ -1241,6 +1922,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 TokenId::from(request.token_id),
                 &self.get_last_block_infos(),
                 request.fee,
+                MAX_INPUTS as usize,
+                None,
             )
             .map_err(|err| {
                 rpc_internal_error(
@@ -1421,6 +2104,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 request.fee,
                 request.tombstone,
                 Box::new(memo_builder),
+                &SmallestFirst,
             )
             .map_err(|err| {
                 rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
@@ -1611,6 +2295,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 &self.get_last_block_infos(),
                 request.tombstone,
                 None, // opt_memo_builder
+                &SmallestFirst,
             )
             .map_err(|err| {
                 rpc_internal_error("transactions_manager.generate_swap", err, &self.logger)
@@ -1630,6 +2315,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
             .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
 
+        // Check the resubmission policy, if any, before anything gets submitted.
+        let opt_resubmitted_tx = if request.has_resubmission_policy() {
+            Some(self.resubmitted_tx_from_request(&request, &tx_proposal)?)
+        } else {
+            None
+        };
+
         // Submit to network.
         let block_height = self
             .transactions_manager
@@ -1657,6 +2349,53 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             );
         }
 
+        // Keep track of the transaction if it is to be resubmitted. Similarly to the
+        // above, we swallow the error since the transaction was already sent to the
+        // network.
+        if let Some(mut resubmitted_tx) = opt_resubmitted_tx {
+            let result = TxAttempt::new(&tx_proposal, block_height).and_then(|attempt| {
+                resubmitted_tx.attempts.push(attempt);
+                self.mobilecoind_db.add_resubmitted_tx(&resubmitted_tx)
+            });
+            if let Err(err) = result {
+                log::error!(
+                    self.logger,
+                    "failed tracking resubmission of tx {}: {:?}",
+                    tx_proposal.tx,
+                    err
+                );
+            }
+        }
+
+        // If webhooks are enabled, keep track of the transaction so that its final
+        // status gets reported. Similarly to the above, we swallow the error
+        // since the transaction was already sent to the network.
+        if self.mobilecoind_db.webhooks_enabled() {
+            let watched_tx = WatchedTx {
+                key_images: tx_proposal
+                    .utxos
+                    .iter()
+                    .map(|utxo| utxo.key_image)
+                    .collect(),
+                tx_public_keys: tx_proposal
+                    .tx
+                    .prefix
+                    .outputs
+                    .iter()
+                    .map(|tx_out| tx_out.public_key)
+                    .collect(),
+                tombstone: tx_proposal.tx.prefix.tombstone_block,
+            };
+            if let Err(err) = self.mobilecoind_db.watch_submitted_tx(&watched_tx) {
+                log::error!(
+                    self.logger,
+                    "failed watching submitted tx {}: {:?}",
+                    tx_proposal.tx,
+                    err
+                );
+            }
+        }
+
         // Construct sender receipt.
         let mut sender_tx_receipt = api::SenderTxReceipt::new();
         sender_tx_receipt.set_key_image_list(RepeatedField::from_vec(
@@ -1934,108 +2673,30 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             })
             .collect::<Result<Vec<CompressedRistrettoPublic>, RpcStatus>>()?;
 
-        // Check the tx_public_keys in the receiver receipt, to also get the
-        // block_height. Note that if the transaction has not yet landed, the
-        // result will be a vec of LedgerDb::NotFound errors.
-        let found_pubkey_indices: Vec<u64> = compressed_pubkeys
-            .iter()
-            .map(|compressed_tx_public_key| {
-                self.ledger_db
-                    .get_tx_out_index_by_public_key(compressed_tx_public_key)
-                    .and_then(|txo_index| self.ledger_db.get_block_index_by_tx_out_index(txo_index))
-            })
-            .filter_map(Result::ok)
-            .collect();
-
-        // If we didn't find any of the tx_public_keys, then the transaction is either
-        // still pending, or the inputs were spent in another transaction and
-        // this transaction will never land.
-        if found_pubkey_indices.is_empty() {
-            // Verify that the key images are not anywhere else in the ledger.
-            let key_image_in_ledger: Vec<bool> = key_images
-                .iter()
-                .map(|key_image| {
-                    self.ledger_db.contains_key_image(key_image).map_err(|err| {
-                        rpc_internal_error("ledger_db.contains_key_image", err, &self.logger)
-                    })
-                })
-                .collect::<Result<Vec<bool>, RpcStatus>>()?;
-            if key_image_in_ledger
-                .iter()
-                .any(|key_image_in_ledger| *key_image_in_ledger)
-            {
-                let mut response = api::GetTxStatusAsSenderResponse::new();
-                response.set_status(api::TxStatus::TransactionFailureKeyImageAlreadySpent);
-                return Ok(response);
+        // Transactions that get resubmitted are looked up by their inputs, since later
+        // attempts create different outputs.
+        match self.mobilecoind_db.get_resubmitted_tx(&key_images[0]) {
+            Ok(resubmitted_tx) => return self.resubmitted_tx_status(&resubmitted_tx),
+            Err(Error::ResubmittedTxNotFound) => {}
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "mobilecoind_db.get_resubmitted_tx",
+                    err,
+                    &self.logger,
+                ))
             }
-
-            // Otherwise, the transaction is still pending or otherwise status unknown.
-            let mut response = api::GetTxStatusAsSenderResponse::new();
-            response.set_status(api::TxStatus::Unknown);
-            return Ok(response);
-        }
-
-        // Verify that all block indices are the same value. If this fails, the receipt
-        // is likely malformed, because it should be impossible to construct a
-        // transaction containing output public keys that somehow end up landing
-        // in different blocks.
-        if found_pubkey_indices.iter().min() != found_pubkey_indices.iter().max() {
-            let mut response = api::GetTxStatusAsSenderResponse::new();
-            response.set_status(api::TxStatus::PublicKeysInDifferentBlocks);
-            return Ok(response);
-        }
-
-        // Get the block in which this transaction landed.
-        let block_index = found_pubkey_indices[0];
-        let block_contents = self
-            .ledger_db
-            .get_block_contents(block_index)
-            .map_err(|err| rpc_internal_error("ledger_db.get_block_contents", err, &self.logger))?;
-
-        // Convert key images to a list of booleans indicating whether they were found
-        // in the block or not. All key_images from the same transaction should
-        // land in the same block.
-        let key_image_found: Vec<bool> = key_images
-            .iter()
-            .map(|key_image| block_contents.key_images.contains(key_image))
-            .collect::<Vec<bool>>();
-
-        // If all key images are in the block, the transaction was completed.
-        if key_image_found
-            .iter()
-            .all(|key_image_found| *key_image_found)
-        {
-            let mut response = api::GetTxStatusAsSenderResponse::new();
-            response.set_status(api::TxStatus::Verified);
-            return Ok(response);
-        }
-
-        // If only some key images found their way to the block, they were likely spent
-        // from another transaction.
-        if key_image_found
-            .iter()
-            .any(|key_image_found| *key_image_found)
-        {
-            let mut response = api::GetTxStatusAsSenderResponse::new();
-            response.set_status(api::TxStatus::TransactionFailureKeyImageBlockMismatch);
-            return Ok(response);
         }
 
-        // Check if the tombstone block was exceeded.
-        let num_blocks = self
-            .ledger_db
-            .num_blocks()
-            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
-
-        if num_blocks >= request.get_sender_tx_receipt().tombstone {
-            let mut response = api::GetTxStatusAsSenderResponse::new();
-            response.set_status(api::TxStatus::TombstoneBlockExceeded);
-            return Ok(response);
-        }
+        let status = get_tx_status_as_sender(
+            &self.ledger_db,
+            &key_images,
+            &compressed_pubkeys,
+            request.get_sender_tx_receipt().tombstone,
+        )
+        .map_err(|err| rpc_internal_error("get_tx_status_as_sender", err, &self.logger))?;
 
-        // No key images in ledger, tombstone block not yet exceeded.
         let mut response = api::GetTxStatusAsSenderResponse::new();
-        response.set_status(api::TxStatus::Unknown);
+        response.set_status(status);
         Ok(response)
     }
 
@@ -2093,7 +2754,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                                         &self.logger,
                                     )
                                 })?;
-                        let view_private_key = monitor_data.account_key.view_private_key();
+                        let view_private_key = monitor_data.view_private_key().map_err(|err| {
+                            rpc_internal_error("monitor_data.view_private_key", err, &self.logger)
+                        })?;
 
                         if request.get_receipt().get_confirmation_number().len() != 32 {
                             return Err(RpcStatus::with_message(
@@ -2110,7 +2773,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                                 .copy_from_slice(request.get_receipt().get_confirmation_number());
                             TxOutConfirmationNumber::from(confirmation_bytes)
                         };
-                        if !confirmation_number.validate(&tx_public_key, view_private_key) {
+                        if !confirmation_number.validate(&tx_public_key, &view_private_key) {
                             // If the confirmation number is invalid, this means that the
                             // transaction did get added to the ledger
                             // but the party constructing the receipt failed
@@ -2171,14 +2834,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
-        // We will use the AccountKey to compute the Address Code
-        let account_key = self
+        // We will use the MonitorData to compute the Address Code
+        let monitor_data = self
             .mobilecoind_db
             .get_monitor_data(&monitor_id)
             .map_err(|err| {
                 rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
-            })?
-            .account_key;
+            })?;
 
         // Get all processed block data for the requested block.
         let processed_tx_outs = self
@@ -2186,31 +2848,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .get_processed_block(&monitor_id, request.block)
             .map_err(|err| {
                 rpc_internal_error("mobilecoind_db.get_processed_block", err, &self.logger)
-            })?
-            .iter()
-            .map(|src| {
-                let mut dst = api::ProcessedTxOut::new();
-                dst.set_monitor_id(monitor_id.to_vec());
-                dst.set_subaddress_index(src.subaddress_index);
-                dst.set_public_key((&src.public_key).into());
-                dst.set_key_image((&src.key_image).into());
-                dst.set_value(src.value);
-                dst.set_direction(
-                    api::ProcessedTxOutDirection::from_i32(src.direction)
-                        .unwrap_or(api::ProcessedTxOutDirection::Invalid),
-                );
-
-                let subaddress = account_key.subaddress(src.subaddress_index);
-                let mut wrapper = api::printable::PrintableWrapper::new();
-                wrapper.set_public_address((&subaddress).into());
-                let encoded = wrapper
-                    .b58_encode()
-                    .map_err(|err| rpc_internal_error("wrapper.b58_encode", err, &self.logger))?;
-                dst.set_address_code(encoded);
-                dst.set_token_id(src.token_id);
-                Ok(dst)
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+            })?;
+        let processed_tx_outs = processed_tx_outs_to_proto(
+            &monitor_id,
+            &monitor_data,
+            &processed_tx_outs,
+            &self.logger,
+        )?;
 
         // Return response
         let mut response = api::GetProcessedBlockResponse::new();
@@ -2218,52 +2862,272 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
-    fn get_block_index_by_tx_pub_key_impl(
+    fn get_transaction_history_impl(
         &mut self,
-        request: api::GetBlockIndexByTxPubKeyRequest,
-    ) -> Result<api::GetBlockIndexByTxPubKeyResponse, RpcStatus> {
-        let tx_public_key = RistrettoPublic::try_from(request.get_tx_public_key())
-            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
-
-        let compressed_tx_public_key = CompressedRistrettoPublic::from(&tx_public_key);
+        request: api::GetTransactionHistoryRequest,
+    ) -> Result<api::GetTransactionHistoryResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_invalid_arg_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
-        let tx_out_index = self
-            .ledger_db
-            .get_tx_out_index_by_public_key(&compressed_tx_public_key)
+        // We will use the view private key to decrypt the memos of received TxOuts.
+        let view_private_key = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
             .map_err(|err| {
-                rpc_internal_error(
-                    "ledger_db.get_tx_out_index_by_public_key",
-                    err,
-                    &self.logger,
-                )
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?
+            .view_private_key()
+            .map_err(|err| {
+                rpc_internal_error("monitor_data.view_private_key", err, &self.logger)
             })?;
 
-        let block_index = self
-            .ledger_db
-            .get_block_index_by_tx_out_index(tx_out_index)
+        let max_events = match request.max_events {
+            0 => DEFAULT_MAX_HISTORY_EVENTS,
+            max_events => max_events as usize,
+        };
+
+        let (blocks, next_block) = self
+            .mobilecoind_db
+            .get_processed_blocks_page(&monitor_id, request.first_block, max_events)
             .map_err(|err| {
                 rpc_internal_error(
-                    "ledger_db.get_block_index_by_tx_out_index",
+                    "mobilecoind_db.get_processed_blocks_page",
                     err,
                     &self.logger,
                 )
             })?;
 
-        let mut response = api::GetBlockIndexByTxPubKeyResponse::new();
-        response.set_block(block_index);
+        let mut events = Vec::new();
+        for (block_index, processed_tx_outs) in blocks {
+            let (timestamp, timestamp_result_code) = self.get_block_timestamp(block_index);
+
+            let mut block_events = processed_tx_outs
+                .iter()
+                .map(|src| {
+                    let mut event = api::TransactionHistoryEvent::new();
+                    event.set_block(block_index);
+                    event.set_timestamp_result_code((&timestamp_result_code).into());
+                    event.set_timestamp(timestamp);
+                    event.set_direction(
+                        api::ProcessedTxOutDirection::from_i32(src.direction)
+                            .unwrap_or(api::ProcessedTxOutDirection::Invalid),
+                    );
+                    event.set_subaddress_index(src.subaddress_index);
+                    event.set_public_key((&src.public_key).into());
+                    event.set_key_image((&src.key_image).into());
+                    event.set_value(src.value);
+                    event.set_token_id(src.token_id);
+
+                    if event.get_direction() != api::ProcessedTxOutDirection::Received {
+                        return Ok(event);
+                    }
+                    if let Some(decoded_memo) =
+                        self.decode_tx_out_memo(&view_private_key, &src.public_key)?
+                    {
+                        if decoded_memo.has_authenticated_sender_memo() {
+                            event.set_counterparty_hash(
+                                decoded_memo
+                                    .get_authenticated_sender_memo()
+                                    .get_sender_hash()
+                                    .to_vec(),
+                            );
+                        } else if decoded_memo.has_destination_memo() {
+                            event.set_counterparty_hash(
+                                decoded_memo
+                                    .get_destination_memo()
+                                    .get_recipient_hash()
+                                    .to_vec(),
+                            );
+                        }
+                        event.set_decoded_memo(decoded_memo);
+                    }
+
+                    Ok(event)
+                })
+                .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+            set_spent_counterparty_hashes(&mut block_events);
+            events.extend(block_events);
+        }
+
+        // Return response
+        let mut response = api::GetTransactionHistoryResponse::new();
+        if request.csv {
+            response.set_csv(transaction_history_to_csv(&events));
+        }
+        response.set_events(RepeatedField::from_vec(events));
+        response.set_next_block(next_block);
         Ok(response)
     }
 
-    fn get_tx_out_results_by_pub_key_impl(
+    fn subscribe_processed_blocks_impl(
         &mut self,
-        request: api::GetTxOutResultsByPubKeyRequest,
-    ) -> Result<api::GetTxOutResultsByPubKeyResponse, RpcStatus> {
-        let tx_out_pub_keys = request
-            .tx_out_public_keys
-            .iter()
-            .map(CompressedRistrettoPublic::try_from)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|err| rpc_invalid_arg_error("tx_out_public_keys", err, &self.logger))?;
+        ctx: RpcContext,
+        request: api::SubscribeProcessedBlocksRequest,
+        sink: ServerStreamingSink<api::SubscribeProcessedBlocksResponse>,
+    ) {
+        let logger = rpc_logger(&ctx, &self.logger);
+
+        let receiver = match self.start_processed_blocks_subscription(request, &logger) {
+            Ok(receiver) => receiver,
+            Err(rpc_status) => {
+                let fail_logger = logger.clone();
+                ctx.spawn(async move {
+                    if let Err(err) = sink.fail(rpc_status).await {
+                        log::error!(fail_logger, "failed to reply: {:?}", err);
+                    }
+                });
+                return;
+            }
+        };
+
+        ctx.spawn(forward_processed_blocks(receiver, sink, logger));
+    }
+
+    /// Validate a subscription request and start a thread that feeds the
+    /// processed blocks of the requested monitor into the returned channel,
+    /// in order, starting at the requested block. The thread exits once the
+    /// receiving end of the channel is dropped, which happens when the
+    /// subscriber disconnects, or after sending an error.
+    fn start_processed_blocks_subscription(
+        &self,
+        request: api::SubscribeProcessedBlocksRequest,
+        logger: &Logger,
+    ) -> Result<ProcessedBlocksReceiver, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_invalid_arg_error("monitor_id.try_from.bytes", err, logger))?;
+
+        // We will use the AccountKey to compute the Address Code
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| rpc_internal_error("mobilecoind_db.get_monitor_data", err, logger))?;
+
+        if request.first_block < monitor_data.first_block {
+            return Err(rpc_invalid_arg_error(
+                "first_block",
+                Error::BlockIndexTooSmall(request.first_block, monitor_data.first_block),
+                logger,
+            ));
+        }
+
+        // The channel is bounded so that blocks are only read from the database
+        // as fast as the subscriber consumes them.
+        let (mut sender, receiver) = channel(SUBSCRIPTION_CHANNEL_SIZE);
+        let mobilecoind_db = self.mobilecoind_db.clone();
+        let mut next_block = request.first_block;
+        let thread_logger = logger.clone();
+
+        thread::Builder::new()
+            .name("subscribe_processed_blocks".to_string())
+            .spawn(move || {
+                while !sender.is_closed() {
+                    // Grab the generation before querying the database so that a block
+                    // processed in between does not get missed.
+                    let generation = mobilecoind_db.processed_block_generation();
+
+                    let result = match mobilecoind_db.get_processed_block(&monitor_id, next_block) {
+                        Ok(processed_tx_outs) => processed_tx_outs_to_proto(
+                            &monitor_id,
+                            &monitor_data,
+                            &processed_tx_outs,
+                            &thread_logger,
+                        )
+                        .map(|tx_outs| {
+                            let mut response = api::SubscribeProcessedBlocksResponse::new();
+                            response.set_monitor_id(monitor_id.to_vec());
+                            response.set_block(next_block);
+                            response.set_tx_outs(RepeatedField::from_vec(tx_outs));
+                            response
+                        }),
+
+                        // The monitor has not caught up yet, wait for it to process more blocks.
+                        Err(Error::BlockNotYetProcessed(_, _)) => {
+                            mobilecoind_db
+                                .wait_for_processed_block(generation, SUBSCRIPTION_POLL_INTERVAL);
+
+                            // Nothing gets sent to the subscriber while we wait, so probe it
+                            // to find out whether it went away.
+                            if block_on(sender.send(None)).is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+
+                        Err(err) => Err(rpc_internal_error(
+                            "mobilecoind_db.get_processed_block",
+                            err,
+                            &thread_logger,
+                        )),
+                    };
+
+                    let is_err = result.is_err();
+                    if block_on(sender.send(Some(result))).is_err() || is_err {
+                        break;
+                    }
+                    next_block += 1;
+                }
+
+                log::debug!(
+                    thread_logger,
+                    "Processed blocks subscription for monitor {} ended at block {}",
+                    monitor_id,
+                    next_block
+                );
+            })
+            .map_err(|err| rpc_internal_error("thread.spawn", err, logger))?;
+
+        Ok(receiver)
+    }
+
+    fn get_block_index_by_tx_pub_key_impl(
+        &mut self,
+        request: api::GetBlockIndexByTxPubKeyRequest,
+    ) -> Result<api::GetBlockIndexByTxPubKeyResponse, RpcStatus> {
+        let tx_public_key = RistrettoPublic::try_from(request.get_tx_public_key())
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+
+        let compressed_tx_public_key = CompressedRistrettoPublic::from(&tx_public_key);
+
+        let tx_out_index = self
+            .ledger_db
+            .get_tx_out_index_by_public_key(&compressed_tx_public_key)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "ledger_db.get_tx_out_index_by_public_key",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        let block_index = self
+            .ledger_db
+            .get_block_index_by_tx_out_index(tx_out_index)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "ledger_db.get_block_index_by_tx_out_index",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        let mut response = api::GetBlockIndexByTxPubKeyResponse::new();
+        response.set_block(block_index);
+        Ok(response)
+    }
+
+    fn get_tx_out_results_by_pub_key_impl(
+        &mut self,
+        request: api::GetTxOutResultsByPubKeyRequest,
+    ) -> Result<api::GetTxOutResultsByPubKeyResponse, RpcStatus> {
+        let tx_out_pub_keys = request
+            .tx_out_public_keys
+            .iter()
+            .map(CompressedRistrettoPublic::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| rpc_invalid_arg_error("tx_out_public_keys", err, &self.logger))?;
 
         let latest_block: mc_api::blockchain::Block = (&self
             .ledger_db
@@ -2374,7 +3238,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Get transaction memo builder.
         let transaction_memo = TransactionMemo::try_from(request.get_memo())
             .map_err(|err| rpc_invalid_arg_error("transaction_memo.try_from", err, &self.logger))?;
-        let memo_builder = transaction_memo.memo_builder(&sender_monitor_data.account_key);
+        let sender_account_key = sender_monitor_data
+            .spending_account_key()
+            .map_err(|err| rpc_invalid_arg_error("sender_monitor_id", err, &self.logger))?;
+        let memo_builder = transaction_memo.memo_builder(sender_account_key);
+
+        let utxo_selection: Box<dyn UtxoSelectionStrategy> =
+            request.get_utxo_selection_strategy().into();
 
         // Attempt to construct a transaction.
         let tx_proposal = self
@@ -2389,6 +3259,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 request.fee,
                 request.tombstone,
                 memo_builder,
+                utxo_selection.as_ref(),
             )
             .map_err(|err| {
                 rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
@@ -2446,6 +3317,110 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         self.send_payment_impl(send_payment_request)
     }
 
+    fn create_scheduled_payment_impl(
+        &mut self,
+        request: api::CreateScheduledPaymentRequest,
+    ) -> Result<api::CreateScheduledPaymentResponse, RpcStatus> {
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_invalid_arg_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let sender_monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&sender_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+
+        // Scheduled payments are signed by mobilecoind.
+        sender_monitor_data
+            .spending_account_key()
+            .map_err(|err| rpc_invalid_arg_error("sender_monitor_id", err, &self.logger))?;
+
+        // Verify subaddress falls in the range we are monitoring.
+        if !sender_monitor_data
+            .subaddress_indexes()
+            .contains(&request.sender_subaddress)
+        {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "sender_subaddress".into(),
+            ));
+        }
+
+        let receiver = PublicAddress::try_from(request.get_receiver())
+            .map_err(|err| rpc_invalid_arg_error("receiver.try_from", err, &self.logger))?;
+
+        let data = ScheduledPaymentData {
+            sender_monitor_id,
+            sender_subaddress: request.sender_subaddress,
+            receiver,
+            value: request.value,
+            token_id: request.token_id,
+            interval_blocks: request.interval_blocks,
+            interval_seconds: request.interval_seconds,
+            next_block: request.first_block,
+            next_timestamp: request.first_timestamp,
+            max_executions: request.max_executions,
+            cancelled: false,
+            executions: vec![],
+            num_pruned_executions: 0,
+        };
+        data.validate()
+            .map_err(|err| rpc_invalid_arg_error("scheduled_payment", err, &self.logger))?;
+
+        let scheduled_payment_id =
+            self.mobilecoind_db
+                .add_scheduled_payment(&data)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.add_scheduled_payment", err, &self.logger)
+                })?;
+
+        let mut response = api::CreateScheduledPaymentResponse::new();
+        response.set_scheduled_payment_id(scheduled_payment_id);
+        Ok(response)
+    }
+
+    fn get_scheduled_payment_list_impl(
+        &mut self,
+        _request: api::Empty,
+    ) -> Result<api::GetScheduledPaymentListResponse, RpcStatus> {
+        let scheduled_payments = self
+            .mobilecoind_db
+            .get_scheduled_payments()
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_scheduled_payments", err, &self.logger)
+            })?;
+
+        let mut response = api::GetScheduledPaymentListResponse::new();
+        response.set_scheduled_payment_list(
+            scheduled_payments
+                .iter()
+                .map(|(id, data)| scheduled_payment_to_api(*id, data))
+                .collect(),
+        );
+        Ok(response)
+    }
+
+    fn cancel_scheduled_payment_impl(
+        &mut self,
+        request: api::CancelScheduledPaymentRequest,
+    ) -> Result<api::Empty, RpcStatus> {
+        self.mobilecoind_db
+            .update_scheduled_payment(request.scheduled_payment_id, |data| {
+                data.cancelled = true;
+            })
+            .map_err(|err| match err {
+                Error::ScheduledPaymentNotFound => {
+                    rpc_invalid_arg_error("scheduled_payment_id", err, &self.logger)
+                }
+                err => {
+                    rpc_internal_error("mobilecoind_db.update_scheduled_payment", err, &self.logger)
+                }
+            })?;
+
+        Ok(api::Empty::new())
+    }
+
     fn get_network_status_impl(
         &mut self,
         _request: api::Empty,
@@ -2567,6 +3542,234 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .unwrap_or((u64::MAX, TimestampResultCode::WatcherDatabaseError))
     }
 
+    /// Validate the resubmission policy of a SubmitTx request, and build the
+    /// data needed to track the transaction.
+    fn resubmitted_tx_from_request(
+        &self,
+        request: &api::SubmitTxRequest,
+        tx_proposal: &TxProposal,
+    ) -> Result<ResubmittedTx, RpcStatus> {
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id).map_err(|err| {
+            rpc_invalid_arg_error("sender_monitor_id.try_from.bytes", err, &self.logger)
+        })?;
+
+        let src = request.get_resubmission_policy();
+        let policy = ResubmissionPolicy {
+            resubmit_after_blocks: src.get_resubmit_after_blocks(),
+            fee_bump_percent: src.get_fee_bump_percent(),
+            max_fee: src.get_max_fee(),
+            max_attempts: src.get_max_attempts(),
+        };
+        policy
+            .validate()
+            .map_err(|err| rpc_invalid_arg_error("resubmission_policy", err, &self.logger))?;
+
+        if !tx_proposal.scis.is_empty() {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "Transactions spending signed contingent inputs cannot be resubmitted".into(),
+            ));
+        }
+        if tx_proposal.utxos.is_empty() {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "tx_proposal.input_list".into(),
+            ));
+        }
+
+        // Make sure the monitor owns the inputs and the change subaddress.
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&sender_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+        if !monitor_data
+            .subaddress_indexes()
+            .any(|index| index == request.change_subaddress)
+        {
+            return Err(RpcStatus::with_message(
+                RpcStatusCode::INVALID_ARGUMENT,
+                "change_subaddress".into(),
+            ));
+        }
+        let account_key = monitor_data
+            .spending_account_key()
+            .map_err(|err| rpc_invalid_arg_error("sender_monitor_id", err, &self.logger))?;
+        for utxo in &tx_proposal.utxos {
+            let onetime_private_key = recover_onetime_private_key(
+                &RistrettoPublic::try_from(&utxo.tx_out.public_key).map_err(|err| {
+                    rpc_internal_error("RistrettoPublic.try_from", err, &self.logger)
+                })?,
+                account_key.view_private_key(),
+                &account_key.subaddress_spend_private(utxo.subaddress_index),
+            );
+            if KeyImage::from(&onetime_private_key) != utxo.key_image {
+                return Err(RpcStatus::with_message(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    "sender_monitor_id does not own the inputs".into(),
+                ));
+            }
+        }
+
+        Ok(ResubmittedTx {
+            policy,
+            sender_monitor_id,
+            change_subaddress: request.change_subaddress,
+            fee_token_id: tx_proposal.tx.prefix.fee_token_id,
+            utxos: tx_proposal.utxos.clone(),
+            outlays: tx_proposal
+                .outlays
+                .iter()
+                .map(|outlay| ResubmittedOutlay {
+                    value: outlay.amount.value,
+                    token_id: *outlay.amount.token_id,
+                    receiver: outlay.receiver.clone(),
+                    tx_private_key: outlay.tx_private_key,
+                })
+                .collect(),
+            attempts: vec![],
+            finished: false,
+            last_error: String::new(),
+        })
+    }
+
+    /// Report the status of every attempt of a resubmitted transaction.
+    fn resubmitted_tx_status(
+        &self,
+        resubmitted_tx: &ResubmittedTx,
+    ) -> Result<api::GetTxStatusAsSenderResponse, RpcStatus> {
+        let attempts = resubmitted_tx
+            .attempts
+            .iter()
+            .map(|attempt| {
+                let status =
+                    get_final_tx_status(&self.ledger_db, &resubmitted_tx.watched_tx(attempt))
+                        .map_err(|err| {
+                            rpc_internal_error("get_final_tx_status", err, &self.logger)
+                        })?
+                        .unwrap_or(api::TxStatus::Unknown);
+
+                let mut dst = api::TxAttempt::new();
+                dst.set_fee(attempt.fee);
+                dst.set_tombstone(attempt.tombstone);
+                dst.set_submitted_block(attempt.submitted_block);
+                dst.set_status(status);
+                dst.set_receiver_tx_receipt_list(
+                    attempt
+                        .receipts
+                        .iter()
+                        .zip(resubmitted_tx.outlays.iter())
+                        .map(|(receipt, outlay)| {
+                            let mut receiver_tx_receipt = api::ReceiverTxReceipt::new();
+                            receiver_tx_receipt.set_recipient((&outlay.receiver).into());
+                            receiver_tx_receipt.set_tx_public_key((&receipt.tx_public_key).into());
+                            receiver_tx_receipt.set_tx_out_hash(receipt.tx_out_hash.clone());
+                            receiver_tx_receipt.set_tombstone(attempt.tombstone);
+                            receiver_tx_receipt
+                                .set_confirmation_number(receipt.confirmation_number.clone());
+                            receiver_tx_receipt
+                        })
+                        .collect(),
+                );
+                Ok(dst)
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+        // The transaction is pending until either an attempt lands, or no more attempts
+        // are going to be made.
+        let status = if attempts
+            .iter()
+            .any(|attempt| attempt.get_status() == api::TxStatus::Verified)
+        {
+            api::TxStatus::Verified
+        } else if !resubmitted_tx.finished {
+            api::TxStatus::Unknown
+        } else {
+            attempts
+                .last()
+                .map(|attempt| attempt.get_status())
+                .unwrap_or(api::TxStatus::Unknown)
+        };
+
+        let mut response = api::GetTxStatusAsSenderResponse::new();
+        response.set_status(status);
+        response.set_attempts(RepeatedField::from_vec(attempts));
+        Ok(response)
+    }
+
+    /// Look up a TxOut owned by an account in the ledger, and decode its memo.
+    /// Returns None if the TxOut is no longer in the ledger, e.g. because it
+    /// was pruned.
+    fn decode_tx_out_memo(
+        &self,
+        view_private_key: &RistrettoPrivate,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<Option<api::DecodedMemo>, RpcStatus> {
+        let tx_out = match self
+            .ledger_db
+            .get_tx_out_index_by_public_key(public_key)
+            .and_then(|index| self.ledger_db.get_tx_out_by_index(index))
+        {
+            Ok(tx_out) => tx_out,
+            Err(LedgerError::NotFound) | Err(LedgerError::Pruned) => return Ok(None),
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "ledger_db.get_tx_out_by_public_key",
+                    err,
+                    &self.logger,
+                ))
+            }
+        };
+
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key)
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+        let shared_secret = get_tx_out_shared_secret(view_private_key, &tx_public_key);
+
+        Ok(Some(decode_memo(&tx_out.decrypt_memo(&shared_secret))))
+    }
+
+    /// Build the status of a tracked payment request. Expiry is judged
+    /// against the progress of the receiving monitor, so that a request does
+    /// not expire before the monitor had a chance to see the payment.
+    fn payment_request_status(
+        &self,
+        payment_id: u64,
+        data: PaymentRequestData,
+    ) -> Result<api::PaymentRequestStatus, RpcStatus> {
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&data.monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+
+        let mut status = api::PaymentRequestStatus::new();
+        status.set_payment_id(payment_id);
+        status.set_monitor_id(data.monitor_id.to_vec());
+        status.set_subaddress_index(data.subaddress_index);
+        status.set_value(data.value);
+        status.set_token_id(data.token_id);
+        status.set_expiry_block(data.expiry_block);
+        status.set_amount_received(data.amount_received);
+        status.set_tx_out_public_key_list(
+            data.tx_out_public_keys
+                .iter()
+                .map(mc_api::external::CompressedRistretto::from)
+                .collect(),
+        );
+        status.set_state(match data.status(monitor_data.next_block) {
+            PaymentRequestStatus::Pending => api::PaymentRequestState::PaymentRequestPending,
+            PaymentRequestStatus::PartiallyPaid => {
+                api::PaymentRequestState::PaymentRequestPartiallyPaid
+            }
+            PaymentRequestStatus::Paid => api::PaymentRequestState::PaymentRequestPaid,
+            PaymentRequestStatus::Expired => api::PaymentRequestState::PaymentRequestExpired,
+        });
+        status.set_memo(data.memo);
+        Ok(status)
+    }
+
     fn get_tx_out_result(
         &self,
         tx_out_pubkey: &CompressedRistrettoPublic,
@@ -2612,6 +3815,205 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     }
 }
 
+/// Convert processed TxOuts of a given monitor into their API representation.
+fn processed_tx_outs_to_proto(
+    monitor_id: &MonitorId,
+    monitor_data: &MonitorData,
+    processed_tx_outs: &[ProcessedTxOut],
+    logger: &Logger,
+) -> Result<Vec<api::ProcessedTxOut>, RpcStatus> {
+    processed_tx_outs
+        .iter()
+        .map(|src| {
+            let mut dst = api::ProcessedTxOut::new();
+            dst.set_monitor_id(monitor_id.to_vec());
+            dst.set_subaddress_index(src.subaddress_index);
+            dst.set_public_key((&src.public_key).into());
+            dst.set_key_image((&src.key_image).into());
+            dst.set_value(src.value);
+            dst.set_direction(
+                api::ProcessedTxOutDirection::from_i32(src.direction)
+                    .unwrap_or(api::ProcessedTxOutDirection::Invalid),
+            );
+
+            let subaddress = monitor_data
+                .subaddress(src.subaddress_index)
+                .map_err(|err| rpc_internal_error("monitor_data.subaddress", err, logger))?;
+            let mut wrapper = api::printable::PrintableWrapper::new();
+            wrapper.set_public_address((&subaddress).into());
+            let encoded = wrapper
+                .b58_encode()
+                .map_err(|err| rpc_internal_error("wrapper.b58_encode", err, logger))?;
+            dst.set_address_code(encoded);
+            dst.set_token_id(src.token_id);
+            Ok(dst)
+        })
+        .collect()
+}
+
+/// Spent TxOuts carry no memo of our own. The change output of the transaction
+/// that spent them does, and it lands in the same block: its destination memo
+/// records the total outlay of the transaction, so the inputs of the
+/// transaction add up to the outlay plus the change. Set the counterparty hash
+/// of the Spent events of a block to the recipient hash of the transaction
+/// whose inputs they match, leaving it empty when no transaction matches.
+fn set_spent_counterparty_hashes(events: &mut [api::TransactionHistoryEvent]) {
+    let changes = events
+        .iter()
+        .filter(|event| event.get_decoded_memo().has_destination_memo())
+        .filter_map(|event| {
+            let memo = event.get_decoded_memo().get_destination_memo();
+            let inputs_value = memo.get_total_outlay().checked_add(event.get_value())?;
+            Some((
+                event.get_token_id(),
+                inputs_value,
+                event.get_counterparty_hash().to_vec(),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut unmatched = events
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| event.get_direction() == api::ProcessedTxOutDirection::Spent)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let search = ExactMatch::default();
+    for (token_id, inputs_value, recipient_hash) in changes {
+        let mut candidates = unmatched
+            .iter()
+            .copied()
+            .filter(|index| events[*index].get_token_id() == token_id)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|index| Reverse(events[*index].get_value()));
+        let values = candidates
+            .iter()
+            .map(|index| events[*index].get_value())
+            .collect::<Vec<_>>();
+        let Some(selected) = search.search(&values, inputs_value, MAX_INPUTS as usize) else {
+            continue;
+        };
+        for position in selected {
+            let index = candidates[position];
+            events[index].set_counterparty_hash(recipient_hash.clone());
+            unmatched.retain(|unmatched_index| *unmatched_index != index);
+        }
+    }
+}
+
+/// Render transaction history events as CSV, with a header row.
+fn transaction_history_to_csv(events: &[api::TransactionHistoryEvent]) -> String {
+    let mut csv = String::from(
+        "block,timestamp,direction,subaddress_index,token_id,value,public_key,key_image,\
+         memo_type,counterparty_hash,payment_request_id,payment_intent_id,fee,total_outlay\n",
+    );
+
+    for event in events {
+        let timestamp = if event.get_timestamp_result_code()
+            == mc_api::watcher::TimestampResultCode::TimestampFound
+        {
+            event.get_timestamp().to_string()
+        } else {
+            String::new()
+        };
+        let direction = match event.get_direction() {
+            api::ProcessedTxOutDirection::Received => "received",
+            api::ProcessedTxOutDirection::Spent => "spent",
+            api::ProcessedTxOutDirection::Invalid => "invalid",
+        };
+
+        let memo = event.get_decoded_memo();
+        let (memo_type, payment_request_id, payment_intent_id, fee, total_outlay) =
+            if memo.has_authenticated_sender_memo() {
+                let asm = memo.get_authenticated_sender_memo();
+                (
+                    "authenticated_sender",
+                    asm.has_payment_request_id()
+                        .then(|| asm.get_payment_request_id()),
+                    asm.has_payment_intent_id()
+                        .then(|| asm.get_payment_intent_id()),
+                    None,
+                    None,
+                )
+            } else if memo.has_destination_memo() {
+                let dm = memo.get_destination_memo();
+                (
+                    "destination",
+                    dm.has_payment_request_id()
+                        .then(|| dm.get_payment_request_id()),
+                    dm.has_payment_intent_id()
+                        .then(|| dm.get_payment_intent_id()),
+                    Some(dm.get_fee()),
+                    Some(dm.get_total_outlay()),
+                )
+            } else if memo.has_unknown_memo() {
+                ("unknown", None, None, None, None)
+            } else {
+                ("", None, None, None, None)
+            };
+        let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            event.get_block(),
+            timestamp,
+            direction,
+            event.get_subaddress_index(),
+            event.get_token_id(),
+            event.get_value(),
+            HexFmt(event.get_public_key().get_data()),
+            HexFmt(event.get_key_image().get_data()),
+            memo_type,
+            HexFmt(event.get_counterparty_hash()),
+            optional(payment_request_id),
+            optional(payment_intent_id),
+            optional(fee),
+            optional(total_outlay),
+        ));
+    }
+
+    csv
+}
+
+/// Forward subscription responses produced by a subscription thread into a
+/// gRPC streaming sink, until either side goes away. Returning drops the
+/// receiver, which in turn stops the subscription thread.
+async fn forward_processed_blocks(
+    mut receiver: ProcessedBlocksReceiver,
+    mut sink: ServerStreamingSink<api::SubscribeProcessedBlocksResponse>,
+    logger: Logger,
+) {
+    while let Some(result) = receiver.next().await {
+        match result {
+            // The sink only reports a cancelled call when polled, so check it without
+            // waiting for it to become ready.
+            None => {
+                let poll = poll_fn(|cx| Poll::Ready(sink.poll_ready_unpin(cx))).await;
+                if let Poll::Ready(Err(err)) = poll {
+                    log::debug!(logger, "Processed blocks subscriber went away: {:?}", err);
+                    return;
+                }
+            }
+            Some(Ok(response)) => {
+                if let Err(err) = sink.send((response, WriteFlags::default())).await {
+                    log::debug!(logger, "Processed blocks subscriber went away: {:?}", err);
+                    return;
+                }
+            }
+            Some(Err(rpc_status)) => {
+                if let Err(err) = sink.fail(rpc_status).await {
+                    log::error!(logger, "failed to reply: {:?}", err);
+                }
+                return;
+            }
+        }
+    }
+
+    if let Err(err) = sink.close().await {
+        log::debug!(logger, "failed closing processed blocks stream: {:?}", err);
+    }
+}
+
 macro_rules! build_api {
     ($( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident $(,)?)+)
     =>
@@ -2633,10 +4035,87 @@ macro_rules! build_api {
                     )
                 }
             )+
+
+            fn subscribe_processed_blocks(
+                &mut self,
+                ctx: RpcContext,
+                request: api::SubscribeProcessedBlocksRequest,
+                sink: ServerStreamingSink<api::SubscribeProcessedBlocksResponse>,
+            ) {
+                self.subscribe_processed_blocks_impl(ctx, request, sink)
+            }
         }
     );
 }
 
+/// Convert a scheduled payment to its API representation.
+fn scheduled_payment_to_api(id: u64, data: &ScheduledPaymentData) -> api::ScheduledPayment {
+    let mut dst = api::ScheduledPayment::new();
+    dst.set_scheduled_payment_id(id);
+    dst.set_sender_monitor_id(data.sender_monitor_id.to_vec());
+    dst.set_sender_subaddress(data.sender_subaddress);
+    dst.set_receiver((&data.receiver).into());
+    dst.set_value(data.value);
+    dst.set_token_id(data.token_id);
+    dst.set_interval_blocks(data.interval_blocks);
+    dst.set_interval_seconds(data.interval_seconds);
+    dst.set_next_block(data.next_block);
+    dst.set_next_timestamp(data.next_timestamp);
+    dst.set_max_executions(data.max_executions);
+    dst.set_cancelled(data.cancelled);
+    dst.set_active(data.is_active());
+    dst.set_num_executions(data.num_executions());
+    dst.set_execution_list(
+        data.executions
+            .iter()
+            .map(|execution| {
+                let mut dst = api::ScheduledPaymentExecution::new();
+                dst.set_block_count(execution.block_count);
+                dst.set_timestamp(execution.timestamp);
+                dst.set_fee(execution.fee);
+                if let Some(tx) = &execution.tx {
+                    let mut sender_tx_receipt = api::SenderTxReceipt::new();
+                    sender_tx_receipt.set_key_image_list(
+                        tx.key_images
+                            .iter()
+                            .map(mc_api::external::KeyImage::from)
+                            .collect(),
+                    );
+                    sender_tx_receipt.set_tombstone(tx.tombstone);
+                    dst.set_sender_tx_receipt(sender_tx_receipt);
+
+                    if let Some(receipt) = &execution.receipt {
+                        let mut receiver_tx_receipt = api::ReceiverTxReceipt::new();
+                        receiver_tx_receipt.set_recipient((&data.receiver).into());
+                        receiver_tx_receipt.set_tx_public_key((&receipt.tx_public_key).into());
+                        receiver_tx_receipt.set_tx_out_hash(receipt.tx_out_hash.clone());
+                        receiver_tx_receipt.set_tombstone(tx.tombstone);
+                        receiver_tx_receipt
+                            .set_confirmation_number(receipt.confirmation_number.clone());
+                        dst.set_receiver_tx_receipt(receiver_tx_receipt);
+                    }
+                }
+                dst.set_state(
+                    match ScheduledPaymentExecutionStatus::try_from(execution.status) {
+                        Ok(ScheduledPaymentExecutionStatus::Verified) => {
+                            api::ScheduledPaymentExecutionState::ScheduledPaymentExecutionVerified
+                        }
+                        Ok(ScheduledPaymentExecutionStatus::Failed) | Err(_) => {
+                            api::ScheduledPaymentExecutionState::ScheduledPaymentExecutionFailed
+                        }
+                        Ok(ScheduledPaymentExecutionStatus::Pending) => {
+                            api::ScheduledPaymentExecutionState::ScheduledPaymentExecutionPending
+                        }
+                    },
+                );
+                dst.set_error(execution.error.clone());
+                dst
+            })
+            .collect(),
+    );
+    dst
+}
+
 build_api! {
     // Monitors
     add_monitor AddMonitorRequest AddMonitorResponse add_monitor_impl,
@@ -2645,6 +4124,19 @@ build_api! {
     get_monitor_status GetMonitorStatusRequest GetMonitorStatusResponse get_monitor_status_impl,
     get_unspent_tx_out_list GetUnspentTxOutListRequest GetUnspentTxOutListResponse get_unspent_tx_out_list_impl,
     get_all_unspent_tx_out GetAllUnspentTxOutRequest GetAllUnspentTxOutResponse get_all_unspent_tx_out_impl,
+    set_defragmentation_policy SetDefragmentationPolicyRequest Empty set_defragmentation_policy_impl,
+
+    // HD monitors
+    add_hd_monitor AddHdMonitorRequest AddHdMonitorResponse add_hd_monitor_impl,
+    remove_hd_monitor RemoveHdMonitorRequest Empty remove_hd_monitor_impl,
+    get_hd_monitor_status GetHdMonitorStatusRequest GetHdMonitorStatusResponse get_hd_monitor_status_impl,
+    get_hd_monitor_balance GetHdMonitorBalanceRequest GetHdMonitorBalanceResponse get_hd_monitor_balance_impl,
+
+    // Watch-only monitors
+    get_unsynced_tx_out_list GetUnsyncedTxOutListRequest GetUnsyncedTxOutListResponse get_unsynced_tx_out_list_impl,
+    sync_tx_outs SyncTxOutsRequest SyncTxOutsResponse sync_tx_outs_impl,
+    generate_unsigned_tx GenerateUnsignedTxRequest GenerateUnsignedTxResponse generate_unsigned_tx_impl,
+    submit_signed_tx SubmitSignedTxRequest SubmitTxResponse submit_signed_tx_impl,
 
     // Utilities
     generate_root_entropy Empty GenerateRootEntropyResponse generate_root_entropy_impl,
@@ -2664,6 +4156,12 @@ build_api! {
     parse_address_code ParseAddressCodeRequest ParseAddressCodeResponse parse_address_code_impl,
     create_address_code CreateAddressCodeRequest CreateAddressCodeResponse create_address_code_impl,
 
+    // Payment requests
+    add_payment_request AddPaymentRequestRequest AddPaymentRequestResponse add_payment_request_impl,
+    remove_payment_request RemovePaymentRequestRequest Empty remove_payment_request_impl,
+    get_payment_request_status GetPaymentRequestStatusRequest GetPaymentRequestStatusResponse get_payment_request_status_impl,
+    get_payment_request_list Empty GetPaymentRequestListResponse get_payment_request_list_impl,
+
     // Transactions
     get_mixins GetMixinsRequest GetMixinsResponse get_mixins_impl,
     get_membership_proofs GetMembershipProofsRequest GetMembershipProofsResponse get_membership_proofs_impl,
@@ -2687,6 +4185,7 @@ build_api! {
     get_tx_status_as_sender SubmitTxResponse GetTxStatusAsSenderResponse get_tx_status_as_sender_impl,
     get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse get_tx_status_as_receiver_impl,
     get_processed_block GetProcessedBlockRequest GetProcessedBlockResponse get_processed_block_impl,
+    get_transaction_history GetTransactionHistoryRequest GetTransactionHistoryResponse get_transaction_history_impl,
     get_block_index_by_tx_pub_key GetBlockIndexByTxPubKeyRequest GetBlockIndexByTxPubKeyResponse get_block_index_by_tx_pub_key_impl,
     get_tx_out_results_by_pub_key GetTxOutResultsByPubKeyRequest GetTxOutResultsByPubKeyResponse get_tx_out_results_by_pub_key_impl,
 
@@ -2695,6 +4194,11 @@ build_api! {
     send_payment SendPaymentRequest SendPaymentResponse send_payment_impl,
     pay_address_code PayAddressCodeRequest SendPaymentResponse pay_address_code_impl,
 
+    // Scheduled payments
+    create_scheduled_payment CreateScheduledPaymentRequest CreateScheduledPaymentResponse create_scheduled_payment_impl,
+    get_scheduled_payment_list Empty GetScheduledPaymentListResponse get_scheduled_payment_list_impl,
+    cancel_scheduled_payment CancelScheduledPaymentRequest Empty cancel_scheduled_payment_impl,
+
     // Network status
     get_network_status Empty GetNetworkStatusResponse get_network_status_impl,
 
@@ -2721,6 +4225,7 @@ mod test {
     use mc_account_keys::burn_address_view_private;
     use mc_blockchain_types::{Block, BlockVersion};
     use mc_common::{logger::test_with_logger, HashSet};
+    use mc_crypto_ring_signature_signer::LocalRingSigner;
     use mc_fog_report_validation::{FullyValidatedFogPubkey, MockFogPubkeyResolver};
     use mc_fog_report_validation_test_utils::MockFogResolver;
     use mc_ledger_db::test_utils::add_txos_and_key_images_to_ledger;
@@ -2738,10 +4243,12 @@ mod test {
         CompressedCommitment, EncryptedMemo, MaskedAmount, MaskedAmountV2, Token,
     };
     use mc_transaction_extra::{SenderMemoCredential, SignedContingentInput};
+    use mc_transaction_signer::Operations;
     use mc_util_repr_bytes::{typenum::U32, GenericArray, ReprBytes};
     use mc_util_uri::FogUri;
     use rand::{rngs::StdRng, SeedableRng};
     use std::{assert_matches::assert_matches, str::FromStr};
+    use tempfile::TempDir;
 
     const BLOCK_VERSION: BlockVersion = BlockVersion::MAX;
 
@@ -2764,7 +4271,9 @@ mod test {
         .expect("failed to create data");
 
         let mut request = api::AddMonitorRequest::new();
-        request.set_account_key(mc_api::external::AccountKey::from(&data.account_key));
+        request.set_account_key(mc_api::external::AccountKey::from(
+            data.account_key.as_ref().unwrap(),
+        ));
         request.set_first_subaddress(data.first_subaddress);
         request.set_num_subaddresses(data.num_subaddresses);
         request.set_first_block(data.first_block);
@@ -2778,7 +4287,7 @@ mod test {
         // Compare the MonitorId we got back to the value we expected.
         let monitor_id = MonitorId::try_from(&response.monitor_id)
             .expect("failed to convert response to MonitorId");
-        let expected_monitor_id = MonitorId::from(&data);
+        let expected_monitor_id = MonitorId::try_from(&data).unwrap();
 
         assert_eq!(expected_monitor_id, monitor_id);
 
@@ -2929,7 +4438,7 @@ mod test {
         // Verify the data we got matches what we expected
         assert_eq!(
             data.account_key,
-            AccountKey::try_from(status.account_key.as_ref().unwrap()).unwrap(),
+            Some(AccountKey::try_from(status.account_key.as_ref().unwrap()).unwrap()),
         );
         assert_eq!(status.first_subaddress, data.first_subaddress);
         assert_eq!(status.num_subaddresses, data.num_subaddresses);
@@ -2952,6 +4461,237 @@ mod test {
         assert!(client.get_monitor_status(&request).is_err());
     }
 
+    #[test_with_logger]
+    fn test_set_defragmentation_policy_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                10,
+                &[],
+                &[data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = MonitorId::try_from(&data).unwrap();
+
+        let mut status_request = api::GetMonitorStatusRequest::new();
+        status_request.set_monitor_id(monitor_id.to_vec());
+
+        // Monitors have no policy by default.
+        let response = client.get_monitor_status(&status_request).unwrap();
+        assert!(!response.has_defragmentation());
+
+        let mut policy = api::DefragmentationPolicy::new();
+        policy.set_token_id(0);
+        policy.set_trigger_utxo_count(30);
+        policy.set_target_utxo_count(10);
+        policy.set_fee_budget(Mob::MINIMUM_FEE * 5);
+
+        let mut request = api::SetDefragmentationPolicyRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_policy(policy.clone());
+        client.set_defragmentation_policy(&request).unwrap();
+
+        // The 10 UTXOs of the monitor are below the trigger count, so nothing happens.
+        let response = client.get_monitor_status(&status_request).unwrap();
+        let status = response.get_defragmentation();
+        assert_eq!(status.get_policy(), &policy);
+        assert!(!status.active);
+        assert_eq!(status.fees_spent, 0);
+        assert_eq!(status.remaining_budget, Mob::MINIMUM_FEE * 5);
+        assert_eq!(status.num_txs_submitted, 0);
+        assert!(!status.tx_pending);
+
+        // Invalid policies are rejected.
+        let mut invalid_policy = policy.clone();
+        invalid_policy.set_trigger_utxo_count(10);
+        request.set_policy(invalid_policy);
+        assert!(client.set_defragmentation_policy(&request).is_err());
+
+        // Policies cannot be set for unknown monitors.
+        let mut unknown_monitor_request = api::SetDefragmentationPolicyRequest::new();
+        unknown_monitor_request.set_monitor_id(vec![3; 32]);
+        unknown_monitor_request.set_policy(policy);
+        assert!(client
+            .set_defragmentation_policy(&unknown_monitor_request)
+            .is_err());
+
+        // Clearing the policy removes it, and can be repeated.
+        request.clear_policy();
+        client.set_defragmentation_policy(&request).unwrap();
+        client.set_defragmentation_policy(&request).unwrap();
+        let response = client.get_monitor_status(&status_request).unwrap();
+        assert!(!response.has_defragmentation());
+
+        // Removing a monitor removes its policy.
+        mobilecoind_db
+            .set_defragmentation_policy(
+                &monitor_id,
+                &DefragmentationPolicy {
+                    token_id: 0,
+                    trigger_utxo_count: 2,
+                    target_utxo_count: 1,
+                    fee_budget: 0,
+                },
+            )
+            .unwrap();
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        assert_matches!(
+            mobilecoind_db.get_defragmentation_data(&monitor_id),
+            Err(Error::DefragmentationPolicyNotFound)
+        );
+    }
+
+    #[test_with_logger]
+    fn test_hd_monitor_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let account_key0 = AccountKey::from(mnemonic.clone().derive_slip10_key(0));
+        let account_key1 = AccountKey::from(mnemonic.clone().derive_slip10_key(1));
+
+        // 2 known recipients, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[account_key0.subaddress(0), account_key1.subaddress(2)],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let mut request = api::AddHdMonitorRequest::new();
+        request.set_mnemonic(mnemonic.phrase().to_owned());
+        request.set_account_indices(vec![0, 1]);
+        request.set_first_subaddress(0);
+        request.set_num_subaddresses(3);
+        request.set_gap_limit(2);
+        request.set_name("hd".to_owned());
+
+        let response = client
+            .add_hd_monitor(&request)
+            .expect("failed to add hd monitor");
+        assert!(response.is_new);
+        assert_eq!(response.monitor_id_list.len(), 2);
+
+        // Each account is tracked by a regular monitor.
+        let monitor_map = mobilecoind_db.get_monitor_map().unwrap();
+        assert_eq!(monitor_map.len(), 2);
+        assert_eq!(
+            monitor_map[&MonitorId::try_from(&response.monitor_id_list[0]).unwrap()].account_key,
+            Some(account_key0)
+        );
+        assert_eq!(
+            monitor_map[&MonitorId::try_from(&response.monitor_id_list[1]).unwrap()].account_key,
+            Some(account_key1)
+        );
+
+        // Allow the new monitors to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Adding the same accounts again returns the same ids.
+        let repeated_response = client
+            .add_hd_monitor(&request)
+            .expect("failed to add hd monitor");
+        assert!(!repeated_response.is_new);
+        assert_eq!(repeated_response.hd_monitor_id, response.hd_monitor_id);
+        assert_eq!(repeated_response.monitor_id_list, response.monitor_id_list);
+
+        // Funds arriving at subaddress 2 of account 1 extend its range to 0..5.
+        let mut request = api::GetHdMonitorStatusRequest::new();
+        request.set_hd_monitor_id(response.hd_monitor_id.clone());
+        let status_response = client
+            .get_hd_monitor_status(&request)
+            .expect("failed to get hd monitor status");
+        assert_eq!(status_response.name, "hd");
+        let status_list = status_response.get_account_status_list();
+        assert_eq!(status_list.len(), 2);
+        assert_eq!(status_list[0].account_index, 0);
+        assert_eq!(status_list[0].get_status().num_subaddresses, 3);
+        assert_eq!(status_list[1].account_index, 1);
+        assert_eq!(status_list[1].get_status().num_subaddresses, 5);
+
+        // Check per-subaddress balances.
+        let expected_balance = DEFAULT_PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap();
+        let mut request = api::GetHdMonitorBalanceRequest::new();
+        request.set_hd_monitor_id(response.hd_monitor_id.clone());
+        let balance_response = client
+            .get_hd_monitor_balance(&request)
+            .expect("failed to get hd monitor balance");
+        let balance_list = balance_response.get_account_balance_list();
+        assert_eq!(balance_list.len(), 2);
+
+        assert_eq!(balance_list[0].account_index, 0);
+        assert_eq!(balance_list[0].balance, expected_balance);
+        assert_eq!(
+            balance_list[0]
+                .get_subaddress_balance_list()
+                .iter()
+                .map(|balance| (balance.subaddress_index, balance.balance))
+                .collect::<Vec<_>>(),
+            vec![(0, expected_balance), (1, 0), (2, 0)]
+        );
+
+        assert_eq!(balance_list[1].account_index, 1);
+        assert_eq!(balance_list[1].balance, expected_balance);
+        assert_eq!(
+            balance_list[1]
+                .get_subaddress_balance_list()
+                .iter()
+                .map(|balance| (balance.subaddress_index, balance.balance))
+                .collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (2, expected_balance), (3, 0), (4, 0)]
+        );
+
+        // Removing the HD monitor removes the monitors of its accounts.
+        let mut request = api::RemoveHdMonitorRequest::new();
+        request.set_hd_monitor_id(response.hd_monitor_id.clone());
+        client
+            .remove_hd_monitor(&request)
+            .expect("failed to remove hd monitor");
+        assert_eq!(mobilecoind_db.get_monitor_map().unwrap().len(), 0);
+
+        let mut request = api::GetHdMonitorStatusRequest::new();
+        request.set_hd_monitor_id(response.hd_monitor_id);
+        assert!(client.get_hd_monitor_status(&request).is_err());
+
+        // An empty list of accounts is rejected.
+        let mut request = api::AddHdMonitorRequest::new();
+        request.set_mnemonic(mnemonic.phrase().to_owned());
+        request.set_num_subaddresses(1);
+        assert!(client.add_hd_monitor(&request).is_err());
+
+        // An account that already has a regular monitor cannot be added.
+        let data = MonitorData::new(account_key1, 0, 3, 0, "").unwrap();
+        mobilecoind_db.add_monitor(&data).unwrap();
+        let mut request = api::AddHdMonitorRequest::new();
+        request.set_mnemonic(mnemonic.phrase().to_owned());
+        request.set_account_indices(vec![0, 1]);
+        request.set_first_subaddress(0);
+        request.set_num_subaddresses(3);
+        match client.add_hd_monitor(&request) {
+            Ok(_) => panic!("Should've returned an error"),
+            Err(GrpcError::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.code(), RpcStatusCode::ALREADY_EXISTS);
+                assert!(rpc_status.message().starts_with("account 1 "));
+            }
+            Err(err) => panic!("Unexpected error: {err:?}"),
+        };
+        assert_eq!(mobilecoind_db.get_monitor_map().unwrap().len(), 1);
+    }
+
     #[test_with_logger]
     fn test_get_unspent_tx_out_list_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -3238,6 +4978,167 @@ mod test {
         );
     }
 
+    #[test_with_logger]
+    fn test_watch_only_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Add a watch-only monitor.
+        let mut request = api::AddMonitorRequest::new();
+        request.set_view_account_key((&ViewAccountKey::from(&account_key)).into());
+        request.set_num_subaddresses(1);
+        let response = client.add_monitor(&request).expect("failed to add monitor");
+        let monitor_id = MonitorId::try_from(&response.monitor_id).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The monitor found one TxOut in each block, but none of them are spendable
+        // until their key images are supplied.
+        assert!(mobilecoind_db
+            .get_utxos_for_monitor(&monitor_id)
+            .unwrap()
+            .is_empty());
+
+        let mut request = api::GetUnsyncedTxOutListRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        let unsynced_txos = client
+            .get_unsynced_tx_out_list(&request)
+            .expect("failed to get unsynced tx outs")
+            .take_unsynced_tx_out_list()
+            .into_vec();
+        assert_eq!(
+            unsynced_txos.len(),
+            ledger_db.num_blocks().unwrap() as usize
+        );
+
+        // Compute the key images with the spend private key.
+        let mut request = api::SyncTxOutsRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        for unsynced_txo in &unsynced_txos {
+            let tx_out = TxOut::try_from(unsynced_txo.get_tx_out()).unwrap();
+            let onetime_private_key = recover_onetime_private_key(
+                &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+                account_key.view_private_key(),
+                &account_key.subaddress_spend_private(unsynced_txo.subaddress_index),
+            );
+
+            let mut synced_txo = api::SyncedTxOut::new();
+            synced_txo.set_tx_out_public_key((&tx_out.public_key).into());
+            synced_txo.set_key_image((&KeyImage::from(&onetime_private_key)).into());
+            request.mut_synced_tx_out_list().push(synced_txo);
+        }
+
+        let utxos: Vec<UnspentTxOut> = client
+            .sync_tx_outs(&request)
+            .expect("failed to sync tx outs")
+            .get_utxo_list()
+            .iter()
+            .map(|proto_utxo| UnspentTxOut::try_from(proto_utxo).unwrap())
+            .collect();
+        assert_eq!(utxos.len(), unsynced_txos.len());
+        assert_eq!(
+            HashSet::from_iter(utxos.iter()),
+            HashSet::from_iter(
+                mobilecoind_db
+                    .get_utxos_for_monitor(&monitor_id)
+                    .unwrap()
+                    .iter()
+            )
+        );
+
+        // Key images can only be supplied once.
+        assert!(client.sync_tx_outs(&request).is_err());
+        assert!(mobilecoind_db
+            .get_unsynced_txos(&monitor_id)
+            .unwrap()
+            .is_empty());
+
+        let outlays = vec![Outlay {
+            value: 123,
+            receiver: AccountKey::random(&mut rng).default_subaddress(),
+            tx_private_key: None,
+        }];
+
+        // mobilecoind cannot sign for a watch-only monitor.
+        let mut request = api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_input_list(RepeatedField::from_vec(
+            utxos.iter().map(api::UnspentTxOut::from).collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays.iter().map(api::Outlay::from).collect(),
+        ));
+        assert!(client.generate_tx(&request).is_err());
+
+        // Build an unsigned transaction instead, and sign it with the spend private
+        // key.
+        let mut request = api::GenerateUnsignedTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_input_list(RepeatedField::from_vec(
+            utxos.iter().map(api::UnspentTxOut::from).collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays.iter().map(api::Outlay::from).collect(),
+        ));
+        let response = client
+            .generate_unsigned_tx(&request)
+            .expect("failed to generate unsigned tx");
+        let proposal = UnsignedTxProposal::try_from(response.get_unsigned_tx_proposal()).unwrap();
+        assert_eq!(proposal.outlays.len(), 1);
+        assert_eq!(proposal.outlays[0].amount.value, 123);
+
+        // Sign it the way the mc-transaction-signer CLI does.
+        let signer_dir = TempDir::new().unwrap();
+        let tx_sign_req_path = signer_dir.path().join("tx_sign_req.json");
+        let tx_sign_resp_path = signer_dir.path().join("tx_sign_resp.json");
+        std::fs::write(&tx_sign_req_path, response.get_tx_sign_request()).unwrap();
+        Operations::sign_tx(
+            &LocalRingSigner::from(&account_key),
+            tx_sign_req_path.to_str().unwrap(),
+            tx_sign_resp_path.to_str().unwrap(),
+        )
+        .expect("failed to sign tx");
+        let tx_sign_response = std::fs::read_to_string(&tx_sign_resp_path).unwrap();
+
+        // A signed transaction that differs from the unsigned one is rejected.
+        let mut tampered: TxSignResp = serde_json::from_str(&tx_sign_response).unwrap();
+        tampered.tx.prefix.tombstone_block += 1;
+        let mut request = api::SubmitSignedTxRequest::new();
+        request.set_unsigned_tx_proposal(response.get_unsigned_tx_proposal().clone());
+        request.set_tx_sign_response(serde_json::to_string(&tampered).unwrap());
+        assert!(client.submit_signed_tx(&request).is_err());
+
+        // The signed transaction is submitted.
+        request.set_tx_sign_response(tx_sign_response);
+        let response = client
+            .submit_signed_tx(&request)
+            .expect("failed to submit signed tx");
+        let key_images: HashSet<KeyImage> = response
+            .get_sender_tx_receipt()
+            .get_key_image_list()
+            .iter()
+            .map(|key_image| KeyImage::try_from(key_image).unwrap())
+            .collect();
+        assert_eq!(
+            key_images,
+            HashSet::from_iter(proposal.utxos.iter().map(|utxo| utxo.key_image))
+        );
+        assert_eq!(response.get_receiver_tx_receipt_list().len(), 1);
+    }
+
     #[test_with_logger]
     fn test_generate_root_entropy_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -4428,6 +6329,278 @@ mod test {
         assert!(client.get_processed_block(&request).is_err());
     }
 
+    #[test_with_logger]
+    fn test_subscribe_processed_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            1,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let num_blocks = ledger_db.num_blocks().expect("failed getting num blocks");
+
+        // Subscribing from a block before the monitor's first block should fail.
+        let mut request = api::SubscribeProcessedBlocksRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_first_block(0);
+
+        let mut stream = client.subscribe_processed_blocks(&request).unwrap();
+        assert!(block_on(stream.next()).unwrap().is_err());
+
+        // Subscribing with an unknown monitor id should fail.
+        let mut request = api::SubscribeProcessedBlocksRequest::new();
+        request.set_monitor_id(vec![1; 32]);
+        request.set_first_block(1);
+
+        let mut stream = client.subscribe_processed_blocks(&request).unwrap();
+        assert!(block_on(stream.next()).unwrap().is_err());
+
+        // Subscribing from the middle of the already-processed range should return
+        // every block from that point on, in order, without gaps.
+        let first_block = 5;
+        let mut request = api::SubscribeProcessedBlocksRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_first_block(first_block);
+
+        let mut stream = client.subscribe_processed_blocks(&request).unwrap();
+
+        for block_index in first_block..num_blocks {
+            let response = block_on(stream.next()).unwrap().unwrap();
+            assert_eq!(response.get_monitor_id().to_vec(), monitor_id.to_vec());
+            assert_eq!(response.get_block(), block_index);
+
+            // We expect one utxo per block for our monitor, and it should match what
+            // GetProcessedBlock returns.
+            let mut request = api::GetProcessedBlockRequest::new();
+            request.set_monitor_id(monitor_id.to_vec());
+            request.set_block(block_index);
+            let expected = client.get_processed_block(&request).unwrap();
+
+            assert_eq!(response.get_tx_outs().len(), 1);
+            assert_eq!(response.get_tx_outs(), expected.get_tx_outs());
+            assert_eq!(
+                response.get_tx_outs()[0].get_direction(),
+                api::ProcessedTxOutDirection::Received
+            );
+        }
+
+        // Add a block spending one of our utxos, it should get pushed to the stream
+        // once the monitor processes it.
+        let spent_tx_out = ledger_db.get_block_contents(1).unwrap().outputs[3].clone();
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&spent_tx_out.public_key).unwrap(),
+            account_key.view_private_key(),
+            &account_key.subaddress_spend_private(0),
+        );
+        let spent_key_image = KeyImage::from(&onetime_private_key);
+
+        add_block_to_ledger(
+            &mut ledger_db,
+            BLOCK_VERSION,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+            &[spent_key_image],
+            &mut rng,
+        )
+        .unwrap();
+
+        let response = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(response.get_block(), num_blocks);
+
+        let tx_outs = response.get_tx_outs();
+        assert_eq!(tx_outs.len(), 1);
+        assert_eq!(tx_outs[0].get_key_image(), &(&spent_key_image).into());
+        assert_eq!(
+            tx_outs[0].get_direction(),
+            api::ProcessedTxOutDirection::Spent
+        );
+    }
+
+    #[test_with_logger]
+    fn test_get_transaction_history(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([25u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            1,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[account_key.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let num_blocks = ledger_db.num_blocks().expect("failed getting num blocks");
+
+        // The first page should hold one received TxOut per block, starting at the
+        // monitor's first block.
+        let mut request = api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_first_block(0);
+        request.set_max_events(3);
+        request.set_csv(true);
+
+        let response = client.get_transaction_history(&request).unwrap();
+        let events = response.get_events();
+        assert_eq!(events.len(), 3);
+        for (event, block_index) in events.iter().zip(1..) {
+            assert_eq!(event.get_block(), block_index);
+            assert_eq!(
+                event.get_direction(),
+                api::ProcessedTxOutDirection::Received
+            );
+            assert_eq!(event.get_value(), DEFAULT_PER_RECIPIENT_AMOUNT);
+            assert_eq!(event.get_token_id(), *Mob::ID);
+            assert!(event.has_decoded_memo());
+            assert_eq!(
+                event.get_timestamp_result_code(),
+                mc_api::watcher::TimestampResultCode::WatcherDatabaseError
+            );
+        }
+        assert_eq!(response.get_next_block(), 4);
+
+        let csv_lines = response.get_csv().lines().collect::<Vec<_>>();
+        assert_eq!(csv_lines.len(), 4);
+        assert!(csv_lines[0].starts_with("block,timestamp,direction,"));
+        assert!(csv_lines[1].starts_with("1,,received,"));
+
+        // The second page should hold the rest of the history, and point at the
+        // next block the monitor is going to process.
+        let mut request = api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_first_block(response.get_next_block());
+
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.get_events().len() as u64, num_blocks - 4);
+        assert_eq!(response.get_next_block(), num_blocks);
+        assert!(response.get_csv().is_empty());
+
+        // Spending one of our TxOuts should show up as a spent event.
+        let spent_tx_out = ledger_db.get_block_contents(1).unwrap().outputs[3].clone();
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&spent_tx_out.public_key).unwrap(),
+            account_key.view_private_key(),
+            &account_key.subaddress_spend_private(0),
+        );
+        let spent_key_image = KeyImage::from(&onetime_private_key);
+
+        add_block_to_ledger(
+            &mut ledger_db,
+            BLOCK_VERSION,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            Amount::new(DEFAULT_PER_RECIPIENT_AMOUNT, Mob::ID),
+            &[spent_key_image],
+            &mut rng,
+        )
+        .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut request = api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_first_block(num_blocks);
+
+        let response = client.get_transaction_history(&request).unwrap();
+        let events = response.get_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].get_block(), num_blocks);
+        assert_eq!(
+            events[0].get_direction(),
+            api::ProcessedTxOutDirection::Spent
+        );
+        assert_eq!(events[0].get_key_image(), &(&spent_key_image).into());
+        assert!(!events[0].has_decoded_memo());
+        assert!(events[0].get_counterparty_hash().is_empty());
+        assert_eq!(response.get_next_block(), num_blocks + 1);
+
+        // Query with an unknown monitor id.
+        let mut request = api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(vec![1; 32]);
+
+        assert!(client.get_transaction_history(&request).is_err());
+    }
+
+    #[test]
+    fn test_set_spent_counterparty_hashes() {
+        let spent = |value: u64| {
+            let mut event = api::TransactionHistoryEvent::new();
+            event.set_direction(api::ProcessedTxOutDirection::Spent);
+            event.set_value(value);
+            event.set_token_id(*Mob::ID);
+            event
+        };
+        let change = |value: u64, total_outlay: u64, recipient_hash: u8| {
+            let mut memo = api::DestinationMemo::new();
+            memo.set_recipient_hash(vec![recipient_hash; 16]);
+            memo.set_total_outlay(total_outlay);
+            let mut decoded_memo = api::DecodedMemo::new();
+            decoded_memo.set_destination_memo(memo);
+
+            let mut event = api::TransactionHistoryEvent::new();
+            event.set_direction(api::ProcessedTxOutDirection::Received);
+            event.set_value(value);
+            event.set_token_id(*Mob::ID);
+            event.set_counterparty_hash(vec![recipient_hash; 16]);
+            event.set_decoded_memo(decoded_memo);
+            event
+        };
+
+        // Two transactions in the same block: one spending 300 + 200 with 50 of
+        // change, and one spending 700 with 100 of change. The 1000 input matches
+        // neither.
+        let mut events = vec![
+            spent(300),
+            spent(1000),
+            spent(700),
+            spent(200),
+            change(50, 450, 1),
+            change(100, 600, 2),
+        ];
+        set_spent_counterparty_hashes(&mut events);
+
+        let counterparty_hashes = events[..4]
+            .iter()
+            .map(|event| event.get_counterparty_hash().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counterparty_hashes,
+            vec![vec![1; 16], vec![], vec![2; 16], vec![1; 16]]
+        );
+    }
+
     #[test_with_logger]
     /// Get mixins should return the correct number of distinct mixins.
     fn test_get_mixins(logger: Logger) {
@@ -4888,7 +7061,7 @@ mod test {
             .unwrap();
 
             let mut request = request.clone();
-            request.set_sender_monitor_id(MonitorId::from(&data).to_vec());
+            request.set_sender_monitor_id(MonitorId::try_from(&data).unwrap().to_vec());
             assert!(client.generate_swap(&request).is_err());
         }
 
@@ -5239,7 +7412,7 @@ mod test {
             .unwrap();
 
             let mut request = request.clone();
-            request.set_sender_monitor_id(MonitorId::from(&data).to_vec());
+            request.set_sender_monitor_id(MonitorId::try_from(&data).unwrap().to_vec());
             assert!(client.generate_tx(&request).is_err());
         }
 
@@ -5429,6 +7602,103 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    fn test_generate_tx_utxo_selection_strategy(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                3,
+                &[sender.default_subaddress()],
+                &[],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Get list of unspent tx outs
+        let mut utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(utxos.len() > 2);
+
+        let receiver = AccountKey::random(&mut rng);
+        let generate_tx =
+            |utxos: &[UnspentTxOut], value: u64, strategy: api::UtxoSelectionStrategy| -> Tx {
+                let outlays = [Outlay {
+                    value,
+                    receiver: receiver.default_subaddress(),
+                    tx_private_key: None,
+                }];
+
+                let mut request = api::GenerateTxRequest::new();
+                request.set_sender_monitor_id(monitor_id.to_vec());
+                request.set_change_subaddress(0);
+                request.set_input_list(RepeatedField::from_vec(
+                    utxos.iter().map(api::UnspentTxOut::from).collect(),
+                ));
+                request.set_outlay_list(RepeatedField::from_vec(
+                    outlays.iter().map(api::Outlay::from).collect(),
+                ));
+                request.set_utxo_selection_strategy(strategy);
+
+                let response = client.generate_tx(&request).unwrap();
+                Tx::try_from(response.get_tx_proposal().get_tx()).unwrap()
+            };
+
+        // Spending exactly two UTXOs worth of value (minus the fee) does not require a
+        // change output.
+        let tx = generate_tx(
+            &utxos,
+            2 * test_utils::DEFAULT_PER_RECIPIENT_AMOUNT - Mob::MINIMUM_FEE,
+            api::UtxoSelectionStrategy::ExactMatch,
+        );
+        assert_eq!(tx.prefix.inputs.len(), 2);
+        assert_eq!(tx.prefix.outputs.len(), 1);
+
+        // Mark all but the last UTXO as part of a transaction that could still land.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let num_utxos = utxos.len();
+        for utxo in utxos.iter_mut().take(num_utxos - 1) {
+            utxo.attempted_spend_height = num_blocks;
+            utxo.attempted_spend_tombstone = num_blocks + 10;
+        }
+
+        // Only the last UTXO should get used, since it is enough to cover the value.
+        let tx = generate_tx(
+            &utxos,
+            123,
+            api::UtxoSelectionStrategy::AvoidRecentlyAttempted,
+        );
+        assert_eq!(tx.key_images(), vec![utxos[num_utxos - 1].key_image]);
+
+        // When it is not enough, the other UTXOs get used as well.
+        let tx = generate_tx(
+            &utxos,
+            test_utils::DEFAULT_PER_RECIPIENT_AMOUNT,
+            api::UtxoSelectionStrategy::AvoidRecentlyAttempted,
+        );
+        assert_eq!(tx.prefix.inputs.len(), 2);
+        assert!(tx.key_images().contains(&utxos[num_utxos - 1].key_image));
+    }
+
     #[test_with_logger]
     fn test_generate_mixed_tx(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -6166,10 +8436,7 @@ mod test {
         assert_eq!(tx_proposal.tx.prefix.inputs.len(), expected_num_inputs);
 
         assert_eq!(tx_proposal.outlays.len(), 1);
-        assert_eq!(
-            tx_proposal.outlays[0].receiver,
-            data.account_key.subaddress(0)
-        );
+        assert_eq!(tx_proposal.outlays[0].receiver, data.subaddress(0).unwrap());
         assert_eq!(
             tx_proposal.outlays[0].amount.value,
             // Each UTXO we have has PER_RECIPIENT_AMOUNT coins. We will be merging MAX_INPUTS of
@@ -6185,7 +8452,7 @@ mod test {
         let tx_out = &tx_proposal.tx.prefix.outputs[0];
         let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
         let shared_secret =
-            get_tx_out_shared_secret(data.account_key.view_private_key(), &tx_public_key);
+            get_tx_out_shared_secret(&data.view_private_key().unwrap(), &tx_public_key);
         let (amount, _blinding) = tx_out
             .get_masked_amount()
             .unwrap()
@@ -7367,6 +9634,202 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    fn test_payment_request(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            5,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 3 random recipients and one monitor.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(BLOCK_VERSION, 3, &[], &[data.clone()], logger, &mut rng);
+        let monitor_id = MonitorId::try_from(&data).unwrap();
+
+        // Add a payment request with a chosen payment id.
+        let mut request = api::AddPaymentRequestRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_subaddress_index(2);
+        request.set_value(1234);
+        request.set_token_id(1);
+        request.set_memo("invoice".to_owned());
+        request.set_payment_id(42);
+        request.set_expiry_block(ledger_db.num_blocks().unwrap() + 10);
+        let response = client.add_payment_request(&request).unwrap();
+        assert_eq!(response.payment_id, 42);
+
+        // The request code carries the payment id.
+        let mut parse_request = api::ParseRequestCodeRequest::new();
+        parse_request.set_b58_code(response.b58_code.clone());
+        let parse_response = client.parse_request_code(&parse_request).unwrap();
+        assert_eq!(
+            PublicAddress::try_from(parse_response.get_receiver()).unwrap(),
+            account_key.subaddress(2)
+        );
+        assert_eq!(parse_response.value, 1234);
+        assert_eq!(parse_response.token_id, 1);
+        assert_eq!(parse_response.get_memo(), "invoice");
+        assert_eq!(parse_response.payment_id, 42);
+
+        // The same payment id cannot be tracked twice.
+        assert!(client.add_payment_request(&request).is_err());
+
+        // Subaddresses outside of the monitored range are rejected.
+        request.set_subaddress_index(5);
+        request.set_payment_id(43);
+        assert!(client.add_payment_request(&request).is_err());
+
+        // A random payment id is picked when none is given.
+        request.set_subaddress_index(0);
+        request.set_payment_id(0);
+        request.set_expiry_block(1);
+        let random_id = client.add_payment_request(&request).unwrap().payment_id;
+        assert_ne!(random_id, 0);
+
+        // Check the status of the requests.
+        let mut status_request = api::GetPaymentRequestStatusRequest::new();
+        status_request.set_payment_id(42);
+        let status_response = client.get_payment_request_status(&status_request).unwrap();
+        let status = status_response.get_status();
+        assert_eq!(status.payment_id, 42);
+        assert_eq!(status.monitor_id, monitor_id.to_vec());
+        assert_eq!(status.subaddress_index, 2);
+        assert_eq!(status.value, 1234);
+        assert_eq!(status.amount_received, 0);
+        assert_eq!(
+            status.state,
+            api::PaymentRequestState::PaymentRequestPending
+        );
+
+        let list_response = client.get_payment_request_list(&api::Empty::new()).unwrap();
+        let states: HashMap<u64, api::PaymentRequestState> = list_response
+            .get_status_list()
+            .iter()
+            .map(|status| (status.payment_id, status.state))
+            .collect();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[&42], api::PaymentRequestState::PaymentRequestPending);
+        // The monitor is past the expiry block of the second request.
+        assert_eq!(
+            states[&random_id],
+            api::PaymentRequestState::PaymentRequestExpired
+        );
+
+        // Remove a request.
+        let mut remove_request = api::RemovePaymentRequestRequest::new();
+        remove_request.set_payment_id(42);
+        client.remove_payment_request(&remove_request).unwrap();
+        assert!(client.get_payment_request_status(&status_request).is_err());
+        assert!(client.remove_payment_request(&remove_request).is_err());
+
+        // Removing the monitor stops tracking its requests.
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        assert!(client
+            .get_payment_request_list(&api::Empty::new())
+            .unwrap()
+            .get_status_list()
+            .is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_scheduled_payments(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                BLOCK_VERSION,
+                10,
+                &[],
+                &[data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = MonitorId::try_from(&data).unwrap();
+
+        let mut request = api::CreateScheduledPaymentRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_receiver((&receiver).into());
+        request.set_value(100);
+        request.set_interval_seconds(86400);
+        request.set_first_timestamp(1_700_000_000);
+        request.set_max_executions(12);
+        let response = client.create_scheduled_payment(&request).unwrap();
+        assert_eq!(response.scheduled_payment_id, 1);
+
+        let list = client
+            .get_scheduled_payment_list(&api::Empty::new())
+            .unwrap();
+        assert_eq!(list.get_scheduled_payment_list().len(), 1);
+        let scheduled_payment = &list.get_scheduled_payment_list()[0];
+        assert_eq!(scheduled_payment.scheduled_payment_id, 1);
+        assert_eq!(
+            scheduled_payment.get_sender_monitor_id(),
+            monitor_id.as_bytes()
+        );
+        assert_eq!(
+            PublicAddress::try_from(scheduled_payment.get_receiver()).unwrap(),
+            receiver
+        );
+        assert_eq!(scheduled_payment.value, 100);
+        assert_eq!(scheduled_payment.interval_seconds, 86400);
+        assert_eq!(scheduled_payment.next_timestamp, 1_700_000_000);
+        assert_eq!(scheduled_payment.max_executions, 12);
+        assert!(scheduled_payment.active);
+        assert!(scheduled_payment.get_execution_list().is_empty());
+
+        // Invalid schedules are rejected.
+        let mut invalid_request = request.clone();
+        invalid_request.set_interval_blocks(10);
+        assert!(client.create_scheduled_payment(&invalid_request).is_err());
+
+        let mut invalid_request = request.clone();
+        invalid_request.set_sender_subaddress(1);
+        assert!(client.create_scheduled_payment(&invalid_request).is_err());
+
+        let mut invalid_request = request.clone();
+        invalid_request.set_sender_monitor_id(vec![3; 32]);
+        assert!(client.create_scheduled_payment(&invalid_request).is_err());
+
+        // Cancelled payments are kept, but no longer active.
+        let mut cancel_request = api::CancelScheduledPaymentRequest::new();
+        cancel_request.set_scheduled_payment_id(1);
+        client.cancel_scheduled_payment(&cancel_request).unwrap();
+        let list = client
+            .get_scheduled_payment_list(&api::Empty::new())
+            .unwrap();
+        let scheduled_payment = &list.get_scheduled_payment_list()[0];
+        assert!(scheduled_payment.cancelled);
+        assert!(!scheduled_payment.active);
+
+        cancel_request.set_scheduled_payment_id(2);
+        assert!(client.cancel_scheduled_payment(&cancel_request).is_err());
+
+        // Removing the monitor removes its scheduled payments.
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        assert!(client
+            .get_scheduled_payment_list(&api::Empty::new())
+            .unwrap()
+            .get_scheduled_payment_list()
+            .is_empty());
+    }
+
     #[test_with_logger]
     fn test_transfer_code_root_entropy(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -7701,7 +10164,9 @@ mod test {
             );
 
         let mut request = api::AddMonitorRequest::new();
-        request.set_account_key(mc_api::external::AccountKey::from(&data.account_key));
+        request.set_account_key(mc_api::external::AccountKey::from(
+            data.account_key.as_ref().unwrap(),
+        ));
         request.set_first_subaddress(data.first_subaddress);
         request.set_num_subaddresses(data.num_subaddresses);
         request.set_first_block(data.first_block);
@@ -7790,7 +10255,9 @@ mod test {
 
         // Re-add the monitor.
         let mut request = api::AddMonitorRequest::new();
-        request.set_account_key(mc_api::external::AccountKey::from(&data.account_key));
+        request.set_account_key(mc_api::external::AccountKey::from(
+            data.account_key.as_ref().unwrap(),
+        ));
         request.set_first_subaddress(data.first_subaddress);
         request.set_num_subaddresses(data.num_subaddresses);
         request.set_first_block(data.first_block);
@@ -25,6 +25,7 @@ use crate::{
     error::Error,
     monitor_store::{MonitorData, MonitorId},
     subaddress_store::SubaddressSPKId,
+    unsynced_txo_store::UnsyncedTxOut,
     utxo_store::UnspentTxOut,
 };
 use mc_common::{
@@ -331,7 +332,7 @@ fn sync_monitor(
     let parallel_results = (0..num_blocks_to_load)
         .into_par_iter()
         .map(
-            |worker_idx| -> Result<(Vec<UnspentTxOut>, Vec<KeyImage>), Error> {
+            |worker_idx| -> Result<(Vec<UnspentTxOut>, Vec<UnsyncedTxOut>, Vec<KeyImage>), Error> {
                 // We don't expect to get Error::NotFound here, since we earlier tested
                 // ledger_db.num_blocks(). The other error cases are not common
                 // either.
@@ -348,7 +349,7 @@ fn sync_monitor(
                 );
 
                 // Match tx outs into UTXOs.
-                let utxos = match_tx_outs_into_utxos(
+                let (utxos, unsynced_txos) = match_tx_outs_into_utxos(
                     mobilecoind_db,
                     &block_contents.outputs,
                     monitor_id,
@@ -356,10 +357,10 @@ fn sync_monitor(
                     logger,
                 )?;
 
-                Ok((utxos, block_contents.key_images))
+                Ok((utxos, unsynced_txos, block_contents.key_images))
             },
         )
-        .collect::<Vec<Result<(Vec<UnspentTxOut>, Vec<KeyImage>), Error>>>();
+        .collect::<Vec<Result<(Vec<UnspentTxOut>, Vec<UnsyncedTxOut>, Vec<KeyImage>), Error>>>();
 
     // For diagnostics, we want to keep track of which workers were successful.
     // Usually it should be all of them, but if sometimes a worker in the middle
@@ -371,12 +372,13 @@ fn sync_monitor(
 
     // Now add everything to the database
     for (worker_idx, result) in parallel_results.into_iter().enumerate() {
-        if let Err(err) = result.and_then(|(utxos, key_images)| {
+        if let Err(err) = result.and_then(|(utxos, unsynced_txos, key_images)| {
             // Update database.
             mobilecoind_db.block_processed(
                 monitor_id,
                 monitor_data.next_block + worker_idx as u64,
                 &utxos,
+                &unsynced_txos,
                 &key_images,
             )
         }) {
@@ -399,16 +401,19 @@ fn sync_monitor(
 }
 
 /// Helper function for matching a list of TxOuts to a given monitor.
+///
+/// Watch-only monitors cannot compute key images, so the TxOuts they receive
+/// are returned separately as unsynced TxOuts.
 fn match_tx_outs_into_utxos(
     mobilecoind_db: &Database,
     outputs: &[TxOut],
     monitor_id: &MonitorId,
     monitor_data: &MonitorData,
     logger: &Logger,
-) -> Result<Vec<UnspentTxOut>, Error> {
-    let account_key = &monitor_data.account_key;
+) -> Result<(Vec<UnspentTxOut>, Vec<UnsyncedTxOut>), Error> {
+    let view_private_key = monitor_data.view_private_key()?;
     // Iterate over each output and filter the results using a parallel iterator.
-    let results: Result<Vec<UnspentTxOut>, Error> = outputs
+    let results: Result<Vec<(UnsyncedTxOut, Option<KeyImage>)>, Error> = outputs
         .into_par_iter()
         .filter_map(|tx_out| {
             // Convert target and public keys to RistrettoPublic type.
@@ -417,7 +422,7 @@ fn match_tx_outs_into_utxos(
 
             // Generate subaddress spend public key for tx_out.
             let subaddress_spk = SubaddressSPKId::from(&recover_public_subaddress_spend_key(
-                account_key.view_private_key(),
+                &view_private_key,
                 &tx_out_target_key,
                 &tx_public_key,
             ));
@@ -443,8 +448,7 @@ fn match_tx_outs_into_utxos(
             assert_eq!(monitor_id, &subaddress_id.monitor_id);
 
             // Generate the shared secret between the subaddress and output public key.
-            let shared_secret =
-                get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+            let shared_secret = get_tx_out_shared_secret(&view_private_key, &tx_public_key);
 
             // Get the amount and blinding factor for the output.
             let (amount, _blinding) = tx_out
@@ -454,33 +458,43 @@ fn match_tx_outs_into_utxos(
                 .expect("Malformed amount"); // TODO
 
             // Recover the onetime private key using the account and subaddress spend
-            // private keys.
-            let onetime_private_key = recover_onetime_private_key(
-                &tx_public_key,
-                account_key.view_private_key(),
-                &account_key.subaddress_spend_private(subaddress_id.index),
-            );
-
-            // Generate the key image from the onetime private key.
-            let key_image = KeyImage::from(&onetime_private_key);
+            // private keys, and generate the key image from it. This is only possible
+            // when the monitor has the spend private key.
+            let key_image = monitor_data.account_key.as_ref().map(|account_key| {
+                let onetime_private_key = recover_onetime_private_key(
+                    &tx_public_key,
+                    account_key.view_private_key(),
+                    &account_key.subaddress_spend_private(subaddress_id.index),
+                );
+                KeyImage::from(&onetime_private_key)
+            });
 
             let memo_payload = tx_out.decrypt_memo(&shared_secret).into();
 
-            // Construct a new unspent transaction output.
-            Some(Ok(UnspentTxOut {
+            let unsynced_txo = UnsyncedTxOut {
                 tx_out: tx_out.clone(),
                 subaddress_index: subaddress_id.index,
-                key_image,
                 value: amount.value,
-                attempted_spend_height: 0,
-                attempted_spend_tombstone: 0,
                 token_id: *amount.token_id,
                 memo_payload,
-            }))
+            };
+
+            Some(Ok((unsynced_txo, key_image)))
         })
         .collect();
 
-    results
+    // Construct new unspent transaction outputs for the TxOuts whose key image is
+    // known.
+    let mut utxos = Vec::new();
+    let mut unsynced_txos = Vec::new();
+    for (unsynced_txo, key_image) in results? {
+        match key_image {
+            Some(key_image) => utxos.push(unsynced_txo.into_utxo(key_image)),
+            None => unsynced_txos.push(unsynced_txo),
+        }
+    }
+
+    Ok((utxos, unsynced_txos))
 }
 
 #[cfg(test)]
@@ -515,7 +529,7 @@ mod test {
         )
         .unwrap();
 
-        let monitor_id = MonitorId::from(&data);
+        let monitor_id = MonitorId::try_from(&data).unwrap();
 
         let recipients: Vec<PublicAddress> = account_keys
             .iter()
@@ -733,7 +747,7 @@ mod test {
         )
         .unwrap();
 
-        let monitor_id = MonitorId::from(&data);
+        let monitor_id = MonitorId::try_from(&data).unwrap();
 
         let recipients: Vec<PublicAddress> = account_keys
             .iter()
@@ -954,7 +968,7 @@ mod test {
         )
         .unwrap();
 
-        let monitor_id = MonitorId::from(&data);
+        let monitor_id = MonitorId::try_from(&data).unwrap();
 
         let recipients: Vec<PublicAddress> = account_keys
             .iter()
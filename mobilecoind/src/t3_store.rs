@@ -106,7 +106,7 @@ impl T3Store {
         };
 
         let our_short_address_hash =
-            ShortAddressHash::from(&monitor_data.account_key.subaddress(utxo.subaddress_index));
+            ShortAddressHash::from(&monitor_data.subaddress(utxo.subaddress_index)?);
 
         let (sender_address_hash, recipient_address_hash) = match memo_type {
             MemoType::AuthenticatedSender(contents) => {
@@ -111,7 +111,7 @@ pub fn get_test_monitor_data_and_id(
     )
     .unwrap();
 
-    let monitor_id = MonitorId::from(&data);
+    let monitor_id = MonitorId::try_from(&data).unwrap();
     (data, monitor_id)
 }
 
@@ -169,6 +169,7 @@ pub fn setup_server<FPR: FogPubkeyResolver + Default + Send + Sync + 'static>(
         mobilecoind_db.clone(),
         conn_manager.clone(),
         fog_resolver_factory.unwrap_or_else(|| Arc::new(|_| Ok(FPR::default()))),
+        Default::default(),
         logger.clone(),
     );
 
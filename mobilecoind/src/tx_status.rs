@@ -0,0 +1,98 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Logic for figuring out the status of a submitted transaction by looking at
+//! the local ledger.
+
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_ledger_db::{Error as LedgerError, Ledger};
+use mc_mobilecoind_api::TxStatus;
+use mc_transaction_core::ring_signature::KeyImage;
+
+/// Get the status of a submitted transaction, from the point of view of the
+/// sender.
+///
+/// # Arguments
+/// * `ledger_db` - The ledger to look at.
+/// * `key_images` - Key images of the inputs spent by the transaction.
+/// * `tx_public_keys` - Public keys of the outputs created by the transaction.
+/// * `tombstone` - The tombstone block of the transaction.
+pub fn get_tx_status_as_sender(
+    ledger_db: &impl Ledger,
+    key_images: &[KeyImage],
+    tx_public_keys: &[CompressedRistrettoPublic],
+    tombstone: u64,
+) -> Result<TxStatus, LedgerError> {
+    // Check the tx_public_keys, to also get the block_height. Note that if the
+    // transaction has not yet landed, the result will be a vec of
+    // LedgerDb::NotFound errors.
+    let found_pubkey_indices: Vec<u64> = tx_public_keys
+        .iter()
+        .map(|compressed_tx_public_key| {
+            ledger_db
+                .get_tx_out_index_by_public_key(compressed_tx_public_key)
+                .and_then(|txo_index| ledger_db.get_block_index_by_tx_out_index(txo_index))
+        })
+        .filter_map(Result::ok)
+        .collect();
+
+    // If we didn't find any of the tx_public_keys, then the transaction is either
+    // still pending, or the inputs were spent in another transaction and
+    // this transaction will never land.
+    if found_pubkey_indices.is_empty() {
+        // Verify that the key images are not anywhere else in the ledger.
+        for key_image in key_images {
+            if ledger_db.contains_key_image(key_image)? {
+                return Ok(TxStatus::TransactionFailureKeyImageAlreadySpent);
+            }
+        }
+
+        // Otherwise, the transaction is still pending or otherwise status unknown.
+        return Ok(TxStatus::Unknown);
+    }
+
+    // Verify that all block indices are the same value. If this fails, the receipt
+    // is likely malformed, because it should be impossible to construct a
+    // transaction containing output public keys that somehow end up landing
+    // in different blocks.
+    if found_pubkey_indices.iter().min() != found_pubkey_indices.iter().max() {
+        return Ok(TxStatus::PublicKeysInDifferentBlocks);
+    }
+
+    // Get the block in which this transaction landed.
+    let block_index = found_pubkey_indices[0];
+    let block_contents = ledger_db.get_block_contents(block_index)?;
+
+    // Convert key images to a list of booleans indicating whether they were found
+    // in the block or not. All key_images from the same transaction should
+    // land in the same block.
+    let key_image_found: Vec<bool> = key_images
+        .iter()
+        .map(|key_image| block_contents.key_images.contains(key_image))
+        .collect::<Vec<bool>>();
+
+    // If all key images are in the block, the transaction was completed.
+    if key_image_found
+        .iter()
+        .all(|key_image_found| *key_image_found)
+    {
+        return Ok(TxStatus::Verified);
+    }
+
+    // If only some key images found their way to the block, they were likely spent
+    // from another transaction.
+    if key_image_found
+        .iter()
+        .any(|key_image_found| *key_image_found)
+    {
+        return Ok(TxStatus::TransactionFailureKeyImageBlockMismatch);
+    }
+
+    // Check if the tombstone block was exceeded.
+    let num_blocks = ledger_db.num_blocks()?;
+    if num_blocks >= tombstone {
+        return Ok(TxStatus::TombstoneBlockExceeded);
+    }
+
+    // No key images in ledger, tombstone block not yet exceeded.
+    Ok(TxStatus::Unknown)
+}
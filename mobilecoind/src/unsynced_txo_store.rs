@@ -0,0 +1,287 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for TxOuts received by watch-only monitors.
+//! * Watch-only monitors cannot compute the key images of the TxOuts they
+//!   receive, so these are held here until their key images are supplied
+//!   externally, at which point they become [UnspentTxOut]s.
+//! * Stores a map of (monitor id, tx out public key) -> [UnsyncedTxOut].
+
+use crate::{error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_common::logger::{log, Logger};
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::{ring_signature::KeyImage, tx::TxOut};
+use mc_util_serial::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const UNSYNCED_TXO_KEY_TO_UNSYNCED_TXO_DB_NAME: &str =
+    "mobilecoind_db:unsynced_txo_store:unsynced_txo_key_to_unsynced_txo";
+
+/// A TxOut received by a watch-only monitor, whose key image is not known yet.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct UnsyncedTxOut {
+    /// The actual transaction output.
+    #[prost(message, required, tag = "1")]
+    pub tx_out: TxOut,
+
+    /// Index of the subaddress to which this output was sent.
+    #[prost(uint64, tag = "2")]
+    pub subaddress_index: u64,
+
+    /// Value of this TxOut.
+    #[prost(uint64, tag = "3")]
+    pub value: u64,
+
+    /// The token id of this TxOut
+    #[prost(uint64, tag = "4")]
+    pub token_id: u64,
+
+    /// The (decrypted) MemoPayload of this TxOut
+    #[prost(bytes, tag = "5")]
+    pub memo_payload: Vec<u8>,
+}
+
+impl UnsyncedTxOut {
+    /// Turn this into an UnspentTxOut, once its key image is known.
+    pub fn into_utxo(self, key_image: KeyImage) -> UnspentTxOut {
+        UnspentTxOut {
+            tx_out: self.tx_out,
+            subaddress_index: self.subaddress_index,
+            key_image,
+            value: self.value,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            token_id: self.token_id,
+            memo_payload: self.memo_payload,
+        }
+    }
+}
+
+/// Type used as the key in the unsynced_txo_key_to_unsynced_txo database.
+/// Keys sort by monitor id, so the TxOuts of a monitor are stored
+/// contiguously.
+fn unsynced_txo_key(
+    monitor_id: &MonitorId,
+    tx_out_public_key: &CompressedRistrettoPublic,
+) -> [u8; 64] {
+    let mut buf = [0u8; 64];
+    buf[0..32].copy_from_slice(monitor_id.as_bytes());
+    buf[32..64].copy_from_slice(tx_out_public_key.as_bytes());
+    buf
+}
+
+/// The unsynced TxOuts database.
+#[derive(Clone)]
+pub struct UnsyncedTxoStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Mapping of (MonitorId, tx out public key) -> UnsyncedTxOut.
+    unsynced_txo_key_to_unsynced_txo: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl UnsyncedTxoStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let unsynced_txo_key_to_unsynced_txo = env.create_db(
+            Some(UNSYNCED_TXO_KEY_TO_UNSYNCED_TXO_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            _env: env,
+            unsynced_txo_key_to_unsynced_txo,
+            logger,
+        })
+    }
+
+    /// Add a TxOut received by a given monitor.
+    pub fn add(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+        unsynced_txo: &UnsyncedTxOut,
+    ) -> Result<(), Error> {
+        let key = unsynced_txo_key(monitor_id, &unsynced_txo.tx_out.public_key);
+        db_txn.put(
+            self.unsynced_txo_key_to_unsynced_txo,
+            &key,
+            &mc_util_serial::encode(unsynced_txo),
+            WriteFlags::empty(),
+        )?;
+
+        log::trace!(
+            self.logger,
+            "Added unsynced TxOut {} for monitor {}",
+            unsynced_txo.tx_out.public_key,
+            monitor_id
+        );
+        Ok(())
+    }
+
+    /// Remove and return the TxOut of a given monitor with a given public key.
+    pub fn remove(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<UnsyncedTxOut, Error> {
+        let key = unsynced_txo_key(monitor_id, tx_out_public_key);
+        let unsynced_txo = match db_txn.get(self.unsynced_txo_key_to_unsynced_txo, &key) {
+            Ok(value_bytes) => mc_util_serial::decode(value_bytes)?,
+            Err(lmdb::Error::NotFound) => return Err(Error::UnsyncedTxOutNotFound),
+            Err(err) => return Err(err.into()),
+        };
+        db_txn.del(self.unsynced_txo_key_to_unsynced_txo, &key, None)?;
+        Ok(unsynced_txo)
+    }
+
+    /// Get the unsynced TxOuts of a given monitor.
+    pub fn get_for_monitor(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+    ) -> Result<Vec<UnsyncedTxOut>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.unsynced_txo_key_to_unsynced_txo)?;
+
+        let mut unsynced_txos = Vec::new();
+        for result in cursor.iter_from(monitor_id.as_bytes()) {
+            let (key, value) = result?;
+            if !key.starts_with(monitor_id.as_bytes()) {
+                break;
+            }
+            unsynced_txos.push(mc_util_serial::decode(value)?);
+        }
+        Ok(unsynced_txos)
+    }
+
+    /// Remove all unsynced TxOuts of a given monitor.
+    pub fn remove_for_monitor(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        let mut cursor = db_txn.open_rw_cursor(self.unsynced_txo_key_to_unsynced_txo)?;
+
+        for (key, _value) in cursor
+            .iter_from(monitor_id.as_bytes())
+            .filter_map(|r| r.ok())
+        {
+            if !key.starts_with(monitor_id.as_bytes()) {
+                break;
+            }
+            cursor.del(WriteFlags::empty())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{get_test_monitor_data_and_id, BlockVersion};
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::test_with_logger;
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_transaction_core::{tokens::Mob, Amount, Token};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    fn random_unsynced_txo(rng: &mut StdRng, subaddress_index: u64) -> UnsyncedTxOut {
+        let tx_out = TxOut::new(
+            BlockVersion::MAX,
+            Amount::new(100 + subaddress_index, Mob::ID),
+            &PublicAddress::from_random(rng),
+            &RistrettoPrivate::from_random(rng),
+            Default::default(),
+        )
+        .unwrap();
+
+        UnsyncedTxOut {
+            tx_out,
+            subaddress_index,
+            value: 100 + subaddress_index,
+            token_id: *Mob::ID,
+            memo_payload: vec![],
+        }
+    }
+
+    #[test_with_logger]
+    fn test_unsynced_txo_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([17u8; 32]);
+
+        let db_tmp = TempDir::new().expect("Could not make tempdir for unsynced txo store db");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_tmp.path())
+                .unwrap(),
+        );
+        let store = UnsyncedTxoStore::new(env.clone(), logger).unwrap();
+
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let txos0: Vec<_> = (0..3).map(|i| random_unsynced_txo(&mut rng, i)).collect();
+        let txo1 = random_unsynced_txo(&mut rng, 0);
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            for txo in &txos0 {
+                store.add(&mut db_txn, &monitor_id0, txo).unwrap();
+            }
+            store.add(&mut db_txn, &monitor_id1, &txo1).unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        // Each monitor only sees its own TxOuts.
+        {
+            let db_txn = env.begin_ro_txn().unwrap();
+            let mut found = store.get_for_monitor(&db_txn, &monitor_id0).unwrap();
+            found.sort_by_key(|txo| txo.subaddress_index);
+            assert_eq!(found, txos0);
+            assert_eq!(
+                store.get_for_monitor(&db_txn, &monitor_id1).unwrap(),
+                vec![txo1.clone()]
+            );
+        }
+
+        // Removing a TxOut returns it, and it can only be removed once.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            let removed = store
+                .remove(&mut db_txn, &monitor_id0, &txos0[1].tx_out.public_key)
+                .unwrap();
+            assert_eq!(removed, txos0[1]);
+            assert_matches!(
+                store.remove(&mut db_txn, &monitor_id0, &txos0[1].tx_out.public_key),
+                Err(Error::UnsyncedTxOutNotFound)
+            );
+            assert_matches!(
+                store.remove(&mut db_txn, &monitor_id1, &txos0[0].tx_out.public_key),
+                Err(Error::UnsyncedTxOutNotFound)
+            );
+            db_txn.commit().unwrap();
+        }
+
+        // Removing a monitor's TxOuts leaves the other monitor's alone.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store.remove_for_monitor(&mut db_txn, &monitor_id0).unwrap();
+            assert!(store
+                .get_for_monitor(&db_txn, &monitor_id0)
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                store.get_for_monitor(&db_txn, &monitor_id1).unwrap(),
+                vec![txo1]
+            );
+        }
+    }
+}
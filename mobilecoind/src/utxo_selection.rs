@@ -0,0 +1,427 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Strategies for choosing which UTXOs fund a transaction.
+//!
+//! Strategies only decide which UTXOs to use. Checking that the request can be
+//! satisfied at all (enough funds, not too fragmented) is done by the
+//! `TransactionsManager` before a strategy is consulted.
+
+use crate::utxo_store::UnspentTxOut;
+use rand::seq::SliceRandom;
+use std::cmp::Reverse;
+
+/// A way of choosing UTXOs to fund a transaction.
+pub trait UtxoSelectionStrategy: Send + Sync {
+    /// Select UTXOs totalling at least `value`, using at most `max_inputs` of
+    /// them.
+    ///
+    /// # Arguments
+    /// * `utxos` - Candidate UTXOs, all of the token being spent. The
+    ///   `max_inputs` largest of them are guaranteed to total at least `value`.
+    /// * `value` - The value that needs to be covered.
+    /// * `max_inputs` - Maximum number of UTXOs that can be selected.
+    /// * `num_blocks_in_ledger` - Number of blocks in the local ledger, used to
+    ///   tell whether an earlier spend attempt could still land.
+    fn select(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        num_blocks_in_ledger: u64,
+    ) -> Vec<UnspentTxOut>;
+}
+
+/// Check whether at most `max_inputs` of `utxos` can total `value`.
+fn can_cover(utxos: &[UnspentTxOut], value: u64, max_inputs: usize) -> bool {
+    let mut values: Vec<u64> = utxos.iter().map(|utxo| utxo.value).collect();
+    values.sort_unstable_by_key(|value| Reverse(*value));
+    values
+        .iter()
+        .take(max_inputs)
+        .map(|value| *value as u128)
+        .sum::<u128>()
+        >= value as u128
+}
+
+fn total_value(utxos: &[UnspentTxOut]) -> u128 {
+    utxos.iter().map(|utxo| utxo.value as u128).sum()
+}
+
+/// Add UTXOs in the given order until `value` is covered. Whenever more than
+/// `max_inputs` are selected the smallest selected UTXO is dropped, so this
+/// always succeeds when the `max_inputs` largest UTXOs cover `value`.
+fn select_in_order(
+    utxos: impl IntoIterator<Item = UnspentTxOut>,
+    value: u64,
+    max_inputs: usize,
+) -> Vec<UnspentTxOut> {
+    let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+    for utxo in utxos {
+        if total_value(&selected_utxos) >= value as u128 {
+            break;
+        }
+
+        selected_utxos.push(utxo);
+
+        // Cap at maximum allowed inputs.
+        if selected_utxos.len() > max_inputs {
+            let (smallest_index, _) = selected_utxos
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, utxo)| utxo.value)
+                .expect("selected_utxos is not empty");
+            selected_utxos.remove(smallest_index);
+        }
+    }
+    selected_utxos
+}
+
+/// Spend the smallest UTXOs first. This keeps large UTXOs around, but leads to
+/// transactions with many inputs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmallestFirst;
+
+impl UtxoSelectionStrategy for SmallestFirst {
+    fn select(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        _num_blocks_in_ledger: u64,
+    ) -> Vec<UnspentTxOut> {
+        let mut sorted_utxos = utxos.to_vec();
+        sorted_utxos.sort_by_key(|utxo| utxo.value);
+        select_in_order(sorted_utxos, value, max_inputs)
+    }
+}
+
+/// Spend the largest UTXOs first. This minimizes the number of inputs, at the
+/// cost of accumulating small UTXOs over time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LargestFirst;
+
+impl UtxoSelectionStrategy for LargestFirst {
+    fn select(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        _num_blocks_in_ledger: u64,
+    ) -> Vec<UnspentTxOut> {
+        let mut sorted_utxos = utxos.to_vec();
+        sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
+        select_in_order(sorted_utxos, value, max_inputs)
+    }
+}
+
+/// Look for UTXOs totalling exactly `value` using a branch-and-bound search,
+/// so that the transaction needs no change output. Falls back to
+/// [SmallestFirst] when there is no exact match, or none was found within
+/// `max_tries` steps.
+#[derive(Clone, Copy, Debug)]
+pub struct ExactMatch {
+    /// Maximum number of search steps.
+    pub max_tries: usize,
+}
+
+impl Default for ExactMatch {
+    fn default() -> Self {
+        Self { max_tries: 100_000 }
+    }
+}
+
+impl ExactMatch {
+    /// Search for indices into `values` (sorted in descending order) of at
+    /// most `max_inputs` entries summing up to `target`.
+    pub(crate) fn search(
+        &self,
+        values: &[u64],
+        target: u64,
+        max_inputs: usize,
+    ) -> Option<Vec<usize>> {
+        // remaining[i] is the sum of values[i..].
+        let mut remaining = vec![0u128; values.len() + 1];
+        for i in (0..values.len()).rev() {
+            remaining[i] = remaining[i + 1] + values[i] as u128;
+        }
+
+        let mut tries = 0;
+        let mut selected = Vec::new();
+        self.search_from(
+            values,
+            &remaining,
+            0,
+            target as u128,
+            max_inputs,
+            &mut selected,
+            &mut tries,
+        )
+        .then_some(selected)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search_from(
+        &self,
+        values: &[u64],
+        remaining: &[u128],
+        index: usize,
+        target: u128,
+        max_inputs: usize,
+        selected: &mut Vec<usize>,
+        tries: &mut usize,
+    ) -> bool {
+        if target == 0 {
+            return true;
+        }
+        *tries += 1;
+        if index == values.len()
+            || selected.len() == max_inputs
+            || remaining[index] < target
+            || *tries > self.max_tries
+        {
+            return false;
+        }
+
+        // Include values[index], unless it overshoots.
+        if values[index] as u128 <= target {
+            selected.push(index);
+            if self.search_from(
+                values,
+                remaining,
+                index + 1,
+                target - values[index] as u128,
+                max_inputs,
+                selected,
+                tries,
+            ) {
+                return true;
+            }
+            selected.pop();
+        }
+
+        // Exclude values[index], along with any following equal values, since
+        // trying them in its place would only repeat the same search.
+        let next_index = values[index..]
+            .iter()
+            .position(|value| *value != values[index])
+            .map_or(values.len(), |offset| index + offset);
+        self.search_from(
+            values, remaining, next_index, target, max_inputs, selected, tries,
+        )
+    }
+}
+
+impl UtxoSelectionStrategy for ExactMatch {
+    fn select(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        num_blocks_in_ledger: u64,
+    ) -> Vec<UnspentTxOut> {
+        let mut sorted_utxos = utxos.to_vec();
+        sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
+        let values: Vec<u64> = sorted_utxos.iter().map(|utxo| utxo.value).collect();
+
+        match self.search(&values, value, max_inputs) {
+            Some(indices) => indices
+                .into_iter()
+                .map(|index| sorted_utxos[index].clone())
+                .collect(),
+            None => SmallestFirst.select(utxos, value, max_inputs, num_blocks_in_ledger),
+        }
+    }
+}
+
+/// Spend UTXOs in random order, which makes it harder to link the
+/// transactions of a wallet through its input selection habits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomSelection;
+
+impl UtxoSelectionStrategy for RandomSelection {
+    fn select(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        _num_blocks_in_ledger: u64,
+    ) -> Vec<UnspentTxOut> {
+        let mut shuffled_utxos = utxos.to_vec();
+        shuffled_utxos.shuffle(&mut rand::thread_rng());
+        select_in_order(shuffled_utxos, value, max_inputs)
+    }
+}
+
+/// Avoid UTXOs that were used by a transaction that could still land in the
+/// ledger, since spending them again would make one of the two transactions
+/// fail. Selection among the remaining UTXOs is delegated to an inner
+/// strategy. If they are not enough, all UTXOs are considered.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AvoidRecentlyAttempted<S: UtxoSelectionStrategy = SmallestFirst>(pub S);
+
+impl<S: UtxoSelectionStrategy> UtxoSelectionStrategy for AvoidRecentlyAttempted<S> {
+    fn select(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        num_blocks_in_ledger: u64,
+    ) -> Vec<UnspentTxOut> {
+        let settled_utxos: Vec<UnspentTxOut> = utxos
+            .iter()
+            .filter(|utxo| num_blocks_in_ledger >= utxo.attempted_spend_tombstone)
+            .cloned()
+            .collect();
+
+        if can_cover(&settled_utxos, value, max_inputs) {
+            self.0
+                .select(&settled_utxos, value, max_inputs, num_blocks_in_ledger)
+        } else {
+            self.0
+                .select(utxos, value, max_inputs, num_blocks_in_ledger)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mc_account_keys::AccountKey;
+    use mc_blockchain_types::BlockVersion;
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_transaction_core::{tokens::Mob, tx::TxOut, Amount, Token};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn generate_utxos(values: &[u64]) -> Vec<UnspentTxOut> {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+
+        values
+            .iter()
+            .map(|value| {
+                let tx_out = TxOut::new(
+                    BlockVersion::MAX,
+                    Amount::new(*value, Mob::ID),
+                    &alice.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap();
+
+                UnspentTxOut {
+                    tx_out,
+                    subaddress_index: 0,
+                    key_image: Default::default(),
+                    value: *value,
+                    attempted_spend_height: 0,
+                    attempted_spend_tombstone: 0,
+                    token_id: *Mob::ID,
+                    memo_payload: vec![],
+                }
+            })
+            .collect()
+    }
+
+    fn values(utxos: &[UnspentTxOut]) -> Vec<u64> {
+        let mut values: Vec<u64> = utxos.iter().map(|utxo| utxo.value).collect();
+        values.sort_unstable();
+        values
+    }
+
+    fn select(
+        strategy: &dyn UtxoSelectionStrategy,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+    ) -> Vec<u64> {
+        let selected = strategy.select(utxos, value, max_inputs, 10);
+        assert!(selected.len() <= max_inputs);
+        values(&selected)
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let utxos = generate_utxos(&[100, 200, 300, 2000, 1000]);
+        assert_eq!(select(&LargestFirst, &utxos, 300, 5), vec![2000]);
+        assert_eq!(select(&LargestFirst, &utxos, 2500, 5), vec![1000, 2000]);
+    }
+
+    #[test]
+    fn test_exact_match() {
+        let utxos = generate_utxos(&[100, 200, 300, 2000, 1000, 700]);
+
+        // 100 + 200 + 700 and 1000 both match exactly, the search prefers large
+        // UTXOs.
+        assert_eq!(select(&ExactMatch::default(), &utxos, 1000, 5), vec![1000]);
+        assert_eq!(
+            select(&ExactMatch::default(), &utxos, 1100, 5),
+            vec![100, 1000]
+        );
+        assert_eq!(
+            select(&ExactMatch::default(), &utxos, 3300, 6),
+            vec![300, 1000, 2000]
+        );
+        assert_eq!(
+            select(&ExactMatch::default(), &utxos, 600, 3),
+            vec![100, 200, 300]
+        );
+
+        // The input limit is respected, 600 cannot be matched exactly with two inputs.
+        assert_eq!(
+            select(&ExactMatch::default(), &utxos, 600, 2),
+            vec![300, 700]
+        );
+
+        // Without an exact match, fall back to smallest first.
+        assert_eq!(
+            select(&ExactMatch::default(), &utxos, 150, 5),
+            vec![100, 200]
+        );
+
+        // Same when the search gives up.
+        assert_eq!(
+            select(&ExactMatch { max_tries: 1 }, &utxos, 1000, 5),
+            vec![100, 200, 300, 700]
+        );
+    }
+
+    #[test]
+    fn test_random_selection() {
+        let utxos = generate_utxos(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        for _ in 0..100 {
+            let selected = select(&RandomSelection, &utxos, 25, 3);
+            assert!(selected.len() <= 3);
+            assert!(selected.iter().sum::<u64>() >= 25);
+        }
+    }
+
+    #[test]
+    fn test_avoid_recently_attempted() {
+        let mut utxos = generate_utxos(&[100, 200, 300, 2000, 1000]);
+
+        // The two smallest UTXOs are part of a transaction that could still land.
+        utxos[0].attempted_spend_tombstone = 11;
+        utxos[1].attempted_spend_tombstone = 11;
+
+        let strategy = AvoidRecentlyAttempted::default();
+        assert_eq!(select(&strategy, &utxos, 300, 5), vec![300]);
+
+        // If the settled UTXOs are not enough, pending ones are used too.
+        assert_eq!(
+            select(&strategy, &utxos, 3500, 5),
+            vec![100, 200, 300, 1000, 2000]
+        );
+
+        // Once the tombstone block passed the UTXOs are available again.
+        utxos[0].attempted_spend_tombstone = 10;
+        utxos[1].attempted_spend_tombstone = 10;
+        assert_eq!(select(&strategy, &utxos, 300, 5), vec![100, 200]);
+
+        // The inner strategy decides among settled UTXOs.
+        assert_eq!(
+            select(&AvoidRecentlyAttempted(LargestFirst), &utxos, 300, 5),
+            vec![2000]
+        );
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Database storage for webhook callbacks that still need to be delivered.
+//! * Stores a map of event id -> WebhookEvent, for events that have not been
+//!   successfully delivered yet.
+//! * Stores a map of watch id -> WatchedTx, for submitted transactions whose
+//!   final status we still need to report.
+
+use crate::error::Error;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_common::logger::Logger;
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::ring_signature::KeyImage;
+use prost::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const COUNTERS_DB_NAME: &str = "mobilecoind_db:webhook_store:counters";
+pub const EVENT_ID_TO_EVENT_DB_NAME: &str = "mobilecoind_db:webhook_store:event_id_to_event";
+pub const WATCH_ID_TO_WATCHED_TX_DB_NAME: &str =
+    "mobilecoind_db:webhook_store:watch_id_to_watched_tx";
+
+// Key we use for storing how many events we have added to the store. This
+// gives us a monotonically increasing id for each event, which also determines
+// delivery order.
+pub const EVENTS_COUNTER_KEY: &str = "events_counter";
+
+// Key we use for storing how many transactions we have started watching.
+pub const WATCHED_TXS_COUNTER_KEY: &str = "watched_txs_counter";
+
+/// A webhook callback that is pending delivery.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct WebhookEvent {
+    /// Unique, monotonically increasing event id.
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+
+    /// The event type, e.g. "tx_out_received".
+    #[prost(string, tag = "2")]
+    pub event_type: String,
+
+    /// JSON-encoded event data.
+    #[prost(bytes, tag = "3")]
+    pub data: Vec<u8>,
+
+    /// Time the event was created at, in seconds since the Unix epoch.
+    #[prost(uint64, tag = "4")]
+    pub created_at: u64,
+
+    /// Number of failed delivery attempts so far.
+    #[prost(uint32, tag = "5")]
+    pub attempts: u32,
+
+    /// Earliest time the next delivery attempt should happen at, in seconds
+    /// since the Unix epoch.
+    #[prost(uint64, tag = "6")]
+    pub next_attempt_at: u64,
+}
+
+/// A submitted transaction we are waiting to reach a final state.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct WatchedTx {
+    /// Key images of the inputs spent by the transaction.
+    #[prost(message, repeated, tag = "1")]
+    pub key_images: Vec<KeyImage>,
+
+    /// Public keys of the outputs created by the transaction.
+    #[prost(message, repeated, tag = "2")]
+    pub tx_public_keys: Vec<CompressedRistrettoPublic>,
+
+    /// Tombstone block of the transaction.
+    #[prost(uint64, tag = "3")]
+    pub tombstone: u64,
+}
+
+/// The webhook database.
+#[derive(Clone)]
+pub struct WebhookStore {
+    /// Retain a reference to the Environment so the Database handles are valid.
+    _env: Arc<Environment>,
+
+    /// Database for keeping track of counters.
+    counters: Database,
+
+    /// Mapping of event id -> WebhookEvent.
+    event_id_to_event: Database,
+
+    /// Mapping of watch id -> WatchedTx.
+    watch_id_to_watched_tx: Database,
+}
+
+impl WebhookStore {
+    pub fn new(env: Arc<Environment>, _logger: Logger) -> Result<Self, Error> {
+        let counters = env.create_db(Some(COUNTERS_DB_NAME), DatabaseFlags::empty())?;
+        let event_id_to_event =
+            env.create_db(Some(EVENT_ID_TO_EVENT_DB_NAME), DatabaseFlags::empty())?;
+        let watch_id_to_watched_tx =
+            env.create_db(Some(WATCH_ID_TO_WATCHED_TX_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            _env: env,
+            counters,
+            event_id_to_event,
+            watch_id_to_watched_tx,
+        })
+    }
+
+    /// Add a new event to the store. The event is due for delivery
+    /// immediately. Returns the id assigned to the event.
+    pub fn append_event(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        event_type: &str,
+        data: &[u8],
+        now: u64,
+    ) -> Result<u64, Error> {
+        let id = self.get_counter(db_txn, EVENTS_COUNTER_KEY)?;
+
+        let event = WebhookEvent {
+            id,
+            event_type: event_type.to_owned(),
+            data: data.to_vec(),
+            created_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+        };
+
+        db_txn.put(
+            self.event_id_to_event,
+            &id.to_be_bytes(),
+            &mc_util_serial::encode(&event),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+
+        self.set_counter(db_txn, EVENTS_COUNTER_KEY, id + 1)?;
+
+        Ok(id)
+    }
+
+    /// Get up to `limit` events that are due for delivery at time `now`, in
+    /// the order they were added.
+    pub fn get_due_events(
+        &self,
+        db_txn: &impl Transaction,
+        now: u64,
+        limit: usize,
+    ) -> Result<Vec<WebhookEvent>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.event_id_to_event)?;
+
+        let mut events = Vec::new();
+        for result in cursor.iter_start() {
+            if events.len() >= limit {
+                break;
+            }
+
+            let (_key, value) = result?;
+            let event: WebhookEvent = mc_util_serial::decode(value)?;
+            if event.next_attempt_at <= now {
+                events.push(event);
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Update a previously added event (used to record failed delivery
+    /// attempts).
+    pub fn update_event(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        event: &WebhookEvent,
+    ) -> Result<(), Error> {
+        let id_bytes = event.id.to_be_bytes();
+
+        // Ensure the event exists, we do not want to resurrect delivered events.
+        db_txn.get(self.event_id_to_event, &id_bytes)?;
+
+        db_txn.put(
+            self.event_id_to_event,
+            &id_bytes,
+            &mc_util_serial::encode(event),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove an event from the store (used once it was delivered).
+    pub fn remove_event(&self, db_txn: &mut RwTransaction<'_>, id: u64) -> Result<(), Error> {
+        db_txn.del(self.event_id_to_event, &id.to_be_bytes(), None)?;
+        Ok(())
+    }
+
+    /// Start watching a submitted transaction. Returns the id assigned to the
+    /// watch.
+    pub fn add_watched_tx(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        watched_tx: &WatchedTx,
+    ) -> Result<u64, Error> {
+        let id = self.get_counter(db_txn, WATCHED_TXS_COUNTER_KEY)?;
+
+        db_txn.put(
+            self.watch_id_to_watched_tx,
+            &id.to_be_bytes(),
+            &mc_util_serial::encode(watched_tx),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+
+        self.set_counter(db_txn, WATCHED_TXS_COUNTER_KEY, id + 1)?;
+
+        Ok(id)
+    }
+
+    /// Get all currently watched transactions, along with their watch ids.
+    pub fn get_watched_txs(
+        &self,
+        db_txn: &impl Transaction,
+    ) -> Result<Vec<(u64, WatchedTx)>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.watch_id_to_watched_tx)?;
+
+        cursor
+            .iter_start()
+            .map(|result| {
+                let (key, value) = result?;
+                let id = u64::from_be_bytes(key.try_into().map_err(|_| Error::KeyDeserialization)?);
+                Ok((id, mc_util_serial::decode(value)?))
+            })
+            .collect()
+    }
+
+    /// Stop watching a transaction.
+    pub fn remove_watched_tx(&self, db_txn: &mut RwTransaction<'_>, id: u64) -> Result<(), Error> {
+        db_txn.del(self.watch_id_to_watched_tx, &id.to_be_bytes(), None)?;
+        Ok(())
+    }
+
+    fn get_counter(&self, db_txn: &impl Transaction, key: &str) -> Result<u64, Error> {
+        match db_txn.get(self.counters, &key) {
+            Ok(bytes) => {
+                let counter =
+                    u64::from_be_bytes(bytes.try_into().map_err(|_| Error::ValueDeserialization)?);
+                Ok(counter)
+            }
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set_counter(
+        &self,
+        db_txn: &mut RwTransaction<'_>,
+        key: &str,
+        value: u64,
+    ) -> Result<(), Error> {
+        db_txn.put(
+            self.counters,
+            &key,
+            &value.to_be_bytes(),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use std::assert_matches::assert_matches;
+    use tempfile::TempDir;
+
+    fn setup_test_webhook_store(logger: &Logger) -> (Arc<Environment>, WebhookStore) {
+        let db_tmp = TempDir::new().expect("Could not make tempdir for webhook store db");
+        let db_path = db_tmp
+            .path()
+            .to_str()
+            .expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+
+        let webhook_store = WebhookStore::new(env.clone(), logger.clone()).unwrap();
+
+        (env, webhook_store)
+    }
+
+    #[test_with_logger]
+    fn test_webhook_store_events(logger: Logger) {
+        let (env, webhook_store) = setup_test_webhook_store(&logger);
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        for i in 0..5u64 {
+            let id = webhook_store
+                .append_event(&mut db_txn, "test", &[i as u8], 100)
+                .unwrap();
+            assert_eq!(id, i);
+        }
+        db_txn.commit().unwrap();
+
+        // Nothing is due before the events were created.
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert!(webhook_store
+            .get_due_events(&db_txn, 99, 10)
+            .unwrap()
+            .is_empty());
+
+        // Events are returned in order, and the limit is respected.
+        let events = webhook_store.get_due_events(&db_txn, 100, 3).unwrap();
+        assert_eq!(
+            events.iter().map(|event| event.id).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(events[1].data, vec![1]);
+        assert_eq!(events[1].created_at, 100);
+        drop(db_txn);
+
+        // Postpone the first event, it should no longer be due.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        let mut event = events[0].clone();
+        event.attempts += 1;
+        event.next_attempt_at = 200;
+        webhook_store.update_event(&mut db_txn, &event).unwrap();
+
+        // Remove the second event.
+        webhook_store.remove_event(&mut db_txn, 1).unwrap();
+        db_txn.commit().unwrap();
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        let events = webhook_store.get_due_events(&db_txn, 150, 10).unwrap();
+        assert_eq!(
+            events.iter().map(|event| event.id).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+
+        let events = webhook_store.get_due_events(&db_txn, 200, 10).unwrap();
+        assert_eq!(
+            events.iter().map(|event| event.id).collect::<Vec<_>>(),
+            vec![0, 2, 3, 4]
+        );
+        assert_eq!(events[0].attempts, 1);
+        drop(db_txn);
+
+        // Updating or removing a removed event should fail.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_matches!(
+            webhook_store.remove_event(&mut db_txn, 1),
+            Err(Error::Lmdb(lmdb::Error::NotFound))
+        );
+        let mut event = events[1].clone();
+        event.id = 1;
+        assert_matches!(
+            webhook_store.update_event(&mut db_txn, &event),
+            Err(Error::Lmdb(lmdb::Error::NotFound))
+        );
+
+        // New events keep getting fresh ids.
+        assert_eq!(
+            webhook_store
+                .append_event(&mut db_txn, "test", &[], 100)
+                .unwrap(),
+            5
+        );
+    }
+
+    #[test_with_logger]
+    fn test_webhook_store_watched_txs(logger: Logger) {
+        let (env, webhook_store) = setup_test_webhook_store(&logger);
+
+        let watched_txs = (0..3u64)
+            .map(|i| WatchedTx {
+                key_images: vec![KeyImage::from(i)],
+                tx_public_keys: vec![],
+                tombstone: 10 + i,
+            })
+            .collect::<Vec<_>>();
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        for (i, watched_tx) in watched_txs.iter().enumerate() {
+            assert_eq!(
+                webhook_store
+                    .add_watched_tx(&mut db_txn, watched_tx)
+                    .unwrap(),
+                i as u64
+            );
+        }
+        db_txn.commit().unwrap();
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(
+            webhook_store.get_watched_txs(&db_txn).unwrap(),
+            watched_txs
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(i, watched_tx)| (i as u64, watched_tx))
+                .collect::<Vec<_>>()
+        );
+        drop(db_txn);
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        webhook_store.remove_watched_tx(&mut db_txn, 1).unwrap();
+        assert_eq!(
+            webhook_store.get_watched_txs(&db_txn).unwrap(),
+            vec![(0, watched_txs[0].clone()), (2, watched_txs[2].clone())]
+        );
+    }
+}
@@ -0,0 +1,478 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Delivery of monitor event webhooks.
+//!
+//! Events are written into the mobilecoind database (see `webhook_store`) in
+//! the same transaction that produces them, so they survive restarts. A
+//! background thread periodically picks up events that are due, POSTs them to
+//! the configured URL and removes them once the receiver acknowledged them with
+//! a 2xx status code. Failed deliveries are retried with exponential backoff.
+//!
+//! Each request body is a JSON object of the form
+//! `{"id": .., "type": .., "created_at": .., "data": {..}}`, and is signed
+//! with HMAC-SHA256 using a shared secret. The hex-encoded signature is sent in
+//! the `x-mobilecoind-signature` header, prefixed by `sha256=`.
+
+use crate::{
+    database::Database,
+    error::Error,
+    monitor_store::MonitorId,
+    tx_status::get_tx_status_as_sender,
+    utxo_store::UnspentTxOut,
+    webhook_store::{WatchedTx, WebhookEvent},
+};
+use hex_fmt::HexFmt;
+use hmac::{Hmac, Mac};
+use mc_common::logger::{log, Logger};
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_mobilecoind_api::TxStatus;
+use protobuf::ProtobufEnum;
+use reqwest::{
+    blocking::Client,
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    Url,
+};
+use serde_json::json;
+use sha2::Sha256;
+use std::{
+    cmp::min,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Event type for a TxOut received by a monitor.
+pub const TX_OUT_RECEIVED_EVENT: &str = "tx_out_received";
+
+/// Event type for a TxOut of a monitor that got spent.
+pub const TX_OUT_SPENT_EVENT: &str = "tx_out_spent";
+
+/// Event type for a submitted transaction that reached a final state.
+pub const TX_STATUS_EVENT: &str = "tx_status";
+
+/// Header carrying the HMAC signature of the request body.
+pub const SIGNATURE_HEADER: &str = "x-mobilecoind-signature";
+
+/// Header carrying the event id, so that receivers can de-duplicate
+/// deliveries.
+pub const EVENT_ID_HEADER: &str = "x-mobilecoind-event-id";
+
+/// Maximum number of events to deliver in a single poll loop iteration.
+const MAX_EVENTS_PER_POLL: usize = 100;
+
+/// Delay before retrying a failed delivery for the first time.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Upper bound on the delay between retries of a failed delivery.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Number of failed delivery attempts after which an event is dropped. With
+/// the backoff above, this keeps retrying for roughly two days.
+const MAX_DELIVERY_ATTEMPTS: u32 = 60;
+
+/// Timeout for a single delivery attempt.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Current time, in seconds since the Unix epoch.
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// How long to wait before the next delivery attempt, given the number of
+/// attempts that failed so far.
+pub fn retry_delay(attempts: u32) -> Duration {
+    let factor = 1u32
+        .checked_shl(attempts.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    min(INITIAL_RETRY_DELAY.saturating_mul(factor), MAX_RETRY_DELAY)
+}
+
+/// Construct the data of a TxOut received/spent event.
+pub fn tx_out_event_data(monitor_id: &MonitorId, block_index: u64, utxo: &UnspentTxOut) -> Vec<u8> {
+    json!({
+        "monitor_id": HexFmt(monitor_id.as_bytes()).to_string(),
+        "block_index": block_index,
+        "subaddress_index": utxo.subaddress_index,
+        "public_key": HexFmt(utxo.tx_out.public_key.as_bytes()).to_string(),
+        "key_image": HexFmt(utxo.key_image.as_bytes()).to_string(),
+        "value": utxo.value,
+        "token_id": utxo.token_id,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Construct the data of a transaction status event.
+pub fn tx_status_event_data(watched_tx: &WatchedTx, status: TxStatus) -> Vec<u8> {
+    json!({
+        "status": format!("{status:?}"),
+        "status_code": status.value(),
+        "key_images": watched_tx
+            .key_images
+            .iter()
+            .map(|key_image| HexFmt(key_image.as_bytes()).to_string())
+            .collect::<Vec<_>>(),
+        "tx_public_keys": watched_tx
+            .tx_public_keys
+            .iter()
+            .map(|public_key| HexFmt(public_key.as_bytes()).to_string())
+            .collect::<Vec<_>>(),
+        "tombstone": watched_tx.tombstone,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Construct the request body for delivering an event.
+pub fn event_body(event: &WebhookEvent) -> Result<Vec<u8>, serde_json::Error> {
+    let data: serde_json::Value = serde_json::from_slice(&event.data)?;
+    serde_json::to_vec(&json!({
+        "id": event.id,
+        "type": event.event_type,
+        "created_at": event.created_at,
+        "data": data,
+    }))
+}
+
+/// Compute the value of the signature header for a given request body.
+pub fn sign_body(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(body);
+    format!("sha256={}", HexFmt(mac.finalize().into_bytes()))
+}
+
+/// Figure out whether a watched transaction reached a final state, and if so
+/// what that state is.
+pub fn get_final_tx_status(
+    ledger_db: &impl Ledger,
+    watched_tx: &WatchedTx,
+) -> Result<Option<TxStatus>, Error> {
+    let status = get_tx_status_as_sender(
+        ledger_db,
+        &watched_tx.key_images,
+        &watched_tx.tx_public_keys,
+        watched_tx.tombstone,
+    )?;
+
+    match status {
+        TxStatus::Unknown => {
+            // The transaction did not land, it never will once the tombstone block
+            // was exceeded.
+            if ledger_db.num_blocks()? >= watched_tx.tombstone {
+                Ok(Some(TxStatus::TombstoneBlockExceeded))
+            } else {
+                Ok(None)
+            }
+        }
+        status => Ok(Some(status)),
+    }
+}
+
+/// Webhook Thread - holds objects needed to cleanly terminate the webhook
+/// delivery thread.
+pub struct WebhookThread {
+    /// The main thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl WebhookThread {
+    pub fn start(
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        url: Url,
+        secret: String,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        // Start recording events now, so that nothing that happens from this point on
+        // is missed.
+        mobilecoind_db.enable_webhooks();
+
+        let stop_requested = Arc::new(AtomicBool::new(false));
+
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = thread::Builder::new()
+            .name("webhooks".to_string())
+            .spawn(move || {
+                webhook_thread_entry_point(
+                    ledger_db,
+                    mobilecoind_db,
+                    url,
+                    secret,
+                    poll_interval,
+                    thread_stop_requested,
+                    logger,
+                );
+            })
+            .expect("failed starting webhook thread");
+
+        Self {
+            join_handle: Some(join_handle),
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("WebhookThread join failed");
+        }
+    }
+}
+
+impl Drop for WebhookThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn webhook_thread_entry_point(
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    url: Url,
+    secret: String,
+    poll_interval: Duration,
+    stop_requested: Arc<AtomicBool>,
+    logger: Logger,
+) {
+    log::info!(logger, "Webhook thread started, delivering to {}", url);
+
+    let client = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("failed creating webhook http client");
+
+    loop {
+        if stop_requested.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if let Err(err) = check_watched_txs(&ledger_db, &mobilecoind_db, &logger) {
+            log::error!(logger, "Error checking watched transactions: {:?}", err);
+        }
+
+        if let Err(err) = deliver_due_events(&mobilecoind_db, &client, &url, &secret, &logger) {
+            log::error!(logger, "Error delivering webhook events: {:?}", err);
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+/// Queue status events for watched transactions that reached a final state.
+fn check_watched_txs(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    logger: &Logger,
+) -> Result<(), Error> {
+    for (watch_id, watched_tx) in mobilecoind_db.get_watched_txs()? {
+        if let Some(status) = get_final_tx_status(ledger_db, &watched_tx)? {
+            log::debug!(
+                logger,
+                "Watched transaction {} reached final status {:?}",
+                watch_id,
+                status
+            );
+            mobilecoind_db
+                .watched_tx_finalized(watch_id, &tx_status_event_data(&watched_tx, status))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Attempt to deliver all events that are currently due.
+fn deliver_due_events(
+    mobilecoind_db: &Database,
+    client: &Client,
+    url: &Url,
+    secret: &str,
+    logger: &Logger,
+) -> Result<(), Error> {
+    let now = unix_timestamp();
+
+    for mut event in mobilecoind_db.get_due_webhook_events(now, MAX_EVENTS_PER_POLL)? {
+        match deliver_event(client, url, secret, &event) {
+            Ok(()) => {
+                log::debug!(logger, "Delivered webhook event {}", event.id);
+                mobilecoind_db.remove_webhook_event(event.id)?;
+            }
+            Err(err) => {
+                event.attempts += 1;
+                if event.attempts >= MAX_DELIVERY_ATTEMPTS {
+                    log::error!(
+                        logger,
+                        "Giving up on webhook event {} after {} failed attempts: {}",
+                        event.id,
+                        event.attempts,
+                        err
+                    );
+                    mobilecoind_db.remove_webhook_event(event.id)?;
+                    continue;
+                }
+
+                let delay = retry_delay(event.attempts);
+                event.next_attempt_at = now + delay.as_secs();
+
+                log::warn!(
+                    logger,
+                    "Failed delivering webhook event {} (attempt {}), retrying in {:?}: {}",
+                    event.id,
+                    event.attempts,
+                    delay,
+                    err
+                );
+                mobilecoind_db.update_webhook_event(&event)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deliver a single event.
+fn deliver_event(
+    client: &Client,
+    url: &Url,
+    secret: &str,
+    event: &WebhookEvent,
+) -> Result<(), String> {
+    let body = event_body(event).map_err(|err| format!("failed encoding event: {err}"))?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(EVENT_ID_HEADER, HeaderValue::from(event.id));
+    headers.insert(
+        SIGNATURE_HEADER,
+        HeaderValue::from_str(&sign_body(secret.as_bytes(), &body))
+            .map_err(|err| format!("invalid signature header: {err}"))?,
+    );
+
+    let response = client
+        .post(url.clone())
+        .headers(headers)
+        .body(body)
+        .send()
+        .map_err(|err| format!("request failed: {err}"))?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("unexpected response status {}", response.status()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{add_block_to_ledger, get_test_databases, BlockVersion};
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::test_with_logger;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Amount, Token};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), INITIAL_RETRY_DELAY);
+        assert_eq!(retry_delay(2), INITIAL_RETRY_DELAY * 2);
+        assert_eq!(retry_delay(3), INITIAL_RETRY_DELAY * 4);
+        assert_eq!(retry_delay(30), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_sign_body() {
+        // Test vector from RFC 4231, test case 2.
+        assert_eq!(
+            sign_body(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_event_body() {
+        let event = WebhookEvent {
+            id: 7,
+            event_type: TX_OUT_RECEIVED_EVENT.to_owned(),
+            data: br#"{"value":10}"#.to_vec(),
+            created_at: 1234,
+            attempts: 3,
+            next_attempt_at: 5678,
+        };
+
+        let body: serde_json::Value = serde_json::from_slice(&event_body(&event).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "id": 7,
+                "type": "tx_out_received",
+                "created_at": 1234,
+                "data": {"value": 10},
+            })
+        );
+    }
+
+    #[test_with_logger]
+    fn test_get_final_tx_status(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let (mut ledger_db, _mobilecoind_db) =
+            get_test_databases(BlockVersion::MAX, 3, &[], 10, logger, &mut rng);
+
+        // A transaction whose outputs did not land, with its tombstone block ahead of
+        // us is not final.
+        let key_image = KeyImage::from(1234);
+        let pending_tx = WatchedTx {
+            key_images: vec![key_image],
+            tx_public_keys: vec![],
+            tombstone: 11,
+        };
+        assert_eq!(get_final_tx_status(&ledger_db, &pending_tx).unwrap(), None);
+
+        // Once the tombstone block is reached it is.
+        let expired_tx = WatchedTx {
+            tombstone: 10,
+            ..pending_tx.clone()
+        };
+        assert_eq!(
+            get_final_tx_status(&ledger_db, &expired_tx).unwrap(),
+            Some(TxStatus::TombstoneBlockExceeded)
+        );
+
+        // A transaction whose outputs landed along with its key images is verified.
+        add_block_to_ledger(
+            &mut ledger_db,
+            BlockVersion::MAX,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            Amount::new(1000, Mob::ID),
+            &[key_image],
+            &mut rng,
+        )
+        .unwrap();
+        let block_contents = ledger_db.get_block_contents(10).unwrap();
+        let landed_tx = WatchedTx {
+            key_images: vec![key_image],
+            tx_public_keys: vec![block_contents.outputs[0].public_key],
+            tombstone: 20,
+        };
+        assert_eq!(
+            get_final_tx_status(&ledger_db, &landed_tx).unwrap(),
+            Some(TxStatus::Verified)
+        );
+
+        // The pending transaction now fails since its key image was spent elsewhere.
+        assert_eq!(
+            get_final_tx_status(&ledger_db, &pending_tx).unwrap(),
+            Some(TxStatus::TransactionFailureKeyImageAlreadySpent)
+        );
+    }
+}
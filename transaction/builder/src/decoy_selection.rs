@@ -0,0 +1,455 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Selection of decoy TxOuts (mixins) for transaction rings.
+//!
+//! Real inputs tend to be young, since funds are usually spent soon after
+//! they are received. If decoys are picked uniformly over the whole ledger,
+//! the youngest member of a ring is very likely the real input. Picking decoy
+//! ages from a distribution resembling real spending behaviour makes the real
+//! input harder to spot.
+
+use crate::DecoyDistributionParseError;
+use alloc::{collections::BTreeSet, string::ToString, vec::Vec};
+use core::{fmt, str::FromStr};
+use rand::{seq::SliceRandom, Rng};
+use rand_core::{CryptoRng, RngCore};
+
+/// How many times, per requested decoy, a non-uniform distribution is sampled
+/// before falling back to uniform sampling. This only matters when nearly all
+/// of the ledger is excluded or needed.
+const MAX_ATTEMPTS_PER_DECOY: usize = 100;
+
+/// The distribution decoy TxOuts are sampled from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DecoyDistribution {
+    /// Every TxOut in the ledger is equally likely to be picked.
+    #[default]
+    Uniform,
+
+    /// The age of a decoy, in blocks counted back from the latest block,
+    /// follows a gamma distribution. A TxOut is then picked uniformly within
+    /// the block of that age.
+    Gamma {
+        /// Shape parameter (k) of the distribution.
+        shape: f64,
+        /// Scale parameter (theta) of the distribution, in blocks. The mean
+        /// age is `shape * scale`.
+        scale: f64,
+    },
+}
+
+impl DecoyDistribution {
+    /// Sample indices of TxOuts to use as decoys.
+    ///
+    /// The caller must make sure that the ledger holds at least
+    /// `num_requested` TxOuts which are not in `excluded`.
+    ///
+    /// # Arguments
+    /// * `num_requested` - Number of distinct indices to return.
+    /// * `excluded` - Indices that must not be returned, e.g. the real inputs.
+    /// * `num_txos` - Number of TxOuts in the ledger.
+    /// * `num_blocks` - Number of blocks in the ledger.
+    /// * `block_index_of` - Maps a TxOut index to the index of the block
+    ///   containing it, as `Ledger::get_block_index_by_tx_out_index` does. This
+    ///   must be non-decreasing in the TxOut index.
+    /// * `rng` - Randomness.
+    ///
+    /// The indices are returned in random order, so that consecutive chunks of
+    /// them can be used as rings.
+    pub fn sample_indices<E>(
+        &self,
+        num_requested: usize,
+        excluded: &BTreeSet<u64>,
+        num_txos: u64,
+        num_blocks: u64,
+        mut block_index_of: impl FnMut(u64) -> Result<u64, E>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<u64>, E> {
+        let mut samples = BTreeSet::new();
+
+        if let Self::Gamma { shape, scale } = *self {
+            let mut attempts = 0;
+            while samples.len() < num_requested && attempts < num_requested * MAX_ATTEMPTS_PER_DECOY
+            {
+                attempts += 1;
+
+                let age = sample_gamma(shape, scale, rng) as u64;
+                if age >= num_blocks {
+                    continue;
+                }
+                let block_index = num_blocks - 1 - age;
+
+                let first =
+                    first_tx_out_index_of_block(block_index, num_txos, &mut block_index_of)?;
+                let end =
+                    first_tx_out_index_of_block(block_index + 1, num_txos, &mut block_index_of)?;
+                if first == end {
+                    continue;
+                }
+
+                let index = rng.gen_range(first..end);
+                if !excluded.contains(&index) {
+                    samples.insert(index);
+                }
+            }
+        }
+
+        while samples.len() < num_requested {
+            let index = rng.gen_range(0..num_txos);
+            if !excluded.contains(&index) {
+                samples.insert(index);
+            }
+        }
+
+        let mut indices: Vec<u64> = samples.into_iter().collect();
+        indices.shuffle(rng);
+        Ok(indices)
+    }
+}
+
+impl fmt::Display for DecoyDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Uniform => write!(f, "uniform"),
+            Self::Gamma { shape, scale } => write!(f, "gamma:{shape}:{scale}"),
+        }
+    }
+}
+
+impl FromStr for DecoyDistribution {
+    type Err = DecoyDistributionParseError;
+
+    /// Parses either `uniform` or `gamma:<shape>:<scale>`.
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut parts = src.split(':');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("uniform"), None, None, None) => Ok(Self::Uniform),
+            (Some("gamma"), Some(shape), Some(scale), None) => {
+                let parse = |value: &str| {
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|value| value.is_finite() && *value > 0.0)
+                        .ok_or_else(|| {
+                            DecoyDistributionParseError::InvalidParameter(value.to_string())
+                        })
+                };
+                Ok(Self::Gamma {
+                    shape: parse(shape)?,
+                    scale: parse(scale)?,
+                })
+            }
+            _ => Err(DecoyDistributionParseError::Unknown(src.to_string())),
+        }
+    }
+}
+
+/// Find the index of the first TxOut in the given block, or in the first
+/// non-empty block after it. Returns `num_txos` if there is none.
+fn first_tx_out_index_of_block<E>(
+    block_index: u64,
+    num_txos: u64,
+    block_index_of: &mut impl FnMut(u64) -> Result<u64, E>,
+) -> Result<u64, E> {
+    let (mut low, mut high) = (0, num_txos);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if block_index_of(mid)? < block_index {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+/// Draw a value from a gamma distribution, using the method of Marsaglia and
+/// Tsang.
+pub fn sample_gamma(shape: f64, scale: f64, rng: &mut (impl RngCore + CryptoRng)) -> f64 {
+    if shape < 1.0 {
+        // Gamma(k) = Gamma(k + 1) * U^(1/k)
+        let u = open_unit(rng);
+        return sample_gamma(shape + 1.0, scale, rng) * libm::pow(u, 1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / libm::sqrt(9.0 * d);
+    loop {
+        let x = sample_standard_normal(rng);
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let u = open_unit(rng);
+        if libm::log(u) < 0.5 * x * x + d - d * v + d * libm::log(v) {
+            return d * v * scale;
+        }
+    }
+}
+
+/// Draw a value from the standard normal distribution (Box-Muller).
+fn sample_standard_normal(rng: &mut (impl RngCore + CryptoRng)) -> f64 {
+    let u1 = open_unit(rng);
+    let u2 = open_unit(rng);
+    libm::sqrt(-2.0 * libm::log(u1)) * libm::cos(2.0 * core::f64::consts::PI * u2)
+}
+
+/// Draw a value uniformly from (0, 1].
+fn open_unit(rng: &mut (impl RngCore + CryptoRng)) -> f64 {
+    1.0 - rng.gen::<f64>()
+}
+
+/// How easily real inputs can be told apart from decoys, based on their ages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Distinguishability {
+    /// How often an observer who guesses that the youngest ring member is the
+    /// real input is right. For indistinguishable decoys this is
+    /// `1 / ring_size`, and it approaches 1 as real inputs stand out by being
+    /// young.
+    pub youngest_guess_rate: f64,
+
+    /// Kolmogorov-Smirnov statistic between the ages of real inputs and the
+    /// ages of decoys: the largest distance between their cumulative
+    /// distribution functions. This is 0 for identical distributions and 1
+    /// for disjoint ones.
+    pub ks_statistic: f64,
+}
+
+/// Measure how distinguishable real inputs are from decoys.
+///
+/// Each real input age is put in a ring together with the next
+/// `ring_size - 1` decoy ages, and rings are formed until either list runs
+/// out. Ages are in blocks, as seen when the rings were built.
+pub fn measure_distinguishability(
+    real_ages: &[u64],
+    decoy_ages: &[u64],
+    ring_size: usize,
+) -> Distinguishability {
+    assert!(ring_size >= 2, "a ring needs at least one decoy");
+
+    let mut num_rings = 0;
+    let mut correct_guesses = 0.0;
+    for (real_age, decoys) in real_ages.iter().zip(decoy_ages.chunks_exact(ring_size - 1)) {
+        num_rings += 1;
+        let youngest = decoys.iter().copied().min().unwrap_or(u64::MAX);
+        if *real_age < youngest {
+            correct_guesses += 1.0;
+        } else if *real_age == youngest {
+            // The observer picks one of the tied members at random.
+            let num_tied = 1 + decoys.iter().filter(|age| **age == youngest).count();
+            correct_guesses += 1.0 / num_tied as f64;
+        }
+    }
+
+    Distinguishability {
+        youngest_guess_rate: if num_rings == 0 {
+            0.0
+        } else {
+            correct_guesses / num_rings as f64
+        },
+        ks_statistic: ks_statistic(real_ages, decoy_ages),
+    }
+}
+
+/// Two-sample Kolmogorov-Smirnov statistic.
+fn ks_statistic(a: &[u64], b: &[u64]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.sort_unstable();
+    b.sort_unstable();
+
+    let (mut i, mut j) = (0, 0);
+    let mut max_distance: f64 = 0.0;
+    while i < a.len() && j < b.len() {
+        let value = a[i].min(b[j]);
+        while i < a.len() && a[i] == value {
+            i += 1;
+        }
+        while j < b.len() && b[j] == value {
+            j += 1;
+        }
+        let distance = i as f64 / a.len() as f64 - j as f64 / b.len() as f64;
+        max_distance = max_distance.max(libm::fabs(distance));
+    }
+    max_distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use core::convert::Infallible;
+    use mc_util_test_helper::{get_seeded_rng, RngType};
+
+    const NUM_BLOCKS: u64 = 50_000;
+    const TXOS_PER_BLOCK: u64 = 3;
+    const RING_SIZE: usize = 11;
+
+    fn block_index_of(index: u64) -> Result<u64, Infallible> {
+        Ok(index / TXOS_PER_BLOCK)
+    }
+
+    // Sample decoys from a synthetic ledger and return their ages.
+    fn sample_decoy_ages(
+        distribution: DecoyDistribution,
+        num: usize,
+        rng: &mut RngType,
+    ) -> Vec<u64> {
+        distribution
+            .sample_indices(
+                num,
+                &BTreeSet::new(),
+                NUM_BLOCKS * TXOS_PER_BLOCK,
+                NUM_BLOCKS,
+                block_index_of,
+                rng,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|index| NUM_BLOCKS - 1 - block_index_of(index).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for src in ["uniform", "gamma:2:500", "gamma:0.5:12.25"] {
+            let distribution = DecoyDistribution::from_str(src).unwrap();
+            assert_eq!(format!("{distribution}"), src);
+        }
+        assert_eq!(
+            DecoyDistribution::from_str("gamma:2:500").unwrap(),
+            DecoyDistribution::Gamma {
+                shape: 2.0,
+                scale: 500.0
+            }
+        );
+
+        for src in ["", "poisson", "uniform:1", "gamma:2", "gamma:2:500:1"] {
+            assert_eq!(
+                DecoyDistribution::from_str(src),
+                Err(DecoyDistributionParseError::Unknown(src.to_string()))
+            );
+        }
+        for (src, param) in [
+            ("gamma:x:500", "x"),
+            ("gamma:2:-1", "-1"),
+            ("gamma:0:1", "0"),
+        ] {
+            assert_eq!(
+                DecoyDistribution::from_str(src),
+                Err(DecoyDistributionParseError::InvalidParameter(
+                    param.to_string()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn gamma_samples_have_expected_mean() {
+        let mut rng = get_seeded_rng();
+        for (shape, scale) in [(0.5, 10.0), (2.0, 500.0), (19.0, 1.5)] {
+            let n = 20_000;
+            let mean = (0..n)
+                .map(|_| sample_gamma(shape, scale, &mut rng))
+                .sum::<f64>()
+                / n as f64;
+            let expected = shape * scale;
+            assert!(
+                libm::fabs(mean - expected) < 0.05 * expected,
+                "shape {shape}, scale {scale}: mean {mean}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn sampled_indices_are_distinct_and_not_excluded() {
+        let mut rng = get_seeded_rng();
+
+        // A ledger with some empty blocks: block i holds TxOuts only if i is even.
+        let block_index_of = |index: u64| Ok::<_, Infallible>(2 * index);
+        let num_txos = 100;
+        let excluded: BTreeSet<u64> = (0..num_txos).filter(|index| index % 3 == 0).collect();
+        let num_available = num_txos as usize - excluded.len();
+
+        for distribution in [
+            DecoyDistribution::Uniform,
+            DecoyDistribution::Gamma {
+                shape: 2.0,
+                scale: 10.0,
+            },
+        ] {
+            // Asking for every available TxOut exercises the uniform fallback.
+            for num_requested in [10, num_available] {
+                let indices = distribution
+                    .sample_indices(
+                        num_requested,
+                        &excluded,
+                        num_txos,
+                        2 * num_txos,
+                        block_index_of,
+                        &mut rng,
+                    )
+                    .unwrap();
+                assert_eq!(indices.len(), num_requested);
+                assert_eq!(indices.iter().collect::<BTreeSet<_>>().len(), num_requested);
+                assert!(indices
+                    .iter()
+                    .all(|index| *index < num_txos && !excluded.contains(index)));
+            }
+        }
+    }
+
+    #[test]
+    fn gamma_decoys_hide_young_real_inputs() {
+        let mut rng = get_seeded_rng();
+        let spending = DecoyDistribution::Gamma {
+            shape: 2.0,
+            scale: 500.0,
+        };
+
+        // Real inputs are modelled as being spent with the same age
+        // distribution the gamma decoys use.
+        let real_ages = sample_decoy_ages(spending, 2_000, &mut rng);
+
+        let uniform = measure_distinguishability(
+            &real_ages,
+            &sample_decoy_ages(DecoyDistribution::Uniform, 20_000, &mut rng),
+            RING_SIZE,
+        );
+        let gamma = measure_distinguishability(
+            &real_ages,
+            &sample_decoy_ages(spending, 20_000, &mut rng),
+            RING_SIZE,
+        );
+
+        // With uniform decoys the youngest ring member is nearly always the real
+        // input.
+        assert!(uniform.youngest_guess_rate > 0.7, "{uniform:?}");
+        assert!(uniform.ks_statistic > 0.7, "{uniform:?}");
+
+        // With matching decoys the observer does about as well as guessing.
+        let guess_rate = 1.0 / RING_SIZE as f64;
+        assert!(
+            libm::fabs(gamma.youngest_guess_rate - guess_rate) < 0.03,
+            "{gamma:?}"
+        );
+        assert!(gamma.ks_statistic < 0.06, "{gamma:?}");
+    }
+
+    #[test]
+    fn distinguishability_of_identical_and_disjoint_ages() {
+        let ages: Vec<u64> = (0..100).collect();
+        let result = measure_distinguishability(&[5], &[5, 5, 5], 4);
+        assert_eq!(result.youngest_guess_rate, 0.25);
+        assert_eq!(result.ks_statistic, 0.0);
+
+        let result = measure_distinguishability(&ages[..10], &ages[10..], 2);
+        assert_eq!(result.youngest_guess_rate, 1.0);
+        assert_eq!(result.ks_statistic, 1.0);
+    }
+}
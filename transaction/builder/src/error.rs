@@ -1,5 +1,6 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
+use alloc::string::String;
 use displaydoc::Display;
 use mc_crypto_ring_signature_signer::Error as SignerError;
 use mc_fog_report_validation::FogPubkeyError;
@@ -149,3 +150,12 @@ impl From<NewMemoError> for SignedContingentInputBuilderError {
         SignedContingentInputBuilderError::Memo(src)
     }
 }
+
+/// An error that can occur when parsing a decoy distribution
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum DecoyDistributionParseError {
+    /// Unknown decoy distribution: {0}
+    Unknown(String),
+    /// Invalid decoy distribution parameter: {0}
+    InvalidParameter(String),
+}
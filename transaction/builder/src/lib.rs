@@ -10,6 +10,7 @@ extern crate std;
 
 extern crate alloc;
 
+mod decoy_selection;
 mod error;
 mod input_credentials;
 mod input_materials;
@@ -21,7 +22,10 @@ mod transaction_builder;
 #[cfg(any(test, feature = "test-only"))]
 pub mod test_utils;
 
-pub use error::{SignedContingentInputBuilderError, TxBuilderError};
+pub use decoy_selection::{
+    measure_distinguishability, sample_gamma, DecoyDistribution, Distinguishability,
+};
+pub use error::{DecoyDistributionParseError, SignedContingentInputBuilderError, TxBuilderError};
 pub use input_credentials::InputCredentials;
 pub use memo_builder::{
     BurnRedemptionMemoBuilder, DefragmentationMemoBuilder, EmptyMemoBuilder,
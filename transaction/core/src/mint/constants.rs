@@ -10,3 +10,6 @@ pub const MAX_MINT_TXS_PER_BLOCK: usize = 10;
 
 /// Maximum number of MintConfigTx that may be included in a Block.
 pub const MAX_MINT_CONFIG_TXS_PER_BLOCK: usize = 10;
+
+/// Domain separator for minted txouts public keys.
+pub const MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG: &str = "mc_minted_output_private_key";
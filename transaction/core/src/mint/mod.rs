@@ -3,12 +3,14 @@
 //! Minting transactions and associated data structures.
 
 mod config;
+mod output;
 mod tx;
 mod validation;
 
 pub mod constants;
 
 pub use config::{MintConfig, MintConfigTx, MintConfigTxPrefix, ValidatedMintConfigTx};
+pub use output::{minted_tx_out_public_key, network_output_private_key};
 pub use tx::{MintTx, MintTxPrefix};
 pub use validation::{
     config::validate_mint_config_tx, error::Error as MintValidationError, tx::validate_mint_tx,
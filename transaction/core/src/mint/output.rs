@@ -0,0 +1,61 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Outputs created by the network rather than by a transaction, i.e. minted
+//! TxOuts and fee outputs.
+
+use super::{constants::MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG, MintTx};
+use crate::{onetime_keys::create_tx_out_public_key, Amount, TokenId};
+use curve25519_dalek::scalar::Scalar;
+use mc_crypto_digestible::{Digestible, MerlinTranscript};
+use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
+
+/// Derive the private key of an output created by the network.
+///
+/// The key is derived from the parent block and the contents of the block,
+/// so that all nodes create the same output.
+///
+/// # Arguments
+/// * `domain_tag` - Domain separator for the kind of output.
+/// * `parent_block_id` - The id of the parent of the block being formed.
+/// * `transactions` - The transactions the output is created for.
+/// * `amount` - The amount of the output.
+/// * `counter` - Distinguishes outputs of the same kind and amount created for
+///   the same transactions.
+pub fn network_output_private_key<B: Digestible, T: Digestible>(
+    domain_tag: &'static [u8],
+    parent_block_id: &B,
+    transactions: &[T],
+    amount: Amount,
+    counter: usize,
+) -> RistrettoPrivate {
+    let mut hash_value = [0u8; 32];
+    {
+        let mut transcript = MerlinTranscript::new(domain_tag);
+        parent_block_id.append_to_transcript(b"parent_block_id", &mut transcript);
+        transactions.append_to_transcript(b"transactions", &mut transcript);
+        amount.append_to_transcript(b"amount", &mut transcript);
+        counter.append_to_transcript(b"counter", &mut transcript);
+        transcript.extract_digest(&mut hash_value);
+    };
+
+    RistrettoPrivate::from(Scalar::from_bytes_mod_order(hash_value))
+}
+
+/// Compute the public key of the TxOut minted by the MintTx at `counter` in
+/// a block's `mint_txs`. Since the block's outputs are sorted, this is how
+/// minted TxOuts can be told apart from other outputs.
+pub fn minted_tx_out_public_key<B: Digestible>(
+    parent_block_id: &B,
+    mint_txs: &[MintTx],
+    counter: usize,
+) -> RistrettoPublic {
+    let prefix = &mint_txs[counter].prefix;
+    let tx_private_key = network_output_private_key(
+        MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG.as_bytes(),
+        parent_block_id,
+        mint_txs,
+        Amount::new(prefix.amount, TokenId::from(prefix.token_id)),
+        counter,
+    );
+    create_tx_out_public_key(&tx_private_key, &prefix.spend_public_key)
+}
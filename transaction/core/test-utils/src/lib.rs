@@ -17,8 +17,8 @@ pub use mc_transaction_core::{
 pub use mc_util_serial::round_trip_message;
 pub use mint::{
     create_mint_config_tx, create_mint_config_tx_and_signers, create_mint_tx,
-    create_mint_tx_to_recipient, mint_config_tx_to_validated, sign_mint_config_tx_prefix,
-    sign_mint_tx_prefix,
+    create_mint_tx_to_recipient, create_minted_tx_out, mint_config_tx_to_validated,
+    sign_mint_config_tx_prefix, sign_mint_tx_prefix,
 };
 
 use mc_crypto_keys::RistrettoPrivate;
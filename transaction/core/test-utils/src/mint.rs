@@ -2,15 +2,18 @@
 //! Test helpers for minting transactions
 
 use mc_account_keys::PublicAddress;
+use mc_crypto_digestible::Digestible;
 use mc_crypto_keys::{Ed25519Pair, Ed25519Signature, RistrettoPublic, Signer};
 use mc_crypto_multisig::{MultiSig, SignerSet};
 use mc_rand::{CryptoRng, RngCore};
 use mc_transaction_core::{
     mint::{
-        constants::NONCE_LENGTH, MintConfig, MintConfigTx, MintConfigTxPrefix, MintTx,
+        constants::{MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG, NONCE_LENGTH},
+        network_output_private_key, MintConfig, MintConfigTx, MintConfigTxPrefix, MintTx,
         MintTxPrefix, ValidatedMintConfigTx,
     },
-    TokenId,
+    tx::TxOut,
+    Amount, BlockVersion, TokenId,
 };
 use mc_util_from_random::FromRandom;
 
@@ -171,6 +174,41 @@ pub fn create_mint_tx(
     create_mint_tx_to_recipient(token_id, signers, amount, &public_address, rng)
 }
 
+/// Create the TxOut that consensus mints for a MintTx, the way it does when
+/// forming a block.
+///
+/// # Arguments
+/// * `block_version` - The block version of the block being formed.
+/// * `parent_block_id` - The id of the parent of the block being formed.
+/// * `mint_txs` - The MintTxs included in the block being formed.
+/// * `counter` - The position of the MintTx in `mint_txs`.
+pub fn create_minted_tx_out(
+    block_version: BlockVersion,
+    parent_block_id: &impl Digestible,
+    mint_txs: &[MintTx],
+    counter: usize,
+) -> TxOut {
+    let prefix = &mint_txs[counter].prefix;
+    let amount = Amount::new(prefix.amount, TokenId::from(prefix.token_id));
+    let tx_private_key = network_output_private_key(
+        MINTED_OUTPUT_PRIVATE_KEY_DOMAIN_TAG.as_bytes(),
+        parent_block_id,
+        mint_txs,
+        amount,
+        counter,
+    );
+    let recipient = PublicAddress::new(&prefix.spend_public_key, &prefix.view_public_key);
+
+    TxOut::new(
+        block_version,
+        amount,
+        &recipient,
+        &tx_private_key,
+        prefix.e_fog_hint.clone().unwrap_or_default(),
+    )
+    .expect("failed creating minted TxOut")
+}
+
 /// Sign a MintTxPrefix.
 ///
 /// # Arguments
@@ -30,6 +30,8 @@ use types::*;
 pub mod traits;
 use traits::*;
 
+pub mod threshold;
+
 /// Command enumeration for offline / detached / hardware signing
 #[derive(Clone, PartialEq, Debug, Parser)]
 #[non_exhaustive]
@@ -157,21 +159,36 @@ impl Operations {
     /// input - file containing the unsigned transaction object
     /// output - file to write the signed transaction output
     pub fn sign_tx(ctx: impl RingSigner, input: &str, output: &str) -> anyhow::Result<()> {
+        Self::sign_tx_with(input, output, |req| {
+            SignatureRctBulletproofs::sign(
+                req.block_version,
+                &req.tx_prefix,
+                req.rings.as_slice(),
+                &req.output_secrets(),
+                Amount::new(req.tx_prefix.fee, TokenId::from(req.tx_prefix.fee_token_id)),
+                &ctx,
+                &mut OsRng {},
+            )
+        })
+    }
+
+    /// Sign an unsigned transaction with the provided signing function, for
+    /// signers that need the whole request rather than a [RingSigner]
+    ///
+    /// input - file containing the unsigned transaction object
+    /// output - file to write the signed transaction output
+    pub fn sign_tx_with(
+        input: &str,
+        output: &str,
+        sign: impl FnOnce(&TxSignReq) -> Result<SignatureRctBulletproofs, RingCtError>,
+    ) -> anyhow::Result<()> {
         // Load unsigned transaction object
         debug!("Reading unsigned transaction from '{}'", input);
         let req: TxSignReq = read_input(input)?;
 
         // Sign transaction
         let prefix = req.tx_prefix.clone();
-        let signature = match SignatureRctBulletproofs::sign(
-            req.block_version,
-            &prefix,
-            req.rings.as_slice(),
-            &req.output_secrets(),
-            Amount::new(prefix.fee, TokenId::from(prefix.fee_token_id)),
-            &ctx,
-            &mut OsRng {},
-        ) {
+        let signature = match sign(&req) {
             Ok(v) => v,
             Err(e) => {
                 return Err(anyhow::anyhow!("Failed to sign transaction: {:?}", e));
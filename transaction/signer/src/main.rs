@@ -8,13 +8,20 @@ use std::path::Path;
 
 use bip39::{Language, Mnemonic, MnemonicType};
 use clap::Parser;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 
 use mc_core::{account::Account, slip10::Slip10KeyGenerator};
+use mc_crypto_ring_signature::KeyShare;
 use mc_crypto_ring_signature_signer::LocalRingSigner;
 use mc_transaction_core::AccountKey;
-use mc_transaction_signer::{read_input, write_output, Operations};
+use mc_transaction_signer::{
+    read_input,
+    threshold::{LocalThresholdParty, ThresholdRingSigner},
+    types::ThresholdAccountInfo,
+    write_output, Operations,
+};
+use rand_core::OsRng;
 
 #[derive(Clone, PartialEq, Debug, Parser)]
 struct Args {
@@ -48,6 +55,52 @@ enum Actions {
         output: String,
     },
 
+    /// Split the spend key of an account into key shares for threshold
+    /// signing, writing the threshold account and one file per key share.
+    ///
+    /// This is a trusted-dealer split, the original mnemonic can still spend
+    /// alone and must be destroyed once the key shares are distributed.
+    SplitKey {
+        /// SLIP-0010 account index for SLIP-010 derivation
+        #[clap(long, default_value = "0")]
+        account: u32,
+
+        /// Number of key shares needed to sign
+        #[clap(long)]
+        threshold: u32,
+
+        /// Number of key shares to create
+        #[clap(long)]
+        shares: u32,
+
+        /// Output file to write threshold account object
+        #[clap(long)]
+        output: String,
+
+        /// Prefix for key share files, written to `<prefix><index>.json`
+        #[clap(long)]
+        share_prefix: String,
+    },
+    /// Sign offline transaction with key shares of a threshold account,
+    /// returning a signed transaction object
+    ThresholdSignTx {
+        /// Threshold account file
+        #[clap(long)]
+        account_info: String,
+
+        /// Key share files, at least the account threshold
+        #[clap(long = "share")]
+        shares: Vec<String>,
+
+        /// Input file containing transaction for signing
+        #[clap(long)]
+        input: String,
+
+        /// Output file to write signed transaction
+        #[clap(long)]
+        output: String,
+    },
+
     // Implement shared signer commands
     #[command(flatten)]
     Signer(Operations),
@@ -91,19 +144,65 @@ fn main() -> anyhow::Result<()> {
 
             info!("Account secrets written to '{}'", output);
         }
-        Actions::Signer(c) => {
-            // Load account secrets
-            let secrets: AccountSecrets = read_input(&args.secret_file)?;
-            let mnemonic = Mnemonic::from_phrase(&secrets.mnemonic, Language::English)?;
-
-            // Perform SLIP-0010 derivation
-            let account_index = c.account_index();
-            let slip10key = mnemonic.derive_slip10_key(account_index);
+        Actions::SplitKey {
+            account,
+            threshold,
+            shares,
+            output,
+            share_prefix,
+        } => {
+            let a = load_account(&args.secret_file, *account)?;
+
+            let (info, key_shares) =
+                ThresholdAccountInfo::split(&a, *account, *threshold, *shares, OsRng)
+                    .map_err(|e| anyhow::anyhow!("Failed to split spend key: {}", e))?;
+
+            // Check we're not overwriting existing key shares
+            let share_files: Vec<_> = key_shares
+                .iter()
+                .map(|s| format!("{}{}.json", share_prefix, s.index))
+                .collect();
+            if let Some(f) = share_files.iter().find(|f| Path::new(f).exists()) {
+                return Err(anyhow::anyhow!(
+                    "split would overwrite existing key share file '{}'",
+                    f
+                ));
+            }
 
-            // Generate account from secrets
-            let a = Account::from(&slip10key);
+            write_output(output, &info)?;
+            for (f, s) in share_files.iter().zip(&key_shares) {
+                write_output(f, s)?;
+            }
 
-            debug!("Using account: {:?}", a);
+            info!(
+                "Threshold account written to '{}', {} key shares written to '{}*.json'",
+                output, shares, share_prefix
+            );
+            warn!(
+                "The mnemonic in '{}' can still spend alone, destroy it once the key shares are distributed",
+                args.secret_file
+            );
+        }
+        Actions::ThresholdSignTx {
+            account_info,
+            shares,
+            input,
+            output,
+        } => {
+            let info: ThresholdAccountInfo = read_input(account_info)?;
+
+            // Run a party for each key share locally
+            let parties = shares
+                .iter()
+                .map(|f| read_input::<KeyShare>(f).map(LocalThresholdParty::from))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let ring_signer = ThresholdRingSigner::new(info, parties);
+            Operations::sign_tx_with(input, output, |req| ring_signer.sign_tx(req, &mut OsRng))?;
+        }
+        Actions::Signer(c) => {
+            let account_index = c.account_index();
+            let a = load_account(&args.secret_file, account_index)?;
 
             // Handle standard commands
             match c {
@@ -130,3 +229,20 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Load account secrets and perform SLIP-0010 derivation for the account index
+fn load_account(secret_file: &str, account_index: u32) -> anyhow::Result<Account> {
+    // Load account secrets
+    let secrets: AccountSecrets = read_input(secret_file)?;
+    let mnemonic = Mnemonic::from_phrase(&secrets.mnemonic, Language::English)?;
+
+    // Perform SLIP-0010 derivation
+    let slip10key = mnemonic.derive_slip10_key(account_index);
+
+    // Generate account from secrets
+    let a = Account::from(&slip10key);
+
+    debug!("Using account: {:?}", a);
+
+    Ok(a)
+}
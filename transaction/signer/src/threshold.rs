@@ -0,0 +1,779 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Threshold signing, where the spend private key of an account is split into
+//! key shares held by separate parties, any `threshold` of which must cooperate
+//! to sign a transaction.
+//!
+//! The [ThresholdRingSigner] coordinates signing sessions with a set of
+//! [ThresholdParty] implementations. Each round of a session carries the
+//! transaction being signed, so that parties can recompute the message rather
+//! than signing whatever the coordinator sends them. [LocalThresholdParty]
+//! holds its key share in-process.
+//!
+//! Note that splitting an existing account relies on a trusted dealer: the
+//! whole spend private key exists wherever the split is made, and the original
+//! mnemonic can still spend alone.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use displaydoc::Display;
+use log::warn;
+use rand_core::{CryptoRng, CryptoRngCore, OsRng, RngCore};
+
+use mc_core::{
+    account::Account,
+    keys::{RootSpendPrivate, RootViewPrivate},
+    subaddress::Subaddress,
+};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
+use mc_crypto_ring_signature::{
+    generators, onetime_keys::recover_onetime_private_key, split_private_key,
+    Error as RingSignatureError, KeyShare, NonceCommitment, NonceReveal, PartialResponse,
+    RingMLSAG, Scalar, ThresholdMlsagCoordinator, ThresholdMlsagNonce,
+};
+use mc_crypto_ring_signature_signer::{Error, OneTimeKeyDeriveData, RingSigner, SignableInputRing};
+use mc_transaction_core::ring_ct::{
+    compute_mlsag_signing_digest, Error as RingCtError, InputRing, SignatureRctBulletproofs,
+    SigningData,
+};
+
+use crate::{
+    traits::ThresholdParty,
+    types::{
+        ThresholdAbortReq, ThresholdAccountInfo, ThresholdCommitReq, ThresholdRespondReq,
+        ThresholdRevealReq, ThresholdSignContext, TxSignReq,
+    },
+};
+
+impl ThresholdAccountInfo {
+    /// Split the spend private key of an account into `num_shares` key shares,
+    /// any `threshold` of which are needed to sign
+    pub fn split(
+        account: &Account,
+        account_index: u32,
+        threshold: u32,
+        num_shares: u32,
+        rng: impl CryptoRngCore,
+    ) -> Result<(Self, Vec<KeyShare>), RingSignatureError> {
+        let key_shares = split_private_key(
+            &Scalar::from(account.spend_private_key()),
+            threshold,
+            num_shares,
+            rng,
+        )?;
+
+        let info = Self {
+            view_private: account.view_private_key().clone(),
+            spend_public: account.spend_public_key(),
+            account_index,
+            threshold,
+            public_shares: key_shares.iter().map(KeyShare::public_share).collect(),
+        };
+
+        Ok((info, key_shares))
+    }
+}
+
+impl ThresholdSignContext {
+    /// Create the context for signing a transaction with the provided signing
+    /// data
+    pub fn new(tx_sign_req: &TxSignReq, signing_data: &SigningData) -> Self {
+        Self {
+            tx_sign_req: tx_sign_req.clone(),
+            pseudo_output_commitments: signing_data.pseudo_output_commitments.clone(),
+            range_proof_bytes: signing_data.range_proof_bytes.clone(),
+            range_proofs: signing_data.range_proofs.clone(),
+        }
+    }
+
+    /// Recompute the MLSAG signing digest of the transaction
+    pub fn signing_digest(&self) -> Result<Vec<u8>, ThresholdError> {
+        let req = &self.tx_sign_req;
+        let (digest, _tx_summary, _extended_message) = compute_mlsag_signing_digest(
+            req.block_version,
+            &req.tx_prefix,
+            &self.pseudo_output_commitments,
+            &self.range_proof_bytes,
+            &self.range_proofs,
+        )
+        .map_err(|_| ThresholdError::InvalidTransaction)?;
+
+        Ok(digest.into())
+    }
+
+    /// Find the ring whose real input has the onetime public key
+    /// `target_key`, along with its index in the transaction
+    pub fn ring(
+        &self,
+        target_key: &RistrettoPublic,
+    ) -> Result<(usize, &SignableInputRing), ThresholdError> {
+        let target_key = CompressedRistrettoPublic::from(target_key);
+
+        self.tx_sign_req
+            .rings
+            .iter()
+            .enumerate()
+            .find_map(|(i, ring)| match ring {
+                InputRing::Signable(ring)
+                    if ring
+                        .members
+                        .get(ring.real_input_index)
+                        .map(|m| m.target_key)
+                        == Some(target_key) =>
+                {
+                    Some((i, ring))
+                }
+                _ => None,
+            })
+            .ok_or(ThresholdError::UnknownInput)
+    }
+}
+
+/// An error returned by a [LocalThresholdParty]
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum ThresholdError {
+    /// Ring signature: {0}
+    RingSignature(RingSignatureError),
+
+    /// The transaction being signed is malformed
+    InvalidTransaction,
+
+    /// The real input is not spent by the transaction being signed
+    UnknownInput,
+
+    /// The challenge does not match the transaction being signed
+    ContextMismatch,
+
+    /// Unknown or expired signing session `{0}`
+    UnknownSession(u64),
+}
+
+impl From<RingSignatureError> for ThresholdError {
+    fn from(src: RingSignatureError) -> Self {
+        Self::RingSignature(src)
+    }
+}
+
+/// Time after which a [LocalThresholdParty] discards an unfinished signing
+/// session
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A signing session in progress
+struct Session {
+    nonce: ThresholdMlsagNonce,
+
+    /// Onetime public key of the real input being spent
+    target_key: RistrettoPublic,
+
+    /// Signing digest of the transaction, computed before committing
+    message: Vec<u8>,
+
+    /// When the party committed
+    started: Instant,
+}
+
+/// [ThresholdParty] implementation holding its key share in-process
+pub struct LocalThresholdParty {
+    key_share: KeyShare,
+
+    /// Signing sessions in progress
+    sessions: Mutex<HashMap<u64, Session>>,
+
+    /// Time after which an unfinished signing session is discarded
+    session_timeout: Duration,
+}
+
+impl From<KeyShare> for LocalThresholdParty {
+    fn from(key_share: KeyShare) -> Self {
+        Self {
+            key_share,
+            sessions: Mutex::new(HashMap::new()),
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+        }
+    }
+}
+
+impl LocalThresholdParty {
+    /// Discard unfinished signing sessions after `session_timeout`, rather
+    /// than [DEFAULT_SESSION_TIMEOUT]
+    pub fn with_session_timeout(mut self, session_timeout: Duration) -> Self {
+        self.session_timeout = session_timeout;
+        self
+    }
+
+    fn is_expired(&self, session: &Session) -> bool {
+        session.started.elapsed() >= self.session_timeout
+    }
+}
+
+impl ThresholdParty for LocalThresholdParty {
+    type Error = ThresholdError;
+
+    fn index(&self) -> u32 {
+        self.key_share.index
+    }
+
+    fn commit(&self, req: &ThresholdCommitReq) -> Result<NonceCommitment, Self::Error> {
+        // Check the input is spent by the transaction, and compute the message
+        // to be signed before committing to anything
+        req.context.ring(&req.target_key)?;
+        let message = req.context.signing_digest()?;
+
+        let nonce = ThresholdMlsagNonce::new(&self.key_share, &req.target_key, OsRng);
+        let commitment = nonce.commitment();
+
+        let mut sessions = self.sessions.lock().expect("mutex poisoned");
+        sessions.retain(|_, session| !self.is_expired(session));
+        sessions.insert(
+            req.session_id,
+            Session {
+                nonce,
+                target_key: req.target_key,
+                message,
+                started: Instant::now(),
+            },
+        );
+
+        Ok(commitment)
+    }
+
+    fn reveal(&self, req: &ThresholdRevealReq) -> Result<NonceReveal, Self::Error> {
+        let mut sessions = self.sessions.lock().expect("mutex poisoned");
+        let session = sessions
+            .get_mut(&req.session_id)
+            .filter(|session| !self.is_expired(session))
+            .ok_or(ThresholdError::UnknownSession(req.session_id))?;
+
+        Ok(session.nonce.reveal(&req.commitments)?)
+    }
+
+    fn respond(&self, req: &ThresholdRespondReq) -> Result<PartialResponse, Self::Error> {
+        let session = self
+            .sessions
+            .lock()
+            .expect("mutex poisoned")
+            .remove(&req.session_id)
+            .filter(|session| !self.is_expired(session))
+            .ok_or(ThresholdError::UnknownSession(req.session_id))?;
+
+        // Check again that the challenge is for the transaction and ring
+        // committed to
+        let (ring_index, ring) = req.context.ring(&session.target_key)?;
+        let challenge = &req.challenge;
+        if req.context.signing_digest()? != session.message
+            || challenge.message != session.message
+            || challenge.ring != ring.members
+            || challenge.real_index != ring.real_input_index
+            || req.context.pseudo_output_commitments.get(ring_index)
+                != Some(&challenge.output_commitment)
+        {
+            return Err(ThresholdError::ContextMismatch);
+        }
+
+        Ok(session.nonce.respond(&self.key_share, challenge)?)
+    }
+
+    fn abort(&self, req: &ThresholdAbortReq) -> Result<(), Self::Error> {
+        self.sessions
+            .lock()
+            .expect("mutex poisoned")
+            .remove(&req.session_id);
+
+        Ok(())
+    }
+}
+
+/// Coordinator of threshold signing sessions, signing each ring of a
+/// transaction with the parties
+pub struct ThresholdRingSigner<P: ThresholdParty> {
+    account: ThresholdAccountInfo,
+    parties: Vec<P>,
+}
+
+impl<P: ThresholdParty> ThresholdRingSigner<P> {
+    /// Create a threshold ring signer for an account, signing with the
+    /// provided parties
+    pub fn new(account: ThresholdAccountInfo, parties: Vec<P>) -> Self {
+        Self { account, parties }
+    }
+
+    /// Sign a transaction, sending it to the parties with each round so they
+    /// can check what they are signing
+    pub fn sign_tx<RNG: CryptoRng + RngCore>(
+        &self,
+        req: &TxSignReq,
+        rng: &mut RNG,
+    ) -> Result<SignatureRctBulletproofs, RingCtError> {
+        let (signing_data, _tx_summary, _unblinding_data, _extended_message) =
+            req.get_signing_data(rng)?;
+
+        let signer = ThresholdTxSigner {
+            signer: self,
+            context: ThresholdSignContext::new(req, &signing_data),
+        };
+        signing_data.sign(&req.rings, &signer, rng)
+    }
+
+    /// Compute the part of a onetime private key that does not depend on the
+    /// spend private key.
+    fn key_offset(
+        &self,
+        tx_out_public_key: &RistrettoPublic,
+        subaddress_index: u64,
+    ) -> RistrettoPrivate {
+        // The subaddress spend private key is `Hs(a || i) + b`, so deriving it
+        // with a zero spend private key yields `Hs(a || i)`.
+        let zero = RootSpendPrivate::from(Scalar::ZERO);
+        let (_view_private, spend_private) =
+            (&self.account.view_private, &zero).subaddress(subaddress_index);
+
+        recover_onetime_private_key(
+            tx_out_public_key,
+            self.account.view_private.as_ref(),
+            spend_private.as_ref(),
+        )
+    }
+}
+
+/// [RingSigner] for the rings of a single transaction, sending the
+/// transaction to the parties along with each ring
+struct ThresholdTxSigner<'a, P: ThresholdParty> {
+    signer: &'a ThresholdRingSigner<P>,
+    context: ThresholdSignContext,
+}
+
+impl<P: ThresholdParty> ThresholdTxSigner<'_, P> {
+    fn party_error(party: &P, e: P::Error) -> Error {
+        Error::ConnectionFailed(format!("threshold party {}: {:?}", party.index(), e))
+    }
+
+    /// Second and third rounds, with the parties that committed
+    fn finish_session(
+        &self,
+        mut coordinator: ThresholdMlsagCoordinator,
+        session_id: u64,
+        parties: &[&P],
+        commitments: Vec<NonceCommitment>,
+    ) -> Result<RingMLSAG, Error> {
+        coordinator.add_commitments(&commitments)?;
+
+        // Second round
+        let reveal_req = ThresholdRevealReq {
+            session_id,
+            commitments,
+        };
+        let reveals = parties
+            .iter()
+            .map(|party| {
+                party
+                    .reveal(&reveal_req)
+                    .map_err(|e| Self::party_error(party, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let challenge = coordinator.challenge(&reveals)?;
+
+        // Third round
+        let respond_req = ThresholdRespondReq {
+            session_id,
+            challenge,
+            context: self.context.clone(),
+        };
+        let partial_responses = parties
+            .iter()
+            .map(|party| {
+                party
+                    .respond(&respond_req)
+                    .map_err(|e| Self::party_error(party, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(coordinator.finalize(&partial_responses)?)
+    }
+}
+
+impl<P: ThresholdParty> RingSigner for ThresholdTxSigner<'_, P> {
+    fn sign(
+        &self,
+        message: &[u8],
+        ring: &SignableInputRing,
+        pseudo_output_blinding: Scalar,
+        rng: &mut dyn CryptoRngCore,
+    ) -> Result<RingMLSAG, Error> {
+        let real_input = ring
+            .members
+            .get(ring.real_input_index)
+            .ok_or(Error::RealInputIndexOutOfBounds)?;
+        let target_key = RistrettoPublic::try_from(&real_input.target_key)?;
+
+        // Note: Some implementations might be able to cache this generator
+        let generator = generators(*ring.input_secret.amount.token_id);
+
+        let key_offset = match ring.input_secret.onetime_key_derive_data {
+            // When the whole one-time private key is provided (e.g. for gift
+            // codes) the parties are not needed
+            OneTimeKeyDeriveData::OneTimeKey(key) => {
+                if RistrettoPublic::from(&key) != target_key {
+                    return Err(Error::TrueInputNotOwned);
+                }
+
+                return Ok(RingMLSAG::sign(
+                    message,
+                    &ring.members,
+                    ring.real_input_index,
+                    &key,
+                    ring.input_secret.amount.value,
+                    &ring.input_secret.blinding,
+                    &pseudo_output_blinding,
+                    &generator,
+                    rng,
+                )?);
+            }
+            OneTimeKeyDeriveData::SubaddressIndex(subaddress_index) => {
+                let public_key = RistrettoPublic::try_from(&real_input.public_key)?;
+                self.signer.key_offset(&public_key, subaddress_index)
+            }
+        };
+
+        // Check the real input belongs to this account, `P = k * G + B`
+        let account = &self.signer.account;
+        let spend_public: &RistrettoPublic = account.spend_public.as_ref();
+        let offset_public = RistrettoPublic::from(&key_offset);
+        if RistrettoPublic::from(offset_public.as_ref() + spend_public.as_ref()) != target_key {
+            return Err(Error::TrueInputNotOwned);
+        }
+
+        let coordinator = ThresholdMlsagCoordinator::new(
+            message,
+            &ring.members,
+            ring.real_input_index,
+            key_offset.as_ref(),
+            ring.input_secret.amount.value,
+            &ring.input_secret.blinding,
+            &pseudo_output_blinding,
+            &generator,
+            account.threshold,
+            &account.public_shares,
+            &mut *rng,
+        )?;
+        let session_id = rng.next_u64();
+
+        // First round, any parties that respond may take part as long as
+        // there are enough of them
+        let commit_req = ThresholdCommitReq {
+            session_id,
+            target_key,
+            context: self.context.clone(),
+        };
+        let mut parties = vec![];
+        let mut commitments = vec![];
+        for party in &self.signer.parties {
+            match party.commit(&commit_req) {
+                Ok(commitment) => {
+                    parties.push(party);
+                    commitments.push(commitment);
+                }
+                Err(e) => warn!(
+                    "Threshold party {} failed to commit: {:?}",
+                    party.index(),
+                    e
+                ),
+            }
+        }
+
+        let result = self.finish_session(coordinator, session_id, &parties, commitments);
+
+        // Parties that committed must not keep their nonces when the session
+        // fails
+        if result.is_err() {
+            let abort_req = ThresholdAbortReq { session_id };
+            for party in &parties {
+                if let Err(e) = party.abort(&abort_req) {
+                    warn!(
+                        "Threshold party {} failed to abort session: {:?}",
+                        party.index(),
+                        e
+                    );
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mc_crypto_ring_signature::{get_tx_out_shared_secret, ReducedTxOut};
+    use mc_crypto_ring_signature_signer::{InputSecret, LocalRingSigner};
+    use mc_transaction_core::{
+        ring_ct::{OutputSecret, SignedInputRing},
+        tokens::Mob,
+        tx::{TxIn, TxOut, TxPrefix},
+        AccountKey, Amount, BlockVersion, Token,
+    };
+
+    use crate::types::TxSignSecrets;
+
+    fn random_private_key(rng: &mut OsRng) -> RistrettoPrivate {
+        RistrettoPrivate::from(Scalar::random(rng))
+    }
+
+    fn tx_out(value: u64, address: &mc_transaction_core::PublicAddress, rng: &mut OsRng) -> TxOut {
+        TxOut::new(
+            BlockVersion::MAX,
+            Amount::new(value, Mob::ID),
+            address,
+            &random_private_key(rng),
+            Default::default(),
+        )
+        .unwrap()
+    }
+
+    /// Recover the amount and blinding of a TxOut sent to `account_key`
+    fn unmask(account_key: &AccountKey, tx_out: &TxOut) -> (Amount, Scalar) {
+        let shared_secret = get_tx_out_shared_secret(
+            account_key.view_private_key(),
+            &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+        );
+        tx_out
+            .get_masked_amount()
+            .unwrap()
+            .get_value(&shared_secret)
+            .unwrap()
+    }
+
+    /// A threshold account with three key shares, any two of which can sign,
+    /// and a request to spend a TxOut it received at subaddress 3
+    fn setup(rng: &mut OsRng) -> (AccountKey, ThresholdAccountInfo, Vec<KeyShare>, TxSignReq) {
+        let account = Account::new(
+            RootViewPrivate::from(random_private_key(rng)),
+            RootSpendPrivate::from(random_private_key(rng)),
+        );
+        let account_key = AccountKey::new(
+            account.spend_private_key().as_ref(),
+            account.view_private_key().as_ref(),
+        );
+        let (account_info, key_shares) =
+            ThresholdAccountInfo::split(&account, 0, 2, 3, &mut *rng).unwrap();
+
+        let real_tx_out = tx_out(1000, &account_key.subaddress(3), rng);
+        let (amount, blinding) = unmask(&account_key, &real_tx_out);
+
+        let mut ring_tx_outs: Vec<TxOut> = (0..10)
+            .map(|_| {
+                let other = AccountKey::new(&random_private_key(rng), &random_private_key(rng));
+                tx_out(1000, &other.default_subaddress(), rng)
+            })
+            .collect();
+        ring_tx_outs.insert(4, real_tx_out);
+
+        let ring = SignableInputRing {
+            members: ring_tx_outs
+                .iter()
+                .map(|tx_out| ReducedTxOut::try_from(tx_out).unwrap())
+                .collect(),
+            real_input_index: 4,
+            input_secret: InputSecret {
+                onetime_key_derive_data: OneTimeKeyDeriveData::SubaddressIndex(3),
+                amount,
+                blinding,
+            },
+        };
+
+        let recipient = AccountKey::new(&random_private_key(rng), &random_private_key(rng));
+        let output = tx_out(900, &recipient.default_subaddress(), rng);
+        let (output_amount, output_blinding) = unmask(&recipient, &output);
+
+        let tx_prefix = TxPrefix::new(
+            vec![TxIn {
+                ring: ring_tx_outs,
+                proofs: vec![],
+                input_rules: None,
+            }],
+            vec![output],
+            Amount::new(100, Mob::ID),
+            1000,
+        );
+
+        let req = TxSignReq {
+            account_id: [0u8; 32].into(),
+            tx_prefix,
+            rings: vec![InputRing::Signable(ring)],
+            secrets: TxSignSecrets::OutputSecrets(vec![OutputSecret {
+                amount: output_amount,
+                blinding: output_blinding,
+            }]),
+            block_version: BlockVersion::MAX,
+        };
+
+        (account_key, account_info, key_shares, req)
+    }
+
+    fn signable_ring(req: &TxSignReq) -> &SignableInputRing {
+        match &req.rings[0] {
+            InputRing::Signable(ring) => ring,
+            InputRing::Presigned(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_threshold_sign_tx() {
+        let mut rng = OsRng;
+        let (account_key, account_info, key_shares, req) = setup(&mut rng);
+
+        let expected = SignatureRctBulletproofs::sign(
+            req.block_version,
+            &req.tx_prefix,
+            &req.rings,
+            &req.output_secrets(),
+            Amount::new(req.tx_prefix.fee, Mob::ID),
+            &LocalRingSigner::from(&account_key),
+            &mut rng,
+        )
+        .unwrap();
+
+        let signed_rings = vec![SignedInputRing::from(signable_ring(&req))];
+        let output_commitments: Vec<_> = req
+            .tx_prefix
+            .output_commitments()
+            .unwrap()
+            .into_iter()
+            .cloned()
+            .collect();
+
+        // Any two of the three parties can sign, producing the same key image as
+        // the whole account key
+        for indices in [[0, 1], [1, 2], [2, 0]] {
+            let parties: Vec<_> = indices
+                .iter()
+                .map(|&i| LocalThresholdParty::from(key_shares[i].clone()))
+                .collect();
+            let signer = ThresholdRingSigner::new(account_info.clone(), parties);
+
+            let signature = signer.sign_tx(&req, &mut rng).unwrap();
+            signature
+                .verify(
+                    req.block_version,
+                    &req.tx_prefix,
+                    &signed_rings,
+                    &output_commitments,
+                    Amount::new(req.tx_prefix.fee, Mob::ID),
+                    &mut rng,
+                )
+                .unwrap();
+            assert_eq!(
+                signature.ring_signatures[0].key_image,
+                expected.ring_signatures[0].key_image
+            );
+
+            // Finished sessions are not kept
+            for party in &signer.parties {
+                assert!(party.sessions.lock().unwrap().is_empty());
+            }
+        }
+
+        // A single party cannot, and its nonce is discarded
+        let signer = ThresholdRingSigner::new(
+            account_info,
+            vec![LocalThresholdParty::from(key_shares[0].clone())],
+        );
+        assert!(signer.sign_tx(&req, &mut rng).is_err());
+        assert!(signer.parties[0].sessions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parties_check_message() {
+        let mut rng = OsRng;
+        let (_account_key, account_info, key_shares, req) = setup(&mut rng);
+        let parties = key_shares[..2]
+            .iter()
+            .cloned()
+            .map(LocalThresholdParty::from)
+            .collect();
+        let signer = ThresholdRingSigner::new(account_info, parties);
+
+        let (signing_data, ..) = req.get_signing_data(&mut rng).unwrap();
+        let tx_signer = ThresholdTxSigner {
+            signer: &signer,
+            context: ThresholdSignContext::new(&req, &signing_data),
+        };
+        let ring = signable_ring(&req);
+        let pseudo_output_blinding = signing_data.pseudo_output_blindings[0];
+
+        // A coordinator asking for a signature of anything but the transaction
+        // is refused, and the session is aborted
+        let result = tx_signer.sign(
+            b"not the transaction",
+            ring,
+            pseudo_output_blinding,
+            &mut rng,
+        );
+        assert!(matches!(result, Err(Error::ConnectionFailed(_))));
+        for party in &signer.parties {
+            assert!(party.sessions.lock().unwrap().is_empty());
+        }
+
+        // Signing the transaction's own digest succeeds
+        let message = tx_signer.context.signing_digest().unwrap();
+        assert_eq!(message, signing_data.mlsag_signing_digest);
+        tx_signer
+            .sign(&message, ring, pseudo_output_blinding, &mut rng)
+            .unwrap();
+
+        // Parties refuse to commit to inputs the transaction does not spend
+        let other = RistrettoPublic::from(&random_private_key(&mut rng));
+        let commit_req = ThresholdCommitReq {
+            session_id: 1,
+            target_key: other,
+            context: tx_signer.context.clone(),
+        };
+        assert_eq!(
+            signer.parties[0].commit(&commit_req),
+            Err(ThresholdError::UnknownInput)
+        );
+    }
+
+    #[test]
+    fn test_sessions_expire() {
+        let mut rng = OsRng;
+        let (_account_key, _account_info, key_shares, req) = setup(&mut rng);
+        let party =
+            LocalThresholdParty::from(key_shares[0].clone()).with_session_timeout(Duration::ZERO);
+
+        let (signing_data, ..) = req.get_signing_data(&mut rng).unwrap();
+        let target_key = RistrettoPublic::try_from(
+            &signable_ring(&req).members[signable_ring(&req).real_input_index].target_key,
+        )
+        .unwrap();
+        let commit_req = ThresholdCommitReq {
+            session_id: 1,
+            target_key,
+            context: ThresholdSignContext::new(&req, &signing_data),
+        };
+        let commitment = party.commit(&commit_req).unwrap();
+
+        let reveal_req = ThresholdRevealReq {
+            session_id: 1,
+            commitments: vec![commitment],
+        };
+        assert_eq!(
+            party.reveal(&reveal_req),
+            Err(ThresholdError::UnknownSession(1))
+        );
+
+        // Expired sessions are dropped when the next one starts
+        party
+            .commit(&ThresholdCommitReq {
+                session_id: 2,
+                ..commit_req
+            })
+            .unwrap();
+        let sessions = party.sessions.lock().unwrap();
+        assert_eq!(sessions.keys().collect::<Vec<_>>(), vec![&2]);
+    }
+}
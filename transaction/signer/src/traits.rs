@@ -12,7 +12,14 @@ use mc_core::{
     subaddress::Subaddress,
 };
 
-use mc_crypto_ring_signature::{onetime_keys::recover_onetime_private_key, KeyImage};
+use mc_crypto_ring_signature::{
+    onetime_keys::recover_onetime_private_key, KeyImage, NonceCommitment, NonceReveal,
+    PartialResponse,
+};
+
+use crate::types::{
+    ThresholdAbortReq, ThresholdCommitReq, ThresholdRespondReq, ThresholdRevealReq,
+};
 
 /// View only account provider
 pub trait ViewAccountProvider {
@@ -129,3 +136,55 @@ impl<T: MemoHmacSigner> MemoHmacSigner for &T {
         )
     }
 }
+
+/// A party holding a share of the spend private key of a threshold account,
+/// as seen by the coordinator of a threshold signing session.
+///
+/// Signing each ring takes three rounds, see
+/// [mc_crypto_ring_signature::ThresholdMlsagCoordinator]. The first and last
+/// rounds carry the transaction being signed, which parties should check
+/// against the message before committing to or responding to a challenge.
+pub trait ThresholdParty {
+    /// [`ThresholdParty`] error
+    type Error: Send + Sync + Debug;
+
+    /// Index of the key share held by this party
+    fn index(&self) -> u32;
+
+    /// First round, commit to a nonce for the signing session
+    fn commit(&self, req: &ThresholdCommitReq) -> Result<NonceCommitment, Self::Error>;
+
+    /// Second round, reveal the committed nonce
+    fn reveal(&self, req: &ThresholdRevealReq) -> Result<NonceReveal, Self::Error>;
+
+    /// Third round, respond to the challenge, ending the signing session
+    fn respond(&self, req: &ThresholdRespondReq) -> Result<PartialResponse, Self::Error>;
+
+    /// Abort the signing session, discarding its nonce
+    fn abort(&self, req: &ThresholdAbortReq) -> Result<(), Self::Error>;
+}
+
+/// Threshold party impl for reference types
+impl<T: ThresholdParty> ThresholdParty for &T {
+    type Error = <T as ThresholdParty>::Error;
+
+    fn index(&self) -> u32 {
+        <T as ThresholdParty>::index(self)
+    }
+
+    fn commit(&self, req: &ThresholdCommitReq) -> Result<NonceCommitment, Self::Error> {
+        <T as ThresholdParty>::commit(self, req)
+    }
+
+    fn reveal(&self, req: &ThresholdRevealReq) -> Result<NonceReveal, Self::Error> {
+        <T as ThresholdParty>::reveal(self, req)
+    }
+
+    fn respond(&self, req: &ThresholdRespondReq) -> Result<PartialResponse, Self::Error> {
+        <T as ThresholdParty>::respond(self, req)
+    }
+
+    fn abort(&self, req: &ThresholdAbortReq) -> Result<(), Self::Error> {
+        <T as ThresholdParty>::abort(self, req)
+    }
+}
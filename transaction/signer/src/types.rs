@@ -5,7 +5,10 @@
 
 use mc_core::keys::{RootSpendPublic, RootViewPrivate, TxOutPublic};
 
-use mc_crypto_ring_signature::KeyImage;
+use mc_crypto_keys::RistrettoPublic;
+use mc_crypto_ring_signature::{
+    CompressedCommitment, KeyImage, NonceCommitment, PublicKeyShare, ThresholdMlsagChallenge,
+};
 use mc_transaction_core::{
     ring_ct::{InputRing, OutputSecret},
     tx::{Tx, TxPrefix},
@@ -37,6 +40,97 @@ impl From<AccountInfo> for mc_core::account::ViewAccount {
     }
 }
 
+/// Credentials of a threshold account, whose spend private key is split into
+/// key shares held by separate parties. Produced when splitting the spend key,
+/// for use by the coordinator of threshold signing sessions.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdAccountInfo {
+    /// Root view private key
+    #[serde(with = "pri_key_hex")]
+    pub view_private: RootViewPrivate,
+
+    /// Root spend public key
+    #[serde(with = "pub_key_hex")]
+    pub spend_public: RootSpendPublic,
+
+    /// SLIP-0010 account index used for key derivation
+    pub account_index: u32,
+
+    /// Number of parties needed to sign
+    pub threshold: u32,
+
+    /// Public key shares of every party, used to check their responses
+    pub public_shares: Vec<PublicKeyShare>,
+}
+
+/// Transaction being signed in a threshold signing session, sent to each
+/// party so it can recompute the message it is asked to sign
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdSignContext {
+    /// Transaction signing request
+    pub tx_sign_req: TxSignReq,
+
+    /// Pseudo-output commitments, one for each ring
+    pub pseudo_output_commitments: Vec<CompressedCommitment>,
+
+    /// Aggregate range proof, for block versions before 3
+    pub range_proof_bytes: Vec<u8>,
+
+    /// Range proofs, one for each token, for block version 3 and later
+    pub range_proofs: Vec<Vec<u8>>,
+}
+
+/// First round of a threshold signing session, issued by the coordinator to
+/// each party
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdCommitReq {
+    /// Signing session ID, unique to the ring being signed
+    #[serde(with = "string")]
+    pub session_id: u64,
+
+    /// Onetime public key of the real input being spent
+    pub target_key: RistrettoPublic,
+
+    /// Transaction being signed
+    pub context: ThresholdSignContext,
+}
+
+/// Second round of a threshold signing session, issued by the coordinator to
+/// each party once every commitment has been collected
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdRevealReq {
+    /// Signing session ID
+    #[serde(with = "string")]
+    pub session_id: u64,
+
+    /// Nonce commitments of every participating party
+    pub commitments: Vec<NonceCommitment>,
+}
+
+/// Third round of a threshold signing session, issued by the coordinator to
+/// each party once every nonce has been revealed
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdRespondReq {
+    /// Signing session ID
+    #[serde(with = "string")]
+    pub session_id: u64,
+
+    /// Challenge to respond to
+    pub challenge: ThresholdMlsagChallenge,
+
+    /// Transaction being signed
+    pub context: ThresholdSignContext,
+}
+
+/// Abort a threshold signing session, issued by the coordinator to each party
+/// that committed when the session cannot be completed
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ThresholdAbortReq {
+    /// Signing session ID
+    #[serde(with = "string")]
+    pub session_id: u64,
+}
+
 /// Request to sync TxOuts for the provided account, issued by full-service
 /// to support key image scanning.
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -0,0 +1,49 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! A utility to verify a ledger's integrity, printing a JSON report of the
+//! first inconsistency found.
+
+#![deny(missing_docs)]
+
+use clap::Parser;
+use mc_blockchain_validators::MetadataValidator;
+use mc_ledger_db::LedgerDB;
+use mc_util_dump_ledger::verify_ledger;
+use std::{path::PathBuf, process::exit};
+
+/// Configuration.
+#[derive(Debug, Parser)]
+struct Config {
+    /// Path to [LedgerDB].
+    #[clap(long, env = "MC_LEDGER_DB")]
+    pub ledger_db: PathBuf,
+
+    /// Optional path to a `metadata-signers.toml` file. If provided, block
+    /// metadata signing keys are validated against it; otherwise only the
+    /// metadata signatures are checked.
+    #[clap(long, env = "MC_METADATA_SIGNERS")]
+    pub metadata_signers: Option<PathBuf>,
+}
+
+fn main() {
+    mc_common::setup_panic_handler();
+
+    let config = Config::parse();
+
+    let ledger_db = LedgerDB::open(&config.ledger_db).expect("failed to open LedgerDB");
+    let metadata_validator = config
+        .metadata_signers
+        .map(|path| MetadataValidator::new(path).expect("failed to load metadata signers"));
+
+    let report =
+        verify_ledger(&ledger_db, metadata_validator.as_ref()).expect("failed to verify LedgerDB");
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("failed to serialize report")
+    );
+
+    if !report.is_ok() {
+        exit(1);
+    }
+}
@@ -1,10 +1,11 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
-//! A utility to dump a ledger's contents as JSON.
+//! Utilities to dump a ledger's contents as JSON, and to verify its integrity.
 
 #![deny(missing_docs)]
 
 mod error;
+pub mod verify;
 
 use clap::Parser;
 use mc_blockchain_types::BlockIndex;
@@ -12,6 +13,7 @@ use mc_ledger_db::Ledger;
 use serde_json::to_string_pretty as to_json;
 
 pub use error::{Error, Result};
+pub use verify::{verify_ledger, VerifyReport};
 
 /// Parameters for [dump_ledger].
 #[derive(Debug, Default, Clone, Parser)]
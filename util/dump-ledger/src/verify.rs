@@ -0,0 +1,538 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Ledger integrity verification.
+//!
+//! Walks every block of a [Ledger] and recomputes everything that can be
+//! derived from the stored data, stopping at the first inconsistency.
+
+use crate::Error;
+use mc_blockchain_types::{Block, BlockID, BlockIndex, BlockVersion};
+use mc_blockchain_validators::MetadataValidator;
+use mc_common::HashSet;
+use mc_ledger_db::{Error as LedgerError, Ledger};
+use mc_transaction_core::{
+    membership_proofs::{hash_leaf, hash_nodes, Range, NIL_HASH},
+    tx::TxOutMembershipElement,
+};
+use serde::Serialize;
+
+/// The kind of check that failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// Stored data could not be read or decoded.
+    Read,
+    /// The block's index does not match its position.
+    BlockIndex,
+    /// The block's ID does not match its header.
+    BlockId,
+    /// The block's parent ID does not match the previous block's ID.
+    ParentId,
+    /// The block's contents do not match its contents hash.
+    ContentsHash,
+    /// The block's cumulative TxOut count does not match its contents.
+    CumulativeTxoCount,
+    /// The block's root element does not match the TxOut Merkle tree.
+    RootElement,
+    /// A TxOut index lookup returned the wrong result.
+    TxOutIndex,
+    /// A key image is duplicated, or indexed to the wrong block.
+    KeyImage,
+    /// The block signature is invalid.
+    Signature,
+    /// The block metadata is missing or invalid.
+    Metadata,
+    /// The TxOut Merkle root does not match the TxOuts in the ledger.
+    TxOutMerkleRoot,
+    /// The total number of TxOuts does not match the blocks.
+    NumTxos,
+}
+
+/// A single inconsistency found in the ledger.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Inconsistency {
+    /// The block where the inconsistency was found.
+    pub block_index: BlockIndex,
+    /// The check that failed.
+    pub check: Check,
+    /// Human readable details.
+    pub details: String,
+}
+
+/// The result of verifying a ledger.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct VerifyReport {
+    /// Number of blocks in the ledger.
+    pub num_blocks: u64,
+    /// Number of blocks that passed verification.
+    pub num_blocks_verified: u64,
+    /// Number of blocks whose signature and metadata had been pruned, and so
+    /// could not be verified.
+    pub num_blocks_pruned: u64,
+    /// The first inconsistency found, if any.
+    pub inconsistency: Option<Inconsistency>,
+}
+
+impl VerifyReport {
+    /// Returns true if no inconsistency was found.
+    pub fn is_ok(&self) -> bool {
+        self.inconsistency.is_none()
+    }
+}
+
+/// Verify the integrity of every block in the given [Ledger].
+///
+/// If a `metadata_validator` is provided, block metadata signing keys are
+/// validated against it; otherwise only the metadata signatures are checked.
+pub fn verify_ledger(
+    ledger: &impl Ledger,
+    metadata_validator: Option<&MetadataValidator>,
+) -> Result<VerifyReport, Error> {
+    let num_blocks = ledger.num_blocks()?;
+    let mut verifier = Verifier {
+        ledger,
+        metadata_validator,
+        tx_outs: MerkleAccumulator::default(),
+        key_images: HashSet::default(),
+        parent_id: BlockID::default(),
+        num_blocks_pruned: 0,
+    };
+
+    let mut report = VerifyReport {
+        num_blocks,
+        num_blocks_verified: 0,
+        num_blocks_pruned: 0,
+        inconsistency: None,
+    };
+    for block_index in 0..num_blocks {
+        if let Err(inconsistency) = verifier.verify_block(block_index) {
+            report.inconsistency = Some(inconsistency);
+            break;
+        }
+        report.num_blocks_verified += 1;
+    }
+    if report.is_ok() && num_blocks > 0 {
+        report.inconsistency = verifier.verify_tx_out_store(num_blocks - 1).err();
+    }
+    report.num_blocks_pruned = verifier.num_blocks_pruned;
+
+    Ok(report)
+}
+
+/// State carried from one block to the next while verifying.
+struct Verifier<'a, L: Ledger> {
+    ledger: &'a L,
+    metadata_validator: Option<&'a MetadataValidator>,
+    tx_outs: MerkleAccumulator,
+    key_images: HashSet<[u8; 32]>,
+    parent_id: BlockID,
+    num_blocks_pruned: u64,
+}
+
+impl<'a, L: Ledger> Verifier<'a, L> {
+    fn verify_block(&mut self, block_index: BlockIndex) -> Result<(), Inconsistency> {
+        let fail = |check: Check, details: String| Inconsistency {
+            block_index,
+            check,
+            details,
+        };
+        let read_err = |err: LedgerError| fail(Check::Read, err.to_string());
+
+        let block = self.ledger.get_block(block_index).map_err(read_err)?;
+        let contents = self
+            .ledger
+            .get_block_contents(block_index)
+            .map_err(read_err)?;
+
+        if block.index != block_index {
+            return Err(fail(
+                Check::BlockIndex,
+                format!("block has index {}", block.index),
+            ));
+        }
+        if !block.is_block_id_valid() {
+            return Err(fail(Check::BlockId, format!("stored ID is {}", block.id)));
+        }
+        if block.parent_id != self.parent_id {
+            return Err(fail(
+                Check::ParentId,
+                format!("expected {}, found {}", self.parent_id, block.parent_id),
+            ));
+        }
+        if block.contents_hash != contents.hash() {
+            return Err(fail(
+                Check::ContentsHash,
+                format!("stored hash is {}", hex::encode(block.contents_hash)),
+            ));
+        }
+
+        // The root element captures the TxOut Merkle tree the block was validated
+        // against, i.e. before its own outputs were added. Blocks that were not
+        // formed by consensus (like the origin block) leave it empty.
+        if block.root_element != TxOutMembershipElement::default() {
+            let expected = self
+                .tx_outs
+                .root_element()
+                .map_err(|err| fail(Check::RootElement, format!("{err}")))?;
+            if block.root_element != expected {
+                return Err(fail(
+                    Check::RootElement,
+                    format!(
+                        "expected range {:?}, found {:?}",
+                        expected.range, block.root_element.range
+                    ),
+                ));
+            }
+        }
+
+        for tx_out in &contents.outputs {
+            let tx_out_index = self.tx_outs.len();
+            let by_public_key = self
+                .ledger
+                .get_tx_out_index_by_public_key(&tx_out.public_key);
+            let by_hash = self.ledger.get_tx_out_index_by_hash(&tx_out.hash());
+            let block_by_index = self.ledger.get_block_index_by_tx_out_index(tx_out_index);
+            if by_public_key != Ok(tx_out_index)
+                || by_hash != Ok(tx_out_index)
+                || block_by_index != Ok(block_index)
+            {
+                return Err(fail(
+                    Check::TxOutIndex,
+                    format!(
+                        "TxOut {tx_out_index}: by public key {by_public_key:?}, by hash {by_hash:?}, block {block_by_index:?}"
+                    ),
+                ));
+            }
+            self.tx_outs.push(hash_leaf(tx_out));
+        }
+        if block.cumulative_txo_count != self.tx_outs.len() {
+            return Err(fail(
+                Check::CumulativeTxoCount,
+                format!(
+                    "expected {}, found {}",
+                    self.tx_outs.len(),
+                    block.cumulative_txo_count
+                ),
+            ));
+        }
+
+        for key_image in &contents.key_images {
+            if !self.key_images.insert(*key_image.as_bytes()) {
+                return Err(fail(
+                    Check::KeyImage,
+                    format!("{key_image:?} was already spent"),
+                ));
+            }
+            match self.ledger.check_key_image(key_image) {
+                Ok(Some(index)) if index == block_index => {}
+                result => {
+                    return Err(fail(
+                        Check::KeyImage,
+                        format!("{key_image:?} is indexed as {result:?}"),
+                    ))
+                }
+            }
+        }
+
+        self.verify_signature_and_metadata(&block, block_index)?;
+
+        self.parent_id = block.id;
+        Ok(())
+    }
+
+    fn verify_signature_and_metadata(
+        &mut self,
+        block: &Block,
+        block_index: BlockIndex,
+    ) -> Result<(), Inconsistency> {
+        let fail = |check: Check, details: String| Inconsistency {
+            block_index,
+            check,
+            details,
+        };
+
+        let mut pruned = false;
+        match self.ledger.get_block_signature(block_index) {
+            Ok(signature) => signature
+                .verify(block)
+                .map_err(|err| fail(Check::Signature, err.to_string()))?,
+            // Signatures are optional.
+            Err(LedgerError::NotFound) => {}
+            Err(LedgerError::Pruned) => pruned = true,
+            Err(err) => return Err(fail(Check::Read, err.to_string())),
+        }
+
+        match self.ledger.get_block_metadata(block_index) {
+            Ok(metadata) => {
+                if metadata.contents().block_id() != &block.id {
+                    return Err(fail(
+                        Check::Metadata,
+                        format!("metadata is for block {}", metadata.contents().block_id()),
+                    ));
+                }
+                match self.metadata_validator {
+                    Some(validator) => validator.validate(&metadata, block_index),
+                    None => metadata.verify().map_err(Into::into),
+                }
+                .map_err(|err| fail(Check::Metadata, err.to_string()))?;
+            }
+            Err(LedgerError::NotFound) => {
+                let requires_metadata = BlockVersion::try_from(block.version)
+                    .map(|version| version.require_block_metadata())
+                    .unwrap_or(false);
+                if requires_metadata {
+                    return Err(fail(Check::Metadata, "missing metadata".to_string()));
+                }
+            }
+            Err(LedgerError::Pruned) => pruned = true,
+            Err(err) => return Err(fail(Check::Read, err.to_string())),
+        }
+
+        if pruned {
+            self.num_blocks_pruned += 1;
+        }
+        Ok(())
+    }
+
+    /// Compare the TxOut store against the TxOuts in the blocks.
+    fn verify_tx_out_store(&self, last_block_index: BlockIndex) -> Result<(), Inconsistency> {
+        let fail = |check: Check, details: String| Inconsistency {
+            block_index: last_block_index,
+            check,
+            details,
+        };
+
+        let num_txos = self
+            .ledger
+            .num_txos()
+            .map_err(|err| fail(Check::Read, err.to_string()))?;
+        if num_txos != self.tx_outs.len() {
+            return Err(fail(
+                Check::NumTxos,
+                format!("expected {}, found {}", self.tx_outs.len(), num_txos),
+            ));
+        }
+
+        let stored = self
+            .ledger
+            .get_root_tx_out_membership_element()
+            .map_err(|err| fail(Check::Read, err.to_string()))?;
+        let expected = self
+            .tx_outs
+            .root_element()
+            .map_err(|err| fail(Check::TxOutMerkleRoot, format!("{err}")))?;
+        if stored != expected {
+            return Err(fail(
+                Check::TxOutMerkleRoot,
+                "stored Merkle tree does not match TxOuts".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Incrementally computes the root of the TxOut Merkle tree as TxOuts are
+/// added, the same way [mc_ledger_db::TxOutStore] does, without keeping every
+/// leaf in memory.
+#[derive(Default)]
+struct MerkleAccumulator {
+    /// `branch[h]` is the hash of the most recently completed subtree with
+    /// `2^h` leaves.
+    branch: Vec<[u8; 32]>,
+    len: u64,
+}
+
+impl MerkleAccumulator {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn push(&mut self, leaf_hash: [u8; 32]) {
+        self.len += 1;
+        let mut size = self.len;
+        let mut node = leaf_hash;
+        let mut height = 0;
+        while size & 1 == 0 {
+            node = hash_nodes(&self.branch[height], &node);
+            size >>= 1;
+            height += 1;
+        }
+        if self.branch.len() <= height {
+            self.branch.resize(height + 1, *NIL_HASH);
+        }
+        self.branch[height] = node;
+    }
+
+    fn root_hash(&self) -> [u8; 32] {
+        if self.len == 0 {
+            return *NIL_HASH;
+        }
+        let depth = self.len.next_power_of_two().trailing_zeros() as usize;
+        if self.len.is_power_of_two() {
+            return self.branch[depth];
+        }
+
+        // Walk up the right edge of the tree. Subtrees without any leaves hash to
+        // the nil hash.
+        let mut node: Option<[u8; 32]> = None;
+        for height in 0..depth {
+            node = if (self.len >> height) & 1 == 1 {
+                Some(hash_nodes(&self.branch[height], &node.unwrap_or(*NIL_HASH)))
+            } else {
+                node.map(|node| hash_nodes(&node, &NIL_HASH))
+            };
+        }
+        node.expect("len is not a power of two, so some bit is set below depth")
+    }
+
+    fn root_element(&self) -> Result<TxOutMembershipElement, LedgerError> {
+        let last = self
+            .len
+            .checked_next_power_of_two()
+            .ok_or(LedgerError::CapacityExceeded)?
+            .saturating_sub(1);
+        Ok(TxOutMembershipElement::new(
+            Range::new(0, last)?,
+            self.root_hash(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_blockchain_test_utils::get_blocks;
+    use mc_ledger_db::test_utils::{create_ledger, mock_ledger::get_mock_ledger_and_blocks};
+    use mc_util_test_helper::get_seeded_rng;
+
+    #[test]
+    fn verify_valid_ledger() {
+        let mut ledger = create_ledger();
+        let blocks = get_blocks(
+            BlockVersion::MAX,
+            7,
+            2,
+            1,
+            3,
+            1 << 20,
+            None,
+            &mut get_seeded_rng(),
+        );
+        for block_data in &blocks {
+            ledger.append_block_data(block_data).unwrap();
+        }
+
+        let report = verify_ledger(&ledger, None).unwrap();
+        assert_eq!(
+            report,
+            VerifyReport {
+                num_blocks: 7,
+                num_blocks_verified: 7,
+                num_blocks_pruned: 0,
+                inconsistency: None,
+            }
+        );
+
+        ledger.enable_pruning(2).unwrap();
+        let report = verify_ledger(&ledger, None).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.num_blocks_pruned, 5);
+    }
+
+    #[test]
+    fn verify_detects_broken_parent_chain() {
+        let (ledger, mut blocks) = get_mock_ledger_and_blocks(5);
+        let mut block = blocks[3].block().clone();
+        block.parent_id = BlockID([7u8; 32]);
+        blocks[3] =
+            mc_blockchain_types::BlockData::new(block, blocks[3].contents().clone(), None, None);
+        ledger
+            .lock()
+            .block_data_by_index
+            .insert(3, blocks[3].clone());
+
+        let report = verify_ledger(&ledger, None).unwrap();
+        assert_eq!(report.num_blocks_verified, 3);
+        let inconsistency = report.inconsistency.unwrap();
+        assert_eq!(inconsistency.block_index, 3);
+        // The block ID covers the parent ID, so that is detected first.
+        assert_eq!(inconsistency.check, Check::BlockId);
+    }
+
+    #[test]
+    fn verify_detects_misindexed_key_image() {
+        let (ledger, blocks) = get_mock_ledger_and_blocks(5);
+        let key_image = blocks[2].contents().key_images[0];
+        ledger.lock().key_images.insert(key_image, 4);
+
+        let report = verify_ledger(&ledger, None).unwrap();
+        let inconsistency = report.inconsistency.unwrap();
+        assert_eq!(inconsistency.block_index, 2);
+        assert_eq!(inconsistency.check, Check::KeyImage);
+    }
+
+    #[test]
+    fn verify_detects_missing_key_image() {
+        let (ledger, _blocks) = get_mock_ledger_and_blocks(5);
+        ledger.lock().key_images.clear();
+
+        let report = verify_ledger(&ledger, None).unwrap();
+        let inconsistency = report.inconsistency.unwrap();
+        assert_eq!(inconsistency.block_index, 1);
+        assert_eq!(inconsistency.check, Check::KeyImage);
+        assert!(inconsistency.details.contains("None"));
+    }
+
+    #[test]
+    fn verify_detects_misindexed_tx_out() {
+        let (ledger, _blocks) = get_mock_ledger_and_blocks(5);
+        ledger.lock().block_index_by_tx_out_index.insert(4, 0);
+
+        let report = verify_ledger(&ledger, None).unwrap();
+        let inconsistency = report.inconsistency.unwrap();
+        assert_eq!(inconsistency.block_index, 2);
+        assert_eq!(inconsistency.check, Check::TxOutIndex);
+    }
+
+    #[test]
+    fn verify_detects_unreadable_block() {
+        let (ledger, _blocks) = get_mock_ledger_and_blocks(5);
+        // The mock ledger still reports 4 blocks, the last of which is now
+        // missing.
+        ledger.lock().block_data_by_index.remove(&3);
+
+        let report = verify_ledger(&ledger, None).unwrap();
+        let inconsistency = report.inconsistency.unwrap();
+        assert_eq!(inconsistency.block_index, 3);
+        assert_eq!(inconsistency.check, Check::Read);
+    }
+
+    #[test]
+    fn merkle_accumulator_matches_tx_out_store() {
+        let mut ledger = create_ledger();
+        let mut accumulator = MerkleAccumulator::default();
+        assert_eq!(accumulator.root_hash(), *NIL_HASH);
+
+        // Check every tree size from 1 to 20 TxOuts.
+        for block_data in get_blocks(
+            BlockVersion::MAX,
+            20,
+            1,
+            1,
+            1,
+            1 << 20,
+            None,
+            &mut get_seeded_rng(),
+        ) {
+            ledger.append_block_data(&block_data).unwrap();
+            for tx_out in &block_data.contents().outputs {
+                accumulator.push(hash_leaf(tx_out));
+            }
+            assert_eq!(
+                accumulator.root_element().unwrap(),
+                ledger.get_root_tx_out_membership_element().unwrap()
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 //! Parsing of hex values from `&str`
 
+use core::fmt::Display;
 use hex::{FromHex, FromHexError};
 
 /// Parse a hex string
@@ -11,3 +12,19 @@ use hex::{FromHex, FromHexError};
 pub fn parse_hex<T: FromHex<Error = FromHexError>>(hex: &str) -> Result<T, FromHexError> {
     <T>::from_hex(hex)
 }
+
+/// Parse a hex-encoded key, e.g. a trusted public key given on the command
+/// line
+///
+/// This can be used with Clap
+///
+/// # Arguments:
+/// * `hex`- The hex string to parse
+pub fn parse_hex_key<T>(hex: &str) -> Result<T, String>
+where
+    T: for<'a> TryFrom<&'a [u8]>,
+    for<'a> <T as TryFrom<&'a [u8]>>::Error: Display,
+{
+    let bytes = hex::decode(hex).map_err(|err| format!("Invalid hex '{hex}': {err}"))?;
+    T::try_from(&bytes[..]).map_err(|err| format!("Invalid key '{hex}': {err}"))
+}
@@ -10,7 +10,7 @@ use std::{fs, str::FromStr, time::Duration};
 pub use mc_sgx_css::Signature as CssSignature;
 
 mod hex;
-pub use crate::hex::parse_hex;
+pub use crate::hex::{parse_hex, parse_hex_key};
 
 /// Parse a number of seconds into a duration
 ///
@@ -16,6 +16,8 @@ use clap::Parser;
 use futures::executor::block_on;
 use grpcio::{EnvBuilder, ServerBuilder};
 use mc_common::logger::{create_app_logger, log, o, Logger};
+use mc_consensus_api::consensus_common_grpc::create_block_data_api;
+use mc_ledger_sync::BlockDataApiService;
 use mc_util_grpc::{ConnectionUriGrpcioServer, HealthCheckStatus, HealthService};
 use mc_util_uri::ConnectionUri;
 use std::{
@@ -41,11 +43,16 @@ fn main() {
         logger.clone(),
     )
     .expect("Could not create or open watcher db");
-    let watcher = Watcher::new(watcher_db.clone(), config.store_block_data, logger.clone())
-        .expect("Failed creating watcher");
+    let watcher = Watcher::new(
+        watcher_db.clone(),
+        config.store_block_data,
+        &config.archive_manifest_signers,
+        logger.clone(),
+    )
+    .expect("Failed creating watcher");
 
     let _verification_reports_collector = <AttestationEvidenceCollector>::new(
-        watcher_db,
+        watcher_db.clone(),
         sources_config.sources().to_vec(),
         config.poll_interval,
         logger.clone(),
@@ -67,7 +74,12 @@ fn main() {
             .build(),
     );
 
-    let server_builder = ServerBuilder::new(env).register_service(health_service);
+    let mut server_builder = ServerBuilder::new(env).register_service(health_service);
+    if config.serve_blocks {
+        server_builder = server_builder.register_service(create_block_data_api(
+            BlockDataApiService::new(watcher_db, logger.clone()),
+        ));
+    }
 
     let mut server = server_builder
         .build_using_uri(&config.client_listen_uri, logger.clone())
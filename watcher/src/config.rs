@@ -4,7 +4,8 @@
 //! Configuration parameters for the watcher test utility.
 
 use clap::Parser;
-use mc_util_parse::parse_duration_in_seconds;
+use mc_crypto_keys::Ed25519Public;
+use mc_util_parse::{parse_duration_in_seconds, parse_hex_key};
 use mc_util_uri::{ConsensusClientUri, WatcherUri};
 use serde::{Deserialize, Serialize};
 use std::{fs, path::PathBuf, str::FromStr, time::Duration};
@@ -37,6 +38,22 @@ pub struct WatcherConfig {
     #[clap(long, env = "MC_STORE_BLOCK_DATA")]
     pub store_block_data: bool,
 
+    /// Serve the stored block data to other clients on the gRPC listening URI,
+    /// with the block data API.
+    #[clap(long, requires = "store_block_data", env = "MC_SERVE_BLOCKS")]
+    pub serve_blocks: bool,
+
+    /// Hex-encoded Ed25519 public keys trusted to sign the manifests of the
+    /// compressed archives published at the watched sources. Compressed
+    /// archives are only used when at least one key is given.
+    #[clap(
+        long = "archive-manifest-signer",
+        value_parser = parse_hex_key::<Ed25519Public>,
+        use_value_delimiter = true,
+        env = "MC_ARCHIVE_MANIFEST_SIGNERS"
+    )]
+    pub archive_manifest_signers: Vec<Ed25519Public>,
+
     /// gRPC listening URI.
     #[clap(
         long,
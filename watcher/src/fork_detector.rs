@@ -0,0 +1,757 @@
+// Copyright (c) 2018-2024 The MobileCoin Foundation
+
+//! Detects forks and equivocation among the watched sources.
+//!
+//! Every block fetched by the watcher is compared with the blocks other
+//! sources provided for the same index, and the metadata of each block signer
+//! is compared with what that signer reported for earlier blocks. Block
+//! signer keys are generated by the enclave every time a consensus node
+//! starts, so the quorum set, responder id and attestation evidence that come
+//! with a signer are not expected to change, since changing the configuration
+//! of a node requires restarting it.
+//!
+//! The detector only keeps what it saw in memory. When the watcher starts, it
+//! is seeded with the metadata of the last block each source signed, so that a
+//! change made while the watcher was down is still reported.
+
+use displaydoc::Display;
+use mc_attest_core::{DcapEvidence, MrEnclave, VerificationReportData};
+use mc_blockchain_types::{
+    AttestationEvidence, BlockContentsHash, BlockData, BlockID, BlockIndex, BlockMetadataContents,
+    BlockSignature, QuorumSet,
+};
+use mc_common::{
+    logger::{log, Logger},
+    HashMap, ResponderId,
+};
+use mc_crypto_keys::Ed25519Public;
+use std::{collections::BTreeMap, fmt};
+use url::Url;
+
+/// Maximum number of block indices for which observations are kept, so that a
+/// source that stopped syncing does not make the detector grow forever.
+pub const MAX_TRACKED_BLOCKS: usize = 10_000;
+
+/// A block, as provided by one of the sources.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockObservation {
+    /// The source the block was fetched from.
+    pub src_url: Url,
+
+    /// The signer of the block, if it came with a valid signature.
+    pub signer: Option<Ed25519Public>,
+
+    /// The block ID.
+    pub block_id: BlockID,
+
+    /// The block contents hash.
+    pub contents_hash: BlockContentsHash,
+}
+
+impl BlockObservation {
+    fn conflicts_with(&self, other: &Self) -> bool {
+        self.block_id != other.block_id || self.contents_hash != other.contents_hash
+    }
+}
+
+impl fmt::Display for BlockObservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (block id {}, contents hash {}, ",
+            self.src_url,
+            self.block_id,
+            hex::encode(self.contents_hash.0)
+        )?;
+        match &self.signer {
+            Some(signer) => write!(f, "signed by {signer})"),
+            None => write!(f, "unsigned)"),
+        }
+    }
+}
+
+/// An inconsistency found by the [ForkDetector].
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum ForkAlert {
+    /// Conflicting blocks at index {block_index}: {first} and {second}
+    ConflictingBlocks {
+        /// The block index.
+        block_index: BlockIndex,
+        /// The block seen first.
+        first: BlockObservation,
+        /// The conflicting block.
+        second: BlockObservation,
+    },
+
+    /// Signer signed two different blocks at index {block_index}: {first} and
+    /// {second}
+    Equivocation {
+        /// The block index.
+        block_index: BlockIndex,
+        /// The block seen first.
+        first: BlockObservation,
+        /// The conflicting block, signed by the same signer.
+        second: BlockObservation,
+    },
+
+    /// Quorum set of signer {signer} changed at block {block_index} from
+    /// {src_url}
+    QuorumSetChanged {
+        /// The block index.
+        block_index: BlockIndex,
+        /// The source the block was fetched from.
+        src_url: Url,
+        /// The block signer.
+        signer: Ed25519Public,
+        /// The quorum set the signer reported before.
+        previous: QuorumSet,
+        /// The quorum set in the metadata of this block.
+        current: QuorumSet,
+    },
+
+    /// Responder id of signer {signer} changed from {previous} to {current} at
+    /// block {block_index} from {src_url}
+    ResponderIdChanged {
+        /// The block index.
+        block_index: BlockIndex,
+        /// The source the block was fetched from.
+        src_url: Url,
+        /// The block signer.
+        signer: Ed25519Public,
+        /// The responder id the signer reported before.
+        previous: ResponderId,
+        /// The responder id in the metadata of this block.
+        current: ResponderId,
+    },
+
+    /// Attestation evidence of signer {signer} changed without an enclave
+    /// measurement update at block {block_index} from {src_url}
+    AttestationEvidenceChanged {
+        /// The block index.
+        block_index: BlockIndex,
+        /// The source the block was fetched from.
+        src_url: Url,
+        /// The block signer.
+        signer: Ed25519Public,
+    },
+}
+
+impl ForkAlert {
+    /// A short name for the kind of alert, used as a metric and log label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ConflictingBlocks { .. } => "conflicting_blocks",
+            Self::Equivocation { .. } => "equivocation",
+            Self::QuorumSetChanged { .. } => "quorum_set_changed",
+            Self::ResponderIdChanged { .. } => "responder_id_changed",
+            Self::AttestationEvidenceChanged { .. } => "attestation_evidence_changed",
+        }
+    }
+
+    /// The index of the block that raised the alert.
+    pub fn block_index(&self) -> BlockIndex {
+        match self {
+            Self::ConflictingBlocks { block_index, .. }
+            | Self::Equivocation { block_index, .. }
+            | Self::QuorumSetChanged { block_index, .. }
+            | Self::ResponderIdChanged { block_index, .. }
+            | Self::AttestationEvidenceChanged { block_index, .. } => *block_index,
+        }
+    }
+
+    /// The source of the block that raised the alert.
+    pub fn src_url(&self) -> &Url {
+        match self {
+            Self::ConflictingBlocks { second, .. } | Self::Equivocation { second, .. } => {
+                &second.src_url
+            }
+            Self::QuorumSetChanged { src_url, .. }
+            | Self::ResponderIdChanged { src_url, .. }
+            | Self::AttestationEvidenceChanged { src_url, .. } => src_url,
+        }
+    }
+}
+
+/// What a block signer reported in the metadata of the last block it signed.
+struct SignerState {
+    quorum_set: QuorumSet,
+    responder_id: ResponderId,
+    attestation_evidence: AttestationEvidence,
+}
+
+impl From<&BlockMetadataContents> for SignerState {
+    fn from(metadata: &BlockMetadataContents) -> Self {
+        Self {
+            quorum_set: metadata.quorum_set().clone(),
+            responder_id: metadata.responder_id().clone(),
+            attestation_evidence: metadata.attestation_evidence().clone(),
+        }
+    }
+}
+
+/// Compares the blocks fetched from the watched sources, and the metadata of
+/// their signers.
+pub struct ForkDetector {
+    /// The blocks seen so far, by block index.
+    observations: BTreeMap<BlockIndex, Vec<BlockObservation>>,
+
+    /// The last metadata seen for each block signer.
+    signers: HashMap<Ed25519Public, SignerState>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl ForkDetector {
+    /// Create a detector that has not seen any block yet.
+    pub fn new(logger: Logger) -> Self {
+        Self {
+            observations: BTreeMap::new(),
+            signers: HashMap::default(),
+            logger,
+        }
+    }
+
+    /// Check a block fetched from `src_url` against everything seen so far,
+    /// returning the inconsistencies it reveals.
+    pub fn observe(&mut self, src_url: &Url, block_data: &BlockData) -> Vec<ForkAlert> {
+        let block = block_data.block();
+        let signer = self.verified_signer(src_url, block_data);
+
+        let observation = BlockObservation {
+            src_url: src_url.clone(),
+            signer,
+            block_id: block.id.clone(),
+            contents_hash: block.contents_hash.clone(),
+        };
+        let mut alerts = self.check_block(block.index, observation);
+
+        if let (Some(signer), Some(metadata)) =
+            (signer, self.verified_metadata(src_url, block_data))
+        {
+            alerts.extend(self.check_signer(block.index, src_url, signer, metadata));
+        }
+
+        alerts
+    }
+
+    /// Record the metadata of a block the watcher synced from `src_url` before
+    /// it restarted, without comparing it with anything. `signature` is the
+    /// signature that was stored for the block, and `block_data` is ignored
+    /// unless it was signed by the same signer.
+    pub fn seed(&mut self, src_url: &Url, signature: &BlockSignature, block_data: &BlockData) {
+        let Some(signer) = self.verified_signer(src_url, block_data) else {
+            return;
+        };
+        if signer != *signature.signer() {
+            log::warn!(
+                self.logger,
+                "Block {} from {} is not signed by the stored signer {}",
+                block_data.block().index,
+                src_url,
+                signature.signer()
+            );
+            return;
+        }
+        if let Some(metadata) = self.verified_metadata(src_url, block_data) {
+            self.signers.insert(signer, metadata.into());
+        }
+    }
+
+    /// Forget the blocks below `block_index`, once every source has synced
+    /// them.
+    pub fn prune_below(&mut self, block_index: BlockIndex) {
+        self.observations = self.observations.split_off(&block_index);
+        while self.observations.len() > MAX_TRACKED_BLOCKS {
+            self.observations.pop_first();
+        }
+    }
+
+    /// The signer of the block. A signature only tells who signed the block if
+    /// it verifies, otherwise the block is treated as unsigned.
+    fn verified_signer(&self, src_url: &Url, block_data: &BlockData) -> Option<Ed25519Public> {
+        let block = block_data.block();
+        let signature = block_data.signature()?;
+        match signature.verify(block) {
+            Ok(()) => Some(*signature.signer()),
+            Err(err) => {
+                log::warn!(
+                    self.logger,
+                    "Invalid signature for block {} from {}: {}",
+                    block.index,
+                    src_url,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// The metadata of the block, if its signature verifies and it was signed
+    /// for this block.
+    fn verified_metadata<'a>(
+        &self,
+        src_url: &Url,
+        block_data: &'a BlockData,
+    ) -> Option<&'a BlockMetadataContents> {
+        let block = block_data.block();
+        let metadata = block_data.metadata()?;
+        if let Err(err) = metadata.verify() {
+            log::warn!(
+                self.logger,
+                "Invalid metadata signature for block {} from {}: {}",
+                block.index,
+                src_url,
+                err
+            );
+            return None;
+        }
+        if metadata.contents().block_id() != &block.id {
+            log::warn!(
+                self.logger,
+                "Metadata for block {} from {} was signed for block {}",
+                block.index,
+                src_url,
+                metadata.contents().block_id()
+            );
+            return None;
+        }
+        Some(metadata.contents())
+    }
+
+    fn check_block(
+        &mut self,
+        block_index: BlockIndex,
+        observation: BlockObservation,
+    ) -> Vec<ForkAlert> {
+        let observations = self.observations.entry(block_index).or_default();
+
+        let equivocation = observations.iter().find(|existing| {
+            existing.signer.is_some()
+                && existing.signer == observation.signer
+                && existing.conflicts_with(&observation)
+        });
+        let alerts = match equivocation {
+            Some(first) => vec![ForkAlert::Equivocation {
+                block_index,
+                first: first.clone(),
+                second: observation.clone(),
+            }],
+            // Each conflicting block is reported once, with the first source
+            // that provided it, so that a fork between two groups of sources
+            // is reported once per source.
+            None => {
+                let mut conflicting: Vec<&BlockObservation> = vec![];
+                for existing in observations.iter() {
+                    if existing.conflicts_with(&observation)
+                        && conflicting
+                            .iter()
+                            .all(|first| first.conflicts_with(existing))
+                    {
+                        conflicting.push(existing);
+                    }
+                }
+                conflicting
+                    .into_iter()
+                    .map(|first| ForkAlert::ConflictingBlocks {
+                        block_index,
+                        first: first.clone(),
+                        second: observation.clone(),
+                    })
+                    .collect()
+            }
+        };
+
+        observations.push(observation);
+        alerts
+    }
+
+    fn check_signer(
+        &mut self,
+        block_index: BlockIndex,
+        src_url: &Url,
+        signer: Ed25519Public,
+        metadata: &BlockMetadataContents,
+    ) -> Vec<ForkAlert> {
+        let Some(previous) = self.signers.insert(signer, metadata.into()) else {
+            return vec![];
+        };
+        let current = &self.signers[&signer];
+
+        let mut alerts = vec![];
+        if previous.quorum_set != current.quorum_set {
+            alerts.push(ForkAlert::QuorumSetChanged {
+                block_index,
+                src_url: src_url.clone(),
+                signer,
+                previous: previous.quorum_set,
+                current: current.quorum_set.clone(),
+            });
+        }
+        if previous.responder_id != current.responder_id {
+            alerts.push(ForkAlert::ResponderIdChanged {
+                block_index,
+                src_url: src_url.clone(),
+                signer,
+                previous: previous.responder_id,
+                current: current.responder_id.clone(),
+            });
+        }
+        if previous.attestation_evidence != current.attestation_evidence {
+            match (
+                enclave_measurement(&previous.attestation_evidence),
+                enclave_measurement(&current.attestation_evidence),
+            ) {
+                (Some(previous), Some(current)) if previous != current => log::info!(
+                    self.logger,
+                    "Enclave measurement of signer {} changed at block {} from {}",
+                    signer,
+                    block_index,
+                    src_url
+                ),
+                // Evidence that cannot be parsed cannot justify the change.
+                _ => alerts.push(ForkAlert::AttestationEvidenceChanged {
+                    block_index,
+                    src_url: src_url.clone(),
+                    signer,
+                }),
+            }
+        }
+        alerts
+    }
+}
+
+/// The MRENCLAVE the attestation evidence attests to, if it can be parsed.
+fn enclave_measurement(attestation_evidence: &AttestationEvidence) -> Option<MrEnclave> {
+    match attestation_evidence {
+        AttestationEvidence::VerificationReport(report) => {
+            let report_data = VerificationReportData::try_from(report).ok()?;
+            let report_body = report_data.quote.report_body().ok()?;
+            Some(report_body.mr_enclave())
+        }
+        AttestationEvidence::DcapEvidence(evidence) => {
+            let evidence = DcapEvidence::try_from(evidence).ok()?;
+            Some(evidence.quote.app_report_body().mr_enclave())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher_db::tests::setup_blocks;
+    use mc_blockchain_test_utils::{get_blocks, make_quorum_set, make_verification_report};
+    use mc_blockchain_types::{BlockMetadata, BlockSignature, BlockVersion};
+    use mc_common::logger::test_with_logger;
+    use mc_crypto_keys::Ed25519Pair;
+    use mc_util_from_random::FromRandom;
+    use mc_util_test_helper::{get_seeded_rng, CryptoRng, RngCore};
+    use std::str::FromStr;
+
+    /// A watched source, and the consensus node whose blocks it serves.
+    struct TestSource {
+        src_url: Url,
+        signer: Ed25519Pair,
+        msg_signer: Ed25519Pair,
+        quorum_set: QuorumSet,
+        attestation_evidence: AttestationEvidence,
+        responder_id: ResponderId,
+    }
+
+    impl TestSource {
+        fn new(name: &str, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+            Self {
+                src_url: Url::parse(&format!("http://{name}.example.com/")).unwrap(),
+                signer: Ed25519Pair::from_random(rng),
+                msg_signer: Ed25519Pair::from_random(rng),
+                quorum_set: make_quorum_set(rng),
+                attestation_evidence: make_verification_report(rng).into(),
+                responder_id: ResponderId::from_str(&format!("{name}.example.com:443")).unwrap(),
+            }
+        }
+
+        /// The block as this source serves it, signed by its node.
+        fn serve(&self, block_data: &BlockData) -> (Url, BlockData) {
+            let block = block_data.block();
+            let signature = BlockSignature::from_block_and_keypair(block, &self.signer).unwrap();
+            let contents = BlockMetadataContents::new(
+                block.id.clone(),
+                self.quorum_set.clone(),
+                self.attestation_evidence.clone(),
+                self.responder_id.clone(),
+            );
+            let metadata =
+                BlockMetadata::from_contents_and_keypair(contents, &self.msg_signer).unwrap();
+            (
+                self.src_url.clone(),
+                BlockData::new(
+                    block.clone(),
+                    block_data.contents().clone(),
+                    signature,
+                    metadata,
+                ),
+            )
+        }
+    }
+
+    /// Feed the blocks to a new detector, in order, returning all the alerts.
+    fn replay(events: &[(Url, BlockData)], logger: Logger) -> Vec<ForkAlert> {
+        let mut detector = ForkDetector::new(logger);
+        events
+            .iter()
+            .flat_map(|(src_url, block_data)| detector.observe(src_url, block_data))
+            .collect()
+    }
+
+    /// Every source serving every block, source after source for each block.
+    fn interleave(sources: &[&TestSource], blocks_data: &[BlockData]) -> Vec<(Url, BlockData)> {
+        blocks_data
+            .iter()
+            .flat_map(|block_data| sources.iter().map(|source| source.serve(block_data)))
+            .collect()
+    }
+
+    #[test_with_logger]
+    fn consistent_sources_raise_no_alerts(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let sources = ["node1", "node2", "node3"].map(|name| TestSource::new(name, &mut rng));
+        let blocks_data = setup_blocks();
+
+        let events = interleave(&sources.each_ref(), &blocks_data);
+        assert_eq!(replay(&events, logger.clone()), vec![]);
+
+        // Sources syncing at different paces see the same blocks.
+        let mut events = sources
+            .iter()
+            .flat_map(|source| {
+                blocks_data
+                    .iter()
+                    .map(|block_data| source.serve(block_data))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(replay(&events, logger.clone()), vec![]);
+
+        // Unsigned blocks are compared too.
+        for (_, block_data) in events.iter_mut() {
+            *block_data = BlockData::new(
+                block_data.block().clone(),
+                block_data.contents().clone(),
+                None,
+                None,
+            );
+        }
+        assert_eq!(replay(&events, logger), vec![]);
+    }
+
+    #[test_with_logger]
+    fn detects_conflicting_blocks(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let node1 = TestSource::new("node1", &mut rng);
+        let node2 = TestSource::new("node2", &mut rng);
+        let node3 = TestSource::new("node3", &mut rng);
+
+        // node3 forks off after block 4.
+        let blocks_data = setup_blocks();
+        let fork_data = get_blocks(
+            BlockVersion::ZERO,
+            2,
+            20,
+            1,
+            10,
+            1 << 20,
+            blocks_data[4].block().clone(),
+            &mut rng,
+        );
+
+        let mut events = interleave(&[&node1, &node2], &blocks_data[..7]);
+        events.extend(interleave(&[&node3], &blocks_data[..5]));
+        events.extend(interleave(&[&node3], &fork_data));
+        let alerts = replay(&events, logger);
+
+        // Each forked block conflicts once, with the block the others agree on.
+        assert_eq!(alerts.len(), 2);
+        for (alert, fork_block_data) in alerts.iter().zip(&fork_data) {
+            let block_index = fork_block_data.block().index;
+            assert_eq!(
+                alert,
+                &ForkAlert::ConflictingBlocks {
+                    block_index,
+                    first: BlockObservation {
+                        src_url: node1.src_url.clone(),
+                        signer: Some(node1.signer.public_key()),
+                        block_id: blocks_data[block_index as usize].block().id.clone(),
+                        contents_hash: blocks_data[block_index as usize]
+                            .block()
+                            .contents_hash
+                            .clone(),
+                    },
+                    second: BlockObservation {
+                        src_url: node3.src_url.clone(),
+                        signer: Some(node3.signer.public_key()),
+                        block_id: fork_block_data.block().id.clone(),
+                        contents_hash: fork_block_data.block().contents_hash.clone(),
+                    },
+                }
+            );
+            assert_eq!(alert.kind(), "conflicting_blocks");
+            assert_eq!(alert.src_url(), &node3.src_url);
+        }
+    }
+
+    #[test_with_logger]
+    fn detects_equivocation(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let node1 = TestSource::new("node1", &mut rng);
+        // A second source serving blocks signed by the same enclave.
+        let mut mirror = TestSource::new("mirror", &mut rng);
+        mirror.signer = Ed25519Pair::from(node1.signer.private_key());
+        mirror.quorum_set = node1.quorum_set.clone();
+        mirror.attestation_evidence = node1.attestation_evidence.clone();
+        mirror.responder_id = node1.responder_id.clone();
+
+        let blocks_data = setup_blocks();
+        let fork_data = get_blocks(
+            BlockVersion::ZERO,
+            1,
+            20,
+            1,
+            10,
+            1 << 20,
+            blocks_data[1].block().clone(),
+            &mut rng,
+        );
+
+        let events = vec![
+            node1.serve(&blocks_data[2]),
+            mirror.serve(&blocks_data[2]),
+            mirror.serve(&fork_data[0]),
+        ];
+        let alerts = replay(&events, logger);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind(), "equivocation");
+        assert_eq!(alerts[0].block_index(), 2);
+    }
+
+    #[test_with_logger]
+    fn detects_signer_metadata_changes(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let blocks_data = setup_blocks();
+
+        let mut node1 = TestSource::new("node1", &mut rng);
+        let mut events = vec![node1.serve(&blocks_data[1])];
+        node1.quorum_set = make_quorum_set(&mut rng);
+        events.push(node1.serve(&blocks_data[2]));
+        node1.responder_id = ResponderId::from_str("other.example.com:443").unwrap();
+        events.push(node1.serve(&blocks_data[3]));
+        node1.attestation_evidence = make_verification_report(&mut rng).into();
+        events.push(node1.serve(&blocks_data[4]));
+        events.push(node1.serve(&blocks_data[5]));
+
+        let alerts = replay(&events, logger.clone());
+        assert_eq!(
+            alerts
+                .iter()
+                .map(|alert| (alert.kind(), alert.block_index()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("quorum_set_changed", 2),
+                ("responder_id_changed", 3),
+                ("attestation_evidence_changed", 4),
+            ]
+        );
+
+        // A restarted node signs with a new key, and may change its configuration.
+        let mut node1 = TestSource::new("node1", &mut rng);
+        let mut events = vec![node1.serve(&blocks_data[1])];
+        node1.signer = Ed25519Pair::from_random(&mut rng);
+        node1.quorum_set = make_quorum_set(&mut rng);
+        node1.attestation_evidence = make_verification_report(&mut rng).into();
+        events.push(node1.serve(&blocks_data[2]));
+        assert_eq!(replay(&events, logger), vec![]);
+    }
+
+    #[test_with_logger]
+    fn ignores_metadata_of_other_blocks(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let blocks_data = setup_blocks();
+
+        let mut node1 = TestSource::new("node1", &mut rng);
+        let mut events = vec![node1.serve(&blocks_data[1])];
+
+        // Block 2 comes with metadata signed for block 1, reporting another quorum
+        // set.
+        node1.quorum_set = make_quorum_set(&mut rng);
+        let (src_url, block_data) = node1.serve(&blocks_data[2]);
+        let contents = BlockMetadataContents::new(
+            blocks_data[1].block().id.clone(),
+            node1.quorum_set.clone(),
+            node1.attestation_evidence.clone(),
+            node1.responder_id.clone(),
+        );
+        let metadata =
+            BlockMetadata::from_contents_and_keypair(contents, &node1.msg_signer).unwrap();
+        events.push((
+            src_url,
+            BlockData::new(
+                block_data.block().clone(),
+                block_data.contents().clone(),
+                block_data.signature().cloned(),
+                metadata,
+            ),
+        ));
+        assert_eq!(replay(&events, logger.clone()), vec![]);
+
+        // The same change, in metadata signed for block 2.
+        events.push(node1.serve(&blocks_data[2]));
+        let alerts = replay(&events, logger);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind(), "quorum_set_changed");
+    }
+
+    #[test_with_logger]
+    fn seeded_detector_reports_signer_changes(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let blocks_data = setup_blocks();
+        let mut node1 = TestSource::new("node1", &mut rng);
+        let node2 = TestSource::new("node2", &mut rng);
+
+        // The block node1 signed last before the watcher restarted.
+        let (src_url, block_data) = node1.serve(&blocks_data[1]);
+        let signature = block_data.signature().unwrap().clone();
+
+        // Blocks signed by another signer than the stored one are not used.
+        let mut detector = ForkDetector::new(logger.clone());
+        let (_, other_block_data) = node2.serve(&blocks_data[1]);
+        detector.seed(&src_url, &signature, &other_block_data);
+        node1.quorum_set = make_quorum_set(&mut rng);
+        let (src_url, next_block_data) = node1.serve(&blocks_data[2]);
+        assert_eq!(detector.observe(&src_url, &next_block_data), vec![]);
+
+        let mut detector = ForkDetector::new(logger);
+        detector.seed(&src_url, &signature, &block_data);
+        let alerts = detector.observe(&src_url, &next_block_data);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind(), "quorum_set_changed");
+        assert_eq!(alerts[0].block_index(), 2);
+    }
+
+    #[test_with_logger]
+    fn pruned_blocks_are_forgotten(logger: Logger) {
+        let mut rng = get_seeded_rng();
+        let node1 = TestSource::new("node1", &mut rng);
+        let node2 = TestSource::new("node2", &mut rng);
+        let blocks_data = setup_blocks();
+
+        let mut detector = ForkDetector::new(logger);
+        let (src_url, block_data) = node1.serve(&blocks_data[3]);
+        assert_eq!(detector.observe(&src_url, &block_data), vec![]);
+        detector.prune_below(4);
+
+        // The same block index with different contents, from a second source.
+        let (src_url, mut block_data) = node2.serve(&blocks_data[4]);
+        let mut block = block_data.block().clone();
+        block.index = 3;
+        block_data = BlockData::new(block, block_data.contents().clone(), None, None);
+        assert_eq!(detector.observe(&src_url, &block_data), vec![]);
+    }
+}
@@ -8,6 +8,7 @@ pub mod attestation_evidence_collector;
 pub mod block_data_store;
 pub mod config;
 pub mod error;
+pub mod fork_detector;
 pub mod metrics;
 pub mod watcher;
 pub mod watcher_db;
@@ -1,7 +1,9 @@
 // Copyright (c) 2018-2022 The MobileCoin Foundation
 
-//! Watcher metrics comparing ledger height and block height
+//! Watcher metrics comparing ledger height and block height, and counting the
+//! alerts raised by the fork detector
 
+use crate::fork_detector::ForkAlert;
 use mc_common::HashMap;
 use mc_util_metrics::{IntGauge, OpMetrics};
 use url::Url;
@@ -45,4 +47,13 @@ impl WatcherMetrics {
                 .set(num_blocks.unwrap_or(0) as i64);
         });
     }
+
+    /// Count an alert raised by the fork detector, by kind and by the source
+    /// of the block that raised it
+    pub fn inc_fork_alert(&self, alert: &ForkAlert) {
+        COLLECTOR.inc(&format!("fork_alert_{}", alert.kind()));
+        COLLECTOR
+            .peer_counter("watcher_fork_alerts", alert.src_url().as_str())
+            .inc();
+    }
 }
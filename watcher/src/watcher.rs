@@ -4,12 +4,14 @@
 
 use crate::{
     error::{WatcherDBError, WatcherError},
+    fork_detector::ForkDetector,
     metrics::WatcherMetrics,
     watcher_db::WatcherDB,
 };
 use mc_api::block_num_to_s3block_path;
 use mc_blockchain_types::{BlockData, BlockIndex};
 use mc_common::logger::{log, Logger};
+use mc_crypto_keys::Ed25519Public;
 use mc_ledger_db::Ledger;
 use mc_ledger_sync::ReqwestTransactionsFetcher;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
@@ -17,7 +19,7 @@ use std::{
     collections::HashMap,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     thread,
     time::Duration,
@@ -38,6 +40,7 @@ pub struct Watcher {
     transactions_fetcher_by_url: Arc<HashMap<Url, ReqwestTransactionsFetcher>>,
     watcher_db: WatcherDB,
     store_block_data: bool,
+    fork_detector: Mutex<ForkDetector>,
     logger: Logger,
     metrics: WatcherMetrics,
 }
@@ -63,10 +66,13 @@ impl Watcher {
     /// * `transactions_fetcher` - The transaction fetcher used to fetch blocks
     ///   from watched source URLs
     /// * `store_block_data` - The fetched BlockData objects into the database
+    /// * `manifest_signers` - Keys trusted to sign the manifests of compressed
+    ///   archives at the watched source URLs
     /// * `logger` - Logger
     pub fn new(
         watcher_db: WatcherDB,
         store_block_data: bool,
+        manifest_signers: &[Ed25519Public],
         logger: Logger,
     ) -> Result<Self, WatcherError> {
         let tx_source_urls = watcher_db.get_config_urls()?;
@@ -75,26 +81,87 @@ impl Watcher {
             tx_source_urls
                 .into_iter()
                 .map(|source_url| {
-                    Ok((
-                        source_url.clone(),
-                        ReqwestTransactionsFetcher::new(
-                            vec![source_url.to_string()],
-                            logger.clone(),
-                        )?,
-                    ))
+                    let mut transactions_fetcher = ReqwestTransactionsFetcher::new(
+                        vec![source_url.to_string()],
+                        logger.clone(),
+                    )?;
+                    transactions_fetcher.set_manifest_signers(manifest_signers);
+                    Ok((source_url, transactions_fetcher))
                 })
                 .collect::<Result<HashMap<_, _>, WatcherError>>()?,
         );
 
         let metrics = WatcherMetrics::new();
+        let fork_detector = Mutex::new(ForkDetector::new(logger.clone()));
 
-        Ok(Self {
+        let watcher = Self {
             transactions_fetcher_by_url,
             watcher_db,
             store_block_data,
+            fork_detector,
             logger,
             metrics,
-        })
+        };
+        watcher.seed_fork_detector()?;
+        Ok(watcher)
+    }
+
+    /// Seed the fork detector with the metadata of the last block each source
+    /// signed, so that signer changes made while the watcher was down are
+    /// still detected. The blocks are read from the database when block data
+    /// is stored, and fetched again otherwise.
+    fn seed_fork_detector(&self) -> Result<(), WatcherError> {
+        let mut url_to_signature = HashMap::new();
+        for (src_url, last_synced) in self.watcher_db.last_synced_blocks()? {
+            let Some(block_index) = last_synced else {
+                continue;
+            };
+            let signature_data = self
+                .watcher_db
+                .get_block_signatures(block_index)?
+                .into_iter()
+                .find(|signature_data| signature_data.src_url == src_url.as_str());
+            if let Some(signature_data) = signature_data {
+                url_to_signature.insert(src_url, (block_index, signature_data.block_signature));
+            }
+        }
+
+        let mut url_to_block_data = HashMap::new();
+        let mut url_to_block_index = HashMap::new();
+        for (src_url, (block_index, _signature)) in url_to_signature.iter() {
+            match self.watcher_db.get_block_data(src_url, *block_index) {
+                Ok(block_data) => {
+                    url_to_block_data.insert(src_url.clone(), block_data);
+                }
+                Err(WatcherDBError::NotFound) => {
+                    url_to_block_index.insert(src_url.clone(), *block_index);
+                }
+                Err(err) => Err(err)?,
+            }
+        }
+        let url_to_block_data_result =
+            parallel_fetch_blocks(url_to_block_index, self.transactions_fetcher_by_url.clone());
+        for (src_url, (block_index, block_data_result)) in url_to_block_data_result {
+            match block_data_result {
+                Ok(block_data) => {
+                    url_to_block_data.insert(src_url, block_data);
+                }
+                Err(err) => log::warn!(
+                    self.logger,
+                    "Could not fetch block {} from {} to seed the fork detector: {}",
+                    block_index,
+                    src_url,
+                    err
+                ),
+            }
+        }
+
+        let mut fork_detector = self.fork_detector.lock().expect("mutex poisoned");
+        for (src_url, block_data) in url_to_block_data {
+            let (_block_index, signature) = &url_to_signature[&src_url];
+            fork_detector.seed(&src_url, signature, &block_data);
+        }
+        Ok(())
     }
 
     /// The lowest next block we need to try and sync.
@@ -185,6 +252,15 @@ impl Watcher {
                 })
                 .collect();
 
+            // Every source has synced the blocks below the lowest index we are about to
+            // fetch, so the fork detector no longer needs them.
+            if let Some(lowest_block_index) = url_to_block_index.values().min() {
+                self.fork_detector
+                    .lock()
+                    .expect("mutex poisoned")
+                    .prune_below(*lowest_block_index);
+            }
+
             // Attempt to fetch block data for all urls in parallel.
             let url_to_block_data_result =
                 parallel_fetch_blocks(url_to_block_index, self.transactions_fetcher_by_url.clone());
@@ -203,6 +279,8 @@ impl Watcher {
                             src_url,
                             block_index
                         );
+                        self.detect_forks(&src_url, &block_data);
+
                         if self.store_block_data {
                             match self.watcher_db.add_block_data(&src_url, &block_data) {
                                 Ok(()) => {}
@@ -269,6 +347,26 @@ impl Watcher {
             }
         }
     }
+
+    /// Compare a block with the ones fetched from the other sources, and report
+    /// any inconsistency as a metric and an alert log.
+    fn detect_forks(&self, src_url: &Url, block_data: &BlockData) {
+        let alerts = self
+            .fork_detector
+            .lock()
+            .expect("mutex poisoned")
+            .observe(src_url, block_data);
+        for alert in alerts {
+            self.metrics.inc_fork_alert(&alert);
+            log::crit!(
+                self.logger,
+                "Fork detector alert: {}", alert;
+                "alert" => alert.kind(),
+                "block_index" => alert.block_index(),
+                "src_url" => alert.src_url().as_str(),
+            );
+        }
+    }
 }
 
 /// Given a map of block indexes per source URL and a map of transaction
@@ -315,10 +413,16 @@ impl WatcherSyncThread {
         ledger: impl Ledger + 'static,
         poll_interval: Duration,
         store_block_data: bool,
+        manifest_signers: &[Ed25519Public],
         logger: Logger,
     ) -> Result<Self, WatcherError> {
         log::debug!(logger, "Creating watcher sync thread.");
-        let watcher = Watcher::new(watcher_db, store_block_data, logger.clone())?;
+        let watcher = Watcher::new(
+            watcher_db,
+            store_block_data,
+            manifest_signers,
+            logger.clone(),
+        )?;
 
         let currently_behind = Arc::new(AtomicBool::new(false));
         let stop_requested = Arc::new(AtomicBool::new(false));
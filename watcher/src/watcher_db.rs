@@ -11,6 +11,7 @@ use mc_common::{
 };
 use mc_crypto_digestible::{Digestible, MerlinTranscript};
 use mc_crypto_keys::Ed25519Public;
+use mc_ledger_sync::BlockDataProvider;
 use mc_util_lmdb::{MetadataStore, MetadataStoreSettings};
 use mc_util_repr_bytes::ReprBytes;
 use mc_util_serial::{decode, encode, Message};
@@ -1053,6 +1054,23 @@ impl WatcherDB {
     }
 }
 
+/// Serve the stored block data to other clients, preferring the data of a
+/// source that provided signed block metadata, since peers only accept blocks
+/// with metadata.
+impl BlockDataProvider for WatcherDB {
+    fn block_data(&self, block_index: BlockIndex, logger: &Logger) -> Option<BlockData> {
+        match self.get_block_data_map(block_index) {
+            Ok(block_data_map) => block_data_map
+                .into_values()
+                .max_by_key(|block_data| block_data.metadata().is_some()),
+            Err(err) => {
+                log::error!(logger, "Error getting block data {}: {}", block_index, err);
+                None
+            }
+        }
+    }
+}
+
 /// Open an existing WatcherDB or create a new one in read-write mode.
 pub fn create_or_open_rw_watcher_db(
     watcher_db_path: &Path,